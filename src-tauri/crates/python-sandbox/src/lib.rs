@@ -7,22 +7,108 @@
 //! - Captures stdout/stderr
 //! - Designed to be compiled to WASM for double-sandbox security
 
+pub mod memory;
 pub mod protocol;
 pub mod sandbox;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod watchdog;
 
+use memory::{MemoryBudget, MEMORY_LIMIT_MESSAGE};
 use protocol::{ExecutionRequest, ExecutionResult, ExecutionStatus};
 pub use protocol::{ToolFunctionInfo, ToolModuleInfo};
-use rustpython_compiler::Mode;
+use rustpython_compiler::parser::{ast, Parse};
+use rustpython_compiler::{CompileError, Mode};
 use rustpython_vm::{builtins::PyBaseException, AsObject, PyRef, VirtualMachine};
 use sandbox::{
-    build_sandbox_setup_code, create_sandboxed_interpreter, generate_tool_module_code,
-    get_pending_calls, get_stderr, get_stdout, json_to_pyobject, pyobject_to_json,
-    reset_execution_state, set_available_tools, set_tool_modules, set_tool_results,
+    build_sandbox_setup_code_with_modules, collect_session_globals,
+    create_sandboxed_interpreter_with_signals, generate_tool_module_code, get_final_answer,
+    get_pending_calls,
+    get_stderr, get_stdout, json_to_pyobject, pyobject_to_json, reset_execution_state,
+    resolve_allowed_modules, set_available_tools, set_output_limit, set_stdout_listener,
+    set_tool_modules, set_tool_results, set_tool_streams, stderr_truncated, stdout_truncated,
+    StdoutListener,
 };
 use std::alloc::{alloc, dealloc, Layout};
 
-/// Format a Python exception into a readable error message
-fn format_python_exception(exc: &PyRef<PyBaseException>, vm: &VirtualMachine) -> String {
+/// Source name user code is compiled under. Traceback frames and compile
+/// errors with this name map back to the lines of `request.code`.
+const USER_SOURCE_PATH: &str = "<code_execution>";
+
+/// Format a Python exception into a readable error message.
+///
+/// When the exception passed through user code, the message is preceded by a
+/// Python-style traceback of those frames, each showing the line number and the
+/// text of that line from `user_source`. Frames from sandbox internals are left out.
+fn format_python_exception(
+    exc: &PyRef<PyBaseException>,
+    vm: &VirtualMachine,
+    user_source: &str,
+) -> String {
+    let summary = format_exception_summary(exc, vm);
+
+    let source_lines: Vec<&str> = user_source.lines().collect();
+    let mut frames = Vec::new();
+    let mut entry = exc.traceback();
+    while let Some(tb) = entry {
+        let code = &tb.frame.code;
+        if code.source_path.as_str() == USER_SOURCE_PATH {
+            let lineno = tb.lineno.to_usize();
+            let mut frame = format!(
+                "  File \"{}\", line {}, in {}",
+                USER_SOURCE_PATH,
+                lineno,
+                code.obj_name.as_str()
+            );
+            if let Some(text) = source_lines
+                .get(lineno.saturating_sub(1))
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+            {
+                frame.push_str(&format!("\n    {}", text));
+            }
+            frames.push(frame);
+        }
+        entry = tb.next.lock().clone();
+    }
+
+    if frames.is_empty() {
+        summary
+    } else {
+        format!(
+            "Traceback (most recent call last):\n{}\n{}",
+            frames.join("\n"),
+            summary
+        )
+    }
+}
+
+/// Format a compile error with its line/column and the offending line of code
+fn format_compile_error(err: &CompileError, source: &str) -> String {
+    let Some(location) = err.location else {
+        return format!("Compilation failed: {}", err.error);
+    };
+    let row = location.row.to_usize();
+    let column = location.column.to_usize();
+    let mut message = format!(
+        "Compilation failed: {} (line {}, column {})",
+        err.error, row, column
+    );
+    if let Some(line) = source.lines().nth(row.saturating_sub(1)) {
+        let indent = line.len() - line.trim_start().len();
+        let caret_offset = column.saturating_sub(1).saturating_sub(indent);
+        message.push_str(&format!(
+            "\n  File \"{}\", line {}\n    {}\n    {}^",
+            USER_SOURCE_PATH,
+            row,
+            line.trim(),
+            " ".repeat(caret_offset)
+        ));
+    }
+    message
+}
+
+/// Format an exception as "TypeName: message" like Python does
+fn format_exception_summary(exc: &PyRef<PyBaseException>, vm: &VirtualMachine) -> String {
     // Try to get the exception type name
     let type_name = exc.class().name().to_string();
 
@@ -54,26 +140,50 @@ fn format_python_exception(exc: &PyRef<PyBaseException>, vm: &VirtualMachine) ->
 pub fn execute(request: &ExecutionRequest) -> ExecutionResult {
     // Reset state for fresh execution
     reset_execution_state();
+    set_output_limit(request.output_limit_bytes);
 
     // Set up available tools and any results from previous round
     set_available_tools(request.available_tools.clone());
     set_tool_results(request.tool_results.clone());
+    set_tool_streams(request.tool_streams.clone());
 
     // Set up tool modules for import
     set_tool_modules(request.tool_modules.clone());
 
+    // Count everything this execution allocates against its memory ceiling
+    let memory_budget = request.memory_limit_bytes.map(MemoryBudget::new);
+    let _memory_tracking = memory_budget.as_ref().map(|budget| budget.track());
+
+    // Interrupting running code needs a helper thread, so timeouts and the
+    // memory ceiling are only enforced mid-run natively. Under WASM the host
+    // bounds execution time and the memory ceiling is checked after the run.
+    #[cfg(not(target_arch = "wasm32"))]
+    let cancel = watchdog::current_cancel_token();
+    #[cfg(not(target_arch = "wasm32"))]
+    let watched = request.timeout_ms.is_some() || memory_budget.is_some() || cancel.is_some();
+    #[cfg(not(target_arch = "wasm32"))]
+    let (interrupt_signal_tx, signal_rx) = if watched {
+        let (tx, rx) = watchdog::ExecutionWatchdog::channel();
+        (Some(tx), Some(rx))
+    } else {
+        (None, None)
+    };
+    #[cfg(target_arch = "wasm32")]
+    let signal_rx = None;
+
     // Create fresh sandboxed interpreter
-    let interpreter = create_sandboxed_interpreter();
+    let interpreter = create_sandboxed_interpreter_with_signals(signal_rx);
 
     // Enter the interpreter context
-    interpreter.enter(|vm| {
+    let mut result = interpreter.enter(|vm| {
         // Create a scope for execution
         let scope = vm.new_scope_with_builtins();
 
         // First, run sandbox setup code to configure restrictions
-        // Use build_sandbox_setup_code() to generate the setup with allowed modules
-        // from the Rust ALLOWED_MODULES constant (single source of truth)
-        let setup_code_str = build_sandbox_setup_code();
+        // The allowed modules come from the Rust ALLOWED_MODULES constant (single
+        // source of truth) unless the request supplies its own list
+        let allowed_modules = resolve_allowed_modules(request.allowed_modules.as_deref());
+        let setup_code_str = build_sandbox_setup_code_with_modules(&allowed_modules);
         let setup_code = match vm.compile(
             &setup_code_str,
             Mode::Exec,
@@ -119,7 +229,7 @@ pub fn execute(request: &ExecutionRequest) -> ExecutionResult {
             if let Err(e) = vm.run_code_obj(module_code, scope.clone()) {
                 let error_msg = format!(
                     "Tool module injection failed: {}",
-                    format_python_exception(&e, vm)
+                    format_python_exception(&e, vm, "")
                 );
                 return ExecutionResult {
                     status: ExecutionStatus::Error(error_msg),
@@ -129,13 +239,34 @@ pub fn execute(request: &ExecutionRequest) -> ExecutionResult {
             }
         }
 
-        // Join code lines and compile user code
+        // Join code lines and compile user code. A trailing bare expression is
+        // compiled separately in eval mode so its value can be returned.
         let code_str = request.code.join("\n");
+        let (body_str, trailing_expr) = split_trailing_expression(&code_str);
 
-        let user_code = match vm.compile(&code_str, Mode::Exec, "<code_execution>".to_string()) {
+        let user_code = match vm.compile(body_str, Mode::Exec, USER_SOURCE_PATH.to_string()) {
+            Ok(code) => code,
+            Err(e) => {
+                let error_msg = format_compile_error(&e, &code_str);
+                return ExecutionResult {
+                    status: ExecutionStatus::Error(error_msg.clone()),
+                    stderr: format!("{}\n{}", get_stderr(), error_msg),
+                    ..Default::default()
+                };
+            }
+        };
+        // Pad the expression with the body's line count so its line numbers
+        // match the original source in tracebacks
+        let trailing_expr_code = match trailing_expr
+            .map(|expr| {
+                let padded = format!("{}{}", "\n".repeat(body_str.matches('\n').count()), expr);
+                vm.compile(&padded, Mode::Eval, USER_SOURCE_PATH.to_string())
+            })
+            .transpose()
+        {
             Ok(code) => code,
             Err(e) => {
-                let error_msg = format!("Compilation failed: {:?}", e);
+                let error_msg = format_compile_error(&e, &code_str);
                 return ExecutionResult {
                     status: ExecutionStatus::Error(error_msg.clone()),
                     stderr: format!("{}\n{}", get_stderr(), error_msg),
@@ -153,8 +284,56 @@ pub fn execute(request: &ExecutionRequest) -> ExecutionResult {
             }
         }
 
-        // Execute the user code
-        let result = vm.run_code_obj(user_code, scope);
+        // Execute the user code (under the watchdog when a limit is set)
+        #[cfg(not(target_arch = "wasm32"))]
+        let watchdog = interrupt_signal_tx.map(|tx| {
+            watchdog::ExecutionWatchdog::start(
+                request.timeout_ms,
+                memory_budget.clone(),
+                cancel.clone(),
+                tx,
+            )
+        });
+
+        let mut return_obj = None;
+        let result = vm
+            .run_code_obj(user_code, scope.clone())
+            .and_then(|body_result| {
+                if let Some(expr_code) = trailing_expr_code {
+                    return_obj = Some(vm.run_code_obj(expr_code, scope.clone())?);
+                }
+                Ok(body_result)
+            });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let interrupt_error = match watchdog.and_then(|w| w.finish()) {
+            Some(watchdog::Interruption::TimedOut) => Some(watchdog::timeout_message(
+                request.timeout_ms.unwrap_or_default(),
+            )),
+            Some(watchdog::Interruption::Cancelled) => {
+                Some(watchdog::CANCELLED_MESSAGE.to_string())
+            }
+            _ => None,
+        };
+        #[cfg(target_arch = "wasm32")]
+        let interrupt_error: Option<String> = None;
+
+        // The memory flag is checked directly so overruns are reported even
+        // when the code finished before the watchdog noticed them
+        let memory_exceeded = memory_budget.as_ref().is_some_and(|b| b.exceeded());
+        let limit_error = if memory_exceeded {
+            Some(MEMORY_LIMIT_MESSAGE.to_string())
+        } else {
+            interrupt_error
+        };
+        if let Some(error_msg) = limit_error {
+            return ExecutionResult {
+                status: ExecutionStatus::Error(error_msg.clone()),
+                stdout: get_stdout(),
+                stderr: format!("{}\n{}", get_stderr(), error_msg),
+                ..Default::default()
+            };
+        }
 
         // Check for pending tool calls
         let pending_calls = get_pending_calls();
@@ -170,23 +349,36 @@ pub fn execute(request: &ExecutionRequest) -> ExecutionResult {
                         stdout: get_stdout(),
                         stderr: get_stderr(),
                         result: result_value,
+                        return_value: None,
+                        session_globals: None,
                         pending_calls,
                         tool_calls_made: num_pending,
+                        ..Default::default()
                     }
                 } else {
+                    let return_value = return_obj
+                        .filter(|obj| !vm.is_none(obj))
+                        .and_then(|obj| pyobject_to_json(&obj, vm).ok());
+                    let session_globals = request
+                        .capture_session
+                        .then(|| collect_session_globals(&scope.globals, vm));
                     ExecutionResult {
                         status: ExecutionStatus::Complete,
                         stdout: get_stdout(),
                         stderr: get_stderr(),
                         result: result_value,
+                        return_value,
+                        session_globals,
+                        final_answer: get_final_answer(),
                         pending_calls: Vec::new(),
                         tool_calls_made: 0,
+                        ..Default::default()
                     }
                 }
             }
             Err(exc) => {
                 // Extract the actual error message from the Python exception
-                let error_msg = format_python_exception(&exc, vm);
+                let error_msg = format_python_exception(&exc, vm, &code_str);
                 let num_pending = pending_calls.len();
 
                 if error_msg.contains("ToolCallPending:") || !pending_calls.is_empty() {
@@ -195,8 +387,11 @@ pub fn execute(request: &ExecutionRequest) -> ExecutionResult {
                         stdout: get_stdout(),
                         stderr: get_stderr(),
                         result: None,
+                        return_value: None,
+                        session_globals: None,
                         pending_calls,
                         tool_calls_made: num_pending,
+                        ..Default::default()
                     }
                 } else {
                     ExecutionResult {
@@ -204,13 +399,84 @@ pub fn execute(request: &ExecutionRequest) -> ExecutionResult {
                         stdout: get_stdout(),
                         stderr: format!("{}\n{}", get_stderr(), error_msg),
                         result: None,
+                        return_value: None,
+                        session_globals: None,
                         pending_calls: Vec::new(),
                         tool_calls_made: 0,
+                        ..Default::default()
                     }
                 }
             }
         }
-    })
+    });
+
+    result.stdout_truncated = stdout_truncated();
+    result.stderr_truncated = stderr_truncated();
+    result
+}
+
+/// Execute Python code, passing each stdout chunk to `on_stdout` as it is written
+///
+/// The returned result still carries the full stdout, so callers that only
+/// need progress can ignore the chunks afterwards.
+pub fn execute_with_stdout_listener(
+    request: &ExecutionRequest,
+    on_stdout: StdoutListener,
+) -> ExecutionResult {
+    set_stdout_listener(Some(on_stdout));
+    let result = execute(request);
+    set_stdout_listener(None);
+    result
+}
+
+/// Execute Python code that stops early once `cancel` is tripped from another thread
+///
+/// A cancelled run is reported as an error with `watchdog::CANCELLED_MESSAGE`.
+/// Stdout chunks are passed to `on_stdout` as they are written, when given.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn execute_with_cancel(
+    request: &ExecutionRequest,
+    cancel: &watchdog::CancelToken,
+    on_stdout: Option<StdoutListener>,
+) -> ExecutionResult {
+    watchdog::set_cancel_token(Some(cancel.clone()));
+    set_stdout_listener(on_stdout);
+    let result = execute(request);
+    set_stdout_listener(None);
+    watchdog::set_cancel_token(None);
+    result
+}
+
+/// Compile Python code without running it
+///
+/// Syntax errors are formatted the same way `execute` reports them.
+pub fn check_syntax(code: &str) -> Result<(), String> {
+    rustpython_compiler::compile(
+        code,
+        Mode::Exec,
+        USER_SOURCE_PATH.to_string(),
+        Default::default(),
+    )
+    .map(|_| ())
+    .map_err(|e| format_compile_error(&e, code))
+}
+
+/// Split user code into the statements to exec and a trailing expression to eval.
+///
+/// Returns `(body, Some(expr))` when the last top-level statement is a bare
+/// expression, otherwise `(code, None)`. Code that fails to parse is returned
+/// untouched so compilation reports the syntax error as before.
+fn split_trailing_expression(code: &str) -> (&str, Option<&str>) {
+    let Ok(suite) = ast::Suite::parse(code, USER_SOURCE_PATH) else {
+        return (code, None);
+    };
+    match suite.last() {
+        Some(ast::Stmt::Expr(ast::StmtExpr { range, .. })) => {
+            let start = usize::from(range.start());
+            (&code[..start], Some(&code[start..]))
+        }
+        _ => (code, None),
+    }
 }
 
 // ============ WASM Exports ============
@@ -292,7 +558,7 @@ fn encode_result(result: &ExecutionResult) -> *mut u8 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use protocol::ToolInfo;
+    use protocol::{ToolInfo, DEFAULT_MEMORY_LIMIT_BYTES, DEFAULT_OUTPUT_LIMIT_BYTES};
     use std::collections::HashMap;
 
     // ============ Test Helper ============
@@ -305,6 +571,12 @@ mod tests {
             tool_results: HashMap::new(),
             available_tools: vec![],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
         execute(&request)
     }
@@ -317,6 +589,12 @@ mod tests {
             tool_results: HashMap::new(),
             available_tools: vec![],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
         execute(&request)
     }
@@ -329,6 +607,7 @@ mod tests {
             description: description.map(|s| s.to_string()),
             parameters: serde_json::json!({}),
             python_module: None,
+            streamable: false,
         }
     }
 
@@ -342,6 +621,12 @@ mod tests {
             tool_results: HashMap::new(),
             available_tools: vec![],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -358,6 +643,12 @@ mod tests {
             tool_results: HashMap::new(),
             available_tools: vec![],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -395,6 +686,12 @@ mod tests {
             tool_results: HashMap::new(),
             available_tools: vec![],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -437,6 +734,12 @@ mod tests {
             tool_results: HashMap::new(),
             available_tools: vec![],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -457,8 +760,15 @@ mod tests {
                 description: Some("Get weather".to_string()),
                 parameters: serde_json::json!({}),
                 python_module: None,
+                streamable: false,
             }],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -479,6 +789,12 @@ mod tests {
             tool_results: HashMap::new(),
             available_tools: vec![],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -499,6 +815,12 @@ mod tests {
             tool_results: HashMap::new(),
             available_tools: vec![],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -524,6 +846,12 @@ mod tests {
             tool_results: HashMap::new(),
             available_tools: vec![],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -549,6 +877,12 @@ mod tests {
             tool_results: HashMap::new(),
             available_tools: vec![],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -570,6 +904,12 @@ mod tests {
             tool_results: HashMap::new(),
             available_tools: vec![],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -590,6 +930,12 @@ mod tests {
             tool_results: HashMap::new(),
             available_tools: vec![],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -624,8 +970,15 @@ mod tests {
                 description: Some("Get current time".to_string()),
                 parameters: serde_json::json!({}),
                 python_module: None,
+                streamable: false,
             }],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -634,6 +987,27 @@ mod tests {
         assert!(result.stdout.contains("2024-01-15"));
     }
 
+    #[test]
+    fn test_final_answer_records_the_answer() {
+        let result = exec_code(&[
+            "total = 40 + 2",
+            "final_answer(f'The total is {total}.')",
+            "print('still runs')",
+        ]);
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        assert_eq!(result.final_answer.as_deref(), Some("The total is 42."));
+        assert!(result.stdout.contains("still runs"));
+
+        // Keyword form, non-string answers, and runs that never call it
+        let result = exec_code(&["final_answer(answer=42)"]);
+        assert_eq!(result.final_answer.as_deref(), Some("42"));
+        assert_eq!(exec_code(&["print(1)"]).final_answer, None);
+
+        let result = exec_code(&["final_answer()"]);
+        assert!(matches!(result.status, ExecutionStatus::Error(_)));
+        assert_eq!(result.final_answer, None);
+    }
+
     // ============ Python Language Features - Success Cases ============
 
     #[test]
@@ -943,6 +1317,53 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_data_csv_round_trip() {
+        // Quoted fields with commas, quotes and newlines survive a round trip
+        let result = exec_code(&[
+            "import data",
+            "rows = [{'name': 'Smith, Ann', 'note': 'said \"hi\"'}, {'name': 'Bo', 'note': 'a\\nb'}]",
+            "text = data.to_csv(rows)",
+            "back = data.parse_csv(text)",
+            "print(f'Equal: {back == rows}')",
+            "print(f'Header: {text.splitlines()[0]}')",
+            "crlf = data.parse_csv('a,b\\r\\n1,2\\r\\n3\\r\\n')",
+            "print(f'Rows: {len(crlf)} {crlf[1]}')",
+        ]);
+        assert_eq!(
+            result.status,
+            ExecutionStatus::Complete,
+            "data csv helpers should work. Error: {:?}",
+            result.status
+        );
+        assert!(result.stdout.contains("Equal: True"), "stdout: {}", result.stdout);
+        assert!(result.stdout.contains("Header: name,note"), "stdout: {}", result.stdout);
+        assert!(
+            result.stdout.contains("Rows: 2 {'a': '3', 'b': ''}"),
+            "stdout: {}",
+            result.stdout
+        );
+    }
+
+    #[test]
+    fn test_data_json_round_trip() {
+        let result = exec_code(&[
+            "from data import to_json, from_json",
+            "value = {'items': [1, 2.5, None, True], 'name': 'x'}",
+            "text = to_json(value)",
+            "print(f'Equal: {from_json(text) == value}')",
+            "print(f'Indented: {chr(10) in text}')",
+        ]);
+        assert_eq!(
+            result.status,
+            ExecutionStatus::Complete,
+            "data json helpers should work. Error: {:?}",
+            result.status
+        );
+        assert!(result.stdout.contains("Equal: True"), "stdout: {}", result.stdout);
+        assert!(result.stdout.contains("Indented: True"), "stdout: {}", result.stdout);
+    }
+
     #[test]
     fn test_datetime_datetime_class() {
         // Test datetime.datetime class
@@ -1082,6 +1503,58 @@ mod tests {
         let result = exec_code(&["None"]);
         assert_eq!(result.status, ExecutionStatus::Complete);
         assert_eq!(result.result, Some(serde_json::json!(null)));
+        // A None-valued final expression carries no return value
+        assert_eq!(result.return_value, None);
+    }
+
+    #[test]
+    fn test_trailing_expression_return_value() {
+        let result = exec_code(&["x = 40", "y = 2", "x + y"]);
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        assert_eq!(result.return_value, Some(serde_json::json!(42)));
+        assert!(result.stdout.is_empty());
+    }
+
+    #[test]
+    fn test_trailing_expression_structured_value() {
+        let result = exec_code(&[
+            "rows = [{'name': 'a', 'n': 1}, {'name': 'b', 'n': 2}]",
+            "[r for r in rows",
+            " if r['n'] > 1]",
+        ]);
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        assert_eq!(
+            result.return_value,
+            Some(serde_json::json!([{"name": "b", "n": 2}]))
+        );
+    }
+
+    #[test]
+    fn test_trailing_statement_has_no_return_value() {
+        let result = exec_code(&["total = 0", "for i in range(3):", "    total += i"]);
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        assert_eq!(result.return_value, None);
+
+        let result = exec_code(&["def f():", "    return 1"]);
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        assert_eq!(result.return_value, None);
+    }
+
+    #[test]
+    fn test_trailing_print_has_no_return_value() {
+        let result = exec_code(&["print('hi')"]);
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        assert!(result.stdout.contains("hi"));
+        assert_eq!(result.return_value, None);
+    }
+
+    #[test]
+    fn test_trailing_expression_error_reported() {
+        let result = exec_code(&["x = {}", "x['missing']"]);
+        match result.status {
+            ExecutionStatus::Error(ref msg) => assert!(msg.contains("KeyError"), "{}", msg),
+            other => panic!("Expected KeyError, got {:?}", other),
+        }
     }
 
     #[test]
@@ -1090,6 +1563,63 @@ mod tests {
         let result = exec_code(&["x = 42", "print(x)"]);
         assert_eq!(result.status, ExecutionStatus::Complete);
         assert!(result.stdout.contains("42"));
+        assert!(!result.stdout_truncated);
+    }
+
+    #[test]
+    fn test_heavy_printing_is_truncated() {
+        let request = ExecutionRequest {
+            code: vec![
+                "for i in range(20000):".to_string(),
+                "    print('line', i)".to_string(),
+                "print('done')".to_string(),
+            ],
+            ..Default::default()
+        }
+        .with_output_limit_bytes(Some(1000));
+        let result = execute(&request);
+
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        assert!(result.stdout_truncated);
+        assert!(!result.stderr_truncated);
+        assert!(result.stdout.starts_with("line 0\nline 1\n"));
+        assert!(result.stdout.ends_with(sandbox::OUTPUT_TRUNCATED_MARKER));
+        assert_eq!(result.stdout.len(), 1000 + sandbox::OUTPUT_TRUNCATED_MARKER.len());
+        assert_eq!(result.stdout.matches("[output truncated]").count(), 1);
+    }
+
+    #[test]
+    fn test_check_syntax() {
+        assert!(check_syntax("x = 1\nprint(x)").is_ok());
+        // Names are not resolved, so undefined variables still pass
+        assert!(check_syntax("print(undefined_variable)").is_ok());
+
+        let err = check_syntax("x = 1\nif x == 1\n    print(x)").unwrap_err();
+        assert!(err.contains("line 2"), "got: {}", err);
+        assert!(err.contains("if x == 1"), "got: {}", err);
+    }
+
+    #[test]
+    fn test_stdout_listener_streams_prints() {
+        use std::sync::mpsc;
+        let (tx, rx) = mpsc::channel();
+        let request = ExecutionRequest {
+            code: vec![
+                "for i in range(3):".to_string(),
+                "    print('step', i)".to_string(),
+            ],
+            ..Default::default()
+        };
+        let result = execute_with_stdout_listener(
+            &request,
+            Box::new(move |chunk| {
+                let _ = tx.send(chunk.to_string());
+            }),
+        );
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        let chunks: Vec<String> = rx.try_iter().collect();
+        assert_eq!(chunks, vec!["step 0\n", "step 1\n", "step 2\n"]);
+        assert_eq!(result.stdout, chunks.concat());
     }
 
     #[test]
@@ -1811,6 +2341,282 @@ mod tests {
         assert!(result.stdout.contains("49995000"));
     }
 
+    #[test]
+    fn test_infinite_loop_times_out() {
+        let request = ExecutionRequest::new(vec![
+            "print('before loop')".to_string(),
+            "while True:".to_string(),
+            "    pass".to_string(),
+        ])
+        .with_timeout_ms(200);
+
+        let result = execute(&request);
+        match result.status {
+            ExecutionStatus::Error(ref msg) => {
+                assert_eq!(msg, "Execution timed out after 200 ms");
+            }
+            other => panic!("Expected timeout error, got {:?}", other),
+        }
+        // Output produced before the timeout is preserved
+        assert!(result.stdout.contains("before loop"));
+    }
+
+    #[test]
+    fn test_timeout_cannot_be_swallowed() {
+        let request = ExecutionRequest::new(vec![
+            "while True:".to_string(),
+            "    try:".to_string(),
+            "        while True:".to_string(),
+            "            pass".to_string(),
+            "    except BaseException:".to_string(),
+            "        pass".to_string(),
+        ])
+        .with_timeout_ms(100);
+
+        let result = execute(&request);
+        assert_eq!(
+            result.status,
+            ExecutionStatus::Error("Execution timed out after 100 ms".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fast_code_completes_within_timeout() {
+        let request = ExecutionRequest::new(vec!["print(sum(range(100)))".to_string()])
+            .with_timeout_ms(5_000);
+
+        let result = execute(&request);
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        assert!(result.stdout.contains("4950"));
+    }
+
+    #[test]
+    fn test_cancel_stops_running_code() {
+        let request = ExecutionRequest::new(vec![
+            "print('before loop')".to_string(),
+            "while True:".to_string(),
+            "    pass".to_string(),
+        ]);
+        let cancel = watchdog::CancelToken::new();
+        let trigger = cancel.clone();
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            trigger.cancel();
+        });
+
+        let result = execute_with_cancel(&request, &cancel, None);
+        canceller.join().unwrap();
+        assert_eq!(
+            result.status,
+            ExecutionStatus::Error("Execution cancelled".to_string())
+        );
+        assert!(result.stdout.contains("before loop"));
+    }
+
+    #[test]
+    fn test_traceback_shows_user_lines() {
+        let result = exec_code(&[
+            "x = 1",
+            "def parse(value):",
+            "    return int(value)",
+            "y = parse('abc')",
+        ]);
+        match result.status {
+            ExecutionStatus::Error(ref msg) => {
+                assert!(
+                    msg.starts_with("Traceback (most recent call last):"),
+                    "{}",
+                    msg
+                );
+                assert!(
+                    msg.contains(
+                        "  File \"<code_execution>\", line 4, in <module>\n    y = parse('abc')"
+                    ),
+                    "{}",
+                    msg
+                );
+                assert!(
+                    msg.contains(
+                        "  File \"<code_execution>\", line 3, in parse\n    return int(value)"
+                    ),
+                    "{}",
+                    msg
+                );
+                assert!(msg.ends_with("ValueError: invalid literal for int() with base 10: 'abc'"));
+            }
+            other => panic!("Expected error, got {:?}", other),
+        }
+        assert!(result.stderr.contains("line 3, in parse"));
+    }
+
+    #[test]
+    fn test_traceback_line_of_trailing_expression() {
+        let result = exec_code(&["data = {}", "", "data['missing']"]);
+        match result.status {
+            ExecutionStatus::Error(ref msg) => {
+                assert!(
+                    msg.contains("line 3, in <module>\n    data['missing']"),
+                    "{}",
+                    msg
+                );
+                assert!(msg.contains("KeyError"));
+            }
+            other => panic!("Expected error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_syntax_error_shows_location_and_snippet() {
+        let result = exec_code(&["x = 1", "print(x))"]);
+        match result.status {
+            ExecutionStatus::Error(ref msg) => {
+                assert!(msg.starts_with("Compilation failed: "), "{}", msg);
+                assert!(msg.contains("(line 2, column "), "{}", msg);
+                assert!(
+                    msg.contains("File \"<code_execution>\", line 2\n    print(x))\n"),
+                    "{}",
+                    msg
+                );
+                assert!(msg.ends_with('^'));
+            }
+            other => panic!("Expected compilation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_large_list_comprehension_exceeds_memory_limit() {
+        let request = ExecutionRequest::new(vec![
+            "print('allocating')".to_string(),
+            "data = [str(i) * 10 for i in range(10**8)]".to_string(),
+            "print('done')".to_string(),
+        ])
+        .with_memory_limit_bytes(Some(64 * 1024 * 1024));
+
+        let result = execute(&request);
+        assert_eq!(
+            result.status,
+            ExecutionStatus::Error("memory limit exceeded".to_string())
+        );
+        assert!(result.stdout.contains("allocating"));
+        assert!(!result.stdout.contains("done"));
+    }
+
+    #[test]
+    fn test_memory_limit_cannot_be_swallowed() {
+        let request = ExecutionRequest::new(vec![
+            "try:".to_string(),
+            "    data = [str(i) * 10 for i in range(10**8)]".to_string(),
+            "except MemoryError:".to_string(),
+            "    data = []".to_string(),
+            "print('recovered')".to_string(),
+        ])
+        .with_memory_limit_bytes(Some(64 * 1024 * 1024));
+
+        let result = execute(&request);
+        assert_eq!(
+            result.status,
+            ExecutionStatus::Error("memory limit exceeded".to_string())
+        );
+        assert!(!result.stdout.contains("recovered"));
+    }
+
+    #[test]
+    fn test_small_program_within_memory_limit() {
+        let request = ExecutionRequest::new(vec![
+            "data = [i * i for i in range(10000)]".to_string(),
+            "print(sum(data))".to_string(),
+        ])
+        .with_memory_limit_bytes(Some(64 * 1024 * 1024));
+
+        let result = execute(&request);
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        assert!(result.stdout.contains("333283335000"));
+    }
+
+    #[test]
+    fn test_session_capture_keeps_plain_data_only() {
+        let request = ExecutionRequest::new(vec![
+            "import math".to_string(),
+            "count = 3".to_string(),
+            "rows = [{'id': 1, 'tags': ['a']}, {'id': 2, 'tags': []}]".to_string(),
+            "ratio = 0.5".to_string(),
+            "_scratch = 'private'".to_string(),
+            "def helper():".to_string(),
+            "    return 1".to_string(),
+            "pair = (1, 2)".to_string(),
+        ])
+        .with_context(serde_json::json!({"injected": 7}))
+        .with_session_capture();
+
+        let result = execute(&request);
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        let globals = result.session_globals.expect("session globals requested");
+        assert_eq!(globals.get("count"), Some(&serde_json::json!(3)));
+        assert_eq!(globals.get("ratio"), Some(&serde_json::json!(0.5)));
+        assert_eq!(globals.get("injected"), Some(&serde_json::json!(7)));
+        assert_eq!(
+            globals.get("rows"),
+            Some(&serde_json::json!([{"id": 1, "tags": ["a"]}, {"id": 2, "tags": []}]))
+        );
+        // Modules, functions, tuples and private names are skipped
+        for skipped in ["math", "helper", "pair", "_scratch", "print"] {
+            assert!(
+                !globals.contains_key(skipped),
+                "{} should not be captured",
+                skipped
+            );
+        }
+    }
+
+    #[test]
+    fn test_session_globals_not_captured_by_default() {
+        let result = exec_code(&["count = 3"]);
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        assert!(result.session_globals.is_none());
+    }
+
+    #[test]
+    fn test_session_capture_with_tool_results() {
+        let tool = ToolInfo {
+            name: "get_total".to_string(),
+            server_id: "test_server".to_string(),
+            description: None,
+            parameters: serde_json::json!({}),
+            python_module: None,
+            streamable: false,
+        };
+        let code = vec!["total = tool_call('get_total')".to_string()];
+
+        // First round pauses on the tool call and captures nothing
+        let first = execute(
+            &ExecutionRequest::new(code.clone())
+                .with_tools(vec![tool.clone()])
+                .with_session_capture(),
+        );
+        assert_eq!(first.status, ExecutionStatus::ToolCallsPending);
+        assert!(first.session_globals.is_none());
+
+        // Second round sees the injected result and captures the variable
+        let mut tool_results = HashMap::new();
+        tool_results.insert(
+            "get_total".to_string(),
+            protocol::ToolCallResult {
+                success: true,
+                result: serde_json::json!(99),
+                error: None,
+            },
+        );
+        let second = execute(
+            &ExecutionRequest::new(code)
+                .with_tools(vec![tool])
+                .with_tool_results(tool_results)
+                .with_session_capture(),
+        );
+        assert_eq!(second.status, ExecutionStatus::Complete);
+        let globals = second.session_globals.expect("session globals requested");
+        assert_eq!(globals.get("total"), Some(&serde_json::json!(99)));
+    }
+
     // ============ Tool Call Scenarios ============
 
     #[test]
@@ -1835,8 +2641,15 @@ mod tests {
                     }
                 }),
                 python_module: None,
+                streamable: false,
             }],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -1866,8 +2679,15 @@ mod tests {
                 description: Some("Create a user".to_string()),
                 parameters: serde_json::json!({}),
                 python_module: None,
+                streamable: false,
             }],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -1912,8 +2732,15 @@ mod tests {
                 description: Some("A tool that fails".to_string()),
                 parameters: serde_json::json!({}),
                 python_module: None,
+                streamable: false,
             }],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -1935,6 +2762,12 @@ mod tests {
             tool_results: HashMap::new(),
             available_tools: vec![], // No tools available
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -1985,8 +2818,15 @@ mod tests {
                 description: Some("Get numbers".to_string()),
                 parameters: serde_json::json!({}),
                 python_module: None,
+                streamable: false,
             }],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -2013,8 +2853,15 @@ mod tests {
                 description: Some("Calculate".to_string()),
                 parameters: serde_json::json!({}),
                 python_module: None,
+                streamable: false,
             }],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -2042,8 +2889,15 @@ mod tests {
                 description: Some("Get item by index".to_string()),
                 parameters: serde_json::json!({}),
                 python_module: None,
+                streamable: false,
             }],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -2072,8 +2926,15 @@ mod tests {
                 description: Some("Conditional tool".to_string()),
                 parameters: serde_json::json!({}),
                 python_module: None,
+                streamable: false,
             }],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -2096,8 +2957,15 @@ mod tests {
                 description: Some("Search".to_string()),
                 parameters: serde_json::json!({}),
                 python_module: None,
+                streamable: false,
             }],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -2108,6 +2976,190 @@ mod tests {
         assert!(args["filter"].is_null());
     }
 
+    #[test]
+    fn test_tool_call_stream_consumes_chunks_across_rounds() {
+        // Mock streaming tool: the host delivers a growing list of chunks per round
+        let tail_log = ToolInfo {
+            streamable: true,
+            ..make_tool_info("tail_log", "log_server", Some("Tail a log"))
+        };
+        let code = vec![
+            "for line in tool_call_stream('tail_log', path='app.log'):".to_string(),
+            "    print('got', line)".to_string(),
+        ];
+        let request = |streams: HashMap<String, protocol::ToolStreamChunks>| {
+            ExecutionRequest::new(code.clone())
+                .with_tools(vec![tail_log.clone()])
+                .with_tool_streams(streams)
+        };
+        let delivered = |chunks: &[&str], done: bool| {
+            let mut streams = HashMap::new();
+            streams.insert(
+                "tail_log#1".to_string(),
+                protocol::ToolStreamChunks {
+                    chunks: chunks.iter().map(|c| serde_json::json!(c)).collect(),
+                    done,
+                    error: None,
+                },
+            );
+            streams
+        };
+
+        let first = execute(&request(HashMap::new()));
+        assert_eq!(first.status, ExecutionStatus::ToolCallsPending);
+        assert_eq!(first.pending_calls[0].id, "tail_log#1");
+        assert!(first.pending_calls[0].stream);
+        assert_eq!(first.pending_calls[0].arguments["path"], "app.log");
+
+        // Partial delivery: the chunks so far are consumed, then more are requested
+        let second = execute(&request(delivered(&["a", "b"], false)));
+        assert_eq!(second.status, ExecutionStatus::ToolCallsPending);
+        assert!(second.stdout.contains("got a\ngot b\n"));
+        assert_eq!(second.pending_calls[0].id, "tail_log#1");
+
+        let last = execute(&request(delivered(&["a", "b", "c"], true)));
+        assert_eq!(last.status, ExecutionStatus::Complete);
+        assert_eq!(last.stdout, "got a\ngot b\ngot c\n");
+    }
+
+    #[test]
+    fn test_tool_call_stream_buffers_non_streaming_tools() {
+        let mut tool_results = HashMap::new();
+        tool_results.insert(
+            "get_time".to_string(),
+            protocol::ToolCallResult {
+                success: true,
+                result: serde_json::json!("10:30"),
+                error: None,
+            },
+        );
+        let code = vec!["print(list(tool_call_stream('get_time')))".to_string()];
+        let tools = vec![make_tool_info("get_time", "time_server", None)];
+
+        let first = execute(&ExecutionRequest::new(code.clone()).with_tools(tools.clone()));
+        assert_eq!(first.status, ExecutionStatus::ToolCallsPending);
+        assert!(!first.pending_calls[0].stream);
+
+        let result = execute(
+            &ExecutionRequest::new(code)
+                .with_tools(tools)
+                .with_tool_results(tool_results),
+        );
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        assert_eq!(result.stdout.trim(), "['10:30']");
+    }
+
+    /// Request exposing a `search` tool (required `query`, optional `limit`/`sort`)
+    /// both as `tool_call('search', ...)` and as an injected `search(...)` function
+    fn search_tool_request(code: &[&str]) -> ExecutionRequest {
+        use crate::protocol::{ToolFunctionInfo, ToolModuleInfo};
+
+        let parameters = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "query": {"type": "string"},
+                "limit": {"type": "integer"},
+                "sort": {"type": "string"}
+            },
+            "required": ["query"]
+        });
+        ExecutionRequest {
+            code: code.iter().map(|s| s.to_string()).collect(),
+            available_tools: vec![ToolInfo {
+                name: "search".to_string(),
+                server_id: "search_server".to_string(),
+                description: Some("Search".to_string()),
+                parameters: parameters.clone(),
+                python_module: Some("search_tools".to_string()),
+                streamable: false,
+            }],
+            tool_modules: vec![ToolModuleInfo {
+                python_name: "search_tools".to_string(),
+                server_id: "search_server".to_string(),
+                functions: vec![ToolFunctionInfo {
+                    name: "search".to_string(),
+                    description: Some("Search".to_string()),
+                    parameters,
+                }],
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tool_call_mixed_required_and_optional_kwargs() {
+        let result = execute(&search_tool_request(&[
+            "result = search(query='rust', limit=5)",
+        ]));
+
+        assert_eq!(result.status, ExecutionStatus::ToolCallsPending);
+        let args = &result.pending_calls[0].arguments;
+        assert_eq!(args, &serde_json::json!({"query": "rust", "limit": 5}));
+
+        // Optional parameters may be omitted entirely
+        let result = execute(&search_tool_request(&[
+            "result = tool_call('search', query='rust')",
+        ]));
+        assert_eq!(result.status, ExecutionStatus::ToolCallsPending);
+        assert_eq!(
+            result.pending_calls[0].arguments,
+            serde_json::json!({"query": "rust"})
+        );
+    }
+
+    #[test]
+    fn test_tool_call_unknown_kwarg_raises_type_error() {
+        let result = execute(&search_tool_request(&[
+            "result = search(query='rust', limt=5)",
+        ]));
+
+        assert!(result.pending_calls.is_empty());
+        match result.status {
+            ExecutionStatus::Error(ref msg) => {
+                assert!(msg.contains("TypeError"), "got: {}", msg);
+                assert!(
+                    msg.contains("unexpected keyword argument 'limt'"),
+                    "got: {}",
+                    msg
+                );
+                assert!(msg.contains("limit, query, sort"), "got: {}", msg);
+            }
+            ref other => panic!("expected TypeError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tool_call_missing_required_kwarg_raises_type_error() {
+        let result = execute(&search_tool_request(&[
+            "result = tool_call('search', limit=5)",
+        ]));
+
+        assert!(result.pending_calls.is_empty());
+        match result.status {
+            ExecutionStatus::Error(ref msg) => {
+                assert!(msg.contains("TypeError"), "got: {}", msg);
+                assert!(msg.contains("'query'"), "got: {}", msg);
+            }
+            ref other => panic!("expected TypeError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tool_call_argument_errors_are_catchable() {
+        let result = execute(&search_tool_request(&[
+            "try:",
+            "    tool_call('search', 'rust')",
+            "except TypeError as e:",
+            "    print('caught:', e)",
+        ]));
+
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        assert!(result.pending_calls.is_empty());
+        assert!(result
+            .stdout
+            .contains("caught: search() takes keyword arguments only"));
+    }
+
     #[test]
     fn test_tool_call_print_before_and_after() {
         // Output before tool call should be captured
@@ -2135,8 +3187,15 @@ mod tests {
                 description: Some("Simple tool".to_string()),
                 parameters: serde_json::json!({}),
                 python_module: None,
+                streamable: false,
             }],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -2165,6 +3224,7 @@ mod tests {
                 description: Some("List datasets".to_string()),
                 parameters: serde_json::json!({}),
                 python_module: None,
+                streamable: false,
             }],
             tool_modules: vec![ToolModuleInfo {
                 python_name: "bigquery".to_string(),
@@ -2175,6 +3235,12 @@ mod tests {
                     parameters: serde_json::json!({}),
                 }],
             }],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -2209,6 +3275,7 @@ mod tests {
                 description: Some("List datasets".to_string()),
                 parameters: serde_json::json!({}),
                 python_module: None,
+                streamable: false,
             }],
             tool_modules: vec![ToolModuleInfo {
                 python_name: "bigquery".to_string(),
@@ -2219,6 +3286,12 @@ mod tests {
                     parameters: serde_json::json!({}),
                 }],
             }],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let result = execute(&request);
@@ -2394,4 +3467,58 @@ mod tests {
         assert!(setup_code.contains("'_py_abc'"), "Should include _py_abc");
         assert!(setup_code.contains("'_weakrefset'"), "Should include _weakrefset");
     }
+
+    #[test]
+    fn test_allowed_modules_override_restricts_user_imports() {
+        let request = ExecutionRequest::new(vec![
+            "import math".to_string(),
+            "print(math.sqrt(16))".to_string(),
+            "import random".to_string(),
+        ])
+        .with_allowed_modules(vec!["math".to_string()]);
+
+        let result = execute(&request);
+        assert!(result.stdout.contains("4.0"));
+        match result.status {
+            ExecutionStatus::Error(ref msg) => {
+                assert!(msg.contains("Import 'random' is not allowed"), "{}", msg);
+                assert!(msg.contains("Allowed modules: math."), "{}", msg);
+            }
+            other => panic!("Expected import error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_allowed_modules_override_keeps_stdlib_dependencies() {
+        // json needs re, codecs, etc. internally even though user code may not import them
+        let request = ExecutionRequest::new(vec![
+            "import json".to_string(),
+            "print(json.dumps({'a': [1, 2]}))".to_string(),
+            "from re import compile".to_string(),
+        ])
+        .with_allowed_modules(vec!["json".to_string()]);
+
+        let result = execute(&request);
+        assert!(result.stdout.contains("{\"a\": [1, 2]}"), "{}", result.stderr);
+        match result.status {
+            ExecutionStatus::Error(ref msg) => {
+                assert!(msg.contains("Import 're' is not allowed"), "{}", msg)
+            }
+            other => panic!("Expected import error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_allowed_modules_override_cannot_add_denylisted_modules() {
+        let request = ExecutionRequest::new(vec!["import subprocess".to_string()])
+            .with_allowed_modules(vec!["math".to_string(), "subprocess".to_string()]);
+
+        let result = execute(&request);
+        match result.status {
+            ExecutionStatus::Error(ref msg) => {
+                assert!(msg.contains("Import 'subprocess' is not allowed"), "{}", msg)
+            }
+            other => panic!("Expected import error, got {:?}", other),
+        }
+    }
 }
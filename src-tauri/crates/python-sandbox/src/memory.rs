@@ -0,0 +1,197 @@
+//! Memory accounting for sandboxed execution
+//!
+//! `CountingAllocator` forwards to the system allocator and counts the bytes
+//! owned by the execution running on the current thread; threads that are not
+//! running `execute()` with a memory limit pay a single thread-local check.
+//!
+//! The crate only installs it as the global allocator where the crate is the
+//! whole program: the `wasm32` guest module (one sandbox instance) and its own
+//! tests. Native hosts that run the sandbox in-process opt in by registering
+//! it themselves.
+//!
+//! In the guest an allocation that would pass the ceiling is refused. That
+//! aborts the instance, which the host reports as `MemoryError`. Natively a
+//! refused allocation would abort the whole host process, so the allocation
+//! goes through, the budget is flagged and the watchdog raises `MemoryError`
+//! inside the VM at the next instruction.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+use std::sync::Arc;
+
+/// Error message reported when an execution passes its memory ceiling
+pub const MEMORY_LIMIT_MESSAGE: &str = "memory limit exceeded";
+
+/// Whether allocations past the ceiling are refused rather than flagged
+const REFUSE_OVER_LIMIT: bool = cfg!(target_arch = "wasm32");
+
+/// Allocator that forwards to the system allocator while counting the bytes
+/// owned by the execution running on the current thread
+pub struct CountingAllocator;
+
+#[cfg(any(test, target_arch = "wasm32"))]
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+thread_local! {
+    /// Budget of the execution running on this thread (null when untracked)
+    static ACTIVE_BUDGET: Cell<*const MemoryBudget> = const { Cell::new(std::ptr::null()) };
+}
+
+/// Whether an allocation of `size` more bytes is allowed on this thread
+#[inline]
+fn admit(size: usize) -> bool {
+    ACTIVE_BUDGET
+        .try_with(|active| {
+            let budget = active.get();
+            // SAFETY: see `record`
+            budget.is_null() || unsafe { (*budget).admit(size) }
+        })
+        .unwrap_or(true)
+}
+
+/// Apply `delta` bytes to the budget tracked on this thread, if any
+#[inline]
+fn record(delta: isize) {
+    // try_with: the thread-local may already be gone during thread teardown
+    let _ = ACTIVE_BUDGET.try_with(|active| {
+        let budget = active.get();
+        if !budget.is_null() {
+            // SAFETY: the pointer is only set by `MemoryBudget::track`, whose guard
+            // keeps the budget alive and clears the pointer before releasing it.
+            unsafe { (*budget).record(delta) };
+        }
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if !admit(layout.size()) {
+            return std::ptr::null_mut();
+        }
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if !admit(layout.size()) {
+            return std::ptr::null_mut();
+        }
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        record(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size > layout.size() && !admit(new_size - layout.size()) {
+            return std::ptr::null_mut();
+        }
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record(new_size as isize - layout.size() as isize);
+        }
+        new_ptr
+    }
+}
+
+/// Live-byte budget for a single execution
+pub struct MemoryBudget {
+    limit_bytes: usize,
+    used_bytes: AtomicIsize,
+    exceeded: AtomicBool,
+}
+
+impl MemoryBudget {
+    /// Create a budget allowing `limit_bytes` of live allocations
+    pub fn new(limit_bytes: usize) -> Arc<Self> {
+        Arc::new(Self {
+            limit_bytes,
+            used_bytes: AtomicIsize::new(0),
+            exceeded: AtomicBool::new(false),
+        })
+    }
+
+    /// Count allocations made on the current thread against this budget
+    /// until the returned guard is dropped
+    pub fn track(self: &Arc<Self>) -> TrackingGuard {
+        let budget = Arc::clone(self);
+        let previous = ACTIVE_BUDGET.with(|active| active.replace(Arc::as_ptr(&budget)));
+        TrackingGuard {
+            _budget: budget,
+            previous,
+        }
+    }
+
+    /// Whether live allocations have passed the limit at any point
+    pub fn exceeded(&self) -> bool {
+        self.exceeded.load(Ordering::Relaxed)
+    }
+
+    /// Bytes currently counted against the budget. Memory allocated before
+    /// tracking started and freed during it can make this negative.
+    pub fn used_bytes(&self) -> isize {
+        self.used_bytes.load(Ordering::Relaxed)
+    }
+
+    /// Whether `size` more bytes fit in the budget. An allocation that does
+    /// not fit flags the budget, and is refused when refusing is safe.
+    // Must not allocate: this runs inside the global allocator
+    fn admit(&self, size: usize) -> bool {
+        let used = self.used_bytes.load(Ordering::Relaxed);
+        let size = isize::try_from(size).unwrap_or(isize::MAX);
+        if used.saturating_add(size) <= self.limit_bytes as isize {
+            return true;
+        }
+        self.exceeded.store(true, Ordering::Relaxed);
+        !REFUSE_OVER_LIMIT
+    }
+
+    // Must not allocate: this runs inside the global allocator
+    fn record(&self, delta: isize) {
+        let used = self.used_bytes.fetch_add(delta, Ordering::Relaxed) + delta;
+        if delta > 0 && used > self.limit_bytes as isize {
+            self.exceeded.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Stops counting against a budget when dropped
+pub struct TrackingGuard {
+    // Keeps the budget alive while the thread-local points at it
+    _budget: Arc<MemoryBudget>,
+    previous: *const MemoryBudget,
+}
+
+impl Drop for TrackingGuard {
+    fn drop(&mut self) {
+        let _ = ACTIVE_BUDGET.try_with(|active| active.set(self.previous));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admit_flags_allocations_past_the_limit() {
+        let budget = MemoryBudget::new(1024);
+        assert!(budget.admit(512));
+        assert!(!budget.exceeded());
+
+        // Natively the allocation still goes through; the guest refuses it
+        assert_eq!(budget.admit(4096), !REFUSE_OVER_LIMIT);
+        assert!(budget.exceeded());
+        assert_eq!(budget.admit(usize::MAX), !REFUSE_OVER_LIMIT);
+    }
+}
@@ -7,6 +7,20 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 
+/// Default ceiling on memory allocated by a single execution (256 MB)
+pub const DEFAULT_MEMORY_LIMIT_BYTES: usize = 256 * 1024 * 1024;
+
+/// Default cap on captured stdout, and separately on stderr (1 MB)
+pub const DEFAULT_OUTPUT_LIMIT_BYTES: usize = 1024 * 1024;
+
+fn default_memory_limit_bytes() -> Option<usize> {
+    Some(DEFAULT_MEMORY_LIMIT_BYTES)
+}
+
+fn default_output_limit_bytes() -> Option<usize> {
+    Some(DEFAULT_OUTPUT_LIMIT_BYTES)
+}
+
 /// Information about an available tool
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ToolInfo {
@@ -25,6 +39,9 @@ pub struct ToolInfo {
     /// Python module name for this tool's server (optional)
     #[serde(default)]
     pub python_module: Option<String>,
+    /// Whether the host can deliver the result in chunks (see `tool_call_stream`)
+    #[serde(default)]
+    pub streamable: bool,
 }
 
 /// Information about a tool module to be injected as an importable Python module
@@ -50,7 +67,7 @@ pub struct ToolFunctionInfo {
 }
 
 /// Request from host to execute Python code
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionRequest {
     /// Lines of Python code to execute
     #[serde(default)]
@@ -61,12 +78,51 @@ pub struct ExecutionRequest {
     /// Results from previous tool calls (for continuation)
     #[serde(default)]
     pub tool_results: HashMap<String, ToolCallResult>,
+    /// Chunks delivered so far for `tool_call_stream` calls, keyed by stream call id
+    #[serde(default)]
+    pub tool_streams: HashMap<String, ToolStreamChunks>,
     /// Available tools that can be called
     #[serde(default)]
     pub available_tools: Vec<ToolInfo>,
     /// Tool modules to inject as importable Python modules
     #[serde(default)]
     pub tool_modules: Vec<ToolModuleInfo>,
+    /// Wall-clock limit for running the user code, in milliseconds (None = unbounded)
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Ceiling on live bytes allocated during execution (None = unbounded)
+    #[serde(default = "default_memory_limit_bytes")]
+    pub memory_limit_bytes: Option<usize>,
+    /// Cap on bytes captured from each of stdout and stderr; later writes
+    /// are dropped (None = unbounded)
+    #[serde(default = "default_output_limit_bytes")]
+    pub output_limit_bytes: Option<usize>,
+    /// Modules user code may import, replacing the default list
+    /// (None = `sandbox::ALLOWED_MODULES`)
+    #[serde(default)]
+    pub allowed_modules: Option<Vec<String>>,
+    /// Return plain-data globals after a successful run so the host can carry
+    /// them into the next execution of the same session
+    #[serde(default)]
+    pub capture_session: bool,
+}
+
+impl Default for ExecutionRequest {
+    fn default() -> Self {
+        Self {
+            code: Vec::new(),
+            context: None,
+            tool_results: HashMap::new(),
+            tool_streams: HashMap::new(),
+            available_tools: Vec::new(),
+            tool_modules: Vec::new(),
+            timeout_ms: None,
+            memory_limit_bytes: default_memory_limit_bytes(),
+            output_limit_bytes: default_output_limit_bytes(),
+            allowed_modules: None,
+            capture_session: false,
+        }
+    }
 }
 
 impl ExecutionRequest {
@@ -96,11 +152,47 @@ impl ExecutionRequest {
         self
     }
 
+    /// Builder pattern: add the chunks delivered so far for streamed tool calls
+    pub fn with_tool_streams(mut self, streams: HashMap<String, ToolStreamChunks>) -> Self {
+        self.tool_streams = streams;
+        self
+    }
+
     /// Builder pattern: add tool modules
     pub fn with_tool_modules(mut self, modules: Vec<ToolModuleInfo>) -> Self {
         self.tool_modules = modules;
         self
     }
+
+    /// Builder pattern: set the wall-clock timeout
+    pub fn with_timeout_ms(mut self, timeout_ms: u64) -> Self {
+        self.timeout_ms = Some(timeout_ms);
+        self
+    }
+
+    /// Builder pattern: set the memory ceiling (None disables it)
+    pub fn with_memory_limit_bytes(mut self, limit: Option<usize>) -> Self {
+        self.memory_limit_bytes = limit;
+        self
+    }
+
+    /// Builder pattern: set the stdout/stderr capture cap (None disables it)
+    pub fn with_output_limit_bytes(mut self, limit: Option<usize>) -> Self {
+        self.output_limit_bytes = limit;
+        self
+    }
+
+    /// Builder pattern: restrict imports to the given modules
+    pub fn with_allowed_modules(mut self, modules: Vec<String>) -> Self {
+        self.allowed_modules = Some(modules);
+        self
+    }
+
+    /// Builder pattern: capture plain-data globals for session persistence
+    pub fn with_session_capture(mut self) -> Self {
+        self.capture_session = true;
+        self
+    }
 }
 
 /// Result of a tool call from a previous round
@@ -114,6 +206,21 @@ pub struct ToolCallResult {
     pub error: Option<String>,
 }
 
+/// Chunks of a streamed tool call delivered so far. The host re-sends the
+/// growing list with every round until the tool is done.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolStreamChunks {
+    /// Every chunk received so far, in order
+    #[serde(default)]
+    pub chunks: Vec<Value>,
+    /// Whether the tool has finished producing chunks
+    #[serde(default)]
+    pub done: bool,
+    /// Error that ended the stream, raised after the delivered chunks
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
 /// A pending tool call that needs to be executed by the host
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PendingToolCall {
@@ -125,6 +232,10 @@ pub struct PendingToolCall {
     pub server_id: String,
     /// Arguments to pass to the tool
     pub arguments: Value,
+    /// Whether this is a `tool_call_stream` call waiting for more chunks;
+    /// its `id` is the key to deliver them under in `tool_streams`
+    #[serde(default)]
+    pub stream: bool,
 }
 
 /// Status of execution
@@ -151,8 +262,23 @@ pub struct ExecutionResult {
     pub stdout: String,
     /// Standard error from Python
     pub stderr: String,
+    /// Whether stdout hit `output_limit_bytes` and later output was dropped
+    #[serde(default)]
+    pub stdout_truncated: bool,
+    /// Whether stderr hit `output_limit_bytes` and later output was dropped
+    #[serde(default)]
+    pub stderr_truncated: bool,
     /// Return value from the code (if any)
     pub result: Option<Value>,
+    /// Value of the final expression statement, when the code ends with one
+    #[serde(default)]
+    pub return_value: Option<Value>,
+    /// User globals captured when `capture_session` was requested
+    #[serde(default)]
+    pub session_globals: Option<serde_json::Map<String, Value>>,
+    /// Answer passed to `final_answer(...)` by code that ran to completion
+    #[serde(default)]
+    pub final_answer: Option<String>,
     /// Tool calls that need to be executed
     pub pending_calls: Vec<PendingToolCall>,
     /// Number of tool calls made in this execution
@@ -165,7 +291,12 @@ impl Default for ExecutionResult {
             status: ExecutionStatus::Complete,
             stdout: String::new(),
             stderr: String::new(),
+            stdout_truncated: false,
+            stderr_truncated: false,
             result: None,
+            return_value: None,
+            session_globals: None,
+            final_answer: None,
             pending_calls: Vec::new(),
             tool_calls_made: 0,
         }
@@ -203,6 +334,12 @@ mod tests {
             tool_results: HashMap::new(),
             available_tools: vec![],
             tool_modules: vec![],
+            timeout_ms: None,
+            memory_limit_bytes: Some(DEFAULT_MEMORY_LIMIT_BYTES),
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: None,
+            capture_session: false,
+            tool_streams: HashMap::new(),
         };
 
         let json = serde_json::to_string(&request).unwrap();
@@ -211,6 +348,18 @@ mod tests {
         assert_eq!(parsed.code.len(), 2);
     }
 
+    #[test]
+    fn test_execution_request_default_memory_limit() {
+        assert_eq!(
+            ExecutionRequest::new(vec![]).memory_limit_bytes,
+            Some(DEFAULT_MEMORY_LIMIT_BYTES)
+        );
+
+        // Requests from older hosts that omit the field still get the ceiling
+        let parsed: ExecutionRequest = serde_json::from_str(r#"{"code": ["x = 1"]}"#).unwrap();
+        assert_eq!(parsed.memory_limit_bytes, Some(DEFAULT_MEMORY_LIMIT_BYTES));
+    }
+
     #[test]
     fn test_execution_result_default() {
         let result = ExecutionResult::default();
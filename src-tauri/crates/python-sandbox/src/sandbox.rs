@@ -6,23 +6,45 @@
 //! - Injects the tool_call() function for calling external tools
 //! - Sets resource limits (recursion depth)
 
-use rustpython_vm::builtins::{PyDict, PyFloat, PyInt, PyList, PyModule, PyStr};
+use rustpython_vm::builtins::{PyDict, PyDictRef, PyFloat, PyInt, PyList, PyModule, PyStr};
 use rustpython_vm::function::FuncArgs;
+use rustpython_vm::signal::UserSignalReceiver;
 use rustpython_vm::{
     AsObject, Interpreter, PyObjectRef, PyPayload, PyRef, PyResult, Settings, VirtualMachine,
 };
 use serde_json::Value;
-use std::cell::RefCell;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
 
-use crate::protocol::{PendingToolCall, ToolCallResult, ToolInfo, ToolModuleInfo};
+use crate::protocol::{
+    PendingToolCall, ToolCallResult, ToolInfo, ToolModuleInfo, ToolStreamChunks,
+};
+
+/// Callback that observes stdout chunks while code runs
+pub type StdoutListener = Box<dyn FnMut(&str)>;
+
+/// Appended once to a capture buffer when its output limit is reached
+pub const OUTPUT_TRUNCATED_MARKER: &str = "\n…[output truncated]…\n";
 
 // Thread-local state for collecting tool calls during execution
 thread_local! {
     static PENDING_CALLS: RefCell<Vec<PendingToolCall>> = const { RefCell::new(Vec::new()) };
     static TOOL_RESULTS: RefCell<std::collections::HashMap<String, ToolCallResult>> = RefCell::new(std::collections::HashMap::new());
     static AVAILABLE_TOOLS: RefCell<Vec<ToolInfo>> = const { RefCell::new(Vec::new()) };
+    /// Chunks delivered so far for streamed tool calls, keyed by stream call id
+    static TOOL_STREAMS: RefCell<std::collections::HashMap<String, ToolStreamChunks>> = RefCell::new(std::collections::HashMap::new());
+    /// Streams opened by this run: call id -> (tool name, server id, arguments)
+    static STREAM_CALLS: RefCell<std::collections::HashMap<String, (String, String, Value)>> = RefCell::new(std::collections::HashMap::new());
     static STDOUT_BUFFER: RefCell<String> = const { RefCell::new(String::new()) };
     static STDERR_BUFFER: RefCell<String> = const { RefCell::new(String::new()) };
+    /// Cap on each capture buffer (see `set_output_limit`)
+    static OUTPUT_LIMIT: Cell<Option<usize>> = const { Cell::new(None) };
+    static STDOUT_TRUNCATED: Cell<bool> = const { Cell::new(false) };
+    static STDERR_TRUNCATED: Cell<bool> = const { Cell::new(false) };
+    /// Receives each stdout chunk as it is written (see `set_stdout_listener`)
+    static STDOUT_LISTENER: RefCell<Option<StdoutListener>> = const { RefCell::new(None) };
+    /// Answer recorded by `final_answer(...)` during this run
+    static FINAL_ANSWER: RefCell<Option<String>> = const { RefCell::new(None) };
     /// Tool modules that should be injected as importable Python modules
     static TOOL_MODULES: RefCell<Vec<ToolModuleInfo>> = const { RefCell::new(Vec::new()) };
 }
@@ -31,8 +53,13 @@ thread_local! {
 pub fn reset_execution_state() {
     PENDING_CALLS.with(|pc| pc.borrow_mut().clear());
     TOOL_RESULTS.with(|tr| tr.borrow_mut().clear());
+    TOOL_STREAMS.with(|ts| ts.borrow_mut().clear());
+    STREAM_CALLS.with(|sc| sc.borrow_mut().clear());
     STDOUT_BUFFER.with(|sb| sb.borrow_mut().clear());
     STDERR_BUFFER.with(|se| se.borrow_mut().clear());
+    STDOUT_TRUNCATED.with(|t| t.set(false));
+    STDERR_TRUNCATED.with(|t| t.set(false));
+    FINAL_ANSWER.with(|fa| *fa.borrow_mut() = None);
     // Note: We don't clear TOOL_MODULES here as they persist across executions
 }
 
@@ -111,7 +138,7 @@ pub fn generate_tool_module_code() -> String {
 
         // Add to allowed imports
         code.push_str(&format!(
-            "_sandbox_allowed_modules.add('{}')\n\n",
+            "_sandbox_allowed_modules.add('{0}')\n_sandbox_user_modules.add('{0}')\n\n",
             module.python_name
         ));
     }
@@ -143,6 +170,11 @@ pub fn set_tool_results(results: std::collections::HashMap<String, ToolCallResul
     TOOL_RESULTS.with(|tr| *tr.borrow_mut() = results);
 }
 
+/// Set the chunks delivered so far for streamed tool calls
+pub fn set_tool_streams(streams: std::collections::HashMap<String, ToolStreamChunks>) {
+    TOOL_STREAMS.with(|ts| *ts.borrow_mut() = streams);
+}
+
 /// Get the pending tool calls
 pub fn get_pending_calls() -> Vec<PendingToolCall> {
     PENDING_CALLS.with(|pc| pc.borrow().clone())
@@ -153,23 +185,93 @@ pub fn get_stdout() -> String {
     STDOUT_BUFFER.with(|sb| sb.borrow().clone())
 }
 
+/// Get the answer recorded by `final_answer(...)`, if the code called it
+pub fn get_final_answer() -> Option<String> {
+    FINAL_ANSWER.with(|fa| fa.borrow().clone())
+}
+
 /// Get the stderr buffer
 pub fn get_stderr() -> String {
     STDERR_BUFFER.with(|se| se.borrow().clone())
 }
 
+/// Set the byte cap applied to each of the stdout and stderr buffers
+pub fn set_output_limit(limit: Option<usize>) {
+    OUTPUT_LIMIT.with(|l| l.set(limit));
+}
+
+/// Whether stdout output was dropped after reaching the output limit
+pub fn stdout_truncated() -> bool {
+    STDOUT_TRUNCATED.with(|t| t.get())
+}
+
+/// Whether stderr output was dropped after reaching the output limit
+pub fn stderr_truncated() -> bool {
+    STDERR_TRUNCATED.with(|t| t.get())
+}
+
+/// Append `s` to a capture buffer without growing it past the output limit.
+/// Returns what was actually appended, which ends with the truncation marker
+/// on the write that reaches the limit and is empty after that.
+fn append_capped<'a>(buffer: &mut String, truncated: &Cell<bool>, s: &'a str) -> Cow<'a, str> {
+    if truncated.get() {
+        return Cow::Borrowed("");
+    }
+
+    let limit = OUTPUT_LIMIT.with(|l| l.get());
+    let Some(limit) = limit.filter(|limit| buffer.len() + s.len() > *limit) else {
+        buffer.push_str(s);
+        return Cow::Borrowed(s);
+    };
+
+    let mut keep = limit.saturating_sub(buffer.len()).min(s.len());
+    while !s.is_char_boundary(keep) {
+        keep -= 1;
+    }
+    let appended = format!("{}{}", &s[..keep], OUTPUT_TRUNCATED_MARKER);
+    buffer.push_str(&appended);
+    truncated.set(true);
+    Cow::Owned(appended)
+}
+
 /// Append to stdout
 pub fn append_stdout(s: &str) {
-    STDOUT_BUFFER.with(|sb| sb.borrow_mut().push_str(s));
+    let appended = STDOUT_BUFFER.with(|sb| {
+        STDOUT_TRUNCATED.with(|t| append_capped(&mut sb.borrow_mut(), t, s))
+    });
+    if appended.is_empty() {
+        return;
+    }
+    STDOUT_LISTENER.with(|listener| {
+        if let Some(listener) = listener.borrow_mut().as_mut() {
+            listener(&appended);
+        }
+    });
+}
+
+/// Install (or clear) the stdout listener for executions on this thread.
+/// Unlike the buffers it survives `reset_execution_state`.
+pub fn set_stdout_listener(listener: Option<StdoutListener>) {
+    STDOUT_LISTENER.with(|l| *l.borrow_mut() = listener);
 }
 
 /// Append to stderr  
 pub fn append_stderr(s: &str) {
-    STDERR_BUFFER.with(|se| se.borrow_mut().push_str(s));
+    STDERR_BUFFER.with(|se| {
+        STDERR_TRUNCATED.with(|t| append_capped(&mut se.borrow_mut(), t, s));
+    });
 }
 
 /// Create a sandboxed Python interpreter
 pub fn create_sandboxed_interpreter() -> Interpreter {
+    create_sandboxed_interpreter_with_signals(None)
+}
+
+/// Create a sandboxed interpreter that also drains `signal_rx` between bytecode
+/// instructions, so the host can interrupt running code (e.g. on timeout).
+pub fn create_sandboxed_interpreter_with_signals(
+    signal_rx: Option<UserSignalReceiver>,
+) -> Interpreter {
     let mut settings = Settings::default();
     settings.isolated = true;
     settings.user_site_directory = false;
@@ -187,6 +289,10 @@ pub fn create_sandboxed_interpreter() -> Interpreter {
 
         // Add our sandbox module with tool_call function
         vm.add_native_module("_sandbox".to_owned(), Box::new(make_sandbox_module));
+
+        if let Some(rx) = signal_rx {
+            vm.set_user_signal_channel(rx);
+        }
     })
 }
 
@@ -205,6 +311,18 @@ fn make_sandbox_module(vm: &VirtualMachine) -> PyRef<PyModule> {
         vm,
     );
 
+    // Add streamed tool call bridge (used by tool_call_stream)
+    let _ = dict.set_item(
+        "tool_stream_open",
+        vm.new_function("tool_stream_open", tool_stream_open_impl).into(),
+        vm,
+    );
+    let _ = dict.set_item(
+        "tool_stream_next",
+        vm.new_function("tool_stream_next", tool_stream_next_impl).into(),
+        vm,
+    );
+
     // Add get_tool_result function
     let _ = dict.set_item(
         "get_tool_result",
@@ -220,6 +338,13 @@ fn make_sandbox_module(vm: &VirtualMachine) -> PyRef<PyModule> {
         vm,
     );
 
+    // Add final_answer, which records the turn's answer
+    let _ = dict.set_item(
+        "final_answer",
+        vm.new_function("final_answer", final_answer_impl).into(),
+        vm,
+    );
+
     // Add stderr-capable print wrapper for agentic handoffs
     let _ = dict.set_item(
         "sandbox_stderr",
@@ -240,17 +365,34 @@ fn tool_call_impl(args: FuncArgs, vm: &VirtualMachine) -> PyResult {
         .ok_or_else(|| vm.new_type_error("tool_call requires a tool name".to_string()))?
         .try_to_value(vm)?;
 
+    // Tool parameters are passed by name only, so they map 1:1 onto MCP arguments
+    if args.args.len() > 1 {
+        return Err(vm.new_type_error(format!(
+            "{}() takes keyword arguments only, e.g. {}(name=value)",
+            tool_name, tool_name
+        )));
+    }
+
     // Get keyword arguments as a map
     let arguments = funcargs_to_json(&args, vm)?;
 
-    // Find the tool info to get server_id
-    let server_id = AVAILABLE_TOOLS.with(|at| {
+    // Find the tool info to get server_id and check arguments against its schema
+    let tool_info = AVAILABLE_TOOLS.with(|at| {
         at.borrow()
             .iter()
             .find(|t| t.name == tool_name)
-            .map(|t| t.server_id.clone())
-            .unwrap_or_else(|| "unknown".to_string())
+            .map(|t| (t.server_id.clone(), t.parameters.clone()))
     });
+    let server_id = match tool_info {
+        Some((server_id, parameters)) => {
+            if let Value::Object(map) = &arguments {
+                validate_tool_arguments(&tool_name, &parameters, map)
+                    .map_err(|msg| vm.new_type_error(msg))?;
+            }
+            server_id
+        }
+        None => "unknown".to_string(),
+    };
 
     // Generate a unique call ID
     let call_id = uuid::Uuid::new_v4().to_string();
@@ -275,6 +417,7 @@ fn tool_call_impl(args: FuncArgs, vm: &VirtualMachine) -> PyResult {
             tool_name,
             server_id,
             arguments,
+            stream: false,
         };
 
         PENDING_CALLS.with(|pc| pc.borrow_mut().push(pending_call));
@@ -286,6 +429,156 @@ fn tool_call_impl(args: FuncArgs, vm: &VirtualMachine) -> PyResult {
     }
 }
 
+/// Implementation of tool_stream_open(name, **kwargs) -> stream call id, or
+/// None when the tool doesn't stream (tool_call_stream then buffers)
+///
+/// Stream call ids are numbered in call order (`name#1`, `name#2`, ...), so
+/// they are the same every time the host re-runs the code with more chunks.
+fn tool_stream_open_impl(args: FuncArgs, vm: &VirtualMachine) -> PyResult {
+    let tool_name: String = args
+        .args
+        .first()
+        .ok_or_else(|| vm.new_type_error("tool_call_stream requires a tool name".to_string()))?
+        .try_to_value(vm)?;
+    let arguments = funcargs_to_json(&args, vm)?;
+
+    let tool_info = AVAILABLE_TOOLS.with(|at| {
+        at.borrow()
+            .iter()
+            .find(|t| t.name == tool_name && t.streamable)
+            .map(|t| (t.server_id.clone(), t.parameters.clone()))
+    });
+    let Some((server_id, parameters)) = tool_info else {
+        return Ok(vm.ctx.none());
+    };
+    if let Value::Object(map) = &arguments {
+        validate_tool_arguments(&tool_name, &parameters, map)
+            .map_err(|msg| vm.new_type_error(msg))?;
+    }
+
+    let call_id = STREAM_CALLS.with(|sc| {
+        let mut calls = sc.borrow_mut();
+        let call_id = format!("{}#{}", tool_name, calls.len() + 1);
+        calls.insert(call_id.clone(), (tool_name, server_id, arguments));
+        call_id
+    });
+    Ok(vm.ctx.new_str(call_id).into())
+}
+
+/// Implementation of tool_stream_next(call_id, consumed) -> list of new chunks,
+/// or None once the stream is done. Raises ToolCallPending when the host has
+/// to deliver more chunks first.
+fn tool_stream_next_impl(args: FuncArgs, vm: &VirtualMachine) -> PyResult {
+    let call_id: String = args
+        .args
+        .first()
+        .ok_or_else(|| vm.new_type_error("tool_stream_next requires a call id".to_string()))?
+        .try_to_value(vm)?;
+    let consumed: usize = match args.args.get(1) {
+        Some(value) => value.try_to_value(vm)?,
+        None => 0,
+    };
+
+    let stream = TOOL_STREAMS.with(|ts| ts.borrow().get(&call_id).cloned());
+    match stream {
+        Some(stream) if stream.chunks.len() > consumed => {
+            let chunks = stream.chunks[consumed..]
+                .iter()
+                .map(|chunk| json_to_pyobject(chunk, vm))
+                .collect::<PyResult<Vec<_>>>()?;
+            Ok(vm.ctx.new_list(chunks).into())
+        }
+        Some(ToolStreamChunks {
+            done: true, error, ..
+        }) => match error {
+            Some(error) => Err(vm.new_runtime_error(error)),
+            None => Ok(vm.ctx.none()),
+        },
+        _ => {
+            let (tool_name, server_id, arguments) = STREAM_CALLS
+                .with(|sc| sc.borrow().get(&call_id).cloned())
+                .ok_or_else(|| {
+                    vm.new_value_error(format!("Unknown tool stream: {}", call_id))
+                })?;
+            PENDING_CALLS.with(|pc| {
+                pc.borrow_mut().push(PendingToolCall {
+                    id: call_id.clone(),
+                    tool_name,
+                    server_id,
+                    arguments,
+                    stream: true,
+                })
+            });
+            Err(vm.new_exception_msg(
+                vm.ctx.exceptions.runtime_error.to_owned(),
+                format!("ToolCallPending:{}", call_id),
+            ))
+        }
+    }
+}
+
+/// Check keyword arguments against a tool's JSON Schema `parameters`.
+///
+/// Only names are checked: unknown keywords (unless the schema allows
+/// `additionalProperties`) and missing required ones are rejected with a
+/// Python-style message. Schemas without `properties` accept anything.
+pub fn validate_tool_arguments(
+    tool_name: &str,
+    schema: &Value,
+    arguments: &serde_json::Map<String, Value>,
+) -> Result<(), String> {
+    let Some(properties) = schema.get("properties").and_then(|p| p.as_object()) else {
+        return Ok(());
+    };
+
+    let allows_extra = match schema.get("additionalProperties") {
+        Some(Value::Bool(allowed)) => *allowed,
+        Some(Value::Object(_)) => true,
+        _ => false,
+    };
+    if !allows_extra {
+        if let Some(unknown) = arguments.keys().find(|k| !properties.contains_key(*k)) {
+            let mut accepted: Vec<&str> = properties.keys().map(|k| k.as_str()).collect();
+            accepted.sort_unstable();
+            return Err(format!(
+                "{}() got an unexpected keyword argument '{}' (accepted: {})",
+                tool_name,
+                unknown,
+                if accepted.is_empty() {
+                    "none".to_string()
+                } else {
+                    accepted.join(", ")
+                }
+            ));
+        }
+    }
+
+    let missing: Vec<&str> = schema
+        .get("required")
+        .and_then(|r| r.as_array())
+        .map(|required| {
+            required
+                .iter()
+                .filter_map(|name| name.as_str())
+                .filter(|name| !arguments.contains_key(*name))
+                .collect()
+        })
+        .unwrap_or_default();
+    if !missing.is_empty() {
+        return Err(format!(
+            "{}() missing required keyword argument(s): {}",
+            tool_name,
+            missing
+                .iter()
+                .map(|name| format!("'{}'", name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
 /// Get result of a previously made tool call
 fn get_tool_result_impl(args: FuncArgs, vm: &VirtualMachine) -> PyResult {
     let call_id: String = args
@@ -346,6 +639,21 @@ fn sandbox_stderr_impl(args: FuncArgs, vm: &VirtualMachine) -> PyResult<()> {
     Ok(())
 }
 
+/// Implementation of final_answer(answer): records `str(answer)` as the turn's
+/// answer (the last call wins). The code keeps running.
+fn final_answer_impl(args: FuncArgs, vm: &VirtualMachine) -> PyResult<()> {
+    let answer = match (args.args.as_slice(), args.kwargs.get("answer")) {
+        ([answer], None) | ([], Some(answer)) => answer.str(vm)?.to_string(),
+        _ => {
+            return Err(vm.new_type_error(
+                "final_answer() takes exactly one argument: the answer".to_string(),
+            ))
+        }
+    };
+    FINAL_ANSWER.with(|fa| *fa.borrow_mut() = Some(answer));
+    Ok(())
+}
+
 /// Convert FuncArgs kwargs to JSON Value
 fn funcargs_to_json(args: &FuncArgs, vm: &VirtualMachine) -> PyResult<Value> {
     let mut map = serde_json::Map::new();
@@ -421,6 +729,56 @@ pub fn pyobject_to_json(obj: &PyObjectRef, vm: &VirtualMachine) -> PyResult<Valu
     Ok(Value::String(s))
 }
 
+/// Collect globals that are plain data (None, bool, int, float, str, list,
+/// dict) so the host can re-inject them into the next execution of the same
+/// session. Private names and anything else (functions, modules, tool stubs,
+/// ...) are left out.
+pub fn collect_session_globals(
+    globals: &PyDictRef,
+    vm: &VirtualMachine,
+) -> serde_json::Map<String, Value> {
+    let mut captured = serde_json::Map::new();
+    for (key, value) in &**globals {
+        let Some(name) = key.downcast_ref::<PyStr>().map(|s| s.as_str().to_string()) else {
+            continue;
+        };
+        if name.starts_with('_') {
+            continue;
+        }
+        if !is_plain_data(&value, vm) {
+            continue;
+        }
+        if let Ok(json) = pyobject_to_json(&value, vm) {
+            captured.insert(name, json);
+        }
+    }
+    captured
+}
+
+/// Whether a value round-trips through JSON without losing its type
+fn is_plain_data(obj: &PyObjectRef, vm: &VirtualMachine) -> bool {
+    if vm.is_none(obj) {
+        return true;
+    }
+    let class = obj.class();
+    if class.is(vm.ctx.types.bool_type)
+        || class.is(vm.ctx.types.int_type)
+        || class.is(vm.ctx.types.float_type)
+        || class.is(vm.ctx.types.str_type)
+    {
+        return true;
+    }
+    if let Some(list) = obj.downcast_ref::<PyList>() {
+        return list.borrow_vec().iter().all(|item| is_plain_data(item, vm));
+    }
+    if let Some(dict) = obj.downcast_ref::<PyDict>() {
+        return dict
+            .into_iter()
+            .all(|(k, v)| k.downcast_ref::<PyStr>().is_some() && is_plain_data(&v, vm));
+    }
+    false
+}
+
 /// Convert a JSON Value to Python object.
 /// 
 /// Special handling for datetime objects:
@@ -535,6 +893,7 @@ pub const ALLOWED_MODULES: &[&str] = &[
     "random",
     "re",
     "datetime",
+    "data",
     "collections",
     "itertools",
     "functools",
@@ -603,28 +962,131 @@ pub const ALLOWED_MODULES: &[&str] = &[
     "contextlib",        // Commonly used with with statements
 ];
 
-/// Generate Python code that creates the _sandbox_allowed_modules set
-/// from the Rust ALLOWED_MODULES constant (single source of truth).
-/// 
-/// This ensures the Python runtime check uses the same list as the Rust constant,
-/// including all internal dependencies.
-fn generate_allowed_modules_python_set() -> String {
-    let mut modules: Vec<&str> = ALLOWED_MODULES.to_vec();
-    modules.push("_sandbox");
-    modules.push("builtins");
-    
-    let quoted: Vec<String> = modules.iter()
-        .map(|m| format!("'{}'", m))
-        .collect();
-    
-    format!("_sandbox_allowed_modules = {{{}}}", quoted.join(", "))
+/// Modules that can never be made importable, even when a request supplies
+/// its own allowed-module list.
+pub const NEVER_ALLOWED_MODULES: &[&str] = &["os", "sys", "subprocess", "socket"];
+
+/// One-line descriptions of the user-facing ALLOWED_MODULES (the internal
+/// dependencies are left out), shown in the settings UI.
+pub const MODULE_DESCRIPTIONS: &[(&str, &str)] = &[
+    ("math", "Mathematical functions and constants"),
+    ("json", "Encode and decode JSON"),
+    ("random", "Pseudo-random numbers and choices"),
+    ("re", "Regular expressions"),
+    ("datetime", "Dates, times and durations"),
+    ("data", "Sandbox helper module for chat data"),
+    ("collections", "Container types such as Counter, deque and defaultdict"),
+    ("itertools", "Iterator building blocks"),
+    ("functools", "Higher-order functions such as reduce and partial"),
+    ("operator", "Operators as functions"),
+    ("string", "String constants and templates"),
+    ("textwrap", "Wrap and indent text"),
+    ("copy", "Shallow and deep copies"),
+    ("types", "Names for built-in types"),
+    ("typing", "Type hints"),
+    ("abc", "Abstract base classes"),
+    ("numbers", "Numeric abstract base classes"),
+    ("decimal", "Exact decimal arithmetic"),
+    ("fractions", "Rational numbers"),
+    ("statistics", "Mean, median, stdev and other statistics"),
+    ("hashlib", "Secure hashes such as sha256 and md5"),
+    ("base64", "Base64 and related encodings"),
+    ("binascii", "Binary and ASCII conversions"),
+    ("html", "Escape and unescape HTML"),
+    ("contextlib", "Helpers for with statements"),
+];
+
+/// Description of an allowed module, if it is a documented user-facing one.
+pub fn module_description(name: &str) -> Option<&'static str> {
+    MODULE_DESCRIPTIONS
+        .iter()
+        .find(|(module, _)| *module == name)
+        .map(|(_, description)| *description)
+}
+
+/// Resolve the modules user code may import for a request.
+///
+/// `None` means the default ALLOWED_MODULES list. A requested list replaces it,
+/// minus anything in NEVER_ALLOWED_MODULES (or a submodule of one) and any name
+/// that is not a dotted Python identifier.
+pub fn resolve_allowed_modules(requested: Option<&[String]>) -> Vec<String> {
+    let Some(requested) = requested else {
+        return ALLOWED_MODULES.iter().map(|m| m.to_string()).collect();
+    };
+
+    let mut modules: Vec<String> = Vec::new();
+    for name in requested {
+        let name = name.trim();
+        let is_identifier = !name.is_empty()
+            && name.split('.').all(|part| {
+                part.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                    && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            });
+        let top_level = name.split('.').next().unwrap_or(name);
+        if is_identifier
+            && !NEVER_ALLOWED_MODULES.contains(&top_level)
+            && !modules.iter().any(|m| m == name)
+        {
+            modules.push(name.to_string());
+        }
+    }
+    modules
+}
+
+/// Generate Python code that creates the module sets used by the import hook.
+///
+/// `_sandbox_user_modules` holds what user code may import. `_sandbox_allowed_modules`
+/// additionally includes every ALLOWED_MODULES entry so stdlib modules can still
+/// load their internal dependencies when the user list is narrower.
+fn generate_allowed_modules_python_set(user_modules: &[String]) -> String {
+    let quote = |modules: &[&str]| -> String {
+        modules
+            .iter()
+            .map(|m| format!("'{}'", m))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut user: Vec<&str> = user_modules.iter().map(|m| m.as_str()).collect();
+    user.push("_sandbox");
+    user.push("builtins");
+
+    let mut allowed: Vec<&str> = ALLOWED_MODULES.to_vec();
+    for module in &user {
+        if !allowed.contains(module) {
+            allowed.push(module);
+        }
+    }
+
+    format!(
+        "_sandbox_allowed_modules = {{{}}}\n_sandbox_user_modules = {{{}}}",
+        quote(&allowed),
+        quote(&user)
+    )
 }
 
 /// Setup code PART 1: Everything before the allowed modules set
 /// This includes sandbox setup, dangerous builtin removal, and datetime shim
 const SANDBOX_SETUP_PART1: &str = r##"
 # Sandbox setup - import sandbox functions
-from _sandbox import tool_call, get_tool_result, sandbox_print, sandbox_stderr
+from _sandbox import tool_call, get_tool_result, sandbox_print, sandbox_stderr, final_answer
+from _sandbox import tool_stream_open, tool_stream_next
+
+def tool_call_stream(name, **kwargs):
+    """Call a tool and yield its result in chunks as they arrive.
+    Tools that don't stream yield their whole result once."""
+    call_id = tool_stream_open(name, **kwargs)
+    if call_id is None:
+        yield tool_call(name, **kwargs)
+        return
+    consumed = 0
+    while True:
+        chunks = tool_stream_next(call_id, consumed)
+        if chunks is None:
+            return
+        for chunk in chunks:
+            yield chunk
+        consumed += len(chunks)
 
 # Replace print with sandbox version  
 import builtins
@@ -869,6 +1331,90 @@ _datetime_instance.date = _DatetimeModule.date
 _datetime_instance.datetime = _DatetimeModule.datetime
 _datetime_instance.timedelta = _DatetimeModule.timedelta
 
+# ============== Data Helpers Shim ==============
+# CSV/JSON helpers for munging tool results, in pure Python (no I/O)
+
+import json as _data_json
+
+class _DataModule:
+    """Namespace class acting as data module"""
+
+    @staticmethod
+    def parse_csv(text, delimiter=','):
+        """Parse CSV text whose first row is the header into a list of dicts."""
+        records, row, field = [], [], []
+        in_quotes = False
+        i, n = 0, len(text)
+        while i < n:
+            c = text[i]
+            if in_quotes:
+                if c == '"' and i + 1 < n and text[i + 1] == '"':
+                    field.append('"')
+                    i += 1
+                elif c == '"':
+                    in_quotes = False
+                else:
+                    field.append(c)
+            elif c == '"':
+                in_quotes = True
+            elif c == delimiter:
+                row.append(''.join(field))
+                field = []
+            elif c == '\n' or c == '\r':
+                if c == '\r' and i + 1 < n and text[i + 1] == '\n':
+                    i += 1
+                row.append(''.join(field))
+                records.append(row)
+                row, field = [], []
+            else:
+                field.append(c)
+            i += 1
+        if in_quotes:
+            raise ValueError("CSV text ends inside a quoted field")
+        if field or row:
+            row.append(''.join(field))
+            records.append(row)
+        records = [r for r in records if r != ['']]
+        if not records:
+            return []
+        header = records[0]
+        return [dict(zip(header, r + [''] * (len(header) - len(r)))) for r in records[1:]]
+
+    @staticmethod
+    def _csv_field(value, delimiter):
+        text = '' if value is None else str(value)
+        if any(ch in text for ch in (delimiter, '"', '\n', '\r')):
+            return '"' + text.replace('"', '""') + '"'
+        return text
+
+    @staticmethod
+    def to_csv(rows, columns=None, delimiter=','):
+        """Format a list of dicts as CSV text with a header row."""
+        rows = list(rows)
+        if columns is None:
+            columns = []
+            for row in rows:
+                for key in row:
+                    if key not in columns:
+                        columns.append(key)
+        field = _DataModule._csv_field
+        lines = [delimiter.join(field(c, delimiter) for c in columns)]
+        for row in rows:
+            lines.append(delimiter.join(field(row.get(c), delimiter) for c in columns))
+        return '\n'.join(lines) + '\n'
+
+    @staticmethod
+    def to_json(value, indent=2):
+        """Pretty-print a value as JSON; non-JSON values are converted with str()."""
+        return _data_json.dumps(value, indent=indent, ensure_ascii=False, default=str)
+
+    @staticmethod
+    def from_json(text):
+        """Parse JSON text."""
+        return _data_json.loads(text)
+
+_data_instance = _DataModule()
+
 # NOTE: _sandbox_allowed_modules is inserted here dynamically by build_sandbox_setup_code()
 "##;
 
@@ -876,10 +1422,31 @@ _datetime_instance.timedelta = _DatetimeModule.timedelta
 const SANDBOX_SETUP_PART2: &str = r##"
 _original_import = builtins.__import__
 
+# User code runs in this module's globals, so imports it makes can be told
+# apart from imports made inside stdlib modules
+_sandbox_user_globals = globals()
+
+def _raise_import_not_allowed(name):
+    # Only show user-facing modules in error message (hide internal _ prefixed modules)
+    allowed_list = ', '.join(sorted(m for m in _sandbox_user_modules
+                                    if m not in ('_sandbox', 'builtins') and not m.startswith('_')))
+    raise ImportError(
+        f"Import '{name}' is not allowed in the sandbox. "
+        f"Allowed modules: {allowed_list}. "
+        f"For data analysis, use the built-in math, statistics, collections, and itertools modules."
+    )
+
 def _restricted_import(name, globals=None, locals=None, fromlist=(), level=0):
-    # Handle datetime specially - return our shim
+    # User code may only import the modules allowed for this request
+    if level == 0 and globals is _sandbox_user_globals:
+        if name.split('.')[0] not in _sandbox_user_modules:
+            _raise_import_not_allowed(name)
+
+    # Handle datetime and data specially - return our shims
     if name == 'datetime':
         return _datetime_instance
+    if name == 'data':
+        return _data_instance
     
     # For relative imports (level > 0), get the parent package from globals
     if level > 0 and globals:
@@ -893,14 +1460,7 @@ def _restricted_import(name, globals=None, locals=None, fromlist=(), level=0):
     
     top_level = name.split('.')[0]
     if top_level not in _sandbox_allowed_modules:
-        # Only show user-facing modules in error message (hide internal _ prefixed modules)
-        allowed_list = ', '.join(sorted(m for m in _sandbox_allowed_modules 
-                                        if m not in ('_sandbox', 'builtins') and not m.startswith('_')))
-        raise ImportError(
-            f"Import '{name}' is not allowed in the sandbox. "
-            f"Allowed modules: {allowed_list}. "
-            f"For data analysis, use the built-in math, statistics, collections, and itertools modules."
-        )
+        _raise_import_not_allowed(name)
     return _original_import(name, globals, locals, fromlist, level)
 
 builtins.__import__ = _restricted_import
@@ -914,10 +1474,16 @@ del _blocked, _name
 /// This generates the `_sandbox_allowed_modules` Python set from the Rust `ALLOWED_MODULES`
 /// constant, ensuring a single source of truth for which modules are allowed.
 pub fn build_sandbox_setup_code() -> String {
+    build_sandbox_setup_code_with_modules(&resolve_allowed_modules(None))
+}
+
+/// Build the sandbox setup code for a specific set of user-importable modules
+/// (see `resolve_allowed_modules`).
+pub fn build_sandbox_setup_code_with_modules(user_modules: &[String]) -> String {
     format!(
         "{}\n\n{}\n\n{}",
         SANDBOX_SETUP_PART1,
-        generate_allowed_modules_python_set(),
+        generate_allowed_modules_python_set(user_modules),
         SANDBOX_SETUP_PART2
     )
 }
@@ -968,6 +1534,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_resolve_allowed_modules() {
+        // No override keeps the default list
+        let defaults = resolve_allowed_modules(None);
+        assert_eq!(defaults.len(), ALLOWED_MODULES.len());
+
+        let requested: Vec<String> = [
+            "math",
+            " json ",
+            "math",
+            "os",
+            "os.path",
+            "sys",
+            "subprocess",
+            "socket",
+            "statistics'}; import os; {'",
+            "",
+        ]
+        .iter()
+        .map(|m| m.to_string())
+        .collect();
+        assert_eq!(resolve_allowed_modules(Some(&requested)), vec!["math", "json"]);
+    }
+
+    #[test]
+    fn test_module_descriptions_cover_allowed_modules() {
+        for (module, description) in MODULE_DESCRIPTIONS {
+            assert!(ALLOWED_MODULES.contains(module), "{} is not allowed", module);
+            assert!(!description.is_empty());
+        }
+        assert_eq!(module_description("json"), Some("Encode and decode JSON"));
+        assert_eq!(module_description("_json"), None);
+        assert_eq!(module_description("os"), None);
+    }
+
     #[test]
     fn test_reset_state() {
         PENDING_CALLS.with(|pc| {
@@ -976,6 +1577,7 @@ mod tests {
                 tool_name: "test".to_string(),
                 server_id: "test".to_string(),
                 arguments: Value::Null,
+                stream: false,
             })
         });
 
@@ -992,6 +1594,89 @@ mod tests {
         assert_eq!(get_stdout(), "Hello World\n");
     }
 
+    #[test]
+    fn test_stdout_capture_truncates_at_limit() {
+        reset_execution_state();
+        set_output_limit(Some(8));
+        append_stdout("12345");
+        append_stdout("6789");
+        append_stdout("more");
+        append_stderr("short");
+        set_output_limit(None);
+
+        assert_eq!(get_stdout(), format!("12345678{}", OUTPUT_TRUNCATED_MARKER));
+        assert!(stdout_truncated());
+        assert_eq!(get_stderr(), "short");
+        assert!(!stderr_truncated());
+
+        reset_execution_state();
+        assert!(!stdout_truncated());
+    }
+
+    #[test]
+    fn test_validate_tool_arguments() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {"query": {"type": "string"}, "limit": {"type": "integer"}},
+            "required": ["query"]
+        });
+        let args = |v: Value| v.as_object().cloned().unwrap();
+
+        assert!(validate_tool_arguments(
+            "search",
+            &schema,
+            &args(serde_json::json!({"query": "x"}))
+        )
+        .is_ok());
+        assert!(validate_tool_arguments(
+            "search",
+            &schema,
+            &args(serde_json::json!({"query": "x", "limit": 3}))
+        )
+        .is_ok());
+
+        let err = validate_tool_arguments(
+            "search",
+            &schema,
+            &args(serde_json::json!({"query": "x", "page": 2})),
+        )
+        .unwrap_err();
+        assert!(err.contains("unexpected keyword argument 'page'"));
+
+        let err =
+            validate_tool_arguments("search", &schema, &args(serde_json::json!({"limit": 3})))
+                .unwrap_err();
+        assert!(err.contains("missing required keyword argument(s): 'query'"));
+
+        // No declared properties, or extra properties allowed: anything goes
+        let open = serde_json::json!({});
+        assert!(
+            validate_tool_arguments("search", &open, &args(serde_json::json!({"page": 2}))).is_ok()
+        );
+        let extra = serde_json::json!({"properties": {"query": {}}, "additionalProperties": true});
+        assert!(
+            validate_tool_arguments("search", &extra, &args(serde_json::json!({"page": 2})))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_stdout_listener_sees_chunks() {
+        use std::rc::Rc;
+        reset_execution_state();
+        let chunks = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&chunks);
+        set_stdout_listener(Some(Box::new(move |s| {
+            sink.borrow_mut().push(s.to_string())
+        })));
+        append_stdout("one\n");
+        append_stdout("two\n");
+        set_stdout_listener(None);
+        append_stdout("three\n");
+        assert_eq!(*chunks.borrow(), vec!["one\n", "two\n"]);
+        assert_eq!(get_stdout(), "one\ntwo\nthree\n");
+    }
+
     #[test]
     fn test_stderr_capture() {
         reset_execution_state();
@@ -0,0 +1,229 @@
+//! Wall-clock, memory and cancellation watchdog for sandboxed execution
+//!
+//! RustPython checks its user signal channel before every bytecode
+//! instruction. The watchdog runs on a helper thread and, once the deadline
+//! passes, the memory budget is exceeded or the host cancels the run, sends a
+//! signal that raises `TimeoutError` / `MemoryError` / `KeyboardInterrupt`
+//! inside the VM. The signal re-queues itself so
+//! user code cannot swallow it with `except:`, and the helper thread keeps
+//! re-sending it in case another VM on a different thread consumed the
+//! process-wide "signal pending" flag first.
+
+use crate::memory::{MemoryBudget, MEMORY_LIMIT_MESSAGE};
+use rustpython_vm::signal::{
+    user_signal_channel, UserSignal, UserSignalReceiver, UserSignalSender,
+};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often the interrupt signal is re-sent after it first fires
+const RETRIGGER_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often the memory budget is checked while code runs
+const MEMORY_POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/// How often the cancel token is checked while code runs
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Error message reported when the host cancels an execution
+pub const CANCELLED_MESSAGE: &str = "Execution cancelled";
+
+thread_local! {
+    /// Cancel token for the execution running on this thread, if any
+    static CANCEL_TOKEN: RefCell<Option<CancelToken>> = const { RefCell::new(None) };
+}
+
+/// Shared flag the host trips to stop a running execution from another thread
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the execution holding this token to stop at its next instruction
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Install (or clear) the cancel token for executions on the current thread
+pub fn set_cancel_token(token: Option<CancelToken>) {
+    CANCEL_TOKEN.with(|slot| *slot.borrow_mut() = token);
+}
+
+/// Cancel token installed for the current thread, if any
+pub fn current_cancel_token() -> Option<CancelToken> {
+    CANCEL_TOKEN.with(|slot| slot.borrow().clone())
+}
+
+/// Build the error message reported when execution exceeds its time budget
+pub fn timeout_message(timeout_ms: u64) -> String {
+    format!("Execution timed out after {} ms", timeout_ms)
+}
+
+/// Why the watchdog interrupted an execution
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interruption {
+    /// The wall-clock deadline passed
+    TimedOut,
+    /// Live allocations passed the memory ceiling
+    MemoryLimitExceeded,
+    /// The host cancelled the execution
+    Cancelled,
+}
+
+/// Watches a single execution and interrupts it when a limit is hit
+pub struct ExecutionWatchdog {
+    done_tx: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+    interruption: Arc<Mutex<Option<Interruption>>>,
+}
+
+impl ExecutionWatchdog {
+    /// Create the signal channel for a watched execution. The receiver must be
+    /// installed on the interpreter (see `create_sandboxed_interpreter_with_signals`).
+    pub fn channel() -> (UserSignalSender, UserSignalReceiver) {
+        user_signal_channel()
+    }
+
+    /// Start watching the deadline, memory budget and cancel token (each
+    /// optional), interrupting through `signal_tx`
+    pub fn start(
+        timeout_ms: Option<u64>,
+        memory: Option<Arc<MemoryBudget>>,
+        cancel: Option<CancelToken>,
+        signal_tx: UserSignalSender,
+    ) -> Self {
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+        let interruption = Arc::new(Mutex::new(None));
+        let slot = Arc::clone(&interruption);
+
+        let handle = std::thread::spawn(move || {
+            let deadline = timeout_ms.map(|ms| Instant::now() + Duration::from_millis(ms));
+            let reason = loop {
+                let until_deadline = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+                let poll = if memory.is_some() {
+                    Some(MEMORY_POLL_INTERVAL)
+                } else if cancel.is_some() {
+                    Some(CANCEL_POLL_INTERVAL)
+                } else {
+                    None
+                };
+                let wait = match (poll, until_deadline) {
+                    (Some(poll), Some(remaining)) => remaining.min(poll),
+                    (Some(poll), None) => poll,
+                    (None, Some(remaining)) => remaining,
+                    (None, None) => {
+                        // Nothing to watch: wait for the execution to finish
+                        let _ = done_rx.recv();
+                        return;
+                    }
+                };
+                match done_rx.recv_timeout(wait) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    // Finished (or watchdog dropped) before any limit was hit
+                    _ => return,
+                }
+
+                if cancel.as_ref().is_some_and(|c| c.is_cancelled()) {
+                    break Interruption::Cancelled;
+                }
+                if memory.as_ref().is_some_and(|m| m.exceeded()) {
+                    break Interruption::MemoryLimitExceeded;
+                }
+                if deadline.is_some_and(|d| Instant::now() >= d) {
+                    break Interruption::TimedOut;
+                }
+            };
+
+            if let Ok(mut slot) = slot.lock() {
+                *slot = Some(reason);
+            }
+            let message = match reason {
+                Interruption::TimedOut => timeout_message(timeout_ms.unwrap_or_default()),
+                Interruption::MemoryLimitExceeded => MEMORY_LIMIT_MESSAGE.to_string(),
+                Interruption::Cancelled => CANCELLED_MESSAGE.to_string(),
+            };
+            loop {
+                if !send_interrupt_signal(&signal_tx, reason, &message) {
+                    return;
+                }
+                match done_rx.recv_timeout(RETRIGGER_INTERVAL) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => return,
+                }
+            }
+        });
+
+        Self {
+            done_tx: Some(done_tx),
+            handle: Some(handle),
+            interruption,
+        }
+    }
+
+    /// Stop the watchdog and report which limit (if any) interrupted execution
+    pub fn finish(mut self) -> Option<Interruption> {
+        self.stop();
+        self.interruption.lock().ok().and_then(|slot| *slot)
+    }
+
+    fn stop(&mut self) {
+        if let Some(tx) = self.done_tx.take() {
+            let _ = tx.send(());
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for ExecutionWatchdog {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Queue the interrupt on the VM. Returns false once the VM is gone.
+fn send_interrupt_signal(
+    signal_tx: &UserSignalSender,
+    reason: Interruption,
+    message: &str,
+) -> bool {
+    signal_tx
+        .send(interrupt_signal(
+            signal_tx.clone(),
+            reason,
+            message.to_string(),
+        ))
+        .is_ok()
+}
+
+/// Build a signal that raises the interrupt's exception and re-queues itself, so
+/// the very next instruction (including the first one of any `except` handler)
+/// raises again.
+fn interrupt_signal(
+    signal_tx: UserSignalSender,
+    reason: Interruption,
+    message: String,
+) -> UserSignal {
+    Box::new(move |vm| {
+        let _ = signal_tx.send(interrupt_signal(signal_tx.clone(), reason, message.clone()));
+        let exc_type = match reason {
+            Interruption::TimedOut => vm.ctx.exceptions.timeout_error,
+            Interruption::MemoryLimitExceeded => vm.ctx.exceptions.memory_error,
+            Interruption::Cancelled => vm.ctx.exceptions.keyboard_interrupt,
+        };
+        Err(vm.new_exception_msg(exc_type.to_owned(), message.clone()))
+    })
+}
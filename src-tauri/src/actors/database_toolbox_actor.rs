@@ -15,6 +15,7 @@ use crate::settings::{
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot, RwLock};
 
@@ -34,6 +35,11 @@ pub enum DatabaseToolboxMsg {
     GetStatus {
         reply_to: oneshot::Sender<ToolboxStatus>,
     },
+    /// Get the connection status of one source
+    GetSourceStatus {
+        source_id: String,
+        reply_to: oneshot::Sender<SourceConnectionStatus>,
+    },
     /// Enumerate datasets/schemas for a source
     EnumerateSchemas {
         source_id: String,
@@ -60,7 +66,7 @@ pub enum DatabaseToolboxMsg {
     },
     /// Test connection to a source
     TestConnection {
-        source: DatabaseSourceConfig,
+        source: Box<DatabaseSourceConfig>,
         reply_to: oneshot::Sender<Result<(), String>>,
     },
 }
@@ -70,6 +76,9 @@ pub enum DatabaseToolboxMsg {
 pub struct ToolboxStatus {
     pub running: bool,
     pub connected_sources: Vec<String>,
+    /// Connection error per enabled source that failed to connect (source_id -> error)
+    #[serde(default)]
+    pub failed_sources: HashMap<String, String>,
     pub error: Option<String>,
 }
 
@@ -78,11 +87,69 @@ impl Default for ToolboxStatus {
         Self {
             running: false,
             connected_sources: Vec::new(),
+            failed_sources: HashMap::new(),
             error: None,
         }
     }
 }
 
+impl ToolboxStatus {
+    /// Status after syncing `enabled_sources`, given the ones that failed to connect.
+    /// Healthy sources stay usable when others fail.
+    pub fn after_sync(enabled_sources: &[String], failed_sources: HashMap<String, String>) -> Self {
+        let connected_sources: Vec<String> = enabled_sources
+            .iter()
+            .filter(|id| !failed_sources.contains_key(*id))
+            .cloned()
+            .collect();
+        let error = (!failed_sources.is_empty()).then(|| {
+            let mut ids: Vec<&String> = failed_sources.keys().collect();
+            ids.sort();
+            format!(
+                "Failed to connect database MCP servers: {}",
+                ids.iter()
+                    .map(|id| failed_sources[*id].as_str())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            )
+        });
+        Self {
+            running: true,
+            connected_sources,
+            failed_sources,
+            error,
+        }
+    }
+
+    /// Connection status of one source
+    pub fn source_status(&self, source_id: &str) -> SourceConnectionStatus {
+        let connected = self.running && self.connected_sources.iter().any(|id| id == source_id);
+        let error = if connected {
+            None
+        } else {
+            Some(
+                self.failed_sources
+                    .get(source_id)
+                    .cloned()
+                    .unwrap_or_else(|| "Source is not connected".to_string()),
+            )
+        };
+        SourceConnectionStatus {
+            source_id: source_id.to_string(),
+            connected,
+            error,
+        }
+    }
+}
+
+/// Connection status of a single database source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceConnectionStatus {
+    pub source_id: String,
+    pub connected: bool,
+    pub error: Option<String>,
+}
+
 /// Result from SQL execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SqlExecutionResult {
@@ -154,6 +221,13 @@ impl DatabaseToolboxActor {
                     let status = self.state.read().await.status.clone();
                     let _ = reply_to.send(status);
                 }
+                DatabaseToolboxMsg::GetSourceStatus {
+                    source_id,
+                    reply_to,
+                } => {
+                    let status = self.state.read().await.status.source_status(&source_id);
+                    let _ = reply_to.send(status);
+                }
                 DatabaseToolboxMsg::EnumerateSchemas {
                     source_id,
                     reply_to,
@@ -244,8 +318,9 @@ impl DatabaseToolboxActor {
             .await
             .map_err(|_| "MCP host unavailable while syncing database servers".to_string())?;
 
-        // Surface any connection errors immediately so the caller can show them
-        let failed: Vec<String> = results
+        // Record connection errors per source, so one broken source doesn't take the
+        // healthy ones down with it
+        let failed: HashMap<String, String> = results
             .into_iter()
             .filter_map(|(id, res)| match res {
                 Ok(_) => None,
@@ -254,31 +329,38 @@ impl DatabaseToolboxActor {
                         .get(&id)
                         .cloned()
                         .unwrap_or_else(|| id.clone());
-                    Some(format!("{} ({}): {}", label, id, err))
+                    let message = format!("{} ({}): {}", label, id, err);
+                    Some((id, message))
                 }
             })
             .collect();
-        if !failed.is_empty() {
-            return Err(format!(
-                "Failed to connect database MCP servers: {}",
-                failed.join("; ")
-            ));
+        let enabled_sources: Vec<String> = config
+            .sources
+            .iter()
+            .filter(|s| s.enabled)
+            .map(|s| s.id.clone())
+            .collect();
+        let status = ToolboxStatus::after_sync(&enabled_sources, failed);
+
+        // Nothing usable: report the failure without replacing the previous state
+        if !enabled_sources.is_empty() && status.connected_sources.is_empty() {
+            return Err(status.error.unwrap_or_default());
+        }
+
+        if let Some(error) = &status.error {
+            println!(
+                "[DatabaseToolboxActor] {} of {} sources connected; {}",
+                status.connected_sources.len(),
+                enabled_sources.len(),
+                error
+            );
         }
 
         // Update state
         {
             let mut state = self.state.write().await;
             state.config = Some(config.clone());
-            state.status = ToolboxStatus {
-                running: true,
-                connected_sources: config
-                    .sources
-                    .iter()
-                    .filter(|s| s.enabled)
-                    .map(|s| s.id.clone())
-                    .collect(),
-                error: None,
-            };
+            state.status = status;
         }
 
         println!("[DatabaseToolboxActor] Database MCP servers synced");
@@ -323,6 +405,11 @@ impl DatabaseToolboxActor {
             defer_tools: source.defer_tools,
             python_name: None,
             is_database_source: true,
+            tool_timeout_secs: None,
+            auto_approve_tool_overrides: Default::default(),
+            validate_tool_arguments: true,
+            auto_reconnect: false,
+            roots: Vec::new(),
         }
     }
 
@@ -338,6 +425,7 @@ impl DatabaseToolboxActor {
                 server_id: server_id.to_string(),
                 tool_name: tool_name.to_string(),
                 arguments: params,
+                timeout: Some(crate::actors::mcp_host_actor::REQUEST_TIMEOUT),
                 respond_to: tx,
             })
             .await
@@ -1560,6 +1648,32 @@ mod tests {
         assert!(status.error.is_none());
     }
 
+    #[test]
+    fn test_toolbox_status_isolates_failed_sources() {
+        let enabled = vec!["healthy".to_string(), "broken".to_string()];
+        let failed = HashMap::from([(
+            "broken".to_string(),
+            "Broken DB (broken): connection refused".to_string(),
+        )]);
+        let status = ToolboxStatus::after_sync(&enabled, failed);
+
+        assert!(status.running);
+        assert_eq!(status.connected_sources, vec!["healthy".to_string()]);
+        assert!(status.error.as_deref().unwrap().contains("connection refused"));
+
+        let healthy = status.source_status("healthy");
+        assert!(healthy.connected);
+        assert!(healthy.error.is_none());
+
+        let broken = status.source_status("broken");
+        assert!(!broken.connected);
+        assert!(broken.error.unwrap().contains("connection refused"));
+
+        let unknown = ToolboxStatus::default().source_status("healthy");
+        assert!(!unknown.connected);
+        assert_eq!(unknown.error.as_deref(), Some("Source is not connected"));
+    }
+
     #[test]
     fn test_sql_execution_result_serde() {
         let result = SqlExecutionResult {
@@ -14,6 +14,9 @@ mod stream_handler;
 pub use model_gateway_actor::ModelGatewayActor;
 
 // Re-export commonly used items from submodules for internal use
-pub use request_builder::{build_foundry_chat_request_body, convert_chat_messages_to_foundry_format};
+pub use request_builder::{
+    apply_sampling_overrides, apply_stop_sequences, build_foundry_chat_request_body,
+    convert_chat_messages_to_completions_format, convert_chat_messages_to_foundry_format,
+};
 pub use service_manager::{find_foundry_binary, parse_foundry_service_status_output, ServiceStatus, FoundryModel, FoundryModelsResponse, DEFAULT_FALLBACK_MODEL};
 pub use stream_handler::{StreamingToolCalls, extract_text_from_stream_chunk};
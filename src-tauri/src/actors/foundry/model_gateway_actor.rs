@@ -12,10 +12,10 @@ use crate::crash_handler::SuppressCrashDialogGuard;
 use crate::is_verbose_logging_enabled;
 use crate::process_utils::HideConsoleWindow;
 use crate::protocol::{
-    CachedModel, CatalogModel, FoundryMsg, FoundryServiceStatus, ModelFamily,
+    CachedModel, CatalogModel, ChatRequest, FoundryMsg, FoundryServiceStatus, ModelFamily,
     ModelInfo, ModelState, ReasoningFormat, ResourceStatus, ToolFormat,
 };
-use crate::app_state::{GpuResourceGuard, LoggingPersistence, SettingsState};
+use crate::app_state::{EmbeddingModelState, GpuResourceGuard, LoggingPersistence, SettingsState};
 use crate::settings;
 use crate::settings::ChatFormatName;
 use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
@@ -40,12 +40,15 @@ use tokio::sync::{mpsc, RwLock};
 use tokio::time::{sleep, timeout};
 
 // Import from sibling modules in the foundry package
-use super::request_builder::build_foundry_chat_request_body;
+use super::request_builder::{
+    apply_anthropic_format, apply_sampling_overrides, apply_stop_sequences,
+    build_foundry_chat_request_body, chat_endpoint,
+};
 use super::service_manager::{
     find_foundry_binary, parse_foundry_service_status_output, 
     FoundryModel, FoundryModelsResponse, ServiceStatus, DEFAULT_FALLBACK_MODEL,
 };
-use super::stream_handler::{extract_text_from_stream_chunk, StreamingToolCalls};
+use super::stream_handler::ChatStreamDecoder;
 
 /// Target embedding dimension (must match LanceDB schema)
 const EMBEDDING_DIM: usize = 768;
@@ -567,6 +570,7 @@ impl ModelGatewayActor {
                 }
                 Ok(Ok(Err(e))) => {
                     println!("FoundryActor ERROR: ❌ Failed to load CPU embedding model: {:?}", e);
+                    record_embedding_load_error(&app_handle_clone, e.to_string()).await;
                     println!("FoundryActor: CPU embedding model load error details - check if the model file exists and is accessible");
                     let _ = app_handle_clone.emit("embedding-init-progress", json!({
                         "message": format!("Failed to load CPU embedding model: {}", e),
@@ -586,6 +590,11 @@ impl ModelGatewayActor {
                     println!("FoundryActor ERROR: ❌ ONNX Runtime initialization panicked: {}", panic_msg);
                     println!("FoundryActor: This usually means onnxruntime.dll is missing on Windows.");
                     println!("FoundryActor: Embedding/search features will be unavailable.");
+                    record_embedding_load_error(
+                        &app_handle_clone,
+                        format!("ONNX Runtime unavailable: {}", panic_msg),
+                    )
+                    .await;
                     let _ = app_handle_clone.emit("embedding-init-progress", json!({
                         "message": format!("ONNX Runtime unavailable: {}. Embedding features disabled.", panic_msg),
                         "is_complete": true,
@@ -595,6 +604,8 @@ impl ModelGatewayActor {
                 Err(e) => {
                     println!("FoundryActor ERROR: ❌ CPU embedding model init task failed: {:?}", e);
                     println!("FoundryActor: This may indicate an out-of-memory condition or incompatible hardware");
+                    let error = format!("init task failed: {}", e);
+                    record_embedding_load_error(&app_handle_clone, error).await;
                     let _ = app_handle_clone.emit("embedding-init-progress", json!({
                         "message": "CPU embedding model initialization task failed",
                         "is_complete": true,
@@ -804,17 +815,23 @@ impl ModelGatewayActor {
                     
                     let _ = respond_to.send(true);
                 }
-                FoundryMsg::Chat {
-                    model: requested_model,
-                    chat_history_messages,
-                    reasoning_effort,
-                    native_tool_specs,
-                    native_tool_calling_enabled,
-                    chat_format_default,
-                    chat_format_overrides,
-                    respond_to,
-                    mut stream_cancel_rx,
-                } => {
+                FoundryMsg::Chat(request) => {
+                    let ChatRequest {
+                        model: requested_model,
+                        chat_history_messages,
+                        reasoning_effort,
+                        native_tool_specs,
+                        native_tool_calling_enabled,
+                        chat_format_default,
+                        chat_format_overrides,
+                        respond_to,
+                        mut stream_cancel_rx,
+                        response_schema,
+                        temperature,
+                        seed,
+                        stop_sequences,
+                    } = *request;
+
                     // Clone GPU guard to avoid borrow conflicts with self
                     let gpu_guard = self.gpu_guard.clone();
                     
@@ -889,10 +906,11 @@ impl ModelGatewayActor {
                             );
                         }
 
-                        let desired_chat_format = chat_format_overrides
-                            .get(&model)
-                            .copied()
-                            .unwrap_or(chat_format_default);
+                        let desired_chat_format = settings::resolve_chat_format(
+                            &chat_format_overrides,
+                            chat_format_default,
+                            &model,
+                        );
                         let supports_responses = Self::model_supports_responses(&model);
                         let effective_chat_format = if desired_chat_format == ChatFormatName::OpenaiResponses
                             && !supports_responses
@@ -907,12 +925,11 @@ impl ModelGatewayActor {
                         };
                         let use_responses_api =
                             matches!(effective_chat_format, ChatFormatName::OpenaiResponses);
+                        // Backends that speak the Anthropic messages format take content blocks
+                        let uses_anthropic_format =
+                            matches!(effective_chat_format, ChatFormatName::AnthropicMessages);
 
-                        let url = if use_responses_api {
-                            format!("http://127.0.0.1:{}/v1/responses", port)
-                        } else {
-                            format!("http://127.0.0.1:{}/v1/chat/completions", port)
-                        };
+                        let url = chat_endpoint(port, effective_chat_format);
                         let verbose_logging = is_verbose_logging_enabled();
 
                         // Log incoming messages for debugging
@@ -985,9 +1002,7 @@ impl ModelGatewayActor {
                                 crate::protocol::ChatMessage {
                                     role: "system".to_string(),
                                     content: "You are a helpful AI assistant.".to_string(),
-                                    system_prompt: None,
-                                    tool_calls: None,
-                                    tool_call_id: None,
+                                    ..Default::default()
                                 },
                             );
                         } else {
@@ -1025,8 +1040,23 @@ impl ModelGatewayActor {
                             .map(|m| m.family)
                             .unwrap_or(ModelFamily::Generic);
 
+                        // Per-turn sampling overrides only apply to models that take a temperature
+                        let supports_temperature =
+                            model_info.as_ref().is_some_and(|m| m.supports_temperature);
+                        let (temperature, seed) = if supports_temperature {
+                            (temperature, seed)
+                        } else {
+                            if temperature.is_some() || seed.is_some() {
+                                println!(
+                                    "[FoundryActor] WARNING: Model {} does not support temperature; ignoring temperature/seed overrides",
+                                    model
+                                );
+                            }
+                            (None, None)
+                        };
+
                         // Only use native tools if model supports them, tools were provided, and native tool calling is enabled.
-                        let use_native_tools = model_supports_tools
+                        let use_native_tools = (model_supports_tools || uses_anthropic_format)
                             && native_tool_calling_enabled
                             && native_tool_specs
                                 .as_ref()
@@ -1109,18 +1139,14 @@ impl ModelGatewayActor {
                         for attempt in 1..=MAX_RETRIES {
                             // Rebuild URL in case port changed after restart
                             let current_url = if let Some(p) = self.port {
-                                if use_responses_api {
-                                    format!("http://127.0.0.1:{}/v1/responses", p)
-                                } else {
-                                    format!("http://127.0.0.1:{}/v1/chat/completions", p)
-                                }
+                                chat_endpoint(p, effective_chat_format)
                             } else {
                                 url.clone()
                             };
 
                             // Rebuild body in case anything changed after restart
                             let body_build_start = std::time::Instant::now();
-                            let current_body = build_foundry_chat_request_body(
+                            let mut current_body = build_foundry_chat_request_body(
                                 &model,
                                 model_family,
                                 &messages,
@@ -1130,7 +1156,26 @@ impl ModelGatewayActor {
                                 supports_reasoning_effort,
                                 &reasoning_effort,
                                 use_responses_api,
+                                response_schema.as_ref(),
                             );
+                            apply_sampling_overrides(&mut current_body, temperature, seed);
+                            // Anthropic-format backends take content blocks instead of tool_calls
+                            if uses_anthropic_format {
+                                apply_anthropic_format(
+                                    &mut current_body,
+                                    model_family,
+                                    &messages,
+                                    &native_tool_specs,
+                                );
+                            }
+                            // The responses API has no stop parameter
+                            if !use_responses_api {
+                                apply_stop_sequences(
+                                    &mut current_body,
+                                    &stop_sequences,
+                                    uses_anthropic_format,
+                                );
+                            }
                             let body_build_elapsed = body_build_start.elapsed();
 
                             // Note: Request body logging moved to log_with_diff for system prompt and tools JSON
@@ -1218,9 +1263,8 @@ impl ModelGatewayActor {
                                         break;
                                     } else {
                                         // Success - stream the response
-                                        let mut buffer = String::new();
-                                        let mut streaming_tool_calls =
-                                            StreamingToolCalls::default();
+                                        let mut decoder =
+                                            ChatStreamDecoder::new(effective_chat_format);
                                         let stream_start = std::time::Instant::now();
                                         let mut token_count: usize = 0;
                                         let mut last_token_time = stream_start;
@@ -1231,9 +1275,10 @@ impl ModelGatewayActor {
                                         );
                                         let _ = std::io::stdout().flush();
 
-                                        // Note: Tool calls can arrive in two formats:
+                                        // Note: Tool calls can arrive in three formats:
                                         // 1. Text-based: in content field as <tool_call>JSON</tool_call>
-                                        // 2. Native OpenAI: in delta.tool_calls array (accumulated here)
+                                        // 2. Native OpenAI: in delta.tool_calls array (accumulated by the decoder)
+                                        // 3. Anthropic: tool_use content blocks (accumulated by the decoder)
 
                                         let mut stream_cancelled = false;
                                         'stream_loop: loop {
@@ -1257,58 +1302,35 @@ impl ModelGatewayActor {
                                                     match chunk_result {
                                                         Ok(Some(chunk)) => {
                                                             if let Ok(s) = String::from_utf8(chunk.to_vec()) {
-                                                                buffer.push_str(&s);
-
-                                                                // Process lines
-                                                                while let Some(idx) = buffer.find('\n') {
-                                                                    let line = buffer[..idx].to_string();
-                                                                    buffer = buffer[idx + 1..].to_string();
-
-                                                                    let trimmed = line.trim();
-                                                                    if trimmed.starts_with("data: ") {
-                                                                        let data = &trimmed["data: ".len()..];
-                                                                        if data == "[DONE]" {
-                                                                            let elapsed = stream_start.elapsed();
-                                                                            println!("[FoundryActor] ✅ Stream DONE. {} tokens in {:.2}s ({:.1} tok/s)",
-                                                                                token_count,
-                                                                                elapsed.as_secs_f64(),
-                                                                                token_count as f64 / elapsed.as_secs_f64());
-                                                                            let _ = std::io::stdout().flush();
-                                                                            break 'stream_loop;
-                                                                        }
-                                                                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(data) {
-                                                                        if let Some(content) =
-                                                                            extract_text_from_stream_chunk(&json, use_responses_api)
-                                                                        {
-                                                                            if !content.is_empty() {
-                                                                                token_count += 1;
-                                                                                last_token_time = std::time::Instant::now();
-
-                                                                                let _ = respond_to_clone.send(content);
-
-                                                                                // Log progress every 5 seconds (verbose only)
-                                                                                if verbose_logging
-                                                                                    && last_progress_log.elapsed()
-                                                                                        >= Duration::from_secs(5)
-                                                                                {
-                                                                                    let elapsed = stream_start.elapsed();
-                                                                                    println!("[FoundryActor] 📊 Streaming: {} tokens so far ({:.2}s elapsed, {:.1} tok/s)",
-                                                                                        token_count,
-                                                                                        elapsed.as_secs_f64(),
-                                                                                        token_count as f64 / elapsed.as_secs_f64());
-                                                                                    let _ = std::io::stdout().flush();
-                                                                                    last_progress_log = std::time::Instant::now();
-                                                                                }
-                                                                            }
-                                                                        }
-
-                                                                            // Accumulate native OpenAI tool calls (delta.tool_calls)
-                                                                            if let Some(tool_calls) = json["choices"][0]["delta"]["tool_calls"].as_array() {
-                                                                                streaming_tool_calls.process_streaming_tool_call_delta(tool_calls);
-                                                                            }
-                                                                        }
+                                                                for content in decoder.push_chunk(&s) {
+                                                                    token_count += 1;
+                                                                    last_token_time = std::time::Instant::now();
+
+                                                                    let _ = respond_to_clone.send(content);
+
+                                                                    // Log progress every 5 seconds (verbose only)
+                                                                    if verbose_logging
+                                                                        && last_progress_log.elapsed()
+                                                                            >= Duration::from_secs(5)
+                                                                    {
+                                                                        let elapsed = stream_start.elapsed();
+                                                                        println!("[FoundryActor] 📊 Streaming: {} tokens so far ({:.2}s elapsed, {:.1} tok/s)",
+                                                                            token_count,
+                                                                            elapsed.as_secs_f64(),
+                                                                            token_count as f64 / elapsed.as_secs_f64());
+                                                                        let _ = std::io::stdout().flush();
+                                                                        last_progress_log = std::time::Instant::now();
                                                                     }
                                                                 }
+                                                                if decoder.is_done() {
+                                                                    let elapsed = stream_start.elapsed();
+                                                                    println!("[FoundryActor] ✅ Stream DONE. {} tokens in {:.2}s ({:.1} tok/s)",
+                                                                        token_count,
+                                                                        elapsed.as_secs_f64(),
+                                                                        token_count as f64 / elapsed.as_secs_f64());
+                                                                    let _ = std::io::stdout().flush();
+                                                                    break 'stream_loop;
+                                                                }
                                                             }
                                                         }
                                                         Ok(None) => {
@@ -1351,19 +1373,8 @@ impl ModelGatewayActor {
 
                                         // After stream ends, emit any accumulated native tool calls as text
                                         // so the existing agentic loop parser can detect them
-                                        if !streaming_tool_calls.is_empty() {
-                                            let native_calls =
-                                                streaming_tool_calls.into_parsed_calls();
-                                            println!("[FoundryActor] Emitting {} native tool calls as text", native_calls.len());
-                                            for call in &native_calls {
-                                                // Emit in <tool_call> format for parser compatibility
-                                                let tool_call_text = format!(
-                                                    "\n<tool_call>{{\"name\": \"{}\", \"arguments\": {}}}</tool_call>\n",
-                                                    call.tool,
-                                                    serde_json::to_string(&call.arguments).unwrap_or_else(|_| "{}".to_string())
-                                                );
-                                                let _ = respond_to_clone.send(tool_call_text);
-                                            }
+                                        for tool_call_text in decoder.finish() {
+                                            let _ = respond_to_clone.send(tool_call_text);
                                         }
 
                                         println!("Foundry stream loop finished.");
@@ -1761,7 +1772,7 @@ impl ModelGatewayActor {
                     } else {
                         match family {
                             // Qwen, Mistral, LLaMA use Hermes-style <tool_call> format
-                            ModelFamily::GptOss => ToolFormat::Hermes,
+                            ModelFamily::GptOss | ModelFamily::Qwen => ToolFormat::Hermes,
                             ModelFamily::Gemma => ToolFormat::Gemini,
                             ModelFamily::Phi => ToolFormat::Hermes,
                             ModelFamily::Granite => ToolFormat::Granite,
@@ -1778,7 +1789,7 @@ impl ModelGatewayActor {
                     } else {
                         match family {
                             ModelFamily::GptOss => ReasoningFormat::ChannelBased,
-                            ModelFamily::Phi => ReasoningFormat::ThinkTags,
+                            ModelFamily::Phi | ModelFamily::Qwen => ReasoningFormat::ThinkTags,
                             ModelFamily::Granite => ReasoningFormat::ThinkingTags,
                             _ => ReasoningFormat::None,
                         }
@@ -2137,9 +2148,7 @@ impl ModelGatewayActor {
             }
             _ => {
                 // Content unchanged, skip logging unless verbose
-                if crate::is_verbose_logging_enabled() {
-                    println!("[{}] Content unchanged ({} chars)", label, current_content.len());
-                }
+                crate::log_event!(label, "content_unchanged", chars = current_content.len());
             }
         }
     }
@@ -3036,3 +3045,11 @@ impl ModelGatewayActor {
         }
     }
 }
+
+/// Remember why the CPU embedding model failed to load, so chat can leave out
+/// the search features that need it and the UI can report the reason
+async fn record_embedding_load_error(app_handle: &AppHandle, error: String) {
+    if let Some(state) = app_handle.try_state::<EmbeddingModelState>() {
+        *state.cpu_load_error.write().await = Some(error);
+    }
+}
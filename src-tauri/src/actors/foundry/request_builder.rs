@@ -3,9 +3,23 @@
 //! This module handles:
 //! - Building model-family-specific chat request bodies
 //! - Converting chat messages to Responses API format
+//! - Rewriting bodies for backends that speak the Anthropic messages format
 
 use serde_json::{json, Value};
-use crate::protocol::{ChatMessage, ModelFamily, OpenAITool};
+use crate::message_builders::to_anthropic_messages;
+use crate::protocol::{ChatMessage, ModelFamily, OpenAITool, ToolFormat};
+use crate::settings::ChatFormatName;
+use crate::structured_output::{completions_response_format, responses_text_format};
+
+/// Local endpoint for a chat format
+pub fn chat_endpoint(port: u16, format: ChatFormatName) -> String {
+    let path = match format {
+        ChatFormatName::OpenaiCompletions => "/v1/chat/completions",
+        ChatFormatName::OpenaiResponses => "/v1/responses",
+        ChatFormatName::AnthropicMessages => "/v1/messages",
+    };
+    format!("http://127.0.0.1:{}{}", port, path)
+}
 
 /// Build a chat request body with model-family-specific parameters
 pub fn build_foundry_chat_request_body(
@@ -18,6 +32,7 @@ pub fn build_foundry_chat_request_body(
     supports_reasoning_effort: bool,
     reasoning_effort: &str,
     use_responses_api: bool,
+    response_schema: Option<&Value>,
 ) -> Value {
     let mut body = if use_responses_api {
         json!({
@@ -28,7 +43,7 @@ pub fn build_foundry_chat_request_body(
     } else {
         json!({
             "model": model,
-            "messages": messages,
+            "messages": convert_chat_messages_to_completions_format(messages),
             "stream": true,
         })
     };
@@ -38,8 +53,8 @@ pub fn build_foundry_chat_request_body(
 
     // Add model-family-specific parameters
     match family {
-        ModelFamily::GptOss => {
-            // GPT-OSS models: standard OpenAI-compatible parameters
+        ModelFamily::GptOss | ModelFamily::Qwen => {
+            // GPT-OSS and Qwen models: standard OpenAI-compatible parameters
             body[if use_responses_api { "max_output_tokens" } else { "max_tokens" }] =
                 json!(16384);
             body["temperature"] = json!(0.7);
@@ -52,7 +67,7 @@ pub fn build_foundry_chat_request_body(
         }
         ModelFamily::Phi => {
             // Phi models: may support reasoning_effort
-            if supports_reasoning && supports_reasoning_effort {
+            if supports_reasoning && supports_reasoning_effort && !reasoning_effort.is_empty() {
                 println!(
                     "[FoundryActor] Phi model with reasoning, using effort: {}",
                     reasoning_effort
@@ -107,7 +122,7 @@ pub fn build_foundry_chat_request_body(
         }
         ModelFamily::Generic => {
             // Generic/unknown models: use safe defaults
-            if supports_reasoning && supports_reasoning_effort {
+            if supports_reasoning && supports_reasoning_effort && !reasoning_effort.is_empty() {
                 body[if use_responses_api { "max_output_tokens" } else { "max_tokens" }] =
                     json!(8192);
                 body["reasoning_effort"] = json!(reasoning_effort);
@@ -123,22 +138,104 @@ pub fn build_foundry_chat_request_body(
         }
     }
 
+    // Structured output: constrain the response to the requested JSON schema
+    if let Some(schema) = response_schema {
+        if use_responses_api {
+            body["text"] = json!({ "format": responses_text_format(schema) });
+        } else {
+            body["response_format"] = completions_response_format(schema);
+        }
+    }
+
     body
 }
 
-/// Convert OpenAI chat messages into Responses API input blocks (text-only)
+/// Override the sampling parameters of a request body (per-turn `temperature`
+/// and `seed` from `chat`)
+pub fn apply_sampling_overrides(body: &mut Value, temperature: Option<f32>, seed: Option<u64>) {
+    if let Some(temperature) = temperature {
+        // Go through the decimal form so 0.7f32 is sent as 0.7, not 0.699999988
+        let temperature: f64 = temperature.to_string().parse().unwrap_or(temperature as f64);
+        body["temperature"] = json!(temperature);
+    }
+    if let Some(seed) = seed {
+        body["seed"] = json!(seed);
+    }
+}
+
+/// Add stop sequences to a request body. Anthropic-format bodies take them as
+/// `stop_sequences`, chat completions bodies as `stop`.
+pub fn apply_stop_sequences(body: &mut Value, stop_sequences: &[String], anthropic_format: bool) {
+    if stop_sequences.is_empty() {
+        return;
+    }
+    let key = if anthropic_format { "stop_sequences" } else { "stop" };
+    body[key] = json!(stop_sequences);
+}
+
+/// Rewrite a chat completions body for a backend that speaks the Anthropic
+/// messages format: content-block messages, a top-level `system`, and Anthropic
+/// tool definitions (when tools are included). Parameters that format doesn't
+/// take are dropped.
+pub fn apply_anthropic_format(
+    body: &mut Value,
+    family: ModelFamily,
+    messages: &[ChatMessage],
+    tools: &Option<Vec<OpenAITool>>,
+) {
+    if let Some(obj) = body.as_object_mut() {
+        for key in ["reasoning_effort", "response_format", "repetition_penalty", "seed"] {
+            obj.remove(key);
+        }
+    }
+    let (system, converted) = to_anthropic_messages(messages);
+    body["messages"] = Value::Array(converted);
+    if let Some(system) = system {
+        body["system"] = json!(system);
+    }
+    if let (Some(tool_list), true) = (tools, body.get("tools").is_some()) {
+        body["tools"] =
+            crate::tool_parsing::format_tools_for_model(tool_list, family, ToolFormat::Anthropic);
+    }
+}
+
+/// Convert chat messages for the chat completions API. Messages with images
+/// get multimodal content parts; all others serialize unchanged.
+pub fn convert_chat_messages_to_completions_format(messages: &[ChatMessage]) -> Vec<Value> {
+    messages
+        .iter()
+        .map(|msg| {
+            let mut value = json!(msg);
+            if !msg.images.is_empty() {
+                let mut parts = vec![json!({"type": "text", "text": msg.content})];
+                parts.extend(msg.images.iter().map(|url| {
+                    json!({"type": "image_url", "image_url": {"url": url}})
+                }));
+                value["content"] = Value::Array(parts);
+                if let Some(obj) = value.as_object_mut() {
+                    obj.remove("images");
+                }
+            }
+            value
+        })
+        .collect()
+}
+
+/// Convert OpenAI chat messages into Responses API input blocks
 pub fn convert_chat_messages_to_foundry_format(messages: &[ChatMessage]) -> Vec<Value> {
     messages
         .iter()
         .map(|msg| {
+            let mut content = vec![json!({
+                "type": "text",
+                "text": msg.content
+            })];
+            content.extend(msg.images.iter().map(|url| {
+                json!({"type": "input_image", "image_url": url})
+            }));
             json!({
                 "role": msg.role,
-                "content": [
-                    {
-                        "type": "text",
-                        "text": msg.content
-                    }
-                ]
+                "content": content
             })
         })
         .collect()
@@ -153,13 +250,111 @@ mod tests {
         let messages = vec![ChatMessage {
             role: "user".to_string(),
             content: "hi there".to_string(),
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         }];
         let input = convert_chat_messages_to_foundry_format(&messages);
         assert_eq!(input.len(), 1);
         assert_eq!(input[0]["role"], "user");
         assert_eq!(input[0]["content"][0]["text"], "hi there");
     }
+
+    #[test]
+    fn image_messages_become_multimodal_content() {
+        let mut message = ChatMessage {
+            role: "user".to_string(),
+            content: "what is this?".to_string(),
+            images: vec!["data:image/png;base64,AAAA".to_string()],
+            ..Default::default()
+        };
+
+        let completions = convert_chat_messages_to_completions_format(&[message.clone()]);
+        assert_eq!(completions[0]["content"][0]["text"], "what is this?");
+        assert_eq!(completions[0]["content"][1]["type"], "image_url");
+        assert_eq!(
+            completions[0]["content"][1]["image_url"]["url"],
+            "data:image/png;base64,AAAA"
+        );
+        assert!(completions[0].get("images").is_none());
+
+        let responses = convert_chat_messages_to_foundry_format(&[message.clone()]);
+        assert_eq!(responses[0]["content"][1]["type"], "input_image");
+
+        message.images.clear();
+        let plain = convert_chat_messages_to_completions_format(&[message]);
+        assert_eq!(plain[0]["content"], "what is this?");
+    }
+
+    #[test]
+    fn response_schema_becomes_structured_output_constraint() {
+        let schema = json!({"type": "object", "properties": {"name": {"type": "string"}}});
+        let build = |use_responses_api| {
+            build_foundry_chat_request_body(
+                "phi-4",
+                ModelFamily::Phi,
+                &[],
+                &None,
+                false,
+                false,
+                false,
+                "low",
+                use_responses_api,
+                Some(&schema),
+            )
+        };
+
+        let completions = build(false);
+        assert_eq!(completions["response_format"]["type"], "json_schema");
+        assert_eq!(completions["response_format"]["json_schema"]["schema"], schema);
+
+        let responses = build(true);
+        assert_eq!(responses["text"]["format"]["schema"], schema);
+        assert!(responses.get("response_format").is_none());
+    }
+
+    #[test]
+    fn empty_reasoning_effort_is_not_sent() {
+        let build = |effort| {
+            build_foundry_chat_request_body(
+                "phi-4-mini-reasoning", ModelFamily::Phi, &[], &None, false, true, true, effort,
+                false, None,
+            )
+        };
+        assert_eq!(build("high")["reasoning_effort"], "high");
+        assert!(build("").get("reasoning_effort").is_none());
+    }
+
+    #[test]
+    fn sampling_overrides_replace_family_defaults() {
+        let mut body = build_foundry_chat_request_body(
+            "qwen2.5-7b", ModelFamily::Qwen, &[], &None, false, false, false, "low", false, None,
+        );
+        assert_eq!(body["temperature"], json!(0.7));
+        assert!(body.get("seed").is_none());
+
+        apply_sampling_overrides(&mut body, Some(0.2), Some(42));
+        assert_eq!(body["temperature"], json!(0.2));
+        assert_eq!(body["seed"], json!(42));
+    }
+
+    #[test]
+    fn stop_sequences_use_the_body_format_key() {
+        let build = || {
+            build_foundry_chat_request_body(
+                "qwen2.5-7b", ModelFamily::Qwen, &[], &None, false, false, false, "low", false, None,
+            )
+        };
+        let stops = vec!["</tool_call>".to_string()];
+
+        let mut body = build();
+        apply_stop_sequences(&mut body, &[], false);
+        assert!(body.get("stop").is_none());
+
+        apply_stop_sequences(&mut body, &stops, false);
+        assert_eq!(body["stop"], json!(["</tool_call>"]));
+
+        let mut body = build();
+        apply_stop_sequences(&mut body, &stops, true);
+        assert_eq!(body["stop_sequences"], json!(["</tool_call>"]));
+        assert!(body.get("stop").is_none());
+    }
 }
@@ -1,12 +1,16 @@
 //! Stream handling for Foundry API responses.
 //!
 //! This module handles:
+//! - Decoding server-sent event bodies into text tokens
 //! - Accumulating OpenAI-style streaming tool calls
+//! - Accumulating Anthropic messages stream events (text and `tool_use` blocks)
 //! - Extracting text from Chat Completions and Responses API payloads
 
-use std::collections::HashMap;
-use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use serde_json::{json, Value};
 use crate::protocol::ParsedToolCall;
+use crate::settings::ChatFormatName;
+use crate::tool_parsing::json_fixer::repair_json_arguments;
 use crate::tool_parsing::parse_combined_tool_name;
 
 /// Accumulator for OpenAI-style streaming tool calls.
@@ -69,6 +73,10 @@ impl StreamingToolCalls {
                 Value::Object(serde_json::Map::new())
             } else {
                 serde_json::from_str(&arguments_str).unwrap_or_else(|e| {
+                    if let Some(repaired) = repair_json_arguments(&arguments_str) {
+                        println!("[StreamingToolCalls] Repaired malformed arguments for {}", name);
+                        return repaired;
+                    }
                     println!(
                         "[StreamingToolCalls] Failed to parse arguments for {}: {}",
                         name, e
@@ -108,6 +116,189 @@ impl StreamingToolCalls {
     }
 }
 
+/// Accumulator for Anthropic messages stream events.
+///
+/// Text arrives as `content_block_delta` events with a `text_delta`. A `tool_use`
+/// block opens with `content_block_start` (id and name) and its input streams as
+/// `input_json_delta` fragments.
+#[derive(Default)]
+pub struct AnthropicStreamBlocks {
+    /// Map of block index -> (id, name, accumulated input JSON)
+    tool_uses: BTreeMap<usize, (String, String, String)>,
+}
+
+impl AnthropicStreamBlocks {
+    /// Process one stream event, returning the text it carries (if any)
+    pub fn process_event(&mut self, event: &Value) -> Option<String> {
+        let index = event["index"].as_u64().unwrap_or(0) as usize;
+        let text = match event["type"].as_str() {
+            Some("content_block_start") => {
+                let block = &event["content_block"];
+                if block["type"] == "tool_use" {
+                    self.tool_uses.insert(
+                        index,
+                        (
+                            block["id"].as_str().unwrap_or_default().to_string(),
+                            block["name"].as_str().unwrap_or_default().to_string(),
+                            String::new(),
+                        ),
+                    );
+                    None
+                } else {
+                    block["text"].as_str()
+                }
+            }
+            Some("content_block_delta") => {
+                let delta = &event["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => delta["text"].as_str(),
+                    Some("input_json_delta") => {
+                        if let (Some(entry), Some(partial)) = (
+                            self.tool_uses.get_mut(&index),
+                            delta["partial_json"].as_str(),
+                        ) {
+                            entry.2.push_str(partial);
+                        }
+                        None
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        text.filter(|t| !t.is_empty()).map(str::to_string)
+    }
+
+    /// Check if any `tool_use` blocks have been accumulated
+    pub fn is_empty(&self) -> bool {
+        self.tool_uses.is_empty()
+    }
+
+    /// The accumulated `tool_use` blocks as a content block array, the shape the
+    /// Anthropic tool call parser reads
+    pub fn into_tool_use_text(self) -> Option<String> {
+        let blocks: Vec<Value> = self
+            .tool_uses
+            .into_values()
+            .filter(|(_, name, _)| !name.is_empty())
+            .map(|(id, name, input)| {
+                let input = if input.trim().is_empty() {
+                    json!({})
+                } else {
+                    serde_json::from_str(&input)
+                        .ok()
+                        .or_else(|| repair_json_arguments(&input))
+                        .unwrap_or_else(|| {
+                            println!("[AnthropicStreamBlocks] Failed to parse input for {}: {}", name, input);
+                            json!({})
+                        })
+                };
+                json!({"type": "tool_use", "id": id, "name": name, "input": input})
+            })
+            .collect();
+        if blocks.is_empty() {
+            return None;
+        }
+        Some(format!("\n{}\n", Value::Array(blocks)))
+    }
+}
+
+/// Decoder for a streamed chat response body (server-sent events).
+///
+/// Text tokens come out as their lines complete. Tool calls that arrive as
+/// structured deltas are accumulated and turned into text by `finish`, so the
+/// agentic loop's parsers can detect them.
+pub struct ChatStreamDecoder {
+    format: ChatFormatName,
+    buffer: String,
+    tool_calls: StreamingToolCalls,
+    anthropic_blocks: AnthropicStreamBlocks,
+    done: bool,
+}
+
+impl ChatStreamDecoder {
+    pub fn new(format: ChatFormatName) -> Self {
+        Self {
+            format,
+            buffer: String::new(),
+            tool_calls: StreamingToolCalls::default(),
+            anthropic_blocks: AnthropicStreamBlocks::default(),
+            done: false,
+        }
+    }
+
+    /// Feed a chunk of the body; returns the text tokens of the lines it completes
+    pub fn push_chunk(&mut self, chunk: &str) -> Vec<String> {
+        self.buffer.push_str(chunk);
+        let mut tokens = Vec::new();
+        while !self.done {
+            let Some(idx) = self.buffer.find('\n') else {
+                break;
+            };
+            let line: String = self.buffer.drain(..=idx).collect();
+            let Some(data) = line.trim().strip_prefix("data: ") else {
+                continue;
+            };
+            if data == "[DONE]" {
+                self.done = true;
+                break;
+            }
+            let Ok(json) = serde_json::from_str::<Value>(data) else {
+                continue;
+            };
+            match self.format {
+                ChatFormatName::AnthropicMessages => {
+                    if json["type"] == "message_stop" {
+                        self.done = true;
+                        break;
+                    }
+                    tokens.extend(self.anthropic_blocks.process_event(&json));
+                }
+                ChatFormatName::OpenaiCompletions | ChatFormatName::OpenaiResponses => {
+                    let use_responses_api = self.format == ChatFormatName::OpenaiResponses;
+                    if let Some(content) = extract_text_from_stream_chunk(&json, use_responses_api) {
+                        if !content.is_empty() {
+                            tokens.push(content);
+                        }
+                    }
+                    // Accumulate native OpenAI tool calls (delta.tool_calls)
+                    if let Some(tool_calls) = json["choices"][0]["delta"]["tool_calls"].as_array() {
+                        self.tool_calls.process_streaming_tool_call_delta(tool_calls);
+                    }
+                }
+            }
+        }
+        tokens
+    }
+
+    /// Whether the stream signalled its end (`[DONE]` or `message_stop`)
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Text for the tool calls that arrived as structured deltas
+    pub fn finish(self) -> Vec<String> {
+        let mut texts = Vec::new();
+        if !self.tool_calls.is_empty() {
+            let native_calls = self.tool_calls.into_parsed_calls();
+            println!("[FoundryActor] Emitting {} native tool calls as text", native_calls.len());
+            for call in &native_calls {
+                // Emit in <tool_call> format for parser compatibility
+                texts.push(format!(
+                    "\n<tool_call>{{\"name\": \"{}\", \"arguments\": {}}}</tool_call>\n",
+                    call.tool,
+                    serde_json::to_string(&call.arguments).unwrap_or_else(|_| "{}".to_string())
+                ));
+            }
+        }
+        if !self.anthropic_blocks.is_empty() {
+            println!("[FoundryActor] Emitting Anthropic tool_use blocks as text");
+            texts.extend(self.anthropic_blocks.into_tool_use_text());
+        }
+        texts
+    }
+}
+
 /// Extract streamed text from either Chat Completions or Responses API payloads.
 pub fn extract_text_from_stream_chunk(json: &Value, use_responses_api: bool) -> Option<String> {
     // Chat Completions delta string form
@@ -191,7 +382,165 @@ pub fn extract_text_from_stream_chunk(json: &Value, use_responses_api: bool) ->
 #[cfg(test)]
 mod tests {
     use super::*;
-    use serde_json::json;
+    use super::super::request_builder::{
+        apply_anthropic_format, apply_stop_sequences, build_foundry_chat_request_body,
+        chat_endpoint,
+    };
+    use crate::protocol::{ChatMessage, ModelFamily, OpenAIFunction, OpenAITool, ToolFormat};
+    use crate::settings::{ToolCallFormatConfig, ToolCallFormatName};
+    use crate::tool_parsing::parse_tool_calls_for_model_profile;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Answer one request with a server-sent event body; returns the request line
+    /// and the JSON body that was received
+    async fn serve_sse_once(listener: TcpListener, events: String) -> (String, Value) {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        let mut buf = [0u8; 4096];
+        let (head, body) = loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            assert!(n > 0, "connection closed before the request was complete");
+            received.extend_from_slice(&buf[..n]);
+            let text = String::from_utf8_lossy(&received).to_string();
+            if let Some(split) = text.find("\r\n\r\n") {
+                let head = text[..split].to_string();
+                let length = head
+                    .lines()
+                    .find_map(|l| {
+                        l.to_ascii_lowercase()
+                            .strip_prefix("content-length:")
+                            .map(|v| v.trim().parse::<usize>().unwrap())
+                    })
+                    .unwrap_or(0);
+                if text.len() >= split + 4 + length {
+                    break (head, text[split + 4..split + 4 + length].to_string());
+                }
+            }
+        };
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            events.len(),
+            events
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+        let request_line = head.lines().next().unwrap_or_default().to_string();
+        (request_line, serde_json::from_str(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn anthropic_messages_stream_round_trips_tool_use_over_http() {
+        let events: String = [
+            json!({"type": "message_start", "message": {"id": "msg_1", "role": "assistant", "content": []}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "text", "text": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "Checking the "}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "text_delta", "text": "forecast."}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "toolu_01", "name": "weather___get_forecast", "input": {}}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"city\": \"Os"}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "lo\"}"}}),
+            json!({"type": "content_block_stop", "index": 1}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}}),
+            json!({"type": "message_stop"}),
+        ]
+        .iter()
+        .map(|event| format!("event: {}\ndata: {}\n\n", event["type"].as_str().unwrap(), event))
+        .collect();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(serve_sse_once(listener, events));
+
+        // Build the request the way the gateway does for an anthropic_messages model
+        let messages = vec![
+            message("system", "You are a weather assistant."),
+            message("user", "Will it rain in Oslo?"),
+        ];
+        let tools = Some(vec![OpenAITool {
+            tool_type: "function".to_string(),
+            function: OpenAIFunction {
+                name: "weather___get_forecast".to_string(),
+                description: Some("Get the forecast for a city".to_string()),
+                parameters: Some(json!({"type": "object", "properties": {"city": {"type": "string"}}})),
+            },
+        }]);
+        let mut body = build_foundry_chat_request_body(
+            "claude-local",
+            ModelFamily::Generic,
+            &messages,
+            &tools,
+            true,
+            false,
+            false,
+            "",
+            false,
+            None,
+        );
+        apply_anthropic_format(&mut body, ModelFamily::Generic, &messages, &tools);
+        apply_stop_sequences(&mut body, &["</tool_call>".to_string()], true);
+
+        let mut resp = reqwest::Client::new()
+            .post(chat_endpoint(port, ChatFormatName::AnthropicMessages))
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        let mut decoder = ChatStreamDecoder::new(ChatFormatName::AnthropicMessages);
+        let mut tokens = Vec::new();
+        while let Some(chunk) = resp.chunk().await.unwrap() {
+            tokens.extend(decoder.push_chunk(std::str::from_utf8(&chunk).unwrap()));
+        }
+        assert!(decoder.is_done());
+        assert_eq!(tokens.concat(), "Checking the forecast.");
+
+        let (request_line, sent) = server.await.unwrap();
+        assert_eq!(request_line, "POST /v1/messages HTTP/1.1");
+        assert_eq!(sent["system"], "You are a weather assistant.");
+        assert_eq!(sent["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(sent["messages"][0]["role"], "user");
+        assert_eq!(sent["tools"][0]["name"], "weather___get_forecast");
+        assert!(sent["tools"][0].get("input_schema").is_some());
+        assert_eq!(sent["stop_sequences"], json!(["</tool_call>"]));
+
+        // The tool_use block reaches the agentic loop as text its parser reads
+        let tool_text = decoder.finish().concat();
+        let formats = ToolCallFormatConfig {
+            enabled: vec![ToolCallFormatName::Anthropic],
+            primary: ToolCallFormatName::Anthropic,
+        };
+        let calls = parse_tool_calls_for_model_profile(
+            &tool_text,
+            ModelFamily::Generic,
+            ToolFormat::Anthropic,
+            &formats,
+            ToolCallFormatName::Anthropic,
+        );
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].id.as_deref(), Some("toolu_01"));
+        assert_eq!(calls[0].server, "weather");
+        assert_eq!(calls[0].tool, "get_forecast");
+        assert_eq!(calls[0].arguments, json!({"city": "Oslo"}));
+    }
+
+    #[test]
+    fn chat_stream_decoder_accumulates_native_tool_calls_across_chunks() {
+        let mut decoder = ChatStreamDecoder::new(ChatFormatName::OpenaiCompletions);
+        let mut tokens = decoder.push_chunk("data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\ndata: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",");
+        tokens.extend(decoder.push_chunk("\"function\":{\"name\":\"echo\",\"arguments\":\"{}\"}}]}}]}\n\ndata: [DONE]\n\n"));
+        assert_eq!(tokens, vec!["hi".to_string()]);
+        assert!(decoder.is_done());
+        assert_eq!(
+            decoder.finish(),
+            vec!["\n<tool_call>{\"name\": \"echo\", \"arguments\": {}}</tool_call>\n".to_string()]
+        );
+    }
 
     #[test]
     fn extract_text_from_stream_chunk_handles_chat_delta_string() {
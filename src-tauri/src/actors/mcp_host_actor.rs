@@ -5,12 +5,12 @@ use std::sync::Arc;
 use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
 use tokio::process::{Child, ChildStdin, Command};
-use tokio::sync::mpsc;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{Mutex, RwLock};
 
 use crate::process_utils::HideConsoleWindow;
 use crate::protocol::McpHostMsg;
-use crate::settings::{McpServerConfig, Transport};
+use crate::settings::{resolve_env_vars, McpServerConfig, Transport};
 
 /// MCP JSON-RPC request
 #[derive(Debug, Serialize)]
@@ -57,6 +57,10 @@ pub struct McpTool {
     /// Allowed callers for programmatic tool use (e.g., ["python_execution_20251206"])
     #[serde(default, rename = "allowedCallers", alias = "allowed_callers")]
     pub allowed_callers: Option<Vec<String>>,
+    /// Whether python_execution code may consume the result in chunks via
+    /// `tool_call_stream` (chunks are the messages of the call's progress notifications)
+    #[serde(default)]
+    pub streamable: bool,
 }
 
 /// Result from tool execution
@@ -78,6 +82,124 @@ pub struct McpContent {
     pub data: Option<String>,
     #[serde(rename = "mimeType", default)]
     pub mime_type: Option<String>,
+    /// Target of a `resource_link` part
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    /// Embedded contents of a `resource` part
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<McpResourceContents>,
+}
+
+/// Contents of an embedded resource in a tool result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpResourceContents {
+    pub uri: String,
+    #[serde(rename = "mimeType", default)]
+    pub mime_type: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
+    /// Base64-encoded binary contents
+    #[serde(default)]
+    pub blob: Option<String>,
+}
+
+/// Resolve a server's configured roots to `file://` URIs, failing if any
+/// path does not exist
+fn resolve_roots(config: &McpServerConfig) -> Result<Vec<Value>, String> {
+    config
+        .roots
+        .iter()
+        .map(|root| {
+            let path = std::fs::canonicalize(root).map_err(|e| {
+                format!(
+                    "Root '{}' for MCP server {} does not exist: {}",
+                    root, config.id, e
+                )
+            })?;
+            let uri = tauri::Url::from_file_path(&path)
+                .map_err(|_| format!("Root '{}' cannot be expressed as a file URI", root))?;
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| root.clone());
+            Ok(json!({ "uri": uri.as_str(), "name": name }))
+        })
+        .collect()
+}
+
+/// Params for the `initialize` request. The roots capability is only
+/// declared when the server has roots configured.
+fn initialize_params(client_name: &str, roots: &[Value]) -> Value {
+    let mut capabilities = json!({ "tools": {} });
+    if !roots.is_empty() {
+        capabilities["roots"] = json!({ "listChanged": false, "roots": roots });
+    }
+    json!({
+        "protocolVersion": "2024-11-05",
+        "capabilities": capabilities,
+        "clientInfo": {
+            "name": client_name,
+            "version": "0.1.0"
+        }
+    })
+}
+
+/// Reply to a request sent by the server, or None if `message` is not one.
+/// Only `roots/list` is supported; anything else gets "method not found".
+fn server_request_reply(message: &Value, roots: &[Value]) -> Option<Value> {
+    let method = message.get("method")?.as_str()?;
+    let id = message.get("id")?.clone();
+    Some(if method == "roots/list" {
+        json!({ "jsonrpc": "2.0", "id": id, "result": { "roots": roots } })
+    } else {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": -32601, "message": format!("Method not found: {}", method) }
+        })
+    })
+}
+
+/// Progress token sent with streamed tool calls. One request runs per
+/// connection at a time, so a fixed token is enough to match notifications.
+const STREAM_PROGRESS_TOKEN: &str = "tool_call_stream";
+
+/// Chunk carried by `message` if it is a progress notification for a streamed tool call
+fn stream_progress_chunk(message: &Value) -> Option<String> {
+    if message.get("method")?.as_str()? != "notifications/progress" {
+        return None;
+    }
+    let params = message.get("params")?;
+    if params.get("progressToken")?.as_str()? != STREAM_PROGRESS_TOKEN {
+        return None;
+    }
+    Some(params.get("message")?.as_str()?.to_string())
+}
+
+/// How long protocol requests (initialize, tools/list, health pings) and tool
+/// calls without a configured limit wait for a response
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Wait for the response to request `id`, at most `timeout` (None = no limit)
+async fn await_response(
+    response: impl std::future::Future<Output = Result<JsonRpcResponse, String>>,
+    timeout: Option<Duration>,
+    id: u64,
+) -> Result<Value, String> {
+    let read_result = match timeout {
+        Some(limit) => tokio::time::timeout(limit, response)
+            .await
+            .map_err(|_| format!("Request timed out waiting for id {}", id))?,
+        None => response.await,
+    };
+    let response = read_result?;
+    if let Some(error) = response.error {
+        Err(format!("MCP error {}: {}", error.code, error.message))
+    } else {
+        response
+            .result
+            .ok_or_else(|| "No result in response".to_string())
+    }
 }
 
 /// Connected MCP server state
@@ -88,6 +210,10 @@ struct McpServerConnection {
     stdout_lines: Lines<BufReader<tokio::process::ChildStdout>>,
     tools: Vec<McpTool>,
     request_id: u64,
+    /// Resolved roots, returned when the server sends `roots/list`
+    roots: Vec<Value>,
+    /// Receives the chunks of the streamed tool call in flight, if any
+    stream_tx: Option<mpsc::UnboundedSender<String>>,
 }
 
 impl McpServerConnection {
@@ -96,8 +222,19 @@ impl McpServerConnection {
         self.request_id
     }
 
-    /// Send a request and wait for response
+    /// Send a request and wait up to `REQUEST_TIMEOUT` for the response
     async fn send_request(&mut self, method: &str, params: Option<Value>) -> Result<Value, String> {
+        self.send_request_with_timeout(method, params, Some(REQUEST_TIMEOUT))
+            .await
+    }
+
+    /// Send a request and wait for the response, at most `timeout` (None = no limit)
+    async fn send_request_with_timeout(
+        &mut self,
+        method: &str,
+        params: Option<Value>,
+        timeout: Option<Duration>,
+    ) -> Result<Value, String> {
         let id = self.next_id();
 
         let request = JsonRpcRequest {
@@ -123,22 +260,7 @@ impl McpServerConnection {
             .map_err(|e| format!("Failed to flush request: {}", e))?;
 
         // Read response with timeout, ensuring we match on the request id
-        let read_result =
-            tokio::time::timeout(Duration::from_secs(30), self.read_response(id)).await;
-
-        match read_result {
-            Ok(Ok(response)) => {
-                if let Some(error) = response.error {
-                    Err(format!("MCP error {}: {}", error.code, error.message))
-                } else {
-                    response
-                        .result
-                        .ok_or_else(|| "No result in response".to_string())
-                }
-            }
-            Ok(Err(e)) => Err(e),
-            Err(_) => Err(format!("Request timed out waiting for id {}", id)),
-        }
+        await_response(self.read_response(id), timeout, id).await
     }
 
     /// Read a JSON-RPC response from stdout
@@ -153,6 +275,25 @@ impl McpServerConnection {
 
                     println!("McpHostActor: Received: {}", trimmed);
 
+                    let message = serde_json::from_str::<Value>(trimmed).ok();
+
+                    // Progress of a streamed tool call is passed on as it arrives
+                    if let Some(chunk) = message.as_ref().and_then(stream_progress_chunk) {
+                        if let Some(tx) = &self.stream_tx {
+                            let _ = tx.send(chunk);
+                        }
+                        continue;
+                    }
+
+                    // Servers may ask us for roots while we wait on a response
+                    if let Some(reply) = message
+                        .as_ref()
+                        .and_then(|msg| server_request_reply(msg, &self.roots))
+                    {
+                        self.write_message(&reply).await?;
+                        continue;
+                    }
+
                     // Try to parse as JSON-RPC response
                     match serde_json::from_str::<JsonRpcResponse>(trimmed) {
                         Ok(response) => {
@@ -194,6 +335,21 @@ impl McpServerConnection {
         }
     }
 
+    /// Write one JSON-RPC message to the server's stdin
+    async fn write_message(&mut self, message: &Value) -> Result<(), String> {
+        let line = serde_json::to_string(message)
+            .map_err(|e| format!("Failed to serialize message: {}", e))?;
+        println!("McpHostActor: Sending: {}", line);
+        self.stdin
+            .write_all(format!("{}\n", line).as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write message: {}", e))?;
+        self.stdin
+            .flush()
+            .await
+            .map_err(|e| format!("Failed to flush message: {}", e))
+    }
+
     /// Send a notification (no response expected)
     async fn send_notification(
         &mut self,
@@ -231,10 +387,105 @@ impl McpServerConnection {
     }
 }
 
+/// Round-trip a tools/list request so a dead or hung server shows up
+/// (`list_tools` only returns the tools cached at connect time)
+async fn check_server_health(connection: &Mutex<McpServerConnection>) -> Result<(), String> {
+    let mut connection = connection.lock().await;
+    connection.send_request("tools/list", None).await.map(|_| ())
+}
+
+/// Call a tool on a connected server, streaming progress chunks to `stream_tx` if given
+async fn execute_tool(
+    connection: &Mutex<McpServerConnection>,
+    server_id: &str,
+    tool_name: &str,
+    arguments: Value,
+    timeout: Option<Duration>,
+    stream_tx: Option<mpsc::UnboundedSender<String>>,
+) -> Result<McpToolResult, String> {
+    // Log the input
+    println!("\n╔══════════════════════════════════════════════════════════════");
+    println!("║ MCP TOOL CALL INPUT");
+    println!("╠══════════════════════════════════════════════════════════════");
+    println!("║ Server:    {}", server_id);
+    println!("║ Tool:      {}", tool_name);
+    println!(
+        "║ Arguments: {}",
+        serde_json::to_string_pretty(&arguments).unwrap_or_else(|_| arguments.to_string())
+    );
+    println!("╚══════════════════════════════════════════════════════════════\n");
+
+    let mut connection = connection.lock().await;
+
+    let mut params = json!({
+        "name": tool_name,
+        "arguments": arguments
+    });
+    // Streamed calls ask for progress notifications, which carry the chunks
+    if stream_tx.is_some() {
+        params["_meta"] = json!({ "progressToken": STREAM_PROGRESS_TOKEN });
+    }
+    connection.stream_tx = stream_tx;
+    let result = connection
+        .send_request_with_timeout("tools/call", Some(params), timeout)
+        .await;
+    connection.stream_tx = None;
+
+    match result {
+        Ok(raw_result) => {
+            // Parse the result
+            let tool_result: McpToolResult = serde_json::from_value(raw_result.clone())
+                .map_err(|e| format!("Failed to parse tool result: {}", e))?;
+
+            // Log the output
+            println!("\n╔══════════════════════════════════════════════════════════════");
+            println!("║ MCP TOOL CALL OUTPUT");
+            println!("╠══════════════════════════════════════════════════════════════");
+            println!("║ Server:   {}", server_id);
+            println!("║ Tool:     {}", tool_name);
+            println!("║ Is Error: {}", tool_result.is_error);
+            println!("║ Content:");
+            for content in &tool_result.content {
+                if let Some(text) = &content.text {
+                    // Indent multi-line output
+                    for line in text.lines() {
+                        println!("║   {}", line);
+                    }
+                }
+                if let Some(data) = &content.data {
+                    let preview: String = data.chars().take(200).collect();
+                    println!(
+                        "║   [Binary data: {} bytes, preview: {}...]",
+                        data.len(),
+                        preview
+                    );
+                }
+            }
+            println!("╚══════════════════════════════════════════════════════════════\n");
+
+            Ok(tool_result)
+        }
+        Err(e) => {
+            // Log the error
+            println!("\n╔══════════════════════════════════════════════════════════════");
+            println!("║ MCP TOOL CALL ERROR");
+            println!("╠══════════════════════════════════════════════════════════════");
+            println!("║ Server: {}", server_id);
+            println!("║ Tool:   {}", tool_name);
+            println!("║ Error:  {}", e);
+            println!("╚══════════════════════════════════════════════════════════════\n");
+
+            Err(e)
+        }
+    }
+}
+
 /// MCP Host Actor - manages MCP server connections
 pub struct McpToolRouterActor {
     mcp_tool_msg_rx: mpsc::Receiver<McpHostMsg>,
-    connections: Arc<RwLock<HashMap<String, McpServerConnection>>>,
+    /// Connections by server id. Tool calls and health pings run in their own
+    /// tasks holding only their server's lock (see `spawn_tool_call`).
+    connections: Arc<RwLock<HashMap<String, Arc<Mutex<McpServerConnection>>>>>,
 }
 
 impl McpToolRouterActor {
@@ -271,10 +522,29 @@ impl McpToolRouterActor {
                     server_id,
                     tool_name,
                     arguments,
+                    timeout,
                     respond_to,
                 } => {
-                    let result = self.execute_tool(&server_id, &tool_name, arguments).await;
-                    let _ = respond_to.send(result);
+                    self.spawn_tool_call(server_id, tool_name, arguments, timeout, None, respond_to)
+                        .await;
+                }
+                McpHostMsg::ExecuteToolStream {
+                    server_id,
+                    tool_name,
+                    arguments,
+                    timeout,
+                    chunk_tx,
+                    respond_to,
+                } => {
+                    self.spawn_tool_call(
+                        server_id,
+                        tool_name,
+                        arguments,
+                        timeout,
+                        Some(chunk_tx),
+                        respond_to,
+                    )
+                    .await;
                 }
                 McpHostMsg::GetAllToolDescriptions { respond_to } => {
                     let result = self.get_all_tool_descriptions().await;
@@ -287,6 +557,20 @@ impl McpToolRouterActor {
                     let status = self.get_server_status(&server_id).await;
                     let _ = respond_to.send(status);
                 }
+                McpHostMsg::CheckServerHealth {
+                    server_id,
+                    respond_to,
+                } => {
+                    // Off the loop too: a hung server must not hold up other messages
+                    let connection = self.connection(&server_id).await;
+                    tokio::spawn(async move {
+                        let result = match connection {
+                            Ok(connection) => check_server_health(&connection).await,
+                            Err(e) => Err(e),
+                        };
+                        let _ = respond_to.send(result);
+                    });
+                }
                 McpHostMsg::SyncEnabledServers {
                     configs,
                     respond_to,
@@ -304,9 +588,9 @@ impl McpToolRouterActor {
         println!("McpHostActor: Shutting down...");
         // Clean up all connections on shutdown
         let mut connections = self.connections.write().await;
-        for (id, mut conn) in connections.drain() {
+        for (id, conn) in connections.drain() {
             println!("McpHostActor: Killing server process: {}", id);
-            let _ = conn.process.kill().await;
+            let _ = conn.lock().await.process.kill().await;
         }
     }
 
@@ -347,11 +631,18 @@ impl McpToolRouterActor {
             command, config.args
         );
 
-        let mut cmd = Command::new(&command);
-        cmd.args(&config.args);
+        // Fail before spawning if a configured root is missing
+        let roots = resolve_roots(&config)?;
+
+        // Expand ${VAR} references only for the spawn, so secrets stay out of logs
+        let mut resolved = config.clone();
+        resolve_env_vars(&mut resolved)?;
+
+        let mut cmd = Command::new(resolved.command.as_deref().unwrap_or(&command));
+        cmd.args(&resolved.args);
 
         // Set environment variables
-        for (key, value) in &config.env {
+        for (key, value) in &resolved.env {
             cmd.env(key, value);
         }
 
@@ -427,6 +718,8 @@ impl McpToolRouterActor {
             stdout_lines,
             tools: Vec::new(),
             request_id: 0,
+            roots,
+            stream_tx: None,
         };
 
         // Wait for server to be ready
@@ -492,16 +785,7 @@ impl McpToolRouterActor {
         let init_result = connection
             .send_request(
                 "initialize",
-                Some(json!({
-                    "protocolVersion": "2024-11-05",
-                    "capabilities": {
-                        "tools": {}
-                    },
-                    "clientInfo": {
-                        "name": "plugable-chat",
-                        "version": "0.1.0"
-                    }
-                })),
+                Some(initialize_params("plugable-chat", &connection.roots)),
             )
             .await;
 
@@ -578,7 +862,7 @@ impl McpToolRouterActor {
         // Store connection
         {
             let mut connections = self.connections.write().await;
-            connections.insert(server_id.clone(), connection);
+            connections.insert(server_id.clone(), Arc::new(Mutex::new(connection)));
         }
 
         println!("McpHostActor: Server {} connected successfully", server_id);
@@ -588,9 +872,11 @@ impl McpToolRouterActor {
     async fn disconnect_server(&self, server_id: &str) -> Result<(), String> {
         let mut connections = self.connections.write().await;
 
-        if let Some(mut conn) = connections.remove(server_id) {
+        if let Some(conn) = connections.remove(server_id) {
             println!("McpHostActor: Disconnecting server: {}", server_id);
-            conn.process
+            conn.lock()
+                .await
+                .process
                 .kill()
                 .await
                 .map_err(|e| format!("Failed to kill process: {}", e))?;
@@ -604,124 +890,77 @@ impl McpToolRouterActor {
         let connections = self.connections.read().await;
 
         if let Some(conn) = connections.get(server_id) {
-            Ok(conn.tools.clone())
+            Ok(conn.lock().await.tools.clone())
         } else {
             Err(format!("Server {} not connected", server_id))
         }
     }
 
-    async fn execute_tool(
+    /// A connected server's handle, cloned so the map lock is released before any I/O
+    async fn connection(&self, server_id: &str) -> Result<Arc<Mutex<McpServerConnection>>, String> {
+        self.connections
+            .read()
+            .await
+            .get(server_id)
+            .cloned()
+            .ok_or_else(|| format!("Server {} not connected", server_id))
+    }
+
+    /// Run a tool call in its own task, so the loop keeps serving messages
+    /// (and calls to other servers) while this server works on it
+    async fn spawn_tool_call(
         &self,
-        server_id: &str,
-        tool_name: &str,
+        server_id: String,
+        tool_name: String,
         arguments: Value,
-    ) -> Result<McpToolResult, String> {
-        // Log the input
-        println!("\n╔══════════════════════════════════════════════════════════════");
-        println!("║ MCP TOOL CALL INPUT");
-        println!("╠══════════════════════════════════════════════════════════════");
-        println!("║ Server:    {}", server_id);
-        println!("║ Tool:      {}", tool_name);
-        println!(
-            "║ Arguments: {}",
-            serde_json::to_string_pretty(&arguments).unwrap_or_else(|_| arguments.to_string())
-        );
-        println!("╚══════════════════════════════════════════════════════════════\n");
-
-        let mut connections = self.connections.write().await;
-        let connection = connections.get_mut(server_id).ok_or_else(|| {
-            println!("║ ERROR: Server {} not connected", server_id);
-            format!("Server {} not connected", server_id)
-        })?;
-
-        let result = connection
-            .send_request(
-                "tools/call",
-                Some(json!({
-                    "name": tool_name,
-                    "arguments": arguments
-                })),
-            )
-            .await;
-
-        match result {
-            Ok(raw_result) => {
-                // Parse the result
-                let tool_result: McpToolResult = serde_json::from_value(raw_result.clone())
-                    .map_err(|e| format!("Failed to parse tool result: {}", e))?;
-
-                // Log the output
-                println!("\n╔══════════════════════════════════════════════════════════════");
-                println!("║ MCP TOOL CALL OUTPUT");
-                println!("╠══════════════════════════════════════════════════════════════");
-                println!("║ Server:   {}", server_id);
-                println!("║ Tool:     {}", tool_name);
-                println!("║ Is Error: {}", tool_result.is_error);
-                println!("║ Content:");
-                for content in &tool_result.content {
-                    if let Some(text) = &content.text {
-                        // Indent multi-line output
-                        for line in text.lines() {
-                            println!("║   {}", line);
-                        }
-                    }
-                    if let Some(data) = &content.data {
-                        let preview: String = data.chars().take(200).collect();
-                        println!(
-                            "║   [Binary data: {} bytes, preview: {}...]",
-                            data.len(),
-                            preview
-                        );
-                    }
+        timeout: Option<Duration>,
+        stream_tx: Option<mpsc::UnboundedSender<String>>,
+        respond_to: oneshot::Sender<Result<McpToolResult, String>>,
+    ) {
+        let connection = self.connection(&server_id).await;
+        tokio::spawn(async move {
+            let result = match connection {
+                Ok(connection) => {
+                    execute_tool(&connection, &server_id, &tool_name, arguments, timeout, stream_tx)
+                        .await
                 }
-                println!("╚══════════════════════════════════════════════════════════════\n");
-
-                Ok(tool_result)
-            }
-            Err(e) => {
-                // Log the error
-                println!("\n╔══════════════════════════════════════════════════════════════");
-                println!("║ MCP TOOL CALL ERROR");
-                println!("╠══════════════════════════════════════════════════════════════");
-                println!("║ Server: {}", server_id);
-                println!("║ Tool:   {}", tool_name);
-                println!("║ Error:  {}", e);
-                println!("╚══════════════════════════════════════════════════════════════\n");
-
-                Err(e)
-            }
-        }
+                Err(e) => {
+                    println!("║ ERROR: Server {} not connected", server_id);
+                    Err(e)
+                }
+            };
+            let _ = respond_to.send(result);
+        });
     }
 
     async fn get_all_tool_descriptions(&self) -> Vec<(String, Vec<McpTool>)> {
         let connections = self.connections.read().await;
 
-        let result: Vec<_> = connections
-            .iter()
-            .filter(|(_, conn)| conn.config.enabled)
-            .map(|(id, conn)| (id.clone(), conn.tools.clone()))
-            .collect();
+        let mut result = Vec::new();
+        let mut modes = Vec::new();
+        for (id, conn) in connections.iter() {
+            let conn = conn.lock().await;
+            if conn.config.enabled {
+                result.push((id.clone(), conn.tools.clone()));
+                modes.push(if conn.config.defer_tools {
+                    "DEFERRED"
+                } else {
+                    "ACTIVE"
+                });
+            }
+        }
 
         println!(
             "McpHostActor: get_all_tool_descriptions returning {} servers",
             result.len()
         );
-        for (id, tools) in &result {
-            if let Some(conn) = connections.get(id) {
-                let mode = if conn.config.defer_tools {
-                    "DEFERRED"
-                } else {
-                    "ACTIVE"
-                };
-                println!(
-                    "McpHostActor:   {} has {} tools [{}]",
-                    id,
-                    tools.len(),
-                    mode
-                );
-            } else {
-                println!("McpHostActor:   {} has {} tools", id, tools.len());
-            }
+        for ((id, tools), mode) in result.iter().zip(&modes) {
+            println!(
+                "McpHostActor:   {} has {} tools [{}]",
+                id,
+                tools.len(),
+                mode
+            );
         }
 
         result
@@ -863,11 +1102,18 @@ impl McpToolRouterActor {
             command, config.args
         );
 
-        let mut cmd = Command::new(&command);
-        cmd.args(&config.args);
+        // Fail before spawning if a configured root is missing
+        let roots = resolve_roots(&config)?;
+
+        // Expand ${VAR} references only for the spawn, so secrets stay out of logs
+        let mut resolved = config.clone();
+        resolve_env_vars(&mut resolved)?;
+
+        let mut cmd = Command::new(resolved.command.as_deref().unwrap_or(&command));
+        cmd.args(&resolved.args);
 
         // Set environment variables
-        for (key, value) in &config.env {
+        for (key, value) in &resolved.env {
             cmd.env(key, value);
         }
 
@@ -927,6 +1173,8 @@ impl McpToolRouterActor {
             stdout_lines,
             tools: Vec::new(),
             request_id: 0,
+            roots,
+            stream_tx: None,
         };
 
         // Wait for server to start
@@ -978,16 +1226,7 @@ impl McpToolRouterActor {
         let init_result = connection
             .send_request(
                 "initialize",
-                Some(json!({
-                    "protocolVersion": "2024-11-05",
-                    "capabilities": {
-                        "tools": {}
-                    },
-                    "clientInfo": {
-                        "name": "plugable-chat-test",
-                        "version": "0.1.0"
-                    }
-                })),
+                Some(initialize_params("plugable-chat-test", &connection.roots)),
             )
             .await;
 
@@ -1058,3 +1297,192 @@ impl McpToolRouterActor {
         Ok(tools)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with_roots(roots: Vec<String>) -> McpServerConfig {
+        let mut config = McpServerConfig::new("files".to_string(), "Files".to_string());
+        config.roots = roots;
+        config
+    }
+
+    #[test]
+    fn test_initialize_payload_includes_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config_with_roots(vec![dir.path().to_string_lossy().into_owned()]);
+
+        let roots = resolve_roots(&config).unwrap();
+        let params = initialize_params("plugable-chat", &roots);
+
+        let declared = &params["capabilities"]["roots"];
+        assert_eq!(declared["listChanged"], json!(false));
+        let uris: Vec<&str> = declared["roots"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["uri"].as_str().unwrap())
+            .collect();
+        let expected = tauri::Url::from_file_path(dir.path().canonicalize().unwrap()).unwrap();
+        assert_eq!(uris, vec![expected.as_str()]);
+        assert_eq!(params["capabilities"]["tools"], json!({}));
+    }
+
+    #[test]
+    fn test_initialize_payload_omits_roots_when_unset() {
+        let config = config_with_roots(Vec::new());
+        let roots = resolve_roots(&config).unwrap();
+        let params = initialize_params("plugable-chat", &roots);
+        assert!(params["capabilities"].get("roots").is_none());
+    }
+
+    #[test]
+    fn test_missing_root_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("nope").to_string_lossy().into_owned();
+        let err = resolve_roots(&config_with_roots(vec![missing.clone()])).unwrap_err();
+        assert!(err.contains(&missing), "{}", err);
+        assert!(err.contains("does not exist"), "{}", err);
+    }
+
+    #[test]
+    fn test_roots_list_request_is_answered() {
+        let roots = vec![json!({ "uri": "file:///tmp/project", "name": "project" })];
+
+        let reply = server_request_reply(
+            &json!({ "jsonrpc": "2.0", "id": 7, "method": "roots/list" }),
+            &roots,
+        )
+        .unwrap();
+        assert_eq!(reply["id"], json!(7));
+        assert_eq!(reply["result"]["roots"], json!(roots));
+
+        let unknown = server_request_reply(
+            &json!({ "jsonrpc": "2.0", "id": 8, "method": "sampling/createMessage" }),
+            &roots,
+        )
+        .unwrap();
+        assert_eq!(unknown["error"]["code"], json!(-32601));
+
+        // Responses and notifications are not server requests
+        assert!(
+            server_request_reply(&json!({ "jsonrpc": "2.0", "id": 1, "result": {} }), &roots)
+                .is_none()
+        );
+        assert!(server_request_reply(
+            &json!({ "jsonrpc": "2.0", "method": "notifications/progress" }),
+            &roots
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_stream_progress_chunk_reads_our_progress_messages() {
+        let progress = |token: &str, message: Option<&str>| {
+            let mut params = json!({ "progressToken": token, "progress": 1 });
+            if let Some(message) = message {
+                params["message"] = json!(message);
+            }
+            json!({ "jsonrpc": "2.0", "method": "notifications/progress", "params": params })
+        };
+
+        assert_eq!(
+            stream_progress_chunk(&progress(STREAM_PROGRESS_TOKEN, Some("line 1"))).as_deref(),
+            Some("line 1")
+        );
+        // Other tokens, progress without a message and other messages carry no chunk
+        assert!(stream_progress_chunk(&progress("other", Some("line 1"))).is_none());
+        assert!(stream_progress_chunk(&progress(STREAM_PROGRESS_TOKEN, None)).is_none());
+        assert!(stream_progress_chunk(&json!({ "jsonrpc": "2.0", "id": 1, "result": {} })).is_none());
+    }
+
+    /// Connection to a process that reads requests and never answers
+    #[cfg(unix)]
+    fn hung_connection(id: &str) -> McpServerConnection {
+        let mut process = Command::new("sleep")
+            .arg("60")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .unwrap();
+        McpServerConnection {
+            config: McpServerConfig::new(id.to_string(), id.to_string()),
+            stdin: process.stdin.take().unwrap(),
+            stdout_lines: BufReader::new(process.stdout.take().unwrap()).lines(),
+            process,
+            tools: Vec::new(),
+            request_id: 0,
+            roots: Vec::new(),
+            stream_tx: None,
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hung_server_does_not_block_other_messages() {
+        let (tx, rx) = mpsc::channel(8);
+        let actor = McpToolRouterActor::new(rx);
+        actor.connections.write().await.insert(
+            "hung".to_string(),
+            Arc::new(Mutex::new(hung_connection("hung"))),
+        );
+        tokio::spawn(actor.run());
+
+        let (health_tx, _health_rx) = oneshot::channel();
+        tx.send(McpHostMsg::CheckServerHealth {
+            server_id: "hung".to_string(),
+            respond_to: health_tx,
+        })
+        .await
+        .unwrap();
+        let (call_tx, _call_rx) = oneshot::channel();
+        tx.send(McpHostMsg::ExecuteTool {
+            server_id: "hung".to_string(),
+            tool_name: "wait".to_string(),
+            arguments: json!({}),
+            timeout: None,
+            respond_to: call_tx,
+        })
+        .await
+        .unwrap();
+
+        // Both requests wait on the hung server; the loop still answers
+        let (status_tx, status_rx) = oneshot::channel();
+        tx.send(McpHostMsg::GetServerStatus {
+            server_id: "hung".to_string(),
+            respond_to: status_tx,
+        })
+        .await
+        .unwrap();
+        let connected = tokio::time::timeout(Duration::from_secs(5), status_rx)
+            .await
+            .expect("status blocked behind the hung server")
+            .unwrap();
+        assert!(connected);
+    }
+
+    /// A server that answers after 40s, slower than the fixed protocol timeout
+    async fn slow_response() -> Result<JsonRpcResponse, String> {
+        tokio::time::sleep(Duration::from_secs(40)).await;
+        Ok(JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(3),
+            result: Some(json!({ "content": [] })),
+            error: None,
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_tool_call_waits_for_configured_timeout() {
+        let result = await_response(slow_response(), Some(Duration::from_secs(60)), 3).await;
+        assert_eq!(result.unwrap(), json!({ "content": [] }));
+
+        let result = await_response(slow_response(), None, 3).await;
+        assert!(result.is_ok());
+
+        let result = await_response(slow_response(), Some(REQUEST_TIMEOUT), 3).await;
+        assert_eq!(result.unwrap_err(), "Request timed out waiting for id 3");
+    }
+}
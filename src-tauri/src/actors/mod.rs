@@ -3,6 +3,7 @@ pub mod embedded_sqlite_actor;
 pub mod foundry;
 pub mod mcp_host_actor;
 pub mod python_actor;
+pub mod python_wasm;
 pub mod rag;
 pub mod schema_vector_actor;
 pub mod startup_actor;
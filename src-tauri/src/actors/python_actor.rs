@@ -5,29 +5,42 @@
 //! - Isolated Python execution with restricted builtins/imports
 //! - Tool calls via the tool_call() Python function that pauses execution
 //! - Batch tool call model: execution pauses on tool_call(), host executes, resumes
+//! - Streamed tool calls: tool_call_stream() on a streamable tool re-runs the
+//!   code with the chunks received so far each time the server sends more
 //! - Memory and output size limits for security
 //!
 //! The architecture uses a double-sandbox model:
 //! - Inner: RustPython with restricted Python environment
-//! - Outer: (Optional) WASM sandbox via Wasmtime for additional isolation
+//! - Outer: (Optional) WASM sandbox via Wasmtime for additional isolation,
+//!   selected with `python_sandbox_backend` (see `python_wasm`)
 
 use fastembed::TextEmbedding;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, oneshot, RwLock};
 
+use crate::actors::mcp_host_actor::McpToolResult;
+use crate::actors::python_wasm::WasmSandbox;
+use crate::agentic_loop::mcp_tool_timeout_message;
 use crate::protocol::McpHostMsg;
+use crate::settings::{AppSettings, PythonSandboxBackend};
 use crate::tool_registry::SharedToolRegistry;
+use crate::tool_execution::render_mcp_tool_result;
 use crate::tools::code_execution::{
     CodeExecutionInput, CodeExecutionOutput, ExecutionContext, InnerCallResult, InnerToolCall,
+    PythonToolCallRecord,
 };
 use crate::tools::tool_search::{ToolSearchExecutor, ToolSearchInput};
 
 // Import the python-sandbox crate
-use python_sandbox::protocol::{ExecutionRequest, ExecutionStatus, ToolCallResult, ToolInfo};
+use python_sandbox::protocol::{
+    ExecutionRequest, ExecutionStatus, ToolCallResult, ToolInfo, ToolStreamChunks,
+    DEFAULT_OUTPUT_LIMIT_BYTES,
+};
+use python_sandbox::watchdog::CancelToken;
 
 /// Maximum output size (in bytes)
 const MAX_OUTPUT_SIZE: usize = 1024 * 1024; // 1MB
@@ -40,7 +53,9 @@ pub enum PythonMsg {
     /// Execute Python code
     ExecuteSandboxedCode {
         input: CodeExecutionInput,
-        context: ExecutionContext,
+        context: Box<ExecutionContext>,
+        /// Receives stdout chunks as the code prints them
+        progress_tx: Option<mpsc::Sender<String>>,
         respond_to: oneshot::Sender<Result<CodeExecutionOutput, String>>,
     },
     /// Handle an inner tool call from executing Python code
@@ -50,6 +65,10 @@ pub enum PythonMsg {
     },
     /// Check if the Python runtime is available
     HealthCheck { respond_to: oneshot::Sender<bool> },
+    /// Drop the persisted variables of a session (called at turn start/end)
+    ResetSession { key: String },
+    /// Interrupt the execution with this ID if it is still running
+    Cancel { exec_id: String },
 }
 
 /// Event emitted when Python code makes a tool call
@@ -69,6 +88,93 @@ pub struct PythonToolCallResult {
     pub error: Option<String>,
 }
 
+/// A `tool_call_stream` call in flight: chunks arrive on `chunk_rx` until the
+/// MCP host answers on `result_rx`
+struct ToolStream {
+    tool_name: String,
+    server_id: String,
+    arguments: Value,
+    chunk_rx: mpsc::UnboundedReceiver<String>,
+    result_rx: oneshot::Receiver<Result<McpToolResult, String>>,
+    /// When to give up, with the server's tool timeout it came from
+    deadline: Option<(tokio::time::Instant, Duration)>,
+    /// Everything received so far, re-sent in full with each round
+    delivered: ToolStreamChunks,
+}
+
+impl ToolStream {
+    /// Wait until the tool has sent more chunks or finished
+    async fn wait_for_chunks(&mut self) {
+        let before = self.delivered.chunks.len();
+        while !self.delivered.done && self.delivered.chunks.len() == before {
+            // Chunks are sent before the result, so take them first
+            let next = async {
+                tokio::select! {
+                    biased;
+                    Some(chunk) = self.chunk_rx.recv() => Ok(chunk),
+                    result = &mut self.result_rx => Err(result),
+                }
+            };
+            let event = match self.deadline {
+                Some((deadline, limit)) => match tokio::time::timeout_at(deadline, next).await {
+                    Ok(event) => event,
+                    Err(_) => {
+                        self.delivered.done = true;
+                        self.delivered.error =
+                            Some(mcp_tool_timeout_message(&self.tool_name, limit));
+                        return;
+                    }
+                },
+                None => next.await,
+            };
+            match event {
+                Ok(chunk) => self.delivered.chunks.push(Value::String(chunk)),
+                Err(result) => self.finish(result),
+            }
+        }
+    }
+
+    /// Record the tool's final result. A server that sent no progress chunks
+    /// still streams: each content part of its result becomes a chunk.
+    fn finish(&mut self, result: Result<Result<McpToolResult, String>, oneshot::error::RecvError>) {
+        while let Ok(chunk) = self.chunk_rx.try_recv() {
+            self.delivered.chunks.push(Value::String(chunk));
+        }
+        self.delivered.done = true;
+        match result {
+            Ok(Ok(result)) if result.is_error => {
+                self.delivered.error = Some(render_mcp_tool_result(&result).0);
+            }
+            Ok(Ok(result)) => {
+                if self.delivered.chunks.is_empty() {
+                    self.delivered.chunks = result
+                        .content
+                        .into_iter()
+                        .map(|content| {
+                            let part = McpToolResult {
+                                content: vec![content],
+                                is_error: false,
+                            };
+                            Value::String(render_mcp_tool_result(&part).0)
+                        })
+                        .collect();
+                }
+            }
+            Ok(Err(err)) => self.delivered.error = Some(err),
+            Err(_) => self.delivered.error = Some("Tool call response channel closed".to_string()),
+        }
+    }
+
+    /// The whole stream as one tool call result, for the call record
+    fn as_call_result(&self) -> ToolCallResult {
+        ToolCallResult {
+            success: self.delivered.error.is_none(),
+            result: Value::Array(self.delivered.chunks.clone()),
+            error: self.delivered.error.clone(),
+        }
+    }
+}
+
 /// The Python actor that manages code execution
 pub struct PythonSandboxActor {
     python_msg_rx: mpsc::Receiver<PythonMsg>,
@@ -78,6 +184,14 @@ pub struct PythonSandboxActor {
     /// Channel to send tool calls to the orchestrator for execution
     tool_call_tx: mpsc::Sender<(InnerToolCall, oneshot::Sender<InnerCallResult>)>,
     tool_call_rx: mpsc::Receiver<(InnerToolCall, oneshot::Sender<InnerCallResult>)>,
+    /// Plain-data globals persisted per session key (see `ExecutionContext::session_key`)
+    sessions: HashMap<String, serde_json::Map<String, Value>>,
+    /// Execution requests that arrived while another execution was running
+    deferred: VecDeque<PythonMsg>,
+    /// Read for `python_sandbox_backend` at the start of each execution
+    settings: Arc<RwLock<AppSettings>>,
+    /// Compiled WASM sandbox, loaded on first use of the wasm backend
+    wasm_sandbox: Option<Arc<WasmSandbox>>,
 }
 
 impl PythonSandboxActor {
@@ -86,6 +200,7 @@ impl PythonSandboxActor {
         tool_registry: SharedToolRegistry,
         mcp_host_tx: mpsc::Sender<McpHostMsg>,
         embedding_model: Arc<RwLock<Option<Arc<TextEmbedding>>>>,
+        settings: Arc<RwLock<AppSettings>>,
     ) -> Self {
         let (tool_call_tx, tool_call_rx) = mpsc::channel(32);
 
@@ -96,27 +211,36 @@ impl PythonSandboxActor {
             embedding_model,
             tool_call_tx,
             tool_call_rx,
+            sessions: HashMap::new(),
+            deferred: VecDeque::new(),
+            settings,
+            wasm_sandbox: None,
+        }
+    }
+
+    /// The WASM sandbox, compiled on first use
+    fn wasm_sandbox(&mut self) -> Result<Arc<WasmSandbox>, String> {
+        if let Some(sandbox) = &self.wasm_sandbox {
+            return Ok(sandbox.clone());
         }
+        let sandbox = Arc::new(
+            WasmSandbox::load().map_err(|e| format!("WASM sandbox unavailable: {}", e))?,
+        );
+        self.wasm_sandbox = Some(sandbox.clone());
+        Ok(sandbox)
     }
 
     pub async fn run(mut self) {
 
         loop {
+            if let Some(msg) = self.deferred.pop_front() {
+                self.handle_idle_message(msg).await;
+                continue;
+            }
             tokio::select! {
                 msg = self.python_msg_rx.recv() => {
                     match msg {
-                        Some(PythonMsg::ExecuteSandboxedCode { input, context, respond_to }) => {
-                            let result = self.execute_code(input, context).await;
-                            let _ = respond_to.send(result);
-                        }
-                        Some(PythonMsg::InnerToolCall { call, respond_to }) => {
-                            // Forward to the orchestrator for execution
-                            let _ = self.tool_call_tx.send((call, respond_to)).await;
-                        }
-                        Some(PythonMsg::HealthCheck { respond_to }) => {
-                            // RustPython sandbox is always available
-                            let _ = respond_to.send(true);
-                        }
+                        Some(msg) => self.handle_idle_message(msg).await,
                         None => {
                             println!("[PythonActor] Channel closed, shutting down");
                             break;
@@ -129,6 +253,61 @@ impl PythonSandboxActor {
         println!("[PythonActor] Shutdown complete");
     }
 
+    /// Handle a message while no execution is running
+    async fn handle_idle_message(&mut self, msg: PythonMsg) {
+        match msg {
+            PythonMsg::ExecuteSandboxedCode {
+                input,
+                context,
+                progress_tx,
+                respond_to,
+            } => {
+                let result = self.execute_code(input, *context, progress_tx).await;
+                let _ = respond_to.send(result);
+            }
+            PythonMsg::Cancel { exec_id } => {
+                println!("[PythonActor] Cancel for {} ignored: not running", exec_id);
+            }
+            other => self.handle_message_during_execution(other, None).await,
+        }
+    }
+
+    /// Handle a message that arrived while an execution is running.
+    /// New executions are queued until the current one finishes.
+    async fn handle_message_during_execution(
+        &mut self,
+        msg: PythonMsg,
+        running: Option<(&str, &CancelToken)>,
+    ) {
+        match msg {
+            PythonMsg::ExecuteSandboxedCode { .. } => self.deferred.push_back(msg),
+            PythonMsg::Cancel { exec_id } => match running {
+                Some((running_id, cancel)) if running_id == exec_id => {
+                    println!("[PythonActor] Cancelling execution {}", exec_id);
+                    cancel.cancel();
+                }
+                _ => println!("[PythonActor] Cancel for {} ignored: not running", exec_id),
+            },
+            PythonMsg::InnerToolCall { call, respond_to } => {
+                // Forward to the orchestrator for execution
+                let _ = self.tool_call_tx.send((call, respond_to)).await;
+            }
+            PythonMsg::HealthCheck { respond_to } => {
+                // RustPython sandbox is always available
+                let _ = respond_to.send(true);
+            }
+            PythonMsg::ResetSession { key } => {
+                if let Some(vars) = self.sessions.remove(&key) {
+                    println!(
+                        "[PythonActor] Reset session {} ({} variables dropped)",
+                        key,
+                        vars.len()
+                    );
+                }
+            }
+        }
+    }
+
     /// Get the channel for receiving tool calls that need execution
     pub fn get_tool_call_receiver(
         &mut self,
@@ -145,6 +324,7 @@ impl PythonSandboxActor {
         &mut self,
         input: CodeExecutionInput,
         context: ExecutionContext,
+        progress_tx: Option<mpsc::Sender<String>>,
     ) -> Result<CodeExecutionOutput, String> {
         use std::io::Write;
 
@@ -181,6 +361,13 @@ impl PythonSandboxActor {
         println!("[PythonActor] Input validated");
         let _ = std::io::stdout().flush();
 
+        let backend = self.settings.read().await.python_sandbox_backend;
+        let wasm_sandbox = match backend {
+            PythonSandboxBackend::Wasm => Some(self.wasm_sandbox()?),
+            PythonSandboxBackend::InProcess => None,
+        };
+        println!("[PythonActor] Sandbox backend: {}", backend.as_str());
+
         // Convert available tools from context into ToolInfo for sandbox
         let module_by_server: std::collections::HashMap<String, String> = context
             .tool_modules
@@ -203,22 +390,48 @@ impl PythonSandboxActor {
                     description: schema.description.clone(),
                     parameters: schema.parameters.clone(),
                     python_module,
+                    streamable: schema.streamable,
                 }
             })
             .collect();
 
+        // Session mode: variables from earlier executions in this turn come first,
+        // explicitly provided context (e.g. tabular data) wins on name clashes
+        let request_context = match context.session_key.as_ref().and_then(|k| self.sessions.get(k)) {
+            Some(session_vars) if !session_vars.is_empty() => {
+                let mut merged = session_vars.clone();
+                if let Some(Value::Object(explicit)) = input.context.clone() {
+                    merged.extend(explicit);
+                }
+                println!(
+                    "[PythonActor] Restoring {} session variables",
+                    session_vars.len()
+                );
+                Some(Value::Object(merged))
+            }
+            _ => input.context.clone(),
+        };
+
         // Build the initial request with tool modules from context
         let mut request = ExecutionRequest {
             code: input.code.clone(),
-            context: input.context.clone(),
+            context: request_context,
             tool_results: HashMap::new(),
             available_tools,
             tool_modules: context.tool_modules.clone(),
+            timeout_ms: input.timeout_ms,
+            memory_limit_bytes: context.memory_limit_bytes,
+            output_limit_bytes: Some(DEFAULT_OUTPUT_LIMIT_BYTES),
+            allowed_modules: context.allowed_modules.clone(),
+            capture_session: context.session_key.is_some(),
+            tool_streams: HashMap::new(),
         };
 
         let mut output = CodeExecutionOutput::default();
-        let mut total_tool_calls = 0;
+        // Open tool_call_stream calls, keyed by stream call id
+        let mut streams: HashMap<String, ToolStream> = HashMap::new();
         let mut round = 0;
+        let cancel = CancelToken::new();
 
         // Batch execution loop: run code, execute any pending tool calls, repeat
         loop {
@@ -230,6 +443,30 @@ impl PythonSandboxActor {
                 ));
             }
 
+            // A cancel that landed while tool calls ran stops before the next round
+            if cancel.is_cancelled() {
+                output.success = false;
+                output.stderr.push_str(&format!(
+                    "\nError: {}",
+                    python_sandbox::watchdog::CANCELLED_MESSAGE
+                ));
+                break;
+            }
+
+            // Each round re-runs the program, so give it only what is left of the budget
+            if let Some(timeout_ms) = input.timeout_ms {
+                let elapsed_ms = start_time.elapsed().as_millis() as u64;
+                if elapsed_ms >= timeout_ms {
+                    output.success = false;
+                    output.stderr.push_str(&format!(
+                        "\nError: Execution timed out after {} ms",
+                        timeout_ms
+                    ));
+                    break;
+                }
+                request.timeout_ms = Some(timeout_ms - elapsed_ms);
+            }
+
             println!(
                 "[PythonActor] ========== Execution round {} ==========",
                 round
@@ -239,10 +476,59 @@ impl PythonSandboxActor {
 
             // Execute the Python code using the sandbox on a blocking thread so we don't stall async tasks/UI
             let request_for_exec = request.clone();
-            let result =
-                tokio::task::spawn_blocking(move || python_sandbox::execute(&request_for_exec))
-                    .await
-                    .map_err(|e| format!("python_sandbox::execute join error: {}", e))?;
+            let progress_for_exec = progress_tx.clone();
+            let cancel_for_exec = cancel.clone();
+            // Each round re-runs the program from the top, so its output starts with
+            // what earlier rounds already printed; only the rest is streamed
+            let streamed_before = output.stdout.clone();
+            let mut exec_handle = match wasm_sandbox.clone() {
+                // Output can't be streamed out of the instance, so it arrives once the round ends
+                Some(sandbox) => tokio::task::spawn_blocking(move || {
+                    let result = sandbox.execute(&request_for_exec, &cancel_for_exec);
+                    let new_stdout = new_round_stdout(&streamed_before, &result.stdout);
+                    if let Some(tx) = progress_for_exec.filter(|_| !new_stdout.is_empty()) {
+                        let _ = tx.blocking_send(new_stdout.to_string());
+                    }
+                    result
+                }),
+                None => tokio::task::spawn_blocking(move || {
+                    let mut skip = streamed_before.len();
+                    let on_stdout = progress_for_exec.map(|tx| {
+                        Box::new(move |chunk: &str| {
+                            let new_part = if skip >= chunk.len() {
+                                skip -= chunk.len();
+                                ""
+                            } else {
+                                let part = chunk.get(skip..).unwrap_or(chunk);
+                                skip = 0;
+                                part
+                            };
+                            if !new_part.is_empty() {
+                                let _ = tx.blocking_send(new_part.to_string());
+                            }
+                        }) as python_sandbox::sandbox::StdoutListener
+                    });
+                    python_sandbox::execute_with_cancel(
+                        &request_for_exec,
+                        &cancel_for_exec,
+                        on_stdout,
+                    )
+                }),
+            };
+
+            // Keep serving messages while the code runs so a Cancel can reach it
+            let result = loop {
+                tokio::select! {
+                    joined = &mut exec_handle => {
+                        break joined
+                            .map_err(|e| format!("python_sandbox::execute join error: {}", e))?;
+                    }
+                    Some(msg) = self.python_msg_rx.recv() => {
+                        self.handle_message_during_execution(msg, Some((&context.exec_id, &cancel)))
+                            .await;
+                    }
+                }
+            };
 
             println!("[PythonActor] python_sandbox::execute returned");
             println!("[PythonActor] Status: {:?}", result.status);
@@ -254,18 +540,37 @@ impl PythonSandboxActor {
             if !result.stderr.is_empty() {
                 println!("[PythonActor] stderr: {}", result.stderr);
             }
+            if result.stdout_truncated || result.stderr_truncated {
+                println!("[PythonActor] Output hit the capture limit and was truncated");
+            }
             let _ = std::io::stdout().flush();
 
-            // Accumulate stdout/stderr
-            output.stdout.push_str(&result.stdout);
+            // Accumulate stdout/stderr (stdout without the replay of earlier rounds)
+            let new_stdout = new_round_stdout(&output.stdout, &result.stdout).to_string();
+            output.stdout.push_str(&new_stdout);
             output.stderr.push_str(&result.stderr);
-            total_tool_calls += result.tool_calls_made;
 
             match result.status {
                 ExecutionStatus::Complete => {
                     // Execution finished successfully
                     output.success = true;
                     output.result = result.result;
+                    output.return_value = result.return_value;
+                    output.final_answer = result.final_answer;
+                    if let (Some(key), Some(mut globals)) =
+                        (context.session_key.as_ref(), result.session_globals)
+                    {
+                        // Explicit context is re-sent every call; only keep what the code produced
+                        if let Some(Value::Object(explicit)) = &input.context {
+                            globals.retain(|name, _| !explicit.contains_key(name));
+                        }
+                        println!(
+                            "[PythonActor] Persisting {} session variables for {}",
+                            globals.len(),
+                            key
+                        );
+                        self.sessions.insert(key.clone(), globals);
+                    }
                     break;
                 }
                 ExecutionStatus::ToolCallsPending => {
@@ -284,7 +589,43 @@ impl PythonSandboxActor {
 
                     // Execute each pending tool call
                     let mut tool_results = HashMap::new();
+                    // Rounds that only wait for more chunks of open streams don't count
+                    let mut only_open_streams = true;
                     for pending_call in result.pending_calls {
+                        if pending_call.stream {
+                            let stream = match streams.entry(pending_call.id.clone()) {
+                                std::collections::hash_map::Entry::Occupied(entry) => {
+                                    entry.into_mut()
+                                }
+                                std::collections::hash_map::Entry::Vacant(entry) => {
+                                    only_open_streams = false;
+                                    let stream = self
+                                        .open_tool_stream(
+                                            &pending_call.tool_name,
+                                            &pending_call.server_id,
+                                            &pending_call.arguments,
+                                        )
+                                        .await;
+                                    entry.insert(stream)
+                                }
+                            };
+                            stream.wait_for_chunks().await;
+                            request
+                                .tool_streams
+                                .insert(pending_call.id.clone(), stream.delivered.clone());
+                            if stream.delivered.done {
+                                output.tool_calls_made.push(PythonToolCallRecord::new(
+                                    &stream.tool_name,
+                                    &stream.server_id,
+                                    &stream.arguments,
+                                    &stream.as_call_result(),
+                                ));
+                                streams.remove(&pending_call.id);
+                            }
+                            continue;
+                        }
+
+                        only_open_streams = false;
                         let call_result = self
                             .execute_tool_call(
                                 &pending_call.tool_name,
@@ -292,13 +633,23 @@ impl PythonSandboxActor {
                                 &pending_call.arguments,
                             )
                             .await;
+                        output.tool_calls_made.push(PythonToolCallRecord::new(
+                            &pending_call.tool_name,
+                            &pending_call.server_id,
+                            &pending_call.arguments,
+                            &call_result,
+                        ));
 
                         // Use tool_name as key for matching (simpler than full ID tracking)
                         tool_results.insert(pending_call.tool_name.clone(), call_result);
                     }
 
-                    // Update request with tool results for next round
-                    request.tool_results = tool_results;
+                    // Update request with tool results for next round, keeping earlier
+                    // rounds' results since stream rounds re-run the calls before them
+                    request.tool_results.extend(tool_results);
+                    if only_open_streams {
+                        round -= 1;
+                    }
                 }
                 ExecutionStatus::Error(msg) => {
                     output.success = false;
@@ -314,7 +665,6 @@ impl PythonSandboxActor {
             }
         }
 
-        output.tool_calls_made = total_tool_calls;
         output.duration_ms = start_time.elapsed().as_millis() as u64;
 
         // Truncate output if too large
@@ -326,7 +676,9 @@ impl PythonSandboxActor {
         println!("[PythonActor] ========== EXECUTE CODE COMPLETE ==========");
         println!(
             "[PythonActor] Success: {}, Duration: {}ms, Tool calls: {}",
-            output.success, output.duration_ms, output.tool_calls_made
+            output.success,
+            output.duration_ms,
+            output.tool_calls_made.len()
         );
         println!(
             "[PythonActor] Final stdout ({} chars): {}",
@@ -338,6 +690,46 @@ impl PythonSandboxActor {
         Ok(output)
     }
 
+    /// Start a `tool_call_stream` call on the MCP host. The sandbox only opens
+    /// streams for tools the registry marks as streamable; others are buffered
+    /// through `tool_call`.
+    async fn open_tool_stream(
+        &self,
+        tool_name: &str,
+        server_id: &str,
+        arguments: &Value,
+    ) -> ToolStream {
+        println!("[PythonActor] Streaming tool: {}::{}", server_id, tool_name);
+        let timeout = self.settings.read().await.mcp_tool_timeout_for(server_id);
+        let (chunk_tx, chunk_rx) = mpsc::unbounded_channel();
+        let (tx, result_rx) = oneshot::channel();
+        let mut stream = ToolStream {
+            tool_name: tool_name.to_string(),
+            server_id: server_id.to_string(),
+            arguments: arguments.clone(),
+            chunk_rx,
+            result_rx,
+            deadline: timeout.map(|limit| (tokio::time::Instant::now() + limit, limit)),
+            delivered: ToolStreamChunks::default(),
+        };
+        if let Err(e) = self
+            .mcp_host_tx
+            .send(McpHostMsg::ExecuteToolStream {
+                server_id: server_id.to_string(),
+                tool_name: tool_name.to_string(),
+                arguments: arguments.clone(),
+                timeout,
+                chunk_tx,
+                respond_to: tx,
+            })
+            .await
+        {
+            stream.delivered.done = true;
+            stream.delivered.error = Some(format!("Failed to send tool call: {}", e));
+        }
+        stream
+    }
+
     /// Execute a single tool call via the orchestrator
     async fn execute_tool_call(
         &mut self,
@@ -353,12 +745,14 @@ impl PythonSandboxActor {
                 if let Some(query) = arguments.get("relevant_to").and_then(|v| v.as_str()) {
                     ToolSearchInput {
                         queries: vec![query.to_string()],
+                        query_weights: Vec::new(),
                         top_k: 3,
                     }
                 } else {
                     serde_json::from_value::<ToolSearchInput>(arguments.clone()).unwrap_or(
                         ToolSearchInput {
                             queries: vec![],
+                            query_weights: Vec::new(),
                             top_k: 3,
                         },
                     )
@@ -387,7 +781,11 @@ impl PythonSandboxActor {
             }
         }
 
-        // MCP tool execution
+        // MCP tool execution, abandoned after the server's timeout like direct calls
+        let timeout = {
+            let settings = self.settings.read().await;
+            settings.mcp_tool_timeout_for(server_id)
+        };
         let (tx, rx) = oneshot::channel();
         if let Err(e) = self
             .mcp_host_tx
@@ -395,6 +793,7 @@ impl PythonSandboxActor {
                 server_id: server_id.to_string(),
                 tool_name: tool_name.to_string(),
                 arguments: arguments.clone(),
+                timeout,
                 respond_to: tx,
             })
             .await
@@ -406,15 +805,28 @@ impl PythonSandboxActor {
             };
         }
 
-        match rx.await {
+        let response = match timeout {
+            Some(limit) => match tokio::time::timeout(limit, rx).await {
+                Ok(response) => response,
+                Err(_) => {
+                    println!(
+                        "[PythonActor] Tool {}::{} timed out after {}s",
+                        server_id,
+                        tool_name,
+                        limit.as_secs()
+                    );
+                    return ToolCallResult {
+                        success: false,
+                        result: Value::Null,
+                        error: Some(mcp_tool_timeout_message(tool_name, limit)),
+                    };
+                }
+            },
+            None => rx.await,
+        };
+        match response {
             Ok(Ok(result)) => {
-                let text = result
-                    .content
-                    .iter()
-                    .filter_map(|c| c.text.as_ref())
-                    .map(|s| s.as_str())
-                    .collect::<Vec<_>>()
-                    .join("\n");
+                let (text, _images) = render_mcp_tool_result(&result);
 
                 ToolCallResult {
                     success: !result.is_error,
@@ -436,6 +848,18 @@ impl PythonSandboxActor {
     }
 }
 
+/// Stdout a round added. Rounds re-run the program, so a round's stdout repeats
+/// what earlier rounds printed; the part after the common prefix is new.
+fn new_round_stdout<'a>(printed: &str, round_stdout: &'a str) -> &'a str {
+    let common = printed
+        .char_indices()
+        .zip(round_stdout.chars())
+        .take_while(|((_, a), b)| a == b)
+        .last()
+        .map_or(0, |((i, c), _)| i + c.len_utf8());
+    &round_stdout[common..]
+}
+
 /// Create a channel for communicating with the Python actor
 pub fn create_python_channel() -> (mpsc::Sender<PythonMsg>, mpsc::Receiver<PythonMsg>) {
     mpsc::channel(32)
@@ -444,6 +868,7 @@ pub fn create_python_channel() -> (mpsc::Sender<PythonMsg>, mpsc::Receiver<Pytho
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::actors::mcp_host_actor::McpToolResult;
     use crate::tool_registry::ToolRegistry;
     use crate::tools::code_execution::CodeExecutionExecutor;
 
@@ -455,17 +880,21 @@ mod tests {
         let (mcp_tx, _mcp_rx) = mpsc::channel(1);
         let embedding_model: Arc<RwLock<Option<Arc<TextEmbedding>>>> = Arc::new(RwLock::new(None));
 
-        let mut actor = PythonSandboxActor::new(rx, registry, mcp_tx, embedding_model);
+        let settings = Arc::new(RwLock::new(AppSettings::default()));
+
+        let mut actor = PythonSandboxActor::new(rx, registry, mcp_tx, embedding_model, settings);
 
         let input = CodeExecutionInput {
             code: vec!["x = 1 + 2".to_string(), "print(x)".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
 
         let context =
             CodeExecutionExecutor::create_context("test".to_string(), vec![], None, vec![]);
 
-        let result = actor.execute_code(input, context).await;
+        let result = actor.execute_code(input, context, None).await;
 
         assert!(result.is_ok());
         let output = result.unwrap();
@@ -473,6 +902,249 @@ mod tests {
         assert!(output.stdout.contains("3"));
     }
 
+    #[tokio::test]
+    async fn test_execution_records_tool_calls_made() {
+        use crate::actors::mcp_host_actor::McpContent;
+        use crate::protocol::ToolSchema;
+
+        let (_tx, rx) = create_python_channel();
+        let registry = std::sync::Arc::new(tokio::sync::RwLock::new(ToolRegistry::new()));
+        let (mcp_tx, mut mcp_rx) = mpsc::channel(1);
+        let embedding_model: Arc<RwLock<Option<Arc<TextEmbedding>>>> = Arc::new(RwLock::new(None));
+        let settings = Arc::new(RwLock::new(AppSettings::default()));
+
+        // Mock MCP host: get_weather always answers "Sunny"
+        tokio::spawn(async move {
+            while let Some(msg) = mcp_rx.recv().await {
+                if let McpHostMsg::ExecuteTool { respond_to, .. } = msg {
+                    let _ = respond_to.send(Ok(McpToolResult {
+                        content: vec![McpContent {
+                            content_type: "text".to_string(),
+                            text: Some("Sunny".to_string()),
+                            data: None,
+                            mime_type: None,
+                            uri: None,
+                            resource: None,
+                        }],
+                        is_error: false,
+                    }));
+                }
+            }
+        });
+
+        let mut actor = PythonSandboxActor::new(rx, registry, mcp_tx, embedding_model, settings);
+        let input = CodeExecutionInput {
+            code: vec![
+                "weather = tool_call('get_weather', city='Oslo')".to_string(),
+                "print(weather)".to_string(),
+            ],
+            context: None,
+            timeout_ms: None,
+            validate_only: false,
+        };
+        let context = CodeExecutionExecutor::create_context(
+            "test".to_string(),
+            vec![("weather".to_string(), ToolSchema::new("get_weather"))],
+            None,
+            vec![],
+        );
+
+        let output = actor.execute_code(input, context, None).await.unwrap();
+
+        assert!(output.success, "stderr: {}", output.stderr);
+        assert!(output.stdout.contains("Sunny"));
+        assert_eq!(
+            output.tool_calls_made,
+            vec![PythonToolCallRecord {
+                name: "get_weather".to_string(),
+                server: "weather".to_string(),
+                arguments: serde_json::json!({ "city": "Oslo" }),
+                result_summary: "Sunny".to_string(),
+                is_error: false,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_rounds_stream_each_line_once() {
+        use crate::actors::mcp_host_actor::McpContent;
+        use crate::protocol::ToolSchema;
+
+        let (_tx, rx) = create_python_channel();
+        let registry = std::sync::Arc::new(tokio::sync::RwLock::new(ToolRegistry::new()));
+        let (mcp_tx, mut mcp_rx) = mpsc::channel(1);
+        let embedding_model: Arc<RwLock<Option<Arc<TextEmbedding>>>> = Arc::new(RwLock::new(None));
+        let settings = Arc::new(RwLock::new(AppSettings::default()));
+
+        tokio::spawn(async move {
+            while let Some(msg) = mcp_rx.recv().await {
+                if let McpHostMsg::ExecuteTool { respond_to, .. } = msg {
+                    let _ = respond_to.send(Ok(McpToolResult {
+                        content: vec![McpContent {
+                            content_type: "text".to_string(),
+                            text: Some("Sunny".to_string()),
+                            data: None,
+                            mime_type: None,
+                            uri: None,
+                            resource: None,
+                        }],
+                        is_error: false,
+                    }));
+                }
+            }
+        });
+
+        let mut actor = PythonSandboxActor::new(rx, registry, mcp_tx, embedding_model, settings);
+        // The tool call ends round 1; round 2 re-runs the program with its result
+        let input = CodeExecutionInput {
+            code: vec![
+                "print('checking')".to_string(),
+                "weather = tool_call('get_weather', city='Oslo')".to_string(),
+                "print(weather)".to_string(),
+            ],
+            context: None,
+            timeout_ms: None,
+            validate_only: false,
+        };
+        let context = CodeExecutionExecutor::create_context(
+            "test".to_string(),
+            vec![("weather".to_string(), ToolSchema::new("get_weather"))],
+            None,
+            vec![],
+        );
+        let (progress_tx, mut progress_rx) = mpsc::channel(32);
+
+        let output = actor
+            .execute_code(input, context, Some(progress_tx))
+            .await
+            .unwrap();
+
+        assert!(output.success, "stderr: {}", output.stderr);
+        assert_eq!(output.tool_calls_made.len(), 1);
+        let mut streamed = String::new();
+        while let Ok(chunk) = progress_rx.try_recv() {
+            streamed.push_str(&chunk);
+        }
+        assert_eq!(streamed, "checking\nSunny\n");
+        assert_eq!(output.stdout, "checking\nSunny\n");
+    }
+
+    #[tokio::test]
+    async fn test_tool_call_stream_delivers_chunks_as_they_arrive() {
+        use crate::protocol::ToolSchema;
+
+        let (_tx, rx) = create_python_channel();
+        let registry = std::sync::Arc::new(tokio::sync::RwLock::new(ToolRegistry::new()));
+        let (mcp_tx, mut mcp_rx) = mpsc::channel(1);
+        let embedding_model: Arc<RwLock<Option<Arc<TextEmbedding>>>> = Arc::new(RwLock::new(None));
+        let settings = Arc::new(RwLock::new(AppSettings::default()));
+
+        // Mock streaming tool: two chunks, then the last one only once the code
+        // has printed the first two, so the test hangs unless chunks arrive early
+        let (printed_tx, printed_rx) = oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let mut printed_rx = Some(printed_rx);
+            while let Some(msg) = mcp_rx.recv().await {
+                if let McpHostMsg::ExecuteToolStream {
+                    chunk_tx,
+                    respond_to,
+                    ..
+                } = msg
+                {
+                    let _ = chunk_tx.send("a".to_string());
+                    let _ = chunk_tx.send("b".to_string());
+                    if let Some(printed) = printed_rx.take() {
+                        let _ = printed.await;
+                    }
+                    let _ = chunk_tx.send("c".to_string());
+                    let _ = respond_to.send(Ok(McpToolResult {
+                        content: Vec::new(),
+                        is_error: false,
+                    }));
+                }
+            }
+        });
+        let (progress_tx, mut progress_rx) = mpsc::channel::<String>(32);
+        let printed = tokio::spawn(async move {
+            let mut printed_tx = Some(printed_tx);
+            let mut streamed = String::new();
+            while let Some(chunk) = progress_rx.recv().await {
+                streamed.push_str(&chunk);
+                if streamed.contains("got b\n") {
+                    if let Some(tx) = printed_tx.take() {
+                        let _ = tx.send(());
+                    }
+                }
+            }
+            streamed
+        });
+
+        let mut actor = PythonSandboxActor::new(rx, registry, mcp_tx, embedding_model, settings);
+        let input = CodeExecutionInput {
+            code: vec![
+                "for line in tool_call_stream('tail_log', path='app.log'):".to_string(),
+                "    print('got', line)".to_string(),
+            ],
+            context: None,
+            timeout_ms: None,
+            validate_only: false,
+        };
+        let tail_log = ToolSchema {
+            streamable: true,
+            ..ToolSchema::new("tail_log")
+        };
+        let context = CodeExecutionExecutor::create_context(
+            "test".to_string(),
+            vec![("logs".to_string(), tail_log)],
+            None,
+            vec![],
+        );
+
+        let output = tokio::time::timeout(
+            Duration::from_secs(30),
+            actor.execute_code(input, context, Some(progress_tx)),
+        )
+        .await
+        .expect("chunks were not delivered before the tool finished")
+        .unwrap();
+
+        assert!(output.success, "stderr: {}", output.stderr);
+        assert_eq!(output.stdout, "got a\ngot b\ngot c\n");
+        assert_eq!(printed.await.unwrap(), "got a\ngot b\ngot c\n");
+        assert_eq!(output.tool_calls_made.len(), 1);
+        assert_eq!(output.tool_calls_made[0].name, "tail_log");
+        assert!(!output.tool_calls_made[0].is_error);
+    }
+
+    #[test]
+    fn test_new_round_stdout_skips_the_replayed_prefix() {
+        assert_eq!(new_round_stdout("", "a\n"), "a\n");
+        assert_eq!(new_round_stdout("a\n", "a\nb\n"), "b\n");
+        // A replay that diverges keeps everything after the common part
+        assert_eq!(new_round_stdout("ré 1\n", "ré 2\n"), "2\n");
+    }
+
+    #[tokio::test]
+    async fn test_mcp_tool_call_times_out() {
+        let (_tx, rx) = create_python_channel();
+        let registry = std::sync::Arc::new(tokio::sync::RwLock::new(ToolRegistry::new()));
+        // The MCP host never answers
+        let (mcp_tx, _mcp_rx) = mpsc::channel(1);
+        let embedding_model: Arc<RwLock<Option<Arc<TextEmbedding>>>> = Arc::new(RwLock::new(None));
+        let settings = Arc::new(RwLock::new(AppSettings {
+            mcp_tool_timeout_secs: 1,
+            ..AppSettings::default()
+        }));
+
+        let mut actor = PythonSandboxActor::new(rx, registry, mcp_tx, embedding_model, settings);
+        let result = actor
+            .execute_tool_call("slow", "srv", &serde_json::json!({}))
+            .await;
+
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("timed out"));
+    }
+
     #[tokio::test]
     async fn test_code_validation() {
         use crate::tools::code_execution::CodeExecutionExecutor;
@@ -480,12 +1152,16 @@ mod tests {
         let good = CodeExecutionInput {
             code: vec!["x = 1".to_string(), "print(x)".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         assert!(CodeExecutionExecutor::validate_input(&good).is_ok());
 
         let bad = CodeExecutionInput {
             code: vec!["import os".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         assert!(CodeExecutionExecutor::validate_input(&bad).is_err());
     }
@@ -0,0 +1,346 @@
+//! WASM backend for python_execution.
+//!
+//! Runs the python-sandbox crate compiled to `wasm32-wasip1` (build.rs places it
+//! in `wasm/python-sandbox.wasm`) inside Wasmtime, as the outer layer of the
+//! double sandbox. Each execution round gets a fresh instance whose WASI context
+//! has no preopened directories, environment, arguments or sockets, so code that
+//! escapes the RustPython restrictions still cannot reach the filesystem or the
+//! network. Requests and results cross the boundary as the same JSON
+//! `ExecutionRequest` / `ExecutionResult` the in-process backend uses, so the
+//! tool_call() round trips work unchanged.
+
+use python_sandbox::memory::MEMORY_LIMIT_MESSAGE;
+use python_sandbox::protocol::{ExecutionRequest, ExecutionResult};
+use python_sandbox::watchdog::{timeout_message, CancelToken, CANCELLED_MESSAGE};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use wasmtime::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+use wasmtime_wasi::pipe::MemoryOutputPipe;
+use wasmtime_wasi::preview1::WasiP1Ctx;
+use wasmtime_wasi::WasiCtxBuilder;
+
+/// File name of the compiled sandbox module
+pub const WASM_MODULE_FILE: &str = "python-sandbox.wasm";
+
+/// Environment variable pointing at a sandbox module to use instead of the bundled one
+pub const WASM_MODULE_ENV: &str = "PLUGABLE_PYTHON_SANDBOX_WASM";
+
+/// Ceiling on an instance's linear memory (interpreter, frozen stdlib and user data)
+const MAX_LINEAR_MEMORY_BYTES: usize = 1024 * 1024 * 1024;
+
+/// Bytes of the instance's own stderr kept to diagnose an aborted run
+const GUEST_STDERR_CAPACITY: usize = 4096;
+
+/// What Rust prints when an allocation fails, right before aborting
+const ALLOCATION_FAILED_PREFIX: &str = "memory allocation of ";
+
+/// How often the watcher thread checks the deadline and the cancel token
+const WATCH_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Per-instance store data
+struct InstanceState {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+}
+
+/// Places the sandbox module is looked for, in order: the env override, next to
+/// the executable, then the crate's `wasm/` directory (dev builds)
+pub fn candidate_module_paths(env_override: Option<&str>, exe_dir: Option<&Path>) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(path) = env_override.map(str::trim).filter(|p| !p.is_empty()) {
+        paths.push(PathBuf::from(path));
+    }
+    if let Some(dir) = exe_dir {
+        paths.push(dir.join("wasm").join(WASM_MODULE_FILE));
+        paths.push(dir.join(WASM_MODULE_FILE));
+    }
+    paths.push(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("wasm")
+            .join(WASM_MODULE_FILE),
+    );
+    paths
+}
+
+/// The compiled sandbox module, instantiated once per execution round
+pub struct WasmSandbox {
+    engine: Engine,
+    module: Module,
+    linker: Linker<InstanceState>,
+}
+
+impl WasmSandbox {
+    /// Find and compile the sandbox module
+    pub fn load() -> Result<Self, String> {
+        let env_override = std::env::var(WASM_MODULE_ENV).ok();
+        let exe_dir = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf));
+        let candidates = candidate_module_paths(env_override.as_deref(), exe_dir.as_deref());
+        let path = candidates.iter().find(|p| p.is_file()).ok_or_else(|| {
+            format!(
+                "{} not found (looked in: {}). Build it with `rustup target add wasm32-wasip1` \
+                and a rebuild, or set python_sandbox_backend to in_process.",
+                WASM_MODULE_FILE,
+                candidates
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?;
+        Self::from_file(path)
+    }
+
+    /// Compile the sandbox module at `path`
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let mut config = Config::new();
+        config.epoch_interruption(true);
+        let engine =
+            Engine::new(&config).map_err(|e| format!("Failed to create WASM engine: {}", e))?;
+        let module = Module::from_file(&engine, path)
+            .map_err(|e| format!("Failed to load {}: {}", path.display(), e))?;
+        let mut linker = Linker::new(&engine);
+        wasmtime_wasi::preview1::add_to_linker_sync(&mut linker, |state: &mut InstanceState| {
+            &mut state.wasi
+        })
+        .map_err(|e| format!("Failed to link WASI: {}", e))?;
+        println!("[PythonWasm] Loaded sandbox module from {}", path.display());
+        Ok(Self {
+            engine,
+            module,
+            linker,
+        })
+    }
+
+    /// Run one execution round in a fresh instance. Blocks until the code
+    /// finishes, the request's timeout passes or `cancel` is tripped.
+    pub fn execute(&self, request: &ExecutionRequest, cancel: &CancelToken) -> ExecutionResult {
+        let finished = Arc::new(AtomicBool::new(false));
+        let watcher = self.spawn_watcher(request.timeout_ms, cancel.clone(), finished.clone());
+        let guest_stderr = MemoryOutputPipe::new(GUEST_STDERR_CAPACITY);
+        let outcome = self.run_instance(request, &guest_stderr);
+        finished.store(true, Ordering::SeqCst);
+        let _ = watcher.join();
+
+        match outcome {
+            Ok(result) => result,
+            // The guest refuses allocations past the memory ceiling, which aborts it
+            Err(_) if aborted_on_allocation(&guest_stderr.contents()) => {
+                ExecutionResult::error(MEMORY_LIMIT_MESSAGE)
+            }
+            Err(e) if matches!(e.downcast_ref::<Trap>(), Some(Trap::Interrupt)) => {
+                if cancel.is_cancelled() {
+                    ExecutionResult::error(CANCELLED_MESSAGE)
+                } else {
+                    ExecutionResult::error(timeout_message(request.timeout_ms.unwrap_or_default()))
+                }
+            }
+            Err(e) => ExecutionResult::error(format!("WASM sandbox error: {:#}", e)),
+        }
+    }
+
+    /// Interrupt the running instance once the deadline passes or the run is cancelled
+    fn spawn_watcher(
+        &self,
+        timeout_ms: Option<u64>,
+        cancel: CancelToken,
+        finished: Arc<AtomicBool>,
+    ) -> JoinHandle<()> {
+        let engine = self.engine.clone();
+        let deadline = timeout_ms.map(|ms| Instant::now() + Duration::from_millis(ms));
+        std::thread::spawn(move || {
+            while !finished.load(Ordering::SeqCst) {
+                if cancel.is_cancelled() || deadline.is_some_and(|d| Instant::now() >= d) {
+                    engine.increment_epoch();
+                    return;
+                }
+                std::thread::sleep(WATCH_INTERVAL);
+            }
+        })
+    }
+
+    fn run_instance(
+        &self,
+        request: &ExecutionRequest,
+        guest_stderr: &MemoryOutputPipe,
+    ) -> wasmtime::Result<ExecutionResult> {
+        let request_json = serde_json::to_vec(request)?;
+
+        // No preopened directories, environment, arguments or sockets
+        let state = InstanceState {
+            wasi: WasiCtxBuilder::new().stderr(guest_stderr.clone()).build_p1(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(MAX_LINEAR_MEMORY_BYTES)
+                .build(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_epoch_deadline(1);
+
+        let instance = self.linker.instantiate(&mut store, &self.module)?;
+        // Reactor modules run their static initializers here
+        if let Ok(initialize) = instance.get_typed_func::<(), ()>(&mut store, "_initialize") {
+            initialize.call(&mut store, ())?;
+        }
+
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| wasmtime::Error::msg("sandbox module exports no memory"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc_memory")?;
+        let free = instance.get_typed_func::<(i32, i32), ()>(&mut store, "free_memory")?;
+        let execute = instance.get_typed_func::<(i32, i32), i32>(&mut store, "execute_python")?;
+
+        let request_len = i32::try_from(request_json.len())?;
+        let request_ptr = alloc.call(&mut store, request_len)?;
+        memory.write(&mut store, request_ptr as u32 as usize, &request_json)?;
+        let result_ptr = execute.call(&mut store, (request_ptr, request_len))?;
+        free.call(&mut store, (request_ptr, request_len))?;
+        if result_ptr == 0 {
+            return Err(wasmtime::Error::msg("sandbox returned no result"));
+        }
+
+        // The result is a little-endian u32 length followed by the JSON
+        let result_ptr = result_ptr as u32 as usize;
+        let mut len_bytes = [0u8; 4];
+        memory.read(&store, result_ptr, &mut len_bytes)?;
+        let mut result_json = vec![0u8; u32::from_le_bytes(len_bytes) as usize];
+        memory.read(&store, result_ptr + 4, &mut result_json)?;
+        Ok(serde_json::from_slice(&result_json)?)
+    }
+}
+
+/// Whether the instance's stderr shows it aborted on a failed allocation
+fn aborted_on_allocation(stderr: &[u8]) -> bool {
+    String::from_utf8_lossy(stderr).contains(ALLOCATION_FAILED_PREFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use python_sandbox::protocol::ExecutionStatus;
+
+    #[test]
+    fn test_candidate_module_paths() {
+        let paths = candidate_module_paths(Some("/opt/sandbox.wasm"), Some(Path::new("/app")));
+        assert_eq!(paths[0], PathBuf::from("/opt/sandbox.wasm"));
+        assert_eq!(
+            paths[1],
+            Path::new("/app").join("wasm").join(WASM_MODULE_FILE)
+        );
+        assert_eq!(paths[2], Path::new("/app").join(WASM_MODULE_FILE));
+        assert!(paths[3].ends_with(Path::new("wasm").join(WASM_MODULE_FILE)));
+
+        // A blank override is ignored
+        assert_eq!(candidate_module_paths(Some("  "), None).len(), 1);
+    }
+
+    /// A stand-in for the sandbox module with the same exports. `execute_python`
+    /// runs `body`, which must leave a result pointer on the stack.
+    fn stub_sandbox(dir: &Path, body: &str, result_json: &str) -> WasmSandbox {
+        let len = (result_json.len() as u32).to_le_bytes();
+        let data: String = len
+            .iter()
+            .map(|b| format!("\\{:02x}", b))
+            .chain(std::iter::once(result_json.replace('"', "\\\"")))
+            .collect();
+        let wat = format!(
+            r#"(module
+                (memory (export "memory") 1)
+                (global $next (mut i32) (i32.const 4096))
+                (func (export "alloc_memory") (param i32) (result i32)
+                    global.get $next
+                    global.get $next
+                    local.get 0
+                    i32.add
+                    global.set $next)
+                (func (export "free_memory") (param i32 i32))
+                (func (export "execute_python") (param i32 i32) (result i32) {})
+                (data (i32.const 1024) "{}"))"#,
+            body, data
+        );
+        let path = dir.join("stub.wat");
+        std::fs::write(&path, wat).unwrap();
+        WasmSandbox::from_file(&path).unwrap()
+    }
+
+    #[test]
+    fn test_execute_returns_module_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let sandbox = stub_sandbox(
+            dir.path(),
+            "i32.const 1024",
+            r#"{"status":"Complete","stdout":"hi","stderr":"","result":null,"pending_calls":[],"tool_calls_made":0}"#,
+        );
+
+        let request = ExecutionRequest::new(vec!["print('hi')".to_string()]);
+        let result = sandbox.execute(&request, &CancelToken::new());
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        assert_eq!(result.stdout, "hi");
+    }
+
+    #[test]
+    fn test_execute_interrupts_runaway_code() {
+        let dir = tempfile::tempdir().unwrap();
+        let sandbox = stub_sandbox(dir.path(), "(loop $spin (br $spin)) i32.const 0", "{}");
+
+        let mut request = ExecutionRequest::new(vec!["while True: pass".to_string()]);
+        request.timeout_ms = Some(50);
+        let result = sandbox.execute(&request, &CancelToken::new());
+        assert_eq!(result.status, ExecutionStatus::Error(timeout_message(50)));
+
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let result = sandbox.execute(&ExecutionRequest::new(Vec::new()), &cancel);
+        assert_eq!(
+            result.status,
+            ExecutionStatus::Error(CANCELLED_MESSAGE.to_string())
+        );
+    }
+
+    #[test]
+    fn test_refused_allocation_is_a_memory_error() {
+        let message = "memory allocation of 8000000000 bytes failed";
+        let wat = format!(
+            r#"(module
+                (import "wasi_snapshot_preview1" "fd_write"
+                    (func $fd_write (param i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (func (export "alloc_memory") (param i32) (result i32) i32.const 4096)
+                (func (export "free_memory") (param i32 i32))
+                (func (export "execute_python") (param i32 i32) (result i32)
+                    (i32.store (i32.const 0) (i32.const 1024))
+                    (i32.store (i32.const 4) (i32.const {}))
+                    (drop (call $fd_write (i32.const 2) (i32.const 0) (i32.const 1) (i32.const 8)))
+                    unreachable)
+                (data (i32.const 1024) "{}"))"#,
+            message.len(),
+            message
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abort.wat");
+        std::fs::write(&path, wat).unwrap();
+        let sandbox = WasmSandbox::from_file(&path).unwrap();
+
+        let request = ExecutionRequest::new(vec!["data = [0] * 10**9".to_string()]);
+        let result = sandbox.execute(&request, &CancelToken::new());
+        assert_eq!(
+            result.status,
+            ExecutionStatus::Error(MEMORY_LIMIT_MESSAGE.to_string())
+        );
+        assert!(!aborted_on_allocation(b"thread panicked"));
+    }
+
+    #[test]
+    fn test_invalid_module_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(WASM_MODULE_FILE);
+        std::fs::write(&path, b"not a wasm module").unwrap();
+
+        let err = WasmSandbox::from_file(&path).err().unwrap();
+        assert!(err.starts_with("Failed to load"));
+    }
+}
@@ -15,10 +15,11 @@ use futures::StreamExt;
 use lancedb::query::{ExecutableQuery, QueryBase};
 use lancedb::{connect, Connection, Table};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot};
 
-use crate::is_verbose_logging_enabled;
 use crate::settings::{CachedColumnSchema, CachedTableSchema, SupportedDatabaseKind};
 
 /// Embedding dimension (matches fastembed BGE-Base-EN-v1.5)
@@ -47,6 +48,8 @@ pub enum SchemaVectorMsg {
         query_embedding: Vec<f32>,
         limit: usize,
         min_score: f32,
+        /// Only scan tables from these sources (empty = all sources)
+        source_ids: Vec<String>,
         respond_to: oneshot::Sender<Vec<SchemaSearchResult>>,
     },
     /// Search for relevant columns by embedding
@@ -61,12 +64,24 @@ pub enum SchemaVectorMsg {
         source_id: String,
         respond_to: oneshot::Sender<Vec<CachedTableSchema>>,
     },
+    /// Get the schema fingerprint of each cached table for a source, keyed by
+    /// fully-qualified name (see `table_fingerprint`)
+    GetTableFingerprints {
+        source_id: String,
+        respond_to: oneshot::Sender<HashMap<String, String>>,
+    },
     /// Enable or disable a specific table
     SetTableEnabled {
         table_fq_name: String,
         enabled: bool,
         respond_to: oneshot::Sender<Result<CachedTableSchema, String>>,
     },
+    /// Remove one table and its columns from the cache
+    DeleteTable {
+        source_id: String,
+        table_fq_name: String,
+        respond_to: oneshot::Sender<Result<(), String>>,
+    },
     /// Clear all schemas for a source
     ClearSource {
         source_id: String,
@@ -130,6 +145,26 @@ pub struct ColumnSearchResult {
     pub top_values: Vec<String>,
 }
 
+/// Cheap fingerprint of a table's shape: a hash of its column names and types
+/// in order. Refreshes compare it against the cached table to skip re-embedding
+/// tables whose columns haven't changed.
+pub fn table_fingerprint(schema: &CachedTableSchema) -> String {
+    let mut hasher = DefaultHasher::new();
+    for column in &schema.columns {
+        column.name.hash(&mut hasher);
+        column.data_type.hash(&mut hasher);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// Whether a freshly fetched table matches its cached fingerprint, so its
+/// cached embeddings can be reused
+pub fn is_table_unchanged(cached: &HashMap<String, String>, schema: &CachedTableSchema) -> bool {
+    cached
+        .get(&schema.fully_qualified_name)
+        .is_some_and(|fingerprint| *fingerprint == table_fingerprint(schema))
+}
+
 /// Schema Vector Store Actor
 pub struct SchemaVectorStoreActor {
     rx: mpsc::Receiver<SchemaVectorMsg>,
@@ -197,10 +232,17 @@ impl SchemaVectorStoreActor {
                         query_embedding,
                         limit,
                         min_score,
+                        source_ids,
                         respond_to,
                     } => {
-                        let results =
-                            search_tables(&tables_table, query_embedding, limit, min_score).await;
+                        let results = search_tables(
+                            &tables_table,
+                            query_embedding,
+                            limit,
+                            min_score,
+                            &source_ids,
+                        )
+                        .await;
                         let _ = respond_to.send(results);
                     }
                     SchemaVectorMsg::SearchColumns {
@@ -225,6 +267,17 @@ impl SchemaVectorStoreActor {
                         let results = get_tables_for_source(&tables_table, &source_id).await;
                         let _ = respond_to.send(results);
                     }
+                    SchemaVectorMsg::GetTableFingerprints {
+                        source_id,
+                        respond_to,
+                    } => {
+                        let fingerprints = get_tables_for_source(&tables_table, &source_id)
+                            .await
+                            .iter()
+                            .map(|t| (t.fully_qualified_name.clone(), table_fingerprint(t)))
+                            .collect();
+                        let _ = respond_to.send(fingerprints);
+                    }
                     SchemaVectorMsg::SetTableEnabled {
                         table_fq_name,
                         enabled,
@@ -242,6 +295,16 @@ impl SchemaVectorStoreActor {
                             clear_source(&tables_table, &columns_table, &source_id).await;
                         let _ = respond_to.send(result);
                     }
+                    SchemaVectorMsg::DeleteTable {
+                        source_id,
+                        table_fq_name,
+                        respond_to,
+                    } => {
+                        let result =
+                            delete_table(&tables_table, &columns_table, &source_id, &table_fq_name)
+                                .await;
+                        let _ = respond_to.send(result);
+                    }
                     SchemaVectorMsg::ClearAll { respond_to } => {
                         let result = clear_all(&tables_table, &columns_table).await;
                         let _ = respond_to.send(result);
@@ -577,11 +640,25 @@ async fn upsert_column_schema(
 
 // ========== Search Operations ==========
 
+/// Filter for table search: enabled tables, optionally scoped to some sources
+fn table_search_filter(source_ids: &[String]) -> String {
+    if source_ids.is_empty() {
+        return "enabled = true".to_string();
+    }
+    let source_filter = source_ids
+        .iter()
+        .map(|s| format!("source_id = '{}'", s.replace("'", "''")))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    format!("enabled = true AND ({})", source_filter)
+}
+
 async fn search_tables(
     table: &Table,
     query_embedding: Vec<f32>,
     limit: usize,
     min_score: f32,
+    source_ids: &[String],
 ) -> Vec<SchemaSearchResult> {
     let mut query_builder = match table.query().nearest_to(query_embedding) {
         Ok(q) => q,
@@ -591,7 +668,9 @@ async fn search_tables(
         }
     };
 
-    query_builder = query_builder.only_if("enabled = true");
+    // Filtering in the query (rather than afterwards) keeps other sources
+    // from using up the result limit
+    query_builder = query_builder.only_if(table_search_filter(source_ids));
 
     let mut results = vec![];
     let mut stream = match query_builder.limit(limit).execute().await {
@@ -645,9 +724,13 @@ async fn search_tables(
                 let score = 1.0 / (1.0 + distance);
 
                 if score < min_score {
-                    if is_verbose_logging_enabled() {
-                        println!("[SchemaSearch] Skipping result '{}' with low score {:.3} (min={})", fq.value(i), score, min_score);
-                    }
+                    crate::log_event!(
+                        "SchemaSearch",
+                        "low_score_skipped",
+                        table = fq.value(i),
+                        score = score,
+                        min_score = min_score,
+                    );
                     continue;
                 }
 
@@ -1197,6 +1280,43 @@ async fn clear_source(tables: &Table, columns: &Table, source_id: &str) -> Resul
     Ok(())
 }
 
+async fn delete_table(
+    tables: &Table,
+    columns: &Table,
+    source_id: &str,
+    table_fq_name: &str,
+) -> Result<(), String> {
+    let filter = format!(
+        "source_id = '{}' AND table_fq_name = '{}'",
+        source_id.replace('\'', "''"),
+        table_fq_name.replace('\'', "''")
+    );
+
+    let cached = tables
+        .count_rows(Some(filter.clone()))
+        .await
+        .map_err(|e| format!("Failed to look up table: {}", e))?;
+    if cached == 0 {
+        return Err(format!("Table not found in cache: {}", table_fq_name));
+    }
+
+    tables
+        .delete(&filter)
+        .await
+        .map_err(|e| format!("Failed to delete table: {}", e))?;
+
+    columns
+        .delete(&filter)
+        .await
+        .map_err(|e| format!("Failed to delete columns: {}", e))?;
+
+    println!(
+        "[SchemaVectorActor] Purged table {} from source {}",
+        table_fq_name, source_id
+    );
+    Ok(())
+}
+
 async fn clear_all(tables: &Table, columns: &Table) -> Result<(), String> {
     // Delete all records (LanceDB filter for "all" is tricky, use always-true filter)
     tables
@@ -1255,4 +1375,120 @@ mod tests {
 
         assert_eq!(result.column_name, parsed.column_name);
     }
+
+    #[test]
+    fn test_table_search_filter() {
+        assert_eq!(table_search_filter(&[]), "enabled = true");
+        assert_eq!(
+            table_search_filter(&["crm".to_string(), "o'brien".to_string()]),
+            "enabled = true AND (source_id = 'crm' OR source_id = 'o''brien')"
+        );
+    }
+
+    fn test_table(fq_name: &str, source_id: &str) -> CachedTableSchema {
+        CachedTableSchema {
+            fully_qualified_name: fq_name.to_string(),
+            source_id: source_id.to_string(),
+            kind: SupportedDatabaseKind::Sqlite,
+            sql_dialect: "SQLite".to_string(),
+            enabled: true,
+            columns: vec![],
+            primary_keys: vec![],
+            partition_columns: vec![],
+            cluster_columns: vec![],
+            description: None,
+        }
+    }
+
+    /// Unit vector along `axis`, nudged toward `lean` so scores are distinct
+    fn test_embedding(axis: usize, lean: usize, weight: f32) -> Vec<f32> {
+        let mut v = vec![0.0; SCHEMA_EMBEDDING_DIM as usize];
+        v[axis] = 1.0;
+        v[lean] += weight;
+        v
+    }
+
+    #[tokio::test]
+    async fn test_search_tables_scoped_to_source() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = connect(temp_dir.path().to_str().unwrap())
+            .execute()
+            .await
+            .unwrap();
+        let tables = ensure_tables_table_schema(&db).await;
+
+        // The warehouse table is the closer match for the query
+        upsert_table_schema(
+            &tables,
+            &test_table("warehouse.orders", "warehouse"),
+            test_embedding(0, 1, 0.0),
+        )
+        .await
+        .unwrap();
+        upsert_table_schema(
+            &tables,
+            &test_table("crm.accounts", "crm"),
+            test_embedding(1, 0, 0.5),
+        )
+        .await
+        .unwrap();
+        let query = test_embedding(0, 1, 0.0);
+
+        let unscoped = search_tables(&tables, query.clone(), 1, 0.0, &[]).await;
+        assert_eq!(unscoped.len(), 1);
+        assert_eq!(unscoped[0].source_id, "warehouse");
+
+        let scoped = search_tables(&tables, query, 1, 0.0, &["crm".to_string()]).await;
+        assert_eq!(scoped.len(), 1);
+        assert_eq!(scoped[0].table_fq_name, "crm.accounts");
+    }
+
+    fn test_column(name: &str, data_type: &str) -> CachedColumnSchema {
+        CachedColumnSchema {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable: true,
+            description: None,
+            special_attributes: vec![],
+            top_values: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_table_is_not_reembedded() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = connect(temp_dir.path().to_str().unwrap())
+            .execute()
+            .await
+            .unwrap();
+        let tables = ensure_tables_table_schema(&db).await;
+
+        let mut orders = test_table("main.orders", "shop");
+        orders.columns = vec![test_column("id", "INTEGER"), test_column("total", "REAL")];
+        upsert_table_schema(&tables, &orders, test_embedding(0, 1, 0.0))
+            .await
+            .unwrap();
+
+        let cached: HashMap<String, String> = get_tables_for_source(&tables, "shop")
+            .await
+            .iter()
+            .map(|t| (t.fully_qualified_name.clone(), table_fingerprint(t)))
+            .collect();
+
+        // Same columns fetched again: embeddings are reused
+        let mut refetched = orders.clone();
+        refetched.description = Some("Orders".to_string());
+        assert!(is_table_unchanged(&cached, &refetched));
+
+        // A changed column type or a new column means re-embedding
+        let mut retyped = orders.clone();
+        retyped.columns[1].data_type = "NUMERIC".to_string();
+        assert!(!is_table_unchanged(&cached, &retyped));
+        let mut widened = orders.clone();
+        widened.columns.push(test_column("placed_at", "TEXT"));
+        assert!(!is_table_unchanged(&cached, &widened));
+
+        // Tables that weren't cached before are always embedded
+        assert!(!is_table_unchanged(&cached, &test_table("main.customers", "shop")));
+    }
 }
@@ -12,11 +12,13 @@
 //! - `run_agentic_loop()` - Main loop execution
 //! - `detect_agentic_loop_action()` - Determine if response contains tool calls
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
 use fastembed::TextEmbedding;
+use regex::Regex;
 use serde_json::{json, Value};
 use tauri::Emitter;
 use tokio::sync::{mpsc, RwLock};
@@ -24,31 +26,55 @@ use tokio::sync::{mpsc, RwLock};
 use crate::actors::database_toolbox_actor::DatabaseToolboxMsg;
 use crate::actors::python_actor::PythonMsg;
 use crate::actors::schema_vector_actor::SchemaVectorMsg;
-use crate::app_state::{PendingApprovals, ToolApprovalDecision, TurnProgress};
-use crate::cli::is_builtin_tool;
+use crate::app_state::{
+    PendingApprovals, SharedToolDisables, ToolApprovalDecision, ToolDisableInfo, TurnProgress,
+};
 use crate::message_builders::{
-    create_assistant_message_with_tool_calls, create_native_tool_result_message,
-    should_use_native_tool_results,
+    create_assistant_message_with_tool_calls, create_native_tool_result_message, truncate_tool_result,
+    messages_for_request, should_use_native_tool_results,
 };
 use crate::model_profiles::resolve_profile;
 use crate::protocol::{
-    ChatMessage, FoundryMsg, McpHostMsg, ModelFamily, OpenAITool, ParsedToolCall,
-    ToolCallsPendingEvent, ToolExecutingEvent, ToolFormat, ToolHeartbeatEvent,
-    ToolLoopFinishedEvent, ToolResultEvent, VectorMsg,
+    ChatMessage, ChatRequest, ChatRetryEvent, EmptyResponseEvent, FoundryMsg, IterationMetrics, McpHostMsg,
+    ModelFamily, ModelState, OpenAITool, ParsedToolCall, PythonStdoutChunkEvent, ReasoningFormat,
+    ToolCallsPendingEvent, ToolCancelledEvent, ToolExecutingEvent, ToolFormat, ToolHeartbeatEvent,
+    ToolLoopFinishedEvent, ToolResultEvent, ToolTimeoutEvent, TurnMetrics, VectorMsg,
+};
+use crate::python_helpers::{
+    parse_final_answer_call, parse_python_execution_args, reconstruct_sql_from_malformed_args,
+    FINAL_ANSWER_TOOL,
 };
-use crate::python_helpers::{parse_python_execution_args, reconstruct_sql_from_malformed_args};
+use crate::reasoning::{split_reasoning, ReasoningStreamFilter, REASONING_EVENT};
 use crate::repetition_detector::RepetitionDetector;
-use crate::settings::{ChatFormatName, McpServerConfig, ToolCallFormatConfig, ToolCallFormatName};
+use crate::settings::{
+    ChatFormatName, McpServerConfig, ResultFormat, SqlResultFormat, ToolCallFormatConfig,
+    ToolCallFormatName,
+};
 use crate::state_machine::AgenticStateMachine;
+use crate::structured_output::{repair_prompt, validate_structured_response};
 use crate::tool_execution::{
     dispatch_tool_call_to_executor, execute_python_code, execute_tool_search,
-    resolve_mcp_server_for_tool,
+    resolve_mcp_server_for_tool, validate_tool_arguments,
+};
+use crate::tool_parsing::{
+    any_format_complete, detect_malformed_tool_call, format_is_complete, format_tool_result,
+    parse_tool_calls_for_model_profile, text_before_tool_call,
 };
-use crate::tool_parsing::{format_tool_result, parse_tool_calls_for_model_profile};
 use crate::tool_registry::SharedToolRegistry;
-use crate::tools::code_execution::CodeExecutionInput;
+use crate::tool_result_summary;
+use crate::tools::code_execution::{
+    CodeExecutionInput, CodeExecutionOutput, PythonToolCallRecord,
+};
+use crate::tools::extract::{TurnResultStore, EXTRACT_TOOL};
 use crate::tools::schema_search::{SchemaSearchExecutor, SchemaSearchInput};
+use crate::tools::sql_select::{
+    apply_row_window, ensure_read_only_select, parse_offset, render_sql_result_for_model,
+    DEFAULT_MAX_ROWS,
+};
 use crate::tools::tool_search::ToolSearchInput;
+use crate::turn_checkpoint::{
+    clear_checkpoint, save_checkpoint, ChatTurnRequest, TurnCheckpoint,
+};
 
 // ============================================================================
 // Types
@@ -61,6 +87,9 @@ pub enum AgenticLoopAction {
     Final { response: String },
     /// Tool calls were detected and should be executed
     ToolCalls { calls: Vec<ParsedToolCall> },
+    /// The model attempted a tool call but it could not be parsed (cut off,
+    /// broken JSON); `hint` says what was wrong so the model can retry
+    MalformedToolCall { hint: String },
 }
 
 /// Configuration for the agentic loop.
@@ -76,9 +105,12 @@ pub struct AgenticLoopConfig {
     pub title: String,
     /// Original user message that started this turn
     pub original_message: String,
+    /// Conversation before this turn as the chat stores it, never compacted
+    pub stored_history: Vec<ChatMessage>,
     /// Model name to use for inference
     pub model_name: String,
-    /// Reasoning effort level (e.g., "low", "medium", "high")
+    /// Reasoning effort level (e.g., "low", "medium", "high"); empty when the
+    /// model doesn't support reasoning_effort
     pub reasoning_effort: String,
     /// Whether Python tool mode is enabled (Code Mode)
     pub python_tool_mode: bool,
@@ -90,6 +122,8 @@ pub struct AgenticLoopConfig {
     pub allow_tool_search_for_python: bool,
     /// Maximum number of tools to return from tool_search
     pub tool_search_max_results: usize,
+    /// Minimum relevance score for a tool_search hit to be shown
+    pub tool_search_min_relevance: f32,
     /// System prompt for this turn
     pub turn_system_prompt: String,
     /// Default chat format
@@ -105,6 +139,69 @@ pub struct AgenticLoopConfig {
     /// Whether python_execution is included in native tools
     /// (enables fallback detection of ```python blocks when model doesn't use native format)
     pub python_execution_in_native_tools: bool,
+    /// Wall-clock timeout for each python_execution call in milliseconds (0 = no limit)
+    pub python_execution_timeout_ms: u64,
+    /// Whether Python variables persist across python_execution calls in this turn
+    pub python_session_enabled: bool,
+    /// Modules python_execution code may import (None = sandbox defaults)
+    pub python_allowed_modules: Option<Vec<String>>,
+    /// Ceiling on memory a python_execution run may allocate (None = no limit)
+    pub python_memory_limit_bytes: Option<usize>,
+    /// Maximum loop iterations before tool calling stops
+    pub max_tool_iterations: usize,
+    /// Maximum tool executions per turn across iterations (0 = no limit)
+    pub max_tool_calls_per_turn: usize,
+    /// Whether independent auto-approved tool calls in one iteration run concurrently
+    pub parallel_tool_calls: bool,
+    /// Whether each iteration's tool calls are approved together before any runs
+    pub batch_approval_mode: bool,
+    /// Whether text-format tool calls and results carry correlation id comments
+    pub tool_call_ids_in_text: bool,
+    /// Default MCP tool call timeout in seconds (0 = no limit); servers may override
+    pub mcp_tool_timeout_secs: u64,
+    /// Milliseconds between `tool-heartbeat` events while a tool runs (0 = no heartbeats)
+    pub tool_heartbeat_interval_ms: u64,
+    /// Seconds between provisional saves of the chat while the turn runs (0 = off)
+    pub chat_autosave_interval_secs: u64,
+    /// How python_execution results are rendered for the model
+    pub python_result_format: ResultFormat,
+    /// How sql_select results are rendered for the model
+    pub sql_result_format: SqlResultFormat,
+    /// Delimiters of the model's reasoning blocks, stripped before tool detection
+    pub reasoning_format: ReasoningFormat,
+    /// Whether reasoning is streamed as `reasoning-token` events instead of `chat-token`
+    pub reasoning_events_enabled: bool,
+    /// Whether all python blocks in a response run as one program
+    pub python_multi_block: bool,
+    /// Tool results longer than this many chars are truncated in the middle (0 = no limit)
+    pub max_tool_result_chars: usize,
+    /// Whether oversized MCP tool results are summarized before entering the history
+    pub summarize_large_tool_results: bool,
+    /// MCP tool results longer than this many chars are summarized when enabled
+    pub tool_result_summary_threshold_chars: usize,
+    /// Retries for a chat request that can't be sent or never starts streaming
+    pub foundry_chat_retries: u32,
+    /// Seconds to wait for the first token before a chat attempt counts as failed (0 = no limit)
+    pub first_token_timeout_secs: u64,
+    /// Whether the model accepts images (attachments and images returned by tools)
+    pub model_supports_vision: bool,
+    /// JSON schema the final answer must follow; validated, with one repair attempt
+    pub response_schema: Option<serde_json::Value>,
+    /// Whether the schema is also sent to the model as a structured-output constraint
+    pub response_schema_native: bool,
+    /// Sampling temperature override for every model call of the turn
+    pub temperature: Option<f32>,
+    /// Sampling seed for every model call of the turn
+    pub seed: Option<u64>,
+    /// Stop sequences for every model call of the turn (the enabled formats'
+    /// closing markers when `use_format_stop_sequences` is on)
+    pub stop_sequences: Vec<String>,
+    /// Original `chat` arguments, stored in checkpoints so the turn can be resumed
+    pub turn_request: ChatTurnRequest,
+    /// Iteration to start from (non-zero when resuming from a checkpoint)
+    pub start_iteration: usize,
+    /// File the turn checkpoint is mirrored to for resume_turn
+    pub checkpoint_path: PathBuf,
 }
 
 /// Actor handles and shared state for the agentic loop.
@@ -129,20 +226,42 @@ pub struct AgenticLoopHandles {
     pub embedding_model: Arc<RwLock<Option<Arc<TextEmbedding>>>>,
     /// Pending tool approvals map
     pub pending_approvals: PendingApprovals,
+    /// Chats whose tools the repeated-error check disabled
+    pub tool_disables: SharedToolDisables,
 }
 
 // ============================================================================
 // Action Detection
 // ============================================================================
 
+/// Whether a streaming response holds a complete tool call, so generation can
+/// stop before the model invents results. With `python_multi_block` a closed
+/// Python block is not the end of the program, so code mode never stops early.
+fn tool_call_complete_while_streaming(response: &str, config: &AgenticLoopConfig) -> bool {
+    if !config.python_multi_block {
+        return any_format_complete(response, &config.format_config);
+    }
+    config
+        .format_config
+        .enabled
+        .iter()
+        .filter(|fmt| **fmt != ToolCallFormatName::CodeMode)
+        .any(|fmt| format_is_complete(response, *fmt))
+}
+
 /// Decide whether a response should trigger tool execution or be treated as final text.
 ///
 /// This function examines the model's response and determines the next action:
-/// - If Python tool mode is enabled, looks for Python code blocks
+/// - A `final_answer` pseudo-tool call (as a lone Python program or in any enabled
+///   tool call format) ends the turn with its answer, skipping other detection
+/// - If Python tool mode is enabled, looks for Python code blocks (all of them,
+///   joined into one program, when `python_multi_block` is on)
 /// - If native tool calling includes python_execution, also checks for Python blocks
 ///   (models may output ```python blocks even when they should use native tool calls)
-/// - If tool call formats are enabled, parses for tool call syntax
+/// - If tool call formats are enabled, parses for tool call syntax, reporting
+///   attempts that are cut off or unparseable as `MalformedToolCall`
 /// - Otherwise, treats the response as final text
+#[allow(clippy::too_many_arguments)]
 pub fn detect_agentic_loop_action(
     model_response_text: &str,
     model_family: ModelFamily,
@@ -151,6 +270,7 @@ pub fn detect_agentic_loop_action(
     formats: &ToolCallFormatConfig,
     primary_format: ToolCallFormatName,
     python_execution_in_native_tools: bool,
+    python_multi_block: bool,
 ) -> AgenticLoopAction {
     let non_code_formats_enabled = formats.any_non_code();
 
@@ -159,10 +279,43 @@ pub fn detect_agentic_loop_action(
     // 2. python_execution is available as a native tool (model may output ```python blocks instead of calling the tool)
     let should_detect_python_blocks = python_tool_mode || python_execution_in_native_tools;
 
+    let python_program = if should_detect_python_blocks {
+        extract_python_program_from_response(model_response_text, python_multi_block)
+    } else {
+        None
+    };
+    let parsed_tool_calls = if non_code_formats_enabled {
+        parse_tool_calls_for_model_profile(
+            model_response_text,
+            model_family,
+            tool_format,
+            formats,
+            primary_format,
+        )
+    } else {
+        Vec::new()
+    };
+
+    // An explicit final_answer ends the turn before any other tool detection.
+    // In code mode it may also arrive unfenced as the entire response.
+    let final_answer = if should_detect_python_blocks {
+        python_program
+            .as_deref()
+            .and_then(parse_final_answer_call)
+            .or_else(|| parse_final_answer_call(&[model_response_text.trim().to_string()]))
+    } else {
+        None
+    }
+    .or_else(|| final_answer_from_tool_calls(&parsed_tool_calls, model_response_text));
+    if let Some(answer) = final_answer {
+        crate::log_event!("AgenticLoop", "final_answer_found");
+        return AgenticLoopAction::Final { response: answer };
+    }
+
     if should_detect_python_blocks {
-        if let Some(code_lines) = extract_python_program_from_response(model_response_text) {
+        if let Some(code_lines) = python_program {
             if is_valid_python_syntax_check(&code_lines) {
-                println!("[detect_agentic_loop_action] Found Python code block, converting to python_execution tool call");
+                crate::log_event!("AgenticLoop", "python_block_as_tool_call");
                 return AgenticLoopAction::ToolCalls {
                     calls: vec![ParsedToolCall {
                         server: "builtin".to_string(),
@@ -184,28 +337,55 @@ pub fn detect_agentic_loop_action(
     }
 
     if non_code_formats_enabled {
-        let parsed_tool_calls = parse_tool_calls_for_model_profile(
-            model_response_text,
-            model_family,
-            tool_format,
-            formats,
-            primary_format,
-        );
-        if !parsed_tool_calls.is_empty() {
-            return AgenticLoopAction::ToolCalls {
-                calls: parsed_tool_calls,
-            };
+        if let Some(hint) = detect_malformed_tool_call(model_response_text, formats) {
+            crate::log_event!("AgenticLoop", "malformed_tool_call", hint = hint);
+            return AgenticLoopAction::MalformedToolCall { hint };
         }
     }
 
+    if !parsed_tool_calls.is_empty() {
+        return AgenticLoopAction::ToolCalls {
+            calls: parsed_tool_calls,
+        };
+    }
+
     AgenticLoopAction::Final {
         response: model_response_text.to_string(),
     }
 }
 
+/// Corrective message sent back after a malformed tool call
+fn malformed_tool_call_message(hint: &str) -> String {
+    format!(
+        "Your tool call was malformed: {}. Send the complete tool call again in the same \
+format, with valid JSON and the closing tag, or answer directly if no tool is needed.",
+        hint
+    )
+}
+
+/// Answer carried by a `final_answer` call parsed from a tool call format.
+///
+/// Falls back to parsing the raw call as Python (for positional
+/// `final_answer("...")`), then to the whole response when no text is found.
+fn final_answer_from_tool_calls(calls: &[ParsedToolCall], response: &str) -> Option<String> {
+    let call = calls.iter().find(|call| call.tool == FINAL_ANSWER_TOOL)?;
+    let answer = ["answer", "text", "response"]
+        .iter()
+        .find_map(|key| call.arguments.get(*key)?.as_str())
+        .map(str::to_string)
+        .or_else(|| parse_final_answer_call(&[call.raw.trim().to_string()]))
+        .unwrap_or_else(|| response.to_string());
+    Some(answer)
+}
+
 /// Extract a Python program from the model response.
 /// Prefers fenced ```python blocks, falls back to treating the whole message as code.
-fn extract_python_program_from_response(response: &str) -> Option<Vec<String>> {
+/// With `multi_block`, every detected block is joined in order, separated by a
+/// blank line, so the blocks run as one program sharing state.
+fn extract_python_program_from_response(
+    response: &str,
+    multi_block: bool,
+) -> Option<Vec<String>> {
     use crate::tool_parsing::detect_python_code;
 
     let trimmed = response.trim();
@@ -215,6 +395,18 @@ fn extract_python_program_from_response(response: &str) -> Option<Vec<String>> {
 
     // Prefer structured detections (fenced blocks, explicit python, dedented snippets)
     let detected_blocks = detect_python_code(trimmed);
+    if multi_block && detected_blocks.len() > 1 {
+        crate::log_event!("AgenticLoop", "python_blocks_joined", blocks = detected_blocks.len());
+        let lines: Vec<String> = detected_blocks
+            .iter()
+            .map(|b| b.code.as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+            .lines()
+            .map(|l| l.trim_end_matches('\r').to_string())
+            .collect();
+        return Some(lines);
+    }
     if let Some(block) = detected_blocks
         .iter()
         .find(|b| b.explicit_python)
@@ -265,10 +457,7 @@ fn is_valid_python_syntax_check(code_lines: &[String]) -> bool {
     match ast::Suite::parse(&code, "<embedded>") {
         Ok(_) => true,
         Err(err) => {
-            println!(
-                "[PythonSyntaxCheck] Skipping python_execution due to parse error: {}",
-                err
-            );
+            crate::log_event!("PythonSyntaxCheck", "parse_error_skipped", error = err.to_string());
             false
         }
     }
@@ -286,9 +475,540 @@ fn is_valid_python_syntax_check(code_lines: &[String]) -> bool {
 // 4. Wait on rx with timeout
 // 5. Frontend calls approve_tool_call or reject_tool_call which sends to tx
 
+/// `(result_text, is_error, images, final_answer)` of one tool call; `images` are
+/// data URLs returned by an MCP tool, `final_answer` what python_execution code
+/// passed to `final_answer(...)`
+type ToolCallOutcome = (String, bool, Vec<String>, Option<String>);
+
+/// Run one approved tool call, emitting its `tool-executing`, `tool-heartbeat`
+/// and `tool-result` events.
+async fn execute_tool_call_with_events<R: tauri::Runtime>(
+    resolved_tool_call: &ParsedToolCall,
+    idx: usize,
+    total_calls: usize,
+    handles: &AgenticLoopHandles,
+    config: &AgenticLoopConfig,
+    app_handle: &tauri::AppHandle<R>,
+    loop_iteration_index: usize,
+) -> ToolCallOutcome {
+    // Emit executing event
+    let _ = app_handle.emit(
+        "tool-executing",
+        ToolExecutingEvent {
+            server: resolved_tool_call.server.clone(),
+            tool: resolved_tool_call.tool.clone(),
+            arguments: resolved_tool_call.arguments.clone(),
+        },
+    );
+
+    crate::log_event!(
+        "AgenticLoop",
+        "tool_call_processing",
+        index = idx + 1,
+        total = total_calls,
+        server = resolved_tool_call.server,
+        tool = resolved_tool_call.tool,
+    );
+
+    // Last line the tool printed, reported with each heartbeat
+    let last_progress: Arc<std::sync::Mutex<Option<String>>> = Arc::default();
+
+    // Start heartbeat (0 = disabled)
+    let heartbeat_stop_tx = if config.tool_heartbeat_interval_ms > 0 {
+        let heartbeat_handle = app_handle.clone();
+        let heartbeat_server = resolved_tool_call.server.clone();
+        let heartbeat_tool = resolved_tool_call.tool.clone();
+        let heartbeat_progress = last_progress.clone();
+        let heartbeat_period = Duration::from_millis(config.tool_heartbeat_interval_ms);
+        let (heartbeat_stop_tx, mut heartbeat_stop_rx) = tokio::sync::oneshot::channel::<()>();
+        let heartbeat_start = std::time::Instant::now();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(heartbeat_period);
+            let mut beat_counter: u64 = 0;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        beat_counter += 1;
+                        let progress = heartbeat_progress.lock().ok().and_then(|p| p.clone());
+                        let _ = heartbeat_handle.emit(
+                            "tool-heartbeat",
+                            ToolHeartbeatEvent {
+                                server: heartbeat_server.clone(),
+                                tool: heartbeat_tool.clone(),
+                                elapsed_ms: heartbeat_start.elapsed().as_millis() as u64,
+                                beat: beat_counter,
+                                progress,
+                            },
+                        );
+                    }
+                    _ = &mut heartbeat_stop_rx => {
+                        break;
+                    }
+                }
+            }
+        });
+        Some(heartbeat_stop_tx)
+    } else {
+        None
+    };
+
+    // Forward python_execution stdout to the frontend while the code runs
+    let stdout_tx = if resolved_tool_call.tool == "python_execution" {
+        let (stdout_tx, mut stdout_rx) = mpsc::channel::<String>(64);
+        let stdout_handle = app_handle.clone();
+        let stdout_progress = last_progress.clone();
+        let exec_id = python_exec_id(config, loop_iteration_index, idx);
+        tokio::spawn(async move {
+            while let Some(chunk) = stdout_rx.recv().await {
+                if let Some(line) = last_progress_line(&chunk) {
+                    if let Ok(mut progress) = stdout_progress.lock() {
+                        *progress = Some(line.to_string());
+                    }
+                }
+                let _ = stdout_handle.emit(
+                    "python-stdout-chunk",
+                    PythonStdoutChunkEvent {
+                        exec_id: exec_id.clone(),
+                        chunk,
+                    },
+                );
+            }
+        });
+        Some(stdout_tx)
+    } else {
+        None
+    };
+
+    // Execute the tool
+    let mut python_final_answer = None;
+    let is_builtin = handles
+        .tool_registry
+        .read()
+        .await
+        .is_builtin_tool(&resolved_tool_call.tool);
+    let (result_text, is_error, images) = if is_builtin {
+        let mut python_tool_calls = Vec::new();
+        let (result_text, is_error) = execute_builtin_tool_call(
+            &resolved_tool_call.tool,
+            &resolved_tool_call.arguments,
+            handles,
+            config,
+            loop_iteration_index,
+            idx,
+            stdout_tx,
+            &mut python_tool_calls,
+            &mut python_final_answer,
+        )
+        .await;
+        // Calls made by python_execution code show up in the timeline like direct calls
+        for call in python_tool_calls {
+            let _ = app_handle.emit(
+                "tool-result",
+                ToolResultEvent {
+                    server: call.server,
+                    tool: call.name,
+                    result: call.result_summary,
+                    is_error: call.is_error,
+                    original_length: None,
+                    caller: Some(resolved_tool_call.tool.clone()),
+                    arguments: Some(call.arguments),
+                },
+            );
+        }
+        (result_text, is_error, Vec::new())
+    } else if let Err(problems) =
+        check_mcp_tool_arguments(resolved_tool_call, handles, config).await
+    {
+        crate::log_event!(
+            "AgenticLoop",
+            "mcp_tool_invalid_arguments",
+            tool = resolved_tool_call.tool,
+            problems = problems,
+        );
+        (invalid_arguments_message(&resolved_tool_call.tool, &problems), true, Vec::new())
+    } else {
+        // MCP tool execution, abandoned after the server's timeout so a stalled
+        // server can't hang the loop
+        let timeout = crate::settings::mcp_tool_timeout(
+            &config.server_configs,
+            &resolved_tool_call.server,
+            config.mcp_tool_timeout_secs,
+        );
+        let dispatch =
+            dispatch_tool_call_to_executor(&handles.mcp_host_tx, resolved_tool_call, timeout);
+        let outcome = match timeout {
+            Some(limit) => tokio::time::timeout(limit, dispatch).await.map_err(|_| limit),
+            None => Ok(dispatch.await),
+        };
+        match outcome {
+            Err(limit) => {
+                crate::log_warn!(
+                    "AgenticLoop",
+                    "mcp_tool_timed_out",
+                    tool = resolved_tool_call.tool,
+                    secs = limit.as_secs(),
+                );
+                let _ = app_handle.emit(
+                    "tool-timeout",
+                    ToolTimeoutEvent {
+                        server: resolved_tool_call.server.clone(),
+                        tool: resolved_tool_call.tool.clone(),
+                        timeout_secs: limit.as_secs(),
+                    },
+                );
+                (mcp_tool_timeout_message(&resolved_tool_call.tool, limit), true, Vec::new())
+            }
+            Ok(Ok((result, images))) => {
+                crate::log_event!(
+                    "AgenticLoop",
+                    "mcp_tool_completed",
+                    tool = resolved_tool_call.tool,
+                    chars = result.len(),
+                    images = images.len(),
+                );
+                (result, false, images)
+            }
+            Ok(Err(e)) => {
+                crate::log_warn!(
+                    "AgenticLoop",
+                    "mcp_tool_failed",
+                    tool = resolved_tool_call.tool,
+                    error = e,
+                );
+                (e, true, Vec::new())
+            }
+        }
+    };
+
+    // Stop heartbeat
+    if let Some(heartbeat_stop_tx) = heartbeat_stop_tx {
+        let _ = heartbeat_stop_tx.send(());
+    }
+
+    // Emit result (the UI keeps the full text; the model gets a truncated copy)
+    let result_chars = result_text.chars().count();
+    let max_chars = config.max_tool_result_chars;
+    let _ = app_handle.emit(
+        "tool-result",
+        ToolResultEvent {
+            server: resolved_tool_call.server.clone(),
+            tool: resolved_tool_call.tool.clone(),
+            result: result_text.clone(),
+            is_error,
+            original_length: (max_chars > 0 && result_chars > max_chars).then_some(result_chars),
+            caller: None,
+            arguments: None,
+        },
+    );
+
+    (result_text, is_error, images, python_final_answer)
+}
+
+/// Replace oversized MCP tool results with model-written summaries. A result whose
+/// summary fails keeps its raw text (and is truncated as usual).
+async fn summarize_large_results(
+    tool_results: &mut [(ParsedToolCall, String, bool)],
+    handles: &AgenticLoopHandles,
+    config: &AgenticLoopConfig,
+) {
+    let threshold = config.tool_result_summary_threshold_chars;
+    for (call, result, is_error) in tool_results.iter_mut() {
+        if !tool_result_summary::should_summarize(call, result, *is_error, threshold) {
+            continue;
+        }
+        match tool_result_summary::summarize_tool_result(
+            &handles.foundry_tx,
+            &config.model_name,
+            call,
+            &config.original_message,
+            result,
+            config.chat_format_default,
+            config.chat_format_overrides.clone(),
+        )
+        .await
+        {
+            Ok(summary) => {
+                crate::log_event!(
+                    "AgenticLoop",
+                    "result_summarized",
+                    tool = call.tool,
+                    chars = result.chars().count(),
+                    summary_chars = summary.chars().count(),
+                );
+                *result = summary;
+            }
+            Err(e) => crate::log_event!(
+                "AgenticLoop",
+                "result_summary_skipped",
+                tool = call.tool,
+                error = e,
+            ),
+        }
+    }
+}
+
+/// Run an `extract` call against this turn's earlier results, with the usual
+/// executing/result events
+async fn run_extract_call<R: tauri::Runtime>(
+    call: &ParsedToolCall,
+    turn_results: &TurnResultStore,
+    app_handle: &tauri::AppHandle<R>,
+) -> ToolCallOutcome {
+    let _ = app_handle.emit(
+        "tool-executing",
+        ToolExecutingEvent {
+            server: call.server.clone(),
+            tool: call.tool.clone(),
+            arguments: call.arguments.clone(),
+        },
+    );
+    let (result_text, is_error) = turn_results.execute(&call.arguments);
+    crate::log_event!(
+        "AgenticLoop",
+        "extract_completed",
+        arguments = call.arguments,
+        chars = result_text.len(),
+        is_error = is_error,
+    );
+    let _ = app_handle.emit(
+        "tool-result",
+        ToolResultEvent {
+            server: call.server.clone(),
+            tool: call.tool.clone(),
+            result: result_text.clone(),
+            is_error,
+            original_length: None,
+            caller: None,
+            arguments: None,
+        },
+    );
+    (result_text, is_error, Vec::new(), None)
+}
+
+/// Validate an MCP tool call's arguments against the tool's registered input
+/// schema, unless its server opted out. Tools missing from the registry pass.
+async fn check_mcp_tool_arguments(
+    call: &ParsedToolCall,
+    handles: &AgenticLoopHandles,
+    config: &AgenticLoopConfig,
+) -> Result<(), String> {
+    let validate = config
+        .server_configs
+        .iter()
+        .find(|c| c.id == call.server)
+        .is_none_or(|c| c.validate_tool_arguments);
+    if !validate {
+        return Ok(());
+    }
+    let registry = handles.tool_registry.read().await;
+    match registry.get_tool(&format!("{}___{}", call.server, call.tool)) {
+        Some(schema) => validate_tool_arguments(&call.arguments, &schema.parameters),
+        None => Ok(()),
+    }
+}
+
+/// Error fed back to the model when a tool call's arguments don't match its schema
+fn invalid_arguments_message(tool: &str, problems: &str) -> String {
+    format!(
+        "Tool '{}' was not called because its arguments are invalid: {}. \
+        Fix the arguments to match the tool's parameters and call it again.",
+        tool, problems
+    )
+}
+
+/// Error fed back to the model when an MCP tool call exceeds its timeout
+pub(crate) fn mcp_tool_timeout_message(tool: &str, limit: Duration) -> String {
+    format!(
+        "Tool '{}' timed out after {} seconds without responding. \
+        The server may be busy or stalled; try a simpler request or a different approach.",
+        tool,
+        limit.as_secs()
+    )
+}
+
+/// Delay before the next chat attempt after `failed_attempts` failures:
+/// 1s, 2s, 4s, ... capped at 30s.
+fn chat_retry_backoff(failed_attempts: u32) -> Duration {
+    let doublings = failed_attempts.saturating_sub(1).min(5);
+    Duration::from_secs(1u64 << doublings).min(Duration::from_secs(30))
+}
+
+/// Saved as the answer when the model produced no tokens even after a retry
+pub const EMPTY_RESPONSE_PLACEHOLDER: &str = "(no response — model produced no tokens)";
+
+/// Ask the model gateway for its current state, giving up after a couple of seconds
+async fn current_model_state(foundry_tx: &mpsc::Sender<FoundryMsg>) -> Option<ModelState> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    foundry_tx.send(FoundryMsg::GetModelState { respond_to: tx }).await.ok()?;
+    tokio::time::timeout(Duration::from_secs(2), rx).await.ok()?.ok()
+}
+
+/// Error shown to the user once every chat attempt has failed
+fn chat_retries_exhausted_message(reason: &str, attempts: u32) -> String {
+    if attempts > 1 {
+        format!("{} (gave up after {} attempts)", reason, attempts)
+    } else {
+        reason.to_string()
+    }
+}
+
+/// Decide whether a chat request that failed to start should be tried again.
+/// When retrying, emits `chat-retry` and waits out the backoff; returns false
+/// once retries are used up or the user cancels while waiting.
+async fn retry_chat_request<R: tauri::Runtime>(
+    failed_attempts: u32,
+    reason: &str,
+    config: &AgenticLoopConfig,
+    app_handle: &tauri::AppHandle<R>,
+    cancel_rx: &tokio::sync::watch::Receiver<bool>,
+) -> bool {
+    if failed_attempts > config.foundry_chat_retries || *cancel_rx.borrow() {
+        return false;
+    }
+    let delay = chat_retry_backoff(failed_attempts);
+    crate::log_warn!(
+        "AgenticLoop",
+        "chat_retry",
+        attempt = failed_attempts,
+        reason = reason,
+        delay_ms = delay.as_millis() as u64,
+    );
+    let _ = app_handle.emit(
+        "chat-retry",
+        ChatRetryEvent {
+            attempt: failed_attempts + 1,
+            max_attempts: config.foundry_chat_retries + 1,
+            delay_ms: delay.as_millis() as u64,
+            reason: reason.to_string(),
+        },
+    );
+    tokio::select! {
+        _ = tokio::time::sleep(delay) => true,
+        _ = wait_for_cancel(cancel_rx.clone()) => false,
+    }
+}
+
+/// Run a future, returning its output along with how long it took
+async fn timed<T>(future: impl std::future::Future<Output = T>) -> (T, Duration) {
+    let start = std::time::Instant::now();
+    let output = future.await;
+    (output, start.elapsed())
+}
+
+/// Resolve once the user cancels the turn. Never resolves if the sender is gone.
+async fn wait_for_cancel(mut cancel_rx: tokio::sync::watch::Receiver<bool>) {
+    if cancel_rx.wait_for(|cancelled| *cancelled).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Resolve every pending approval of a turn as cancelled, so nothing waits on a
+/// turn that is gone. Returns how many approvals were cancelled.
+pub async fn cancel_turn_approvals(
+    pending_approvals: &PendingApprovals,
+    chat_id: &str,
+    generation_id: u32,
+) -> usize {
+    let prefix = format!("{}:{}:", chat_id, generation_id);
+    let senders: Vec<_> = {
+        let mut approvals = pending_approvals.write().await;
+        let keys: Vec<String> = approvals
+            .keys()
+            .filter(|key| key.starts_with(&prefix))
+            .cloned()
+            .collect();
+        keys.iter().filter_map(|key| approvals.remove(key)).collect()
+    };
+    let count = senders.len();
+    for sender in senders {
+        let _ = sender.send(ToolApprovalDecision::Cancelled);
+    }
+    count
+}
+
+/// Report a tool call dropped mid-execution by a user cancel. The builtin
+/// Python sandbox keeps running on its own thread, so it is told to stop too.
+async fn cancel_in_flight_tool_call<R: tauri::Runtime>(
+    call: &ParsedToolCall,
+    idx: usize,
+    handles: &AgenticLoopHandles,
+    config: &AgenticLoopConfig,
+    app_handle: &tauri::AppHandle<R>,
+    loop_iteration_index: usize,
+) {
+    crate::log_event!(
+        "AgenticLoop",
+        "tool_call_cancelling",
+        server = call.server,
+        tool = call.tool,
+    );
+    if call.server == "builtin" && call.tool == "python_execution" {
+        let exec_id = python_exec_id(config, loop_iteration_index, idx);
+        let _ = handles.python_tx.send(PythonMsg::Cancel { exec_id }).await;
+    }
+    let _ = app_handle.emit(
+        "tool-cancelled",
+        ToolCancelledEvent {
+            server: call.server.clone(),
+            tool: call.tool.clone(),
+        },
+    );
+}
+
+/// Last non-empty line of a stdout chunk, used as the tool's progress line
+fn last_progress_line(chunk: &str) -> Option<&str> {
+    chunk.lines().map(str::trim).rfind(|line| !line.is_empty())
+}
+
+/// Identifier for one python_execution call, shared by logs and stdout events
+fn python_exec_id(config: &AgenticLoopConfig, loop_iteration_index: usize, call_index: usize) -> String {
+    format!("{}-{}-{}", config.chat_id, loop_iteration_index, call_index)
+}
+
+/// Whether a tool call may run concurrently with others from the same response.
+///
+/// Built-ins qualify only when read-only; MCP calls qualify when their server is
+/// auto-approved, since manual approval has to be asked for one call at a time.
+fn is_parallel_safe_tool_call(call: &ParsedToolCall, server_configs: &[McpServerConfig]) -> bool {
+    if call.server == "builtin" {
+        return matches!(call.tool.as_str(), "sql_select" | "schema_search");
+    }
+    !tool_call_requires_approval(call, server_configs)
+}
+
+/// Whether the user must approve a tool call before it runs.
+///
+/// Built-in tools never need approval. MCP tools follow their server's
+/// per-tool override when one is set, then the server-wide auto-approve flag;
+/// calls to unknown servers always ask.
+fn tool_call_requires_approval(call: &ParsedToolCall, server_configs: &[McpServerConfig]) -> bool {
+    if call.server == "builtin" {
+        return false;
+    }
+    !server_configs
+        .iter()
+        .find(|c| c.id == call.server)
+        .map(|c| c.auto_approves(&call.tool))
+        .unwrap_or(false)
+}
+
+/// The message for a call to a built-in tool that is disabled this turn,
+/// or None when the call may proceed.
+fn disabled_builtin_message(call: &ParsedToolCall, state_machine: &AgenticStateMachine) -> Option<String> {
+    if call.server != "builtin" {
+        return None;
+    }
+    state_machine.disabled_builtin_message(&call.tool)
+}
+
 /// Execute a built-in tool call (tool_search, python_execution, schema_search, sql_select).
 ///
+/// `stdout_tx` receives python_execution stdout as it is printed,
+/// `python_tool_calls` the tool calls its code made, and `python_final_answer`
+/// the answer it passed to `final_answer(...)`.
+///
 /// Returns `(result_text, is_error)`.
+#[allow(clippy::too_many_arguments)]
 pub async fn execute_builtin_tool_call(
     tool_name: &str,
     arguments: &Value,
@@ -296,12 +1016,15 @@ pub async fn execute_builtin_tool_call(
     config: &AgenticLoopConfig,
     loop_iteration_index: usize,
     call_index: usize,
+    stdout_tx: Option<mpsc::Sender<String>>,
+    python_tool_calls: &mut Vec<PythonToolCallRecord>,
+    python_final_answer: &mut Option<String>,
 ) -> (String, bool) {
     use std::io::Write;
 
     match tool_name {
         "tool_search" => {
-            println!("[AgenticLoop] Executing built-in: tool_search");
+            crate::log_event!("AgenticLoop", "builtin_executing", tool = "tool_search");
             let _ = std::io::stdout().flush();
             let exec_start = std::time::Instant::now();
 
@@ -310,6 +1033,7 @@ pub async fn execute_builtin_tool_call(
                 .map_err(|e| format!("Invalid tool_search arguments: {}", e))
                 .unwrap_or(ToolSearchInput {
                     queries: vec![],
+                    query_weights: Vec::new(),
                     top_k: config.tool_search_max_results,
                 });
 
@@ -318,24 +1042,27 @@ pub async fn execute_builtin_tool_call(
                 handles.tool_registry.clone(),
                 handles.embedding_model.clone(),
                 config.tool_search_max_results,
+                config.tool_search_min_relevance,
             )
             .await
             {
                 Ok((result, discovered_tools)) => {
                     let elapsed = exec_start.elapsed();
-                    println!(
-                        "[AgenticLoop] tool_search completed in {:.2}s, found {} tools",
-                        elapsed.as_secs_f64(),
-                        discovered_tools.len()
+                    crate::log_event!(
+                        "AgenticLoop",
+                        "tool_search_completed",
+                        secs = elapsed.as_secs_f64(),
+                        tools = discovered_tools.len(),
                     );
                     (result, false)
                 }
                 Err(e) => {
                     let elapsed = exec_start.elapsed();
-                    println!(
-                        "[AgenticLoop] tool_search failed in {:.2}s: {}",
-                        elapsed.as_secs_f64(),
-                        e
+                    crate::log_warn!(
+                        "AgenticLoop",
+                        "tool_search_failed",
+                        secs = elapsed.as_secs_f64(),
+                        error = e,
                     );
                     (e, true)
                 }
@@ -343,7 +1070,7 @@ pub async fn execute_builtin_tool_call(
         }
 
         "python_execution" => {
-            println!("[AgenticLoop] Executing built-in: python_execution");
+            crate::log_event!("AgenticLoop", "builtin_executing", tool = "python_execution");
             let _ = std::io::stdout().flush();
             let exec_start = std::time::Instant::now();
 
@@ -367,17 +1094,22 @@ pub async fn execute_builtin_tool_call(
                     tabular_ctx.clone()
                 };
                 input.context = Some(merged_context);
-                println!("[AgenticLoop] Injected tabular context into python_execution");
+                crate::log_event!("AgenticLoop", "tabular_context_injected");
+            }
+
+            // The configured timeout is a ceiling: models may ask for less, never more
+            if config.python_execution_timeout_ms > 0 {
+                let ceiling = config.python_execution_timeout_ms;
+                input.timeout_ms = Some(input.timeout_ms.map_or(ceiling, |t| t.min(ceiling)));
             }
             
-            let exec_id = format!(
-                "{}-{}-{}",
-                config.chat_id, loop_iteration_index, call_index
-            );
+            let exec_id = python_exec_id(config, loop_iteration_index, call_index);
             let code_lines = input.code.len();
-            println!(
-                "[AgenticLoop] python_execution triggered (exec_id={}, code_lines={})",
-                exec_id, code_lines
+            crate::log_event!(
+                "AgenticLoop",
+                "python_execution_started",
+                exec_id = exec_id,
+                code_lines = code_lines,
             );
 
             match execute_python_code(
@@ -386,53 +1118,33 @@ pub async fn execute_builtin_tool_call(
                 handles.tool_registry.clone(),
                 &handles.python_tx,
                 config.allow_tool_search_for_python,
+                python_session_key(config),
+                config.python_allowed_modules.clone(),
+                config.python_memory_limit_bytes,
+                stdout_tx,
             )
             .await
             {
                 Ok(output) => {
                     let elapsed = exec_start.elapsed();
-                    println!(
-                        "[AgenticLoop] {} python_execution completed in {:.2}s",
-                        if output.success { "OK" } else { "WARN" },
-                        elapsed.as_secs_f64()
+                    crate::log_event!(
+                        "AgenticLoop",
+                        "python_execution_completed",
+                        success = output.success,
+                        secs = elapsed.as_secs_f64(),
                     );
+                    python_tool_calls.extend(output.tool_calls_made.iter().cloned());
+                    python_final_answer.clone_from(&output.final_answer);
 
-                    let has_stdout = !output.stdout.trim().is_empty();
-                    let has_stderr = !output.stderr.trim().is_empty();
-                    let (result, is_error) = if output.success {
-                        match (has_stdout, has_stderr) {
-                            (true, true) => {
-                                (format!("STDOUT:\n{}\n\nSTDERR:\n{}", output.stdout, output.stderr), false)
-                            }
-                            (true, false) => (output.stdout, false),
-                            (false, true) => (format!("(no stdout)\nSTDERR:\n{}", output.stderr), false),
-                            (false, false) => {
-                                // No output at all - this is likely a bug in the code
-                                // (e.g., forgot to call the function, or print statement is unreachable)
-                                // Mark as error so the model gets a chance to fix it
-                                println!("[AgenticLoop] WARNING: Python execution produced no output - treating as error for model feedback");
-                                (
-                                    "Error: Execution completed with no output. Your code ran without errors, but nothing was printed. \
-                                    Common causes:\n\
-                                    1. You defined a function but forgot to call it\n\
-                                    2. You called print() inside a function after a return statement (unreachable code)\n\
-                                    3. You forgot to add a print() statement for the result\n\n\
-                                    Please fix your code and try again.".to_string(),
-                                    true
-                                )
-                            }
-                        }
-                    } else {
-                        (format!("Error: {}", output.stderr), true)
-                    };
-                    (result, is_error)
+                    format_python_output(output, config.python_result_format)
                 }
                 Err(e) => {
                     let elapsed = exec_start.elapsed();
-                    println!(
-                        "[AgenticLoop] python_execution failed in {:.2}s: {}",
-                        elapsed.as_secs_f64(),
-                        e
+                    crate::log_warn!(
+                        "AgenticLoop",
+                        "python_execution_failed",
+                        secs = elapsed.as_secs_f64(),
+                        error = e,
                     );
                     (e, true)
                 }
@@ -440,39 +1152,53 @@ pub async fn execute_builtin_tool_call(
         }
 
         "schema_search" => {
-            println!("[AgenticLoop] Executing built-in: schema_search");
+            crate::log_event!("AgenticLoop", "builtin_executing", tool = "schema_search");
             let _ = std::io::stdout().flush();
             let exec_start = std::time::Instant::now();
 
-            let input: SchemaSearchInput = serde_json::from_value(arguments.clone())
+            let mut input: SchemaSearchInput = serde_json::from_value(arguments.clone())
                 .unwrap_or_else(|e| {
-                    println!(
-                        "[AgenticLoop] Failed to parse schema_search args: {}, using defaults",
-                        e
+                    crate::log_event!(
+                        "AgenticLoop",
+                        "schema_search_default_args",
+                        error = e.to_string(),
                     );
                     SchemaSearchInput {
                         query: String::new(),
                         max_tables: 10,
                         max_columns_per_table: 25,
                         min_relevance: 0.3,
+                        source_ids: Vec::new(),
                     }
                 });
 
+            // Scope the vector search to enabled sources (or the requested subset of them)
+            input.source_ids = if input.source_ids.is_empty() {
+                config.enabled_db_sources.clone()
+            } else {
+                input
+                    .source_ids
+                    .into_iter()
+                    .filter(|id| config.enabled_db_sources.contains(id))
+                    .collect()
+            };
+
             let executor =
                 SchemaSearchExecutor::new(handles.schema_tx.clone(), handles.embedding_model.clone());
 
             match executor.execute(input).await {
                 Ok(mut output) => {
-                    // Filter by enabled sources
+                    // Backstop: an empty scope searches every source
                     let enabled: std::collections::HashSet<String> =
                         config.enabled_db_sources.iter().cloned().collect();
                     output.tables.retain(|t| enabled.contains(&t.source_id));
 
                     let elapsed = exec_start.elapsed();
-                    println!(
-                        "[AgenticLoop] schema_search completed in {:.2}s: {} tables found",
-                        elapsed.as_secs_f64(),
-                        output.tables.len()
+                    crate::log_event!(
+                        "AgenticLoop",
+                        "schema_search_completed",
+                        secs = elapsed.as_secs_f64(),
+                        tables = output.tables.len(),
                     );
                     (
                         serde_json::to_string_pretty(&output).unwrap_or_default(),
@@ -481,10 +1207,11 @@ pub async fn execute_builtin_tool_call(
                 }
                 Err(e) => {
                     let elapsed = exec_start.elapsed();
-                    println!(
-                        "[AgenticLoop] schema_search failed in {:.2}s: {}",
-                        elapsed.as_secs_f64(),
-                        e
+                    crate::log_warn!(
+                        "AgenticLoop",
+                        "schema_search_failed",
+                        secs = elapsed.as_secs_f64(),
+                        error = e,
                     );
                     (e, true)
                 }
@@ -492,7 +1219,7 @@ pub async fn execute_builtin_tool_call(
         }
 
         "sql_select" => {
-            println!("[AgenticLoop] Executing built-in: sql_select");
+            crate::log_event!("AgenticLoop", "builtin_executing", tool = "sql_select");
             let _ = std::io::stdout().flush();
             let exec_start = std::time::Instant::now();
 
@@ -505,6 +1232,30 @@ pub async fn execute_builtin_tool_call(
                     true,
                 );
             }
+            if let Err(e) = ensure_read_only_select(&sql) {
+                crate::log_event!("AgenticLoop", "sql_select_rejected", error = e);
+                return (format!("Error: {}", e), true);
+            }
+
+            // Paging is opt-in: without an offset the query runs as written
+            let offset = match arguments.get("offset").filter(|v| !v.is_null()) {
+                Some(value) => match parse_offset(value) {
+                    Ok(offset) => Some(offset),
+                    Err(e) => return (format!("Error: {}", e), true),
+                },
+                None => None,
+            };
+            let max_rows = arguments
+                .get("max_rows")
+                .and_then(|v| v.as_u64())
+                .map_or(DEFAULT_MAX_ROWS, |n| (n as usize).max(1));
+            let exec_sql = match offset {
+                Some(offset) => match apply_row_window(&sql, max_rows, offset) {
+                    Ok(windowed) => windowed,
+                    Err(e) => return (format!("Error: {}", e), true),
+                },
+                None => sql.clone(),
+            };
 
             // Resolve source_id from table names in the SQL query
             let source_id = match resolve_source_from_sql(
@@ -516,7 +1267,7 @@ pub async fn execute_builtin_tool_call(
             {
                 Ok(id) => id,
                 Err(e) => {
-                    println!("[AgenticLoop] Failed to resolve source from SQL: {}", e);
+                    crate::log_warn!("AgenticLoop", "sql_source_unresolved", error = e);
                     // Return structured error for recovery
                     let error_json = serde_json::json!({
                         "sql_executed": sql,
@@ -533,7 +1284,7 @@ pub async fn execute_builtin_tool_call(
                 .database_toolbox_tx
                 .send(DatabaseToolboxMsg::ExecuteSql {
                     source_id: source_id.clone(),
-                    sql: sql.clone(),
+                    sql: exec_sql.clone(),
                     parameters: vec![],
                     reply_to: respond_tx,
                 })
@@ -544,27 +1295,37 @@ pub async fn execute_builtin_tool_call(
             }
 
             match respond_rx.await {
-                Ok(Ok(result)) => {
+                Ok(Ok(mut result)) => {
                     let elapsed = exec_start.elapsed();
+                    // The window fetched one extra row to detect whether more remain
+                    let has_more = offset.is_some() && result.rows.len() > max_rows;
+                    if offset.is_some() {
+                        result.rows.truncate(max_rows);
+                        result.row_count = result.rows.len();
+                    }
                     let row_count = result.rows.len();
-                    println!(
-                        "[AgenticLoop] sql_select completed in {:.2}s: {} rows (source: {})",
-                        elapsed.as_secs_f64(),
-                        row_count,
-                        source_id
+                    crate::log_event!(
+                        "AgenticLoop",
+                        "sql_select_completed",
+                        secs = elapsed.as_secs_f64(),
+                        rows = row_count,
+                        source = source_id,
                     );
-                    (
-                        serde_json::to_string_pretty(&result).unwrap_or_default(),
-                        false,
-                    )
+                    let mut output = serde_json::to_value(&result).unwrap_or_default();
+                    if let (Some(offset), Some(obj)) = (offset, output.as_object_mut()) {
+                        obj.insert("offset".to_string(), serde_json::json!(offset));
+                        obj.insert("has_more".to_string(), serde_json::json!(has_more));
+                    }
+                    (serde_json::to_string_pretty(&output).unwrap_or_default(), false)
                 }
                 Ok(Err(e)) => {
                     let elapsed = exec_start.elapsed();
-                    println!(
-                        "[AgenticLoop] sql_select failed in {:.2}s (source: {}): {}",
-                        elapsed.as_secs_f64(),
-                        source_id,
-                        e
+                    crate::log_warn!(
+                        "AgenticLoop",
+                        "sql_select_failed",
+                        secs = elapsed.as_secs_f64(),
+                        source = source_id,
+                        error = e,
                     );
                     // Return structured error for recovery
                     let error_json = serde_json::json!({
@@ -587,6 +1348,80 @@ pub async fn execute_builtin_tool_call(
     }
 }
 
+/// Render a python_execution result for the model in the configured format.
+fn format_python_output(output: CodeExecutionOutput, format: ResultFormat) -> (String, bool) {
+    if format == ResultFormat::Json {
+        return (output.to_model_json(), !output.success);
+    }
+
+    let has_stdout = !output.stdout.trim().is_empty();
+    let has_stderr = !output.stderr.trim().is_empty();
+    // Value of a trailing bare expression (e.g. `result` on the last line)
+    let return_section = output.return_value.as_ref().map(|value| {
+        format!(
+            "RETURN:\n{}",
+            serde_json::to_string_pretty(value).unwrap_or_default()
+        )
+    });
+    if output.success {
+        match (has_stdout, has_stderr, return_section) {
+            (true, true, ret) => {
+                let text = format!("STDOUT:\n{}\n\nSTDERR:\n{}", output.stdout, output.stderr);
+                (append_return_section(text, ret), false)
+            }
+            (true, false, ret) => (append_return_section(output.stdout, ret), false),
+            (false, true, ret) => {
+                let text = format!("(no stdout)\nSTDERR:\n{}", output.stderr);
+                (append_return_section(text, ret), false)
+            }
+            (false, false, Some(ret)) => (ret, false),
+            (false, false, None) => {
+                // No output at all - this is likely a bug in the code
+                // (e.g., forgot to call the function, or print statement is unreachable)
+                // Mark as error so the model gets a chance to fix it
+                crate::log_event!("AgenticLoop", "python_execution_no_output");
+                (
+                    "Error: Execution completed with no output. Your code ran without errors, but nothing was printed. \
+                    Common causes:\n\
+                    1. You defined a function but forgot to call it\n\
+                    2. You called print() inside a function after a return statement (unreachable code)\n\
+                    3. You forgot to add a print() statement for the result\n\n\
+                    Please fix your code and try again.".to_string(),
+                    true
+                )
+            }
+        }
+    } else {
+        (format!("Error: {}", output.stderr), true)
+    }
+}
+
+/// Append the `RETURN:` section (if any) after the stdout/stderr text.
+fn append_return_section(text: String, return_section: Option<String>) -> String {
+    match return_section {
+        Some(section) => format!("{}\n\n{}", text, section),
+        None => text,
+    }
+}
+
+/// Key under which the Python actor keeps session variables for this turn,
+/// or None when session persistence is disabled.
+fn python_session_key(config: &AgenticLoopConfig) -> Option<String> {
+    config
+        .python_session_enabled
+        .then(|| format!("{}:{}", config.chat_id, config.generation_id))
+}
+
+/// Drop any Python session variables held for this turn.
+async fn reset_python_session(handles: &AgenticLoopHandles, config: &AgenticLoopConfig) {
+    if let Some(key) = python_session_key(config) {
+        let _ = handles
+            .python_tx
+            .send(PythonMsg::ResetSession { key })
+            .await;
+    }
+}
+
 /// Parse sql_select arguments, handling malformed input.
 /// Returns the SQL query string.
 fn parse_sql_select_arguments(arguments: &Value) -> String {
@@ -597,9 +1432,10 @@ fn parse_sql_select_arguments(arguments: &Value) -> String {
 
     // Try reconstruction for malformed arguments
     if let Some(reconstructed) = reconstruct_sql_from_malformed_args(arguments) {
-        println!(
-            "[AgenticLoop] Reconstructed SQL: {}...",
-            reconstructed.chars().take(50).collect::<String>()
+        crate::log_event!(
+            "AgenticLoop",
+            "sql_reconstructed",
+            sql = reconstructed.chars().take(50).collect::<String>(),
         );
         return reconstructed;
     }
@@ -681,10 +1517,295 @@ async fn resolve_source_from_sql(
 // Main Loop
 // ============================================================================
 
-/// Maximum number of tool call iterations before stopping (safety limit).
-const MAX_LOOP_ITERATIONS: usize = 20;
+/// Number of similar errors (within the window) that disables tool calling.
+const REPEATED_ERROR_THRESHOLD: usize = 3;
 
-/// Run the agentic loop: call model, detect tool calls, execute, repeat.
+/// Number of recent errors remembered per tool.
+const REPEATED_ERROR_WINDOW: usize = 5;
+
+lazy_static::lazy_static! {
+    static ref UUID_RE: Regex = Regex::new(
+        r"(?i)\b[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\b"
+    )
+    .unwrap();
+    static ref QUOTED_RE: Regex = Regex::new(r#"'[^'\n]*'|"[^"\n]*""#).unwrap();
+    static ref NUMBER_RE: Regex = Regex::new(r"\d+").unwrap();
+    static ref WHITESPACE_RE: Regex = Regex::new(r"\s+").unwrap();
+}
+
+/// Reduce an error message to its stable shape so failures that differ only by
+/// dynamic values (IDs, timestamps, quoted literals) compare equal.
+pub fn normalize_error_signature(error: &str) -> String {
+    let normalized = UUID_RE.replace_all(error, "<uuid>");
+    let normalized = QUOTED_RE.replace_all(&normalized, "<str>");
+    let normalized = NUMBER_RE.replace_all(&normalized, "<n>");
+    let normalized = WHITESPACE_RE.replace_all(normalized.trim(), " ");
+    normalized.to_lowercase().chars().take(100).collect()
+}
+
+/// Detects a model stuck on the same tool failure.
+///
+/// Trips when the exact same error repeats back to back, or when
+/// `threshold` of the last `window` errors from one tool normalize to the same
+/// signature (see `normalize_error_signature`).
+struct RepeatedErrorTracker {
+    last_exact_signature: Option<String>,
+    recent_by_tool: HashMap<String, VecDeque<String>>,
+    threshold: usize,
+    window: usize,
+}
+
+impl Default for RepeatedErrorTracker {
+    fn default() -> Self {
+        Self::new(REPEATED_ERROR_THRESHOLD, REPEATED_ERROR_WINDOW)
+    }
+}
+
+impl RepeatedErrorTracker {
+    fn new(threshold: usize, window: usize) -> Self {
+        Self {
+            last_exact_signature: None,
+            recent_by_tool: HashMap::new(),
+            threshold,
+            window: window.max(threshold),
+        }
+    }
+
+    /// Record a tool error. Returns true when tool calling should be disabled.
+    fn record(&mut self, tool: &str, error: &str) -> bool {
+        let exact = format!("{}::{}", tool, error.chars().take(100).collect::<String>());
+        let exact_repeat = self.last_exact_signature.as_ref() == Some(&exact);
+        self.last_exact_signature = Some(exact);
+
+        let signature = normalize_error_signature(error);
+        let recent = self.recent_by_tool.entry(tool.to_string()).or_default();
+        recent.push_back(signature.clone());
+        if recent.len() > self.window {
+            recent.pop_front();
+        }
+        let similar = recent.iter().filter(|s| **s == signature).count();
+
+        exact_repeat || similar >= self.threshold
+    }
+}
+
+/// Start of a turn: tools disabled by the chat's previous turn are enabled again.
+/// Returns the cleared record.
+async fn begin_turn_tool_disables(
+    tool_disables: &SharedToolDisables,
+    chat_id: &str,
+) -> Option<ToolDisableInfo> {
+    tool_disables.write().await.reset(chat_id)
+}
+
+/// Note shown to the user when the loop stops at `max_tool_iterations`.
+fn max_iterations_message(limit: usize) -> String {
+    format!(
+        "[Stopped: reached the tool iteration limit of {} (max_tool_iterations). \
+        Raise it in settings or with --max-tool-iterations to allow longer tool workflows.]",
+        limit
+    )
+}
+
+/// Re-append the Hermes closing marker when generation stopped on it as a stop
+/// sequence (the backend leaves the stop sequence out of the output), so the
+/// saved history keeps well-formed calls. Returns the appended marker.
+fn restore_stop_sequence_marker(
+    response: &mut String,
+    stop_sequences: &[String],
+) -> Option<&'static str> {
+    let marker = ToolCallFormatName::Hermes.stop_sequence()?;
+    if !stop_sequences.iter().any(|s| s == marker) {
+        return None;
+    }
+    let opened = response.matches("<tool_call>").count();
+    if opened > response.matches(marker).count() {
+        response.push_str(marker);
+        return Some(marker);
+    }
+    None
+}
+
+/// Split off the calls that would exceed the per-turn tool budget.
+///
+/// `executed` counts calls already run this turn; returns the calls to run
+/// now and how many were dropped. A `limit` of 0 means no limit.
+fn apply_tool_call_budget(
+    mut calls: Vec<ParsedToolCall>,
+    executed: usize,
+    limit: usize,
+) -> (Vec<ParsedToolCall>, usize) {
+    if limit == 0 {
+        return (calls, 0);
+    }
+    let remaining = limit.saturating_sub(executed);
+    let skipped = calls.len().saturating_sub(remaining);
+    calls.truncate(remaining);
+    (calls, skipped)
+}
+
+/// Message fed back to the model when a turn runs out of tool calls
+fn tool_budget_exceeded_message(limit: usize, skipped: usize) -> String {
+    format!(
+        "Too many tool calls: this turn reached its limit of {} tool calls \
+        (max_tool_calls_per_turn), so {} further call(s) were not run. \
+        Answer now with the information you already have.",
+        limit, skipped
+    )
+}
+
+/// Message fed back to the model when the user rejects an iteration's tool calls
+fn batch_declined_message(calls: &[ParsedToolCall]) -> String {
+    let tools: Vec<&str> = calls.iter().map(|c| c.tool.as_str()).collect();
+    format!(
+        "The user declined to run the proposed tool calls ({}). \
+        Do not call them again; answer with the information you already have, \
+        or explain what you would need.",
+        tools.join(", ")
+    )
+}
+
+/// Serialize arguments with object keys sorted so equal calls compare equal
+fn canonical_arguments(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            let fields: Vec<String> = keys
+                .into_iter()
+                .map(|k| format!("{}:{}", Value::String(k.clone()), canonical_arguments(&map[k])))
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical_arguments).collect();
+            format!("[{}]", items.join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Give text-format tool calls without a native ID a short generated one, so their
+/// results can be correlated in saved history
+fn assign_correlation_ids(calls: &mut [ParsedToolCall]) {
+    for call in calls.iter_mut().filter(|c| c.id.is_none()) {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        call.id = Some(format!("call_{}", &id[..8]));
+    }
+}
+
+/// Drop repeated tool calls from one response, keeping the first occurrence
+/// (and its native ID). Returns the retained calls and how many were dropped.
+fn dedupe_tool_calls(calls: Vec<ParsedToolCall>) -> (Vec<ParsedToolCall>, usize) {
+    let mut seen = HashSet::new();
+    let total = calls.len();
+    let retained: Vec<ParsedToolCall> = calls
+        .into_iter()
+        .filter(|call| {
+            seen.insert((
+                call.server.clone(),
+                call.tool.clone(),
+                canonical_arguments(&call.arguments),
+            ))
+        })
+        .collect();
+    let dropped = total - retained.len();
+    (retained, dropped)
+}
+
+/// Outcome of asking the user to approve an iteration's tool calls as a batch
+enum BatchApproval {
+    /// Run these calls (the proposed ones, or the user's edits)
+    Approved(Vec<ParsedToolCall>),
+    /// Rejected, timed out, or the approval channel closed
+    Declined,
+    /// The user stopped generation while the batch was pending
+    Cancelled,
+}
+
+/// Emit `tool-batch-pending` with every call of this iteration and wait for
+/// a single decision.
+async fn request_batch_approval<R: tauri::Runtime>(
+    calls: &[ParsedToolCall],
+    handles: &AgenticLoopHandles,
+    config: &AgenticLoopConfig,
+    app_handle: &tauri::AppHandle<R>,
+    loop_iteration_index: usize,
+    cancel_rx: &tokio::sync::watch::Receiver<bool>,
+) -> BatchApproval {
+    let approval_key = format!(
+        "{}:{}:{}:batch",
+        config.chat_id, config.generation_id, loop_iteration_index
+    );
+    let (approval_tx, approval_rx) = tokio::sync::oneshot::channel();
+    {
+        let mut approvals = handles.pending_approvals.write().await;
+        approvals.insert(approval_key.clone(), approval_tx);
+    }
+
+    let _ = app_handle.emit(
+        "tool-batch-pending",
+        ToolCallsPendingEvent {
+            approval_key: approval_key.clone(),
+            calls: calls.to_vec(),
+            iteration: loop_iteration_index,
+        },
+    );
+    crate::log_event!(
+        "AgenticLoop",
+        "batch_approval_waiting",
+        calls = calls.len(),
+        key = approval_key,
+    );
+
+    let decision = tokio::select! {
+        result = tokio::time::timeout(Duration::from_secs(300), approval_rx) => result,
+        _ = wait_for_cancel(cancel_rx.clone()) => {
+            handles.pending_approvals.write().await.remove(&approval_key);
+            return BatchApproval::Cancelled;
+        }
+    };
+
+    match decision {
+        Ok(Ok(ToolApprovalDecision::Approved)) => {
+            crate::log_event!("AgenticLoop", "batch_approved");
+            BatchApproval::Approved(calls.to_vec())
+        }
+        Ok(Ok(ToolApprovalDecision::Edited(mut edited))) => {
+            crate::log_event!("AgenticLoop", "batch_approved_with_edits", calls = edited.len());
+            let registry = handles.tool_registry.read().await;
+            for call in &mut edited {
+                if registry.is_builtin_tool(&call.tool) {
+                    call.server = "builtin".to_string();
+                }
+            }
+            BatchApproval::Approved(edited)
+        }
+        Ok(Ok(ToolApprovalDecision::Rejected)) => {
+            crate::log_event!("AgenticLoop", "batch_rejected");
+            BatchApproval::Declined
+        }
+        Ok(Ok(ToolApprovalDecision::Cancelled)) => {
+            crate::log_event!("AgenticLoop", "batch_approval_cancelled");
+            BatchApproval::Declined
+        }
+        Ok(Err(_)) => {
+            crate::log_event!("AgenticLoop", "batch_approval_channel_closed");
+            BatchApproval::Declined
+        }
+        Err(_) => {
+            crate::log_event!("AgenticLoop", "batch_approval_timed_out");
+            handles
+                .pending_approvals
+                .write()
+                .await
+                .remove(&approval_key);
+            BatchApproval::Declined
+        }
+    }
+}
+
+/// Run the agentic loop: call model, detect tool calls, execute, repeat.
 ///
 /// This is the core execution loop that:
 /// 1. Sends the current history to the model
@@ -692,10 +1813,10 @@ const MAX_LOOP_ITERATIONS: usize = 20;
 /// 3. Detects tool calls in the response
 /// 4. Executes tools (with approval if required)
 /// 5. Adds results to history and continues until a final response
-pub async fn run_agentic_loop(
+pub async fn run_agentic_loop<R: tauri::Runtime>(
     handles: AgenticLoopHandles,
     config: AgenticLoopConfig,
-    app_handle: tauri::AppHandle,
+    app_handle: tauri::AppHandle<R>,
     mut full_history: Vec<ChatMessage>,
     cancel_rx: tokio::sync::watch::Receiver<bool>,
     mut openai_tools: Option<Vec<OpenAITool>>,
@@ -706,57 +1827,139 @@ pub async fn run_agentic_loop(
 
     // Derive native tool calling from format config
     let native_tool_calling_enabled = config.format_config.native_enabled();
+    // Anthropic tool_use blocks carry ids and are kept in the history like native calls
+    let anthropic_blocks_enabled = config
+        .format_config
+        .is_enabled(ToolCallFormatName::Anthropic);
 
     // Resolve model profile from model name
     let profile = resolve_profile(&config.model_name);
     let model_family = profile.model_family;
-    let tool_format = profile.tool_call_format;
-    let mut loop_iteration_index = 0;
-    let mut had_tool_calls = false;
+    // Anthropic blocks are only left enabled for models served with the Anthropic messages format
+    let tool_format = if anthropic_blocks_enabled {
+        ToolFormat::Anthropic
+    } else {
+        profile.tool_call_format
+    };
+    let mut loop_iteration_index = config.start_iteration;
+
+    // Start each turn with a clean Python session
+    reset_python_session(&handles, &config).await;
+    // Iterations only advance past 0 after tool calls
+    let mut had_tool_calls = config.start_iteration > 0;
     let mut final_response = String::new();
+    // Set when the user stops generation while a tool call is running
+    let mut cancelled_mid_tool = false;
+    // Set when the user stops generation while the model is still streaming
+    let mut cancelled_mid_stream = false;
+    // Whether the latest user message's images go out with the next request
+    let mut send_images = true;
+    // Whether the final answer was already sent back once for not matching the response schema
+    let mut response_schema_repaired = false;
+    // Whether a malformed tool call was already sent back once for a retry
+    let mut malformed_tool_call_retried = false;
+    // Whether a stream that produced no tokens was already retried once
+    let mut empty_response_retried = false;
+    // Failed attempts at starting the current iteration's chat request
+    let mut failed_chat_attempts: u32 = 0;
+    // Set when the model could not be reached; the checkpoint is kept for resume_turn
+    let mut turn_failed = false;
+    let mut checkpointed_iteration: Option<usize> = None;
+    let turn_start = std::time::Instant::now();
+    let mut metrics = TurnMetrics::default();
 
     // Track repeated errors to detect when model is stuck
-    let mut last_error_signature: Option<String> = None;
+    let mut error_tracker = RepeatedErrorTracker::default();
     let mut tools_disabled_due_to_repeated_error = false;
+    // Native tool definitions set aside while tools are disabled
+    let mut tools_before_disable: Option<Option<Vec<OpenAITool>>> = None;
+    if let Some(previous) =
+        begin_turn_tool_disables(&handles.tool_disables, &config.chat_id).await
+    {
+        crate::log_event!("AgenticLoop", "tools_reenabled", tool = previous.tool);
+    }
+
+    // Tool executions so far this turn, checked against max_tool_calls_per_turn
+    let mut tool_calls_this_turn = 0usize;
+    let mut tool_budget_exhausted = false;
+
+    // Results of this turn's tool calls, readable by the extract pseudo-tool
+    let mut turn_results = TurnResultStore::default();
     
     // Track if previous iteration had errors - allows tool retry even if state machine would block
     let mut previous_iteration_had_errors = false;
 
-    let verbose_logging = crate::is_verbose_logging_enabled();
-    
+    // Periodically persist the partial response so a crash mid-turn keeps it
+    let autosave = spawn_chat_autosave(&handles, &config, &turn_progress);
+
     // Test emit to verify app_handle works in spawned task
-    println!("[AgenticLoop] Testing event emit from spawned task...");
+    crate::log_event!("AgenticLoop", "test_emit");
     match app_handle.emit("agentic-loop-started", &config.chat_id) {
-        Ok(_) => println!("[AgenticLoop] ✅ Test emit succeeded"),
-        Err(e) => println!("[AgenticLoop] ❌ Test emit failed: {}", e),
+        Ok(_) => crate::log_event!("AgenticLoop", "test_emit_succeeded"),
+        Err(e) => crate::log_warn!(
+            "AgenticLoop",
+            "test_emit_failed",
+            error = e.to_string(),
+        ),
     }
 
     // Current system prompt - regenerated by state machine after transitions
     #[allow(unused_assignments)]
     let mut current_system_prompt = config.turn_system_prompt.clone();
 
-    println!(
-        "[AgenticLoop] Starting with model_family={:?}, tool_format={:?}, python_tool_mode={}, primary_format={:?}, state={:?}",
-        model_family, tool_format, config.python_tool_mode, config.primary_format, state_machine.current_state().name()
+    crate::log_event!(
+        "AgenticLoop",
+        "loop_started",
+        model_family = format!("{:?}", model_family),
+        tool_format = format!("{:?}", tool_format),
+        python_tool_mode = config.python_tool_mode,
+        primary_format = format!("{:?}", config.primary_format),
+        state = state_machine.current_state().name(),
     );
     let _ = std::io::stdout().flush();
 
     loop {
-        println!(
-            "\n[AgenticLoop] Iteration {} starting...",
-            loop_iteration_index
-        );
+        crate::log_event!("AgenticLoop", "iteration_started", iteration = loop_iteration_index);
         let iteration_start = std::time::Instant::now();
         let _ = std::io::stdout().flush();
 
+        // reset_tool_disable_state can re-enable tools in the middle of a turn
+        if tools_disabled_due_to_repeated_error
+            && !handles.tool_disables.read().await.is_disabled(&config.chat_id)
+        {
+            crate::log_event!("AgenticLoop", "tools_reenabled_by_reset");
+            tools_disabled_due_to_repeated_error = false;
+            error_tracker = RepeatedErrorTracker::default();
+            if let (Some(tools), false) = (tools_before_disable.take(), tool_budget_exhausted) {
+                openai_tools = tools;
+            }
+        }
+
+        // Checkpoint once per iteration (chat retries re-enter the loop at the same index)
+        if checkpointed_iteration != Some(loop_iteration_index) {
+            checkpointed_iteration = Some(loop_iteration_index);
+            let (tool_search, schema_search) = state_machine.auto_discovery_context();
+            let checkpoint = TurnCheckpoint {
+                request: config.turn_request.clone(),
+                state: state_machine.current_state().clone(),
+                tool_search: tool_search.cloned(),
+                schema_search: schema_search.cloned(),
+                full_history: full_history.clone(),
+                stored_history: config.stored_history.clone(),
+                iteration: loop_iteration_index,
+            };
+            record_turn_checkpoint(&turn_progress, &config.checkpoint_path, checkpoint).await;
+        }
+
         // Log materialized tools from previous iteration
         if loop_iteration_index > 0 {
             let registry = handles.tool_registry.read().await;
             let stats = registry.stats();
             if stats.materialized_tools > 0 {
-                println!(
-                    "[AgenticLoop] {} materialized tools available from previous iteration",
-                    stats.materialized_tools
+                crate::log_event!(
+                    "AgenticLoop",
+                    "materialized_tools_available",
+                    tools = stats.materialized_tools,
                 );
             }
         }
@@ -787,54 +1990,94 @@ pub async fn run_agentic_loop(
         // Clone iter_cancel_rx before moving into the request
         let iter_cancel_for_stream = iter_cancel_rx.clone();
         
-        let chat_request = FoundryMsg::Chat {
+        let chat_request = FoundryMsg::Chat(Box::new(ChatRequest {
             model: config.model_name.clone(),
-            chat_history_messages: full_history.clone(),
+            // Attached images go out with the first request of the turn only,
+            // tool images with the request that follows their results
+            chat_history_messages: messages_for_request(&full_history, send_images),
             reasoning_effort: config.reasoning_effort.clone(),
             native_tool_specs: openai_tools.clone(),
-            native_tool_calling_enabled,
+            // tool_use blocks are that backend's native calls
+            native_tool_calling_enabled: native_tool_calling_enabled || anthropic_blocks_enabled,
             chat_format_default: chat_format,
             chat_format_overrides: config.chat_format_overrides.clone(),
             respond_to: token_tx,
             stream_cancel_rx: iter_cancel_for_stream,
-        };
+            response_schema: config
+                .response_schema
+                .clone()
+                .filter(|_| config.response_schema_native),
+            temperature: config.temperature,
+            seed: config.seed,
+            stop_sequences: config.stop_sequences.clone(),
+        }));
         let mut token_rx = token_rx;
 
-        println!("[AgenticLoop] Sending chat request to Foundry...");
+        crate::log_event!("AgenticLoop", "chat_request_sending");
         let _ = std::io::stdout().flush();
 
         if handles.foundry_tx.send(chat_request).await.is_err() {
-            println!("[AgenticLoop] ERROR: Failed to send to Foundry");
-            let _ = app_handle.emit(
-                "chat-error",
-                serde_json::json!({ "error": "Failed to send to model gateway" }),
-            );
+            crate::log_warn!("AgenticLoop", "chat_request_send_failed");
+            let reason = "Failed to send to model gateway";
+            failed_chat_attempts += 1;
+            if retry_chat_request(failed_chat_attempts, reason, &config, &app_handle, &cancel_rx)
+                .await
+            {
+                continue;
+            }
+            let error = chat_retries_exhausted_message(reason, failed_chat_attempts);
+            let _ = app_handle.emit("chat-error", serde_json::json!({ "error": error }));
+            turn_failed = true;
             break;
         }
 
-        println!("[AgenticLoop] Request sent, waiting for tokens...");
+        crate::log_event!("AgenticLoop", "chat_request_sent");
         let _ = std::io::stdout().flush();
 
+        // Auto-save picks up this iteration's messages and streamed answer
+        {
+            let mut progress = turn_progress.write().await;
+            progress.iteration_history = full_history.clone();
+            progress.iteration_visible_text.clear();
+        }
+
         // Receive streaming response
         let mut model_response_text = String::new();
+        // Text outside reasoning blocks; early tool detection only looks at this
+        let mut visible_response_text = String::new();
+        let mut reasoning_filter = ReasoningStreamFilter::new(config.reasoning_format);
         let mut token_count = 0;
         let mut first_token_received = false;
+        let mut first_token_ms: Option<u64> = None;
         let iteration_start_time = std::time::Instant::now();
         let mut repetition_detector = RepetitionDetector::new();
-        #[allow(unused_assignments)]
-        let mut early_stopped_for_tool = false;
         let mut iter_cancel_check = iter_cancel_rx.clone();
+        let first_token_deadline =
+            tokio::time::sleep(Duration::from_secs(config.first_token_timeout_secs));
+        tokio::pin!(first_token_deadline);
+        let mut start_failure: Option<String> = None;
 
         // Token streaming loop
         loop {
             tokio::select! {
+                _ = &mut first_token_deadline, if !first_token_received && config.first_token_timeout_secs > 0 => {
+                    crate::log_warn!(
+                        "AgenticLoop",
+                        "first_token_timeout",
+                        secs = config.first_token_timeout_secs,
+                    );
+                    start_failure = Some(format!(
+                        "No response from the model within {} seconds",
+                        config.first_token_timeout_secs
+                    ));
+                    break;
+                }
                 _ = iter_cancel_check.changed() => {
                     if *iter_cancel_check.borrow() {
                         if *cancel_rx.borrow() {
-                            println!("[AgenticLoop] User cancellation received!");
+                            crate::log_event!("AgenticLoop", "user_cancelled");
                         } else {
-                            println!("[AgenticLoop] Internal early-stop cancellation triggered.");
-                            // Note: early_stopped_for_tool not set here since we break immediately
+                            crate::log_event!("AgenticLoop", "early_stop_cancelled");
                         }
                         break;
                     }
@@ -845,7 +2088,12 @@ pub async fn run_agentic_loop(
                             if !first_token_received {
                                 first_token_received = true;
                                 let ttft = iteration_start_time.elapsed();
-                                println!("[AgenticLoop] First token received! TTFT: {:.2}s", ttft.as_secs_f64());
+                                first_token_ms = Some(ttft.as_millis() as u64);
+                                crate::log_event!(
+                                    "AgenticLoop",
+                                    "first_token",
+                                    ttft_secs = ttft.as_secs_f64(),
+                                );
                             }
 
                             model_response_text.push_str(&token);
@@ -865,7 +2113,13 @@ pub async fn run_agentic_loop(
                             repetition_detector.push(&token);
                             if let Some((pattern, repetitions)) = repetition_detector.detect_loop() {
                                 let score = pattern.len() * repetitions;
-                                println!("[AgenticLoop] LOOP DETECTED: '{}' repeated {} times (score={})", pattern, repetitions, score);
+                                crate::log_warn!(
+                                    "AgenticLoop",
+                                    "loop_detected",
+                                    pattern = pattern,
+                                    repetitions = repetitions,
+                                    score = score,
+                                );
                                 // Emit model-stuck event for frontend
                                 let _ = app_handle.emit(
                                     "model-stuck",
@@ -879,37 +2133,58 @@ pub async fn run_agentic_loop(
                                 let _ = iter_cancel_tx.send(true);
                             }
 
+                            let split = reasoning_filter.push(&token);
+                            visible_response_text.push_str(&split.visible);
+                            if let Ok(mut progress) = turn_progress.try_write() {
+                                progress.iteration_visible_text.push_str(&split.visible);
+                            }
+                            let chat_text = if config.reasoning_events_enabled {
+                                if !split.reasoning.is_empty() {
+                                    let _ = app_handle.emit(REASONING_EVENT, &split.reasoning);
+                                }
+                                &split.visible
+                            } else {
+                                &token
+                            };
+
                             // Emit token to frontend
-                            match app_handle.emit("chat-token", &token) {
-                                Ok(_) => {
-                                    if token_count == 1 {
-                                        println!("[AgenticLoop] ✅ First token emitted to frontend");
+                            if !chat_text.is_empty() {
+                                match app_handle.emit("chat-token", chat_text) {
+                                    Ok(_) => {
+                                        if token_count == 1 {
+                                            crate::log_event!("AgenticLoop", "first_token_emitted");
+                                        }
+                                    }
+                                    Err(e) => {
+                                        crate::log_warn!(
+                                            "AgenticLoop",
+                                            "token_emit_failed",
+                                            token = token_count,
+                                            error = e.to_string(),
+                                        );
                                     }
-                                }
-                                Err(e) => {
-                                    println!("[AgenticLoop] ❌ Failed to emit token {}: {}", token_count, e);
                                 }
                             }
 
                             // Early tool call detection to prevent hallucination
-                            if !early_stopped_for_tool
-                                && model_response_text.contains("</tool_call>")
-                            {
-                                println!("[AgenticLoop] Detected complete tool call during streaming, stopping early.");
+                            if tool_call_complete_while_streaming(&visible_response_text, &config) {
+                                crate::log_event!("AgenticLoop", "tool_call_complete_while_streaming");
                                 let _ = iter_cancel_tx.send(true);
-                                early_stopped_for_tool = true;
+                                break;
                             }
 
-                            if verbose_logging && token_count % 50 == 0 {
-                                println!(
-                                    "[AgenticLoop] Receiving: {} tokens, {} chars",
-                                    token_count,
-                                    model_response_text.len()
+                            if token_count % 50 == 0 {
+                                crate::log_event!(
+                                    "AgenticLoop",
+                                    "stream_progress",
+                                    iteration = loop_iteration_index,
+                                    tokens = token_count,
+                                    chars = model_response_text.len(),
                                 );
                             }
                         }
                         None => {
-                            println!("[AgenticLoop] Channel closed, stream complete");
+                            crate::log_event!("AgenticLoop", "stream_closed");
                             break;
                         }
                     }
@@ -917,22 +2192,101 @@ pub async fn run_agentic_loop(
             }
         }
 
+        if let Some(reason) = start_failure {
+            // Stop the stalled stream before starting over
+            let _ = iter_cancel_tx.send(true);
+            failed_chat_attempts += 1;
+            if retry_chat_request(failed_chat_attempts, &reason, &config, &app_handle, &cancel_rx)
+                .await
+            {
+                continue;
+            }
+            let error = chat_retries_exhausted_message(&reason, failed_chat_attempts);
+            let _ = app_handle.emit("chat-error", serde_json::json!({ "error": error }));
+            turn_failed = true;
+            break;
+        }
+        failed_chat_attempts = 0;
+
         let stream_elapsed = iteration_start.elapsed();
-        println!(
-            "[AgenticLoop] Response complete: {} tokens, {} chars in {:.2}s",
-            token_count,
-            model_response_text.len(),
-            stream_elapsed.as_secs_f64()
+        metrics.record_iteration(IterationMetrics {
+            tokens: token_count,
+            ttft_ms: first_token_ms,
+            duration_ms: iteration_start_time.elapsed().as_millis() as u64,
+        });
+        crate::log_event!(
+            "AgenticLoop",
+            "response_complete",
+            tokens = token_count,
+            chars = model_response_text.len(),
+            secs = stream_elapsed.as_secs_f64(),
         );
 
         // Always log the full response for debugging
-        println!(
-            "[AgenticLoop] Full model response:\n---\n{}\n---",
-            model_response_text
-        );
+        crate::log_event!("AgenticLoop", "model_response", text = model_response_text);
+
+        // Reasoning never reaches tool detection, the final answer or the saved history
+        let held_back = reasoning_filter.finish();
+        if config.reasoning_events_enabled {
+            if !held_back.reasoning.is_empty() {
+                let _ = app_handle.emit(REASONING_EVENT, &held_back.reasoning);
+            }
+            if !held_back.visible.is_empty() {
+                let _ = app_handle.emit("chat-token", &held_back.visible);
+            }
+        }
+        if config.reasoning_format != ReasoningFormat::None {
+            let (reasoning, answer) =
+                split_reasoning(&model_response_text, config.reasoning_format);
+            if !reasoning.is_empty() {
+                crate::log_event!("AgenticLoop", "reasoning_removed", chars = reasoning.len());
+            }
+            model_response_text = answer;
+        }
+        if let Some(marker) =
+            restore_stop_sequence_marker(&mut model_response_text, &config.stop_sequences)
+        {
+            crate::log_event!("AgenticLoop", "stop_sequence_restored", marker = marker);
+            let _ = app_handle.emit("chat-token", marker);
+        }
+
+        // A cancelled stream is kept as a partial answer; its tool calls are not run
+        if *cancel_rx.borrow() {
+            crate::log_event!("AgenticLoop", "cancelled_during_generation");
+            final_response = model_response_text.clone();
+            cancelled_mid_stream = true;
+            break;
+        }
+
+        // The stream completed without a single token (model crashed, context overflow)
+        if token_count == 0 {
+            let model_state = current_model_state(&handles.foundry_tx).await;
+            let will_retry = !empty_response_retried;
+            crate::log_warn!(
+                "AgenticLoop",
+                "empty_response",
+                model_state = format!("{:?}", model_state),
+                will_retry = will_retry,
+            );
+            let _ = app_handle.emit(
+                "empty-response",
+                EmptyResponseEvent {
+                    iteration: loop_iteration_index,
+                    will_retry,
+                    model_state,
+                },
+            );
+            if will_retry {
+                empty_response_retried = true;
+                continue;
+            }
+            final_response = EMPTY_RESPONSE_PLACEHOLDER.to_string();
+            let _ = app_handle.emit("chat-token", &final_response);
+            break;
+        }
 
         // Detect action (tool calls vs final response)
-        let action = if tools_disabled_due_to_repeated_error {
+        let action = if tools_disabled_due_to_repeated_error || tool_budget_exhausted {
             AgenticLoopAction::Final {
                 response: model_response_text.clone(),
             }
@@ -945,68 +2299,137 @@ pub async fn run_agentic_loop(
                 &config.format_config,
                 config.primary_format,
                 config.python_execution_in_native_tools,
+                config.python_multi_block,
             )
         };
 
         let parsed_tool_calls = match action {
             AgenticLoopAction::Final { response } => {
-                println!("[AgenticLoop] No tool calls detected, loop complete");
+                crate::log_event!("AgenticLoop", "no_tool_calls");
+                if response != model_response_text {
+                    // A final_answer call: only the call itself was streamed, so show its answer
+                    let _ = app_handle.emit("chat-token", format!("\n\n{}", response));
+                }
+                if let Some(schema) = &config.response_schema {
+                    if let Err(problems) = validate_structured_response(&response, schema) {
+                        if !response_schema_repaired {
+                            crate::log_event!(
+                                "AgenticLoop",
+                                "schema_repair_requested",
+                                problems = problems,
+                            );
+                            response_schema_repaired = true;
+                            let _ = app_handle.emit(
+                                "chat-warning",
+                                json!({ "message": "Response did not match the JSON schema; asking the model to fix it" }),
+                            );
+                            let _ = app_handle.emit("chat-token", "\n\n");
+                            full_history.push(ChatMessage {
+                                role: "assistant".to_string(),
+                                content: model_response_text.clone(),
+                                ..Default::default()
+                            });
+                            full_history.push(ChatMessage {
+                                role: "user".to_string(),
+                                content: repair_prompt(&problems, schema),
+                                ..Default::default()
+                            });
+                            continue;
+                        }
+                        let error = format!(
+                            "The response does not match the requested JSON schema after one repair attempt: {}",
+                            problems
+                        );
+                        crate::log_warn!("AgenticLoop", "schema_mismatch", error = error);
+                        let _ = app_handle.emit("chat-error", json!({ "error": error }));
+                        // The answer is kept, but the turn stays resumable like other failures
+                        turn_failed = true;
+                    }
+                }
                 final_response = response;
                 break;
             }
             AgenticLoopAction::ToolCalls { calls } => calls,
+            AgenticLoopAction::MalformedToolCall { hint } => {
+                if malformed_tool_call_retried {
+                    crate::log_warn!("AgenticLoop", "malformed_tool_call_after_retry");
+                    final_response = model_response_text.clone();
+                    break;
+                }
+                crate::log_event!("AgenticLoop", "malformed_tool_call_retry", hint = hint);
+                malformed_tool_call_retried = true;
+                let _ = app_handle.emit(
+                    "chat-warning",
+                    json!({ "message": "The model's tool call was malformed; asking it to retry" }),
+                );
+                let _ = app_handle.emit("chat-token", "\n\n");
+                full_history.push(ChatMessage {
+                    role: "assistant".to_string(),
+                    content: model_response_text.clone(),
+                    ..Default::default()
+                });
+                full_history.push(ChatMessage {
+                    role: "user".to_string(),
+                    content: malformed_tool_call_message(&hint),
+                    ..Default::default()
+                });
+                continue;
+            }
         };
 
         // Safety: max iterations
-        if loop_iteration_index >= MAX_LOOP_ITERATIONS {
-            println!(
-                "[AgenticLoop] Max iterations ({}) reached, stopping",
-                MAX_LOOP_ITERATIONS
+        if loop_iteration_index >= config.max_tool_iterations {
+            crate::log_warn!(
+                "AgenticLoop",
+                "max_iterations_reached",
+                max = config.max_tool_iterations,
             );
-            final_response = model_response_text.clone();
+            let limit_note = max_iterations_message(config.max_tool_iterations);
+            final_response = format!("{}\n\n{}", model_response_text, limit_note);
+            let _ = app_handle.emit("chat-token", format!("\n\n{}", limit_note));
 
             // Emit warning
             let _ = app_handle.emit(
                 "chat-warning",
                 serde_json::json!({
-                    "message": format!("Stopped after {} iterations (safety limit)", MAX_LOOP_ITERATIONS)
+                    "message": format!("Stopped after {} iterations (safety limit)", config.max_tool_iterations)
                 }),
             );
             break;
         }
 
-        println!(
-            "[AgenticLoop] Found {} tool call(s)",
-            parsed_tool_calls.len()
-        );
+        crate::log_event!("AgenticLoop", "tool_calls_found", count = parsed_tool_calls.len());
         had_tool_calls = true;
 
         // Check if native format
         let use_native_results =
-            should_use_native_tool_results(native_tool_calling_enabled, &parsed_tool_calls);
+            should_use_native_tool_results(
+                native_tool_calling_enabled || anthropic_blocks_enabled,
+                &parsed_tool_calls,
+            );
         if use_native_results {
-            println!("[AgenticLoop] Using native OpenAI tool result format");
+            crate::log_event!("AgenticLoop", "native_tool_results");
         }
 
         // Resolve servers for tools
         let mut resolved_tool_calls: Vec<ParsedToolCall> = Vec::new();
         for call in &parsed_tool_calls {
-            let resolved_server = if is_builtin_tool(&call.tool) {
+            let is_builtin = handles.tool_registry.read().await.is_builtin_tool(&call.tool);
+            let resolved_server = if is_builtin || call.tool == EXTRACT_TOOL {
                 "builtin".to_string()
             } else if call.server == "unknown" {
                 match resolve_mcp_server_for_tool(&handles.mcp_host_tx, &call.tool).await {
                     Some(server_id) => {
-                        println!(
-                            "[AgenticLoop] Resolved unknown server to '{}' for tool '{}'",
-                            server_id, call.tool
+                        crate::log_event!(
+                            "AgenticLoop",
+                            "server_resolved",
+                            server = server_id,
+                            tool = call.tool,
                         );
                         server_id
                     }
                     None => {
-                        println!(
-                            "[AgenticLoop] ERROR: Could not resolve server for tool '{}', skipping",
-                            call.tool
-                        );
+                        crate::log_warn!("AgenticLoop", "server_unresolved", tool = call.tool);
                         continue;
                     }
                 }
@@ -1023,32 +2446,208 @@ pub async fn run_agentic_loop(
             });
         }
 
+        let (resolved_tool_calls, duplicates) = dedupe_tool_calls(resolved_tool_calls);
+        if duplicates > 0 {
+            crate::log_event!("AgenticLoop", "duplicate_tool_calls_dropped", count = duplicates);
+        }
+
+        let (mut resolved_tool_calls, over_budget) = apply_tool_call_budget(
+            resolved_tool_calls,
+            tool_calls_this_turn,
+            config.max_tool_calls_per_turn,
+        );
+        if config.tool_call_ids_in_text && !use_native_results {
+            assign_correlation_ids(&mut resolved_tool_calls);
+        }
+        let budget_note = if over_budget > 0 {
+            crate::log_event!(
+                "AgenticLoop",
+                "tool_call_budget_reached",
+                budget = config.max_tool_calls_per_turn,
+                skipped = over_budget,
+            );
+            let _ = app_handle.emit(
+                "tool-budget-exceeded",
+                json!({
+                    "limit": config.max_tool_calls_per_turn,
+                    "skipped": over_budget,
+                    "iteration": loop_iteration_index,
+                }),
+            );
+            // The model answers from here on; further tool calls are not parsed
+            tool_budget_exhausted = true;
+            openai_tools = None;
+            Some(tool_budget_exceeded_message(config.max_tool_calls_per_turn, over_budget))
+        } else {
+            None
+        };
+        if let (Some(note), true) = (&budget_note, resolved_tool_calls.is_empty()) {
+            full_history.push(create_assistant_message_with_tool_calls(
+                &model_response_text,
+                &[],
+                false,
+                None,
+                config.tool_call_ids_in_text,
+            ));
+            full_history.push(ChatMessage {
+                role: "user".to_string(),
+                content: note.clone(),
+                ..Default::default()
+            });
+            loop_iteration_index += 1;
+            continue;
+        }
+
+        // Batch approval mode: one decision covers every call before any of them runs
+        let mut batch_approved = false;
+        if config.batch_approval_mode && !resolved_tool_calls.is_empty() {
+            match request_batch_approval(
+                &resolved_tool_calls,
+                &handles,
+                &config,
+                &app_handle,
+                loop_iteration_index,
+                &cancel_rx,
+            )
+            .await
+            {
+                BatchApproval::Approved(calls) => {
+                    resolved_tool_calls = calls;
+                    batch_approved = true;
+                }
+                BatchApproval::Declined => {
+                    // Tell the model once, without native tool_calls that would expect results
+                    full_history.push(create_assistant_message_with_tool_calls(
+                        &model_response_text,
+                        &resolved_tool_calls,
+                        false,
+                        None,
+                        config.tool_call_ids_in_text,
+                    ));
+                    full_history.push(ChatMessage {
+                        role: "user".to_string(),
+                        content: batch_declined_message(&resolved_tool_calls),
+                        ..Default::default()
+                    });
+                    loop_iteration_index += 1;
+                    continue;
+                }
+                BatchApproval::Cancelled => {
+                    cancelled_mid_tool = true;
+                    final_response = model_response_text.clone();
+                    break;
+                }
+            }
+        }
+
         // Add assistant message with tool calls to history
         let assistant_msg = create_assistant_message_with_tool_calls(
             &model_response_text,
             &resolved_tool_calls,
             use_native_results,
             None,
+            config.tool_call_ids_in_text,
         );
         full_history.push(assistant_msg);
 
         // Execute each tool call
         let mut tool_results: Vec<(ParsedToolCall, String, bool)> = Vec::new();
+        // Images returned by tools, forwarded to vision models with the results
+        let mut tool_images: Vec<String> = Vec::new();
         let mut executed_any = false;
+        // Answer recorded by python_execution code calling final_answer(...)
+        let mut python_final_answer: Option<String> = None;
+
+        // Run the leading independent calls concurrently up front; the loop below
+        // consumes their results in the original order. From the first call that
+        // needs approval or is gated by the state machine on, calls stay
+        // sequential, so nothing has run ahead when the loop stops at that call.
+        let mut prefetched: HashMap<usize, (ToolCallOutcome, Duration)> = HashMap::new();
+        if config.parallel_tool_calls {
+            let parallel_count = resolved_tool_calls
+                .iter()
+                .take_while(|call| {
+                    (state_machine.is_tool_allowed(&call.tool) || previous_iteration_had_errors)
+                        && disabled_builtin_message(call, &state_machine).is_none()
+                        && is_parallel_safe_tool_call(call, &config.server_configs)
+                })
+                .count();
+            let parallel_indices: Vec<usize> = (0..parallel_count).collect();
+            if parallel_indices.len() > 1 {
+                crate::log_event!(
+                    "AgenticLoop",
+                    "parallel_tool_calls",
+                    count = parallel_indices.len(),
+                );
+                let batch = futures::future::join_all(parallel_indices.iter().map(|&idx| {
+                    timed(execute_tool_call_with_events(
+                        &resolved_tool_calls[idx],
+                        idx,
+                        resolved_tool_calls.len(),
+                        &handles,
+                        &config,
+                        &app_handle,
+                        loop_iteration_index,
+                    ))
+                }));
+                tokio::select! {
+                    results = batch => {
+                        prefetched = parallel_indices.into_iter().zip(results).collect();
+                    }
+                    _ = wait_for_cancel(cancel_rx.clone()) => {
+                        for &idx in &parallel_indices {
+                            cancel_in_flight_tool_call(
+                                &resolved_tool_calls[idx],
+                                idx,
+                                &handles,
+                                &config,
+                                &app_handle,
+                                loop_iteration_index,
+                            )
+                            .await;
+                        }
+                        cancelled_mid_tool = true;
+                        break;
+                    }
+                }
+            }
+        }
 
         for (idx, resolved_tool_call) in resolved_tool_calls.iter().enumerate() {
+            let prefetched_result = prefetched.remove(&idx);
+
+            // A built-in that is turned off gets an actionable error instead of
+            // running or being dropped by the state machine
+            if let Some(message) = disabled_builtin_message(resolved_tool_call, &state_machine) {
+                crate::log_event!("AgenticLoop", "builtin_disabled", message = message);
+                let _ = app_handle.emit(
+                    "tool-blocked",
+                    serde_json::json!({
+                        "tool": resolved_tool_call.tool,
+                        "state": state_machine.current_state().name(),
+                        "message": message,
+                    }),
+                );
+                tool_results.push((resolved_tool_call.clone(), format!("Error: {}", message), true));
+                executed_any = true;
+                continue;
+            }
+
             // Check if blocked by state machine
             // EXCEPTION: If previous iteration had errors, allow the tool to retry
             // This prevents the state machine from blocking error recovery
-            let tool_allowed = state_machine.is_tool_allowed(&resolved_tool_call.tool) 
+            let tool_allowed = prefetched_result.is_some()
+                || resolved_tool_call.tool == EXTRACT_TOOL
+                || state_machine.is_tool_allowed(&resolved_tool_call.tool) 
                 || previous_iteration_had_errors;
             
             if !tool_allowed {
                 let current_state = state_machine.current_state().name();
-                println!(
-                    "[AgenticLoop] Tool '{}' blocked by state machine (state: {})",
-                    resolved_tool_call.tool,
-                    current_state
+                crate::log_event!(
+                    "AgenticLoop",
+                    "tool_blocked",
+                    tool = resolved_tool_call.tool,
+                    state = current_state,
                 );
                 // Emit tool-blocked event for frontend
                 let _ = app_handle.emit(
@@ -1063,23 +2662,16 @@ pub async fn run_agentic_loop(
             }
             
             if previous_iteration_had_errors {
-                println!(
-                    "[AgenticLoop] Tool '{}' allowed due to error retry (bypassing state machine)",
-                    resolved_tool_call.tool
+                crate::log_event!(
+                    "AgenticLoop",
+                    "tool_allowed_for_retry",
+                    tool = resolved_tool_call.tool,
                 );
             }
 
             // Check if approval required
-            let requires_approval = if resolved_tool_call.server == "builtin" {
-                false
-            } else {
-                !config
-                    .server_configs
-                    .iter()
-                    .find(|c| c.id == resolved_tool_call.server)
-                    .map(|c| c.auto_approve_tools)
-                    .unwrap_or(false)
-            };
+            let requires_approval = !batch_approved
+                && tool_call_requires_approval(resolved_tool_call, &config.server_configs);
 
             if requires_approval {
                 // Emit pending event and wait for approval
@@ -1087,9 +2679,10 @@ pub async fn run_agentic_loop(
                     "{}:{}:{}:{}",
                     config.chat_id, config.generation_id, loop_iteration_index, idx
                 );
-                println!(
-                    "[AgenticLoop] Server {} requires manual approval",
-                    resolved_tool_call.server
+                crate::log_event!(
+                    "AgenticLoop",
+                    "approval_required",
+                    server = resolved_tool_call.server,
                 );
 
                 // Create oneshot channel for this approval
@@ -1109,29 +2702,40 @@ pub async fn run_agentic_loop(
                     },
                 );
 
-                println!("[AgenticLoop] Waiting for approval on key: {}", approval_key);
+                crate::log_event!("AgenticLoop", "approval_waiting", key = approval_key);
 
-                // Wait for decision with timeout
-                let approval_result = tokio::time::timeout(
-                    Duration::from_secs(300),
-                    approval_rx,
-                )
-                .await;
+                // Wait for decision with timeout; a user cancel stops the turn
+                let approval_result = tokio::select! {
+                    result = tokio::time::timeout(Duration::from_secs(300), approval_rx) => result,
+                    _ = wait_for_cancel(cancel_rx.clone()) => {
+                        crate::log_event!("AgenticLoop", "cancelled_awaiting_approval");
+                        handles.pending_approvals.write().await.remove(&approval_key);
+                        cancelled_mid_tool = true;
+                        break;
+                    }
+                };
 
                 match approval_result {
                     Ok(Ok(ToolApprovalDecision::Approved)) => {
-                        println!("[AgenticLoop] Tool call approved by user");
+                        crate::log_event!("AgenticLoop", "tool_call_approved");
+                    }
+                    Ok(Ok(ToolApprovalDecision::Edited(_))) => {
+                        crate::log_event!("AgenticLoop", "tool_call_approved_edits_ignored");
                     }
                     Ok(Ok(ToolApprovalDecision::Rejected)) => {
-                        println!("[AgenticLoop] Tool call rejected by user");
+                        crate::log_event!("AgenticLoop", "tool_call_rejected");
+                        continue;
+                    }
+                    Ok(Ok(ToolApprovalDecision::Cancelled)) => {
+                        crate::log_event!("AgenticLoop", "tool_call_approval_cancelled");
                         continue;
                     }
                     Ok(Err(_)) => {
-                        println!("[AgenticLoop] Approval channel closed");
+                        crate::log_event!("AgenticLoop", "approval_channel_closed");
                         continue;
                     }
                     Err(_) => {
-                        println!("[AgenticLoop] Approval timed out");
+                        crate::log_event!("AgenticLoop", "approval_timed_out");
                         // Remove from pending
                         let mut approvals = handles.pending_approvals.write().await;
                         approvals.remove(&approval_key);
@@ -1140,129 +2744,100 @@ pub async fn run_agentic_loop(
                 }
             }
 
-            // Emit executing event
-            let _ = app_handle.emit(
-                "tool-executing",
-                ToolExecutingEvent {
-                    server: resolved_tool_call.server.clone(),
-                    tool: resolved_tool_call.tool.clone(),
-                    arguments: resolved_tool_call.arguments.clone(),
-                },
-            );
-
-            println!(
-                "[AgenticLoop] Processing tool call {}/{}: {}::{}",
-                idx + 1,
-                resolved_tool_calls.len(),
-                resolved_tool_call.server,
-                resolved_tool_call.tool
-            );
-
-            // Start heartbeat
-            let heartbeat_handle = app_handle.clone();
-            let heartbeat_server = resolved_tool_call.server.clone();
-            let heartbeat_tool = resolved_tool_call.tool.clone();
-            let (heartbeat_stop_tx, mut heartbeat_stop_rx) = tokio::sync::oneshot::channel::<()>();
-            let heartbeat_start = std::time::Instant::now();
-            tokio::spawn(async move {
-                let mut interval = tokio::time::interval(Duration::from_secs(1));
-                let mut beat_counter: u64 = 0;
-                loop {
+            let ((result_text, is_error, images, final_answer), duration) = match prefetched_result {
+                Some(result) => result,
+                None if resolved_tool_call.tool == EXTRACT_TOOL => {
+                    timed(run_extract_call(
+                        resolved_tool_call,
+                        &turn_results,
+                        &app_handle,
+                    ))
+                    .await
+                }
+                None => {
+                    let execution = timed(execute_tool_call_with_events(
+                        resolved_tool_call,
+                        idx,
+                        resolved_tool_calls.len(),
+                        &handles,
+                        &config,
+                        &app_handle,
+                        loop_iteration_index,
+                    ));
                     tokio::select! {
-                        _ = interval.tick() => {
-                            beat_counter += 1;
-                            let _ = heartbeat_handle.emit(
-                                "tool-heartbeat",
-                                ToolHeartbeatEvent {
-                                    server: heartbeat_server.clone(),
-                                    tool: heartbeat_tool.clone(),
-                                    elapsed_ms: heartbeat_start.elapsed().as_millis() as u64,
-                                    beat: beat_counter,
-                                },
-                            );
-                        }
-                        _ = &mut heartbeat_stop_rx => {
+                        result = execution => result,
+                        _ = wait_for_cancel(cancel_rx.clone()) => {
+                            cancel_in_flight_tool_call(
+                                resolved_tool_call,
+                                idx,
+                                &handles,
+                                &config,
+                                &app_handle,
+                                loop_iteration_index,
+                            )
+                            .await;
+                            cancelled_mid_tool = true;
                             break;
                         }
                     }
                 }
-            });
-
-            // Execute the tool
-            let (result_text, is_error) = if is_builtin_tool(&resolved_tool_call.tool) {
-                execute_builtin_tool_call(
-                    &resolved_tool_call.tool,
-                    &resolved_tool_call.arguments,
-                    &handles,
-                    &config,
-                    loop_iteration_index,
-                    idx,
-                )
-                .await
-            } else {
-                // MCP tool execution
-                match dispatch_tool_call_to_executor(&handles.mcp_host_tx, resolved_tool_call).await
-                {
-                    Ok(result) => {
-                        println!(
-                            "[AgenticLoop] MCP tool {} completed: {} chars",
-                            resolved_tool_call.tool,
-                            result.len()
-                        );
-                        (result, false)
-                    }
-                    Err(e) => {
-                        println!(
-                            "[AgenticLoop] MCP tool {} failed: {}",
-                            resolved_tool_call.tool, e
-                        );
-                        (e, true)
-                    }
-                }
             };
 
-            // Stop heartbeat
-            let _ = heartbeat_stop_tx.send(());
-
-            // Emit result
-            let _ = app_handle.emit(
-                "tool-result",
-                ToolResultEvent {
-                    server: resolved_tool_call.server.clone(),
-                    tool: resolved_tool_call.tool.clone(),
-                    result: result_text.clone(),
-                    is_error,
-                },
-            );
+            metrics.record_tool(&resolved_tool_call.server, &resolved_tool_call.tool, duration);
+
+            // Keep successful results for extract, by call id (or position). A call
+            // without an id takes the position id, which its result then shows.
+            let mut result_call = resolved_tool_call.clone();
+            if !is_error && resolved_tool_call.tool != EXTRACT_TOOL {
+                let result_id = result_call
+                    .id
+                    .get_or_insert_with(|| format!("result_{}", tool_calls_this_turn + 1))
+                    .clone();
+                turn_results.insert(&result_id, &result_text);
+            }
 
             // Clone result for state machine before moving into tool_results
             let result_for_state = result_text.clone();
-            tool_results.push((resolved_tool_call.clone(), result_text, is_error));
+            tool_results.push((result_call, result_text, is_error));
+            tool_images.extend(images);
+            if final_answer.is_some() {
+                python_final_answer = final_answer;
+            }
             executed_any = true;
+            tool_calls_this_turn += 1;
 
             // Handle state machine transitions via events
             if resolved_tool_call.tool == "sql_select" && !is_error {
                 use crate::agentic_state::{StateEvent, SqlResults};
                 let prev_state = state_machine.current_state().name().to_string();
-                // Parse row count from result if possible
-                let row_count = serde_json::from_str::<serde_json::Value>(&result_for_state)
-                    .ok()
+                // Parse row count (and whether more pages remain) from result if possible
+                let parsed_result =
+                    serde_json::from_str::<serde_json::Value>(&result_for_state).ok();
+                let row_count = parsed_result
+                    .as_ref()
                     .and_then(|v| v.get("row_count").and_then(|c| c.as_u64()))
                     .unwrap_or(0) as usize;
+                let has_more = parsed_result
+                    .as_ref()
+                    .and_then(|v| v.get("has_more").and_then(|m| m.as_bool()))
+                    .unwrap_or(false);
                 state_machine.handle_event(StateEvent::SqlExecuted {
                     results: SqlResults {
                         columns: vec![],
                         rows: vec![],
                         row_count,
-                        truncated: false,
+                        truncated: has_more,
                     },
                     row_count,
                 });
                 let new_state = state_machine.current_state().name();
                 if prev_state != new_state {
-                    println!(
-                        "[AgenticLoop] State transition: {} -> {} (sql_select completed)",
-                        prev_state, new_state
+                    crate::log_event!(
+                        "AgenticLoop",
+                        "state_transition",
+                        from = prev_state,
+                        to = new_state,
+                        reason = "sql_select completed",
                     );
                 }
             } else if resolved_tool_call.tool == "sql_select" && is_error {
@@ -1288,9 +2863,12 @@ pub async fn run_agentic_loop(
                 });
                 
                 let new_state = state_machine.current_state().name();
-                println!(
-                    "[AgenticLoop] State transition: {} -> {} (sql_select FAILED - enabling retry)",
-                    prev_state, new_state
+                crate::log_event!(
+                    "AgenticLoop",
+                    "state_transition",
+                    from = prev_state,
+                    to = new_state,
+                    reason = "sql_select failed, enabling retry",
                 );
             } else if resolved_tool_call.tool == "schema_search" && !is_error {
                 use crate::agentic_state::StateEvent;
@@ -1313,64 +2891,126 @@ pub async fn run_agentic_loop(
             }
         }
 
+        if cancelled_mid_tool {
+            crate::log_event!("AgenticLoop", "cancelled_during_tools");
+            final_response = model_response_text.clone();
+            break;
+        }
+
         if !executed_any {
-            println!("[AgenticLoop] No tools executed (all require approval), stopping loop");
+            crate::log_event!("AgenticLoop", "no_tools_executed");
             break;
         }
 
-        // Add tool results to history
-        if use_native_results {
-            println!(
-                "[AgenticLoop] Adding {} native tool result messages to history",
-                tool_results.len()
-            );
-            for (call, result, _is_error) in &tool_results {
+        // Images from tools reach vision models with the results; other models
+        // only see the placeholders in the result text
+        if !config.model_supports_vision {
+            tool_images.clear();
+        }
+        send_images = !tool_images.is_empty();
+
+        // Summarize oversized MCP results for the history; the UI already has the raw text
+        if config.summarize_large_tool_results {
+            summarize_large_results(&mut tool_results, &handles, &config).await;
+        }
+
+        // sql_select rows can reach the model as a compact table; the UI keeps the JSON
+        for (call, result, is_error) in tool_results.iter_mut() {
+            if call.tool == "sql_select" && !*is_error {
+                *result = render_sql_result_for_model(result, config.sql_result_format);
+            }
+        }
+
+        // Add tool results to history
+        if use_native_results {
+            crate::log_event!(
+                "AgenticLoop",
+                "native_tool_results_added",
+                count = tool_results.len(),
+            );
+            for (call, result, _is_error) in &tool_results {
                 if let Some(ref tool_call_id) = call.id {
-                    let result_msg = create_native_tool_result_message(tool_call_id, result);
+                    let result = truncate_tool_result(result, config.max_tool_result_chars);
+                    let result_msg = create_native_tool_result_message(tool_call_id, &result);
                     full_history.push(result_msg);
                 }
             }
+            if let Some(note) = &budget_note {
+                full_history.push(ChatMessage {
+                    role: "user".to_string(),
+                    content: note.clone(),
+                    ..Default::default()
+                });
+            }
+            // Tool messages can't carry images, so they follow in a user message
+            if !tool_images.is_empty() {
+                full_history.push(ChatMessage {
+                    role: "user".to_string(),
+                    content: "Images returned by the tool calls above.".to_string(),
+                    images: std::mem::take(&mut tool_images),
+                    ..Default::default()
+                });
+            }
         } else {
             // Text-based format: append results to a user message
             let mut combined_results = String::new();
             for (call, result, is_error) in &tool_results {
                 let schema_context = state_machine.get_compact_schema_context();
+                let result = truncate_tool_result(result, config.max_tool_result_chars);
                 let formatted = format_tool_result(
                     call,
-                    result,
+                    &result,
                     *is_error,
                     tool_format,
                     Some(&config.original_message),
                     schema_context.as_deref(),
+                    config.python_result_format,
+                    // Always marked, so the model sees the id to pass to extract
+                    true,
                 );
                 combined_results.push_str(&formatted);
                 combined_results.push_str("\n\n");
             }
+            if let Some(note) = &budget_note {
+                combined_results.push_str(note);
+            }
 
             full_history.push(ChatMessage {
                 role: "user".to_string(),
                 content: combined_results,
-                system_prompt: None,
-                tool_calls: None,
-                tool_call_id: None,
+                images: std::mem::take(&mut tool_images),
+                ..Default::default()
             });
         }
 
+        // Code that called final_answer(...) ends the turn with that answer
+        if let Some(answer) = python_final_answer {
+            crate::log_event!("AgenticLoop", "python_final_answer");
+            let _ = app_handle.emit("chat-token", format!("\n\n{}", answer));
+            final_response = answer;
+            break;
+        }
+
         // Check for repeated errors
         for (call, result, is_error) in &tool_results {
-            if *is_error {
-                let error_sig = format!("{}::{}", call.tool, result.chars().take(100).collect::<String>());
-                if last_error_signature.as_ref() == Some(&error_sig) {
-                    println!(
-                        "[AgenticLoop] REPEATED ERROR DETECTED: Tool '{}' failed with same error twice",
-                        call.tool
-                    );
-                    println!("[AgenticLoop] Disabling tool calling, prompting model to answer directly");
-                    tools_disabled_due_to_repeated_error = true;
-                    openai_tools = None;
-                    break;
-                }
-                last_error_signature = Some(error_sig);
+            if *is_error && error_tracker.record(&call.tool, result) {
+                crate::log_warn!("AgenticLoop", "repeated_tool_error", tool = call.tool);
+                crate::log_event!("AgenticLoop", "tool_calling_disabled");
+                tools_disabled_due_to_repeated_error = true;
+                tools_before_disable = Some(openai_tools.take());
+                let info = ToolDisableInfo {
+                    chat_id: config.chat_id.clone(),
+                    generation_id: config.generation_id,
+                    tool: call.tool.clone(),
+                    error_signature: normalize_error_signature(result),
+                    disabled_at_ms: std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .map(|d| d.as_millis())
+                        .unwrap_or(0),
+                };
+                handles.tool_disables.write().await.disable(info.clone());
+                let _ = app_handle.emit("tools-disabled", &info);
+                break;
             }
         }
 
@@ -1379,11 +3019,12 @@ pub async fn run_agentic_loop(
         
         // Update system prompt from state machine if changed
         let should_continue = state_machine.should_continue_loop() || had_errors_this_iteration;
-        println!(
-            "[AgenticLoop] State machine: state={}, should_continue={} (had_errors={})",
-            state_machine.current_state().name(),
-            should_continue,
-            had_errors_this_iteration
+        crate::log_event!(
+            "AgenticLoop",
+            "state_machine",
+            state = state_machine.current_state().name(),
+            should_continue = should_continue,
+            had_errors = had_errors_this_iteration,
         );
 
         if !should_continue {
@@ -1398,10 +3039,11 @@ pub async fn run_agentic_loop(
             if let Some(first_msg) = full_history.first_mut() {
                 if first_msg.role == "system" || first_msg.system_prompt.is_some() {
                     first_msg.system_prompt = Some(new_prompt.clone());
-                    println!(
-                        "[AgenticLoop] System prompt updated for state: {} ({} chars)",
-                        state_machine.current_state().name(),
-                        new_prompt.len()
+                    crate::log_event!(
+                        "AgenticLoop",
+                        "system_prompt_updated",
+                        state = state_machine.current_state().name(),
+                        chars = new_prompt.len(),
                     );
                 }
             }
@@ -1410,20 +3052,26 @@ pub async fn run_agentic_loop(
         // Track if this iteration had errors for next iteration's state machine bypass
         previous_iteration_had_errors = had_errors_this_iteration;
         
-        println!(
-            "[AgenticLoop] Continuing to iteration {} (state: {}, error_retry={})...",
-            loop_iteration_index + 1,
-            state_machine.current_state().name(),
-            previous_iteration_had_errors
+        crate::log_event!(
+            "AgenticLoop",
+            "iteration_continuing",
+            next = loop_iteration_index + 1,
+            state = state_machine.current_state().name(),
+            error_retry = previous_iteration_had_errors,
         );
         loop_iteration_index += 1;
     }
 
-    println!(
-        "[AgenticLoop] Loop complete after {} iterations, had_tool_calls={}",
-        loop_iteration_index, had_tool_calls
+    crate::log_event!(
+        "AgenticLoop",
+        "loop_complete",
+        iterations = loop_iteration_index,
+        had_tool_calls = had_tool_calls,
     );
 
+    // Session variables only live for the duration of the turn
+    reset_python_session(&handles, &config).await;
+
     // Emit loop finished
     let _ = app_handle.emit(
         "tool-loop-finished",
@@ -1433,13 +3081,53 @@ pub async fn run_agentic_loop(
         },
     );
 
-    // Save chat to vector store
+    metrics.total_ms = turn_start.elapsed().as_millis() as u64;
+    crate::log_event!(
+        "AgenticLoop",
+        "turn_metrics",
+        model_calls = metrics.iterations,
+        tool_calls = metrics.tool_durations_ms.values().map(Vec::len).sum::<usize>(),
+        total_ms = metrics.total_ms,
+    );
+    let _ = app_handle.emit("turn-metrics", &metrics);
+
+    // Stop auto-saving so no provisional record lands after the final one
+    if let Some((stop_tx, task)) = autosave {
+        let _ = stop_tx.send(());
+        let _ = task.await;
+    }
+
+    // Save chat to vector store; a cancelled turn keeps what was generated so far
+    let cancelled = cancelled_mid_tool || cancelled_mid_stream;
+    if cancelled {
+        crate::log_event!("AgenticLoop", "cancelled_saving_partial", chars = final_response.len());
+        let cancelled_approvals = cancel_turn_approvals(
+            &handles.pending_approvals,
+            &config.chat_id,
+            config.generation_id,
+        )
+        .await;
+        if cancelled_approvals > 0 {
+            crate::log_event!(
+                "AgenticLoop",
+                "pending_approvals_cancelled",
+                count = cancelled_approvals,
+            );
+        }
+    }
     save_chat_to_vector_store(
         &handles.vector_tx,
         &config.chat_id,
         &config.title,
         &config.original_message,
         &final_response,
+        &history_for_storage(
+            &config.stored_history,
+            &full_history,
+            &config.original_message,
+            &final_response,
+            cancelled,
+        ),
         &handles.embedding_model,
     )
     .await;
@@ -1447,11 +3135,21 @@ pub async fn run_agentic_loop(
     // Emit chat-saved event for frontend
     let _ = app_handle.emit("chat-saved", &config.chat_id);
 
+    // A failed turn keeps its checkpoint so resume_turn can pick it up
+    if !turn_failed {
+        clear_checkpoint(&config.checkpoint_path).await;
+    }
+
     // Mark turn as complete in TurnProgress
     {
         let mut progress = turn_progress.write().await;
+        if !turn_failed {
+            progress.checkpoint = None;
+        }
+        progress.resumable = progress.checkpoint.is_some();
         progress.active = false;
-        progress.finished = true;
+        progress.finished = !cancelled;
+        progress.metrics = Some(metrics);
         progress.had_tool_calls = had_tool_calls;
         progress.assistant_response = final_response.clone();
         progress.timestamp_ms = std::time::SystemTime::now()
@@ -1461,11 +3159,157 @@ pub async fn run_agentic_loop(
     }
 
     // Signal chat completion to frontend
-    println!("[AgenticLoop] Emitting chat-finished event to frontend");
+    crate::log_event!("AgenticLoop", "chat_finished_emitting");
     match app_handle.emit("chat-finished", ()) {
-        Ok(_) => println!("[AgenticLoop] ✅ chat-finished event emitted successfully"),
-        Err(e) => println!("[AgenticLoop] ❌ Failed to emit chat-finished: {}", e),
+        Ok(_) => crate::log_event!("AgenticLoop", "chat_finished_emitted"),
+        Err(e) => crate::log_warn!(
+            "AgenticLoop",
+            "chat_finished_emit_failed",
+            error = e.to_string(),
+        ),
+    }
+}
+
+/// Keep the latest checkpoint on the turn tracker and mirror it to disk.
+async fn record_turn_checkpoint(
+    turn_progress: &Arc<RwLock<TurnProgress>>,
+    path: &Path,
+    checkpoint: TurnCheckpoint,
+) {
+    if let Err(e) = save_checkpoint(path, &checkpoint).await {
+        crate::log_warn!("AgenticLoop", "checkpoint_persist_failed", error = e);
+    }
+    turn_progress.write().await.checkpoint = Some(checkpoint);
+}
+
+/// Start upserting a provisional chat record every `chat_autosave_interval_secs`
+/// while the turn progresses. Each save has the messages of the current
+/// iteration (earlier tool calls and results included) and the answer text
+/// streamed so far, without reasoning or tool call markup. The record is
+/// embedded on the first save only.
+/// Returns the stop signal and task, or None when auto-save is off.
+fn spawn_chat_autosave(
+    handles: &AgenticLoopHandles,
+    config: &AgenticLoopConfig,
+    turn_progress: &Arc<RwLock<TurnProgress>>,
+) -> Option<(tokio::sync::oneshot::Sender<()>, tokio::task::JoinHandle<()>)> {
+    if config.chat_autosave_interval_secs == 0 {
+        return None;
+    }
+    let period = Duration::from_secs(config.chat_autosave_interval_secs);
+    let vector_tx = handles.vector_tx.clone();
+    let embedding_model = handles.embedding_model.clone();
+    let chat_id = config.chat_id.clone();
+    let title = config.title.clone();
+    let user_message = config.original_message.clone();
+    let stored_history = config.stored_history.clone();
+    let formats = config.format_config.clone();
+    let turn_progress = turn_progress.clone();
+    let (stop_tx, mut stop_rx) = tokio::sync::oneshot::channel::<()>();
+    let task = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        // The first tick fires immediately, before anything was generated
+        interval.tick().await;
+        let mut saved = (0, 0);
+        // Embedded once and reused; the final save embeds the finished answer
+        let mut embedding = None;
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let (history, partial) = {
+                        let progress = turn_progress.read().await;
+                        let answer =
+                            text_before_tool_call(&progress.iteration_visible_text, &formats);
+                        (progress.iteration_history.clone(), answer.to_string())
+                    };
+                    // Debounce: only save when the turn moved on since the last save
+                    if partial.is_empty() || (history.len(), partial.len()) == saved {
+                        continue;
+                    }
+                    saved = (history.len(), partial.len());
+                    let content = chat_record_content(&user_message, &partial);
+                    if embedding.is_none() {
+                        embedding = embed_chat_content(&content, &embedding_model).await;
+                    }
+                    upsert_chat_record(
+                        &vector_tx,
+                        &chat_id,
+                        &title,
+                        content,
+                        &provisional_history_for_storage(
+                            &stored_history,
+                            &history,
+                            &user_message,
+                            &partial,
+                        ),
+                        embedding.clone(),
+                    )
+                    .await;
+                }
+                _ = &mut stop_rx => break,
+            }
+        }
+    });
+    Some((stop_tx, task))
+}
+
+/// Messages of a provisional save: like `history_for_storage`, with the
+/// partial answer marked provisional so a reload shows it as interrupted.
+fn provisional_history_for_storage(
+    stored_history: &[ChatMessage],
+    full_history: &[ChatMessage],
+    user_message: &str,
+    partial_response: &str,
+) -> Vec<ChatMessage> {
+    let mut messages =
+        history_for_storage(stored_history, full_history, user_message, partial_response, false);
+    if let Some(answer) = messages.last_mut() {
+        answer.provisional = true;
     }
+    messages
+}
+
+/// Messages stored with the chat: the uncompacted conversation before this
+/// turn, this turn's user message, its tool calls and results (marked
+/// intermediate so they are kept but not shown), then the assistant's answer
+/// (marked if the user cancelled).
+fn history_for_storage(
+    stored_history: &[ChatMessage],
+    full_history: &[ChatMessage],
+    user_message: &str,
+    final_response: &str,
+    cancelled: bool,
+) -> Vec<ChatMessage> {
+    let mut messages: Vec<ChatMessage> = stored_history
+        .iter()
+        .filter(|m| m.role != "system")
+        .cloned()
+        .collect();
+    // The prompt as sent keeps its images; fall back to the plain text
+    match full_history
+        .iter()
+        .rposition(|m| m.role == "user" && m.content == user_message)
+    {
+        Some(turn_start) => {
+            messages.push(full_history[turn_start].clone());
+            messages.extend(full_history[turn_start + 1..].iter().map(|m| ChatMessage {
+                intermediate: true,
+                ..m.clone()
+            }));
+        }
+        None => messages.push(ChatMessage {
+            role: "user".to_string(),
+            content: user_message.to_string(),
+            ..Default::default()
+        }),
+    }
+    messages.push(ChatMessage {
+        role: "assistant".to_string(),
+        content: final_response.to_string(),
+        cancelled,
+        ..Default::default()
+    });
+    messages
 }
 
 /// Save the chat to the vector store for semantic search.
@@ -1475,30 +3319,47 @@ async fn save_chat_to_vector_store(
     title: &str,
     user_message: &str,
     assistant_response: &str,
+    messages: &[ChatMessage],
     embedding_model: &Arc<RwLock<Option<Arc<TextEmbedding>>>>,
 ) {
-    // Combine for embedding
-    let content = format!("User: {}\n\nAssistant: {}", user_message, assistant_response);
+    let content = chat_record_content(user_message, assistant_response);
+    let embedding = embed_chat_content(&content, embedding_model).await;
+    upsert_chat_record(vector_tx, chat_id, title, content, messages, embedding).await;
+}
 
-    // Get embedding
-    let model_guard = embedding_model.read().await;
-    let embedding = if let Some(model) = model_guard.as_ref() {
-        match model.embed(vec![content.clone()], None) {
-            Ok(embeddings) if !embeddings.is_empty() => Some(embeddings[0].clone()),
-            _ => None,
-        }
-    } else {
-        None
-    };
-    drop(model_guard);
+/// Text a chat record is embedded from
+fn chat_record_content(user_message: &str, assistant_response: &str) -> String {
+    format!("User: {}\n\nAssistant: {}", user_message, assistant_response)
+}
+
+/// Embed a chat record's content on the blocking pool; None without a model
+async fn embed_chat_content(
+    content: &str,
+    embedding_model: &Arc<RwLock<Option<Arc<TextEmbedding>>>>,
+) -> Option<Vec<f32>> {
+    let model = embedding_model.read().await.clone()?;
+    let content = content.to_string();
+    match tokio::task::spawn_blocking(move || model.embed(vec![content], None)).await {
+        Ok(Ok(embeddings)) => embeddings.into_iter().next(),
+        _ => None,
+    }
+}
 
-    // Save to vector store
+/// Send a chat record to the vector store (skipped there without an embedding)
+async fn upsert_chat_record(
+    vector_tx: &mpsc::Sender<VectorMsg>,
+    chat_id: &str,
+    title: &str,
+    content: String,
+    messages: &[ChatMessage],
+    embedding: Option<Vec<f32>>,
+) {
     let _ = vector_tx
         .send(VectorMsg::UpsertChatRecord {
             id: chat_id.to_string(),
             title: title.to_string(),
             content,
-            messages: String::new(), // Full history would be serialized here
+            messages: serde_json::to_string(messages).unwrap_or_default(),
             embedding_vector: embedding,
             pinned: false,
             model: None,
@@ -1510,6 +3371,417 @@ async fn save_chat_to_vector_store(
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_cancel_turn_approvals() {
+        let pending: PendingApprovals = Arc::new(RwLock::new(HashMap::new()));
+        let (this_tx, this_rx) = tokio::sync::oneshot::channel();
+        let (other_tx, _other_rx) = tokio::sync::oneshot::channel();
+        {
+            let mut approvals = pending.write().await;
+            approvals.insert("chat-1:7:0:0".to_string(), this_tx);
+            approvals.insert("chat-1:8:0:0".to_string(), other_tx);
+        }
+
+        assert_eq!(cancel_turn_approvals(&pending, "chat-1", 7).await, 1);
+        assert!(matches!(this_rx.await, Ok(ToolApprovalDecision::Cancelled)));
+        // Other turns' approvals are left alone
+        let remaining: Vec<String> = pending.read().await.keys().cloned().collect();
+        assert_eq!(remaining, vec!["chat-1:8:0:0".to_string()]);
+    }
+
+    #[test]
+    fn test_chat_retry_backoff() {
+        assert_eq!(chat_retry_backoff(1), Duration::from_secs(1));
+        assert_eq!(chat_retry_backoff(2), Duration::from_secs(2));
+        assert_eq!(chat_retry_backoff(3), Duration::from_secs(4));
+        assert_eq!(chat_retry_backoff(10), Duration::from_secs(30));
+        assert_eq!(
+            chat_retries_exhausted_message("Failed to send to model gateway", 3),
+            "Failed to send to model gateway (gave up after 3 attempts)"
+        );
+        assert_eq!(chat_retries_exhausted_message("No output", 1), "No output");
+    }
+
+    #[test]
+    fn test_last_progress_line() {
+        assert_eq!(last_progress_line("step 1\nstep 2\n\n"), Some("step 2"));
+        assert_eq!(last_progress_line("  50% done  "), Some("50% done"));
+        assert_eq!(last_progress_line("\n  \n"), None);
+    }
+
+    #[test]
+    fn test_history_for_storage_keeps_cancelled_partial_answer() {
+        let message = |role: &str, content: &str| ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let mut history = vec![
+            message("system", "You are helpful"),
+            message("user", "Write a poem"),
+        ];
+
+        let stored = history_for_storage(&[], &history, "Write a poem", "Roses are", true);
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0].role, "user");
+        assert_eq!(stored[1].content, "Roses are");
+        assert!(stored[1].cancelled);
+
+        let json = serde_json::to_string(&stored).unwrap();
+        let loaded: Vec<ChatMessage> = serde_json::from_str(&json).unwrap();
+        assert!(loaded[1].cancelled);
+        assert!(!loaded[0].cancelled);
+        assert!(!json.contains("\"cancelled\":false"));
+
+        // Tool traffic after the user message is stored, marked intermediate
+        history.push(message("assistant", "<tool_call>{}</tool_call>"));
+        history.push(message("user", "<tool_response>ok</tool_response>"));
+        let stored = history_for_storage(&[], &history, "Write a poem", "Done", false);
+        assert_eq!(stored.len(), 4);
+        assert!(!stored[0].intermediate);
+        assert!(stored[1].intermediate && stored[2].intermediate);
+        assert_eq!(stored[3].content, "Done");
+        assert!(!stored[3].cancelled && !stored[3].intermediate);
+        let json = serde_json::to_string(&stored).unwrap();
+        let loaded: Vec<ChatMessage> = serde_json::from_str(&json).unwrap();
+        assert!(loaded[2].intermediate);
+    }
+
+    #[test]
+    fn test_history_for_storage_keeps_turns_compacted_for_the_model() {
+        let message = |role: &str, content: &str| ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let earlier = vec![
+            message("user", "Hi"),
+            message("assistant", "Hello"),
+            message("user", "Weather?"),
+            message("assistant", "Sunny"),
+        ];
+        let sent = vec![
+            message("system", "You are helpful"),
+            message("assistant", "Summary of the earlier conversation"),
+            message("user", "Weather?"),
+            message("assistant", "Sunny"),
+            message("user", "Tomorrow?"),
+        ];
+        let stored = history_for_storage(&earlier, &sent, "Tomorrow?", "Rain", false);
+        let contents: Vec<&str> = stored.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["Hi", "Hello", "Weather?", "Sunny", "Tomorrow?", "Rain"]);
+
+        let provisional = provisional_history_for_storage(&earlier, &sent, "Tomorrow?", "Ra");
+        assert_eq!(provisional.len(), 6);
+        assert!(provisional[5].provisional);
+    }
+
+    #[test]
+    fn test_append_return_section() {
+        assert_eq!(append_return_section("42\n".to_string(), None), "42\n");
+        assert_eq!(
+            append_return_section("done".to_string(), Some("RETURN:\n[1, 2]".to_string())),
+            "done\n\nRETURN:\n[1, 2]"
+        );
+    }
+
+    #[test]
+    fn test_format_python_output_text() {
+        let output = CodeExecutionOutput {
+            stdout: "42\n".to_string(),
+            stderr: "warning".to_string(),
+            success: true,
+            ..Default::default()
+        };
+        let (text, is_error) = format_python_output(output, ResultFormat::Text);
+        assert_eq!(text, "STDOUT:\n42\n\n\nSTDERR:\nwarning");
+        assert!(!is_error);
+
+        let failed = CodeExecutionOutput {
+            stderr: "NameError: x".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            format_python_output(failed, ResultFormat::Text),
+            ("Error: NameError: x".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn test_format_python_output_json() {
+        let output = CodeExecutionOutput {
+            stderr: "NameError: x".to_string(),
+            tool_calls_made: vec![PythonToolCallRecord {
+                name: "get_weather".to_string(),
+                server: "weather".to_string(),
+                arguments: json!({ "city": "Oslo" }),
+                result_summary: "Sunny".to_string(),
+                is_error: false,
+            }],
+            ..Default::default()
+        };
+        let (text, is_error) = format_python_output(output, ResultFormat::Json);
+        let parsed: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(parsed["success"], json!(false));
+        assert_eq!(parsed["stderr"], json!("NameError: x"));
+        assert_eq!(parsed["tool_calls_made"], json!(1));
+        assert!(!text.contains("STDERR:"));
+        assert!(is_error);
+    }
+
+    fn tool_call(server: &str, tool: &str) -> ParsedToolCall {
+        ParsedToolCall {
+            server: server.to_string(),
+            tool: tool.to_string(),
+            arguments: json!({}),
+            raw: String::new(),
+            id: None,
+        }
+    }
+
+    #[test]
+    fn test_tool_override_forces_approval_on_auto_approve_server() {
+        let mut server = McpServerConfig::new("files".to_string(), "Files".to_string());
+        server.auto_approve_tools = true;
+        server
+            .auto_approve_tool_overrides
+            .insert("delete_file".to_string(), false);
+        let servers = vec![server];
+
+        assert!(!tool_call_requires_approval(
+            &tool_call("files", "read_file"),
+            &servers
+        ));
+        assert!(tool_call_requires_approval(
+            &tool_call("files", "delete_file"),
+            &servers
+        ));
+        assert!(tool_call_requires_approval(
+            &tool_call("other", "read_file"),
+            &servers
+        ));
+        assert!(!tool_call_requires_approval(
+            &tool_call("builtin", "python_execution"),
+            &servers
+        ));
+    }
+
+    #[test]
+    fn test_is_parallel_safe_tool_call() {
+        let mut auto = McpServerConfig::new("files".to_string(), "Files".to_string());
+        auto.auto_approve_tools = true;
+        auto.auto_approve_tool_overrides
+            .insert("delete_file".to_string(), false);
+        let prompting = McpServerConfig::new("mail".to_string(), "Mail".to_string());
+        let servers = vec![auto, prompting];
+
+        // Read-only built-ins only
+        assert!(is_parallel_safe_tool_call(&tool_call("builtin", "sql_select"), &servers));
+        assert!(is_parallel_safe_tool_call(&tool_call("builtin", "schema_search"), &servers));
+        assert!(!is_parallel_safe_tool_call(&tool_call("builtin", "python_execution"), &servers));
+        assert!(!is_parallel_safe_tool_call(&tool_call("builtin", "tool_search"), &servers));
+        // MCP calls that run without asking
+        assert!(is_parallel_safe_tool_call(&tool_call("files", "read_file"), &servers));
+        assert!(!is_parallel_safe_tool_call(&tool_call("files", "delete_file"), &servers));
+        assert!(!is_parallel_safe_tool_call(&tool_call("mail", "send"), &servers));
+        assert!(!is_parallel_safe_tool_call(&tool_call("unknown", "read_file"), &servers));
+    }
+
+    #[test]
+    fn test_tool_override_auto_approves_on_prompting_server() {
+        let mut server = McpServerConfig::new("files".to_string(), "Files".to_string());
+        server
+            .auto_approve_tool_overrides
+            .insert("read_file".to_string(), true);
+        let servers = vec![server];
+
+        assert!(!tool_call_requires_approval(
+            &tool_call("files", "read_file"),
+            &servers
+        ));
+        assert!(tool_call_requires_approval(
+            &tool_call("files", "write_file"),
+            &servers
+        ));
+    }
+
+    #[test]
+    fn test_turn_metrics_accumulate() {
+        let mut metrics = TurnMetrics::default();
+        metrics.record_iteration(IterationMetrics {
+            tokens: 40,
+            ttft_ms: Some(120),
+            duration_ms: 900,
+        });
+        metrics.record_iteration(IterationMetrics::default());
+        metrics.record_tool("builtin", "sql_select", Duration::from_millis(35));
+        metrics.record_tool("builtin", "sql_select", Duration::from_millis(50));
+        metrics.record_tool("files", "read_file", Duration::from_millis(7));
+
+        assert_eq!(metrics.iterations, 2);
+        assert_eq!(metrics.tool_durations_ms["builtin::sql_select"], vec![35, 50]);
+        assert_eq!(metrics.tool_durations_ms["files::read_file"], vec![7]);
+    }
+
+    #[test]
+    fn test_batch_declined_message_names_tools() {
+        let message = batch_declined_message(&[
+            tool_call("builtin", "sql_select"),
+            tool_call("files", "delete_file"),
+        ]);
+        assert!(message.starts_with("The user declined"));
+        assert!(message.contains("(sql_select, delete_file)"));
+    }
+
+    #[test]
+    fn test_tool_call_budget_counts_across_iterations() {
+        let calls = |n: usize| (0..n).map(|_| tool_call("files", "read_file")).collect::<Vec<_>>();
+
+        // First iteration fits entirely
+        let (run, skipped) = apply_tool_call_budget(calls(3), 0, 5);
+        assert_eq!((run.len(), skipped), (3, 0));
+        // Second iteration only has 2 of 5 left
+        let (run, skipped) = apply_tool_call_budget(calls(4), 3, 5);
+        assert_eq!((run.len(), skipped), (2, 2));
+        // Budget spent: nothing runs
+        let (run, skipped) = apply_tool_call_budget(calls(1), 5, 5);
+        assert_eq!((run.len(), skipped), (0, 1));
+        // 0 = no limit
+        let (run, skipped) = apply_tool_call_budget(calls(50), 100, 0);
+        assert_eq!((run.len(), skipped), (50, 0));
+
+        let message = tool_budget_exceeded_message(5, 2);
+        assert!(message.starts_with("Too many tool calls"));
+        assert!(message.contains("limit of 5 tool calls"));
+    }
+
+    #[test]
+    fn test_dedupe_tool_calls_keeps_first_sql_select() {
+        let mut first = tool_call("builtin", "sql_select");
+        first.arguments = json!({"sql": "SELECT 1", "source_id": "db"});
+        first.id = Some("call_1".to_string());
+        let mut repeat = tool_call("builtin", "sql_select");
+        repeat.arguments = json!({"source_id": "db", "sql": "SELECT 1"});
+        repeat.id = Some("call_2".to_string());
+        let mut other = tool_call("builtin", "sql_select");
+        other.arguments = json!({"sql": "SELECT 2", "source_id": "db"});
+
+        let (calls, dropped) = dedupe_tool_calls(vec![first, repeat, other]);
+
+        assert_eq!(dropped, 1);
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].id.as_deref(), Some("call_1"));
+        assert_eq!(calls[1].arguments["sql"], "SELECT 2");
+    }
+
+    #[test]
+    fn test_assign_correlation_ids_keeps_native_ids() {
+        let mut native = tool_call("builtin", "sql_select");
+        native.id = Some("call_1".to_string());
+        let mut calls = vec![native, tool_call("builtin", "echo"), tool_call("builtin", "echo")];
+
+        assign_correlation_ids(&mut calls);
+
+        assert_eq!(calls[0].id.as_deref(), Some("call_1"));
+        let generated: Vec<&str> = calls[1..].iter().filter_map(|c| c.id.as_deref()).collect();
+        assert_eq!(generated.len(), 2);
+        assert!(generated.iter().all(|id| id.starts_with("call_") && id.len() == 13));
+        assert_ne!(generated[0], generated[1]);
+    }
+
+    #[test]
+    fn test_mcp_tool_timeout_message() {
+        let message = mcp_tool_timeout_message("list_tables", Duration::from_secs(30));
+        assert!(message.contains("'list_tables'"));
+        assert!(message.contains("30 seconds"));
+    }
+
+    #[test]
+    fn test_normalize_error_signature() {
+        assert_eq!(
+            normalize_error_signature("Row 42 not found in 'orders_2024'"),
+            normalize_error_signature("Row 17 not found in 'orders_2025'")
+        );
+        assert_eq!(
+            normalize_error_signature(
+                "Job 3f2b8c1e-9a4d-4e6f-8b2a-1c3d5e7f9a0b failed at 2024-05-01 12:00:03"
+            ),
+            "job <uuid> failed at <n>-<n>-<n> <n>:<n>:<n>"
+        );
+        assert_ne!(
+            normalize_error_signature("Table not found"),
+            normalize_error_signature("Permission denied")
+        );
+    }
+
+    #[test]
+    fn test_repeated_error_tracker_exact_repeat_trips_immediately() {
+        let mut tracker = RepeatedErrorTracker::default();
+        assert!(!tracker.record("sql_select", "syntax error near FROM"));
+        assert!(tracker.record("sql_select", "syntax error near FROM"));
+    }
+
+    #[test]
+    fn test_repeated_error_tracker_similar_errors_hit_threshold() {
+        let mut tracker = RepeatedErrorTracker::default();
+        assert!(!tracker.record("sql_select", "Query 101 timed out after 30s"));
+        assert!(!tracker.record("python_execution", "NameError: name 'x' is not defined"));
+        assert!(!tracker.record("sql_select", "Query 102 timed out after 31s"));
+        assert!(tracker.record("sql_select", "Query 103 timed out after 29s"));
+    }
+
+    #[test]
+    fn test_repeated_error_tracker_window_forgets_old_errors() {
+        let mut tracker = RepeatedErrorTracker::new(3, 3);
+        assert!(!tracker.record("sql_select", "Query 1 timed out"));
+        assert!(!tracker.record("sql_select", "Query 2 timed out"));
+        assert!(!tracker.record("sql_select", "Table 'a' not found"));
+        assert!(!tracker.record("sql_select", "Table 'b' not found"));
+        // The first timeout has left the window, so this is only the 2nd of 3
+        assert!(!tracker.record("sql_select", "Query 3 timed out"));
+    }
+
+    #[tokio::test]
+    async fn test_new_turn_reenables_tools() {
+        let tool_disables = SharedToolDisables::default();
+        tool_disables.write().await.disable(ToolDisableInfo {
+            chat_id: "chat-1".to_string(),
+            generation_id: 3,
+            tool: "sql_select".to_string(),
+            error_signature: normalize_error_signature("Table 'orders' not found"),
+            disabled_at_ms: 0,
+        });
+        assert!(tool_disables.read().await.is_disabled("chat-1"));
+        assert!(!tool_disables.read().await.is_disabled("chat-2"));
+
+        let cleared = begin_turn_tool_disables(&tool_disables, "chat-1").await;
+        assert_eq!(cleared.map(|info| info.tool).as_deref(), Some("sql_select"));
+        assert!(!tool_disables.read().await.is_disabled("chat-1"));
+        assert!(begin_turn_tool_disables(&tool_disables, "chat-1").await.is_none());
+    }
+
+    #[test]
+    fn test_max_iterations_message_names_limit() {
+        let message = max_iterations_message(7);
+        assert!(message.contains("tool iteration limit of 7"));
+        assert!(message.contains("max_tool_iterations"));
+    }
+
+    #[test]
+    fn test_restore_stop_sequence_marker() {
+        let stops = vec!["</tool_call>".to_string()];
+        let mut response = r#"<tool_call>{"name": "echo", "arguments": {}}"#.to_string();
+        assert_eq!(restore_stop_sequence_marker(&mut response, &stops), Some("</tool_call>"));
+        assert!(response.ends_with("}}</tool_call>"));
+        // Already closed
+        assert_eq!(restore_stop_sequence_marker(&mut response, &stops), None);
+
+        // Not using stop sequences: left as the model wrote it
+        let mut response = r#"<tool_call>{"name": "echo""#.to_string();
+        assert_eq!(restore_stop_sequence_marker(&mut response, &[]), None);
+        assert_eq!(response, r#"<tool_call>{"name": "echo""#);
+    }
+
     #[test]
     fn test_detect_final_response() {
         let action = detect_agentic_loop_action(
@@ -1520,6 +3792,7 @@ mod tests {
             &ToolCallFormatConfig::default(),
             ToolCallFormatName::Hermes,
             false, // python_execution_in_native_tools
+            false, // python_multi_block
         );
 
         match action {
@@ -1529,9 +3802,46 @@ mod tests {
             AgenticLoopAction::ToolCalls { .. } => {
                 panic!("Expected Final, got ToolCalls");
             }
+            AgenticLoopAction::MalformedToolCall { hint } => {
+                panic!("Unexpected malformed tool call: {}", hint)
+            }
         }
     }
 
+    #[test]
+    fn test_reasoning_block_is_not_a_tool_call() {
+        let response = "<think>\nI could run <tool_call>{\"name\": \"sql_select\", \"arguments\": {\"sql\": \"SELECT 1\"}}</tool_call>\nbut the answer is known.\n</think>\n\nThe answer is 1.";
+        let mut config = ToolCallFormatConfig::default();
+        config.enabled = vec![ToolCallFormatName::Hermes];
+
+        let (reasoning, answer) = split_reasoning(response, ReasoningFormat::ThinkTags);
+        assert!(reasoning.contains("<tool_call>"));
+        assert!(!any_format_complete(&answer, &config));
+        let action = detect_agentic_loop_action(
+            &answer,
+            ModelFamily::Phi,
+            ToolFormat::Hermes,
+            false,
+            &config,
+            ToolCallFormatName::Hermes,
+            false, // python_execution_in_native_tools
+            false, // python_multi_block
+        );
+        let AgenticLoopAction::Final { response } = action else {
+            panic!("Reasoning should not produce tool calls");
+        };
+        assert_eq!(response, "The answer is 1.");
+
+        let history = vec![ChatMessage {
+            role: "user".to_string(),
+            content: "What is SELECT 1?".to_string(),
+            ..Default::default()
+        }];
+        let stored = history_for_storage(&[], &history, "What is SELECT 1?", &response, false);
+        assert_eq!(stored[1].content, "The answer is 1.");
+        assert!(!stored.iter().any(|m| m.content.contains("<think>")));
+    }
+
     #[test]
     fn test_detect_tool_call() {
         let response = r#"<tool_call>{"name": "sql_select", "arguments": {"sql": "SELECT 1"}}</tool_call>"#;
@@ -1546,6 +3856,7 @@ mod tests {
             &config,
             ToolCallFormatName::Hermes,
             false, // python_execution_in_native_tools
+            false, // python_multi_block
         );
 
         match action {
@@ -1556,6 +3867,9 @@ mod tests {
             AgenticLoopAction::Final { .. } => {
                 panic!("Expected ToolCalls, got Final");
             }
+            AgenticLoopAction::MalformedToolCall { hint } => {
+                panic!("Unexpected malformed tool call: {}", hint)
+            }
         }
     }
 
@@ -1578,6 +3892,7 @@ print(f"The answer is {result}")
             &ToolCallFormatConfig::default(),
             ToolCallFormatName::Native,
             true, // python_execution_in_native_tools - this is the key!
+            false, // python_multi_block
         );
 
         match action {
@@ -1593,6 +3908,931 @@ print(f"The answer is {result}")
             AgenticLoopAction::Final { .. } => {
                 panic!("Expected ToolCalls for Python block detection, got Final");
             }
+            AgenticLoopAction::MalformedToolCall { hint } => {
+                panic!("Unexpected malformed tool call: {}", hint)
+            }
+        }
+    }
+
+    #[test]
+    fn test_multi_block_python_runs_as_one_program() {
+        use python_sandbox::protocol::{ExecutionRequest, ExecutionStatus};
+
+        let response = r#"First load the numbers:
+
+```python
+values = [3, 4, 5]
+```
+
+Then total them:
+
+```python
+print(sum(values))
+```"#;
+
+        let code_for = |multi_block: bool| -> Vec<String> {
+            match detect_agentic_loop_action(
+                response,
+                ModelFamily::Generic,
+                ToolFormat::TextBased,
+                true, // python_tool_mode
+                &code_mode_formats(),
+                ToolCallFormatName::CodeMode,
+                false, // python_execution_in_native_tools
+                multi_block,
+            ) {
+                AgenticLoopAction::ToolCalls { calls } => {
+                    serde_json::from_value(calls[0].arguments["code"].clone()).unwrap()
+                }
+                other => panic!("Expected a python_execution call, got {:?}", other),
+            }
+        };
+
+        // Off: only the first block runs
+        assert_eq!(code_for(false), vec!["values = [3, 4, 5]".to_string()]);
+
+        let program = code_for(true);
+        assert_eq!(
+            program,
+            vec![
+                "values = [3, 4, 5]".to_string(),
+                String::new(),
+                "print(sum(values))".to_string(),
+            ]
+        );
+        let result = python_sandbox::execute(&ExecutionRequest::new(program));
+        assert_eq!(result.status, ExecutionStatus::Complete);
+        assert_eq!(result.stdout.trim(), "12");
+    }
+
+    fn code_mode_formats() -> ToolCallFormatConfig {
+        ToolCallFormatConfig {
+            enabled: vec![ToolCallFormatName::CodeMode],
+            primary: ToolCallFormatName::CodeMode,
+        }
+    }
+
+    #[test]
+    fn test_detect_final_answer_in_code_mode() {
+        let formats = code_mode_formats();
+        for response in [
+            "```python\nfinal_answer(\"The total is 42.\")\n```",
+            "final_answer(answer='The total is 42.')",
+        ] {
+            let action = detect_agentic_loop_action(
+                response,
+                ModelFamily::Generic,
+                ToolFormat::TextBased,
+                true, // python_tool_mode
+                &formats,
+                ToolCallFormatName::CodeMode,
+                false,
+                false, // python_multi_block
+            );
+            match action {
+                AgenticLoopAction::Final { response } => assert_eq!(response, "The total is 42."),
+                AgenticLoopAction::ToolCalls { .. } => panic!("Expected Final for final_answer"),
+                AgenticLoopAction::MalformedToolCall { hint } => {
+                    panic!("Unexpected malformed tool call: {}", hint)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_detect_final_answer_alongside_code_still_runs_code() {
+        let response = "```python\ntotal = 40 + 2\nfinal_answer(str(total))\n```";
+        let action = detect_agentic_loop_action(
+            response,
+            ModelFamily::Generic,
+            ToolFormat::TextBased,
+            true,
+            &code_mode_formats(),
+            ToolCallFormatName::CodeMode,
+            false,
+            false, // python_multi_block
+        );
+
+        match action {
+            AgenticLoopAction::ToolCalls { calls } => assert_eq!(calls[0].tool, "python_execution"),
+            AgenticLoopAction::Final { .. } => panic!("Expected python_execution, got Final"),
+            AgenticLoopAction::MalformedToolCall { hint } => {
+                panic!("Unexpected malformed tool call: {}", hint)
+            }
+        }
+    }
+
+    #[test]
+    fn test_detect_final_answer_tool_call() {
+        let response = r#"<tool_call>{"name": "final_answer", "arguments": {"answer": "All done."}}</tool_call>"#;
+        let mut config = ToolCallFormatConfig::default();
+        config.enabled = vec![ToolCallFormatName::Hermes];
+
+        let action = detect_agentic_loop_action(
+            response,
+            ModelFamily::Phi,
+            ToolFormat::Hermes,
+            false,
+            &config,
+            ToolCallFormatName::Hermes,
+            false,
+            false, // python_multi_block
+        );
+
+        match action {
+            AgenticLoopAction::Final { response } => assert_eq!(response, "All done."),
+            AgenticLoopAction::ToolCalls { .. } => panic!("Expected Final for final_answer"),
+            AgenticLoopAction::MalformedToolCall { hint } => {
+                panic!("Unexpected malformed tool call: {}", hint)
+            }
+        }
+    }
+
+    #[test]
+    fn test_detect_final_answer_ignores_prose_mentions() {
+        let response = "I will call final_answer(\"done\") once the query finishes.";
+        let mut config = ToolCallFormatConfig::default();
+        config.enabled = vec![ToolCallFormatName::Hermes];
+
+        let action = detect_agentic_loop_action(
+            response,
+            ModelFamily::Phi,
+            ToolFormat::Hermes,
+            false,
+            &config,
+            ToolCallFormatName::Hermes,
+            false,
+            false, // python_multi_block
+        );
+        match action {
+            AgenticLoopAction::Final { response: text } => assert_eq!(text, response),
+            AgenticLoopAction::ToolCalls { .. } => panic!("Expected Final, got ToolCalls"),
+            AgenticLoopAction::MalformedToolCall { hint } => {
+                panic!("Unexpected malformed tool call: {}", hint)
+            }
+        }
+
+        let action = detect_agentic_loop_action(
+            response,
+            ModelFamily::Generic,
+            ToolFormat::TextBased,
+            true,
+            &code_mode_formats(),
+            ToolCallFormatName::CodeMode,
+            false,
+            false, // python_multi_block
+        );
+        if let AgenticLoopAction::Final { response: text } = action {
+            assert_ne!(text, "done");
+        }
+    }
+
+    fn detect_with_format(response: &str, format: ToolCallFormatName) -> AgenticLoopAction {
+        let formats = ToolCallFormatConfig {
+            enabled: vec![format],
+            primary: format,
+        };
+        detect_agentic_loop_action(
+            response,
+            ModelFamily::Generic,
+            ToolFormat::OpenAI,
+            false,
+            &formats,
+            format,
+            false,
+            false, // python_multi_block
+        )
+    }
+
+    #[test]
+    fn test_detect_truncated_hermes_tool_call() {
+        let response = r#"Checking.
+<tool_call>{"name": "get_weather", "arguments": {"city": "Os"#;
+
+        match detect_with_format(response, ToolCallFormatName::Hermes) {
+            AgenticLoopAction::MalformedToolCall { hint } => {
+                assert!(hint.contains("</tool_call>"), "hint: {}", hint);
+            }
+            other => panic!("Expected MalformedToolCall, got {:?}", other),
+        }
+        assert!(malformed_tool_call_message("x").starts_with("Your tool call was malformed: x."));
+
+        let complete = r#"<tool_call>{"name": "get_weather", "arguments": {"city": "Oslo"}}</tool_call>"#;
+        assert!(matches!(
+            detect_with_format(complete, ToolCallFormatName::Hermes),
+            AgenticLoopAction::ToolCalls { .. }
+        ));
+    }
+
+    #[test]
+    fn test_detect_truncated_pure_json_tool_call() {
+        let response = r#"{"name": "get_weather", "arguments": {"city": "Oslo"}"#;
+
+        match detect_with_format(response, ToolCallFormatName::PureJson) {
+            AgenticLoopAction::MalformedToolCall { hint } => {
+                assert!(hint.starts_with("the JSON tool call is invalid"), "hint: {}", hint);
+            }
+            other => panic!("Expected MalformedToolCall, got {:?}", other),
+        }
+
+        // Prose and valid JSON answers are not tool call attempts
+        for answer in ["The weather is fine.", r#"Reply with {"name": "...""#] {
+            assert_eq!(
+                detect_with_format(answer, ToolCallFormatName::PureJson),
+                AgenticLoopAction::Final {
+                    response: answer.to_string()
+                }
+            );
+        }
+    }
+
+    // ========== Scripted turns ==========
+
+    const SCRIPTED_USER_MESSAGE: &str = "What's the weather in Oslo?";
+
+    /// What a scripted turn sent to the model and how it ended
+    struct ScriptedTurn {
+        /// Messages of each chat request, in order
+        requests: Vec<Vec<ChatMessage>>,
+        /// Turn state after the loop returned
+        progress: TurnProgress,
+        /// Messages JSON of the chat record saved at the end of the turn
+        saved_messages: Option<String>,
+    }
+
+    /// Loop config for a scripted turn: no actors behind the tools, no timeouts
+    fn scripted_config(
+        format_config: ToolCallFormatConfig,
+        checkpoint_path: PathBuf,
+    ) -> AgenticLoopConfig {
+        AgenticLoopConfig {
+            chat_id: "scripted-chat".to_string(),
+            generation_id: 1,
+            title: "Scripted".to_string(),
+            original_message: SCRIPTED_USER_MESSAGE.to_string(),
+            stored_history: Vec::new(),
+            model_name: "scripted-model".to_string(),
+            reasoning_effort: "low".to_string(),
+            python_tool_mode: false,
+            primary_format: format_config.primary,
+            format_config,
+            allow_tool_search_for_python: false,
+            tool_search_max_results: 5,
+            tool_search_min_relevance: 0.0,
+            turn_system_prompt: "You are a helpful assistant.".to_string(),
+            chat_format_default: ChatFormatName::OpenaiCompletions,
+            chat_format_overrides: HashMap::new(),
+            enabled_db_sources: Vec::new(),
+            server_configs: Vec::new(),
+            tabular_context: None,
+            python_execution_in_native_tools: false,
+            python_execution_timeout_ms: 0,
+            python_session_enabled: false,
+            python_allowed_modules: None,
+            python_memory_limit_bytes: None,
+            max_tool_iterations: 20,
+            max_tool_calls_per_turn: 0,
+            parallel_tool_calls: false,
+            batch_approval_mode: false,
+            tool_call_ids_in_text: false,
+            mcp_tool_timeout_secs: 0,
+            tool_heartbeat_interval_ms: 0,
+            chat_autosave_interval_secs: 0,
+            python_result_format: ResultFormat::Text,
+            sql_result_format: SqlResultFormat::default(),
+            reasoning_format: ReasoningFormat::None,
+            reasoning_events_enabled: false,
+            python_multi_block: false,
+            max_tool_result_chars: 0,
+            summarize_large_tool_results: false,
+            tool_result_summary_threshold_chars: 0,
+            foundry_chat_retries: 0,
+            first_token_timeout_secs: 0,
+            model_supports_vision: false,
+            response_schema: None,
+            response_schema_native: false,
+            temperature: None,
+            seed: None,
+            stop_sequences: Vec::new(),
+            turn_request: ChatTurnRequest::default(),
+            start_iteration: 0,
+            checkpoint_path,
+        }
+    }
+
+    /// Run one turn of `run_agentic_loop` against a model that answers each
+    /// chat request with the next entry of `script`, using Hermes tool calls.
+    async fn run_scripted_turn(script: Vec<String>) -> ScriptedTurn {
+        run_scripted_turn_with(script, &[ToolCallFormatName::Hermes], |_| {}).await
+    }
+
+    /// `run_scripted_turn` with the given tool call formats (the first is
+    /// primary) and a hook to adjust the loop config. No tools are enabled
+    /// (python_execution is, when the config has `python_tool_mode`) and no actor
+    /// sits behind the MCP or database channels; Python runs in a real sandbox.
+    async fn run_scripted_turn_with(
+        script: Vec<String>,
+        formats: &[ToolCallFormatName],
+        configure: impl FnOnce(&mut AgenticLoopConfig),
+    ) -> ScriptedTurn {
+        run_scripted_turn_inner(script, formats, configure, false).await
+    }
+
+    /// `run_scripted_turn_with`, optionally stopping the turn (like the stop
+    /// button) as soon as it waits for a tool approval
+    async fn run_scripted_turn_inner(
+        script: Vec<String>,
+        formats: &[ToolCallFormatName],
+        configure: impl FnOnce(&mut AgenticLoopConfig),
+        stop_on_approval: bool,
+    ) -> ScriptedTurn {
+        let mut settings = crate::settings::AppSettings::default();
+        settings.tool_call_formats.primary = formats[0];
+        settings.tool_call_formats.enabled = formats.to_vec();
+        settings.tool_call_formats.normalize();
+
+        let checkpoint_dir = tempfile::tempdir().unwrap();
+        let mut config = scripted_config(
+            settings.tool_call_formats.clone(),
+            checkpoint_dir.path().join("turn_checkpoint.json"),
+        );
+        configure(&mut config);
+        if config.python_tool_mode {
+            settings.always_on_builtin_tools.push("python_execution".to_string());
+        }
+
+        let settings_sm = crate::settings_state_machine::SettingsStateMachine::from_settings(
+            &settings,
+            &crate::tool_capability::ToolLaunchFilter::default(),
+        );
+        let state_machine = AgenticStateMachine::new_from_settings_sm(
+            &settings_sm,
+            crate::agentic_state::PromptContext {
+                base_prompt: config.turn_system_prompt.clone(),
+                has_attachments: false,
+                attached_tables: Vec::new(),
+                attached_tools: Vec::new(),
+                attached_tabular_files: Vec::new(),
+                tabular_column_info: Vec::new(),
+                mcp_context: crate::agentic_state::McpToolContext::default(),
+                tool_call_format: config.primary_format,
+                model_tool_format: None,
+                custom_tool_prompts: HashMap::new(),
+                python_primary: false,
+                prepend: None,
+                append: None,
+            },
+        );
+
+        // The scripted model: each chat request gets the next response, streamed
+        // line by line until the loop stops it early, then the stream ends
+        let (foundry_tx, mut foundry_rx) = mpsc::channel::<FoundryMsg>(8);
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            let mut script = script.into_iter();
+            while let Some(msg) = foundry_rx.recv().await {
+                if let FoundryMsg::Chat(request) = msg {
+                    let ChatRequest {
+                        chat_history_messages,
+                        respond_to,
+                        stream_cancel_rx,
+                        ..
+                    } = *request;
+                    recorded.lock().unwrap().push(chat_history_messages);
+                    if let Some(response) = script.next() {
+                        for line in response.split_inclusive('\n') {
+                            if *stream_cancel_rx.borrow() || respond_to.send(line.to_string()).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        let (vector_tx, mut vector_rx) = mpsc::channel::<VectorMsg>(8);
+        let tool_registry = crate::tool_registry::create_shared_registry();
+        let (python_tx, python_rx) = crate::actors::python_actor::create_python_channel();
+        let python_actor = crate::actors::python_actor::PythonSandboxActor::new(
+            python_rx,
+            tool_registry.clone(),
+            mpsc::channel(1).0,
+            Arc::new(RwLock::new(None)),
+            Arc::new(RwLock::new(settings.clone())),
+        );
+        tokio::spawn(python_actor.run());
+        let handles = AgenticLoopHandles {
+            foundry_tx,
+            mcp_host_tx: mpsc::channel(1).0,
+            vector_tx,
+            python_tx,
+            schema_tx: mpsc::channel(1).0,
+            database_toolbox_tx: mpsc::channel(1).0,
+            tool_registry,
+            embedding_model: Arc::new(RwLock::new(None)),
+            pending_approvals: Arc::new(RwLock::new(HashMap::new())),
+            tool_disables: Arc::new(RwLock::new(Default::default())),
+        };
+        let history = crate::build_turn_messages(
+            &config.turn_system_prompt,
+            &[],
+            SCRIPTED_USER_MESSAGE,
+            Vec::new(),
+        );
+        let app = tauri::test::mock_app();
+        let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+        if stop_on_approval {
+            let pending = handles.pending_approvals.clone();
+            let cancel_tx = cancel_tx.clone();
+            tokio::spawn(async move {
+                while pending.read().await.is_empty() {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                let _ = cancel_tx.send(true);
+            });
+        }
+        let turn_progress = Arc::new(RwLock::new(TurnProgress::default()));
+
+        run_agentic_loop(
+            handles,
+            config,
+            app.handle().clone(),
+            history,
+            cancel_rx,
+            None,
+            turn_progress.clone(),
+            state_machine,
+        )
+        .await;
+
+        let requests = requests.lock().unwrap().clone();
+        let progress = turn_progress.read().await.clone();
+        let mut saved_messages = None;
+        while let Ok(msg) = vector_rx.try_recv() {
+            if let VectorMsg::UpsertChatRecord { messages, .. } = msg {
+                saved_messages = Some(messages);
+            }
+        }
+        ScriptedTurn {
+            requests,
+            progress,
+            saved_messages,
+        }
+    }
+
+    fn hermes_call(tool: &str, arguments: Value) -> String {
+        format!(
+            "<tool_call>{}</tool_call>",
+            json!({ "name": tool, "arguments": arguments })
+        )
+    }
+
+    #[tokio::test]
+    async fn test_scripted_turn_plain_answer_ends_after_one_request() {
+        let turn = run_scripted_turn(vec!["It's sunny in Oslo.".to_string()]).await;
+
+        assert_eq!(turn.requests.len(), 1);
+        let first = &turn.requests[0];
+        assert_eq!(first[0].role, "system");
+        assert_eq!(first.last().unwrap().role, "user");
+        assert_eq!(first.last().unwrap().content, SCRIPTED_USER_MESSAGE);
+        assert_eq!(turn.progress.assistant_response, "It's sunny in Oslo.");
+        assert!(!turn.progress.had_tool_calls);
+        assert!(turn.progress.finished);
+    }
+
+    #[tokio::test]
+    async fn test_scripted_turn_text_tool_result_goes_back_as_user_message() {
+        let call = hermes_call("tool_search", json!({ "queries": ["weather"] }));
+        let turn = run_scripted_turn(vec![call.clone(), "I can't look that up.".to_string()]).await;
+
+        assert_eq!(turn.requests.len(), 2);
+        let second = &turn.requests[1];
+        let n = second.len();
+        // The model's tool call, then its result as a user message
+        assert_eq!(second[n - 2].role, "assistant");
+        assert!(second[n - 2].content.contains("tool_search"));
+        assert_eq!(second[n - 1].role, "user");
+        assert!(second[n - 1]
+            .content
+            .contains("tool_search is currently disabled"));
+        assert!(second.iter().all(|m| m.role != "tool"));
+        assert_eq!(turn.progress.assistant_response, "I can't look that up.");
+        assert!(turn.progress.had_tool_calls);
+    }
+
+    #[tokio::test]
+    async fn test_scripted_turn_export_includes_stored_tool_calls() {
+        let call = hermes_call("tool_search", json!({ "queries": ["weather"] }));
+        let turn = run_scripted_turn(vec![call, "I can't look that up.".to_string()]).await;
+        let saved = turn.saved_messages.expect("the turn was not saved");
+
+        let markdown = crate::chat_export::render_stored_chat_export(
+            "scripted-chat",
+            &saved,
+            crate::chat_export::ExportFormat::Markdown,
+        )
+        .unwrap();
+        assert!(markdown.contains("### Tool call: builtin::tool_search"), "{}", markdown);
+        assert!(markdown.contains("\"weather\""));
+        assert!(markdown.contains("tool_search is currently disabled"));
+        assert!(markdown.trim_end().ends_with("I can't look that up."));
+    }
+
+    #[tokio::test]
+    async fn test_scripted_turn_multi_block_streams_every_python_block() {
+        let response = "```python\nx = 1\n```\nNow print it:\n```python\nprint(x)\n```\n";
+        for multi_block in [true, false] {
+            let turn = run_scripted_turn_with(
+                vec![response.to_string(), "Done.".to_string()],
+                &[ToolCallFormatName::CodeMode],
+                |config| {
+                    config.python_tool_mode = true;
+                    config.python_multi_block = multi_block;
+                },
+            )
+            .await;
+
+            assert_eq!(turn.requests.len(), 2);
+            let program = turn.requests[1]
+                .iter()
+                .rfind(|m| m.role == "assistant")
+                .unwrap();
+            assert!(program.content.contains("x = 1"));
+            // Without multi_block the stream stops at the first closed block
+            assert_eq!(program.content.contains("print(x)"), multi_block, "{}", program.content);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scripted_turn_code_calling_final_answer_ends_turn() {
+        let program = "```python\ntotal = 40 + 2\nfinal_answer(f\"The total is {total}.\")\n```\n";
+        let turn = run_scripted_turn_with(
+            vec![program.to_string(), "Not requested.".to_string()],
+            &[ToolCallFormatName::CodeMode],
+            |config| config.python_tool_mode = true,
+        )
+        .await;
+
+        // The code ran once and its answer ended the turn without another request
+        assert_eq!(turn.requests.len(), 1);
+        assert!(turn.progress.had_tool_calls);
+        assert_eq!(turn.progress.assistant_response, "The total is 42.");
+    }
+
+    #[tokio::test]
+    async fn test_scripted_turn_text_results_show_extract_result_id() {
+        let program = "```python\nprint(40 + 2)\n```\n";
+        let turn = run_scripted_turn_with(
+            vec![program.to_string(), "Done.".to_string()],
+            &[ToolCallFormatName::CodeMode],
+            |config| config.python_tool_mode = true,
+        )
+        .await;
+
+        // The result is marked with the id extract looks it up by, even
+        // though call ids in text are off
+        assert_eq!(turn.requests.len(), 2);
+        let result = turn.requests[1].last().unwrap();
+        assert_eq!(result.role, "user");
+        assert!(result.content.contains("<!-- tool_call_id: result_1 -->"), "{}", result.content);
+    }
+
+    #[test]
+    fn test_multi_block_skips_code_mode_early_stop() {
+        let mut config = scripted_config(
+            ToolCallFormatConfig {
+                enabled: vec![ToolCallFormatName::CodeMode, ToolCallFormatName::Hermes],
+                primary: ToolCallFormatName::CodeMode,
+            },
+            PathBuf::new(),
+        );
+        let block = "```python\nx = 1\n```\n";
+        assert!(tool_call_complete_while_streaming(block, &config));
+        config.python_multi_block = true;
+        assert!(!tool_call_complete_while_streaming(block, &config));
+        // Other formats still stop the stream
+        let call = hermes_call("sql_select", json!({ "sql": "SELECT 1" }));
+        assert!(tool_call_complete_while_streaming(&call, &config));
+    }
+
+    #[tokio::test]
+    async fn test_scripted_turn_native_calls_without_ids_get_text_results() {
+        // The gateway re-emits native calls as <tool_call> text without ids,
+        // so their results can't be tool messages
+        let call = hermes_call("sql_select", json!({ "sql": "SELECT 1" }));
+        let turn = run_scripted_turn_with(
+            vec![call, "Done.".to_string()],
+            &[ToolCallFormatName::Native, ToolCallFormatName::Hermes],
+            |_| {},
+        )
+        .await;
+
+        assert_eq!(turn.requests.len(), 2);
+        let second = &turn.requests[1];
+        assert!(second.iter().all(|m| m.role != "tool"));
+        assert!(second
+            .last()
+            .unwrap()
+            .content
+            .contains("sql_select is currently disabled"));
+        assert_eq!(turn.progress.assistant_response, "Done.");
+    }
+
+    #[tokio::test]
+    async fn test_scripted_turn_repeated_error_disables_tools() {
+        let call = hermes_call("tool_search", json!({ "queries": ["weather"] }));
+        // The third response is another call, but tools are off by then
+        let turn = run_scripted_turn(vec![call.clone(), call.clone(), call.clone()]).await;
+
+        assert_eq!(turn.requests.len(), 3);
+        assert_eq!(turn.progress.assistant_response, call);
+    }
+
+    #[tokio::test]
+    async fn test_scripted_turn_schema_mismatch_after_repair_fails_turn() {
+        let script = vec!["not json".to_string(), "still not json".to_string()];
+        let turn = run_scripted_turn_with(script, &[ToolCallFormatName::Hermes], |config| {
+            config.response_schema = Some(json!({
+                "type": "object",
+                "properties": { "city": { "type": "string" } },
+                "required": ["city"]
+            }));
+        })
+        .await;
+
+        // One repair request, then the turn ends failed with the answer it got
+        assert_eq!(turn.requests.len(), 2);
+        assert!(turn.requests[1].last().unwrap().content.contains("JSON schema"));
+        assert!(turn.progress.resumable);
+        assert!(turn.progress.checkpoint.is_some());
+        assert!(turn.progress.assistant_response.ends_with("still not json"));
+    }
+
+    #[tokio::test]
+    async fn test_resumed_turn_keeps_earlier_turns() {
+        let earlier = vec![
+            ChatMessage {
+                role: "user".to_string(),
+                content: "Where is Oslo?".to_string(),
+                ..Default::default()
+            },
+            ChatMessage {
+                role: "assistant".to_string(),
+                content: "In Norway.".to_string(),
+                ..Default::default()
+            },
+        ];
+        let stored = earlier.clone();
+        let failed = run_scripted_turn_with(
+            vec!["not json".to_string(), "still not json".to_string()],
+            &[ToolCallFormatName::Hermes],
+            |config| {
+                config.stored_history = stored;
+                config.response_schema = Some(json!({ "type": "object" }));
+            },
+        )
+        .await;
+        let checkpoint = failed.progress.checkpoint.expect("failed turn keeps its checkpoint");
+        assert_eq!(checkpoint.stored_history.len(), 2);
+
+        // Resume the way start_chat_turn does: the checkpoint's stored history, not the request's
+        assert!(checkpoint.request.history.is_empty());
+        let resumed = run_scripted_turn_with(
+            vec!["It's sunny in Oslo.".to_string()],
+            &[ToolCallFormatName::Hermes],
+            |config| {
+                config.stored_history = checkpoint.stored_history.clone();
+                config.start_iteration = checkpoint.iteration;
+            },
+        )
+        .await;
+        let saved: Vec<ChatMessage> =
+            serde_json::from_str(&resumed.saved_messages.expect("resumed turn was saved")).unwrap();
+        assert_eq!(saved[0].content, earlier[0].content);
+        assert_eq!(saved[1].content, earlier[1].content);
+        assert_eq!(saved.last().unwrap().content, "It's sunny in Oslo.");
+    }
+
+    #[tokio::test]
+    async fn test_scripted_turn_stops_at_max_iterations() {
+        // Different tools fail with different errors, so the repeated-error check stays quiet
+        let script = vec![
+            hermes_call("tool_search", json!({ "queries": ["weather"] })),
+            hermes_call("schema_search", json!({ "query": "weather" })),
+            hermes_call("sql_select", json!({ "sql": "SELECT 1" })),
+            "Never requested.".to_string(),
+        ];
+        let turn = run_scripted_turn_with(script, &[ToolCallFormatName::Hermes], |config| {
+            config.max_tool_iterations = 2;
+        })
+        .await;
+
+        assert_eq!(turn.requests.len(), 3);
+        assert!(turn
+            .progress
+            .assistant_response
+            .contains(&max_iterations_message(2)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_chat_autosave_upserts_provisional_record() {
+        let checkpoint_dir = tempfile::tempdir().unwrap();
+        let mut config = scripted_config(
+            ToolCallFormatConfig::default(),
+            checkpoint_dir.path().join("turn_checkpoint.json"),
+        );
+        config.chat_autosave_interval_secs = 1;
+        let (vector_tx, mut vector_rx) = mpsc::channel::<VectorMsg>(8);
+        let handles = AgenticLoopHandles {
+            foundry_tx: mpsc::channel(1).0,
+            mcp_host_tx: mpsc::channel(1).0,
+            vector_tx,
+            python_tx: mpsc::channel(1).0,
+            schema_tx: mpsc::channel(1).0,
+            database_toolbox_tx: mpsc::channel(1).0,
+            tool_registry: crate::tool_registry::create_shared_registry(),
+            embedding_model: Arc::new(RwLock::new(None)),
+            pending_approvals: Arc::new(RwLock::new(HashMap::new())),
+            tool_disables: Arc::new(RwLock::new(Default::default())),
+        };
+        let user = ChatMessage {
+            role: "user".to_string(),
+            content: config.original_message.clone(),
+            ..Default::default()
+        };
+        let turn_progress = Arc::new(RwLock::new(TurnProgress::default()));
+
+        let (stop_tx, task) = spawn_chat_autosave(&handles, &config, &turn_progress).unwrap();
+        {
+            let mut progress = turn_progress.write().await;
+            progress.iteration_history = vec![user.clone()];
+            progress.iteration_visible_text = "Half an ans".to_string();
         }
+
+        let saved = vector_rx.recv().await.expect("no provisional save");
+        let VectorMsg::UpsertChatRecord { id, messages, embedding_vector, .. } = saved else {
+            panic!("expected an upsert");
+        };
+        assert_eq!(id, config.chat_id);
+        let messages: Vec<ChatMessage> = serde_json::from_str(&messages).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].content, "Half an ans");
+        assert!(messages[1].provisional);
+        assert!(!messages[0].provisional);
+
+        // A later iteration is saved with its tool traffic and only its answer text,
+        // without the markup of the call it is streaming; the embedding is reused
+        let tool_result = ChatMessage {
+            role: "user".to_string(),
+            content: "Sunny".to_string(),
+            ..user.clone()
+        };
+        {
+            let mut progress = turn_progress.write().await;
+            progress.iteration_history = vec![user.clone(), tool_result];
+            progress.iteration_visible_text = "Checking again. <tool_call>{\"na".to_string();
+            progress.assistant_response = "Half an answer <tool_call>...".to_string();
+        }
+        let saved = vector_rx.recv().await.expect("no second provisional save");
+        let VectorMsg::UpsertChatRecord { content, messages, embedding_vector: reused, .. } =
+            saved
+        else {
+            panic!("expected an upsert");
+        };
+        let messages: Vec<ChatMessage> = serde_json::from_str(&messages).unwrap();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[1].intermediate);
+        assert_eq!(messages[1].content, "Sunny");
+        assert_eq!(messages[2].content, "Checking again. ");
+        assert!(content.ends_with("Assistant: Checking again. "));
+        assert_eq!(reused, embedding_vector);
+
+        // An unchanged iteration is not saved again; stopping ends the task
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        assert!(vector_rx.try_recv().is_err());
+        stop_tx.send(()).unwrap();
+        task.await.unwrap();
+
+        // Off when the interval is 0
+        config.chat_autosave_interval_secs = 0;
+        assert!(spawn_chat_autosave(&handles, &config, &turn_progress).is_none());
+    }
+
+    /// Run `request_batch_approval` for two calls, answering with `decision`
+    /// the way `approve_tool_call` does (None = never answer).
+    /// Returns the outcome and how many approvals are left pending.
+    async fn batch_approval_outcome(
+        decision: Option<ToolApprovalDecision>,
+    ) -> (BatchApproval, usize) {
+        batch_approval_outcome_with(decision, false).await
+    }
+
+    /// `batch_approval_outcome`, optionally stopping the turn while the batch waits
+    async fn batch_approval_outcome_with(
+        decision: Option<ToolApprovalDecision>,
+        stop_turn: bool,
+    ) -> (BatchApproval, usize) {
+        let checkpoint_dir = tempfile::tempdir().unwrap();
+        let config = scripted_config(
+            ToolCallFormatConfig::default(),
+            checkpoint_dir.path().join("turn_checkpoint.json"),
+        );
+        let handles = AgenticLoopHandles {
+            foundry_tx: mpsc::channel(1).0,
+            mcp_host_tx: mpsc::channel(1).0,
+            vector_tx: mpsc::channel(1).0,
+            python_tx: mpsc::channel(1).0,
+            schema_tx: mpsc::channel(1).0,
+            database_toolbox_tx: mpsc::channel(1).0,
+            tool_registry: crate::tool_registry::create_shared_registry(),
+            embedding_model: Arc::new(RwLock::new(None)),
+            pending_approvals: Arc::new(RwLock::new(HashMap::new())),
+            tool_disables: Arc::new(RwLock::new(Default::default())),
+        };
+        if let Some(decision) = decision {
+            let pending = handles.pending_approvals.clone();
+            tokio::spawn(async move {
+                loop {
+                    let sender = pending.write().await.remove("scripted-chat:1:0:batch");
+                    if let Some(sender) = sender {
+                        let _ = sender.send(decision);
+                        return;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            });
+        }
+        let app = tauri::test::mock_app();
+        let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+        if stop_turn {
+            let pending = handles.pending_approvals.clone();
+            tokio::spawn(async move {
+                while pending.read().await.is_empty() {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                let _ = cancel_tx.send(true);
+            });
+        }
+        let calls = vec![tool_call("files", "read"), tool_call("files", "write")];
+
+        let outcome =
+            request_batch_approval(&calls, &handles, &config, app.handle(), 0, &cancel_rx).await;
+        let pending = handles.pending_approvals.read().await.len();
+        (outcome, pending)
+    }
+
+    #[tokio::test]
+    async fn test_batch_approval_approve_all_runs_every_call() {
+        let (outcome, pending) = batch_approval_outcome(Some(ToolApprovalDecision::Approved)).await;
+        let BatchApproval::Approved(calls) = outcome else {
+            panic!("expected the batch to be approved");
+        };
+        let tools: Vec<&str> = calls.iter().map(|c| c.tool.as_str()).collect();
+        assert_eq!(tools, vec!["read", "write"]);
+        assert_eq!(pending, 0);
+    }
+
+    #[tokio::test]
+    async fn test_batch_approval_reject_declines_the_batch() {
+        let (outcome, pending) = batch_approval_outcome(Some(ToolApprovalDecision::Rejected)).await;
+        assert!(matches!(outcome, BatchApproval::Declined));
+        assert_eq!(pending, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_batch_approval_timeout_declines_and_clears_pending() {
+        let (outcome, pending) = batch_approval_outcome(None).await;
+        assert!(matches!(outcome, BatchApproval::Declined));
+        assert_eq!(pending, 0);
+    }
+
+    #[tokio::test]
+    async fn test_batch_approval_stop_cancels_and_clears_pending() {
+        let (outcome, pending) = batch_approval_outcome_with(None, true).await;
+        assert!(matches!(outcome, BatchApproval::Cancelled));
+        assert_eq!(pending, 0);
+    }
+
+    #[tokio::test]
+    async fn test_scripted_turn_stopped_during_batch_approval_is_cancelled() {
+        let script = vec![hermes_call("python_execution", json!({ "code": ["print(1)"] }))];
+        let turn = run_scripted_turn_inner(
+            script,
+            &[ToolCallFormatName::Hermes],
+            |config| config.batch_approval_mode = true,
+            true,
+        )
+        .await;
+
+        assert_eq!(turn.requests.len(), 1);
+        assert!(!turn.progress.finished);
+        let saved: Vec<ChatMessage> =
+            serde_json::from_str(&turn.saved_messages.expect("turn was not saved")).unwrap();
+        let last = saved.last().unwrap();
+        assert_eq!(last.role, "assistant");
+        assert!(last.cancelled);
+        assert!(last.content.contains("python_execution"));
     }
 }
@@ -167,6 +167,10 @@ pub struct PromptContext {
     pub custom_tool_prompts: HashMap<String, String>,
     /// Whether this is a Python-primary mode (Code Mode)
     pub python_primary: bool,
+    /// Instructions placed before the generated prompt (`system_prompt_prefix`)
+    pub prepend: Option<String>,
+    /// Instructions placed after the generated prompt (`system_prompt_suffix`)
+    pub append: Option<String>,
 }
 
 impl Default for PromptContext {
@@ -183,6 +187,8 @@ impl Default for PromptContext {
             model_tool_format: None,
             custom_tool_prompts: HashMap::new(),
             python_primary: false,
+            prepend: None,
+            append: None,
         }
     }
 }
@@ -8,14 +8,20 @@ use crate::actors::database_toolbox_actor::DatabaseToolboxMsg;
 use crate::actors::python_actor::PythonMsg;
 use crate::actors::schema_vector_actor::SchemaVectorMsg;
 use crate::actors::startup_actor::StartupMsg;
-use crate::protocol::{FoundryMsg, McpHostMsg, RagMsg, VectorMsg};
+use crate::history_compaction::SharedSummaryCache;
+use crate::protocol::{
+    ChatMessage, FoundryMsg, McpHostMsg, OpenAITool, ParsedToolCall, RagMsg, TurnMetrics,
+    VectorMsg,
+};
 use crate::settings::AppSettings;
 use crate::settings_state_machine::SettingsStateMachine;
 use crate::tool_capability::ToolLaunchFilter;
 use crate::tool_registry::SharedToolRegistry;
+use crate::turn_checkpoint::TurnCheckpoint;
 use fastembed::TextEmbedding;
 use serde::Serialize;
 use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::time::Instant;
 use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
@@ -52,6 +58,11 @@ impl Default for GpuResourceGuard {
 pub enum ToolApprovalDecision {
     Approved,
     Rejected,
+    /// Approve a batch with the user's edited calls in place of the proposed ones
+    Edited(Vec<ParsedToolCall>),
+    /// The approval was withdrawn (turn aborted or `cancel_pending_approval`); handled
+    /// like a rejection
+    Cancelled,
 }
 
 /// Pending tool approval state - maps approval keys to response channels
@@ -94,6 +105,50 @@ pub struct EmbeddingModelState {
     /// CPU-only model for search operations during active chat.
     /// Avoids GPU contention that would evict the pre-warmed LLM.
     pub cpu_model: Arc<RwLock<Option<Arc<TextEmbedding>>>>,
+    /// Why the CPU model failed to load (None while loading or once loaded)
+    pub cpu_load_error: Arc<RwLock<Option<String>>>,
+    /// Set once chat has warned that search features are disabled
+    pub unavailable_notice_sent: AtomicBool,
+}
+
+/// Availability of the CPU embedding model that tool search, schema search
+/// and RAG depend on
+#[derive(Clone, Debug, Serialize)]
+pub struct EmbeddingModelStatus {
+    pub available: bool,
+    /// Still loading; neither available nor failed yet
+    pub loading: bool,
+    pub error: Option<String>,
+}
+
+impl EmbeddingModelState {
+    pub async fn status(&self) -> EmbeddingModelStatus {
+        let available = self.cpu_model.read().await.is_some();
+        let error = if available {
+            None
+        } else {
+            self.cpu_load_error.read().await.clone()
+        };
+        EmbeddingModelStatus {
+            available,
+            loading: !available && error.is_none(),
+            error,
+        }
+    }
+
+    /// The CPU model, or an error saying why embedding features are unavailable
+    pub async fn require_cpu_model(&self) -> Result<Arc<TextEmbedding>, String> {
+        if let Some(model) = self.cpu_model.read().await.clone() {
+            return Ok(model);
+        }
+        match self.cpu_load_error.read().await.as_deref() {
+            Some(error) => Err(format!(
+                "Embedding model unavailable ({}); search and document indexing are disabled",
+                error
+            )),
+            None => Err("Embedding model is still loading; try again shortly".to_string()),
+        }
+    }
 }
 
 /// Shared settings state
@@ -146,6 +201,19 @@ pub struct TurnProgress {
     pub finished: bool,
     pub had_tool_calls: bool,
     pub timestamp_ms: u128,
+    /// Timing summary, set once the turn ends
+    pub metrics: Option<TurnMetrics>,
+    /// Whether `resume_turn` can continue an interrupted turn
+    pub resumable: bool,
+    /// Latest checkpoint of the turn (kept after a failure for `resume_turn`)
+    #[serde(skip)]
+    pub checkpoint: Option<TurnCheckpoint>,
+    /// Messages sent with the current loop iteration, for auto-save
+    #[serde(skip)]
+    pub iteration_history: Vec<ChatMessage>,
+    /// Current iteration's streamed text without reasoning, for auto-save
+    #[serde(skip)]
+    pub iteration_visible_text: String,
 }
 
 /// Event payload for system prompt updates
@@ -154,11 +222,95 @@ pub struct SystemPromptEvent {
     pub chat_id: String,
     pub generation_id: u32,
     pub prompt: String,
+    /// Tool descriptions dropped to fit `max_system_prompt_chars`
+    pub omitted_tools: usize,
+}
+
+/// Messages and tool definitions a chat turn would send to the model
+#[derive(Clone, Debug, Serialize)]
+pub struct ModelMessagesPreview {
+    pub messages: Vec<ChatMessage>,
+    /// None when tools are described only in the system prompt (code mode)
+    pub openai_tools: Option<Vec<OpenAITool>>,
+}
+
+/// Event payload emitted when older turns were summarized to fit the context
+#[derive(Clone, Debug, Serialize)]
+pub struct HistoryCompactedEvent {
+    pub chat_id: String,
+    /// Messages replaced by the summary note
+    pub compacted_messages: usize,
+    /// Messages of the recent turns kept verbatim
+    pub kept_messages: usize,
+    pub estimated_tokens_before: usize,
+    pub estimated_tokens_after: usize,
+}
+
+/// Result of the latest health check of one MCP server
+#[derive(Clone, Debug, Serialize)]
+pub struct McpServerHealth {
+    pub server_id: String,
+    pub healthy: bool,
+    /// Why the last check failed
+    pub error: Option<String>,
+    /// When the server was last checked (ms since the Unix epoch)
+    pub last_checked_ms: u128,
+}
+
+/// Health of connected MCP servers, keyed by server id
+#[derive(Clone, Default)]
+pub struct McpHealthState {
+    pub servers: Arc<RwLock<HashMap<String, McpServerHealth>>>,
+}
+
+/// Why the repeated-error check turned off tool calling for a chat's turn
+#[derive(Clone, Debug, Serialize)]
+pub struct ToolDisableInfo {
+    pub chat_id: String,
+    pub generation_id: u32,
+    /// Tool that kept failing
+    pub tool: String,
+    /// Normalized signature of the repeated error
+    pub error_signature: String,
+    /// When tools were disabled (ms since the Unix epoch)
+    pub disabled_at_ms: u128,
+}
+
+/// Chats whose tools are disabled for the rest of the current turn, keyed by chat id.
+/// An entry lasts until the chat's next turn starts or `reset_tool_disable_state`.
+#[derive(Debug, Default)]
+pub struct ToolDisables {
+    chats: HashMap<String, ToolDisableInfo>,
 }
 
+impl ToolDisables {
+    pub fn disable(&mut self, info: ToolDisableInfo) {
+        self.chats.insert(info.chat_id.clone(), info);
+    }
+
+    pub fn get(&self, chat_id: &str) -> Option<&ToolDisableInfo> {
+        self.chats.get(chat_id)
+    }
+
+    pub fn is_disabled(&self, chat_id: &str) -> bool {
+        self.chats.contains_key(chat_id)
+    }
+
+    /// Re-enable tools for a chat; returns the cleared record
+    pub fn reset(&mut self, chat_id: &str) -> Option<ToolDisableInfo> {
+        self.chats.remove(chat_id)
+    }
+}
+
+/// Tool disable records shared by the agentic loop and commands
+pub type SharedToolDisables = Arc<RwLock<ToolDisables>>;
+
 /// Tracks the latest turn progress for reconnect/replay
 pub struct TurnTrackerState {
     pub progress: Arc<RwLock<TurnProgress>>,
+    pub tool_disables: SharedToolDisables,
+    /// Cached summaries of compacted chat history
+    pub history_summaries: SharedSummaryCache,
 }
 
 /// Heartbeat state for monitoring frontend responsiveness
@@ -13,7 +13,10 @@ use crate::actors::schema_vector_actor::SchemaVectorMsg;
 use crate::settings::DatabaseToolboxConfig;
 use crate::tool_registry::SharedToolRegistry;
 use crate::tools::schema_search::{SchemaSearchInput, SchemaSearchOutput};
-use crate::tools::tool_search::{ToolSearchExecutor, ToolSearchInput, ToolSearchOutput};
+use crate::tools::tool_search::{
+    filter_by_min_relevance, ToolSearchExecutor, ToolSearchInput, ToolSearchOutput,
+    NO_RELEVANT_TOOLS_NOTE,
+};
 use crate::tools::SchemaSearchExecutor;
 
 /// Context returned from auto-discovery operations.
@@ -34,10 +37,13 @@ pub struct AutoDiscoveryContext {
 ///
 /// Searches the tool registry for tools relevant to the user's query,
 /// returning matching tools and their schemas for inclusion in the system prompt.
+/// Hits scoring below `min_relevance` are dropped before anything is materialized.
+#[allow(clippy::too_many_arguments)]
 pub async fn auto_tool_search_for_prompt(
     prompt: &str,
     tool_search_enabled: bool,
     tool_search_max_results: usize,
+    min_relevance: f32,
     has_mcp_tools: bool,
     filtered_tool_descriptions: &[(String, Vec<McpTool>)],
     registry: SharedToolRegistry,
@@ -56,11 +62,21 @@ pub async fn auto_tool_search_for_prompt(
     let executor = ToolSearchExecutor::new(registry, embedding_model);
     let search_input = ToolSearchInput {
         queries: vec![prompt.to_string()],
+        query_weights: Vec::new(),
         top_k: tool_search_max_results,
     };
 
     match executor.execute(search_input).await {
-        Ok(output) => {
+        Ok(mut output) => {
+            let found = output.tools.len();
+            output.tools = filter_by_min_relevance(&output.tools, min_relevance);
+            if output.tools.is_empty() && found > 0 {
+                println!(
+                    "[Chat] Auto tool_search: no relevant tools found ({} hit(s) below {:.2})",
+                    found, min_relevance
+                );
+                output.python_docs = NO_RELEVANT_TOOLS_NOTE.to_string();
+            }
             if materialize {
                 executor.materialize_results(&output.tools).await;
             }
@@ -130,11 +146,20 @@ pub async fn auto_schema_search_for_prompt(
         }
     }
 
+    // Filter tables by enabled database sources
+    let enabled_sources: std::collections::HashSet<String> = toolbox_config
+        .sources
+        .iter()
+        .filter(|s| s.enabled)
+        .map(|s| s.id.clone())
+        .collect();
+
     let input = SchemaSearchInput {
         query: prompt.to_string(),
         max_tables: AUTO_SCHEMA_SEARCH_MAX_TABLES,
         max_columns_per_table: 25,
         min_relevance, 
+        source_ids: enabled_sources.iter().cloned().collect(),
     };
 
     let mut search_result = executor.execute(input.clone()).await;
@@ -159,14 +184,7 @@ pub async fn auto_schema_search_for_prompt(
 
     match search_result {
         Ok(mut output) => {
-            // Filter tables by enabled database sources
-            let enabled_sources: std::collections::HashSet<String> = toolbox_config
-                .sources
-                .iter()
-                .filter(|s| s.enabled)
-                .map(|s| s.id.clone())
-                .collect();
-
+            // The search is already scoped to enabled sources; this is a backstop
             output.tables.retain(|t| enabled_sources.contains(&t.source_id));
 
             println!(
@@ -196,6 +214,7 @@ pub async fn perform_auto_discovery_for_prompt(
     prompt: &str,
     tool_search_enabled: bool,
     tool_search_max_results: usize,
+    tool_search_min_relevance: f32,
     has_mcp_tools: bool,
     schema_search_enabled: bool,
     schema_relevancy_threshold: f32,
@@ -206,10 +225,17 @@ pub async fn perform_auto_discovery_for_prompt(
     schema_tx: mpsc::Sender<SchemaVectorMsg>,
     materialize_tools: bool,
 ) -> AutoDiscoveryContext {
+    // Both searches need the embedding model; skip them rather than fail
+    if (tool_search_enabled || schema_search_enabled) && embedding_model.read().await.is_none() {
+        println!("[Chat] Auto-discovery skipped: embedding model not loaded");
+        return AutoDiscoveryContext::default();
+    }
+
     let (tool_search_output, discovered_tool_schemas) = auto_tool_search_for_prompt(
         prompt,
         tool_search_enabled,
         tool_search_max_results,
+        tool_search_min_relevance,
         has_mcp_tools,
         filtered_tool_descriptions,
         registry.clone(),
@@ -235,6 +261,38 @@ pub async fn perform_auto_discovery_for_prompt(
     }
 }
 
+/// Replies that never need tool or schema discovery
+const CONVERSATIONAL_PROMPTS: &[&str] = &[
+    "thanks", "thank you", "thanks a lot", "thank you so much", "thx", "ty", "ok", "okay",
+    "great", "cool", "nice", "perfect", "awesome", "got it", "sounds good", "makes sense",
+    "hi", "hello", "hey", "bye", "goodbye", "good night",
+];
+
+/// Whether auto-discovery can be skipped for a prompt: it is shorter than
+/// `min_prompt_chars`, or a plain acknowledgement or greeting with no question.
+/// A `min_prompt_chars` of 0 disables the heuristic.
+pub fn should_skip_auto_discovery(prompt: &str, min_prompt_chars: usize) -> bool {
+    if min_prompt_chars == 0 {
+        return false;
+    }
+    let prompt = prompt.trim();
+    if prompt.chars().count() < min_prompt_chars {
+        return true;
+    }
+    if prompt.contains('?') {
+        return false;
+    }
+    let normalized: String = prompt
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    CONVERSATIONAL_PROMPTS.contains(&normalized.as_str())
+}
+
 /// Map tool search results to their full schema definitions.
 ///
 /// Given the search results (tool names and relevance scores), looks up
@@ -275,6 +333,16 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_short_prompt_skips_auto_discovery() {
+        assert!(should_skip_auto_discovery("hello", 12));
+        assert!(should_skip_auto_discovery("  Thank you so much!  ", 12));
+        assert!(!should_skip_auto_discovery("List the top 5 customers by revenue", 12));
+        assert!(!should_skip_auto_discovery("Thanks, but why is that?", 12));
+        // 0 disables the heuristic
+        assert!(!should_skip_auto_discovery("hello", 0));
+    }
+
     #[test]
     fn test_map_tool_search_hits_to_schemas() {
         let tool1 = McpTool {
@@ -283,6 +351,7 @@ mod tests {
             input_schema: Some(json!({})),
             input_examples: None,
             allowed_callers: None,
+            streamable: false,
         };
         let tool2 = McpTool {
             name: "search".to_string(),
@@ -290,6 +359,7 @@ mod tests {
             input_schema: Some(json!({})),
             input_examples: None,
             allowed_callers: None,
+            streamable: false,
         };
 
         let filtered = vec![
@@ -316,6 +386,31 @@ mod tests {
         assert_eq!(tools[0].name, "get_weather");
     }
 
+    #[test]
+    fn test_low_scoring_hit_not_mapped() {
+        let tool = McpTool {
+            name: "send_email".to_string(),
+            description: Some("Send an email".to_string()),
+            input_schema: Some(json!({})),
+            input_examples: None,
+            allowed_callers: None,
+            streamable: false,
+        };
+        let filtered = vec![("mail-server".to_string(), vec![tool])];
+        let hits = vec![crate::tool_registry::ToolSearchResult {
+            server_id: "mail-server".to_string(),
+            name: "send_email".to_string(),
+            description: Some("Send an email".to_string()),
+            parameters: json!({}),
+            score: 0.15,
+        }];
+
+        let relevant = filter_by_min_relevance(&hits, 0.3);
+        assert!(map_tool_search_hits_to_schemas(&relevant, &filtered).is_empty());
+        let relevant = filter_by_min_relevance(&hits, 0.1);
+        assert_eq!(map_tool_search_hits_to_schemas(&relevant, &filtered).len(), 1);
+    }
+
     #[test]
     fn test_auto_discovery_context_default() {
         let ctx = AutoDiscoveryContext::default();
@@ -0,0 +1,324 @@
+//! Chat transcript export.
+//!
+//! Renders a stored conversation as a JSON bundle or a Markdown document with
+//! tool calls kept intact. Native tool results (`role = "tool"`) are paired
+//! with the assistant call they answer through `tool_call_id`. Text-format
+//! calls of a turn's stored tool traffic are parsed from the assistant message,
+//! and the tool result message that follows is paired with them.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::cli::is_builtin_tool;
+use crate::protocol::{ChatMessage, ModelFamily, ToolFormat};
+use crate::settings::{ToolCallFormatConfig, ToolCallFormatName};
+use crate::tool_parsing::common::parse_combined_tool_name;
+use crate::tool_parsing::parse_tool_calls_for_model_profile;
+
+/// Output format for `export_chat`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Json,
+    Markdown,
+}
+
+/// A message in the export, with its tool calls and their results inlined
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedMessage {
+    pub role: String,
+    pub content: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ExportedToolCall>,
+}
+
+/// A tool call made by the assistant, paired with its result when one was stored
+#[derive(Debug, Clone, Serialize)]
+pub struct ExportedToolCall {
+    pub id: String,
+    pub server: String,
+    pub tool: String,
+    pub arguments: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+}
+
+/// Tool calls written as text in a stored tool-call message, in any text format
+fn parse_text_tool_calls(content: &str) -> Vec<ExportedToolCall> {
+    let formats = ToolCallFormatConfig {
+        enabled: vec![
+            ToolCallFormatName::Hermes,
+            ToolCallFormatName::Mistral,
+            ToolCallFormatName::Pythonic,
+            ToolCallFormatName::PureJson,
+        ],
+        primary: ToolCallFormatName::Hermes,
+    };
+    parse_tool_calls_for_model_profile(
+        content,
+        ModelFamily::Generic,
+        ToolFormat::TextBased,
+        &formats,
+        ToolCallFormatName::Hermes,
+    )
+    .into_iter()
+    .enumerate()
+    .map(|(index, call)| ExportedToolCall {
+        id: call.id.unwrap_or_else(|| format!("text_call_{}", index + 1)),
+        // Built-in tools are called without a server prefix
+        server: if call.server == "unknown" && is_builtin_tool(&call.tool) {
+            "builtin".to_string()
+        } else {
+            call.server
+        },
+        tool: call.tool,
+        arguments: call.arguments,
+        result: None,
+    })
+    .collect()
+}
+
+/// Pair tool results with their calls.
+///
+/// A `tool` message whose `tool_call_id` matches an earlier call is folded
+/// into that call; results without a matching call stay as their own messages.
+/// A stored text-format result answers the single call of the message before
+/// it, or is kept as a tool result message when that message made several.
+pub fn pair_tool_results(messages: &[ChatMessage]) -> Vec<ExportedMessage> {
+    let mut exported: Vec<ExportedMessage> = Vec::new();
+
+    for message in messages {
+        if message.intermediate && message.role == "user" {
+            let pending = exported.last_mut().filter(|previous| {
+                previous.role == "assistant"
+                    && !previous.tool_calls.is_empty()
+                    && previous.tool_calls.iter().all(|call| call.result.is_none())
+            });
+            if let Some(previous) = pending {
+                if let [call] = previous.tool_calls.as_mut_slice() {
+                    call.result = Some(message.content.clone());
+                } else {
+                    exported.push(ExportedMessage {
+                        role: "tool".to_string(),
+                        content: message.content.clone(),
+                        tool_calls: Vec::new(),
+                    });
+                }
+                continue;
+            }
+        }
+
+        if message.role == "tool" {
+            let call = message.tool_call_id.as_ref().and_then(|id| {
+                exported
+                    .iter_mut()
+                    .rev()
+                    .flat_map(|m| m.tool_calls.iter_mut())
+                    .find(|call| &call.id == id && call.result.is_none())
+            });
+            if let Some(call) = call {
+                call.result = Some(message.content.clone());
+                continue;
+            }
+        }
+
+        if message.intermediate && message.role == "assistant" && message.tool_calls.is_none() {
+            exported.push(ExportedMessage {
+                role: message.role.clone(),
+                content: message.content.clone(),
+                tool_calls: parse_text_tool_calls(&message.content),
+            });
+            continue;
+        }
+
+        let tool_calls = message
+            .tool_calls
+            .iter()
+            .flatten()
+            .map(|call| {
+                // Built-in tools are sent to the model without a server prefix
+                let (server, tool) = if call.function.name.contains("___") {
+                    parse_combined_tool_name(&call.function.name)
+                } else if is_builtin_tool(&call.function.name) {
+                    ("builtin".to_string(), call.function.name.clone())
+                } else {
+                    ("unknown".to_string(), call.function.name.clone())
+                };
+                ExportedToolCall {
+                    id: call.id.clone(),
+                    server,
+                    tool,
+                    arguments: serde_json::from_str(&call.function.arguments)
+                        .unwrap_or_else(|_| Value::String(call.function.arguments.clone())),
+                    result: None,
+                }
+            })
+            .collect();
+
+        exported.push(ExportedMessage {
+            role: message.role.clone(),
+            content: message.content.clone(),
+            tool_calls,
+        });
+    }
+
+    exported
+}
+
+/// Render a chat transcript in the requested format
+pub fn render_chat_export(
+    chat_id: &str,
+    messages: &[ChatMessage],
+    format: ExportFormat,
+) -> Result<String, String> {
+    let exported = pair_tool_results(messages);
+    match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&json!({
+            "chat_id": chat_id,
+            "messages": exported,
+        }))
+        .map_err(|e| format!("Failed to serialize chat export: {}", e)),
+        ExportFormat::Markdown => Ok(render_markdown(chat_id, &exported)),
+    }
+}
+
+/// Render a chat from its stored messages JSON
+pub fn render_stored_chat_export(
+    chat_id: &str,
+    messages_json: &str,
+    format: ExportFormat,
+) -> Result<String, String> {
+    let messages: Vec<ChatMessage> = serde_json::from_str(messages_json)
+        .map_err(|e| format!("Failed to parse messages for chat {}: {}", chat_id, e))?;
+    println!(
+        "[export_chat] Exporting chat {} ({} messages) as {:?}",
+        chat_id,
+        messages.len(),
+        format
+    );
+    render_chat_export(chat_id, &messages, format)
+}
+
+fn render_markdown(chat_id: &str, messages: &[ExportedMessage]) -> String {
+    let mut out = format!("# Chat {}\n", chat_id);
+
+    for message in messages {
+        let heading = match message.role.as_str() {
+            "user" => "User",
+            "assistant" => "Assistant",
+            "system" => "System",
+            "tool" => "Tool result",
+            other => other,
+        };
+        out.push_str(&format!("\n## {}\n\n", heading));
+        if !message.content.trim().is_empty() {
+            out.push_str(message.content.trim_end());
+            out.push('\n');
+        }
+
+        for call in &message.tool_calls {
+            out.push_str(&format!("\n### Tool call: {}::{}\n\n", call.server, call.tool));
+            let arguments = serde_json::to_string_pretty(&call.arguments)
+                .unwrap_or_else(|_| call.arguments.to_string());
+            out.push_str(&format!("```json\n{}\n```\n", arguments));
+            if let Some(result) = &call.result {
+                out.push_str(&format!("\n**Result:**\n\n```\n{}\n```\n", result.trim_end()));
+            }
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{OpenAIToolCall, OpenAIToolCallFunction};
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn transcript() -> Vec<ChatMessage> {
+        let mut assistant = message("assistant", "Let me check.");
+        assistant.tool_calls = Some(vec![OpenAIToolCall {
+            id: "call_1".to_string(),
+            call_type: "function".to_string(),
+            function: OpenAIToolCallFunction {
+                name: "weather___get_forecast".to_string(),
+                arguments: r#"{"city": "Seattle"}"#.to_string(),
+            },
+        }]);
+        let mut result = message("tool", "Rain, 12C");
+        result.tool_call_id = Some("call_1".to_string());
+        vec![
+            message("user", "Weather in Seattle?"),
+            assistant,
+            result,
+            message("assistant", "It is rainy and 12C."),
+        ]
+    }
+
+    #[test]
+    fn test_tool_results_pair_with_calls() {
+        let exported = pair_tool_results(&transcript());
+        assert_eq!(exported.len(), 3);
+        let call = &exported[1].tool_calls[0];
+        assert_eq!(call.server, "weather");
+        assert_eq!(call.tool, "get_forecast");
+        assert_eq!(call.arguments, json!({"city": "Seattle"}));
+        assert_eq!(call.result.as_deref(), Some("Rain, 12C"));
+    }
+
+    #[test]
+    fn test_unmatched_tool_result_is_kept() {
+        let mut orphan = message("tool", "stray output");
+        orphan.tool_call_id = Some("missing".to_string());
+        let exported = pair_tool_results(&[message("user", "hi"), orphan]);
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[1].role, "tool");
+    }
+
+    #[test]
+    fn test_stored_text_tool_calls_pair_with_results() {
+        let mut call = message(
+            "assistant",
+            r#"<tool_call>{"name": "weather___get_forecast", "arguments": {"city": "Oslo"}}</tool_call>"#,
+        );
+        call.intermediate = true;
+        let mut result = message("user", "<tool_response>Snow</tool_response>");
+        result.intermediate = true;
+        let messages = vec![
+            message("user", "Weather in Oslo?"),
+            call,
+            result,
+            message("assistant", "It is snowing."),
+        ];
+
+        let exported = pair_tool_results(&messages);
+        assert_eq!(exported.len(), 3);
+        let call = &exported[1].tool_calls[0];
+        assert_eq!((call.server.as_str(), call.tool.as_str()), ("weather", "get_forecast"));
+        assert_eq!(call.arguments, json!({"city": "Oslo"}));
+        assert_eq!(call.result.as_deref(), Some("<tool_response>Snow</tool_response>"));
+    }
+
+    #[test]
+    fn test_render_markdown_and_json() {
+        let markdown = render_chat_export("abc", &transcript(), ExportFormat::Markdown).unwrap();
+        assert!(markdown.starts_with("# Chat abc\n"));
+        assert!(markdown.contains("### Tool call: weather::get_forecast"));
+        assert!(markdown.contains("\"city\": \"Seattle\""));
+        assert!(markdown.contains("**Result:**\n\n```\nRain, 12C\n```"));
+
+        let bundle = render_chat_export("abc", &transcript(), ExportFormat::Json).unwrap();
+        let parsed: Value = serde_json::from_str(&bundle).unwrap();
+        assert_eq!(parsed["chat_id"], "abc");
+        assert_eq!(parsed["messages"][1]["tool_calls"][0]["result"], "Rain, 12C");
+        assert!(parsed["messages"][0].get("tool_calls").is_none());
+    }
+}
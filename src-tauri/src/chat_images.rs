@@ -0,0 +1,91 @@
+//! Image attachments for vision-capable models.
+//!
+//! Images reach `chat` as data URLs, raw base64, or local file paths. They are
+//! normalized to data URLs before being attached to the user message, so the
+//! request builder and the persisted history only ever see one form.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::path::Path;
+
+/// Largest image accepted as an attachment (20 MB)
+pub const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// Identify a supported image type from its leading bytes
+fn sniff_image_mime(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if bytes.starts_with(b"BM") {
+        Some("image/bmp")
+    } else {
+        None
+    }
+}
+
+fn encode_data_url(bytes: &[u8], source: &str) -> Result<String, String> {
+    if bytes.len() > MAX_IMAGE_BYTES {
+        return Err(format!(
+            "Image {} is too large ({} bytes, limit {})",
+            source,
+            bytes.len(),
+            MAX_IMAGE_BYTES
+        ));
+    }
+    let mime = sniff_image_mime(bytes)
+        .ok_or_else(|| format!("Unsupported image format: {}", source))?;
+    Ok(format!("data:{};base64,{}", mime, STANDARD.encode(bytes)))
+}
+
+/// Normalize an attachment (data URL, base64 payload, or file path) to a data URL
+pub fn image_to_data_url(image: &str) -> Result<String, String> {
+    let image = image.trim();
+    if image.starts_with("data:image/") {
+        return Ok(image.to_string());
+    }
+
+    let path = Path::new(image);
+    if path.is_file() {
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("Failed to read image {}: {}", image, e))?;
+        return encode_data_url(&bytes, image);
+    }
+
+    let bytes = STANDARD
+        .decode(image)
+        .map_err(|_| "Image is neither a readable file nor valid base64".to_string())?;
+    encode_data_url(&bytes, "(base64)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn test_base64_and_data_url_inputs() {
+        let encoded = STANDARD.encode(PNG_HEADER);
+        let url = image_to_data_url(&encoded).unwrap();
+        assert_eq!(url, format!("data:image/png;base64,{}", encoded));
+        assert_eq!(image_to_data_url(&url).unwrap(), url);
+
+        assert!(image_to_data_url(&STANDARD.encode(b"plain text")).is_err());
+        assert!(image_to_data_url("not base64 or a path!").is_err());
+    }
+
+    #[test]
+    fn test_file_path_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+        std::fs::write(&path, [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10]).unwrap();
+
+        let url = image_to_data_url(path.to_str().unwrap()).unwrap();
+        assert!(url.starts_with("data:image/jpeg;base64,"));
+    }
+}
@@ -5,7 +5,8 @@
 
 use crate::app_state::LaunchOverrides;
 use crate::settings::{
-    enforce_python_name, ensure_default_servers, AlwaysOnTableConfig, AppSettings, McpServerConfig, ToolCallFormatName,
+    enforce_python_name, ensure_default_servers, AlwaysOnTableConfig, AppSettings, McpServerConfig, PythonSandboxBackend, ResultFormat, SqlResultFormat, ToolCallFormatName,
+    MAX_TOOL_ITERATIONS, MIN_TOOL_ITERATIONS,
 };
 use crate::tool_capability::ToolLaunchFilter;
 use clap::Parser;
@@ -25,6 +26,12 @@ pub struct CliArgs {
     /// Override global system prompt (string or @path/to/file)
     #[arg(long, value_name = "PROMPT_OR_@FILE", env = "PLUGABLE_SYSTEM_PROMPT")]
     pub system_prompt: Option<String>,
+    /// Text placed before the generated system prompt (string or @path/to/file)
+    #[arg(long, value_name = "TEXT_OR_@FILE", env = "PLUGABLE_SYSTEM_PROMPT_PREFIX")]
+    pub system_prompt_prefix: Option<String>,
+    /// Text placed after the generated system prompt (string or @path/to/file)
+    #[arg(long, value_name = "TEXT_OR_@FILE", env = "PLUGABLE_SYSTEM_PROMPT_SUFFIX")]
+    pub system_prompt_suffix: Option<String>,
     /// Initial user prompt to send on startup (string or @path/to/file)
     #[arg(long, value_name = "PROMPT_OR_@FILE", env = "PLUGABLE_INITIAL_PROMPT")]
     pub initial_prompt: Option<String>,
@@ -34,12 +41,114 @@ pub struct CliArgs {
     /// Maximum number of tools returned by tool_search (caps auto and explicit searches)
     #[arg(long, value_name = "INT", env = "PLUGABLE_TOOL_SEARCH_MAX_RESULTS")]
     pub tool_search_max_results: Option<usize>,
+    /// Minimum relevance score (0.0-1.0) for a tool_search hit to be shown
+    #[arg(long, value_name = "FLOAT", env = "PLUGABLE_TOOL_SEARCH_MIN_RELEVANCE")]
+    pub tool_search_min_relevance: Option<f32>,
+    /// Skip auto-discovery for messages shorter than this many characters (0 = always run)
+    #[arg(long, value_name = "CHARS", env = "PLUGABLE_AUTO_DISCOVERY_MIN_PROMPT_CHARS")]
+    pub auto_discovery_min_prompt_chars: Option<usize>,
+    /// Maximum agentic loop iterations per turn (clamped to 1..=100)
+    #[arg(long, value_name = "INT", env = "PLUGABLE_MAX_TOOL_ITERATIONS")]
+    pub max_tool_iterations: Option<usize>,
+    /// Maximum tool executions per turn across all iterations (0 = no limit)
+    #[arg(long, value_name = "INT", env = "PLUGABLE_MAX_TOOL_CALLS_PER_TURN")]
+    pub max_tool_calls_per_turn: Option<usize>,
+    /// Enable/disable concurrent execution of independent tool calls within one iteration
+    #[arg(long, value_name = "BOOL", env = "PLUGABLE_PARALLEL_TOOL_CALLS", value_parser = clap::builder::BoolishValueParser::new())]
+    pub parallel_tool_calls: Option<bool>,
+    /// Enable/disable approving each iteration's tool calls as one batch before any run
+    #[arg(long, value_name = "BOOL", env = "PLUGABLE_BATCH_APPROVAL_MODE", value_parser = clap::builder::BoolishValueParser::new())]
+    pub batch_approval_mode: Option<bool>,
+    /// Enable/disable correlation id comments on text-format tool calls and results
+    #[arg(long, value_name = "BOOL", env = "PLUGABLE_TOOL_CALL_IDS_IN_TEXT", value_parser = clap::builder::BoolishValueParser::new())]
+    pub tool_call_ids_in_text: Option<bool>,
+    /// Enable/disable clearing materialized tools at the start of every chat message
+    #[arg(long, value_name = "BOOL", env = "PLUGABLE_STRICT_TURN_TOOL_SCOPE", value_parser = clap::builder::BoolishValueParser::new())]
+    pub strict_turn_tool_scope: Option<bool>,
+    /// Expire materialized tools after this many seconds when turns share them (0 = never)
+    #[arg(long, value_name = "SECS", env = "PLUGABLE_MATERIALIZED_TOOLS_TTL_SECS")]
+    pub materialized_tools_ttl_secs: Option<u64>,
+    /// Enable/disable connecting MCP servers and loading the model in the background at launch
+    #[arg(long, value_name = "BOOL", env = "PLUGABLE_WARM_START_ON_LAUNCH", value_parser = clap::builder::BoolishValueParser::new())]
+    pub warm_start_on_launch: Option<bool>,
+    /// Enable/disable sending model reasoning as separate reasoning-token events
+    #[arg(long, value_name = "BOOL", env = "PLUGABLE_REASONING_EVENTS", value_parser = clap::builder::BoolishValueParser::new())]
+    pub reasoning_events: Option<bool>,
+    /// Default timeout for each MCP tool call in seconds (0 = no limit)
+    #[arg(long, value_name = "SECS", env = "PLUGABLE_MCP_TOOL_TIMEOUT_SECS")]
+    pub mcp_tool_timeout_secs: Option<u64>,
+    /// Milliseconds between tool heartbeat events while a tool runs (0 = no heartbeats)
+    #[arg(long, value_name = "MS", env = "PLUGABLE_TOOL_HEARTBEAT_INTERVAL_MS")]
+    pub tool_heartbeat_interval_ms: Option<u64>,
+    /// Seconds between MCP server health checks (0 = no checks)
+    #[arg(long, value_name = "SECS", env = "PLUGABLE_MCP_HEALTH_CHECK_INTERVAL_SECS")]
+    pub mcp_health_check_interval_secs: Option<u64>,
+    /// Seconds between auto-saves of an in-progress response (0 = save only at turn end)
+    #[arg(long, value_name = "SECS", env = "PLUGABLE_CHAT_AUTOSAVE_INTERVAL_SECS")]
+    pub chat_autosave_interval_secs: Option<u64>,
+    /// Enable/disable sending tool call format closing markers as stop sequences
+    #[arg(long, value_name = "BOOL", env = "PLUGABLE_USE_FORMAT_STOP_SEQUENCES", value_parser = clap::builder::BoolishValueParser::new())]
+    pub use_format_stop_sequences: Option<bool>,
+    /// Truncate tool results longer than this many characters before they reach the model (0 = no limit)
+    #[arg(long, value_name = "CHARS", env = "PLUGABLE_MAX_TOOL_RESULT_CHARS")]
+    pub max_tool_result_chars: Option<usize>,
+    /// Enable/disable summarizing oversized MCP tool results before they reach the model
+    #[arg(long, value_name = "BOOL", env = "PLUGABLE_SUMMARIZE_LARGE_TOOL_RESULTS", value_parser = clap::builder::BoolishValueParser::new())]
+    pub summarize_large_tool_results: Option<bool>,
+    /// Summarize MCP tool results longer than this many characters (0 = never)
+    #[arg(long, value_name = "CHARS", env = "PLUGABLE_TOOL_RESULT_SUMMARY_THRESHOLD_CHARS")]
+    pub tool_result_summary_threshold_chars: Option<usize>,
+    /// Drop low-priority tool descriptions once the system prompt exceeds this many characters (0 = no limit)
+    #[arg(long, value_name = "CHARS", env = "PLUGABLE_MAX_SYSTEM_PROMPT_CHARS")]
+    pub max_system_prompt_chars: Option<usize>,
+    /// Enable/disable summarizing older turns when the history nears the model's context size
+    #[arg(long, value_name = "BOOL", env = "PLUGABLE_HISTORY_COMPACTION_ENABLED", value_parser = clap::builder::BoolishValueParser::new())]
+    pub history_compaction_enabled: Option<bool>,
+    /// Most recent turns kept verbatim when the history is compacted
+    #[arg(long, value_name = "TURNS", env = "PLUGABLE_HISTORY_COMPACTION_KEEP_TURNS")]
+    pub history_compaction_keep_turns: Option<usize>,
+    /// Retries for a chat request that fails to start streaming (0 = no retries)
+    #[arg(long, value_name = "COUNT", env = "PLUGABLE_FOUNDRY_CHAT_RETRIES")]
+    pub foundry_chat_retries: Option<u32>,
+    /// Seconds to wait for the model's first token before retrying (0 = wait forever)
+    #[arg(long, value_name = "SECS", env = "PLUGABLE_FIRST_TOKEN_TIMEOUT_SECS")]
+    pub first_token_timeout_secs: Option<u64>,
+    /// Tables embedded at once during a schema refresh (min 1)
+    #[arg(long, value_name = "COUNT", env = "PLUGABLE_SCHEMA_EMBED_CONCURRENCY")]
+    pub schema_embed_concurrency: Option<usize>,
     /// Enable/disable python_execution built-in
     #[arg(long, value_name = "BOOL", env = "PLUGABLE_PYTHON_EXECUTION", value_parser = clap::builder::BoolishValueParser::new())]
     pub python_execution: Option<bool>,
     /// Enable/disable python-driven tool calling
     #[arg(long, value_name = "BOOL", env = "PLUGABLE_PYTHON_TOOL_CALLING", value_parser = clap::builder::BoolishValueParser::new())]
     pub python_tool_calling: Option<bool>,
+    /// Wall-clock timeout for python_execution in milliseconds (0 disables the limit)
+    #[arg(long, value_name = "MS", env = "PLUGABLE_PYTHON_EXECUTION_TIMEOUT_MS")]
+    pub python_execution_timeout_ms: Option<u64>,
+    /// Memory ceiling for a python_execution run in MB (0 disables the limit)
+    #[arg(long, value_name = "MB", env = "PLUGABLE_PYTHON_MEMORY_LIMIT_MB")]
+    pub python_memory_limit_mb: Option<u64>,
+    /// How python_execution results are shown to the model (text or json)
+    #[arg(long, value_name = "FORMAT", env = "PLUGABLE_PYTHON_RESULT_FORMAT")]
+    pub python_result_format: Option<String>,
+    /// Where python_execution code runs (in_process or wasm)
+    #[arg(long, value_name = "BACKEND", env = "PLUGABLE_PYTHON_SANDBOX_BACKEND")]
+    pub python_sandbox_backend: Option<String>,
+    /// How sql_select results are shown to the model (json or table)
+    #[arg(long, value_name = "FORMAT", env = "PLUGABLE_SQL_RESULT_FORMAT")]
+    pub sql_result_format: Option<String>,
+    /// Enable/disable running all python code blocks in a response as one program
+    #[arg(long, value_name = "BOOL", env = "PLUGABLE_PYTHON_MULTI_BLOCK", value_parser = clap::builder::BoolishValueParser::new())]
+    pub python_multi_block: Option<bool>,
+    /// Enable/disable safe mode (no code execution, approval for every MCP tool call)
+    #[arg(long, value_name = "BOOL", env = "PLUGABLE_SAFE_MODE", value_parser = clap::builder::BoolishValueParser::new())]
+    pub safe_mode: Option<bool>,
+    /// Enable/disable persisting Python variables across python_execution calls within a turn
+    #[arg(long, value_name = "BOOL", env = "PLUGABLE_PYTHON_SESSION_PERSISTENCE", value_parser = clap::builder::BoolishValueParser::new())]
+    pub python_session_persistence: Option<bool>,
+    /// Comma-separated modules python_execution may import (replaces the default list)
+    #[arg(long, value_delimiter = ',', env = "PLUGABLE_PYTHON_ALLOWED_MODULES")]
+    pub python_allowed_modules: Option<Vec<String>>,
     /// Enable/disable native tool calling (OpenAI-compatible) when model supports it
     #[arg(long, value_name = "BOOL", env = "PLUGABLE_NATIVE_TOOL_CALLING", value_parser = clap::builder::BoolishValueParser::new())]
     pub native_tool_calling: Option<bool>,
@@ -61,7 +170,7 @@ pub struct CliArgs {
     /// Enable/disable legacy <tool_call> parsing
     #[arg(long, value_name = "BOOL", env = "PLUGABLE_LEGACY_TOOL_FORMAT", value_parser = clap::builder::BoolishValueParser::new())]
     pub legacy_tool_call_format: Option<bool>,
-    /// Comma-separated list of tool call formats to enable (hermes,mistral,pythonic,pure_json,code_mode)
+    /// Comma-separated list of tool call formats to enable (native,hermes,mistral,pythonic,pure_json,code_mode,anthropic)
     #[arg(
         long = "tool-call-enabled",
         value_delimiter = ',',
@@ -79,15 +188,25 @@ pub struct CliArgs {
     /// Override per-tool system prompts (server_id::tool_name=prompt_or_@file). Use server_id=builtin for built-ins.
     #[arg(long = "tool-system-prompt", value_name = "KEY=VALUE_OR_@FILE", env = "PLUGABLE_TOOL_SYSTEM_PROMPTS", value_delimiter = None)]
     pub tool_system_prompts: Vec<String>,
+    /// Override tool descriptions (server_id::tool_name=description_or_@file). Use server_id=builtin for built-ins.
+    #[arg(long = "tool-description-override", value_name = "KEY=VALUE_OR_@FILE", env = "PLUGABLE_TOOL_DESCRIPTION_OVERRIDES", value_delimiter = None)]
+    pub tool_description_overrides: Vec<String>,
+    /// Override the reasoning_effort sent to a model (model_id=effort)
+    #[arg(long = "reasoning-effort-override", value_name = "MODEL=EFFORT", env = "PLUGABLE_REASONING_EFFORT_OVERRIDES", value_delimiter = None)]
+    pub reasoning_effort_overrides: Vec<String>,
     /// Replace MCP server list with JSON configs (inline JSON or @path/to/json)
     #[arg(long = "mcp-server", value_name = "JSON_OR_@FILE", env = "PLUGABLE_MCP_SERVERS", value_delimiter = None)]
     pub mcp_servers: Vec<String>,
     /// Optional allowlist of tools to expose on launch.
     /// Built-ins: python_execution, tool_search
-    /// MCP tools: server_id::tool_name
+    /// MCP tools: server_id::tool_name (`*` globs allowed, e.g. files::read_*, *::list_*)
     /// Servers: server_id (enables all tools from that server)
     #[arg(long, value_delimiter = ',', env = "PLUGABLE_TOOLS")]
     pub tools: Option<Vec<String>>,
+    /// Optional denylist of tools to hide on launch (same syntax as --tools).
+    /// A denied entry wins even if --tools would allow it.
+    #[arg(long = "tools-deny", value_delimiter = ',', env = "PLUGABLE_TOOLS_DENY")]
+    pub tools_deny: Option<Vec<String>>,
     
     // ============ Always-On Configuration ============
     
@@ -206,53 +325,82 @@ pub fn parse_json_or_file<T: DeserializeOwned>(raw: &str) -> Result<T, String> {
 
 /// Parse a tool call format name from string
 pub fn parse_tool_call_format(name: &str) -> Option<ToolCallFormatName> {
+    ToolCallFormatName::from_name(name)
+}
+
+/// Parse a python_execution result format name from string
+pub fn parse_result_format(name: &str) -> Option<ResultFormat> {
+    match name {
+        "text" => Some(ResultFormat::Text),
+        "json" => Some(ResultFormat::Json),
+        _ => None,
+    }
+}
+
+/// Parse a python_execution sandbox backend name from string
+pub fn parse_python_sandbox_backend(name: &str) -> Option<PythonSandboxBackend> {
+    match name {
+        "in_process" => Some(PythonSandboxBackend::InProcess),
+        "wasm" => Some(PythonSandboxBackend::Wasm),
+        _ => None,
+    }
+}
+
+/// Parse a sql_select result format name from string
+pub fn parse_sql_result_format(name: &str) -> Option<SqlResultFormat> {
     match name {
-        "hermes" => Some(ToolCallFormatName::Hermes),
-        "mistral" => Some(ToolCallFormatName::Mistral),
-        "pythonic" => Some(ToolCallFormatName::Pythonic),
-        "pure_json" => Some(ToolCallFormatName::PureJson),
-        "code_mode" => Some(ToolCallFormatName::CodeMode),
+        "json" => Some(SqlResultFormat::Json),
+        "table" => Some(SqlResultFormat::Table),
         _ => None,
     }
 }
 
-/// Check if a tool call is for a built-in tool (python_execution, tool_search, or database tools)
+/// Check if a tool call is for a core built-in tool (see `ToolRegistry::is_builtin_tool`
+/// for one that also knows registered built-ins)
 pub fn is_builtin_tool(tool_name: &str) -> bool {
-    matches!(
-        tool_name,
-        "python_execution" | "tool_search" | "schema_search" | "sql_select"
-    )
+    crate::tool_registry::is_builtin_tool_name(tool_name)
 }
 
-/// Parse CLI args into a launch-time tool filter
-pub fn parse_tool_filter(args: &CliArgs) -> ToolLaunchFilter {
+/// Entries from --tools / --tools-deny split into (builtins, servers, server::tool pairs)
+type ToolEntrySets = (
+    HashSet<String>,
+    HashSet<String>,
+    HashSet<(String, String)>,
+);
+
+fn classify_tool_entries(entries: &[String]) -> ToolEntrySets {
     let mut builtin_set: HashSet<String> = HashSet::new();
     let mut server_set: HashSet<String> = HashSet::new();
     let mut tool_set: HashSet<(String, String)> = HashSet::new();
 
-    let mut has_builtin = false;
-    let mut has_server = false;
-    let mut has_tool = false;
-
-    if let Some(entries) = &args.tools {
-        for raw in entries {
-            if let Some((server_id, tool_name)) = raw.split_once("::") {
-                tool_set.insert((server_id.to_string(), tool_name.to_string()));
-                has_tool = true;
-            } else if is_builtin_tool(raw) {
-                builtin_set.insert(raw.to_string());
-                has_builtin = true;
-            } else {
-                server_set.insert(raw.to_string());
-                has_server = true;
-            }
+    for raw in entries {
+        if let Some((server_id, tool_name)) = raw.split_once("::") {
+            tool_set.insert((server_id.to_string(), tool_name.to_string()));
+        } else if is_builtin_tool(raw) {
+            builtin_set.insert(raw.to_string());
+        } else {
+            server_set.insert(raw.to_string());
         }
     }
 
+    (builtin_set, server_set, tool_set)
+}
+
+/// Parse CLI args into a launch-time tool filter
+pub fn parse_tool_filter(args: &CliArgs) -> ToolLaunchFilter {
+    let (builtin_set, server_set, tool_set) =
+        classify_tool_entries(args.tools.as_deref().unwrap_or_default());
+    let (denied_builtins, denied_servers, denied_tools) =
+        classify_tool_entries(args.tools_deny.as_deref().unwrap_or_default());
+
+    // An empty allowlist category means "no restriction" for that category
     ToolLaunchFilter {
-        allowed_builtins: if has_builtin { Some(builtin_set) } else { None },
-        allowed_servers: if has_server { Some(server_set) } else { None },
-        allowed_tools: if has_tool { Some(tool_set) } else { None },
+        allowed_builtins: (!builtin_set.is_empty()).then_some(builtin_set),
+        allowed_servers: (!server_set.is_empty()).then_some(server_set),
+        allowed_tools: (!tool_set.is_empty()).then_some(tool_set),
+        denied_builtins,
+        denied_servers,
+        denied_tools,
     }
 }
 
@@ -280,6 +428,18 @@ pub fn apply_cli_overrides(args: &CliArgs, settings: &mut AppSettings) -> Launch
             Err(e) => println!("[Launch] Failed to apply system_prompt override: {}", e),
         }
     }
+    if let Some(raw) = &args.system_prompt_prefix {
+        match read_value_or_file(raw) {
+            Ok(prefix) => settings.system_prompt_prefix = Some(prefix),
+            Err(e) => println!("[Launch] Failed to apply system_prompt_prefix override: {}", e),
+        }
+    }
+    if let Some(raw) = &args.system_prompt_suffix {
+        match read_value_or_file(raw) {
+            Ok(suffix) => settings.system_prompt_suffix = Some(suffix),
+            Err(e) => println!("[Launch] Failed to apply system_prompt_suffix override: {}", e),
+        }
+    }
 
     // Core toggles
     if let Some(v) = args.tool_search {
@@ -295,6 +455,81 @@ pub fn apply_cli_overrides(args: &CliArgs, settings: &mut AppSettings) -> Launch
         let capped = max_results.clamp(1, 20);
         settings.tool_search_max_results = capped;
     }
+    if let Some(min_relevance) = args.tool_search_min_relevance {
+        settings.tool_search_min_relevance = min_relevance.clamp(0.0, 1.0);
+    }
+    if let Some(chars) = args.auto_discovery_min_prompt_chars {
+        settings.auto_discovery_min_prompt_chars = chars;
+    }
+    if let Some(max_iterations) = args.max_tool_iterations {
+        settings.max_tool_iterations = max_iterations.clamp(MIN_TOOL_ITERATIONS, MAX_TOOL_ITERATIONS);
+    }
+    if let Some(max_calls) = args.max_tool_calls_per_turn {
+        settings.max_tool_calls_per_turn = max_calls;
+    }
+    if let Some(v) = args.parallel_tool_calls {
+        settings.parallel_tool_calls = v;
+    }
+    if let Some(v) = args.batch_approval_mode {
+        settings.batch_approval_mode = v;
+    }
+    if let Some(v) = args.tool_call_ids_in_text {
+        settings.tool_call_ids_in_text = v;
+    }
+    if let Some(v) = args.strict_turn_tool_scope {
+        settings.strict_turn_tool_scope = v;
+    }
+    if let Some(secs) = args.materialized_tools_ttl_secs {
+        settings.materialized_tools_ttl_secs = secs;
+    }
+    if let Some(v) = args.warm_start_on_launch {
+        settings.warm_start_on_launch = v;
+    }
+    if let Some(v) = args.reasoning_events {
+        settings.reasoning_events_enabled = v;
+    }
+    if let Some(secs) = args.mcp_tool_timeout_secs {
+        settings.mcp_tool_timeout_secs = secs;
+    }
+    if let Some(ms) = args.tool_heartbeat_interval_ms {
+        settings.tool_heartbeat_interval_ms = ms;
+    }
+    if let Some(secs) = args.mcp_health_check_interval_secs {
+        settings.mcp_health_check_interval_secs = secs;
+    }
+    if let Some(secs) = args.chat_autosave_interval_secs {
+        settings.chat_autosave_interval_secs = secs;
+    }
+    if let Some(enabled) = args.use_format_stop_sequences {
+        settings.use_format_stop_sequences = enabled;
+    }
+    if let Some(max_chars) = args.max_tool_result_chars {
+        settings.max_tool_result_chars = max_chars;
+    }
+    if let Some(enabled) = args.summarize_large_tool_results {
+        settings.summarize_large_tool_results = enabled;
+    }
+    if let Some(chars) = args.tool_result_summary_threshold_chars {
+        settings.tool_result_summary_threshold_chars = chars;
+    }
+    if let Some(max_chars) = args.max_system_prompt_chars {
+        settings.max_system_prompt_chars = max_chars;
+    }
+    if let Some(enabled) = args.history_compaction_enabled {
+        settings.history_compaction_enabled = enabled;
+    }
+    if let Some(turns) = args.history_compaction_keep_turns {
+        settings.history_compaction_keep_turns = turns;
+    }
+    if let Some(retries) = args.foundry_chat_retries {
+        settings.foundry_chat_retries = retries;
+    }
+    if let Some(secs) = args.first_token_timeout_secs {
+        settings.first_token_timeout_secs = secs;
+    }
+    if let Some(count) = args.schema_embed_concurrency {
+        settings.schema_embed_concurrency = count.max(1);
+    }
     if let Some(v) = args.python_execution {
         if v {
             if !settings.always_on_builtin_tools.contains(&"python_execution".to_string()) {
@@ -307,6 +542,45 @@ pub fn apply_cli_overrides(args: &CliArgs, settings: &mut AppSettings) -> Launch
     if let Some(v) = args.python_tool_calling {
         settings.python_tool_calling_enabled = v;
     }
+    if let Some(timeout_ms) = args.python_execution_timeout_ms {
+        settings.python_execution_timeout_ms = timeout_ms;
+    }
+    if let Some(limit_mb) = args.python_memory_limit_mb {
+        settings.python_memory_limit_mb = limit_mb;
+    }
+    if let Some(raw) = &args.python_result_format {
+        if let Some(format) = parse_result_format(raw) {
+            settings.python_result_format = format;
+        } else {
+            println!("[Launch] Unknown python_result_format '{}', ignoring", raw);
+        }
+    }
+    if let Some(raw) = &args.python_sandbox_backend {
+        if let Some(backend) = parse_python_sandbox_backend(raw) {
+            settings.python_sandbox_backend = backend;
+        } else {
+            println!("[Launch] Unknown python_sandbox_backend '{}', ignoring", raw);
+        }
+    }
+    if let Some(v) = args.python_multi_block {
+        settings.python_multi_block = v;
+    }
+    if let Some(v) = args.safe_mode {
+        settings.safe_mode = v;
+    }
+    if let Some(raw) = &args.sql_result_format {
+        if let Some(format) = parse_sql_result_format(raw) {
+            settings.sql_result_format = format;
+        } else {
+            println!("[Launch] Unknown sql_result_format '{}', ignoring", raw);
+        }
+    }
+    if let Some(v) = args.python_session_persistence {
+        settings.python_session_persistence_enabled = v;
+    }
+    if let Some(modules) = &args.python_allowed_modules {
+        settings.python_allowed_modules = Some(modules.clone());
+    }
     if let Some(v) = args.native_tool_calling {
         // CLI override for native tool calling - add/remove Native format
         if v {
@@ -393,6 +667,40 @@ pub fn apply_cli_overrides(args: &CliArgs, settings: &mut AppSettings) -> Launch
         }
     }
 
+    // Tool description overrides
+    for entry in &args.tool_description_overrides {
+        if let Some((key, raw_val)) = entry.split_once('=') {
+            match read_value_or_file(raw_val) {
+                Ok(value) => {
+                    settings.tool_description_overrides.insert(key.to_string(), value);
+                }
+                Err(e) => println!(
+                    "[Launch] Failed to apply tool_description_override {}: {}",
+                    key, e
+                ),
+            }
+        } else {
+            println!(
+                "[Launch] Invalid --tool-description-override '{}'. Expected server::tool=description_or_@file",
+                entry
+            );
+        }
+    }
+
+    // Reasoning effort overrides
+    for entry in &args.reasoning_effort_overrides {
+        if let Some((model, effort)) = entry.split_once('=') {
+            settings
+                .reasoning_effort_overrides
+                .insert(model.to_string(), effort.to_string());
+        } else {
+            println!(
+                "[Launch] Invalid --reasoning-effort-override '{}'. Expected model_id=effort",
+                entry
+            );
+        }
+    }
+
     // MCP servers
     if !args.mcp_servers.is_empty() {
         let mut parsed_servers: Vec<McpServerConfig> = Vec::new();
@@ -7,7 +7,10 @@
 //! due to their extensive dependencies on the agentic loop. This module
 //! contains the simpler chat-related commands.
 
-use crate::app_state::{ActorHandles, CancellationState, TurnProgress, TurnTrackerState};
+use crate::app_state::{
+    ActorHandles, CancellationState, ToolDisableInfo, TurnProgress, TurnTrackerState,
+};
+use crate::chat_export::{render_stored_chat_export, ExportFormat};
 use crate::protocol::{FoundryMsg, VectorMsg};
 use std::io::Write;
 use tauri::{Emitter, State};
@@ -97,6 +100,31 @@ pub async fn load_chat(
     rx.await.map_err(|_| "Vector actor died".to_string())
 }
 
+/// Export a chat transcript, tool calls included, as JSON or Markdown.
+/// Returns the rendered document for the frontend to save.
+#[tauri::command]
+pub async fn export_chat(
+    id: String,
+    format: ExportFormat,
+    handles: State<'_, ActorHandles>,
+) -> Result<String, String> {
+    let (tx, rx) = oneshot::channel();
+    handles
+        .vector_tx
+        .send(VectorMsg::FetchChatMessages {
+            id: id.clone(),
+            respond_to: tx,
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    let messages_json = rx
+        .await
+        .map_err(|_| "Vector actor died".to_string())?
+        .filter(|json| !json.trim().is_empty())
+        .ok_or_else(|| format!("Chat {} has no stored messages", id))?;
+    render_stored_chat_export(&id, &messages_json, format)
+}
+
 /// Update chat title and/or pinned status
 #[tauri::command]
 pub async fn update_chat(
@@ -186,6 +214,29 @@ pub async fn get_turn_status(
     Ok(guard.clone())
 }
 
+/// Get why tools are disabled for a chat's turn, if the repeated-error check disabled them
+#[tauri::command]
+pub async fn get_tool_disable_state(
+    chat_id: String,
+    turn_tracker: State<'_, TurnTrackerState>,
+) -> Result<Option<ToolDisableInfo>, String> {
+    Ok(turn_tracker.tool_disables.read().await.get(&chat_id).cloned())
+}
+
+/// Re-enable tools for a chat after the repeated-error check disabled them.
+/// A running turn picks this up on its next iteration. Returns whether tools were disabled.
+#[tauri::command]
+pub async fn reset_tool_disable_state(
+    chat_id: String,
+    turn_tracker: State<'_, TurnTrackerState>,
+) -> Result<bool, String> {
+    let cleared = turn_tracker.tool_disables.write().await.reset(&chat_id);
+    if cleared.is_some() {
+        println!("[Chat] Tools re-enabled for chat {}", chat_id);
+    }
+    Ok(cleared.is_some())
+}
+
 /// Log a message from the frontend to the terminal
 #[tauri::command]
 pub fn log_to_terminal(message: String) {
@@ -6,13 +6,14 @@
 //! NOTE: Schema *caching* uses the GPU embedding model (bulk indexing operation),
 //! while schema *search* during chat uses the CPU model (avoids LLM eviction).
 
-use crate::actors::database_toolbox_actor::DatabaseToolboxMsg;
-use crate::actors::schema_vector_actor::SchemaVectorMsg;
+use crate::actors::database_toolbox_actor::{DatabaseToolboxMsg, SourceConnectionStatus};
+use crate::actors::schema_vector_actor::{is_table_unchanged, SchemaVectorMsg};
 use crate::app_state::{ActorHandles, EmbeddingModelState, SettingsState};
 use crate::settings::{
     CachedTableSchema, DatabaseSourceConfig, DatabaseToolboxConfig, SupportedDatabaseKind,
 };
 use fastembed::TextEmbedding;
+use futures::StreamExt;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tauri::{AppHandle, Emitter, State};
@@ -204,6 +205,7 @@ pub async fn refresh_database_schemas_for_config(
     handles: &State<'_, ActorHandles>,
     embedding_state: &State<'_, EmbeddingModelState>,
     toolbox_config: &DatabaseToolboxConfig,
+    embed_concurrency: usize,
 ) -> Result<SchemaRefreshSummary, String> {
     let sources: Vec<DatabaseSourceConfig> = toolbox_config
         .sources
@@ -239,11 +241,7 @@ pub async fn refresh_database_schemas_for_config(
     }
 
     // Always use CPU embedding model (GPU embedding is disabled)
-    let model_guard = embedding_state.cpu_model.read().await;
-    let embedding_model = model_guard
-        .clone()
-        .ok_or_else(|| "CPU embedding model not initialized".to_string())?;
-    drop(model_guard);
+    let embedding_model = embedding_state.require_cpu_model().await?;
 
     ensure_toolbox_running(&handles.database_toolbox_tx, toolbox_config).await?;
 
@@ -251,8 +249,26 @@ pub async fn refresh_database_schemas_for_config(
     let mut errors = Vec::new();
 
     for source in sources {
-        match refresh_schema_cache_for_source(app_handle, handles, &source, embedding_model.clone())
-            .await
+        // A source that failed to connect is reported without blocking the others
+        let connection = source_connection_status(&handles.database_toolbox_tx, &source.id).await?;
+        if !connection.connected {
+            let err = connection.error.unwrap_or_default();
+            println!(
+                "[SchemaRefresh] Skipping source {} ({}): not connected: {}",
+                source.name, source.id, err
+            );
+            errors.push(format!("{} ({}): not connected: {}", source.name, source.id, err));
+            continue;
+        }
+
+        match refresh_schema_cache_for_source(
+            app_handle,
+            handles,
+            &source,
+            embedding_model.clone(),
+            embed_concurrency,
+        )
+        .await
         {
             Ok(status) => refreshed_sources.push(status),
             Err(err) => {
@@ -313,12 +329,20 @@ pub async fn refresh_database_schemas(
 ) -> Result<SchemaRefreshResult, String> {
     let settings_guard = settings_state.settings.read().await;
     let toolbox_config = settings_guard.database_toolbox.clone();
+    let embed_concurrency = settings_guard.schema_embed_concurrency;
     drop(settings_guard);
 
     println!("[SchemaRefresh] Starting refresh for ALL enabled sources");
 
     let summary =
-        refresh_database_schemas_for_config(&app_handle, &handles, &embedding_state, &toolbox_config).await?;
+        refresh_database_schemas_for_config(
+            &app_handle,
+            &handles,
+            &embedding_state,
+            &toolbox_config,
+            embed_concurrency,
+        )
+        .await?;
 
     let errors: Vec<SchemaRefreshError> = summary
         .errors
@@ -380,6 +404,7 @@ pub async fn refresh_database_schema_for_source(
 ) -> Result<SchemaRefreshResult, String> {
     let settings_guard = settings_state.settings.read().await;
     let toolbox_config = settings_guard.database_toolbox.clone();
+    let embed_concurrency = settings_guard.schema_embed_concurrency;
     drop(settings_guard);
 
     println!(
@@ -413,6 +438,7 @@ pub async fn refresh_database_schema_for_source(
         &handles,
         &embedding_state,
         &single_source_config,
+        embed_concurrency,
     )
     .await?;
 
@@ -463,11 +489,7 @@ pub async fn search_database_tables(
     }
 
     // Use CPU model for schema search during chat (avoids evicting LLM from GPU)
-    let model_guard = embedding_state.cpu_model.read().await;
-    let embedding_model = model_guard
-        .clone()
-        .ok_or_else(|| "CPU embedding model not initialized".to_string())?;
-    drop(model_guard);
+    let embedding_model = embedding_state.require_cpu_model().await?;
 
     // Embed the query
     let query_embeddings = embedding_model
@@ -486,6 +508,7 @@ pub async fn search_database_tables(
             query_embedding: query_vector,
             limit,
             min_score: 0.0, // Return all results up to limit, let UI filter if needed
+            source_ids: Vec::new(),
             respond_to: tx,
         })
         .await
@@ -658,24 +681,125 @@ pub async fn set_schema_table_enabled(
     table_fq_name: String,
     enabled: bool,
 ) -> Result<SchemaTableStatus, String> {
+    let (toolbox_config, source_name) = lookup_source(&settings_state, &source_id).await?;
+    toggle_cached_table(
+        &handles,
+        &embedding_state,
+        &toolbox_config,
+        &source_id,
+        &source_name,
+        &table_fq_name,
+        enabled,
+    )
+    .await
+}
+
+/// Outcome of one table in a batch enable/disable
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SchemaTableToggleResult {
+    pub table_fq_name: String,
+    /// Updated status when the toggle succeeded
+    pub status: Option<SchemaTableStatus>,
+    pub error: Option<String>,
+}
+
+/// Enable or disable many tables of one source. Each table is toggled on its own,
+/// so one failure doesn't stop the rest; every table gets a result.
+#[tauri::command]
+pub async fn set_schema_tables_enabled(
+    handles: State<'_, ActorHandles>,
+    settings_state: State<'_, SettingsState>,
+    embedding_state: State<'_, EmbeddingModelState>,
+    source_id: String,
+    tables: Vec<(String, bool)>,
+) -> Result<Vec<SchemaTableToggleResult>, String> {
+    let (toolbox_config, source_name) = lookup_source(&settings_state, &source_id).await?;
+
+    let mut results = Vec::with_capacity(tables.len());
+    for (table_fq_name, enabled) in tables {
+        let outcome = toggle_cached_table(
+            &handles,
+            &embedding_state,
+            &toolbox_config,
+            &source_id,
+            &source_name,
+            &table_fq_name,
+            enabled,
+        )
+        .await;
+        let (status, error) = match outcome {
+            Ok(status) => (Some(status), None),
+            Err(e) => (None, Some(e)),
+        };
+        results.push(SchemaTableToggleResult {
+            table_fq_name,
+            status,
+            error,
+        });
+    }
+
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    println!(
+        "[SchemaCache] Batch toggle for source {}: {} tables, {} failed",
+        source_id,
+        results.len(),
+        failed
+    );
+    Ok(results)
+}
+
+/// Remove one table and its columns from the schema cache
+#[tauri::command]
+pub async fn purge_schema_table(
+    handles: State<'_, ActorHandles>,
+    source_id: String,
+    table_fq_name: String,
+) -> Result<(), String> {
+    let (tx, rx) = oneshot::channel();
+    handles
+        .schema_tx
+        .send(crate::actors::schema_vector_actor::SchemaVectorMsg::DeleteTable {
+            source_id,
+            table_fq_name,
+            respond_to: tx,
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+    rx.await.map_err(|_| "Schema vector actor unavailable".to_string())?
+}
+
+/// Find a configured source, returning the toolbox config and the source's name
+async fn lookup_source(
+    settings_state: &SettingsState,
+    source_id: &str,
+) -> Result<(DatabaseToolboxConfig, String), String> {
     let settings_guard = settings_state.settings.read().await;
-    let toolbox_config = settings_guard.database_toolbox.clone();
     let source = settings_guard
         .database_toolbox
         .sources
         .iter()
         .find(|s| s.id == source_id)
-        .cloned()
         .ok_or_else(|| format!("Source not found: {}", source_id))?;
-    let source_name = source.name.clone();
-    drop(settings_guard);
+    Ok((settings_guard.database_toolbox.clone(), source.name.clone()))
+}
 
+/// Flip a table's enabled flag, fetching and caching the table first if it isn't cached
+async fn toggle_cached_table(
+    handles: &ActorHandles,
+    embedding_state: &EmbeddingModelState,
+    toolbox_config: &DatabaseToolboxConfig,
+    source_id: &str,
+    source_name: &str,
+    table_fq_name: &str,
+    enabled: bool,
+) -> Result<SchemaTableStatus, String> {
     // Try to flip the enabled flag on the cached record
     let (tx, rx) = oneshot::channel();
     handles
         .schema_tx
         .send(crate::actors::schema_vector_actor::SchemaVectorMsg::SetTableEnabled {
-            table_fq_name: table_fq_name.clone(),
+            table_fq_name: table_fq_name.to_string(),
             enabled,
             respond_to: tx,
         })
@@ -689,15 +813,11 @@ pub async fn set_schema_table_enabled(
         Err(_) => {
             // Table not cached, try to fetch and cache it
             // Use CPU model for schema operations during chat (avoids evicting LLM from GPU)
-            let model_guard = embedding_state.cpu_model.read().await;
-            let embedding_model = model_guard
-                .clone()
-                .ok_or_else(|| "CPU embedding model not initialized".to_string())?;
-            drop(model_guard);
+            let embedding_model = embedding_state.require_cpu_model().await?;
 
-            ensure_toolbox_running(&handles.database_toolbox_tx, &toolbox_config).await?;
+            ensure_source_ready(&handles.database_toolbox_tx, toolbox_config, source_id).await?;
 
-            let schema = fetch_table_schema(&handles.database_toolbox_tx, &source_id, &table_fq_name).await?;
+            let schema = fetch_table_schema(&handles.database_toolbox_tx, source_id, table_fq_name).await?;
 
             let (table_emb, col_embs) = embed_table_and_columns(embedding_model, &schema).await?;
 
@@ -717,7 +837,7 @@ pub async fn set_schema_table_enabled(
             handles
                 .schema_tx
                 .send(crate::actors::schema_vector_actor::SchemaVectorMsg::SetTableEnabled {
-                    table_fq_name: table_fq_name.clone(),
+                    table_fq_name: table_fq_name.to_string(),
                     enabled,
                     respond_to: tx,
                 })
@@ -731,8 +851,8 @@ pub async fn set_schema_table_enabled(
     };
 
     Ok(SchemaTableStatus {
-        source_id,
-        source_name,
+        source_id: source_id.to_string(),
+        source_name: source_name.to_string(),
         table_fq_name: table_schema.fully_qualified_name,
         enabled: table_schema.enabled,
         column_count: table_schema.columns.len(),
@@ -835,6 +955,16 @@ pub async fn ensure_toolbox_running(
     match start_result {
         Ok(()) => {
             println!("[SchemaRefresh] ✓ Toolbox started successfully");
+            // Report per-source readiness; broken sources don't fail the start
+            for source in config.sources.iter().filter(|s| s.enabled) {
+                let status = source_connection_status(toolbox_tx, &source.id).await?;
+                match status.error {
+                    None => println!("[SchemaRefresh]   ✓ '{}' connected", source.name),
+                    Some(err) => {
+                        println!("[SchemaRefresh]   ❌ '{}' not connected: {}", source.name, err)
+                    }
+                }
+            }
             Ok(())
         }
         Err(msg) if msg.contains("already running") => {
@@ -848,6 +978,38 @@ pub async fn ensure_toolbox_running(
     }
 }
 
+/// Connection status of one database source
+pub async fn source_connection_status(
+    toolbox_tx: &tokio::sync::mpsc::Sender<DatabaseToolboxMsg>,
+    source_id: &str,
+) -> Result<SourceConnectionStatus, String> {
+    let (tx, rx) = oneshot::channel();
+    toolbox_tx
+        .send(DatabaseToolboxMsg::GetSourceStatus {
+            source_id: source_id.to_string(),
+            reply_to: tx,
+        })
+        .await
+        .map_err(|e| format!("Failed to send request: {}", e))?;
+
+    rx.await
+        .map_err(|_| "Database toolbox actor unavailable".to_string())
+}
+
+/// Ensure the toolbox is running and the given source is connected
+pub async fn ensure_source_ready(
+    toolbox_tx: &tokio::sync::mpsc::Sender<DatabaseToolboxMsg>,
+    config: &DatabaseToolboxConfig,
+    source_id: &str,
+) -> Result<(), String> {
+    ensure_toolbox_running(toolbox_tx, config).await?;
+    let status = source_connection_status(toolbox_tx, source_id).await?;
+    match status.error {
+        None => Ok(()),
+        Some(err) => Err(format!("Source '{}' is not connected: {}", source_id, err)),
+    }
+}
+
 /// Enumerate schemas for a database source
 pub async fn enumerate_source_schemas(
     toolbox_tx: &tokio::sync::mpsc::Sender<DatabaseToolboxMsg>,
@@ -1105,6 +1267,20 @@ pub async fn embed_table_and_columns(
     Ok((table_embedding, all_column_embeddings))
 }
 
+/// Run `embed` over `items` with at most `concurrency` calls in flight,
+/// returning the results in input order.
+pub async fn embed_in_order<T, R, F, Fut>(items: Vec<T>, concurrency: usize, embed: F) -> Vec<R>
+where
+    F: FnMut(T) -> Fut,
+    Fut: std::future::Future<Output = R>,
+{
+    futures::stream::iter(items)
+        .map(embed)
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
 /// Cache table and columns in the schema vector store
 pub async fn cache_table_and_columns(
     schema_tx: &tokio::sync::mpsc::Sender<SchemaVectorMsg>,
@@ -1219,12 +1395,54 @@ pub async fn load_cached_enabled_flags(
     Ok(map)
 }
 
-/// Refresh schema cache for a single source
+/// Load cached schema fingerprints for a source
+pub async fn load_cached_fingerprints(
+    schema_tx: &tokio::sync::mpsc::Sender<SchemaVectorMsg>,
+    source_id: &str,
+) -> Result<HashMap<String, String>, String> {
+    let (tx, rx) = oneshot::channel();
+    schema_tx
+        .send(SchemaVectorMsg::GetTableFingerprints {
+            source_id: source_id.to_string(),
+            respond_to: tx,
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+    rx.await
+        .map_err(|_| "Schema vector actor unavailable".to_string())
+}
+
+/// Remove one table and its columns from the schema cache
+async fn delete_cached_table(
+    schema_tx: &tokio::sync::mpsc::Sender<SchemaVectorMsg>,
+    source_id: &str,
+    table_fq_name: &str,
+) -> Result<(), String> {
+    let (tx, rx) = oneshot::channel();
+    schema_tx
+        .send(SchemaVectorMsg::DeleteTable {
+            source_id: source_id.to_string(),
+            table_fq_name: table_fq_name.to_string(),
+            respond_to: tx,
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+    rx.await
+        .map_err(|_| "Schema vector actor unavailable".to_string())?
+}
+
+/// Refresh schema cache for a single source. Tables whose columns haven't
+/// changed since the last refresh keep their embeddings; changed and new tables
+/// are re-embedded, up to `embed_concurrency` at a time, and tables that no
+/// longer exist are removed.
 pub async fn refresh_schema_cache_for_source(
     app_handle: &AppHandle,
     handles: &State<'_, ActorHandles>,
     source: &DatabaseSourceConfig,
     embedding_model: Arc<TextEmbedding>,
+    embed_concurrency: usize,
 ) -> Result<SchemaSourceStatus, String> {
     let _ = app_handle.emit(
         "schema-refresh-progress",
@@ -1264,8 +1482,24 @@ pub async fn refresh_schema_cache_for_source(
         }
     };
 
-    // Remove stale entries so we only keep current enumeration
-    let _ = clear_source_cache(&handles.schema_tx, &source.id).await;
+    let cached_fingerprints = match load_cached_fingerprints(&handles.schema_tx, &source.id).await {
+        Ok(m) => m,
+        Err(e) => {
+            let _ = app_handle.emit(
+                "schema-refresh-progress",
+                SchemaRefreshProgress {
+                    message: format!("Failed to load cached schemas for {}", source.name),
+                    source_name: source.name.clone(),
+                    current_table: None,
+                    tables_done: 0,
+                    tables_total: 0,
+                    is_complete: false,
+                    error: Some(e.clone()),
+                },
+            );
+            return Err(e);
+        }
+    };
 
     let mut datasets = match enumerate_source_schemas(&handles.database_toolbox_tx, &source.id).await {
         Ok(d) => d,
@@ -1308,17 +1542,18 @@ pub async fn refresh_schema_cache_for_source(
     
     let mut tables_status = Vec::new();
     let mut all_tables_to_process = Vec::new();
+    // Sharded/dated datasets and tables (e.g. events_20240101) are not cached
+    let skip_pattern = source.skip_table_regex()?;
 
     // First pass: gather all tables to process across all datasets
     for dataset in &datasets {
         let dataset_clean = dataset.trim().to_string();
-        // Skip datasets ending with numeric suffix (commonly sharded/dated tables)
-        if dataset_clean
-            .chars()
-            .last()
-            .map(|c| c.is_ascii_digit())
-            .unwrap_or(false)
-        {
+        if skip_pattern.is_match(&dataset_clean) {
+            println!(
+                "[SchemaRefresh] Skipping dataset {} (matches skip pattern {})",
+                dataset_clean,
+                skip_pattern.as_str()
+            );
             continue;
         }
 
@@ -1335,13 +1570,13 @@ pub async fn refresh_schema_cache_for_source(
 
         for table_name in tables {
             let table_clean = table_name.trim().to_string();
-            // Skip tables ending with numeric suffix
-            if table_clean
-                .chars()
-                .last()
-                .map(|c| c.is_ascii_digit())
-                .unwrap_or(false)
-            {
+            if skip_pattern.is_match(&table_clean) {
+                println!(
+                    "[SchemaRefresh] Skipping table {}.{} (matches skip pattern {})",
+                    dataset_clean,
+                    table_clean,
+                    skip_pattern.as_str()
+                );
                 continue;
             }
 
@@ -1368,12 +1603,28 @@ pub async fn refresh_schema_cache_for_source(
 
     let tables_total = all_tables_to_process.len();
     let mut tables_done = 0;
+    let mut reused = 0;
+
+    // Remove cached tables that are no longer enumerated
+    let current: HashSet<String> = all_tables_to_process
+        .iter()
+        .map(|(dataset, table)| build_fully_qualified_table_name(source, dataset, table))
+        .collect();
+    for fq_name in cached_fingerprints.keys().filter(|n| !current.contains(*n)) {
+        match delete_cached_table(&handles.schema_tx, &source.id, fq_name).await {
+            Ok(()) => println!("[SchemaRefresh] Removed table {} (no longer present)", fq_name),
+            Err(err) => println!("[SchemaRefresh] Failed to remove table {}: {}", fq_name, err),
+        }
+    }
 
     println!(
         "[SchemaRefresh] Source '{}': found {} tables to process",
         source.name, tables_total
     );
 
+    // Fetch schemas in order; changed and new tables are queued for embedding
+    let mut fetched: Vec<(SchemaTableStatus, bool)> = Vec::new();
+    let mut to_embed: Vec<CachedTableSchema> = Vec::new();
     for (dataset_clean, table_clean) in all_tables_to_process {
         tables_done += 1;
         let fq_name = build_fully_qualified_table_name(source, &dataset_clean, &table_clean);
@@ -1400,58 +1651,36 @@ pub async fn refresh_schema_cache_for_source(
         match fetch_table_schema(&handles.database_toolbox_tx, &source.id, &fq_name).await {
             Ok(mut table_schema) => {
                 table_schema.enabled = enabled;
-                // Annotate join-worthy columns for chunk key purposes
-                let partition_set: HashSet<String> =
-                    table_schema.partition_columns.iter().cloned().collect();
-                let cluster_set: HashSet<String> =
-                    table_schema.cluster_columns.iter().cloned().collect();
-                let primary_set: HashSet<String> =
-                    table_schema.primary_keys.iter().cloned().collect();
-
-                let (table_embedding, column_embeddings) =
-                    match embed_table_and_columns(embedding_model.clone(), &table_schema).await
-                    {
-                        Ok(res) => res,
-                        Err(err) => {
-                            println!(
-                                "[SchemaRefresh] Failed to embed table {}: {}",
-                                fq_name, err
-                            );
-                            continue;
-                        }
-                    };
-
-                if let Err(err) = cache_table_and_columns(
-                    &handles.schema_tx,
-                    table_schema.clone(),
-                    table_embedding,
-                    column_embeddings,
-                    &primary_set,
-                    &partition_set,
-                    &cluster_set,
-                )
-                .await
-                {
-                    println!(
-                        "[SchemaRefresh] Failed to cache table {}: {}",
-                        fq_name, err
-                    );
-                    continue;
-                }
-
-                println!(
-                    "[SchemaRefresh] ✓ Cached table {} ({} columns)",
-                    fq_name, table_schema.columns.len()
-                );
-                
-                tables_status.push(SchemaTableStatus {
+                let status = SchemaTableStatus {
                     source_id: source.id.clone(),
                     source_name: source.name.clone(),
                     table_fq_name: fq_name.clone(),
                     enabled,
                     column_count: table_schema.columns.len(),
                     description: table_schema.description.clone(),
-                });
+                };
+
+                if is_table_unchanged(&cached_fingerprints, &table_schema) {
+                    reused += 1;
+                    println!("[SchemaRefresh] Table {} unchanged, keeping embeddings", fq_name);
+                    fetched.push((status, false));
+                    continue;
+                }
+
+                // Drop the old columns so removed ones don't linger
+                if cached_fingerprints.contains_key(&fq_name) {
+                    if let Err(err) =
+                        delete_cached_table(&handles.schema_tx, &source.id, &fq_name).await
+                    {
+                        println!(
+                            "[SchemaRefresh] Failed to remove old schema for {}: {}",
+                            fq_name, err
+                        );
+                    }
+                }
+
+                fetched.push((status, true));
+                to_embed.push(table_schema);
             }
             Err(err) => {
                 println!(
@@ -1462,9 +1691,83 @@ pub async fn refresh_schema_cache_for_source(
         }
     }
 
+    let embed_concurrency = embed_concurrency.max(1);
     println!(
-        "[SchemaRefresh] Source '{}' complete: {} tables cached",
-        source.name, tables_status.len()
+        "[SchemaRefresh] Embedding {} tables for '{}' ({} at a time)",
+        to_embed.len(),
+        source.name,
+        embed_concurrency
+    );
+    let mut embedded = embed_in_order(to_embed, embed_concurrency, |table_schema| {
+        let model = embedding_model.clone();
+        async move {
+            let result = embed_table_and_columns(model, &table_schema).await;
+            (table_schema, result)
+        }
+    })
+    .await
+    .into_iter();
+
+    // Cache in the original order so statuses match the enumeration
+    for (status, needs_embedding) in fetched {
+        if !needs_embedding {
+            tables_status.push(status);
+            continue;
+        }
+        let Some((table_schema, embedding)) = embedded.next() else {
+            break;
+        };
+        let fq_name = &status.table_fq_name;
+        let (table_embedding, column_embeddings) = match embedding {
+            Ok(res) => res,
+            Err(err) => {
+                println!(
+                    "[SchemaRefresh] Failed to embed table {}: {}",
+                    fq_name, err
+                );
+                continue;
+            }
+        };
+
+        // Annotate join-worthy columns for chunk key purposes
+        let partition_set: HashSet<String> =
+            table_schema.partition_columns.iter().cloned().collect();
+        let cluster_set: HashSet<String> =
+            table_schema.cluster_columns.iter().cloned().collect();
+        let primary_set: HashSet<String> =
+            table_schema.primary_keys.iter().cloned().collect();
+        let column_count = table_schema.columns.len();
+
+        if let Err(err) = cache_table_and_columns(
+            &handles.schema_tx,
+            table_schema,
+            table_embedding,
+            column_embeddings,
+            &primary_set,
+            &partition_set,
+            &cluster_set,
+        )
+        .await
+        {
+            println!(
+                "[SchemaRefresh] Failed to cache table {}: {}",
+                fq_name, err
+            );
+            continue;
+        }
+
+        println!(
+            "[SchemaRefresh] ✓ Cached table {} ({} columns)",
+            fq_name, column_count
+        );
+        tables_status.push(status);
+    }
+
+    println!(
+        "[SchemaRefresh] Source '{}' complete: {} tables cached ({} unchanged)",
+        source.name,
+        tables_status.len(),
+        reused
     );
 
     Ok(SchemaSourceStatus {
@@ -1474,3 +1777,66 @@ pub async fn refresh_schema_cache_for_source(
         tables: tables_status,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Stands in for the embedding model: counts calls in flight, optionally
+    /// waits on a barrier, and pairs each table with a vector derived from its name
+    /// the way the refresh pairs schemas with their embeddings.
+    struct EmbedProbe {
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+        barrier: Option<tokio::sync::Barrier>,
+    }
+
+    impl EmbedProbe {
+        fn new(barrier_size: Option<usize>) -> Arc<Self> {
+            Arc::new(Self {
+                in_flight: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                barrier: barrier_size.map(tokio::sync::Barrier::new),
+            })
+        }
+
+        async fn embed(self: Arc<Self>, table: String) -> (String, Vec<f32>) {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            if let Some(barrier) = &self.barrier {
+                barrier.wait().await;
+            } else {
+                tokio::task::yield_now().await;
+            }
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            let embedding = vec![table.len() as f32, table.bytes().map(f32::from).sum()];
+            (table, embedding)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embed_concurrency_overlaps_calls_with_same_results() {
+        let tables: Vec<String> = (0..8).map(|i| format!("dataset.table_{}", i)).collect();
+
+        let serial_probe = EmbedProbe::new(None);
+        let serial = embed_in_order(tables.clone(), 1, |t| serial_probe.clone().embed(t)).await;
+        assert_eq!(serial_probe.peak.load(Ordering::SeqCst), 1);
+
+        // Every group of 4 calls must be in flight together to pass the barrier
+        let concurrent_probe = EmbedProbe::new(Some(4));
+        let concurrent = tokio::time::timeout(
+            Duration::from_secs(5),
+            embed_in_order(tables.clone(), 4, |t| concurrent_probe.clone().embed(t)),
+        )
+        .await
+        .expect("embed calls did not overlap");
+        assert_eq!(concurrent_probe.peak.load(Ordering::SeqCst), 4);
+
+        assert_eq!(serial, concurrent);
+        let order: Vec<&String> = concurrent.iter().map(|(table, _)| table).collect();
+        assert_eq!(order, tables.iter().collect::<Vec<_>>());
+    }
+}
@@ -4,11 +4,21 @@
 //! listing tools, and executing remote tool calls.
 
 use crate::actors::mcp_host_actor::{McpTool, McpToolResult};
-use crate::app_state::{ActorHandles, SettingsState};
+use crate::app_state::{
+    ActorHandles, EmbeddingModelState, LaunchConfigState, McpHealthState, McpServerHealth,
+    SettingsState, ToolRegistryState,
+};
 use crate::protocol::McpHostMsg;
-use crate::settings::McpServerConfig;
+use crate::settings::{self, McpServerConfig};
+use crate::tool_capability::ToolLaunchFilter;
+use crate::tool_registry::{
+    apply_mcp_description_overrides, RegistrySnapshot, RegistryStats, SharedToolRegistry,
+};
+use crate::tools::tool_search::precompute_tool_search_embeddings;
+use std::collections::HashMap;
+use std::time::Instant;
 use tauri::State;
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot};
 
 /// Result of syncing an MCP server - includes error message if failed
 #[derive(Debug, Clone, serde::Serialize)]
@@ -162,7 +172,13 @@ pub async fn execute_mcp_tool(
     tool_name: String,
     arguments: serde_json::Value,
     handles: State<'_, ActorHandles>,
+    settings_state: State<'_, SettingsState>,
 ) -> Result<McpToolResult, String> {
+    let timeout = settings_state
+        .settings
+        .read()
+        .await
+        .mcp_tool_timeout_for(&server_id);
     let (tx, rx) = oneshot::channel();
     handles
         .mcp_host_tx
@@ -170,6 +186,7 @@ pub async fn execute_mcp_tool(
             server_id,
             tool_name,
             arguments,
+            timeout,
             respond_to: tx,
         })
         .await
@@ -178,6 +195,92 @@ pub async fn execute_mcp_tool(
     rx.await.map_err(|_| "MCP Host actor died".to_string())?
 }
 
+/// Outcome of a single MCP tool call made through `debug_mcp_tool`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct McpToolDebugResult {
+    /// The server's result, content parts unmodified
+    pub result: McpToolResult,
+    /// Time spent on the tools/call request (connecting is not included)
+    pub elapsed_ms: u64,
+    /// Whether the server had to be connected for this call
+    pub freshly_connected: bool,
+}
+
+/// Call one MCP tool in isolation, connecting its server first if needed,
+/// and report the raw result with timing
+#[tauri::command]
+pub async fn debug_mcp_tool(
+    server_id: String,
+    tool_name: String,
+    arguments: serde_json::Value,
+    handles: State<'_, ActorHandles>,
+    settings_state: State<'_, SettingsState>,
+) -> Result<McpToolDebugResult, String> {
+    let (tx, rx) = oneshot::channel();
+    handles
+        .mcp_host_tx
+        .send(McpHostMsg::GetServerStatus {
+            server_id: server_id.clone(),
+            respond_to: tx,
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    let connected = rx.await.map_err(|_| "MCP Host actor died".to_string())?;
+
+    if !connected {
+        let config = settings_state
+            .settings
+            .read()
+            .await
+            .get_all_mcp_configs()
+            .into_iter()
+            .find(|c| c.id == server_id)
+            .ok_or_else(|| format!("Unknown MCP server: {}", server_id))?;
+        println!("[MCP] Debug call: connecting {} first", server_id);
+        let (tx, rx) = oneshot::channel();
+        handles
+            .mcp_host_tx
+            .send(McpHostMsg::ConnectServer {
+                config,
+                respond_to: tx,
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        rx.await.map_err(|_| "MCP Host actor died".to_string())??;
+    }
+
+    let timeout = settings_state
+        .settings
+        .read()
+        .await
+        .mcp_tool_timeout_for(&server_id);
+    let started = Instant::now();
+    let (tx, rx) = oneshot::channel();
+    handles
+        .mcp_host_tx
+        .send(McpHostMsg::ExecuteTool {
+            server_id: server_id.clone(),
+            tool_name: tool_name.clone(),
+            arguments,
+            timeout,
+            respond_to: tx,
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    let result = rx.await.map_err(|_| "MCP Host actor died".to_string())??;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    println!(
+        "[MCP] Debug call {}::{} finished in {}ms (is_error={})",
+        server_id, tool_name, elapsed_ms, result.is_error
+    );
+
+    Ok(McpToolDebugResult {
+        result,
+        elapsed_ms,
+        freshly_connected: !connected,
+    })
+}
+
 /// Get connection status of a specific MCP server
 #[tauri::command]
 pub async fn get_mcp_server_status(
@@ -235,3 +338,142 @@ pub async fn test_mcp_server_config(
 
     rx.await.map_err(|_| "MCP Host actor died".to_string())?
 }
+
+/// Latest background health check result for each checked MCP server
+#[tauri::command]
+pub async fn get_mcp_health(
+    health_state: State<'_, McpHealthState>,
+) -> Result<Vec<McpServerHealth>, String> {
+    let mut servers: Vec<McpServerHealth> =
+        health_state.servers.read().await.values().cloned().collect();
+    servers.sort_by(|a, b| a.server_id.cmp(&b.server_id));
+    Ok(servers)
+}
+
+/// Reconnect enabled MCP servers and re-register their current tools, keeping
+/// materialized tools. Returns how many servers registered tools.
+pub async fn sync_and_register_tools(
+    mcp_host_tx: &mpsc::Sender<McpHostMsg>,
+    configs: &[McpServerConfig],
+    tool_filter: &ToolLaunchFilter,
+    description_overrides: &HashMap<String, String>,
+    registry: &SharedToolRegistry,
+) -> Result<usize, String> {
+    let (sync_tx, sync_rx) = oneshot::channel();
+    mcp_host_tx
+        .send(McpHostMsg::SyncEnabledServers {
+            configs: configs.to_vec(),
+            respond_to: sync_tx,
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    for (server_id, result) in sync_rx.await.map_err(|_| "MCP Host actor died".to_string())? {
+        if let Err(e) = result {
+            println!("[MCP] Failed to sync {}: {}", server_id, e);
+        }
+    }
+
+    let (tools_tx, tools_rx) = oneshot::channel();
+    mcp_host_tx
+        .send(McpHostMsg::GetAllToolDescriptions {
+            respond_to: tools_tx,
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    let tool_descriptions = tools_rx
+        .await
+        .map_err(|_| "MCP Host actor died".to_string())?;
+
+    // Same servers and tools as a chat turn registers (database sources go
+    // through sql_select instead)
+    let mut filtered: Vec<(String, Vec<McpTool>)> = tool_descriptions
+        .into_iter()
+        .filter_map(|(server_id, tools)| {
+            let is_enabled = configs
+                .iter()
+                .any(|c| c.id == server_id && c.enabled && !c.is_database_source);
+            if !is_enabled || !tool_filter.server_allowed(&server_id) {
+                return None;
+            }
+            let tools: Vec<McpTool> = tools
+                .into_iter()
+                .filter(|t| tool_filter.tool_allowed(&server_id, &t.name))
+                .collect();
+            (!tools.is_empty()).then_some((server_id, tools))
+        })
+        .collect();
+    apply_mcp_description_overrides(&mut filtered, description_overrides);
+
+    {
+        let mut registry = registry.write().await;
+        let materialized = registry.materialized_tool_keys();
+        registry.clear_domain_tools();
+        for (server_id, tools) in &filtered {
+            let config = configs.iter().find(|c| c.id == *server_id);
+            let defer = config.map(|c| c.defer_tools).unwrap_or(false);
+            let python_name = config
+                .map(|c| c.get_python_name())
+                .unwrap_or_else(|| settings::to_python_identifier(server_id));
+            registry.register_mcp_tools(server_id, &python_name, tools, defer);
+        }
+        registry.materialize_tools(&materialized);
+    }
+
+    Ok(filtered.len())
+}
+
+/// Reconnect enabled MCP servers and rebuild the tool registry from their
+/// current tool lists, so schema changes are picked up without sending a
+/// message. Tools materialized by an in-flight turn stay materialized.
+#[tauri::command]
+pub async fn refresh_tool_registry(
+    handles: State<'_, ActorHandles>,
+    settings_state: State<'_, SettingsState>,
+    launch_config: State<'_, LaunchConfigState>,
+    tool_registry_state: State<'_, ToolRegistryState>,
+    embedding_state: State<'_, EmbeddingModelState>,
+) -> Result<RegistryStats, String> {
+    let (configs, description_overrides) = {
+        let settings = settings_state.settings.read().await;
+        (
+            settings.get_all_mcp_configs(),
+            settings.tool_description_overrides.clone(),
+        )
+    };
+    let registered = sync_and_register_tools(
+        &handles.mcp_host_tx,
+        &configs,
+        &launch_config.tool_filter,
+        &description_overrides,
+        &tool_registry_state.registry,
+    )
+    .await?;
+
+    if registered > 0 {
+        match precompute_tool_search_embeddings(
+            tool_registry_state.registry.clone(),
+            embedding_state.cpu_model.clone(),
+        )
+        .await
+        {
+            Ok(count) => println!("[MCP] Refresh: pre-computed embeddings for {} tools", count),
+            Err(e) => println!("[MCP] Refresh: failed to pre-compute tool embeddings: {}", e),
+        }
+    }
+
+    let stats = tool_registry_state.registry.read().await.stats();
+    println!(
+        "[MCP] Tool registry refreshed: {} domain, {} deferred, {} materialized",
+        stats.domain_tools, stats.deferred_tools, stats.materialized_tools
+    );
+    Ok(stats)
+}
+
+/// Snapshot of the tool registry: built-ins, each server's tools with their
+/// python module, and which tools are deferred, materialized or callable from python
+#[tauri::command]
+pub async fn dump_tool_registry(
+    tool_registry_state: State<'_, ToolRegistryState>,
+) -> Result<RegistrySnapshot, String> {
+    Ok(tool_registry_state.registry.read().await.snapshot())
+}
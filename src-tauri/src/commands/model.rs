@@ -3,7 +3,7 @@
 //! Commands for listing, loading, unloading, and managing AI models
 //! through the Foundry Local service.
 
-use crate::app_state::{ActorHandles, SettingsState};
+use crate::app_state::{ActorHandles, EmbeddingModelState, EmbeddingModelStatus, SettingsState};
 use crate::protocol::{CachedModel, CatalogModel, FoundryMsg, FoundryServiceStatus, ModelInfo, ModelState};
 use crate::settings;
 use tauri::State;
@@ -232,6 +232,15 @@ pub async fn get_current_model(handles: State<'_, ActorHandles>) -> Result<Optio
     Ok(rx.await.map_err(|_| "Foundry actor died".to_string())?)
 }
 
+/// Whether the embedding model used by tool search, schema search and RAG is
+/// loaded, still loading, or failed (with the error)
+#[tauri::command]
+pub async fn get_embedding_model_status(
+    embedding_state: State<'_, EmbeddingModelState>,
+) -> Result<EmbeddingModelStatus, String> {
+    Ok(embedding_state.status().await)
+}
+
 /// Get the current model state machine state
 #[tauri::command]
 pub async fn get_model_state(handles: State<'_, ActorHandles>) -> Result<ModelState, String> {
@@ -41,11 +41,7 @@ pub async fn process_rag_documents(
     println!("[RAG] Processing {} paths (CPU embedding)", paths.len());
 
     // Always use CPU embedding model (GPU embedding is disabled)
-    let model_guard = embedding_state.cpu_model.read().await;
-    let embedding_model = model_guard
-        .clone()
-        .ok_or_else(|| "CPU embedding model not initialized".to_string())?;
-    drop(model_guard);
+    let embedding_model = embedding_state.require_cpu_model().await?;
 
     let (tx, rx) = oneshot::channel();
     handles
@@ -6,13 +6,16 @@
 use crate::agentic_state;
 use crate::app_state::{
     ActorHandles, EmbeddingModelState, LaunchConfigState, SettingsState, SettingsStateMachineState,
+    ToolRegistryState,
 };
 use crate::protocol::McpHostMsg;
 use crate::settings::{
-    self, enforce_python_name, AppSettings, ChatFormatName, McpServerConfig, ToolCallFormatConfig,
+    self, enforce_python_name, AppSettings, ChatFormatName, McpServerConfig, PythonModuleCatalog,
+    ToolCallFormatConfig, ToolCallFormatInfo, ToolCallFormatName,
 };
 use crate::state_machine::{AgenticStateMachine, StatePreview};
-use python_sandbox::sandbox::ALLOWED_MODULES as PYTHON_ALLOWED_MODULES;
+use crate::system_prompt::{lint_prompt_for_format, PromptLintWarning};
+use python_sandbox::sandbox::resolve_allowed_modules;
 use tauri::State;
 use tokio::sync::oneshot;
 
@@ -36,13 +39,52 @@ pub fn get_default_mcp_test_server() -> McpServerConfig {
     settings::default_mcp_test_server()
 }
 
-/// Get list of Python modules allowed in the sandbox
+/// Get list of Python modules allowed in the sandbox (the configured list when set)
 #[tauri::command]
-pub fn get_python_allowed_imports() -> Vec<String> {
-    PYTHON_ALLOWED_MODULES
-        .iter()
-        .map(|m| m.to_string())
-        .collect()
+pub async fn get_python_allowed_imports(
+    settings_state: State<'_, SettingsState>,
+) -> Result<Vec<String>, String> {
+    let guard = settings_state.settings.read().await;
+    Ok(resolve_allowed_modules(
+        guard.python_allowed_modules.as_deref(),
+    ))
+}
+
+/// Get the sandbox's importable modules with descriptions, whether each is a
+/// default or opted in, and the modules that can never be imported
+#[tauri::command]
+pub async fn get_python_module_catalog(
+    settings_state: State<'_, SettingsState>,
+) -> Result<PythonModuleCatalog, String> {
+    let guard = settings_state.settings.read().await;
+    Ok(guard.python_module_catalog())
+}
+
+/// Get every tool calling format with its display metadata
+#[tauri::command]
+pub fn get_available_tool_call_formats() -> Vec<ToolCallFormatInfo> {
+    ToolCallFormatName::ALL.iter().map(|format| format.info()).collect()
+}
+
+/// Check a custom system prompt for instructions that conflict with a tool call
+/// format (the primary format when none is given)
+#[tauri::command]
+pub async fn lint_system_prompt(
+    prompt: String,
+    format: Option<ToolCallFormatName>,
+    settings_state: State<'_, SettingsState>,
+) -> Result<Vec<PromptLintWarning>, String> {
+    let format = match format {
+        Some(format) => format,
+        None => settings_state.settings.read().await.tool_call_formats.primary,
+    };
+    let warnings = lint_prompt_for_format(&prompt, format);
+    println!(
+        "[Settings] System prompt lint ({}): {} warning(s)",
+        format.as_str(),
+        warnings.len()
+    );
+    Ok(warnings)
 }
 
 /// Save application settings
@@ -223,7 +265,14 @@ pub async fn update_tool_system_prompt(
     settings_state: State<'_, SettingsState>,
     settings_sm_state: State<'_, SettingsStateMachineState>,
     launch_config: State<'_, LaunchConfigState>,
+    tool_registry_state: State<'_, ToolRegistryState>,
 ) -> Result<(), String> {
+    if server_id == "builtin"
+        && !tool_registry_state.registry.read().await.is_builtin_tool(&tool_name)
+    {
+        return Err(format!("Unknown built-in tool: {}", tool_name));
+    }
+
     let mut guard = settings_state.settings.write().await;
     let key = tool_prompt_key(&server_id, &tool_name);
 
@@ -460,6 +509,8 @@ pub async fn get_state_machine_preview(
         model_tool_format: None,
         custom_tool_prompts: guard.tool_system_prompts.clone(),
         python_primary: guard.is_builtin_always_on("python_execution"),
+        prepend: guard.system_prompt_prefix.clone(),
+        append: guard.system_prompt_suffix.clone(),
     };
 
     let machine = AgenticStateMachine::new_from_settings_sm(&settings_sm_guard, prompt_context);
@@ -495,6 +546,7 @@ pub async fn update_database_toolbox_config(
     sm_guard.refresh(&guard, &launch_config.tool_filter);
 
     println!("[Settings] database_toolbox config updated");
+    let embed_concurrency = guard.schema_embed_concurrency;
     drop(guard);
 
     // If toolbox is disabled or has no enabled sources, ensure it's stopped
@@ -510,7 +562,14 @@ pub async fn update_database_toolbox_config(
     }
 
     let refresh_summary =
-        refresh_database_schemas_for_config(&app_handle, &handles, &embedding_state, &config).await?;
+        refresh_database_schemas_for_config(
+            &app_handle,
+            &handles,
+            &embedding_state,
+            &config,
+            embed_concurrency,
+        )
+        .await?;
 
     if !refresh_summary.errors.is_empty() {
         let joined = refresh_summary.errors.join("; ");
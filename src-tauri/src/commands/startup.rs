@@ -2,10 +2,14 @@
 //!
 //! This module provides commands for the frontend/backend startup handshake:
 //! - `frontend_ready`: Frontend signals it's ready and receives full state snapshot
+//! - `warm_start`: Connect MCP servers and preload the model ahead of the first message
 
 use crate::actors::startup_actor::StartupMsg;
-use crate::app_state::ActorHandles;
+use crate::app_state::{
+    ActorHandles, EmbeddingModelState, LaunchConfigState, SettingsState, ToolRegistryState,
+};
 use crate::protocol::StartupSnapshot;
+use crate::warm_start::{run_warm_start, WarmStartProgress};
 use tauri::State;
 use tokio::sync::oneshot;
 
@@ -45,3 +49,26 @@ pub async fn get_startup_snapshot(handles: State<'_, ActorHandles>) -> Result<St
     
     rx.await.map_err(|_| "Startup coordinator died".to_string())
 }
+
+/// Connect enabled MCP servers, embed their tools and load the selected model.
+/// Progress is also emitted as `warm-start-progress` events.
+#[tauri::command]
+pub async fn warm_start(
+    app_handle: tauri::AppHandle,
+    handles: State<'_, ActorHandles>,
+    settings_state: State<'_, SettingsState>,
+    launch_config: State<'_, LaunchConfigState>,
+    tool_registry_state: State<'_, ToolRegistryState>,
+    embedding_state: State<'_, EmbeddingModelState>,
+) -> Result<Vec<WarmStartProgress>, String> {
+    Ok(run_warm_start(
+        app_handle,
+        handles.mcp_host_tx.clone(),
+        handles.foundry_tx.clone(),
+        settings_state.settings.clone(),
+        launch_config.tool_filter.clone(),
+        tool_registry_state.registry.clone(),
+        embedding_state.cpu_model.clone(),
+    )
+    .await)
+}
@@ -3,8 +3,14 @@
 //! Commands for detecting tool calls in model responses, executing tools,
 //! and managing the approval workflow for tool execution.
 
-use crate::app_state::{ActorHandles, ToolApprovalDecision, ToolApprovalState};
+use crate::app_state::{
+    ActorHandles, EmbeddingModelState, SettingsState, ToolApprovalDecision, ToolApprovalState,
+    ToolRegistryState,
+};
 use crate::protocol::{parse_tool_calls, McpHostMsg, ParsedToolCall};
+use crate::tool_execution::{execute_python_code, render_mcp_tool_result};
+use crate::tools::code_execution::{CodeExecutionInput, CodeExecutionOutput};
+use crate::tools::tool_search::{ToolSearchExecutor, ToolSearchInput, ToolSearchOutput};
 use tauri::State;
 use tokio::sync::oneshot;
 
@@ -21,6 +27,7 @@ pub async fn execute_tool_call(
     tool_name: String,
     arguments: serde_json::Value,
     handles: State<'_, ActorHandles>,
+    settings_state: State<'_, SettingsState>,
 ) -> Result<String, String> {
     if server_id == "builtin" {
         // Handle built-in tools
@@ -35,6 +42,11 @@ pub async fn execute_tool_call(
         }
     } else {
         // Execute MCP tool
+        let timeout = settings_state
+            .settings
+            .read()
+            .await
+            .mcp_tool_timeout_for(&server_id);
         let (tx, rx) = oneshot::channel();
         handles
             .mcp_host_tx
@@ -42,22 +54,74 @@ pub async fn execute_tool_call(
                 server_id,
                 tool_name,
                 arguments,
+                timeout,
                 respond_to: tx,
             })
             .await
             .map_err(|e| e.to_string())?;
 
         let result = rx.await.map_err(|_| "MCP Host actor died".to_string())?;
-        result.map(|r| {
-            r.content
-                .into_iter()
-                .filter_map(|c| c.text)
-                .collect::<Vec<_>>()
-                .join("\n")
-        })
+        result.map(|r| render_mcp_tool_result(&r).0)
     }
 }
 
+/// Check Python code the way python_execution would (imports, blocked builtins,
+/// tool availability, syntax) without running it
+#[tauri::command]
+pub async fn validate_python_code(
+    code: Vec<String>,
+    handles: State<'_, ActorHandles>,
+    tool_registry_state: State<'_, ToolRegistryState>,
+    settings_state: State<'_, SettingsState>,
+) -> Result<CodeExecutionOutput, String> {
+    let allowed_modules = settings_state
+        .settings
+        .read()
+        .await
+        .resolved_python_allowed_modules();
+    let input = CodeExecutionInput {
+        code,
+        context: None,
+        timeout_ms: None,
+        validate_only: true,
+    };
+    execute_python_code(
+        input,
+        "validate".to_string(),
+        tool_registry_state.registry.clone(),
+        &handles.python_tx,
+        false,
+        None,
+        allowed_modules,
+        None,
+        None,
+    )
+    .await
+}
+
+/// Run a tool_search query on its own (for tuning discovery), returning the
+/// scored matches without materializing them or touching any chat
+#[tauri::command]
+pub async fn run_tool_search(
+    queries: Vec<String>,
+    top_k: usize,
+    tool_registry_state: State<'_, ToolRegistryState>,
+    embedding_state: State<'_, EmbeddingModelState>,
+) -> Result<ToolSearchOutput, String> {
+    // Same CPU model the chat path uses, so scores match what the model would see
+    let executor = ToolSearchExecutor::new(
+        tool_registry_state.registry.clone(),
+        embedding_state.cpu_model.clone(),
+    );
+    executor
+        .execute(ToolSearchInput {
+            queries,
+            query_weights: Vec::new(),
+            top_k: top_k.max(1),
+        })
+        .await
+}
+
 /// Approve a pending tool call
 #[tauri::command]
 pub async fn approve_tool_call(
@@ -100,6 +164,54 @@ pub async fn reject_tool_call(
     }
 }
 
+/// Cancel a pending approval (e.g. its turn was aborted) so the loop stops waiting
+#[tauri::command]
+pub async fn cancel_pending_approval(
+    approval_key: String,
+    approval_state: State<'_, ToolApprovalState>,
+) -> Result<bool, String> {
+    let sender = {
+        let mut pending = approval_state.pending.write().await;
+        pending.remove(&approval_key)
+    };
+
+    if let Some(sender) = sender {
+        sender
+            .send(ToolApprovalDecision::Cancelled)
+            .map_err(|_| "Failed to send cancellation")?;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+/// Approve a pending batch of tool calls (batch approval mode).
+/// When `calls` is given, those edited calls run instead of the proposed ones.
+#[tauri::command]
+pub async fn approve_tool_batch(
+    approval_key: String,
+    calls: Option<Vec<ParsedToolCall>>,
+    approval_state: State<'_, ToolApprovalState>,
+) -> Result<bool, String> {
+    let sender = {
+        let mut pending = approval_state.pending.write().await;
+        pending.remove(&approval_key)
+    };
+
+    if let Some(sender) = sender {
+        let decision = match calls {
+            Some(calls) => ToolApprovalDecision::Edited(calls),
+            None => ToolApprovalDecision::Approved,
+        };
+        sender
+            .send(decision)
+            .map_err(|_| "Failed to send approval")?;
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
 /// Get list of pending tool approval keys
 #[tauri::command]
 pub async fn get_pending_tool_approvals(
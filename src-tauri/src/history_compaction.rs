@@ -0,0 +1,450 @@
+//! Compaction of long chat histories.
+//!
+//! The frontend sends the whole conversation with every `chat` call, so a long
+//! chat eventually outgrows the model's context. When the estimated size of a
+//! turn crosses the threshold, the older turns are summarized by the model and
+//! replaced with a single assistant note; the most recent turns stay verbatim.
+//! Only the messages sent to the model are compacted; the stored chat keeps
+//! every turn. Summaries are cached per chat so later turns only summarize the
+//! messages that aged out since.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use tokio::sync::mpsc;
+
+use crate::protocol::{ChatMessage, ChatRequest, FoundryMsg};
+use crate::settings::ChatFormatName;
+
+/// Context size assumed when the model doesn't report one
+pub const DEFAULT_CONTEXT_TOKENS: usize = 8192;
+
+/// Compact once the estimate passes this share of the context (in percent),
+/// leaving room for tool results and the answer
+const COMPACTION_THRESHOLD_PERCENT: usize = 75;
+
+/// Longest tool result quoted in the summarization transcript
+const MAX_TRANSCRIPT_RESULT_CHARS: usize = 2000;
+
+/// Prefix of the note that replaces the summarized turns
+pub const SUMMARY_NOTE_PREFIX: &str = "[Summary of the earlier conversation]";
+
+const SUMMARY_INSTRUCTIONS: &str = "Summarize the conversation below so it can replace the \
+original messages. Keep facts, decisions, names, numbers, open questions and what each tool \
+call found. Be concise; write plain prose or bullet points with no preamble.";
+
+/// Rough token estimate (about 4 characters per token plus per-message overhead)
+pub fn estimate_tokens(messages: &[ChatMessage]) -> usize {
+    messages
+        .iter()
+        .map(|m| {
+            let tool_chars: usize = m
+                .tool_calls
+                .iter()
+                .flatten()
+                .map(|c| c.function.name.len() + c.function.arguments.len())
+                .sum();
+            (m.content.chars().count() + tool_chars) / 4 + 4
+        })
+        .sum()
+}
+
+/// Token estimate above which the history is compacted
+pub fn compaction_threshold(max_input_tokens: Option<u32>) -> usize {
+    let context = max_input_tokens
+        .map(|t| t as usize)
+        .filter(|t| *t > 0)
+        .unwrap_or(DEFAULT_CONTEXT_TOKENS);
+    context * COMPACTION_THRESHOLD_PERCENT / 100
+}
+
+/// Whether `msg` begins a new turn. Tool results (and text-format results sent
+/// as user messages right after a tool call) belong to the call's turn.
+fn starts_turn(prev: Option<&ChatMessage>, msg: &ChatMessage) -> bool {
+    msg.role == "user" && !prev.is_some_and(|p| p.role == "assistant" && p.tool_calls.is_some())
+}
+
+/// Split the history into the turns to summarize and the `keep_turns` most
+/// recent turns to keep verbatim. None if there is nothing older to compact.
+pub fn split_for_compaction(
+    history: &[ChatMessage],
+    keep_turns: usize,
+) -> Option<(Vec<ChatMessage>, Vec<ChatMessage>)> {
+    let history: Vec<&ChatMessage> = history.iter().filter(|m| m.role != "system").collect();
+    let turn_starts: Vec<usize> = (0..history.len())
+        .filter(|&i| starts_turn(i.checked_sub(1).map(|p| history[p]), history[i]))
+        .collect();
+    if turn_starts.len() <= keep_turns {
+        return None;
+    }
+
+    let split = if keep_turns == 0 {
+        history.len()
+    } else {
+        turn_starts[turn_starts.len() - keep_turns]
+    };
+    if split == 0 {
+        return None;
+    }
+    let older = history[..split].iter().map(|m| (*m).clone()).collect();
+    let recent = history[split..].iter().map(|m| (*m).clone()).collect();
+    Some((older, recent))
+}
+
+/// Plain-text transcript of the turns to summarize. Tool calls and their
+/// results are written next to each other so the summary keeps them together.
+fn render_transcript(messages: &[ChatMessage]) -> String {
+    let mut lines = Vec::new();
+    for msg in messages {
+        match msg.role.as_str() {
+            "user" => lines.push(format!("User: {}", msg.content)),
+            "assistant" => {
+                if !msg.content.trim().is_empty() {
+                    lines.push(format!("Assistant: {}", msg.content));
+                }
+                for call in msg.tool_calls.iter().flatten() {
+                    lines.push(format!(
+                        "Assistant called tool {}({})",
+                        call.function.name, call.function.arguments
+                    ));
+                }
+            }
+            "tool" => {
+                let result: String =
+                    msg.content.chars().take(MAX_TRANSCRIPT_RESULT_CHARS).collect();
+                lines.push(format!("Tool result: {}", result));
+            }
+            _ => {}
+        }
+    }
+    lines.join("\n\n")
+}
+
+/// Messages for the summarization request
+pub fn summary_request_messages(older: &[ChatMessage]) -> Vec<ChatMessage> {
+    let message = |role: &str, content: String| ChatMessage {
+        role: role.to_string(),
+        content,
+        ..Default::default()
+    };
+    vec![
+        message("system", SUMMARY_INSTRUCTIONS.to_string()),
+        message("user", render_transcript(older)),
+    ]
+}
+
+/// The compacted history: one assistant note with the summary, then the
+/// recent turns unchanged
+pub fn compacted_history(summary: &str, recent: Vec<ChatMessage>) -> Vec<ChatMessage> {
+    let mut history = vec![ChatMessage {
+        role: "assistant".to_string(),
+        content: format!("{}\n{}", SUMMARY_NOTE_PREFIX, summary.trim()),
+        ..Default::default()
+    }];
+    history.extend(recent);
+    history
+}
+
+/// A chat's latest summary and the leading messages it covers
+#[derive(Debug, Clone)]
+struct CachedSummary {
+    summarized_messages: usize,
+    fingerprint: u64,
+    summary: String,
+}
+
+/// Summaries of compacted history, keyed by chat id
+#[derive(Debug, Default)]
+pub struct SummaryCache {
+    chats: HashMap<String, CachedSummary>,
+}
+
+/// Summary caches shared by every turn
+pub type SharedSummaryCache = std::sync::Arc<tokio::sync::RwLock<SummaryCache>>;
+
+/// Identifies a run of messages, so a cached summary is only reused for the
+/// history it was made from
+fn fingerprint(messages: &[ChatMessage]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for msg in messages {
+        msg.role.hash(&mut hasher);
+        msg.content.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+impl SummaryCache {
+    /// The cached summary of `chat_id` if it covers a prefix of `older`, with
+    /// the number of messages it covers
+    fn reusable(&self, chat_id: &str, older: &[ChatMessage]) -> Option<(String, usize)> {
+        let cached = self.chats.get(chat_id)?;
+        let covered = cached.summarized_messages;
+        (covered <= older.len() && fingerprint(&older[..covered]) == cached.fingerprint)
+            .then(|| (cached.summary.clone(), covered))
+    }
+
+    fn store(&mut self, chat_id: &str, older: &[ChatMessage], summary: &str) {
+        self.chats.insert(
+            chat_id.to_string(),
+            CachedSummary {
+                summarized_messages: older.len(),
+                fingerprint: fingerprint(older),
+                summary: summary.to_string(),
+            },
+        );
+    }
+}
+
+/// Summarize the older turns of `chat_id`, reusing its cached summary: an
+/// unchanged set of older turns needs no request, and newly aged-out turns are
+/// summarized together with the previous summary
+#[allow(clippy::too_many_arguments)]
+pub async fn summarize_history_cached(
+    cache: &SharedSummaryCache,
+    chat_id: &str,
+    foundry_tx: &mpsc::Sender<FoundryMsg>,
+    model: &str,
+    older: &[ChatMessage],
+    chat_format_default: ChatFormatName,
+    chat_format_overrides: HashMap<String, ChatFormatName>,
+) -> Result<String, String> {
+    let reusable = cache.read().await.reusable(chat_id, older);
+    let summary = match reusable {
+        Some((summary, covered)) if covered == older.len() => {
+            println!(
+                "[HistoryCompaction] Reusing the cached summary of {} message(s)",
+                covered
+            );
+            return Ok(summary);
+        }
+        Some((summary, covered)) => {
+            println!(
+                "[HistoryCompaction] Extending the cached summary with {} message(s)",
+                older.len() - covered
+            );
+            let mut messages = compacted_history(&summary, Vec::new());
+            messages.extend_from_slice(&older[covered..]);
+            summarize_history(
+                foundry_tx,
+                model,
+                &messages,
+                chat_format_default,
+                chat_format_overrides,
+            )
+            .await?
+        }
+        None => {
+            summarize_history(
+                foundry_tx,
+                model,
+                older,
+                chat_format_default,
+                chat_format_overrides,
+            )
+            .await?
+        }
+    };
+    cache.write().await.store(chat_id, older, &summary);
+    Ok(summary)
+}
+
+/// Drop a leading `<think>...</think>` block from a reasoning model's reply
+fn strip_thinking(text: &str) -> &str {
+    match (text.find("<think>"), text.find("</think>")) {
+        (Some(start), Some(end)) if start < end && text[..start].trim().is_empty() => {
+            &text[end + "</think>".len()..]
+        }
+        _ => text,
+    }
+}
+
+/// Ask the model to summarize the older turns
+pub async fn summarize_history(
+    foundry_tx: &mpsc::Sender<FoundryMsg>,
+    model: &str,
+    older: &[ChatMessage],
+    chat_format_default: ChatFormatName,
+    chat_format_overrides: HashMap<String, ChatFormatName>,
+) -> Result<String, String> {
+    request_summary(
+        foundry_tx,
+        model,
+        summary_request_messages(older),
+        chat_format_default,
+        chat_format_overrides,
+    )
+    .await
+}
+
+/// Send a tool-free summarization request and collect the reply, without any
+/// leading thinking block. An empty reply is an error.
+pub async fn request_summary(
+    foundry_tx: &mpsc::Sender<FoundryMsg>,
+    model: &str,
+    messages: Vec<ChatMessage>,
+    chat_format_default: ChatFormatName,
+    chat_format_overrides: HashMap<String, ChatFormatName>,
+) -> Result<String, String> {
+    let (token_tx, mut token_rx) = mpsc::unbounded_channel();
+    // Never cancelled; the sender only has to outlive the request
+    let (_cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+    foundry_tx
+        .send(FoundryMsg::Chat(Box::new(ChatRequest {
+            model: model.to_string(),
+            chat_history_messages: messages,
+            reasoning_effort: "low".to_string(),
+            native_tool_specs: None,
+            native_tool_calling_enabled: false,
+            chat_format_default,
+            chat_format_overrides,
+            respond_to: token_tx,
+            stream_cancel_rx: cancel_rx,
+            response_schema: None,
+            temperature: None,
+            seed: None,
+            stop_sequences: Vec::new(),
+        })))
+        .await
+        .map_err(|e| format!("Failed to send summarization request: {}", e))?;
+
+    let mut summary = String::new();
+    while let Some(token) = token_rx.recv().await {
+        summary.push_str(&token);
+    }
+    let summary = strip_thinking(&summary).trim().to_string();
+    if summary.is_empty() {
+        return Err("Model returned an empty summary".to_string());
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{OpenAIToolCall, OpenAIToolCallFunction};
+
+    fn message(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn tool_call_message() -> ChatMessage {
+        ChatMessage {
+            tool_calls: Some(vec![OpenAIToolCall {
+                id: "call_1".to_string(),
+                call_type: "function".to_string(),
+                function: OpenAIToolCallFunction {
+                    name: "get_weather".to_string(),
+                    arguments: "{\"city\":\"Oslo\"}".to_string(),
+                },
+            }]),
+            ..message("assistant", "")
+        }
+    }
+
+    #[test]
+    fn test_split_keeps_recent_turns_and_tool_pairs() {
+        let history = vec![
+            message("system", "old prompt"),
+            message("user", "Weather in Oslo?"),
+            tool_call_message(),
+            message("tool", "5C and rain"),
+            message("assistant", "It is 5C and raining."),
+            message("user", "And tomorrow?"),
+            message("assistant", "Sunny."),
+            message("user", "Thanks"),
+            message("assistant", "You're welcome."),
+        ];
+
+        let (older, recent) = split_for_compaction(&history, 2).unwrap();
+        // The whole first turn, tool call and result included, is summarized
+        assert_eq!(older.len(), 4);
+        assert_eq!(older[3].content, "It is 5C and raining.");
+        assert_eq!(recent.len(), 4);
+        assert_eq!(recent[0].content, "And tomorrow?");
+
+        let transcript = render_transcript(&older);
+        assert!(transcript.contains("Assistant called tool get_weather({\"city\":\"Oslo\"})"));
+        assert!(transcript.contains("Tool result: 5C and rain"));
+
+        assert!(split_for_compaction(&history, 3).is_none());
+
+        let compacted = compacted_history("Oslo: 5C, rain.", recent);
+        assert_eq!(compacted.len(), 5);
+        assert!(compacted[0].content.starts_with(SUMMARY_NOTE_PREFIX));
+    }
+
+    /// Answers every summarization request with "Summary N", counting requests
+    fn mock_summarizer() -> (
+        mpsc::Sender<FoundryMsg>,
+        std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    ) {
+        let (tx, mut rx) = mpsc::channel::<FoundryMsg>(8);
+        let transcripts = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = transcripts.clone();
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if let FoundryMsg::Chat(request) = msg {
+                    let ChatRequest {
+                        chat_history_messages,
+                        respond_to,
+                        ..
+                    } = *request;
+                    let mut seen = seen.lock().unwrap();
+                    seen.push(chat_history_messages[1].content.clone());
+                    let _ = respond_to.send(format!("Summary {}", seen.len()));
+                }
+            }
+        });
+        (tx, transcripts)
+    }
+
+    #[tokio::test]
+    async fn test_cached_summary_is_reused_and_extended() {
+        let (foundry_tx, transcripts) = mock_summarizer();
+        let cache = SharedSummaryCache::default();
+        let summarize = |older: Vec<ChatMessage>| {
+            let cache = cache.clone();
+            let foundry_tx = foundry_tx.clone();
+            async move {
+                summarize_history_cached(
+                    &cache,
+                    "chat-1",
+                    &foundry_tx,
+                    "model",
+                    &older,
+                    ChatFormatName::OpenaiCompletions,
+                    HashMap::new(),
+                )
+                .await
+                .unwrap()
+            }
+        };
+        let first_turn = vec![message("user", "Hi"), message("assistant", "Hello")];
+        assert_eq!(summarize(first_turn.clone()).await, "Summary 1");
+        // Same older turns: no new request
+        assert_eq!(summarize(first_turn.clone()).await, "Summary 1");
+        assert_eq!(transcripts.lock().unwrap().len(), 1);
+
+        // One more turn aged out: only it is sent, with the previous summary
+        let mut two_turns = first_turn;
+        two_turns.extend([message("user", "Weather?"), message("assistant", "Sunny")]);
+        assert_eq!(summarize(two_turns).await, "Summary 2");
+        let transcripts = transcripts.lock().unwrap();
+        assert!(transcripts[1].contains("Summary 1"));
+        assert!(transcripts[1].contains("User: Weather?"));
+        assert!(!transcripts[1].contains("User: Hi"));
+    }
+
+    #[test]
+    fn test_threshold_and_thinking() {
+        assert_eq!(compaction_threshold(Some(4000)), 3000);
+        assert_eq!(compaction_threshold(None), DEFAULT_CONTEXT_TOKENS * 3 / 4);
+        assert_eq!(estimate_tokens(&[message("user", "12345678")]), 6);
+        assert_eq!(strip_thinking("<think>hmm</think>\nSummary"), "\nSummary");
+        assert_eq!(strip_thinking("Summary"), "Summary");
+    }
+}
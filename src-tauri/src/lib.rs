@@ -14,9 +14,14 @@ pub mod agentic_loop;
 pub mod agentic_state;
 pub mod app_state;
 pub mod auto_discovery;
+pub mod chat_export;
+pub mod chat_images;
 pub mod cli;
 pub mod crash_handler;
 pub mod demo_schema;
+pub mod history_compaction;
+pub mod logging;
+pub mod mcp_health;
 pub mod message_builders;
 pub mod mid_turn_state;
 pub mod model_profiles;
@@ -24,17 +29,22 @@ pub mod paths;
 pub mod process_utils;
 pub mod protocol;
 pub mod python_helpers;
+pub mod reasoning;
 pub mod repetition_detector;
 pub mod settings;
 pub mod settings_state_machine;
 pub mod state_machine;
+pub mod structured_output;
 pub mod system_prompt;
 pub mod tabular_parser;
 pub mod tool_execution;
 pub mod tool_parsing;
 pub mod tool_capability;
 pub mod tool_registry;
+pub mod tool_result_summary;
 pub mod tools;
+pub mod turn_checkpoint;
+pub mod warm_start;
 pub mod commands;
 
 #[cfg(test)]
@@ -50,9 +60,9 @@ use actors::startup_actor::StartupCoordinatorActor;
 use actors::vector_actor::ChatVectorStoreActor;
 use app_state::{
     ActorHandles, CancellationState, EmbeddingModelState, GpuResourceGuard, HeartbeatState,
-    LaunchConfigState, LoggingPersistence, SettingsState,
-    SettingsStateMachineState, SystemPromptEvent, ToolApprovalState,
-    ToolRegistryState, TurnProgress, TurnTrackerState,
+    HistoryCompactedEvent, LaunchConfigState, LoggingPersistence, McpHealthState,
+    ModelMessagesPreview, SettingsState, SettingsStateMachineState, SystemPromptEvent,
+    ToolApprovalState, ToolDisables, ToolRegistryState, TurnProgress, TurnTrackerState,
 };
 use clap::Parser;
 use cli::{apply_cli_overrides, parse_tool_filter, CliArgs};
@@ -62,16 +72,18 @@ use mcp_test_server::{
 use crate::agentic_state::McpToolInfo;
 use crate::protocol::{
     ChatMessage, FoundryMsg, McpHostMsg, ModelFamily, ModelInfo, OpenAITool,
-    RagMsg, ToolFormat, ToolSchema,
+    RagMsg, ToolFormat, ToolSchema, VectorMsg,
 };
-use settings::ToolCallFormatName;
+use settings::{ChatFormatName, ToolCallFormatName};
+use turn_checkpoint::{ChatTurnRequest, TurnCheckpoint};
 use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
 use tauri::{Emitter, Manager, State};
 use tokio::sync::RwLock;
 use tokio::sync::{mpsc, oneshot};
-use tool_capability::ToolCapabilityResolver;
+use tool_capability::{ToolCapabilityResolver, ToolLaunchFilter};
 use tool_registry::{create_shared_registry, SharedToolRegistry};
 use settings_state_machine::{SettingsStateMachine, ChatTurnContext};
 use state_machine::AgenticStateMachine;
@@ -382,6 +394,486 @@ fn tool_schema_to_mcp_tool(schema: &ToolSchema) -> McpTool {
             Some(schema.input_examples.clone())
         },
         allowed_callers: schema.allowed_callers.clone(),
+        streamable: schema.streamable,
+    }
+}
+
+/// Built-ins that search with the embedding model (bare or `builtin::` name)
+fn is_embedding_search_tool(name: &str) -> bool {
+    matches!(
+        name.strip_prefix("builtin::").unwrap_or(name),
+        "tool_search" | "schema_search"
+    )
+}
+
+/// A turn's Always On built-ins. Tool search and schema search need the
+/// embedding model, so without it they're dropped from these and from the
+/// turn's attached tools.
+fn turn_builtin_tools(
+    always_on_builtin_tools: &[String],
+    attached_tools: &mut Vec<String>,
+    embeddings_available: bool,
+) -> Vec<String> {
+    let mut always_on = always_on_builtin_tools.to_vec();
+    if !embeddings_available {
+        always_on.retain(|t| !is_embedding_search_tool(t));
+        attached_tools.retain(|t| !is_embedding_search_tool(t));
+    }
+    always_on
+}
+
+/// Decides which built-in tools a turn offers the model. Per-chat attachments
+/// win; otherwise a built-in must be Always On, enabled and allowed at launch.
+struct BuiltinToolGate<'a> {
+    enabled_tools: &'a [String],
+    always_on_builtin_tools: &'a [String],
+    tool_filter: &'a ToolLaunchFilter,
+    python_execution_enabled: bool,
+    sql_select_enabled: bool,
+    schema_search_enabled: bool,
+    has_deferred_mcp_tools: bool,
+}
+
+impl BuiltinToolGate<'_> {
+    fn allows(&self, name: &str) -> bool {
+        if !self.enabled_tools.is_empty() {
+            return self.enabled_tools.iter().any(|t| t == name);
+        }
+        let enabled = match name {
+            "python_execution" => self.python_execution_enabled,
+            // tool_search only if there are deferred tools to discover
+            "tool_search" => self.has_deferred_mcp_tools,
+            "sql_select" => self.sql_select_enabled,
+            "schema_search" => self.schema_search_enabled,
+            _ => true,
+        };
+        enabled
+            && self.always_on_builtin_tools.iter().any(|t| t == name)
+            && self.tool_filter.builtin_allowed(name)
+    }
+}
+
+/// Add visible tools to a legacy/native tool calling payload: built-ins the
+/// gate allows, and MCP tools (only the attached ones when tools are attached),
+/// with the user's description overrides applied
+fn extend_openai_tools(
+    tools_list: &mut Vec<OpenAITool>,
+    visible_tools: Vec<(String, ToolSchema)>,
+    gate: &BuiltinToolGate,
+    description_overrides: &HashMap<String, String>,
+) {
+    let mut seen: HashSet<String> = tools_list.iter().map(|t| t.function.name.clone()).collect();
+    for (server_id, mut schema) in visible_tools {
+        let allowed = if server_id == "builtin" {
+            gate.allows(&schema.name)
+        } else {
+            gate.enabled_tools.is_empty()
+                || gate.enabled_tools.contains(&format!("{}::{}", server_id, schema.name))
+        };
+        if !allowed {
+            continue;
+        }
+        tool_registry::apply_description_override(&mut schema, &server_id, description_overrides);
+        // MCP tools get server prefix for routing (sanitized)
+        let openai_tool = if server_id == "builtin" {
+            OpenAITool::from_tool_schema(&schema)
+        } else {
+            OpenAITool::from_mcp_schema(&server_id, &schema)
+        };
+        if seen.insert(openai_tool.function.name.clone()) {
+            tools_list.push(openai_tool);
+        }
+    }
+}
+
+/// Register MCP tools in the tool registry with their python module names, so
+/// they're available for python_execution and tool_search
+fn register_turn_mcp_tools(
+    registry: &mut tool_registry::ToolRegistry,
+    tool_descriptions: &[(String, Vec<McpTool>)],
+    server_configs: &[settings::McpServerConfig],
+) {
+    for (server_id, tools) in tool_descriptions {
+        // Get the server config to extract defer_tools and python_name
+        let config = server_configs.iter().find(|c| c.id == *server_id);
+        let defer = config.map(|c| c.defer_tools).unwrap_or(false);
+        let python_name = config
+            .map(|c| c.get_python_name())
+            .unwrap_or_else(|| settings::to_python_identifier(server_id));
+
+        let mode = if defer { "DEFERRED" } else { "ACTIVE" };
+        println!(
+            "[Chat] Registering {} tools from {} [{}] (python_module={})",
+            tools.len(),
+            server_id,
+            mode,
+            python_name
+        );
+        registry.register_mcp_tools(server_id, &python_name, tools, defer);
+    }
+}
+
+/// Scope materialized tools for a new chat message. Strict scope clears them, so a
+/// turn never sees tools found by an earlier (possibly crashed) turn; otherwise only
+/// materializations older than `ttl_secs` are dropped (0 = never expire).
+fn begin_turn_tool_scope(registry: &mut tool_registry::ToolRegistry, strict: bool, ttl_secs: u64) {
+    if strict {
+        let cleared = registry.materialized_tool_keys().len();
+        registry.clear_materialized();
+        if cleared > 0 {
+            println!("[Chat] Cleared {} materialized tools from a previous turn", cleared);
+        }
+    } else if ttl_secs > 0 {
+        let expired = registry.expire_materialized(std::time::Duration::from_secs(ttl_secs));
+        if expired > 0 {
+            println!("[Chat] Expired {} materialized tools older than {}s", expired, ttl_secs);
+        }
+    }
+}
+
+/// Messages for a new turn: the system prompt (if any), the existing history
+/// without its system messages (to avoid duplicates), then the user message
+fn build_turn_messages(
+    system_prompt: &str,
+    history: &[ChatMessage],
+    message: &str,
+    images: Vec<String>,
+) -> Vec<ChatMessage> {
+    let mut messages = Vec::new();
+    if !system_prompt.is_empty() {
+        messages.push(ChatMessage {
+            role: "system".to_string(),
+            content: system_prompt.to_string(),
+            ..Default::default()
+        });
+    }
+    messages.extend(history.iter().filter(|msg| msg.role != "system").cloned());
+    messages.push(ChatMessage {
+        role: "user".to_string(),
+        content: message.to_string(),
+        images,
+        ..Default::default()
+    });
+    messages
+}
+
+/// Model info for the model a turn runs on. The frontend picks the model, so
+/// it's looked up in the service's model list; the selected model is the
+/// fallback when it isn't listed.
+async fn lookup_model_info(foundry_tx: &mpsc::Sender<FoundryMsg>, model: &str) -> Option<ModelInfo> {
+    let (tx, rx) = oneshot::channel();
+    if foundry_tx
+        .send(FoundryMsg::GetModelInfo { respond_to: tx })
+        .await
+        .is_ok()
+    {
+        if let Some(info) = rx
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .find(|m| m.id.eq_ignore_ascii_case(model))
+        {
+            return Some(info);
+        }
+    }
+    let (tx, rx) = oneshot::channel();
+    if foundry_tx
+        .send(FoundryMsg::GetCurrentModel { respond_to: tx })
+        .await
+        .is_ok()
+    {
+        rx.await.ok().flatten()
+    } else {
+        None
+    }
+}
+
+/// How a turn offers tools to its model: the MCP tools it can reach and the
+/// tool call formats resolved for the model. Built the same way for `chat`
+/// and `preview_model_messages`.
+struct TurnToolSetup {
+    model_info: Option<ModelInfo>,
+    format_config: settings::ToolCallFormatConfig,
+    filtered_tool_descriptions: Vec<(String, Vec<McpTool>)>,
+    has_mcp_tools: bool,
+    has_deferred_mcp_tools: bool,
+    tool_search_enabled: bool,
+    primary_format_for_prompt: ToolCallFormatName,
+    python_tool_mode: bool,
+    legacy_tool_calls_enabled: bool,
+    legacy_tool_search_enabled: bool,
+}
+
+impl TurnToolSetup {
+    /// The legacy/native tool payload's fixed built-ins (tool_search, extract),
+    /// or None when tools are only offered through code mode
+    fn base_openai_tools(
+        &self,
+        tool_filter: &ToolLaunchFilter,
+        description_overrides: &HashMap<String, String>,
+    ) -> Option<Vec<OpenAITool>> {
+        if !self.legacy_tool_calls_enabled {
+            return None;
+        }
+        let mut list = Vec::new();
+        if self.legacy_tool_search_enabled {
+            let mut tool_search_tool = tool_registry::tool_search_tool();
+            tool_registry::apply_description_override(
+                &mut tool_search_tool,
+                "builtin",
+                description_overrides,
+            );
+            list.push(OpenAITool::from_tool_schema(&tool_search_tool));
+            println!("[Chat] Added tool_search built-in tool (legacy mode)");
+        }
+        if self.has_mcp_tools && tool_filter.builtin_allowed(tools::extract::EXTRACT_TOOL) {
+            let mut extract_tool = tools::extract::extract_tool_schema();
+            tool_registry::apply_description_override(
+                &mut extract_tool,
+                "builtin",
+                description_overrides,
+            );
+            list.push(OpenAITool::from_tool_schema(&extract_tool));
+        }
+        Some(list)
+    }
+}
+
+/// Resolve a turn's tools for `model`: apply the global tool_search flag to
+/// the servers' defer settings, fetch and filter the MCP tools, pick the
+/// model's tool format and the tool call format to prompt with
+#[allow(clippy::too_many_arguments)]
+async fn resolve_turn_tools(
+    handles: &ActorHandles,
+    settings: &settings::AppSettings,
+    tool_filter: &ToolLaunchFilter,
+    server_configs: &mut [settings::McpServerConfig],
+    always_on_builtin_tools: &[String],
+    model: &str,
+    python_execution_enabled: bool,
+) -> Result<TurnToolSetup, String> {
+    let model_info = lookup_model_info(&handles.foundry_tx, model).await;
+    let profile = model_profiles::resolve_profile(model);
+    let mut format_config = settings.tool_call_formats.clone();
+    format_config.normalize();
+
+    // Anthropic content blocks only work with backends that speak that format,
+    // selected per model with the anthropic_messages chat format
+    let anthropic_chat_format = settings::resolve_chat_format(
+        &settings.chat_format_overrides,
+        settings.chat_format_default,
+        model,
+    ) == ChatFormatName::AnthropicMessages;
+    let model_tool_format = if anthropic_chat_format {
+        ToolFormat::Anthropic
+    } else {
+        model_info
+            .as_ref()
+            .map(|m| m.tool_format)
+            .unwrap_or(profile.tool_call_format)
+    };
+    if model_tool_format == ToolFormat::Anthropic {
+        if format_config.native_enabled() {
+            println!("[chat] Model {} uses Anthropic content blocks for native tool calls", model);
+        }
+        format_config.use_anthropic_blocks();
+    } else if format_config.is_enabled(ToolCallFormatName::Anthropic) {
+        println!("[chat] Model {} does not use Anthropic content blocks, disabling that format", model);
+        format_config.disable(ToolCallFormatName::Anthropic);
+    }
+
+    // Native tool calling is only available if: format is enabled AND model supports it
+    let model_supports_native_tools = model_info.as_ref().is_some_and(|m| m.tool_calling);
+    let native_tool_calling_enabled =
+        format_config.native_enabled() && model_supports_native_tools;
+    println!(
+        "[chat] Model capabilities: model={}, native_enabled_in_config={}, model_supports_native={}, using_native={}",
+        model_info.as_ref().map(|m| m.id.as_str()).unwrap_or("unknown"),
+        format_config.native_enabled(),
+        model_supports_native_tools,
+        native_tool_calling_enabled
+    );
+
+    // Apply global tool_search flag to server defer settings (only if tool_search is actually available)
+    let tool_search_allowed = tool_filter.builtin_allowed("tool_search");
+    let tool_search_enabled = always_on_builtin_tools.iter().any(|t| t == "tool_search");
+    if tool_search_enabled && tool_search_allowed {
+        for config in server_configs.iter_mut() {
+            config.defer_tools = true;
+        }
+    } else if !tool_search_enabled {
+        // If tool search is explicitly disabled globally, we MUST surface regular tools or they'll be unreachable.
+        // HOWEVER, database sources should stay deferred because they are only meant for sql_select context injection.
+        for config in server_configs.iter_mut() {
+            if !config.is_database_source {
+                config.defer_tools = false;
+            }
+        }
+    }
+    // Otherwise, we respect the per-server config. This prevents bloating the prompt with 
+    // database tools that are handled via sql_select.
+
+    // Get tool descriptions from MCP Host Actor
+    let (tools_tx, tools_rx) = oneshot::channel();
+    handles
+        .mcp_host_tx
+        .send(McpHostMsg::GetAllToolDescriptions {
+            respond_to: tools_tx,
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    let tool_descriptions = tools_rx
+        .await
+        .map_err(|_| "MCP Host actor died".to_string())?;
+
+    // Apply launch-time filters and check enabled status
+    let mut filtered_tool_descriptions: Vec<(String, Vec<McpTool>)> = tool_descriptions
+        .into_iter()
+        .filter_map(|(server_id, tools)| {
+            // Check if server is enabled in settings and NOT a database source
+            // (Database tools are handled separately via sql_select/schema_search)
+            let is_enabled = server_configs
+                .iter()
+                .any(|c| c.id == server_id && c.enabled && !c.is_database_source);
+
+            if !is_enabled {
+                return None;
+            }
+
+            if !tool_filter.server_allowed(&server_id) {
+                return None;
+            }
+
+            let filtered_tools: Vec<McpTool> = tools
+                .into_iter()
+                .filter(|t| tool_filter.tool_allowed(&server_id, &t.name))
+                .collect();
+
+            if filtered_tools.is_empty() {
+                None
+            } else {
+                Some((server_id, filtered_tools))
+            }
+        })
+        .collect();
+    tool_registry::apply_mcp_description_overrides(
+        &mut filtered_tool_descriptions,
+        &settings.tool_description_overrides,
+    );
+
+    // Check if there are any MCP tools available
+    let has_mcp_tools = filtered_tool_descriptions
+        .iter()
+        .any(|(_, tools)| !tools.is_empty());
+
+    // Check if there are any deferred MCP tools (for tool_search discovery)
+    let has_deferred_mcp_tools = filtered_tool_descriptions
+        .iter()
+        .any(|(server_id, tools)| {
+            !tools.is_empty()
+                && server_configs
+                    .iter()
+                    .find(|c| c.id == *server_id)
+                    .map(|c| c.defer_tools)
+                    .unwrap_or(true)
+        });
+
+    let python_tool_calling_enabled = settings.python_tool_calling_enabled;
+    let code_mode_possible = format_config.is_enabled(ToolCallFormatName::CodeMode)
+        && python_execution_enabled
+        && python_tool_calling_enabled
+        && tool_filter.builtin_allowed("python_execution");
+    // Primary affects prompting only; execution should honor any enabled format.
+    let primary_format_for_prompt =
+        format_config.resolve_primary_for_prompt(code_mode_possible, native_tool_calling_enabled);
+    // python_tool_mode controls DETECTION of Python code in responses.
+    // This should be enabled when python_execution is available, regardless of prompt format.
+    // Even if we prompt the model with Native/Hermes format, it may still output Python code blocks.
+    // When it does, we should detect and execute them if python_execution is enabled.
+    let python_tool_mode = python_execution_enabled
+        && python_tool_calling_enabled
+        && tool_filter.builtin_allowed("python_execution");
+    let legacy_tool_calls_enabled =
+        format_config.any_non_code() && primary_format_for_prompt != ToolCallFormatName::CodeMode;
+    // tool_search is only offered when explicitly enabled in settings AND there are deferred tools
+    let legacy_tool_search_enabled = legacy_tool_calls_enabled
+        && tool_search_enabled
+        && has_deferred_mcp_tools
+        && tool_search_allowed;
+
+    println!(
+        "[chat] tool_call_formats: config_primary={:?}, resolved_primary={:?}, enabled={:?}, native_available={}, python_execution_enabled={}, python_tool_calling_enabled={}, python_tool_mode={}, code_mode_possible={}",
+        format_config.primary,
+        primary_format_for_prompt,
+        format_config.enabled,
+        native_tool_calling_enabled,
+        python_execution_enabled,
+        python_tool_calling_enabled,
+        python_tool_mode,
+        code_mode_possible
+    );
+
+    Ok(TurnToolSetup {
+        model_info,
+        format_config,
+        filtered_tool_descriptions,
+        has_mcp_tools,
+        has_deferred_mcp_tools,
+        tool_search_enabled,
+        primary_format_for_prompt,
+        python_tool_mode,
+        legacy_tool_calls_enabled,
+        legacy_tool_search_enabled,
+    })
+}
+
+/// Fetch and parse a chat's stored messages; None when the chat has none
+async fn fetch_stored_messages(
+    vector_tx: &mpsc::Sender<VectorMsg>,
+    chat_id: &str,
+) -> Result<Option<Vec<ChatMessage>>, String> {
+    let (tx, rx) = oneshot::channel();
+    vector_tx
+        .send(VectorMsg::FetchChatMessages {
+            id: chat_id.to_string(),
+            respond_to: tx,
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    rx.await
+        .map_err(|_| "Vector actor died".to_string())?
+        .filter(|json| !json.trim().is_empty())
+        .map(|json| {
+            serde_json::from_str(&json)
+                .map_err(|e| format!("Failed to parse messages for chat {}: {}", chat_id, e))
+        })
+        .transpose()
+}
+
+/// History to store with the turn. The frontend only sends the visible
+/// messages, so the stored record (with its tool traffic) is used instead
+/// when its visible messages are the ones sent.
+fn history_to_store(stored: Option<Vec<ChatMessage>>, history: &[ChatMessage]) -> Vec<ChatMessage> {
+    if history.iter().any(|m| m.intermediate) {
+        return history.to_vec();
+    }
+    match stored {
+        Some(stored) => {
+            let visible: Vec<&ChatMessage> = stored.iter().filter(|m| !m.intermediate).collect();
+            // Assistant text may differ (the frontend marks interrupted
+            // answers), so only roles and user messages are compared
+            let matches = visible.len() == history.len()
+                && visible.iter().zip(history).all(|(stored, sent)| {
+                    stored.role == sent.role
+                        && (stored.role != "user" || stored.content == sent.content)
+                });
+            if matches {
+                stored
+            } else {
+                history.to_vec()
+            }
+        }
+        None => history.to_vec(),
     }
 }
 
@@ -397,6 +889,188 @@ async fn chat(
     attached_tables: Vec<crate::settings_state_machine::AttachedTableInfo>,
     attached_tools: Vec<String>,
     attached_tabular_files: Vec<String>, // Paths to CSV/TSV/XLS/XLSX files for Python analysis
+    images: Option<Vec<String>>, // Image attachments for vision models (data URLs, base64 or paths)
+    response_schema: Option<serde_json::Value>, // JSON schema the final answer must follow
+    temperature: Option<f32>, // Sampling temperature override (models that support it)
+    seed: Option<u64>,        // Sampling seed for reproducible output
+    force_state: Option<String>, // Pin the turn-start state (debugging)
+    skip_auto_discovery: Option<bool>, // Skip/force auto-discovery (None = prompt heuristic)
+    handles: State<'_, ActorHandles>,
+    settings_state: State<'_, SettingsState>,
+    settings_sm_state: State<'_, SettingsStateMachineState>,
+    approval_state: State<'_, ToolApprovalState>,
+    tool_registry_state: State<'_, ToolRegistryState>,
+    embedding_state: State<'_, EmbeddingModelState>,
+    launch_config: State<'_, LaunchConfigState>,
+    cancellation_state: State<'_, CancellationState>,
+    turn_tracker: State<'_, TurnTrackerState>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let request = ChatTurnRequest {
+        chat_id,
+        title,
+        message,
+        history,
+        reasoning_effort,
+        model,
+        attached_files,
+        attached_tables,
+        attached_tools,
+        attached_tabular_files,
+        images: images.unwrap_or_default(),
+        response_schema,
+        temperature,
+        seed,
+        force_state,
+        skip_auto_discovery,
+    };
+    start_chat_turn(
+        request,
+        None,
+        handles,
+        settings_state,
+        settings_sm_state,
+        approval_state,
+        tool_registry_state,
+        embedding_state,
+        launch_config,
+        cancellation_state,
+        turn_tracker,
+        app_handle,
+    )
+    .await
+}
+
+/// Resume the last interrupted turn from its checkpoint
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn resume_turn(
+    handles: State<'_, ActorHandles>,
+    settings_state: State<'_, SettingsState>,
+    settings_sm_state: State<'_, SettingsStateMachineState>,
+    approval_state: State<'_, ToolApprovalState>,
+    tool_registry_state: State<'_, ToolRegistryState>,
+    embedding_state: State<'_, EmbeddingModelState>,
+    launch_config: State<'_, LaunchConfigState>,
+    cancellation_state: State<'_, CancellationState>,
+    turn_tracker: State<'_, TurnTrackerState>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let checkpoint = {
+        let progress = turn_tracker.progress.read().await;
+        if progress.active {
+            return Err("A turn is already in progress".to_string());
+        }
+        progress.checkpoint.clone()
+    }
+    .ok_or_else(|| "No interrupted turn to resume".to_string())?;
+
+    println!(
+        "[resume_turn] Resuming chat {:?} at iteration {} (state={})",
+        checkpoint.request.chat_id,
+        checkpoint.iteration,
+        checkpoint.state.name()
+    );
+    start_chat_turn(
+        checkpoint.request.clone(),
+        Some(checkpoint),
+        handles,
+        settings_state,
+        settings_sm_state,
+        approval_state,
+        tool_registry_state,
+        embedding_state,
+        launch_config,
+        cancellation_state,
+        turn_tracker,
+        app_handle,
+    )
+    .await
+}
+
+/// Re-run a chat's last user message against another model. The answer goes
+/// to a new chat (a fork) so the original stays as it was; returns its id.
+/// The frontend can pick the fork's id to follow it while it streams.
+/// System prompt and tool discovery are rebuilt for the new model.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn rerun_turn(
+    chat_id: String,
+    model: String,
+    reasoning_effort: Option<String>,
+    new_chat_id: Option<String>,
+    handles: State<'_, ActorHandles>,
+    settings_state: State<'_, SettingsState>,
+    settings_sm_state: State<'_, SettingsStateMachineState>,
+    approval_state: State<'_, ToolApprovalState>,
+    tool_registry_state: State<'_, ToolRegistryState>,
+    embedding_state: State<'_, EmbeddingModelState>,
+    launch_config: State<'_, LaunchConfigState>,
+    cancellation_state: State<'_, CancellationState>,
+    turn_tracker: State<'_, TurnTrackerState>,
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    if turn_tracker.progress.read().await.active {
+        return Err("A turn is already in progress".to_string());
+    }
+
+    let messages = fetch_stored_messages(&handles.vector_tx, &chat_id)
+        .await?
+        .ok_or_else(|| format!("Chat {} has no stored messages", chat_id))?;
+
+    let (tx, rx) = oneshot::channel();
+    handles
+        .vector_tx
+        .send(VectorMsg::FetchAllChats { respond_to: tx })
+        .await
+        .map_err(|e| e.to_string())?;
+    let original_title = rx
+        .await
+        .map_err(|_| "Vector actor died".to_string())?
+        .into_iter()
+        .find(|summary| summary.id == chat_id)
+        .map(|summary| summary.title);
+
+    let mut request = ChatTurnRequest::rerun_of(
+        &messages,
+        &model,
+        reasoning_effort.as_deref().unwrap_or("low"),
+    )?;
+    request.chat_id = Some(new_chat_id.unwrap_or_else(|| Uuid::new_v4().to_string()));
+    request.title = original_title.map(|title| format!("{} ({})", title, model));
+
+    println!(
+        "[rerun_turn] Re-running last turn of chat {} with {} as chat {:?} ({} history messages)",
+        chat_id,
+        model,
+        request.chat_id,
+        request.history.len()
+    );
+    start_chat_turn(
+        request,
+        None,
+        handles,
+        settings_state,
+        settings_sm_state,
+        approval_state,
+        tool_registry_state,
+        embedding_state,
+        launch_config,
+        cancellation_state,
+        turn_tracker,
+        app_handle,
+    )
+    .await
+}
+
+/// Set up and spawn the agentic loop for a chat turn.
+///
+/// With a checkpoint, the turn continues from the checkpointed state, history
+/// and iteration instead of starting from the new user message.
+#[allow(clippy::too_many_arguments)]
+async fn start_chat_turn(
+    request: ChatTurnRequest,
+    resume: Option<TurnCheckpoint>,
     handles: State<'_, ActorHandles>,
     settings_state: State<'_, SettingsState>,
     settings_sm_state: State<'_, SettingsStateMachineState>,
@@ -409,9 +1083,71 @@ async fn chat(
     app_handle: tauri::AppHandle,
 ) -> Result<String, String> {
     use std::io::Write;
+    let mut turn_request = ChatTurnRequest {
+        history: Vec::new(),
+        images: Vec::new(),
+        ..request.clone()
+    };
+    let ChatTurnRequest {
+        chat_id,
+        title,
+        message,
+        history,
+        reasoning_effort,
+        model,
+        attached_files,
+        attached_tables,
+        mut attached_tools,
+        attached_tabular_files,
+        images,
+        response_schema,
+        temperature,
+        seed,
+        force_state,
+        skip_auto_discovery,
+    } = request;
     let chat_id = chat_id.unwrap_or_else(|| Uuid::new_v4().to_string());
     let chat_id_return = chat_id.clone();
     let title = title.unwrap_or_else(|| message.chars().take(50).collect::<String>());
+    turn_request.chat_id = Some(chat_id.clone());
+    turn_request.title = Some(title.clone());
+
+    // Normalize image attachments up front so a bad one fails before the turn starts
+    let mut images = images
+        .iter()
+        .map(|image| chat_images::image_to_data_url(image))
+        .collect::<Result<Vec<String>, String>>()?;
+
+    // Reject a bad force_state before the turn sets anything up
+    if let Some(requested) = &force_state {
+        let mut probe_state_machine = {
+            let sm_guard = settings_sm_state.machine.read().await;
+            AgenticStateMachine::new_from_settings_sm(
+                &sm_guard,
+                agentic_state::PromptContext {
+                    attached_tables: attached_tables.clone(),
+                    attached_tools: attached_tools.clone(),
+                    ..Default::default()
+                },
+            )
+        };
+        {
+            let settings_guard = settings_state.settings.read().await;
+            probe_state_machine.compute_turn_config(&settings_guard, &launch_config.tool_filter);
+        }
+        probe_state_machine.force_initial_state(requested)?;
+    }
+
+    // A new message (sent or re-run) scopes materialized tools; a resumed turn
+    // keeps the tools it had found
+    if resume.is_none() {
+        let (strict, ttl_secs) = {
+            let settings = settings_state.settings.read().await;
+            (settings.strict_turn_tool_scope, settings.materialized_tools_ttl_secs)
+        };
+        let mut registry = tool_registry_state.registry.write().await;
+        begin_turn_tool_scope(&mut registry, strict, ttl_secs);
+    }
 
     // Log incoming chat request
     let msg_preview: String = message.chars().take(128).collect();
@@ -448,17 +1184,70 @@ async fn chat(
 
     let tool_filter = launch_config.tool_filter.clone();
 
+    // Tool search, schema search and RAG need the embedding model; without it
+    // they're left out of the turn and plain chat carries on
+    let embedding_status = embedding_state.status().await;
+    let embeddings_available = embedding_status.available;
+    if let Some(error) = &embedding_status.error {
+        if !embedding_state.unavailable_notice_sent.swap(true, Ordering::Relaxed) {
+            println!(
+                "[Chat] Embedding model unavailable ({}); tool search, schema search and RAG are disabled",
+                error
+            );
+            let _ = app_handle.emit(
+                "chat-warning",
+                serde_json::json!({
+                    "message": format!(
+                        "The embedding model failed to load ({}); tool search, schema search and document search are disabled",
+                        error
+                    )
+                }),
+            );
+        }
+    } else if embedding_status.loading {
+        println!("[Chat] Embedding model still loading; skipping search features this turn");
+    }
+
     // Get server configs from settings
     let settings = settings_state.settings.read().await;
     let configured_system_prompt = settings.system_prompt.clone();
+    let system_prompt_prefix = settings.system_prompt_prefix.clone();
+    let system_prompt_suffix = settings.system_prompt_suffix.clone();
     let mut server_configs = settings.get_all_mcp_configs();
+    settings.apply_safe_mode_approvals(&mut server_configs);
     let tool_search_max_results = settings.tool_search_max_results.max(1);
+    let tool_search_min_relevance = settings.tool_search_min_relevance;
+    let auto_discovery_min_prompt_chars = settings.auto_discovery_min_prompt_chars;
+    let max_tool_calls_per_turn = settings.max_tool_calls_per_turn;
+    let max_tool_iterations = settings
+        .max_tool_iterations
+        .clamp(settings::MIN_TOOL_ITERATIONS, settings::MAX_TOOL_ITERATIONS);
+    let parallel_tool_calls = settings.parallel_tool_calls;
+    let batch_approval_mode = settings.batch_approval_mode;
+    let tool_call_ids_in_text = settings.tool_call_ids_in_text;
+    let strict_turn_tool_scope = settings.strict_turn_tool_scope;
+    let mcp_tool_timeout_secs = settings.mcp_tool_timeout_secs;
+    let tool_heartbeat_interval_ms = settings.tool_heartbeat_interval_ms;
+    let chat_autosave_interval_secs = settings.chat_autosave_interval_secs;
+    let use_format_stop_sequences = settings.use_format_stop_sequences;
+    let max_tool_result_chars = settings.max_tool_result_chars;
+    let summarize_large_tool_results = settings.summarize_large_tool_results;
+    let tool_result_summary_threshold_chars = settings.tool_result_summary_threshold_chars;
+    let max_system_prompt_chars = settings.max_system_prompt_chars;
+    let history_compaction_enabled = settings.history_compaction_enabled;
+    let history_compaction_keep_turns = settings.history_compaction_keep_turns;
+    let foundry_chat_retries = settings.foundry_chat_retries;
+    let first_token_timeout_secs = settings.first_token_timeout_secs;
     let tool_use_examples_enabled = settings.tool_use_examples_enabled;
     let tool_use_examples_max = settings.tool_use_examples_max;
     let database_toolbox_config = settings.database_toolbox.clone();
     
     // Always-on configuration
-    let always_on_builtin_tools = settings.always_on_builtin_tools.clone();
+    let always_on_builtin_tools = turn_builtin_tools(
+        &settings.always_on_builtin_tools,
+        &mut attached_tools,
+        embeddings_available,
+    );
     let always_on_mcp_tools = settings.always_on_mcp_tools.clone();
     let always_on_tables = settings.always_on_tables.clone();
     let always_on_rag_paths = settings.always_on_rag_paths.clone();
@@ -476,11 +1265,19 @@ async fn chat(
 
     let chat_format_default = settings.chat_format_default;
     let chat_format_overrides = settings.chat_format_overrides.clone();
+    let reasoning_effort_overrides = settings.reasoning_effort_overrides.clone();
     let tool_system_prompts = settings.tool_system_prompts.clone();
-    let python_tool_calling_enabled = settings.python_tool_calling_enabled;
-    let internal_schema_search = settings.should_run_internal_schema_search();
-    let mut format_config = settings.tool_call_formats.clone();
-    format_config.normalize();
+    let tool_description_overrides = settings.tool_description_overrides.clone();
+    let python_execution_timeout_ms = settings.python_execution_timeout_ms;
+    let python_result_format = settings.python_result_format;
+    let sql_result_format = settings.sql_result_format;
+    let reasoning_events_enabled = settings.reasoning_events_enabled;
+    let python_multi_block = settings.python_multi_block;
+    let python_session_enabled = settings.python_session_persistence_enabled;
+    let python_allowed_modules = settings.resolved_python_allowed_modules();
+    let python_memory_limit_bytes = settings.python_memory_limit_bytes();
+    let internal_schema_search =
+        settings.should_run_internal_schema_search() && embeddings_available;
     
     // Derived flags for legacy compatibility within this function
     // A tool is active if it's Always On OR explicitly attached for this chat
@@ -489,7 +1286,9 @@ async fn chat(
             || attached_tools.contains(&format!("builtin::{}", name)) 
             || attached_tools.contains(&name.to_string())
     };
-    let python_execution_enabled = is_builtin_active("python_execution");
+    // Safe mode turns off code execution even when python_execution is attached
+    let python_execution_enabled = is_builtin_active("python_execution")
+        && !settings.safe_mode_blocks_builtin("python_execution");
     let _tool_search_enabled = is_builtin_active("tool_search");
     let schema_search_enabled = is_builtin_active("schema_search");
     let sql_select_enabled = is_builtin_active("sql_select");
@@ -676,6 +1475,11 @@ async fn chat(
             finished: false,
             had_tool_calls: false,
             timestamp_ms: now_ms,
+            metrics: None,
+            resumable: false,
+            checkpoint: None,
+            iteration_history: Vec::new(),
+            iteration_visible_text: String::new(),
         };
     }
 
@@ -698,189 +1502,102 @@ async fn chat(
         configs: server_configs.clone(),
         respond_to: sync_tx,
     }).await {
-        println!("[Chat] Warning: Failed to send sync request to MCP Host: {}", e);
-    } else {
-        let _ = sync_rx.await;
-    }
-
-    // Look up model info for the frontend-provided model to check capabilities
-    // Frontend is the source of truth for model selection
-    let (current_model_info, _model_supports_native_tools) = {
-        let (tx, rx) = oneshot::channel();
-        if handles
-            .foundry_tx
-            .send(FoundryMsg::GetCurrentModel { respond_to: tx })
-            .await
-            .is_ok()
-        {
-            (rx.await.ok().flatten(), true) // Placeholder for now, real check below
-        } else {
-            (None, false)
-        }
-    };
-
-    // Resolve model profile to get tool call format preference
-    let profile = model_profiles::resolve_profile(&model);
-    let resolved_model_tool_format = Some(profile.tool_call_format);
-
-    // Native tool calling is only available if: format is enabled AND model supports it
-    let model_supports_native_tools = current_model_info.as_ref().map(|m| m.tool_calling).unwrap_or(false);
-    let native_tool_calling_enabled =
-        format_config.native_enabled() && model_supports_native_tools;
-
-    // Log model capabilities for debugging
-    let model_id = current_model_info
-        .as_ref()
-        .map(|m| m.id.as_str())
-        .unwrap_or("unknown");
-    println!(
-        "[chat] Model capabilities: model={}, native_enabled_in_config={}, model_supports_native={}, using_native={}",
-        model_id,
-        format_config.native_enabled(),
-        model_supports_native_tools,
-        native_tool_calling_enabled
-    );
-
-    // Ensure registry reflects Always On built-ins before building prompts
-    sync_registry_database_tools(
-        &tool_registry_state.registry,
-        &always_on_builtin_tools,
-    )
-    .await;
-
-    // Apply global tool_search flag to server defer settings (only if tool_search is actually available)
-    let tool_search_allowed = tool_filter.builtin_allowed("tool_search");
-    let tool_search_enabled = always_on_builtin_tools.contains(&"tool_search".to_string());
-    if tool_search_enabled && tool_search_allowed {
-        for config in &mut server_configs {
-            config.defer_tools = true;
-        }
-    } else if !tool_search_enabled {
-        // If tool search is explicitly disabled globally, we MUST surface regular tools or they'll be unreachable.
-        // HOWEVER, database sources should stay deferred because they are only meant for sql_select context injection.
-        for config in &mut server_configs {
-            if !config.is_database_source {
-                config.defer_tools = false;
-            }
-        }
-    }
-    // Otherwise, we respect the per-server config. This prevents bloating the prompt with 
-    // database tools that are handled via sql_select.
-
-    // Get tool descriptions from MCP Host Actor
-    let (tools_tx, tools_rx) = oneshot::channel();
-    handles
-        .mcp_host_tx
-        .send(McpHostMsg::GetAllToolDescriptions {
-            respond_to: tools_tx,
-        })
-        .await
-        .map_err(|e| e.to_string())?;
-    let tool_descriptions = tools_rx
-        .await
-        .map_err(|_| "MCP Host actor died".to_string())?;
-
-    // Apply launch-time filters and check enabled status
-    let filtered_tool_descriptions: Vec<(String, Vec<McpTool>)> = tool_descriptions
-        .into_iter()
-        .filter_map(|(server_id, tools)| {
-            // Check if server is enabled in settings and NOT a database source
-            // (Database tools are handled separately via sql_select/schema_search)
-            let is_enabled = server_configs
-                .iter()
-                .any(|c| c.id == server_id && c.enabled && !c.is_database_source);
-
-            if !is_enabled {
-                return None;
-            }
-
-            if !tool_filter.server_allowed(&server_id) {
-                return None;
-            }
-
-            let filtered_tools: Vec<McpTool> = tools
-                .into_iter()
-                .filter(|t| tool_filter.tool_allowed(&server_id, &t.name))
-                .collect();
-
-            if filtered_tools.is_empty() {
-                None
-            } else {
-                Some((server_id, filtered_tools))
-            }
-        })
-        .collect();
-
-    // Check if there are any MCP tools available
-    let has_mcp_tools = filtered_tool_descriptions
-        .iter()
-        .any(|(_, tools)| !tools.is_empty());
-
-    // Check if there are any deferred MCP tools (for tool_search discovery)
-    let has_deferred_mcp_tools = filtered_tool_descriptions
-        .iter()
-        .any(|(server_id, tools)| {
-            !tools.is_empty()
-                && server_configs
-                    .iter()
-                    .find(|c| c.id == *server_id)
-                    .map(|c| c.defer_tools)
-                    .unwrap_or(true)
-        });
+        println!("[Chat] Warning: Failed to send sync request to MCP Host: {}", e);
+    } else {
+        let _ = sync_rx.await;
+    }
 
-    // Build the tools list:
-    // 1. Include python_execution if enabled in settings
-    // 2. Include tool_search when MCP servers with deferred tools are available
-    // 3. Include all MCP tools
-    let code_mode_possible = format_config.is_enabled(ToolCallFormatName::CodeMode)
-        && python_execution_enabled
-        && python_tool_calling_enabled
-        && tool_filter.builtin_allowed("python_execution");
-    // Primary affects prompting only; execution should honor any enabled format.
-    // Native is available only if both enabled in config AND model supports it.
-    let native_available = native_tool_calling_enabled;
-    let primary_format_for_prompt =
-        format_config.resolve_primary_for_prompt(code_mode_possible, native_available);
-    // python_tool_mode controls DETECTION of Python code in responses.
-    // This should be enabled when python_execution is available, regardless of prompt format.
-    // Even if we prompt the model with Native/Hermes format, it may still output Python code blocks.
-    // When it does, we should detect and execute them if python_execution is enabled.
-    let python_tool_mode = python_execution_enabled
-        && python_tool_calling_enabled
-        && tool_filter.builtin_allowed("python_execution");
-    // tool_search is only offered when explicitly enabled in settings AND there are deferred tools
-    let allow_tool_search_for_python =
-        python_tool_mode && tool_search_enabled && has_deferred_mcp_tools && tool_filter.builtin_allowed("tool_search");
-    let non_code_formats_enabled = format_config.any_non_code();
-    let legacy_tool_calls_enabled =
-        non_code_formats_enabled && primary_format_for_prompt != ToolCallFormatName::CodeMode;
-    let legacy_tool_search_enabled =
-        legacy_tool_calls_enabled && tool_search_enabled && has_deferred_mcp_tools && tool_filter.builtin_allowed("tool_search");
+    // Ensure registry reflects Always On built-ins before building prompts
+    sync_registry_database_tools(
+        &tool_registry_state.registry,
+        &always_on_builtin_tools,
+    )
+    .await;
 
-    println!(
-        "[chat] tool_call_formats: config_primary={:?}, resolved_primary={:?}, enabled={:?}, native_available={}, python_execution_enabled={}, python_tool_calling_enabled={}, python_tool_mode={}, code_mode_possible={}",
-        format_config.primary,
-        primary_format_for_prompt,
-        format_config.enabled,
-        native_available,
+    // Resolve tools and tool call formats for the frontend-provided model
+    // (frontend is the source of truth for model selection)
+    let settings_snapshot = settings_state.settings.read().await.clone();
+    let turn_tools = resolve_turn_tools(
+        &handles,
+        &settings_snapshot,
+        &tool_filter,
+        &mut server_configs,
+        &always_on_builtin_tools,
+        &model,
         python_execution_enabled,
-        python_tool_calling_enabled,
+    )
+    .await?;
+    let mut openai_tools = turn_tools.base_openai_tools(&tool_filter, &tool_description_overrides);
+    let TurnToolSetup {
+        model_info: current_model_info,
+        format_config,
+        filtered_tool_descriptions,
+        has_mcp_tools,
+        has_deferred_mcp_tools,
+        tool_search_enabled,
+        primary_format_for_prompt,
         python_tool_mode,
-        code_mode_possible
-    );
+        ..
+    } = turn_tools;
+    let tool_search_allowed = tool_filter.builtin_allowed("tool_search");
+    let allow_tool_search_for_python =
+        python_tool_mode && tool_search_enabled && has_deferred_mcp_tools && tool_search_allowed;
 
-    let mut openai_tools: Option<Vec<OpenAITool>> = if legacy_tool_calls_enabled {
-        Some(Vec::new())
-    } else {
-        None
+    // Resolve model profile to get tool call format preference
+    let resolved_model_tool_format = Some(model_profiles::resolve_profile(&model).tool_call_format);
+
+    // Models that reject reasoning_effort don't get it; others may get a per-model value
+    let reasoning_effort = match settings::resolve_reasoning_effort(
+        &reasoning_effort_overrides,
+        &model,
+        &reasoning_effort,
+        current_model_info.as_ref().map(|m| m.supports_reasoning_effort),
+    ) {
+        Some(effort) => {
+            if effort != reasoning_effort {
+                println!(
+                    "[chat] Using reasoning_effort override for {}: {} -> {}",
+                    model, reasoning_effort, effort
+                );
+            }
+            effort
+        }
+        None => {
+            println!(
+                "[chat] Model {} does not support reasoning_effort, dropping '{}'",
+                model, reasoning_effort
+            );
+            String::new()
+        }
     };
 
-    if let Some(list) = openai_tools.as_mut() {
-        if legacy_tool_search_enabled {
-            let tool_search_tool = tool_registry::tool_search_tool();
-            list.push(OpenAITool::from_tool_schema(&tool_search_tool));
-            println!("[Chat] Added tool_search built-in tool (legacy mode)");
-        }
+    // Images only go to vision-capable models; others get the text and a warning
+    let model_supports_vision = current_model_info.as_ref().is_some_and(|m| m.vision);
+    if !images.is_empty() && !model_supports_vision {
+        println!(
+            "[chat] Model {} does not support images, dropping {} attachment(s)",
+            model,
+            images.len()
+        );
+        let _ = app_handle.emit(
+            "chat-warning",
+            serde_json::json!({
+                "message": format!(
+                    "{} does not support images; {} image(s) were not sent",
+                    model,
+                    images.len()
+                )
+            }),
+        );
+        images.clear();
+    }
+    let stop_sequences = if use_format_stop_sequences {
+        format_config.stop_sequences()
+    } else {
+        Vec::new()
+    };
+    if !stop_sequences.is_empty() {
+        println!("[chat] Using tool call format stop sequences: {:?}", stop_sequences);
     }
 
     // Add MCP tools to the OpenAI tools list and register them in the tool registry
@@ -888,29 +1605,16 @@ async fn chat(
     {
         let mut registry = tool_registry_state.registry.write().await;
 
-        // Clear any previously registered tools (fresh start for this chat)
+        // Clear any previously registered tools (fresh start for this chat). Without
+        // strict turn scope, tools materialized by earlier turns carry over.
+        let carried_over = if strict_turn_tool_scope {
+            Vec::new()
+        } else {
+            registry.materialized_entries()
+        };
         registry.clear_domain_tools();
-
-        for (server_id, tools) in &filtered_tool_descriptions {
-            // Get the server config to extract defer_tools and python_name
-            let config = server_configs.iter().find(|c| c.id == *server_id);
-            let defer = config.map(|c| c.defer_tools).unwrap_or(false);
-            let python_name = config
-                .map(|c| c.get_python_name())
-                .unwrap_or_else(|| settings::to_python_identifier(server_id));
-
-            let mode = if defer { "DEFERRED" } else { "ACTIVE" };
-            println!(
-                "[Chat] Registering {} tools from {} [{}] (python_module={})",
-                tools.len(),
-                server_id,
-                mode,
-                python_name
-            );
-
-            // Register MCP tools in the registry with python module name
-            registry.register_mcp_tools(server_id, &python_name, tools, defer);
-        }
+        register_turn_mcp_tools(&mut registry, &filtered_tool_descriptions, &server_configs);
+        registry.restore_materialized(carried_over);
 
         let stats = registry.stats();
         println!(
@@ -939,10 +1643,21 @@ async fn chat(
         }
     }
 
+    // Trivial follow-ups ("thanks") skip discovery unless the user attached something
+    let has_explicit_attachments = !attached_files.is_empty()
+        || !turn_attached_tables.is_empty()
+        || !attached_tools.is_empty()
+        || !attached_tabular_files.is_empty();
+    let skip_auto_discovery = skip_auto_discovery.unwrap_or_else(|| {
+        !has_explicit_attachments
+            && auto_discovery::should_skip_auto_discovery(&message, auto_discovery_min_prompt_chars)
+    });
+
     // Compute effective tables (explicit attachments + always-on tables)
     // Schema search only runs when we have effective tables to work with
     let has_effective_tables = !turn_attached_tables.is_empty() || !always_on_tables.is_empty();
     let should_run_schema_search = has_effective_tables 
+        && !skip_auto_discovery
         && (schema_search_enabled || internal_schema_search || sql_select_enabled);
     
     // Compute effective tools (explicit attachments + always-on tools)
@@ -951,12 +1666,13 @@ async fn chat(
         || !always_on_mcp_tools.is_empty();
     let should_run_tool_search = tool_search_enabled 
         && tool_search_allowed 
+        && !skip_auto_discovery
         && (has_effective_tools || has_mcp_tools);
     
     println!(
-        "[Chat] Auto-discovery gating: schema_search={} (effective_tables={}), tool_search={} (effective_tools={})",
+        "[Chat] Auto-discovery gating: schema_search={} (effective_tables={}), tool_search={} (effective_tools={}), skipped={}",
         should_run_schema_search, has_effective_tables,
-        should_run_tool_search, has_effective_tools
+        should_run_tool_search, has_effective_tools, skip_auto_discovery
     );
 
     // Run auto-discovery (tool search + schema search) for this user prompt
@@ -964,6 +1680,7 @@ async fn chat(
         &message,
         should_run_tool_search, // Only run auto tool discovery if we have effective tools
         tool_search_max_results,
+        tool_search_min_relevance,
         has_mcp_tools,
         should_run_schema_search, // Only run auto schema search if we have effective tables
         settings_state.settings.read().await.schema_relevancy_threshold,
@@ -977,7 +1694,7 @@ async fn chat(
     .await;
 
     // Check if there are any attached documents (RAG indexed files)
-    let has_attachments = {
+    let has_attachments = embeddings_available && {
         let (tx, rx) = oneshot::channel();
         if handles
             .rag_tx
@@ -1050,35 +1767,21 @@ async fn chat(
     );
 
     // Visible tools: always include enabled built-ins; defer MCP tools to tool_search unless materialized.
+    let builtin_gate = BuiltinToolGate {
+        enabled_tools: &turn_config.enabled_tools,
+        always_on_builtin_tools: &always_on_builtin_tools,
+        tool_filter: &tool_filter,
+        python_execution_enabled,
+        sql_select_enabled,
+        schema_search_enabled,
+        has_deferred_mcp_tools,
+    };
     let builtin_tools: Vec<(String, Vec<McpTool>)> = {
         let registry = tool_registry_state.registry.read().await;
         registry
             .get_internal_tools()
             .iter()
-            .filter(|schema| {
-                // If per-chat tools are attached, only allow those
-                if !turn_config.enabled_tools.is_empty() {
-                    return turn_config.enabled_tools.contains(&schema.name);
-                }
-
-                // Fallback: include tool only if it's in always_on_builtin_tools AND passes filter
-                // Built-in tools require BOTH their *_enabled flag AND presence in always_on_builtin_tools
-                let is_always_on = always_on_builtin_tools.contains(&schema.name);
-                
-                if schema.name == "python_execution" {
-                    is_always_on && python_execution_enabled && tool_filter.builtin_allowed("python_execution")
-                } else if schema.name == "tool_search" {
-                    // tool_search only if always-on AND there are deferred tools to discover
-                    is_always_on && has_deferred_mcp_tools && tool_filter.builtin_allowed("tool_search")
-                } else if schema.name == "sql_select" {
-                    is_always_on && sql_select_enabled && tool_filter.builtin_allowed("sql_select")
-                } else if schema.name == "schema_search" {
-                    is_always_on && schema_search_enabled && tool_filter.builtin_allowed("schema_search")
-                } else {
-                    // Unknown built-ins: require always_on and filter
-                    is_always_on && tool_filter.builtin_allowed(&schema.name)
-                }
-            })
+            .filter(|schema| builtin_gate.allows(&schema.name))
             .map(|schema| ("builtin".to_string(), vec![tool_schema_to_mcp_tool(schema)]))
             .collect()
     };
@@ -1111,64 +1814,12 @@ async fn chat(
     // Include visible tools in legacy/native tool calling payloads
     if let Some(ref mut tools_list) = openai_tools {
         let registry = tool_registry_state.registry.read().await;
-        let mut seen: HashSet<String> =
-            tools_list.iter().map(|t| t.function.name.clone()).collect();
-
-        for (server_id, schema) in registry.get_visible_tools_with_servers() {
-            // Filter builtin tools based on their enabled state and tool filter
-            if server_id == "builtin" {
-                // If per-chat tools attached, only allow those
-                if !turn_config.enabled_tools.is_empty() {
-                    if !turn_config.enabled_tools.contains(&schema.name) {
-                        continue;
-                    }
-                } else {
-                    // Fallback: require tool to be in always_on_builtin_tools
-                    let is_always_on = always_on_builtin_tools.contains(&schema.name);
-                    
-                    if schema.name == "python_execution" {
-                        if !is_always_on || !python_execution_enabled || !tool_filter.builtin_allowed("python_execution") {
-                            continue;
-                        }
-                    } else if schema.name == "tool_search" {
-                        // tool_search only if always-on AND there are deferred tools to discover
-                        if !is_always_on || !has_deferred_mcp_tools || !tool_filter.builtin_allowed("tool_search") {
-                            continue;
-                        }
-                    } else if schema.name == "sql_select" {
-                        if !is_always_on || !sql_select_enabled || !tool_filter.builtin_allowed("sql_select") {
-                            continue;
-                        }
-                    } else if schema.name == "schema_search" {
-                        if !is_always_on || !schema_search_enabled || !tool_filter.builtin_allowed("schema_search") {
-                            continue;
-                        }
-                    } else if !is_always_on || !tool_filter.builtin_allowed(&schema.name) {
-                        // Unknown built-ins: require always_on and filter
-                        continue;
-                    }
-                }
-            } else {
-                // MCP tools - check if explicitly attached if any tools are attached
-                if !turn_config.enabled_tools.is_empty() {
-                    let key = format!("{}::{}", server_id, schema.name);
-                    if !turn_config.enabled_tools.contains(&key) {
-                        continue;
-                    }
-                }
-            }
-            // Build OpenAI tool; MCP tools get server prefix for routing (sanitized)
-            let openai_tool = if server_id == "builtin" {
-                OpenAITool::from_tool_schema(&schema)
-            } else {
-                OpenAITool::from_mcp_schema(&server_id, &schema)
-            };
-
-            if !seen.insert(openai_tool.function.name.clone()) {
-                continue;
-            }
-            tools_list.push(openai_tool);
-        }
+        extend_openai_tools(
+            tools_list,
+            registry.get_visible_tools_with_servers(),
+            &builtin_gate,
+            &tool_description_overrides,
+        );
     }
 
     if let Some(ref tools) = openai_tools {
@@ -1192,8 +1843,8 @@ async fn chat(
         );
     }
     println!(
-        "[Chat] tool_search_max_results={}",
-        tool_search_max_results
+        "[Chat] tool_search_max_results={}, tool_search_min_relevance={:.2}",
+        tool_search_max_results, tool_search_min_relevance
     );
 
     // === STATE MACHINE: Build system prompt from single source of truth ===
@@ -1240,6 +1891,8 @@ async fn chat(
         model_tool_format: resolved_model_tool_format,
         custom_tool_prompts: tool_system_prompts.clone(),
         python_primary: python_tool_mode,
+        prepend: system_prompt_prefix.clone(),
+        append: system_prompt_suffix.clone(),
     };
     
     // Create state machine using three-tier hierarchy:
@@ -1289,13 +1942,43 @@ async fn chat(
         discovered_tables,
         Vec::new(), // RAG chunks
     );
+    if let Some(requested) = &force_state {
+        // Checked at the start of the turn; attached documents can still rule out SQL states
+        if let Err(e) = initial_state_machine.force_initial_state(requested) {
+            println!("[Chat] Keeping the computed state: {}", e);
+        }
+    } else if skip_auto_discovery {
+        initial_state_machine.force_initial_state("Conversational")?;
+    }
     
     // Pass auto-discovery context to state machine (it owns prompt generation)
     initial_state_machine.set_auto_discovery_context(
         auto_discovery.tool_search_output.clone(),
         auto_discovery.schema_search_output.clone(),
     );
+
+    // A resumed turn continues with the discovery context and state it had
+    if let Some(checkpoint) = &resume {
+        initial_state_machine.set_auto_discovery_context(
+            checkpoint.tool_search.clone(),
+            checkpoint.schema_search.clone(),
+        );
+        initial_state_machine.restore_state(checkpoint.state.clone());
+    }
     
+    // Trim tool descriptions if the prompt would exceed the configured budget
+    let omitted_tools = if max_system_prompt_chars > 0 {
+        initial_state_machine.fit_system_prompt_to_budget(max_system_prompt_chars)
+    } else {
+        0
+    };
+    if omitted_tools > 0 {
+        println!(
+            "[Chat] Omitted {} tool(s) to fit the {} char system prompt budget",
+            omitted_tools, max_system_prompt_chars
+        );
+    }
+
     // Build system prompt from state machine (single source of truth)
     let system_prompt = initial_state_machine.build_system_prompt();
     
@@ -1340,38 +2023,95 @@ async fn chat(
             chat_id: chat_id.clone(),
             generation_id,
             prompt: system_prompt.clone(),
+            omitted_tools,
         },
     );
 
-    // Build full history with system prompt at the beginning
-    let mut full_history = Vec::new();
-
-    // Add system prompt if we have one
-    if !system_prompt.is_empty() {
-        full_history.push(ChatMessage {
-            role: "system".to_string(),
-            content: system_prompt.clone(),
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
-        });
-    }
-
-    // Add existing history (skip any existing system messages to avoid duplicates)
-    for msg in history.iter() {
-        if msg.role != "system" {
-            full_history.push(msg.clone());
+    // Summarize older turns when the history nears the model's context size.
+    // Only the model sees the summary; the chat is stored uncompacted, with the
+    // tool traffic of earlier turns that the model is not sent.
+    let stored_messages = if resume.is_none() && !history.is_empty() {
+        fetch_stored_messages(&handles.vector_tx, &chat_id)
+            .await
+            .unwrap_or_else(|e| {
+                println!("[Chat] Could not load stored messages: {}", e);
+                None
+            })
+    } else {
+        None
+    };
+    let stored_history = match &resume {
+        Some(checkpoint) => checkpoint.stored_history.clone(),
+        None => history_to_store(stored_messages, &history),
+    };
+    let mut history: Vec<ChatMessage> = history.into_iter().filter(|m| !m.intermediate).collect();
+    if history_compaction_enabled && resume.is_none() {
+        let new_message_tokens = history_compaction::estimate_tokens(&[ChatMessage {
+            role: "user".to_string(),
+            content: format!("{}{}", system_prompt, message),
+            ..Default::default()
+        }]);
+        let tokens_before = history_compaction::estimate_tokens(&history) + new_message_tokens;
+        let threshold = history_compaction::compaction_threshold(
+            current_model_info.as_ref().map(|m| m.max_input_tokens),
+        );
+        if tokens_before > threshold {
+            if let Some((older, recent)) =
+                history_compaction::split_for_compaction(&history, history_compaction_keep_turns)
+            {
+                println!(
+                    "[Chat] History at ~{} tokens exceeds {}; summarizing {} older message(s)",
+                    tokens_before,
+                    threshold,
+                    older.len()
+                );
+                match history_compaction::summarize_history_cached(
+                    &turn_tracker.history_summaries,
+                    &chat_id,
+                    &handles.foundry_tx,
+                    &model,
+                    &older,
+                    chat_format_default,
+                    chat_format_overrides.clone(),
+                )
+                .await
+                {
+                    Ok(summary) => {
+                        let kept_messages = recent.len();
+                        history = history_compaction::compacted_history(&summary, recent);
+                        let tokens_after =
+                            history_compaction::estimate_tokens(&history) + new_message_tokens;
+                        println!(
+                            "[Chat] History compacted: ~{} -> ~{} tokens",
+                            tokens_before, tokens_after
+                        );
+                        let _ = app_handle.emit(
+                            "history-compacted",
+                            HistoryCompactedEvent {
+                                chat_id: chat_id.clone(),
+                                compacted_messages: older.len(),
+                                kept_messages,
+                                estimated_tokens_before: tokens_before,
+                                estimated_tokens_after: tokens_after,
+                            },
+                        );
+                    }
+                    Err(e) => {
+                        println!("[Chat] History compaction failed, sending full history: {}", e);
+                    }
+                }
+            }
         }
     }
 
-    // Add the new user message
-    full_history.push(ChatMessage {
-        role: "user".to_string(),
-        content: message.clone(),
-        system_prompt: None,
-        tool_calls: None,
-        tool_call_id: None,
-    });
+    // Build full history with system prompt at the beginning
+    let mut full_history = build_turn_messages(&system_prompt, &history, &message, images);
+    let start_iteration = resume.as_ref().map_or(0, |checkpoint| checkpoint.iteration);
+
+    // A resumed turn already has its messages, tool calls and results
+    if let Some(checkpoint) = resume {
+        full_history = checkpoint.full_history;
+    }
 
     // Use the frontend-provided model (frontend is source of truth)
     let model_name = model.clone();
@@ -1389,6 +2129,7 @@ async fn chat(
         // Use CPU model for embeddings during chat (avoids evicting LLM from GPU)
         embedding_model: embedding_state.cpu_model.clone(),
         pending_approvals: approval_state.pending.clone(),
+        tool_disables: turn_tracker.tool_disables.clone(),
     };
 
     // Check if python_execution is in the native tools list
@@ -1398,12 +2139,25 @@ async fn chat(
         .map(|tools| tools.iter().any(|t| t.function.name == "python_execution"))
         .unwrap_or(false);
 
+    // Code mode and text-based tool formats need free-form output, so only a
+    // native tool calling turn can have the model constrain its answer
+    let response_schema_native = response_schema.is_some()
+        && primary_format_for_prompt == ToolCallFormatName::Native
+        && !python_tool_mode;
+    if response_schema.is_some() {
+        println!(
+            "[Chat] Response schema set (native constraint: {})",
+            response_schema_native
+        );
+    }
+
     // Build agentic loop config (behavior parameters)
     let agentic_config = AgenticLoopConfig {
         chat_id: chat_id.clone(),
         generation_id,
         title: title.clone(),
         original_message: message.clone(),
+        stored_history,
         model_name,
         reasoning_effort,
         python_tool_mode,
@@ -1411,6 +2165,7 @@ async fn chat(
         primary_format: primary_format_for_prompt,
         allow_tool_search_for_python,
         tool_search_max_results,
+        tool_search_min_relevance,
         turn_system_prompt: system_prompt.clone(),
         chat_format_default,
         chat_format_overrides: chat_format_overrides.clone(),
@@ -1418,6 +2173,40 @@ async fn chat(
         server_configs: server_configs.clone(), // Combined list!
         tabular_context: build_tabular_python_context(&parsed_tabular_files),
         python_execution_in_native_tools,
+        python_execution_timeout_ms,
+        python_session_enabled,
+        python_allowed_modules,
+        python_memory_limit_bytes,
+        max_tool_iterations,
+        max_tool_calls_per_turn,
+        parallel_tool_calls,
+        batch_approval_mode,
+        tool_call_ids_in_text,
+        mcp_tool_timeout_secs,
+        tool_heartbeat_interval_ms,
+        chat_autosave_interval_secs,
+        python_result_format,
+        sql_result_format,
+        reasoning_format: current_model_info
+            .as_ref()
+            .map(|m| m.reasoning_format)
+            .unwrap_or_default(),
+        reasoning_events_enabled,
+        python_multi_block,
+        max_tool_result_chars,
+        summarize_large_tool_results,
+        tool_result_summary_threshold_chars,
+        foundry_chat_retries,
+        first_token_timeout_secs,
+        model_supports_vision,
+        response_schema,
+        response_schema_native,
+        temperature,
+        seed,
+        stop_sequences,
+        turn_request,
+        start_iteration,
+        checkpoint_path: turn_checkpoint::get_checkpoint_path(),
     };
 
     let turn_progress = turn_tracker.progress.clone();
@@ -1453,14 +2242,167 @@ async fn get_system_prompt_preview(
     tool_registry_state: State<'_, ToolRegistryState>,
     embedding_state: State<'_, EmbeddingModelState>,
 ) -> Result<String, String> {
+    let (state_machine, _) = preview_state_machine(
+        user_prompt,
+        attached_files,
+        attached_tables,
+        attached_tools,
+        attached_tabular_files,
+        None,
+        &handles,
+        &settings_state,
+        &launch_config,
+        &tool_registry_state,
+        &embedding_state,
+    )
+    .await?;
+    Ok(state_machine.build_system_prompt())
+}
+
+/// Preview the exact messages array and tool definitions a `chat` call would
+/// send to the model, without sending anything or starting a turn.
+///
+/// History compaction and tools materialized by auto-discovery are not applied.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn preview_model_messages(
+    history: Vec<ChatMessage>,
+    message: String,
+    model: String,
+    attached_files: Option<Vec<String>>,
+    attached_tables: Option<Vec<crate::settings_state_machine::AttachedTableInfo>>,
+    attached_tools: Option<Vec<String>>,
+    attached_tabular_files: Option<Vec<String>>,
+    handles: State<'_, ActorHandles>,
+    settings_state: State<'_, SettingsState>,
+    launch_config: State<'_, LaunchConfigState>,
+    tool_registry_state: State<'_, ToolRegistryState>,
+    embedding_state: State<'_, EmbeddingModelState>,
+) -> Result<ModelMessagesPreview, String> {
+    let mut attached_tools = attached_tools.unwrap_or_default();
+    let (mut state_machine, enabled_tools) = preview_state_machine(
+        message.clone(),
+        attached_files.unwrap_or_default(),
+        attached_tables.unwrap_or_default(),
+        attached_tools.clone(),
+        attached_tabular_files.unwrap_or_default(),
+        Some(&model),
+        &handles,
+        &settings_state,
+        &launch_config,
+        &tool_registry_state,
+        &embedding_state,
+    )
+    .await?;
+
+    let settings = settings_state.settings.read().await.clone();
+    if settings.max_system_prompt_chars > 0 {
+        state_machine.fit_system_prompt_to_budget(settings.max_system_prompt_chars);
+    }
+    let system_prompt = state_machine.build_system_prompt();
+    let messages = build_turn_messages(&system_prompt, &history, &message, Vec::new());
+    let always_on_builtin_tools = turn_builtin_tools(
+        &settings.always_on_builtin_tools,
+        &mut attached_tools,
+        embedding_state.status().await.available,
+    );
+
+    // Same tool list as chat(): built-ins that pass the gate plus the visible MCP tools
+    let tool_filter = launch_config.tool_filter.clone();
+    let is_builtin_active = |name: &str| {
+        always_on_builtin_tools.contains(&name.to_string())
+            || attached_tools.contains(&format!("builtin::{}", name))
+            || attached_tools.contains(&name.to_string())
+    };
+    let python_execution_enabled = is_builtin_active("python_execution")
+        && !settings.safe_mode_blocks_builtin("python_execution");
+    let mut server_configs = settings.get_all_mcp_configs();
+    settings.apply_safe_mode_approvals(&mut server_configs);
+    let turn_tools = resolve_turn_tools(
+        &handles,
+        &settings,
+        &tool_filter,
+        &mut server_configs,
+        &always_on_builtin_tools,
+        &model,
+        python_execution_enabled,
+    )
+    .await?;
+
+    let mut openai_tools = turn_tools.base_openai_tools(&tool_filter, &settings.tool_description_overrides);
+    if let Some(tools_list) = openai_tools.as_mut() {
+        // A scratch registry, so a running turn's registered tools are left alone
+        let mut registry = tool_registry::ToolRegistry::new();
+        {
+            let shared = tool_registry_state.registry.read().await;
+            let has_builtin =
+                |name: &str| shared.get_internal_tools().iter().any(|t| t.name == name);
+            registry.set_schema_search_enabled(has_builtin("schema_search"));
+            registry.set_sql_select_enabled(has_builtin("sql_select"));
+        }
+        register_turn_mcp_tools(&mut registry, &turn_tools.filtered_tool_descriptions, &server_configs);
+        let gate = BuiltinToolGate {
+            enabled_tools: &enabled_tools,
+            always_on_builtin_tools: &always_on_builtin_tools,
+            tool_filter: &tool_filter,
+            python_execution_enabled,
+            sql_select_enabled: is_builtin_active("sql_select"),
+            schema_search_enabled: is_builtin_active("schema_search"),
+            has_deferred_mcp_tools: turn_tools.has_deferred_mcp_tools,
+        };
+        extend_openai_tools(
+            tools_list,
+            registry.get_visible_tools_with_servers(),
+            &gate,
+            &settings.tool_description_overrides,
+        );
+    }
+
+    println!(
+        "[preview_model_messages] model={} messages={} tools={}",
+        model,
+        messages.len(),
+        openai_tools.as_ref().map_or(0, |tools| tools.len())
+    );
+    Ok(ModelMessagesPreview {
+        messages,
+        openai_tools,
+    })
+}
+
+/// Build the turn's state machine the way `chat` does, for previews, for
+/// `model` or the selected model. Also returns the tools enabled by the
+/// turn's attachments.
+#[allow(clippy::too_many_arguments)]
+async fn preview_state_machine(
+    user_prompt: String,
+    attached_files: Vec<String>,
+    attached_tables: Vec<crate::settings_state_machine::AttachedTableInfo>,
+    mut attached_tools: Vec<String>,
+    attached_tabular_files: Vec<String>,
+    model: Option<&str>,
+    handles: &ActorHandles,
+    settings_state: &SettingsState,
+    launch_config: &LaunchConfigState,
+    tool_registry_state: &ToolRegistryState,
+    embedding_state: &EmbeddingModelState,
+) -> Result<(AgenticStateMachine, Vec<String>), String> {
     // 1. Get current settings and model info
     let settings = settings_state.settings.read().await;
     let base_prompt = settings.system_prompt.clone();
+    let system_prompt_prefix = settings.system_prompt_prefix.clone();
+    let system_prompt_suffix = settings.system_prompt_suffix.clone();
     let server_configs = settings.mcp_servers.clone();
     let tool_system_prompts = settings.tool_system_prompts.clone();
+    let tool_description_overrides = settings.tool_description_overrides.clone();
     let database_toolbox_config = settings.database_toolbox.clone();
     // Always-on configuration for gating auto-discovery
-    let always_on_builtin_tools = settings.always_on_builtin_tools.clone();
+    let embeddings_available = embedding_state.status().await.available;
+    let always_on_builtin_tools = turn_builtin_tools(
+        &settings.always_on_builtin_tools,
+        &mut attached_tools,
+        embeddings_available,
+    );
     let always_on_mcp_tools = settings.always_on_mcp_tools.clone();
     let always_on_tables = settings.always_on_tables.clone();
 
@@ -1592,7 +2534,7 @@ async fn get_system_prompt_preview(
     }
     let tool_descriptions = tools_rx.await.map_err(|_| "MCP Host actor died")?;
 
-    let filtered_tool_descriptions: Vec<(String, Vec<McpTool>)> = tool_descriptions
+    let mut filtered_tool_descriptions: Vec<(String, Vec<McpTool>)> = tool_descriptions
         .into_iter()
         .filter_map(|(server_id, tools)| {
             let is_enabled = server_configs.iter().any(|c| c.id == server_id && c.enabled);
@@ -1603,10 +2545,15 @@ async fn get_system_prompt_preview(
             if infos.is_empty() { None } else { Some((server_id, infos)) }
         })
         .collect();
+    tool_registry::apply_mcp_description_overrides(
+        &mut filtered_tool_descriptions,
+        &tool_description_overrides,
+    );
 
     // Gate auto-discovery based on effective attachments (explicit + always-on)
     let has_effective_tables = !turn_context.attached_tables.is_empty() || !always_on_tables.is_empty();
-    let internal_schema_search = settings_for_resolver.should_run_internal_schema_search();
+    let internal_schema_search =
+        settings_for_resolver.should_run_internal_schema_search() && embeddings_available;
     let should_run_schema_search = has_effective_tables
         && (schema_search_enabled || internal_schema_search || sql_select_enabled);
     
@@ -1621,6 +2568,7 @@ async fn get_system_prompt_preview(
         &user_prompt,
         should_run_tool_search,
         settings_for_resolver.tool_search_max_results,
+        settings_for_resolver.tool_search_min_relevance,
         !filtered_tool_descriptions.is_empty(),
         should_run_schema_search,
         settings_for_resolver.schema_relevancy_threshold,
@@ -1636,11 +2584,16 @@ async fn get_system_prompt_preview(
 
     let (resolved_capabilities, model_tool_format) = {
         let registry = tool_registry_state.registry.read().await;
-        let (tx, rx) = oneshot::channel();
-        let fetched_model_info = if handles.foundry_tx.send(FoundryMsg::GetCurrentModel { respond_to: tx }).await.is_ok() {
-            rx.await.ok().flatten()
-        } else {
-            None
+        let fetched_model_info = match model {
+            Some(model) => lookup_model_info(&handles.foundry_tx, model).await,
+            None => {
+                let (tx, rx) = oneshot::channel();
+                if handles.foundry_tx.send(FoundryMsg::GetCurrentModel { respond_to: tx }).await.is_ok() {
+                    rx.await.ok().flatten()
+                } else {
+                    None
+                }
+            }
         };
         let default_model_info = ModelInfo {
             id: "unknown".to_string(),
@@ -1698,6 +2651,8 @@ async fn get_system_prompt_preview(
             model_tool_format,
             custom_tool_prompts: tool_system_prompts,
             python_primary: resolved_capabilities.available_builtins.contains(tool_capability::BUILTIN_PYTHON_EXECUTION),
+            prepend: system_prompt_prefix,
+            append: system_prompt_suffix,
             has_attachments,
         },
     );
@@ -1737,11 +2692,13 @@ async fn get_system_prompt_preview(
 
     initial_state_machine.set_auto_discovery_context(auto_discovery.tool_search_output, auto_discovery.schema_search_output);
 
-    Ok(initial_state_machine.build_system_prompt())
+    Ok((initial_state_machine, turn_config.enabled_tools))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    logging::init_tracing();
+
     // Fix PATH for macOS GUI applications (required for finding 'foundry' CLI in production builds).
     // macOS GUI apps don't inherit shell PATH from dotfiles (.zshrc, .bashrc, etc.).
     // This spawns the user's login shell to extract the correct PATH and sets it.
@@ -1805,7 +2762,10 @@ pub fn run() {
             let (startup_tx, startup_rx) = mpsc::channel(32);
             let python_mcp_host_tx = mcp_host_tx.clone();
             let mcp_host_tx_for_db = mcp_host_tx.clone();
+            let mcp_host_tx_for_health = mcp_host_tx.clone();
             let mcp_host_tx_for_handles = mcp_host_tx.clone();
+            let mcp_host_tx_for_warm_start = mcp_host_tx.clone();
+            let foundry_tx_for_warm_start = foundry_tx.clone();
             let startup_tx_for_foundry = startup_tx.clone();
             let startup_tx_for_handles = startup_tx.clone();
             let logging_persistence = Arc::new(LoggingPersistence::default());
@@ -1836,6 +2796,8 @@ pub fn run() {
             let embedding_model_state = EmbeddingModelState {
                 gpu_model: Arc::new(RwLock::new(None)), // DISABLED - always None
                 cpu_model: Arc::new(RwLock::new(None)),
+                cpu_load_error: Arc::new(RwLock::new(None)),
+                unavailable_notice_sent: AtomicBool::new(false),
             };
             let gpu_embedding_model_arc = embedding_model_state.gpu_model.clone();
             let cpu_embedding_model_arc = embedding_model_state.cpu_model.clone();
@@ -1858,6 +2820,7 @@ pub fn run() {
                 app_settings.mcp_servers.len()
             );
             // Create SettingsStateMachine (Tier 1 of the three-tier hierarchy)
+            let warm_start_on_launch = app_settings.warm_start_on_launch;
             let settings_sm = SettingsStateMachine::from_settings(&app_settings, &launch_filter);
             println!(
                 "[SettingsStateMachine] Initialized with mode: {} (capabilities: {:?})",
@@ -1868,6 +2831,9 @@ pub fn run() {
             let settings_state = SettingsState {
                 settings: Arc::new(RwLock::new(app_settings)),
             };
+            let settings_for_health = settings_state.settings.clone();
+            let settings_for_warm_start = settings_state.settings.clone();
+            let settings_for_python = settings_state.settings.clone();
             app.manage(settings_state);
             
             // Manage the settings state machine
@@ -1890,6 +2856,12 @@ pub fn run() {
                     launch_filter.allowed_servers,
                     launch_filter.allowed_tools
                 );
+                println!(
+                    "[Launch] Tool denylist (builtins={:?}, servers={:?}, tools={:?})",
+                    launch_filter.denied_builtins,
+                    launch_filter.denied_servers,
+                    launch_filter.denied_tools
+                );
             }
             if launch_overrides.model.is_some() || launch_overrides.initial_prompt.is_some() {
                 println!(
@@ -1916,9 +2888,28 @@ pub fn run() {
             };
             app.manage(cancellation_state);
 
-            // Track turn progress for reconnect/replay
+            // Track turn progress for reconnect/replay; a turn interrupted by a
+            // crash left its checkpoint on disk and can be resumed
+            let interrupted_turn = tauri::async_runtime::block_on(
+                turn_checkpoint::load_checkpoint(&turn_checkpoint::get_checkpoint_path()),
+            );
+            if let Some(checkpoint) = &interrupted_turn {
+                println!(
+                    "[Launch] Interrupted turn for chat {:?} at iteration {} can be resumed",
+                    checkpoint.request.chat_id, checkpoint.iteration
+                );
+            }
             let turn_tracker_state = TurnTrackerState {
-                progress: Arc::new(RwLock::new(TurnProgress::default())),
+                progress: Arc::new(RwLock::new(TurnProgress {
+                    chat_id: interrupted_turn
+                        .as_ref()
+                        .and_then(|checkpoint| checkpoint.request.chat_id.clone()),
+                    resumable: interrupted_turn.is_some(),
+                    checkpoint: interrupted_turn,
+                    ..Default::default()
+                })),
+                tool_disables: Arc::new(RwLock::new(ToolDisables::default())),
+                history_summaries: Default::default(),
             };
             app.manage(turn_tracker_state);
 
@@ -2033,6 +3024,21 @@ pub fn run() {
                 actor.run().await;
             });
 
+            // Periodically ping connected MCP servers
+            let mcp_health_state = McpHealthState::default();
+            let health_for_checks = mcp_health_state.servers.clone();
+            app.manage(mcp_health_state);
+            let health_app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                mcp_health::run_mcp_health_checks(
+                    health_app_handle,
+                    mcp_host_tx_for_health,
+                    settings_for_health,
+                    health_for_checks,
+                )
+                .await;
+            });
+
             // Spawn Python Actor for code execution
             let python_tool_registry = tool_registry.clone();
             tauri::async_runtime::spawn(async move {
@@ -2041,10 +3047,35 @@ pub fn run() {
                     python_tool_registry,
                     python_mcp_host_tx,
                     embedding_model_arc_for_python,
+                    settings_for_python,
                 );
                 actor.run().await;
             });
 
+            // Opt-in warm start: connect MCP servers and preload the model in the background
+            if warm_start_on_launch {
+                let warm_start_app_handle = app_handle.clone();
+                let warm_start_filter = launch_filter.clone();
+                let warm_start_registry = tool_registry.clone();
+                let warm_start_embedding_model = cpu_embedding_model_arc.clone();
+                tauri::async_runtime::spawn(async move {
+                    tokio::time::sleep(std::time::Duration::from_millis(
+                        warm_start::LAUNCH_DELAY_MS,
+                    ))
+                    .await;
+                    warm_start::run_warm_start(
+                        warm_start_app_handle,
+                        mcp_host_tx_for_warm_start,
+                        foundry_tx_for_warm_start,
+                        settings_for_warm_start,
+                        warm_start_filter,
+                        warm_start_registry,
+                        warm_start_embedding_model,
+                    )
+                    .await;
+                });
+            }
+
             // Embedding model initialization is now handled by ModelGatewayActor
             // after it detects available execution providers from Foundry Local
 
@@ -2094,6 +3125,7 @@ pub fn run() {
             log_to_terminal,
             delete_chat,
             load_chat,
+            export_chat,
             update_chat,
             // Model loading commands
             download_model,
@@ -2105,9 +3137,14 @@ pub fn run() {
             get_foundry_service_status,
             get_current_model,
             get_model_state,
+            get_embedding_model_status,
             remove_cached_model,
             cancel_generation,
             get_turn_status,
+            get_tool_disable_state,
+            reset_tool_disable_state,
+            resume_turn,
+            rerun_turn,
             // RAG commands
             select_files,
             select_folder,
@@ -2122,6 +3159,9 @@ pub fn run() {
             get_settings,
             get_default_mcp_test_server,
             get_python_allowed_imports,
+            get_python_module_catalog,
+            get_available_tool_call_formats,
+            lint_system_prompt,
             save_app_settings,
             add_mcp_server,
             update_mcp_server,
@@ -2145,6 +3185,8 @@ pub fn run() {
             refresh_database_schema_for_source,
             search_database_tables,
             set_schema_table_enabled,
+            set_schema_tables_enabled,
+            purge_schema_table,
             check_table_name_conflicts,
             // MCP commands
             sync_mcp_servers,
@@ -2152,15 +3194,25 @@ pub fn run() {
             disconnect_mcp_server,
             list_mcp_tools,
             execute_mcp_tool,
+            debug_mcp_tool,
             get_mcp_server_status,
             get_all_mcp_tool_descriptions,
             test_mcp_server_config,
+            get_mcp_health,
+            refresh_tool_registry,
+            dump_tool_registry,
+            warm_start,
             get_system_prompt_preview,
+            preview_model_messages,
             detect_tool_calls,
             execute_tool_call,
             approve_tool_call,
             reject_tool_call,
+            cancel_pending_approval,
+            approve_tool_batch,
             get_pending_tool_approvals,
+            validate_python_code,
+            run_tool_search,
             get_current_model,
             get_launch_overrides,
             heartbeat_ping,
@@ -2174,7 +3226,7 @@ pub fn run() {
 
 #[cfg(test)]
 mod inline_tests {
-    use crate::settings::{AppSettings, ToolCallFormatName, ToolCallFormatConfig, McpServerConfig};
+    use crate::settings::{AppSettings, ToolCallFormatName, ToolCallFormatConfig, McpServerConfig, ResultFormat};
     use crate::protocol::{ToolFormat, ParsedToolCall};
     use crate::tool_capability::ToolLaunchFilter;
     use crate::python_helpers::{fix_python_indentation, strip_unsupported_python};
@@ -2196,7 +3248,7 @@ mod inline_tests {
         primary_format: ToolCallFormatName,
     ) -> AgenticLoopAction {
         // Pass false for python_execution_in_native_tools in legacy tests
-        detect_agentic_loop_action(response, model_family, tool_format, python_tool_mode, formats, primary_format, false)
+        detect_agentic_loop_action(response, model_family, tool_format, python_tool_mode, formats, primary_format, false, false)
     }
 
     fn hermes_call(name: &str, args: serde_json::Value) -> String {
@@ -2213,6 +3265,9 @@ mod inline_tests {
             AgenticAction::Final { response } => {
                 panic!("expected tool calls, got final response: {}", response)
             }
+            AgenticAction::MalformedToolCall { hint } => {
+                panic!("expected tool calls, got malformed tool call: {}", hint)
+            }
         }
     }
 
@@ -2429,6 +3484,7 @@ mod inline_tests {
             })),
             input_examples: None,
             allowed_callers: None,
+            streamable: false,
         }])];
 
         let mut app_settings = AppSettings::default();
@@ -2451,6 +3507,8 @@ mod inline_tests {
                 model_tool_format: None,
                 custom_tool_prompts: tool_prompts,
                 python_primary: false,
+                prepend: None,
+                append: None,
                 has_attachments: false,
             },
         );
@@ -2601,7 +3659,16 @@ mod inline_tests {
             ToolCallFormatName::Hermes,
         );
         let calls = unwrap_tool_calls(action);
-        let formatted = format_tool_result(&calls[0], "echo: hi", false, ToolFormat::Hermes, None, None);
+        let formatted = format_tool_result(
+            &calls[0],
+            "echo: hi",
+            false,
+            ToolFormat::Hermes,
+            None,
+            None,
+            ResultFormat::Text,
+            false,
+        );
 
         assert!(
             formatted.contains("echo: hi"),
@@ -2613,4 +3680,204 @@ mod inline_tests {
         );
     }
 
+    #[test]
+    fn test_history_to_store_keeps_stored_tool_traffic() {
+        let message = |role: &str, content: &str| ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let tool_traffic = |role: &str, content: &str| ChatMessage {
+            intermediate: true,
+            ..message(role, content)
+        };
+        let stored = vec![
+            message("user", "Weather?"),
+            tool_traffic("assistant", "<tool_call>{}</tool_call>"),
+            tool_traffic("user", "<tool_response>Sunny</tool_response>"),
+            message("assistant", "Sunny"),
+        ];
+        let sent = vec![message("user", "Weather?"), message("assistant", "Sunny *(interrupted)*")];
+        assert_eq!(history_to_store(Some(stored.clone()), &sent).len(), 4);
+
+        // An edited conversation no longer matches the stored record
+        let edited = vec![message("user", "Weather in Oslo?"), message("assistant", "Sunny")];
+        let kept = history_to_store(Some(stored.clone()), &edited);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].content, "Weather in Oslo?");
+        assert_eq!(history_to_store(None, &sent).len(), 2);
+
+        // A history that already has tool traffic (a re-run) is used as is
+        assert_eq!(history_to_store(None, &stored).len(), 4);
+    }
+
+    #[test]
+    fn test_build_turn_messages() {
+        let message = |role: &str, content: &str| ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let history = vec![
+            message("system", "old system prompt"),
+            message("user", "Hi"),
+            message("assistant", "Hello!"),
+        ];
+
+        let messages = build_turn_messages("You are helpful.", &history, "Bye", Vec::new());
+        let roles: Vec<&str> = messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["system", "user", "assistant", "user"]);
+        assert_eq!(messages[0].content, "You are helpful.");
+        assert_eq!(messages[3].content, "Bye");
+
+        // No system message without a prompt
+        assert_eq!(build_turn_messages("", &history, "Bye", Vec::new()).len(), 3);
+    }
+
+    #[test]
+    fn test_new_turn_starts_with_no_materialized_tools() {
+        let tool = McpTool {
+            name: "deferred_tool".to_string(),
+            description: None,
+            input_schema: None,
+            input_examples: None,
+            allowed_callers: None,
+            streamable: false,
+        };
+        let mut registry = tool_registry::ToolRegistry::new();
+        registry.register_mcp_tools("srv1", "srv1", &[tool], true);
+        assert!(registry.materialize_tool("srv1___deferred_tool"));
+
+        // Shared scope keeps a fresh materialization...
+        begin_turn_tool_scope(&mut registry, false, 600);
+        assert_eq!(registry.materialized_tool_keys().len(), 1);
+
+        // ...but strict scope always starts the message from zero
+        begin_turn_tool_scope(&mut registry, true, 600);
+        assert!(registry.materialized_tool_keys().is_empty());
+        assert!(!registry.is_tool_visible("srv1", "deferred_tool"));
+    }
+
+    #[test]
+    fn test_builtin_tool_gate() {
+        let always_on = vec!["python_execution".to_string(), "tool_search".to_string()];
+        let filter = ToolLaunchFilter::default();
+        let mut gate = BuiltinToolGate {
+            enabled_tools: &[],
+            always_on_builtin_tools: &always_on,
+            tool_filter: &filter,
+            python_execution_enabled: true,
+            sql_select_enabled: true,
+            schema_search_enabled: false,
+            has_deferred_mcp_tools: false,
+        };
+        assert!(gate.allows("python_execution"));
+        // tool_search needs deferred tools; sql_select needs to be Always On
+        assert!(!gate.allows("tool_search"));
+        assert!(!gate.allows("sql_select"));
+
+        let attached = vec!["sql_select".to_string()];
+        gate.enabled_tools = &attached;
+        assert!(gate.allows("sql_select"));
+        assert!(!gate.allows("python_execution"));
+
+        let mut tools = Vec::new();
+        let visible = vec![
+            ("builtin".to_string(), tool_registry::sql_select_tool()),
+            ("builtin".to_string(), tool_registry::python_execution_tool()),
+        ];
+        extend_openai_tools(&mut tools, visible, &gate, &HashMap::new());
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].function.name, "sql_select");
+    }
+
+    #[test]
+    fn test_description_override_in_openai_payload() {
+        let always_on = vec!["python_execution".to_string()];
+        let filter = ToolLaunchFilter::default();
+        let gate = BuiltinToolGate {
+            enabled_tools: &[],
+            always_on_builtin_tools: &always_on,
+            tool_filter: &filter,
+            python_execution_enabled: true,
+            sql_select_enabled: false,
+            schema_search_enabled: false,
+            has_deferred_mcp_tools: false,
+        };
+        let mut weather = tool_registry::python_execution_tool();
+        weather.name = "get_forecast".to_string();
+        let visible = vec![
+            ("builtin".to_string(), tool_registry::python_execution_tool()),
+            ("weather".to_string(), weather),
+        ];
+        let overrides = HashMap::from([
+            (
+                "builtin::python_execution".to_string(),
+                "Run Python for any arithmetic".to_string(),
+            ),
+            (
+                "weather::get_forecast".to_string(),
+                "Seven-day forecast for a city".to_string(),
+            ),
+        ]);
+
+        let mut tools = Vec::new();
+        extend_openai_tools(&mut tools, visible, &gate, &overrides);
+        assert_eq!(tools.len(), 2);
+        assert_eq!(
+            tools[0].function.description.as_deref(),
+            Some("Run Python for any arithmetic")
+        );
+        assert_eq!(tools[1].function.name, "weather___get_forecast");
+        assert_eq!(
+            tools[1].function.description.as_deref(),
+            Some("Seven-day forecast for a city")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_embedding_model_status() {
+        let state = EmbeddingModelState {
+            gpu_model: Arc::new(RwLock::new(None)),
+            cpu_model: Arc::new(RwLock::new(None)),
+            cpu_load_error: Arc::new(RwLock::new(None)),
+            unavailable_notice_sent: AtomicBool::new(false),
+        };
+        let status = state.status().await;
+        assert!(status.loading && !status.available);
+        let error = state.require_cpu_model().await.err();
+        assert!(error.is_some_and(|e| e.contains("still loading")));
+
+        *state.cpu_load_error.write().await = Some("onnxruntime missing".to_string());
+        let status = state.status().await;
+        assert!(!status.loading && !status.available);
+        assert_eq!(status.error.as_deref(), Some("onnxruntime missing"));
+        let error = state.require_cpu_model().await.err();
+        assert!(error.is_some_and(|e| e.contains("onnxruntime missing")));
+
+        assert!(is_embedding_search_tool("tool_search"));
+        assert!(is_embedding_search_tool("builtin::schema_search"));
+        assert!(!is_embedding_search_tool("sql_select"));
+    }
+
+    #[test]
+    fn test_turn_builtin_tools_drop_search_without_embeddings() {
+        let always_on = vec![
+            "tool_search".to_string(),
+            "python_execution".to_string(),
+            "schema_search".to_string(),
+        ];
+        let attached = vec!["builtin::schema_search".to_string(), "builtin::sql_select".to_string()];
+
+        let mut kept = attached.clone();
+        assert_eq!(turn_builtin_tools(&always_on, &mut kept, true), always_on);
+        assert_eq!(kept, attached);
+
+        let mut kept = attached.clone();
+        assert_eq!(
+            turn_builtin_tools(&always_on, &mut kept, false),
+            vec!["python_execution".to_string()]
+        );
+        assert_eq!(kept, vec!["builtin::sql_select".to_string()]);
+    }
 }
@@ -0,0 +1,160 @@
+//! Structured verbose logging.
+//!
+//! `log_event!` records a named event with key/value fields when verbose logging is
+//! enabled (LOG_VERBOSE / PLUGABLE_LOG_VERBOSE). Each event is printed to stdout in the
+//! usual `[Component] ...` form and, when PLUGABLE_LOG_FILE is set, appended to that
+//! file as one JSON object per line. Builds with the `structured-logging` feature also
+//! forward every event to `tracing`, printed by a fmt subscriber on stderr.
+//! `log_warn!` takes the same arguments but records failures even without verbose logging.
+
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+/// Record a structured event when verbose logging is enabled:
+/// `log_event!("AgenticLoop", "stream_progress", tokens = count, chars = len)`.
+/// Field values can be anything `serde_json::json!` accepts.
+#[macro_export]
+macro_rules! log_event {
+    ($component:expr, $event:expr $(, $key:ident = $value:expr)* $(,)?) => {
+        if $crate::is_verbose_logging_enabled() {
+            $crate::logging::emit_event(
+                $component,
+                $event,
+                serde_json::json!({ $(stringify!($key): $value),* }),
+            );
+        }
+    };
+}
+
+/// Like `log_event!`, but recorded even when verbose logging is off:
+/// for failures worth seeing in every run.
+#[macro_export]
+macro_rules! log_warn {
+    ($component:expr, $event:expr $(, $key:ident = $value:expr)* $(,)?) => {
+        $crate::logging::emit_event(
+            $component,
+            $event,
+            serde_json::json!({ $(stringify!($key): $value),* }),
+        )
+    };
+}
+
+/// File that structured events are appended to, from PLUGABLE_LOG_FILE (unset = none)
+pub fn log_file_path() -> Option<PathBuf> {
+    static LOG_FILE: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+    LOG_FILE
+        .get_or_init(|| {
+            std::env::var("PLUGABLE_LOG_FILE")
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+        })
+        .clone()
+}
+
+/// One JSON log line for an event: timestamp, component, event name, then the fields
+pub fn format_event_line(component: &str, event: &str, fields: &Value) -> String {
+    let mut line = json!({
+        "ts": chrono::Utc::now().to_rfc3339(),
+        "component": component,
+        "event": event,
+    });
+    if let (Some(line), Some(fields)) = (line.as_object_mut(), fields.as_object()) {
+        for (key, value) in fields {
+            line.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+    line.to_string()
+}
+
+/// Human-readable form of an event for stdout: `[Component] event key=value ...`
+pub fn format_event_text(component: &str, event: &str, fields: &Value) -> String {
+    let mut text = format!("[{}] {}", component, event);
+    if let Some(fields) = fields.as_object() {
+        for (key, value) in fields {
+            match value {
+                Value::String(s) => text.push_str(&format!(" {}={}", key, s)),
+                other => text.push_str(&format!(" {}={}", key, other)),
+            }
+        }
+    }
+    text
+}
+
+/// Emit an event to stdout, the log file (if configured), and `tracing` (if enabled).
+/// Prefer the `log_event!` macro, which skips all of this unless logging is verbose.
+pub fn emit_event(component: &str, event: &str, fields: Value) {
+    println!("{}", format_event_text(component, event, &fields));
+
+    if let Some(path) = log_file_path() {
+        append_line(&path, &format_event_line(component, event, &fields));
+    }
+
+    #[cfg(feature = "structured-logging")]
+    tracing::debug!(component, event, fields = %fields);
+}
+
+fn append_line(path: &PathBuf, line: &str) {
+    static FILE_LOCK: Mutex<()> = Mutex::new(());
+    let _guard = FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let result = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(e) = result {
+        eprintln!("[Logging] Failed to write {}: {}", path.display(), e);
+    }
+}
+
+/// Install the stderr `tracing` subscriber (structured-logging builds only)
+pub fn init_tracing() {
+    #[cfg(feature = "structured-logging")]
+    {
+        let level = if crate::is_verbose_logging_enabled() {
+            tracing::Level::DEBUG
+        } else {
+            tracing::Level::INFO
+        };
+        let _ = tracing_subscriber::fmt()
+            .with_max_level(level)
+            .with_writer(std::io::stderr)
+            .try_init();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_event_line() {
+        let line = format_event_line(
+            "AgenticLoop",
+            "stream_progress",
+            &json!({"tokens": 50, "event": "ignored"}),
+        );
+        let parsed: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed["component"], "AgenticLoop");
+        assert_eq!(parsed["event"], "stream_progress");
+        assert_eq!(parsed["tokens"], 50);
+        assert!(parsed["ts"].is_string());
+    }
+
+    #[test]
+    fn test_format_event_text() {
+        let text = format_event_text(
+            "SchemaSearch",
+            "low_score_skipped",
+            &json!({"table": "main.users", "score": 0.5}),
+        );
+        assert!(text.starts_with("[SchemaSearch] low_score_skipped"));
+        assert!(text.contains("table=main.users"));
+        assert!(text.contains("score=0.5"));
+    }
+}
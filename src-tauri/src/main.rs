@@ -1,6 +1,12 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+// Counts allocations made while in-process python_execution code runs, so its
+// memory ceiling can be enforced. Other threads are not counted.
+#[global_allocator]
+static ALLOCATOR: python_sandbox::memory::CountingAllocator =
+    python_sandbox::memory::CountingAllocator;
+
 fn main() {
     // Set up ONNX Runtime DLL path for Windows before any ort code runs
     #[cfg(target_os = "windows")]
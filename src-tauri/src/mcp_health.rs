@@ -0,0 +1,184 @@
+//! Background health checks for MCP servers.
+//!
+//! A server can exit or hang after it was connected, and without a check the
+//! failure only shows up when a tool call fails mid-turn. Every
+//! `mcp_health_check_interval_secs` each connected server gets a tools/list
+//! round trip; a server that stops responding is reported with an
+//! `mcp-server-unhealthy` event and reconnected if its config asks for it.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{mpsc, oneshot, RwLock};
+
+use crate::app_state::McpServerHealth;
+use crate::protocol::McpHostMsg;
+use crate::settings::{AppSettings, McpServerConfig};
+
+/// How long to wait before re-reading the interval while checks are off
+const DISABLED_POLL_SECS: u64 = 30;
+
+/// Record the outcome of a check. Returns true when the server just went
+/// from healthy (or unchecked) to unhealthy, so it is reported only once.
+pub fn record_check(
+    health: &mut HashMap<String, McpServerHealth>,
+    server_id: &str,
+    result: &Result<(), String>,
+    now_ms: u128,
+) -> bool {
+    let was_healthy = health.get(server_id).is_none_or(|h| h.healthy);
+    health.insert(
+        server_id.to_string(),
+        McpServerHealth {
+            server_id: server_id.to_string(),
+            healthy: result.is_ok(),
+            error: result.as_ref().err().cloned(),
+            last_checked_ms: now_ms,
+        },
+    );
+    was_healthy && result.is_err()
+}
+
+fn now_ms() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+async fn is_connected(mcp_host_tx: &mpsc::Sender<McpHostMsg>, server_id: &str) -> bool {
+    let (tx, rx) = oneshot::channel();
+    let sent = mcp_host_tx
+        .send(McpHostMsg::GetServerStatus {
+            server_id: server_id.to_string(),
+            respond_to: tx,
+        })
+        .await;
+    sent.is_ok() && rx.await.unwrap_or(false)
+}
+
+async fn ping(mcp_host_tx: &mpsc::Sender<McpHostMsg>, server_id: &str) -> Result<(), String> {
+    let (tx, rx) = oneshot::channel();
+    mcp_host_tx
+        .send(McpHostMsg::CheckServerHealth {
+            server_id: server_id.to_string(),
+            respond_to: tx,
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    rx.await.map_err(|_| "MCP Host actor died".to_string())?
+}
+
+/// Drop the broken connection and connect the server again
+async fn reconnect(
+    mcp_host_tx: &mpsc::Sender<McpHostMsg>,
+    config: &McpServerConfig,
+) -> Result<(), String> {
+    let (tx, rx) = oneshot::channel();
+    mcp_host_tx
+        .send(McpHostMsg::DisconnectServer {
+            server_id: config.id.clone(),
+            respond_to: tx,
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    // The process may already be gone; the connection is dropped either way
+    let _ = rx.await;
+
+    let (tx, rx) = oneshot::channel();
+    mcp_host_tx
+        .send(McpHostMsg::ConnectServer {
+            config: config.clone(),
+            respond_to: tx,
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    rx.await.map_err(|_| "MCP Host actor died".to_string())?
+}
+
+/// Check every connected, enabled server once
+async fn check_servers(
+    app_handle: &AppHandle,
+    mcp_host_tx: &mpsc::Sender<McpHostMsg>,
+    configs: &[McpServerConfig],
+    health: &RwLock<HashMap<String, McpServerHealth>>,
+) {
+    for config in configs.iter().filter(|c| c.enabled) {
+        if !is_connected(mcp_host_tx, &config.id).await {
+            continue;
+        }
+        let result = ping(mcp_host_tx, &config.id).await;
+        let newly_unhealthy =
+            record_check(&mut *health.write().await, &config.id, &result, now_ms());
+        let Err(error) = result else {
+            continue;
+        };
+
+        if newly_unhealthy {
+            println!("[McpHealth] Server {} stopped responding: {}", config.id, error);
+            let _ = app_handle.emit(
+                "mcp-server-unhealthy",
+                serde_json::json!({ "server_id": config.id, "error": error }),
+            );
+        }
+        if config.auto_reconnect {
+            match reconnect(mcp_host_tx, config).await {
+                Ok(()) => {
+                    println!("[McpHealth] Reconnected server {}", config.id);
+                    record_check(&mut *health.write().await, &config.id, &Ok(()), now_ms());
+                }
+                Err(e) => println!("[McpHealth] Reconnecting {} failed: {}", config.id, e),
+            }
+        }
+    }
+}
+
+/// Run health checks until the app exits. The interval is re-read from the
+/// settings each round, so changing it takes effect without a restart.
+pub async fn run_mcp_health_checks(
+    app_handle: AppHandle,
+    mcp_host_tx: mpsc::Sender<McpHostMsg>,
+    settings: Arc<RwLock<AppSettings>>,
+    health: Arc<RwLock<HashMap<String, McpServerHealth>>>,
+) {
+    loop {
+        let interval_secs = settings.read().await.mcp_health_check_interval_secs;
+        if interval_secs == 0 {
+            tokio::time::sleep(Duration::from_secs(DISABLED_POLL_SECS)).await;
+            continue;
+        }
+        tokio::time::sleep(Duration::from_secs(interval_secs)).await;
+
+        let configs = settings.read().await.get_all_mcp_configs();
+        check_servers(&app_handle, &mcp_host_tx, &configs, &health).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_check_reports_once() {
+        let mut health = HashMap::new();
+
+        assert!(!record_check(&mut health, "files", &Ok(()), 1));
+        assert!(health["files"].healthy);
+
+        let failure = Err("Request timed out waiting for id 7".to_string());
+        assert!(record_check(&mut health, "files", &failure, 2));
+        // Still down on the next check: not reported again
+        assert!(!record_check(&mut health, "files", &failure, 3));
+        assert_eq!(health["files"].error.as_deref(), Some("Request timed out waiting for id 7"));
+        assert_eq!(health["files"].last_checked_ms, 3);
+
+        // Recovered, then failing again is a new report
+        assert!(!record_check(&mut health, "files", &Ok(()), 4));
+        assert!(health["files"].error.is_none());
+        assert!(record_check(&mut health, "files", &failure, 5));
+
+        // A server whose first check fails is reported
+        assert!(record_check(&mut health, "search", &failure, 6));
+    }
+}
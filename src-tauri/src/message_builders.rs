@@ -3,17 +3,24 @@
 //! This module provides functions for building chat messages with tool calls
 //! and tool results in the format expected by different model families.
 
+use std::borrow::Cow;
+
+use serde_json::{json, Value};
+
 use crate::protocol::{ChatMessage, OpenAIToolCall, OpenAIToolCallFunction, ParsedToolCall};
+use crate::tool_parsing::result_formatter::tool_call_id_marker;
 
 /// Create an assistant message, optionally with native tool calls.
 ///
 /// If `use_native_format` is true and all tool calls have IDs, includes a `tool_calls`
-/// array in the message. Otherwise, returns a text-only message.
+/// array in the message. Otherwise, returns a text-only message; with `include_call_ids`
+/// the call IDs are appended to its content as `tool_call_id_marker` comments.
 pub fn create_assistant_message_with_tool_calls(
     content: &str,
     calls: &[ParsedToolCall],
     use_native_format: bool,
     system_prompt: Option<String>,
+    include_call_ids: bool,
 ) -> ChatMessage {
     if use_native_format && calls.iter().all(|c| c.id.is_some()) {
         // Native format: include tool_calls array in assistant message
@@ -40,16 +47,22 @@ pub fn create_assistant_message_with_tool_calls(
             content: content.to_string(),
             system_prompt,
             tool_calls: Some(tool_calls),
-            tool_call_id: None,
+            ..Default::default()
         }
     } else {
-        // Text-based format: content only
+        // Text-based format: content only, plus correlation markers if requested
+        let mut content = content.to_string();
+        if include_call_ids {
+            for id in calls.iter().filter_map(|c| c.id.as_deref()) {
+                content.push('\n');
+                content.push_str(&tool_call_id_marker(id));
+            }
+        }
         ChatMessage {
             role: "assistant".to_string(),
-            content: content.to_string(),
+            content,
             system_prompt,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         }
     }
 }
@@ -62,12 +75,178 @@ pub fn create_native_tool_result_message(tool_call_id: &str, content: &str) -> C
     ChatMessage {
         role: "tool".to_string(),
         content: content.to_string(),
-        system_prompt: None,
-        tool_calls: None,
         tool_call_id: Some(tool_call_id.to_string()),
+        ..Default::default()
     }
 }
 
+/// Anthropic `tool_use` block for a native tool call
+pub fn tool_use_block(call: &OpenAIToolCall) -> Value {
+    json!({
+        "type": "tool_use",
+        "id": call.id,
+        "name": call.function.name,
+        "input": serde_json::from_str::<Value>(&call.function.arguments)
+            .unwrap_or_else(|_| json!({})),
+    })
+}
+
+/// Anthropic `tool_result` block answering the `tool_use` block `tool_use_id`
+pub fn tool_result_block(tool_use_id: &str, content: &str) -> Value {
+    json!({
+        "type": "tool_result",
+        "tool_use_id": tool_use_id,
+        "content": content,
+    })
+}
+
+/// Anthropic image block for an image data URL (or a plain URL)
+fn image_block(url: &str) -> Value {
+    let base64 = url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(";base64,"));
+    match base64 {
+        Some((media_type, data)) => json!({
+            "type": "image",
+            "source": {"type": "base64", "media_type": media_type, "data": data},
+        }),
+        None => json!({"type": "image", "source": {"type": "url", "url": url}}),
+    }
+}
+
+/// Convert the history to the Anthropic messages format.
+///
+/// System messages are returned separately (the API takes a top-level `system`),
+/// assistant `tool_calls` become `tool_use` blocks, and consecutive tool results
+/// are merged into one user message of `tool_result` blocks.
+pub fn to_anthropic_messages(messages: &[ChatMessage]) -> (Option<String>, Vec<Value>) {
+    let mut system_parts: Vec<&str> = Vec::new();
+    let mut converted: Vec<Value> = Vec::new();
+
+    for msg in messages {
+        match msg.role.as_str() {
+            "system" => system_parts.push(&msg.content),
+            "tool" => {
+                let block = tool_result_block(
+                    msg.tool_call_id.as_deref().unwrap_or_default(),
+                    &msg.content,
+                );
+                // Results of one batch of calls share a single user message
+                let last_holds_results = converted.last().is_some_and(|last| {
+                    last["role"] == "user"
+                        && last["content"]
+                            .as_array()
+                            .is_some_and(|blocks| blocks.iter().all(|b| b["type"] == "tool_result"))
+                });
+                match converted
+                    .last_mut()
+                    .and_then(|last| last["content"].as_array_mut())
+                {
+                    Some(blocks) if last_holds_results => blocks.push(block),
+                    _ => converted.push(json!({"role": "user", "content": [block]})),
+                }
+            }
+            role => {
+                let mut blocks = Vec::new();
+                if !msg.content.is_empty() {
+                    blocks.push(json!({"type": "text", "text": msg.content}));
+                }
+                blocks.extend(msg.images.iter().map(|url| image_block(url)));
+                if let Some(calls) = &msg.tool_calls {
+                    blocks.extend(calls.iter().map(tool_use_block));
+                }
+                converted.push(json!({"role": role, "content": blocks}));
+            }
+        }
+    }
+
+    let system = (!system_parts.is_empty()).then(|| system_parts.join("\n\n"));
+    (system, converted)
+}
+
+/// Convert one Anthropic message back into history messages: `tool_use` blocks
+/// become the assistant message's `tool_calls`, and each `tool_result` block
+/// becomes its own `tool` message (followed by any text as a user message).
+pub fn from_anthropic_message(message: &Value) -> Vec<ChatMessage> {
+    let role = message["role"].as_str().unwrap_or("user").to_string();
+    let blocks: Vec<Value> = match &message["content"] {
+        Value::String(text) => vec![json!({"type": "text", "text": text})],
+        Value::Array(blocks) => blocks.clone(),
+        _ => Vec::new(),
+    };
+
+    let mut results = Vec::new();
+    let mut text_parts: Vec<&str> = Vec::new();
+    let mut tool_calls = Vec::new();
+    for block in &blocks {
+        match block["type"].as_str() {
+            Some("text") => text_parts.extend(block["text"].as_str()),
+            Some("tool_use") => tool_calls.push(OpenAIToolCall {
+                id: block["id"].as_str().unwrap_or_default().to_string(),
+                call_type: "function".to_string(),
+                function: OpenAIToolCallFunction {
+                    name: block["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: block["input"].to_string(),
+                },
+            }),
+            Some("tool_result") => {
+                let content = match &block["content"] {
+                    Value::String(text) => text.clone(),
+                    // Content may itself be a list of text blocks
+                    Value::Array(parts) => parts
+                        .iter()
+                        .filter_map(|p| p["text"].as_str())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    _ => String::new(),
+                };
+                let tool_use_id = block["tool_use_id"].as_str().unwrap_or_default();
+                results.push(create_native_tool_result_message(tool_use_id, &content));
+            }
+            _ => {}
+        }
+    }
+
+    if !text_parts.is_empty() || !tool_calls.is_empty() || results.is_empty() {
+        results.push(ChatMessage {
+            role,
+            content: text_parts.join("\n"),
+            tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+            ..Default::default()
+        });
+    }
+    results
+}
+
+/// Shorten a tool result that exceeds `max_chars` by cutting out its middle,
+/// keeping the head and tail around a `…[N chars omitted]…` marker.
+///
+/// A `max_chars` of 0 disables truncation.
+pub fn truncate_tool_result(result: &str, max_chars: usize) -> Cow<'_, str> {
+    let total_chars = result.chars().count();
+    if max_chars == 0 || total_chars <= max_chars {
+        return Cow::Borrowed(result);
+    }
+
+    let head_chars = max_chars / 2;
+    let tail_chars = max_chars - head_chars;
+    let byte_offset = |char_index: usize| {
+        result
+            .char_indices()
+            .nth(char_index)
+            .map_or(result.len(), |(offset, _)| offset)
+    };
+    let head_end = byte_offset(head_chars);
+    let tail_start = byte_offset(total_chars - tail_chars);
+
+    Cow::Owned(format!(
+        "{}\n…[{} chars omitted]…\n{}",
+        &result[..head_end],
+        total_chars - max_chars,
+        &result[tail_start..]
+    ))
+}
+
 /// Check if we should use native tool result format.
 ///
 /// Returns true when native tool calling is enabled AND all tool calls have IDs.
@@ -78,6 +257,27 @@ pub fn should_use_native_tool_results(
     native_tool_calling_enabled && calls.iter().all(|c| c.id.is_some())
 }
 
+/// Copy of the history to send to the model for one iteration.
+///
+/// Image attachments stay in the history itself, but only the latest user
+/// message's images are sent, and only when `send_images` is set (the first
+/// request of a turn, or the one right after tools returned images); other
+/// iterations send text only.
+pub fn messages_for_request(history: &[ChatMessage], send_images: bool) -> Vec<ChatMessage> {
+    let latest_user = history.iter().rposition(|m| m.role == "user");
+    history
+        .iter()
+        .enumerate()
+        .map(|(idx, msg)| {
+            let mut msg = msg.clone();
+            if !(send_images && Some(idx) == latest_user) {
+                msg.images.clear();
+            }
+            msg
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,6 +298,7 @@ mod tests {
             &calls,
             true,
             None,
+            false,
         );
 
         assert_eq!(msg.role, "assistant");
@@ -123,12 +324,33 @@ mod tests {
             &calls,
             true, // Even with native enabled, no IDs = text format
             None,
+            false,
         );
 
         assert_eq!(msg.role, "assistant");
         assert!(msg.tool_calls.is_none());
     }
 
+    #[test]
+    fn test_create_assistant_message_text_with_call_ids() {
+        let calls = vec![ParsedToolCall {
+            server: "builtin".to_string(),
+            tool: "sql_select".to_string(),
+            arguments: json!({"sql": "SELECT 1"}),
+            raw: "".to_string(),
+            id: Some("call_ab12cd34".to_string()),
+        }];
+
+        let msg =
+            create_assistant_message_with_tool_calls("Let me query that", &calls, false, None, true);
+        assert!(msg.tool_calls.is_none());
+        assert_eq!(msg.content, "Let me query that\n<!-- tool_call_id: call_ab12cd34 -->");
+
+        let plain =
+            create_assistant_message_with_tool_calls("Let me query that", &calls, false, None, false);
+        assert_eq!(plain.content, "Let me query that");
+    }
+
     #[test]
     fn test_create_native_tool_result_message() {
         let msg = create_native_tool_result_message("call_123", "Query returned 5 rows");
@@ -138,6 +360,91 @@ mod tests {
         assert_eq!(msg.tool_call_id, Some("call_123".to_string()));
     }
 
+    #[test]
+    fn test_anthropic_tool_use_round_trip() {
+        let block = json!({
+            "type": "tool_use",
+            "id": "toolu_01",
+            "name": "weather___get_forecast",
+            "input": {"city": "Oslo", "days": 3}
+        });
+        let response = json!([{"type": "text", "text": "Checking."}, block]).to_string();
+
+        // Parsed from the response, stored in the history, serialized back to blocks
+        let calls = crate::tool_parsing::anthropic_parser::parse_anthropic_tool_calls(&response);
+        let msg = create_assistant_message_with_tool_calls("Checking.", &calls, true, None, false);
+        let (system, messages) = to_anthropic_messages(&[msg.clone()]);
+        assert!(system.is_none());
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["role"], "assistant");
+        assert_eq!(messages[0]["content"][0], json!({"type": "text", "text": "Checking."}));
+        assert_eq!(messages[0]["content"][1], block);
+
+        let restored = from_anthropic_message(&messages[0]);
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].content, msg.content);
+        let restored_calls = restored[0].tool_calls.as_ref().unwrap();
+        assert_eq!(restored_calls[0].id, "toolu_01");
+        assert_eq!(restored_calls[0].function.name, "weather___get_forecast");
+    }
+
+    #[test]
+    fn test_anthropic_tool_result_round_trip() {
+        let history = vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: "Be brief.".to_string(),
+                ..Default::default()
+            },
+            create_native_tool_result_message("toolu_01", "Sunny, 21C"),
+            create_native_tool_result_message("toolu_02", "Rain, 12C"),
+        ];
+
+        let (system, messages) = to_anthropic_messages(&history);
+        assert_eq!(system.as_deref(), Some("Be brief."));
+        // Both results go back in one user message
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["role"], "user");
+        assert_eq!(
+            messages[0]["content"][0],
+            json!({"type": "tool_result", "tool_use_id": "toolu_01", "content": "Sunny, 21C"})
+        );
+
+        let restored = from_anthropic_message(&messages[0]);
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored[0].role, "tool");
+        assert_eq!(restored[0].tool_call_id.as_deref(), Some("toolu_01"));
+        assert_eq!(restored[0].content, "Sunny, 21C");
+        assert_eq!(restored[1].tool_call_id.as_deref(), Some("toolu_02"));
+        assert_eq!(restored[1].content, "Rain, 12C");
+    }
+
+    #[test]
+    fn test_truncate_tool_result_within_budget() {
+        assert_eq!(truncate_tool_result("short result", 100), "short result");
+        assert_eq!(truncate_tool_result("unlimited", 0), "unlimited");
+    }
+
+    #[test]
+    fn test_truncate_tool_result_keeps_head_and_tail() {
+        let result = format!("HEAD{}TAIL", "x".repeat(1000));
+        let truncated = truncate_tool_result(&result, 20);
+
+        assert!(truncated.starts_with("HEADxxxxxx"));
+        assert!(truncated.ends_with("xxxxxxTAIL"));
+        assert!(truncated.contains("…[988 chars omitted]…"));
+    }
+
+    #[test]
+    fn test_truncate_tool_result_respects_char_boundaries() {
+        let result = "é".repeat(50);
+        let truncated = truncate_tool_result(&result, 10);
+
+        assert!(truncated.starts_with("ééééé\n"));
+        assert!(truncated.ends_with("\nééééé"));
+        assert!(truncated.contains("[40 chars omitted]"));
+    }
+
     #[test]
     fn test_should_use_native_tool_results() {
         let calls_with_ids = vec![ParsedToolCall {
@@ -160,4 +467,28 @@ mod tests {
         assert!(!should_use_native_tool_results(true, &calls_without_ids));
         assert!(!should_use_native_tool_results(false, &calls_with_ids));
     }
+
+    #[test]
+    fn test_messages_for_request_sends_images_once() {
+        let user = |content: &str, images: Vec<String>| ChatMessage {
+            role: "user".to_string(),
+            content: content.to_string(),
+            images,
+            ..Default::default()
+        };
+        let history = vec![
+            user("earlier", vec!["data:image/png;base64,AAAA".to_string()]),
+            create_assistant_message_with_tool_calls("ok", &[], false, None, false),
+            user("what is this?", vec!["data:image/png;base64,BBBB".to_string()]),
+        ];
+
+        let first = messages_for_request(&history, true);
+        assert!(first[0].images.is_empty());
+        assert_eq!(first[2].images, vec!["data:image/png;base64,BBBB"]);
+
+        let later = messages_for_request(&history, false);
+        assert!(later.iter().all(|m| m.images.is_empty()));
+        // The history itself keeps its attachments
+        assert_eq!(history[2].images.len(), 1);
+    }
 }
@@ -64,7 +64,9 @@ impl ModelProfile {
         }
 
         match self.model_family {
-            ModelFamily::GptOss => self.build_prompt_openai_style(history, tools, options),
+            ModelFamily::GptOss | ModelFamily::Qwen => {
+                self.build_prompt_openai_style(history, tools, options)
+            }
             ModelFamily::Phi => self.build_prompt_phi(history, tools, options),
             ModelFamily::Granite => self.build_prompt_granite(history, tools, options),
             ModelFamily::Gemma => self.build_prompt_gemma_like(history, tools, options),
@@ -82,6 +84,9 @@ impl ModelProfile {
             ToolFormat::Hermes => parse_hermes_tool_calls(output),
             ToolFormat::Granite => parse_granite_tool_calls(output),
             ToolFormat::Gemini => parse_gemini_tool_calls(output),
+            ToolFormat::Anthropic => {
+                crate::tool_parsing::anthropic_parser::parse_anthropic_tool_calls(output)
+            }
             ToolFormat::Harmony => {
                 // gpt-oss harmony format: <|channel|>commentary to=tool_name...<|message|>args<|call|>
                 crate::tool_parsing::parse_harmony_tool_calls(output)
@@ -118,9 +123,7 @@ impl ModelProfile {
         messages.push(ChatMessage {
             role: "system".to_string(),
             content: system_content,
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         });
 
         // Add history (skip existing system messages)
@@ -202,7 +205,7 @@ impl ModelProfile {
 - Multi-step logic with conditionals
 
 **IMPORTANT: You must `import` modules before using them.**
-Allowed imports: math, json, random, re, datetime, collections, itertools, functools, statistics, decimal, fractions, hashlib, base64, operator, string, textwrap, copy, types, typing, abc, numbers, binascii, html.
+Allowed imports: math, json, random, re, datetime, data, collections, itertools, functools, statistics, decimal, fractions, hashlib, base64, operator, string, textwrap, copy, types, typing, abc, numbers, binascii, html.
 Not available: pandas, numpy, requests, or any external packages.
 
 **Example:**
@@ -244,9 +247,7 @@ Use `tool_search` to discover MCP tools, which then become available as async Py
         messages.push(ChatMessage {
             role: "system".to_string(),
             content: system_content,
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         });
 
         // Add history (skip existing system messages)
@@ -346,9 +347,7 @@ Use `tool_search` to discover MCP tools, which then become available as async Py
         messages.push(ChatMessage {
             role: "system".to_string(),
             content: system_content,
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         });
 
         for msg in history {
@@ -427,9 +426,7 @@ Use `tool_search` to discover MCP tools, which then become available as async Py
         messages.push(ChatMessage {
             role: "system".to_string(),
             content: system_content,
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         });
 
         for msg in history {
@@ -507,9 +504,7 @@ Use `tool_search` to discover MCP tools, which then become available as async Py
         messages.push(ChatMessage {
             role: "system".to_string(),
             content: system_content,
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         });
 
         for msg in history {
@@ -674,7 +669,7 @@ Use `tool_search` to discover MCP tools, which then become available as async Py
             "You must return exactly one runnable Python program when tools are available.\n\n",
         );
         prompt.push_str("Do NOT emit <tool_call> tags or JSON tool calls. All tool use happens inside that Python program. We will execute it and show any print output to the user.\n\n");
-        prompt.push_str("Allowed imports: math, json, random, re, datetime, collections, itertools, functools, operator, string, textwrap, copy, types, typing, abc, numbers, decimal, fractions, statistics, hashlib, base64, binascii, html.\n");
+        prompt.push_str("Allowed imports: math, json, random, re, datetime, data, collections, itertools, functools, operator, string, textwrap, copy, types, typing, abc, numbers, decimal, fractions, statistics, hashlib, base64, binascii, html.\n");
         prompt.push_str("To discover external tools, call the global function tool_search(relevant_to=\"...\") from your Python code, then call the returned functions directly.\n\n");
 
         if !tools.is_empty() {
@@ -705,11 +700,18 @@ lazy_static::lazy_static! {
             ModelFamily::GptOss,
             ToolFormat::Harmony,
         ),
-        // OpenAI-style models (Qwen, LLaMA-Instruct, Mistral) - use Hermes format
+        // Qwen models (Qwen2, Qwen2.5, Qwen3) - JSON in <tool_call> tags (Hermes format)
+        ModelProfile::new(
+            "qwen",
+            r"qwen",
+            ModelFamily::Qwen,
+            ToolFormat::Hermes,
+        ),
+        // OpenAI-style models (LLaMA-Instruct, Mistral) - use Hermes format
         // Note: gpt-oss removed - now uses harmony format above
         ModelProfile::new(
             "openai_style",
-            r"qwen|llama.*instruct|mistral.*instruct",
+            r"llama.*instruct|mistral.*instruct",
             ModelFamily::GptOss,
             ToolFormat::Hermes,
         ),
@@ -773,6 +775,10 @@ mod tests {
     fn test_profile_matching() {
         // Test Qwen matching
         let profile = resolve_profile("Qwen2.5-32B-Instruct");
+        assert_eq!(profile.id, "qwen");
+
+        // Test LLaMA-Instruct matching
+        let profile = resolve_profile("Llama-3.2-3B-Instruct");
         assert_eq!(profile.id, "openai_style");
 
         // Test Granite matching
@@ -792,6 +798,45 @@ mod tests {
         assert_eq!(profile.id, "default");
     }
 
+    #[test]
+    fn test_qwen_models_use_qwen_profile() {
+        for name in ["qwen-7b-chat", "Qwen2-7B-Instruct", "qwen2.5-coder-7b", "Qwen3-8B"] {
+            let profile = resolve_profile(name);
+            assert_eq!(profile.id, "qwen", "{}", name);
+            assert_eq!(profile.model_family, ModelFamily::Qwen);
+            assert_eq!(profile.tool_call_format, ToolFormat::Hermes);
+        }
+        assert_eq!(ModelFamily::from_model_id("Qwen3-8B"), ModelFamily::Qwen);
+    }
+
+    #[test]
+    fn test_qwen_tool_call_parses() {
+        let profile = resolve_profile("Qwen3-8B");
+        let output = "<think>\nI should look up the weather.\n</think>\n\n<tool_call>\n\
+            {\"name\": \"weather___get_forecast\", \"arguments\": {\"city\": \"Seattle\"}}\n\
+            </tool_call>";
+
+        let calls = profile.parse_tool_calls(output);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].server, "weather");
+        assert_eq!(calls[0].tool, "get_forecast");
+        assert_eq!(calls[0].arguments, json!({"city": "Seattle"}));
+
+        let formats = crate::settings::ToolCallFormatConfig::default();
+        let calls = crate::tool_parsing::parse_tool_calls_for_model_profile(
+            output,
+            profile.model_family,
+            profile.tool_call_format,
+            &formats,
+            crate::system_prompt::resolve_effective_format(
+                formats.primary,
+                Some(profile.tool_call_format),
+            ),
+        );
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].tool, "get_forecast");
+    }
+
     #[test]
     fn test_gpt_oss_uses_harmony_profile() {
         // gpt-oss models should match the harmony profile, NOT openai_style
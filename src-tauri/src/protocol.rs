@@ -6,6 +6,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::oneshot;
 use crate::settings::ChatFormatName;
+use crate::tool_parsing::json_fixer::{repair_json_arguments, repair_string_arguments};
 
 // ============ Tool Schema with Code Mode Extensions ============
 
@@ -29,6 +30,9 @@ pub struct ToolSchema {
     /// Whether this tool should be deferred (not shown initially, discovered via tool_search)
     #[serde(default)]
     pub defer_loading: bool,
+    /// Whether the result can be consumed in chunks from python_execution
+    #[serde(default)]
+    pub streamable: bool,
     /// Precomputed embedding for semantic tool search
     #[serde(skip)]
     pub embedding: Option<Vec<f32>>,
@@ -45,6 +49,7 @@ impl ToolSchema {
             tool_type: None,
             allowed_callers: None,
             defer_loading: false,
+            streamable: false,
             embedding: None,
         }
     }
@@ -212,6 +217,53 @@ pub struct ToolHeartbeatEvent {
     pub elapsed_ms: u64,
     /// Monotonic heartbeat counter (1,2,3,...)
     pub beat: u64,
+    /// Last line the tool printed so far (python_execution stdout), if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<String>,
+}
+
+/// Event payload carrying stdout printed by a running python_execution call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PythonStdoutChunkEvent {
+    pub exec_id: String,
+    pub chunk: String,
+}
+
+/// Event payload when an MCP tool call is abandoned after its timeout
+/// (followed by a `tool-result` carrying the error)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolTimeoutEvent {
+    pub server: String,
+    pub tool: String,
+    pub timeout_secs: u64,
+}
+
+/// Event payload when a chat request failed to start and is about to be retried
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatRetryEvent {
+    /// The attempt about to be made (the first retry is attempt 2)
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay_ms: u64,
+    pub reason: String,
+}
+
+/// Event payload when a completed model stream produced no tokens at all
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmptyResponseEvent {
+    pub iteration: usize,
+    /// Whether the request is sent once more; otherwise a placeholder answer is saved
+    pub will_retry: bool,
+    /// Last known model state, for diagnosing crashes and context overflows
+    pub model_state: Option<ModelState>,
+}
+
+/// Event payload when an in-flight tool call is dropped because the user
+/// stopped generation (no `tool-result` follows)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCancelledEvent {
+    pub server: String,
+    pub tool: String,
 }
 
 /// Event payload when a tool finishes executing
@@ -221,6 +273,15 @@ pub struct ToolResultEvent {
     pub tool: String,
     pub result: String,
     pub is_error: bool,
+    /// Full length in chars when the result was truncated before reaching the model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_length: Option<usize>,
+    /// Built-in tool whose code made this call (e.g. "python_execution")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caller: Option<String>,
+    /// Arguments of a call made by a `caller`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<serde_json::Value>,
 }
 
 /// Event payload when the agentic loop completes
@@ -230,6 +291,43 @@ pub struct ToolLoopFinishedEvent {
     pub had_tool_calls: bool,
 }
 
+/// Timing for one model call within a turn
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IterationMetrics {
+    /// Tokens streamed by the model
+    pub tokens: usize,
+    /// Time to first token in milliseconds (None if no token arrived)
+    pub ttft_ms: Option<u64>,
+    /// Time from request to end of stream in milliseconds
+    pub duration_ms: u64,
+}
+
+/// Cost/latency summary of a turn, emitted as `turn-metrics` when the loop ends
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TurnMetrics {
+    /// Model calls made during the turn
+    pub iterations: usize,
+    pub iteration_metrics: Vec<IterationMetrics>,
+    /// Duration in milliseconds of each tool call, keyed by `server::tool`
+    pub tool_durations_ms: HashMap<String, Vec<u64>>,
+    /// Wall time of the whole turn in milliseconds
+    pub total_ms: u64,
+}
+
+impl TurnMetrics {
+    pub fn record_iteration(&mut self, iteration: IterationMetrics) {
+        self.iteration_metrics.push(iteration);
+        self.iterations = self.iteration_metrics.len();
+    }
+
+    pub fn record_tool(&mut self, server: &str, tool: &str, duration: std::time::Duration) {
+        self.tool_durations_ms
+            .entry(format!("{}::{}", server, tool))
+            .or_default()
+            .push(duration.as_millis() as u64);
+    }
+}
+
 /// Parse tool calls from assistant response
 /// Supports two formats:
 /// 1. Text-based: <tool_call>{"server": "...", "tool": "...", "arguments": {...}}</tool_call>
@@ -256,8 +354,10 @@ pub fn parse_tool_calls(content: &str) -> Vec<ParsedToolCall> {
 
             // Try to fix common JSON issues from LLMs
             let fixed_json = fix_llm_json(json_str);
+            let parsed = serde_json::from_str::<serde_json::Value>(&fixed_json)
+                .or_else(|e| repair_json_arguments(json_str).ok_or(e));
 
-            match serde_json::from_str::<serde_json::Value>(&fixed_json) {
+            match parsed {
                 Ok(parsed) => {
                     let raw = cap
                         .get(0)
@@ -272,6 +372,7 @@ pub fn parse_tool_calls(content: &str) -> Vec<ParsedToolCall> {
                         let arguments = parsed
                             .get("arguments")
                             .cloned()
+                            .map(repair_string_arguments)
                             .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
 
                         println!(
@@ -295,6 +396,7 @@ pub fn parse_tool_calls(content: &str) -> Vec<ParsedToolCall> {
                         let arguments = parsed
                             .get("arguments")
                             .cloned()
+                            .map(repair_string_arguments)
                             .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
 
                         // Parse server___tool format if present
@@ -376,6 +478,7 @@ pub fn parse_tool_calls(content: &str) -> Vec<ParsedToolCall> {
                                 let arguments = parsed
                                     .get("arguments")
                                     .cloned()
+                                    .map(repair_string_arguments)
                                     .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
 
                                 let (server, tool) =
@@ -631,6 +734,9 @@ pub enum ModelFamily {
     Gemma,
     /// Microsoft Phi models - use <think> tags for reasoning variants
     Phi,
+    /// Alibaba Qwen models (Qwen2, Qwen2.5, Qwen3) - Hermes-style <tool_call> JSON,
+    /// <think> tags for Qwen3 reasoning
+    Qwen,
     /// IBM Granite models - use <|thinking|> tags for reasoning
     Granite,
     /// Generic/unknown models - standard OpenAI-compatible format
@@ -643,8 +749,10 @@ impl ModelFamily {
     pub fn from_model_id(model_id: &str) -> Self {
         let lower = model_id.to_lowercase();
 
-        // Qwen, Mistral, LLaMA-Instruct models use OpenAI-compatible tool calling
-        if lower.contains("qwen") || lower.contains("mistral") || lower.contains("llama") {
+        if lower.contains("qwen") {
+            ModelFamily::Qwen
+        } else if lower.contains("mistral") || lower.contains("llama") {
+            // Mistral, LLaMA-Instruct models use OpenAI-compatible tool calling
             ModelFamily::GptOss
         } else if lower.contains("gpt-oss") {
             ModelFamily::GptOss
@@ -678,6 +786,9 @@ pub enum ToolFormat {
     /// OpenAI Harmony format for gpt-oss models
     /// Uses <|channel|>commentary to=tool_name<|message|>args<|call|> format
     Harmony,
+    /// Anthropic messages format: `tool_use` content blocks in the response,
+    /// `tool_result` blocks in a user message for results
+    Anthropic,
 }
 
 /// Reasoning/thinking output format
@@ -908,7 +1019,7 @@ pub struct ChatSummary {
     pub model: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ChatMessage {
     pub role: String,
     pub content: String,
@@ -922,6 +1033,20 @@ pub struct ChatMessage {
     /// Present when role="tool" to reference the original tool call
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>,
+    /// Images attached to a user message, as data URLs (sent to vision models only)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+    /// Set on an assistant message whose generation the user stopped partway
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+    /// Set on the assistant message of a chat auto-saved while its turn was
+    /// still running; the final save replaces it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub provisional: bool,
+    /// Set on a stored message of a turn's tool traffic (tool calls, tool
+    /// results and retry prompts); kept as history but not shown in the chat
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub intermediate: bool,
 }
 
 pub enum VectorMsg {
@@ -967,6 +1092,31 @@ pub enum VectorMsg {
     },
 }
 
+/// A streaming chat request to the model (boxed in `FoundryMsg::Chat`)
+pub struct ChatRequest {
+    pub model: String,
+    pub chat_history_messages: Vec<ChatMessage>,
+    pub reasoning_effort: String,
+    /// Optional OpenAI-format tools for native tool calling
+    pub native_tool_specs: Option<Vec<OpenAITool>>,
+    /// Whether to use native tool calling (when model supports it)
+    pub native_tool_calling_enabled: bool,
+    /// Chat API format selection (per-model overrides resolved in actor)
+    pub chat_format_default: ChatFormatName,
+    pub chat_format_overrides: HashMap<String, ChatFormatName>,
+    pub respond_to: tokio::sync::mpsc::UnboundedSender<String>,
+    /// Cancellation signal - when true, abort the stream
+    pub stream_cancel_rx: tokio::sync::watch::Receiver<bool>,
+    /// JSON schema the response must follow, sent as a structured-output constraint
+    pub response_schema: Option<serde_json::Value>,
+    /// Sampling temperature override (applied only if the model supports temperature)
+    pub temperature: Option<f32>,
+    /// Sampling seed for reproducible output (applied only if the model supports temperature)
+    pub seed: Option<u64>,
+    /// Stop sequences for the request (empty = none)
+    pub stop_sequences: Vec<String>,
+}
+
 pub enum FoundryMsg {
     /// Generate an embedding for a string.
     ///
@@ -995,21 +1145,7 @@ pub enum FoundryMsg {
         respond_to: oneshot::Sender<Result<Arc<TextEmbedding>, String>>,
     },
     /// Chat with the model (streaming)
-    Chat {
-        model: String,
-        chat_history_messages: Vec<ChatMessage>,
-        reasoning_effort: String,
-        /// Optional OpenAI-format tools for native tool calling
-        native_tool_specs: Option<Vec<OpenAITool>>,
-        /// Whether to use native tool calling (when model supports it)
-        native_tool_calling_enabled: bool,
-        /// Chat API format selection (per-model overrides resolved in actor)
-        chat_format_default: ChatFormatName,
-        chat_format_overrides: HashMap<String, ChatFormatName>,
-        respond_to: tokio::sync::mpsc::UnboundedSender<String>,
-        /// Cancellation signal - when true, abort the stream
-        stream_cancel_rx: tokio::sync::watch::Receiver<bool>,
-    },
+    Chat(Box<ChatRequest>),
     /// Get available models from running service
     GetModels {
         respond_to: oneshot::Sender<Vec<String>>,
@@ -1426,6 +1562,18 @@ pub enum McpHostMsg {
         server_id: String,
         tool_name: String,
         arguments: serde_json::Value,
+        /// How long to wait for the result (None = no limit); see `settings::mcp_tool_timeout`
+        timeout: Option<std::time::Duration>,
+        respond_to: oneshot::Sender<Result<McpToolResult, String>>,
+    },
+    /// Execute a streamable tool, sending each chunk to `chunk_tx` as the
+    /// server reports it, then the final result to `respond_to`
+    ExecuteToolStream {
+        server_id: String,
+        tool_name: String,
+        arguments: serde_json::Value,
+        timeout: Option<std::time::Duration>,
+        chunk_tx: tokio::sync::mpsc::UnboundedSender<String>,
         respond_to: oneshot::Sender<Result<McpToolResult, String>>,
     },
     /// Get all tool descriptions from enabled servers (for system prompt)
@@ -1437,6 +1585,11 @@ pub enum McpHostMsg {
         server_id: String,
         respond_to: oneshot::Sender<bool>,
     },
+    /// Ping a connected server with a tools/list request
+    CheckServerHealth {
+        server_id: String,
+        respond_to: oneshot::Sender<Result<(), String>>,
+    },
     /// Sync enabled servers - connect enabled ones, disconnect disabled ones
     SyncEnabledServers {
         configs: Vec<McpServerConfig>,
@@ -43,6 +43,8 @@ pub fn parse_python_execution_args(arguments: &serde_json::Value) -> CodeExecuti
             return CodeExecutionInput {
                 code: fixed_code,
                 context: None,
+                timeout_ms: None,
+                validate_only: false,
             };
         }
     }
@@ -63,6 +65,8 @@ pub fn parse_python_execution_args(arguments: &serde_json::Value) -> CodeExecuti
                 return CodeExecutionInput {
                     code: fixed_code,
                     context: None,
+                    timeout_ms: None,
+                    validate_only: false,
                 };
             }
         } else if let Ok(mut input) = serde_json::from_value::<CodeExecutionInput>(inner.clone()) {
@@ -92,6 +96,8 @@ pub fn parse_python_execution_args(arguments: &serde_json::Value) -> CodeExecuti
     CodeExecutionInput {
         code: vec![],
         context: None,
+        timeout_ms: None,
+        validate_only: false,
     }
 }
 
@@ -109,14 +115,23 @@ pub fn parse_python_execution_args(arguments: &serde_json::Value) -> CodeExecuti
 ///       return x
 ///   result = foo()  # This should stay at level 0, not get indented!
 ///
+/// Existing indentation is never re-measured, so code indented with 2 spaces
+/// or tabs keeps its own unit. Indentation added to unindented code uses the
+/// unit of its first continuation line (a tab or 2 spaces), else 4 spaces.
+///
+/// Continuation lines (inside an unclosed `(`, `[` or `{`, or after a trailing
+/// `\`) are left verbatim and don't count as existing indentation; the
+/// statement they belong to is classified as a whole once it is complete.
+///
 /// This is a best-effort fix and may not handle all edge cases perfectly.
 pub fn fix_python_indentation(lines: &[String]) -> Vec<String> {
+    let continuation = continuation_lines(lines);
+
     // First, check if the input already has meaningful indentation
-    // If ANY line has existing indentation, we should preserve the structure
+    // If ANY statement line has existing indentation, we should preserve the structure
     // and NOT add indentation to lines that don't have it
-    let has_existing_indentation = lines.iter().any(|line| {
-        let trimmed = line.trim();
-        !trimmed.is_empty() && line.starts_with(' ') || line.starts_with('\t')
+    let has_existing_indentation = lines.iter().zip(&continuation).any(|(line, continued)| {
+        !continued && !line.trim().is_empty() && (line.starts_with(' ') || line.starts_with('\t'))
     });
 
     if has_existing_indentation {
@@ -141,44 +156,61 @@ pub fn fix_python_indentation(lines: &[String]) -> Vec<String> {
 
     let mut result = Vec::with_capacity(lines.len());
     let mut indent_stack: Vec<usize> = vec![0]; // Stack of indent levels
-    let indent_str = "    "; // 4 spaces
+    let indent_str = detect_indent_unit(lines);
+    // The statement being built, which may span several lines
+    let mut statement = String::new();
+    let mut statement_indent = 0;
 
     for (i, line) in lines.iter().enumerate() {
         let trimmed = line.trim();
 
-        // Skip empty lines
-        if trimmed.is_empty() {
-            result.push(String::new());
-            continue;
-        }
+        if continuation[i] {
+            // Inside brackets or after `\`: keep the line exactly as written
+            result.push(line.clone());
+            statement.push(' ');
+            statement.push_str(trimmed);
+        } else {
+            // Skip empty lines
+            if trimmed.is_empty() {
+                result.push(String::new());
+                continue;
+            }
 
-        // Get current indent level
-        let current_indent = *indent_stack.last().unwrap_or(&0);
+            // Get current indent level
+            let current_indent = *indent_stack.last().unwrap_or(&0);
 
-        // Check if this line should be at reduced indent (else, elif, except, finally)
-        let line_indent = if dedent_before.is_match(trimmed) {
-            // Pop one level for else/elif/except/finally
-            if indent_stack.len() > 1 {
-                indent_stack.pop();
-            }
-            *indent_stack.last().unwrap_or(&0)
-        } else {
-            current_indent
-        };
+            // Check if this line should be at reduced indent (else, elif, except, finally)
+            let line_indent = if dedent_before.is_match(trimmed) {
+                // Pop one level for else/elif/except/finally
+                if indent_stack.len() > 1 {
+                    indent_stack.pop();
+                }
+                *indent_stack.last().unwrap_or(&0)
+            } else {
+                current_indent
+            };
 
-        // Apply indentation
-        let indented_line = if line_indent > 0 {
-            format!("{}{}", indent_str.repeat(line_indent), trimmed)
-        } else {
-            trimmed.to_string()
-        };
+            // Apply indentation
+            let indented_line = if line_indent > 0 {
+                format!("{}{}", indent_str.repeat(line_indent), trimmed)
+            } else {
+                trimmed.to_string()
+            };
 
-        result.push(indented_line);
+            result.push(indented_line);
+            statement = trimmed.to_string();
+            statement_indent = line_indent;
+        }
 
-        // Check if next line needs more indent (this line starts a block)
-        if block_starters.is_match(trimmed) {
-            indent_stack.push(line_indent + 1);
-        } else if block_enders.is_match(trimmed) {
+        // Wait for the rest of a multi-line statement before classifying it
+        if continuation.get(i + 1).copied().unwrap_or(false) {
+            continue;
+        }
+
+        // Check if next line needs more indent (this statement starts a block)
+        if block_starters.is_match(&statement) {
+            indent_stack.push(statement_indent + 1);
+        } else if block_enders.is_match(&statement) {
             // After return/break/continue/pass/raise, next line might be less indented
             // But only pop if we're not at top level and there's a next line
             if indent_stack.len() > 1 && i + 1 < lines.len() {
@@ -200,44 +232,254 @@ pub fn fix_python_indentation(lines: &[String]) -> Vec<String> {
     result
 }
 
-/// Strip unsupported Python keywords/patterns that cause RustPython compilation errors.
+/// Indent unit of code whose only indented lines are continuation lines, taken
+/// from the first of them: a tab, 2 spaces if it's indented by 2 (or 6, ...),
+/// else 4 spaces
+fn detect_indent_unit(lines: &[String]) -> &'static str {
+    let first_indent = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| &l[..l.len() - l.trim_start().len()])
+        .find(|indent| !indent.is_empty());
+    match first_indent {
+        Some(indent) if indent.starts_with('\t') => "\t",
+        Some(indent) if indent.len() % 4 == 2 => "  ",
+        _ => "    ",
+    }
+}
+
+/// Mark lines that continue the previous line's statement: lines inside an
+/// unclosed `(`, `[` or `{`, and lines after one ending in `\`.
 ///
-/// Keywords removed:
-/// - `await` - RustPython sandbox doesn't run in async context
+/// Brackets inside string literals and comments are ignored. Strings are
+/// assumed to end on the line they start (triple-quoted blocks are not tracked).
+fn continuation_lines(lines: &[String]) -> Vec<bool> {
+    let mut flags = Vec::with_capacity(lines.len());
+    let mut depth: usize = 0;
+    let mut backslash = false;
+
+    for line in lines {
+        flags.push(depth > 0 || backslash);
+
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+        for c in line.chars() {
+            if let Some(q) = quote {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+                continue;
+            }
+            match c {
+                '#' => break,
+                '\'' | '"' => quote = Some(c),
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        backslash = line.trim_end().ends_with('\\');
+    }
+
+    flags
+}
+
+/// A syntax rewrite applied to model code before it reaches the sandbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PythonTransform {
+    /// `await expr` -> `expr` (the sandbox doesn't run in an async context)
+    StripAwait,
+    /// `async def` / `async for` / `async with` -> plain `def` / `for` / `with`,
+    /// so coroutine bodies actually run when called
+    StripAsyncQualifier,
+    /// PEP 695 generics: `def f[T](...)` / `class C[T]:` lose their type
+    /// parameter list and `type X = ...` becomes `X = ...`. RustPython 0.4
+    /// parses these, so this is opt-in for parsers that don't.
+    StripPep695Generics,
+}
+
+/// Transforms applied by `strip_unsupported_python`
+pub const DEFAULT_PYTHON_TRANSFORMS: &[PythonTransform] = &[
+    PythonTransform::StripAwait,
+    PythonTransform::StripAsyncQualifier,
+];
+
+impl PythonTransform {
+    /// What gets stripped, for logging
+    fn description(self) -> &'static str {
+        match self {
+            PythonTransform::StripAwait => "`await` keyword(s) (not needed in sandbox)",
+            PythonTransform::StripAsyncQualifier => {
+                "`async` qualifier(s) (sandbox runs code synchronously)"
+            }
+            PythonTransform::StripPep695Generics => "PEP 695 type parameter list(s)",
+        }
+    }
+
+    /// Apply to one code segment. `line_start` is true when the segment begins
+    /// the line. Returns the rewritten text and the number of rewrites.
+    fn apply(self, segment: &str, line_start: bool) -> (String, usize) {
+        lazy_static::lazy_static! {
+            static ref AWAIT_RE: Regex = Regex::new(r"\bawait\s+").unwrap();
+            static ref ASYNC_RE: Regex = Regex::new(r"\basync\s+(def|for|with)\b").unwrap();
+            // One level of nested brackets covers bounds like `[T: list[int]]`
+            static ref GENERIC_DEF_RE: Regex = Regex::new(
+                r"\b((?:def|class)\s+[A-Za-z_]\w*)\s*\[(?:[^\[\]]|\[[^\[\]]*\])*\]"
+            )
+            .unwrap();
+            static ref TYPE_ALIAS_RE: Regex = Regex::new(
+                r"^(\s*)type\s+([A-Za-z_]\w*)\s*(?:\[(?:[^\[\]]|\[[^\[\]]*\])*\])?\s*="
+            )
+            .unwrap();
+        }
+
+        match self {
+            PythonTransform::StripAwait => replace_counted(&AWAIT_RE, segment, ""),
+            PythonTransform::StripAsyncQualifier => replace_counted(&ASYNC_RE, segment, "$1"),
+            PythonTransform::StripPep695Generics => {
+                let (text, generics) = replace_counted(&GENERIC_DEF_RE, segment, "$1");
+                if !line_start {
+                    return (text, generics);
+                }
+                let (text, aliases) = replace_counted(&TYPE_ALIAS_RE, &text, "$1$2 =");
+                (text, generics + aliases)
+            }
+        }
+    }
+}
+
+fn replace_counted(re: &Regex, text: &str, replacement: &str) -> (String, usize) {
+    let count = re.find_iter(text).count();
+    if count == 0 {
+        return (text.to_string(), 0);
+    }
+    (re.replace_all(text, replacement).into_owned(), count)
+}
+
+/// Split a line into `(is_code, text)` segments, separating string literals and
+/// comments from code. `open_string` carries a triple-quoted string across lines.
+fn split_code_segments<'a>(
+    line: &'a str,
+    open_string: &mut Option<&'static str>,
+) -> Vec<(bool, &'a str)> {
+    let bytes = line.as_bytes();
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if let Some(delim) = *open_string {
+            // Inside a string: find its closing delimiter, honoring escapes
+            let mut j = i;
+            let mut closed_at = None;
+            while j < bytes.len() {
+                if bytes[j] == b'\\' {
+                    j += 2;
+                } else if bytes[j..].starts_with(delim.as_bytes()) {
+                    closed_at = Some(j + delim.len());
+                    break;
+                } else {
+                    j += 1;
+                }
+            }
+            let end = closed_at.unwrap_or(bytes.len());
+            segments.push((false, &line[start..end]));
+            // Only triple-quoted strings continue onto the next line
+            if closed_at.is_some() || delim.len() == 1 {
+                *open_string = None;
+            }
+            start = end;
+            i = end;
+            continue;
+        }
+
+        match bytes[i] {
+            b'#' => {
+                if start < i {
+                    segments.push((true, &line[start..i]));
+                }
+                segments.push((false, &line[i..]));
+                return segments;
+            }
+            b'\'' | b'"' => {
+                if start < i {
+                    segments.push((true, &line[start..i]));
+                }
+                let rest = &bytes[i..];
+                let delim = if rest.starts_with(b"\"\"\"") {
+                    "\"\"\""
+                } else if rest.starts_with(b"'''") {
+                    "'''"
+                } else if rest[0] == b'"' {
+                    "\""
+                } else {
+                    "'"
+                };
+                *open_string = Some(delim);
+                start = i;
+                i += delim.len();
+            }
+            _ => i += 1,
+        }
+    }
+
+    if start < bytes.len() {
+        segments.push((open_string.is_none(), &line[start..]));
+    }
+    if open_string.is_some_and(|delim| delim.len() == 1) {
+        *open_string = None;
+    }
+    segments
+}
+
+/// Strip unsupported Python keywords/patterns that cause RustPython compilation
+/// errors or surprising behavior, using `DEFAULT_PYTHON_TRANSFORMS`.
 ///
 /// This is called before code execution to handle models that add unsupported syntax.
 pub fn strip_unsupported_python(lines: &[String]) -> Vec<String> {
-    // Pattern to match standalone `await` keyword (not inside strings)
-    // Matches: `await foo()`, `x = await bar()`, but not `"await"` or `# await`
-    let await_pattern = Regex::new(r"\bawait\s+").unwrap();
+    strip_unsupported_python_with(lines, DEFAULT_PYTHON_TRANSFORMS)
+}
 
+/// Apply `transforms` to each line, leaving string literals and comments untouched.
+pub fn strip_unsupported_python_with(
+    lines: &[String],
+    transforms: &[PythonTransform],
+) -> Vec<String> {
+    let mut counts = vec![0usize; transforms.len()];
+    let mut open_string: Option<&'static str> = None;
     let mut result = Vec::with_capacity(lines.len());
-    let mut stripped_count = 0;
 
     for line in lines {
-        let trimmed = line.trim();
-
-        // Skip comments and string-only lines
-        if trimmed.starts_with('#') {
-            result.push(line.clone());
-            continue;
-        }
-
-        // Strip `await ` from the line
-        if await_pattern.is_match(line) {
-            let fixed = await_pattern.replace_all(line, "").to_string();
-            result.push(fixed);
-            stripped_count += 1;
-        } else {
-            result.push(line.clone());
+        let mut fixed = String::with_capacity(line.len());
+        let segments = split_code_segments(line, &mut open_string);
+        for (index, (is_code, segment)) in segments.into_iter().enumerate() {
+            if !is_code {
+                fixed.push_str(segment);
+                continue;
+            }
+            let mut text = segment.to_string();
+            for (transform, count) in transforms.iter().zip(counts.iter_mut()) {
+                let (rewritten, applied) = transform.apply(&text, index == 0);
+                text = rewritten;
+                *count += applied;
+            }
+            fixed.push_str(&text);
         }
+        result.push(fixed);
     }
 
-    if stripped_count > 0 {
-        println!(
-            "[python_execution] Stripped {} `await` keyword(s) (not needed in sandbox)",
-            stripped_count
-        );
+    for (transform, count) in transforms.iter().zip(&counts) {
+        if *count > 0 {
+            println!(
+                "[python_execution] Stripped {} {}",
+                count,
+                transform.description()
+            );
+        }
     }
 
     result
@@ -357,6 +599,47 @@ pub fn is_valid_python_syntax(code_lines: &[String]) -> bool {
     }
 }
 
+/// Name of the pseudo-tool models call to end a turn with their answer.
+pub const FINAL_ANSWER_TOOL: &str = "final_answer";
+
+/// Extract the answer from a program that consists solely of a
+/// `final_answer("...")` call, with a string literal passed positionally
+/// or as `answer=`.
+///
+/// Returns None for anything else, including programs that call
+/// `final_answer` alongside other statements, since that code still has to run.
+pub fn parse_final_answer_call(code_lines: &[String]) -> Option<String> {
+    let code = code_lines.join("\n");
+    let suite = ast::Suite::parse(&code, "<embedded>").ok()?;
+    let [ast::Stmt::Expr(stmt)] = suite.as_slice() else {
+        return None;
+    };
+    let ast::Expr::Call(call) = stmt.value.as_ref() else {
+        return None;
+    };
+    let ast::Expr::Name(name) = call.func.as_ref() else {
+        return None;
+    };
+    if name.id.as_str() != FINAL_ANSWER_TOOL {
+        return None;
+    }
+
+    let answer = match (call.args.as_slice(), call.keywords.as_slice()) {
+        ([arg], []) => arg,
+        ([], [keyword]) if keyword.arg.as_ref().is_some_and(|a| a.as_str() == "answer") => {
+            &keyword.value
+        }
+        _ => return None,
+    };
+    match answer {
+        ast::Expr::Constant(ast::ExprConstant {
+            value: ast::Constant::Str(text),
+            ..
+        }) => Some(text.clone()),
+        _ => None,
+    }
+}
+
 /// Reconstruct SQL from malformed sql_select arguments.
 ///
 /// When models call sql_select incorrectly (e.g., positional arguments parsed
@@ -365,7 +648,11 @@ pub fn is_valid_python_syntax(code_lines: &[String]) -> bool {
 ///
 /// This function attempts to reconstruct the original SQL by:
 /// 1. Detecting if keys look like SQL fragments (contain SELECT, WHERE, etc.)
-/// 2. Joining keys and values with '=' to reconstruct the query
+/// 2. Joining keys and values with '=' to reconstruct the query. When the split
+///    happened inside a quoted string literal (e.g. `url LIKE 'a=b'`), the '='
+///    is restored without surrounding spaces so the literal is unchanged.
+/// 3. Removing the quotes that wrapped the whole positional argument, while
+///    keeping quoting that belongs to the SQL itself.
 ///
 /// Returns None if the arguments don't look like malformed SQL.
 pub fn reconstruct_sql_from_malformed_args(arguments: &serde_json::Value) -> Option<String> {
@@ -389,7 +676,7 @@ pub fn reconstruct_sql_from_malformed_args(arguments: &serde_json::Value) -> Opt
 
     for (key, value) in obj.iter() {
         // Skip known proper parameter names
-        if key == "sql" || key == "source_id" || key == "parameters" || key == "max_rows" {
+        if matches!(key.as_str(), "sql" | "source_id" | "parameters" | "max_rows" | "offset") {
             continue;
         }
 
@@ -418,29 +705,34 @@ pub fn reconstruct_sql_from_malformed_args(arguments: &serde_json::Value) -> Opt
 
     // Reconstruct the SQL by joining fragments
     // The malformed parsing typically splits on '=' so we join with '='
-    let mut reconstructed = String::new();
+    // Quote that wrapped the whole positional argument, e.g. `"SELECT ... = 20"`
+    let wrapper = sql_fragments[0]
+        .0
+        .trim()
+        .chars()
+        .next()
+        .filter(|c| *c == '"' || *c == '\'');
+    let mut joined = String::new();
     for (i, (key, value)) in sql_fragments.iter().enumerate() {
-        // Clean up the key (remove surrounding quotes if present)
-        let clean_key = key
-            .trim_start_matches('"')
-            .trim_end_matches('"')
-            .to_string();
-
-        // Clean up the value (remove surrounding quotes if present)
-        let clean_value = value
-            .trim_start_matches('"')
-            .trim_end_matches('"')
-            .to_string();
-
+        let key = key.trim();
         if i > 0 {
-            reconstructed.push(' ');
+            joined.push(' ');
+            joined.push_str(key);
+        } else {
+            joined.push_str(wrapper.map_or(key, |q| &key[q.len_utf8()..]));
         }
 
-        reconstructed.push_str(&clean_key);
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
 
-        // Only add '=' if the value is non-empty and doesn't start with common SQL joiners
-        if !clean_value.is_empty() {
-            let value_upper = clean_value.trim().to_uppercase();
+        if inside_sql_quotes(&joined) {
+            // The '=' belonged to a quoted value like 'a=b'; restore it verbatim
+            joined.push('=');
+        } else {
+            // Only add '=' if the value doesn't start with common SQL joiners
+            let value_upper = value.to_uppercase();
             let needs_equals = !value_upper.starts_with("AND ")
                 && !value_upper.starts_with("OR ")
                 && !value_upper.starts_with("FROM ")
@@ -450,13 +742,17 @@ pub fn reconstruct_sql_from_malformed_args(arguments: &serde_json::Value) -> Opt
                 && !value_upper.starts_with("LIMIT ");
 
             if needs_equals {
-                reconstructed.push_str(" = ");
+                joined.push_str(" = ");
             } else {
-                reconstructed.push(' ');
+                joined.push(' ');
             }
-            reconstructed.push_str(&clean_value);
         }
+        joined.push_str(value);
     }
+    let reconstructed = match wrapper {
+        Some(q) => strip_closing_wrapper(joined.trim(), q).to_string(),
+        None => joined.trim().to_string(),
+    };
 
     // Basic validation: must start with SELECT/INSERT/UPDATE/DELETE
     let trimmed_upper = reconstructed.trim().to_uppercase();
@@ -476,6 +772,30 @@ pub fn reconstruct_sql_from_malformed_args(arguments: &serde_json::Value) -> Opt
     Some(reconstructed)
 }
 
+/// True when `sql` ends inside an unterminated quoted literal or identifier.
+/// Doubled quotes (`''`) are SQL escapes and close and reopen the same quote.
+fn inside_sql_quotes(sql: &str) -> bool {
+    let mut open: Option<char> = None;
+    for c in sql.chars() {
+        match open {
+            Some(q) if c == q => open = None,
+            None if c == '\'' || c == '"' => open = Some(c),
+            _ => {}
+        }
+    }
+    open.is_some()
+}
+
+/// Remove the closing quote of the positional argument wrapper, keeping
+/// balanced quotes that belong to the SQL (e.g. `"col"` identifiers).
+fn strip_closing_wrapper(sql: &str, wrapper: char) -> &str {
+    if sql.matches(wrapper).count() % 2 == 1 {
+        sql.strip_suffix(wrapper).unwrap_or(sql)
+    } else {
+        sql
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -530,6 +850,69 @@ mod tests {
         assert_eq!(result[2], "print('done')"); // Stays at top level!
     }
 
+    #[test]
+    fn test_fix_python_indentation_preserves_two_space_and_tab_units() {
+        let two_space: Vec<String> = [
+            "def total(rows):",
+            "  s = 0",
+            "  for r in rows:",
+            "    if r > 0:",
+            "      s += r",
+            "  return s",
+            "print(total([1, -2, 3]))",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        assert_eq!(fix_python_indentation(&two_space), two_space);
+
+        let tabs: Vec<String> = [
+            "for i in range(3):",
+            "\tif i % 2:",
+            "\t\tprint('odd', i)",
+            "\telse:",
+            "\t\tprint('even', i)",
+            "print('done')",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        assert_eq!(fix_python_indentation(&tabs), tabs);
+    }
+
+    #[test]
+    fn test_fix_python_indentation_uses_continuation_indent_unit() {
+        let two_space: Vec<String> = [
+            "rows = [",
+            "  1, -2, 3,",
+            "]",
+            "for r in rows:",
+            "if r > 0:",
+            "print(r)",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let result = fix_python_indentation(&two_space);
+        assert_eq!(result[1], "  1, -2, 3,");
+        assert_eq!(result[4], "  if r > 0:");
+        assert_eq!(result[5], "    print(r)");
+
+        let tabs: Vec<String> = [
+            "config = {",
+            "\t\"limit\": 3,",
+            "}",
+            "for i in range(config[\"limit\"]):",
+            "print(i)",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let result = fix_python_indentation(&tabs);
+        assert_eq!(result[1], "\t\"limit\": 3,");
+        assert_eq!(result[4], "\tprint(i)");
+    }
+
     #[test]
     fn test_fix_python_indentation_function_then_call() {
         // Critical case: function definition followed by call at top level
@@ -552,6 +935,72 @@ mod tests {
         assert_eq!(result[7], "print(json.dumps(result, indent=2))");
     }
 
+    #[test]
+    fn test_fix_python_indentation_multiline_dict_literal() {
+        let input = vec![
+            "data = {".to_string(),
+            "    'if': 1,".to_string(),
+            "'else': 2,".to_string(),
+            "  'items': [1, 2,".to_string(),
+            "3],".to_string(),
+            "}".to_string(),
+            "for key in data:".to_string(),
+            "print(key)".to_string(),
+        ];
+
+        let result = fix_python_indentation(&input);
+
+        // Continuation lines are kept verbatim and don't count as existing indentation
+        assert_eq!(&result[..6], &input[..6]);
+        assert_eq!(result[6], "for key in data:");
+        assert_eq!(result[7], "    print(key)");
+    }
+
+    #[test]
+    fn test_fix_python_indentation_call_spanning_lines() {
+        let input = vec![
+            "for row in rows:".to_string(),
+            "total = compute(".to_string(),
+            "row['price'],".to_string(),
+            "discount=lambda p: p * 0.9,".to_string(),
+            "label=\"a)\",".to_string(),
+            ")".to_string(),
+            "print(total)".to_string(),
+        ];
+
+        let result = fix_python_indentation(&input);
+
+        assert_eq!(result[0], "for row in rows:");
+        assert_eq!(result[1], "    total = compute(");
+        // Inside the call: verbatim, and `lambda p:` doesn't open a block
+        assert_eq!(result[2], "row['price'],");
+        assert_eq!(result[3], "discount=lambda p: p * 0.9,");
+        assert_eq!(result[4], "label=\"a)\",");
+        assert_eq!(result[5], ")");
+        assert_eq!(result[6], "    print(total)");
+    }
+
+    #[test]
+    fn test_fix_python_indentation_block_header_spanning_lines() {
+        let input = vec![
+            "if (x > 0 and".to_string(),
+            "y > 0):".to_string(),
+            "print('both positive')".to_string(),
+            "total = a + \\".to_string(),
+            "b".to_string(),
+            "print(total)".to_string(),
+        ];
+
+        let result = fix_python_indentation(&input);
+
+        assert_eq!(result[0], "if (x > 0 and");
+        assert_eq!(result[1], "y > 0):");
+        assert_eq!(result[2], "    print('both positive')");
+        assert_eq!(result[3], "    total = a + \\");
+        assert_eq!(result[4], "b");
+        assert_eq!(result[5], "    print(total)");
+    }
+
     #[test]
     fn test_strip_unsupported_await() {
         let input = vec![
@@ -577,4 +1026,172 @@ mod tests {
         assert_eq!(result[0], "# This is a comment about await");
         assert_eq!(result[1], "result = foo()");
     }
+
+    #[test]
+    fn test_strip_unsupported_async_qualifiers() {
+        let input = vec![
+            "async def fetch(x):".to_string(),
+            "    async with lock:".to_string(),
+            "        async for item in items:".to_string(),
+            "            pass".to_string(),
+        ];
+
+        let result = strip_unsupported_python(&input);
+
+        assert_eq!(result[0], "def fetch(x):");
+        assert_eq!(result[1], "    with lock:");
+        assert_eq!(result[2], "        for item in items:");
+        assert_eq!(result[3], "            pass");
+    }
+
+    #[test]
+    fn test_strip_unsupported_ignores_strings_and_identifiers() {
+        let input = vec![
+            "print(\"await me\")".to_string(),
+            "awaited = 1".to_string(),
+            "x = awaited_value + await_count".to_string(),
+            "msg = 'async def is fine here'".to_string(),
+        ];
+
+        let result = strip_unsupported_python(&input);
+
+        assert_eq!(result, input);
+    }
+
+    #[test]
+    fn test_strip_unsupported_skips_triple_quoted_strings() {
+        let input = vec![
+            "doc = \"\"\"".to_string(),
+            "await this line".to_string(),
+            "\"\"\"".to_string(),
+            "value = await get()".to_string(),
+        ];
+
+        let result = strip_unsupported_python(&input);
+
+        assert_eq!(result[1], "await this line");
+        assert_eq!(result[3], "value = get()");
+    }
+
+    #[test]
+    fn test_strip_pep695_generics_is_opt_in() {
+        let input = vec![
+            "def first[T](items: list[T]) -> T:".to_string(),
+            "class Box[T: list[int]]:".to_string(),
+            "type Alias[T] = list[T]".to_string(),
+        ];
+
+        assert_eq!(strip_unsupported_python(&input), input);
+
+        let result = strip_unsupported_python_with(
+            &input,
+            &[PythonTransform::StripPep695Generics],
+        );
+
+        assert_eq!(result[0], "def first(items: list[T]) -> T:");
+        assert_eq!(result[1], "class Box:");
+        assert_eq!(result[2], "Alias = list[T]");
+    }
+
+    /// Prepare `sql` against a scratch SQLite schema to prove it parses.
+    fn assert_sql_parses(sql: &str) {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE t (x INTEGER, y INTEGER, url TEXT, name TEXT);")
+            .unwrap();
+        let parsed = conn.prepare(sql).map(|_| ());
+        assert!(
+            parsed.is_ok(),
+            "reconstructed SQL does not parse: {:?}\n{}",
+            parsed,
+            sql
+        );
+    }
+
+    #[test]
+    fn test_reconstruct_sql_doc_comment_shape() {
+        let args = serde_json::json!({"\"SELECT * FROM t WHERE x": "10 AND y = 20\""});
+
+        let sql = reconstruct_sql_from_malformed_args(&args).unwrap();
+
+        assert_eq!(sql, "SELECT * FROM t WHERE x = 10 AND y = 20");
+        assert_sql_parses(&sql);
+    }
+
+    #[test]
+    fn test_reconstruct_sql_ignores_paging_args() {
+        // sql_select("SELECT * FROM t WHERE x = 10", max_rows=5, offset=20)
+        let args = serde_json::json!({
+            "\"SELECT * FROM t WHERE x": "10\"",
+            "max_rows": 5,
+            "offset": 20
+        });
+
+        let sql = reconstruct_sql_from_malformed_args(&args).unwrap();
+
+        assert_eq!(sql, "SELECT * FROM t WHERE x = 10");
+        assert_sql_parses(&sql);
+    }
+
+    #[test]
+    fn test_reconstruct_sql_equals_inside_string_literal() {
+        // sql_select("SELECT * FROM t WHERE url LIKE 'a=b'") split on the '=' in the literal
+        let args = serde_json::json!({"\"SELECT * FROM t WHERE url LIKE 'a": "b'\""});
+
+        let sql = reconstruct_sql_from_malformed_args(&args).unwrap();
+
+        assert_eq!(sql, "SELECT * FROM t WHERE url LIKE 'a=b'");
+        assert_sql_parses(&sql);
+    }
+
+    #[test]
+    fn test_reconstruct_sql_quoted_value_containing_equals() {
+        // sql_select("SELECT * FROM t WHERE url = 'a=b'") split on the comparison
+        let args = serde_json::json!({"\"SELECT * FROM t WHERE url": "'a=b'\""});
+
+        let sql = reconstruct_sql_from_malformed_args(&args).unwrap();
+
+        assert_eq!(sql, "SELECT * FROM t WHERE url = 'a=b'");
+        assert_sql_parses(&sql);
+    }
+
+    #[test]
+    fn test_reconstruct_sql_preserves_identifier_quotes() {
+        // sql_select('SELECT "name" FROM t WHERE x = 1') with a single-quoted wrapper
+        let args = serde_json::json!({"'SELECT \"name\" FROM t WHERE x": "1'"});
+
+        let sql = reconstruct_sql_from_malformed_args(&args).unwrap();
+
+        assert_eq!(sql, "SELECT \"name\" FROM t WHERE x = 1");
+        assert_sql_parses(&sql);
+    }
+
+    #[test]
+    fn test_reconstruct_sql_ignores_well_formed_args() {
+        let proper = serde_json::json!({"sql": "SELECT 1", "max_rows": 10, "offset": 20});
+        assert!(reconstruct_sql_from_malformed_args(&proper).is_none());
+
+        let not_sql = serde_json::json!({"query": "hello"});
+        assert!(reconstruct_sql_from_malformed_args(&not_sql).is_none());
+    }
+
+    #[test]
+    fn test_parse_final_answer_call() {
+        let lines = |code: &str| code.lines().map(String::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            parse_final_answer_call(&lines("final_answer(\"Paris\")")),
+            Some("Paris".to_string())
+        );
+        assert_eq!(
+            parse_final_answer_call(&lines("final_answer(answer='''line one\nline two''')")),
+            Some("line one\nline two".to_string())
+        );
+        // Non-literal answers and extra statements need the code to actually run
+        assert_eq!(parse_final_answer_call(&lines("final_answer(result)")), None);
+        assert_eq!(
+            parse_final_answer_call(&lines("x = 1\nfinal_answer(\"done\")")),
+            None
+        );
+        assert_eq!(parse_final_answer_call(&lines("print(\"final_answer\")")), None);
+    }
 }
@@ -0,0 +1,208 @@
+//! Reasoning ("thinking") blocks in model output.
+//!
+//! Reasoning models wrap their chain of thought in delimiters that depend on the
+//! model's `ReasoningFormat` (`<think>...</think>` for Phi/Qwen, Granite's
+//! `<|thinking|>` tags, gpt-oss analysis channels). The agentic loop removes
+//! these blocks before looking for tool calls and building the final answer, so
+//! a tool call the model only considered is never run and the saved history
+//! holds only the answer.
+
+use crate::protocol::ReasoningFormat;
+
+/// Event carrying reasoning text, when `reasoning_events_enabled` is on
+pub const REASONING_EVENT: &str = "reasoning-token";
+
+/// Opening and closing delimiters of a reasoning block
+pub fn reasoning_delimiters(format: ReasoningFormat) -> Option<(&'static str, &'static str)> {
+    match format {
+        ReasoningFormat::None => None,
+        ReasoningFormat::ThinkTags => Some(("<think>", "</think>")),
+        ReasoningFormat::ThinkingTags => Some(("<|thinking|>", "<|/thinking|>")),
+        ReasoningFormat::ChannelBased => Some(("<|channel|>analysis<|message|>", "<|end|>")),
+    }
+}
+
+/// Split a complete response into `(reasoning, answer)`. An unclosed block (a
+/// truncated response) is reasoning up to the end, and a closing delimiter with
+/// no opening one (templates that open the block in the prompt) ends a leading block.
+pub fn split_reasoning(text: &str, format: ReasoningFormat) -> (String, String) {
+    let Some((open, close)) = reasoning_delimiters(format) else {
+        return (String::new(), text.to_string());
+    };
+
+    let mut reasoning = Vec::new();
+    let mut answer = String::new();
+    let mut rest = text;
+    if let Some(end) = rest.find(close) {
+        if !rest[..end].contains(open) {
+            reasoning.push(rest[..end].trim());
+            rest = &rest[end + close.len()..];
+        }
+    }
+    while let Some(start) = rest.find(open) {
+        answer.push_str(&rest[..start]);
+        let block = &rest[start + open.len()..];
+        match block.find(close) {
+            Some(end) => {
+                reasoning.push(block[..end].trim());
+                rest = &block[end + close.len()..];
+            }
+            None => {
+                reasoning.push(block.trim());
+                rest = "";
+            }
+        }
+    }
+    answer.push_str(rest);
+
+    let reasoning: Vec<&str> = reasoning.into_iter().filter(|r| !r.is_empty()).collect();
+    (reasoning.join("\n\n"), answer.trim().to_string())
+}
+
+/// Text of one streamed token, split by whether it is inside a reasoning block
+#[derive(Debug, Default, PartialEq)]
+pub struct StreamSplit {
+    pub reasoning: String,
+    pub visible: String,
+}
+
+/// Splits streamed tokens into reasoning and visible text. Delimiters may be
+/// split across tokens, so text that could be the start of one is held back
+/// until the next token decides it.
+#[derive(Debug)]
+pub struct ReasoningStreamFilter {
+    delimiters: Option<(&'static str, &'static str)>,
+    in_reasoning: bool,
+    pending: String,
+}
+
+impl ReasoningStreamFilter {
+    pub fn new(format: ReasoningFormat) -> Self {
+        Self {
+            delimiters: reasoning_delimiters(format),
+            in_reasoning: false,
+            pending: String::new(),
+        }
+    }
+
+    pub fn push(&mut self, token: &str) -> StreamSplit {
+        let mut split = StreamSplit::default();
+        let Some((open, close)) = self.delimiters else {
+            split.visible.push_str(token);
+            return split;
+        };
+
+        self.pending.push_str(token);
+        loop {
+            let delimiter = if self.in_reasoning { close } else { open };
+            if let Some(pos) = self.pending.find(delimiter) {
+                self.emit(pos, &mut split);
+                self.pending.drain(..delimiter.len());
+                self.in_reasoning = !self.in_reasoning;
+                continue;
+            }
+            let held = partial_delimiter_len(&self.pending, delimiter);
+            self.emit(self.pending.len() - held, &mut split);
+            return split;
+        }
+    }
+
+    /// Release any held-back text at the end of the stream
+    pub fn finish(&mut self) -> StreamSplit {
+        let mut split = StreamSplit::default();
+        self.emit(self.pending.len(), &mut split);
+        split
+    }
+
+    /// Move the first `len` pending bytes to the current side of the split
+    fn emit(&mut self, len: usize, split: &mut StreamSplit) {
+        let text: String = self.pending.drain(..len).collect();
+        if self.in_reasoning {
+            split.reasoning.push_str(&text);
+        } else {
+            split.visible.push_str(&text);
+        }
+    }
+}
+
+/// Length of the longest suffix of `text` that is a proper prefix of `delimiter`
+fn partial_delimiter_len(text: &str, delimiter: &str) -> usize {
+    (1..delimiter.len().min(text.len() + 1))
+        .rev()
+        .find(|&len| {
+            text.is_char_boundary(text.len() - len)
+                && delimiter.starts_with(&text[text.len() - len..])
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_reasoning_formats() {
+        let (reasoning, answer) = split_reasoning(
+            "<think>\nThe user wants 2+2.\n</think>\n\nThe answer is 4.",
+            ReasoningFormat::ThinkTags,
+        );
+        assert_eq!(reasoning, "The user wants 2+2.");
+        assert_eq!(answer, "The answer is 4.");
+
+        let (reasoning, answer) = split_reasoning(
+            "<|thinking|>check units<|/thinking|>It is 5 km.",
+            ReasoningFormat::ThinkingTags,
+        );
+        assert_eq!(reasoning, "check units");
+        assert_eq!(answer, "It is 5 km.");
+
+        // Formats without reasoning leave the text alone
+        let text = "<think>kept</think> as is";
+        assert_eq!(
+            split_reasoning(text, ReasoningFormat::None),
+            (String::new(), text.to_string())
+        );
+    }
+
+    #[test]
+    fn test_split_reasoning_edge_cases() {
+        // Opening tag supplied by the prompt template
+        let (reasoning, answer) =
+            split_reasoning("planning...</think>Done.", ReasoningFormat::ThinkTags);
+        assert_eq!(reasoning, "planning...");
+        assert_eq!(answer, "Done.");
+
+        // Truncated inside the block
+        let (reasoning, answer) =
+            split_reasoning("Sure. <think>still going", ReasoningFormat::ThinkTags);
+        assert_eq!(reasoning, "still going");
+        assert_eq!(answer, "Sure.");
+
+        let (reasoning, answer) = split_reasoning(
+            "<think>a</think>One <think>b</think>two",
+            ReasoningFormat::ThinkTags,
+        );
+        assert_eq!(reasoning, "a\n\nb");
+        assert_eq!(answer, "One two");
+    }
+
+    #[test]
+    fn test_stream_filter_handles_split_delimiters() {
+        let mut filter = ReasoningStreamFilter::new(ReasoningFormat::ThinkTags);
+        let mut reasoning = String::new();
+        let mut visible = String::new();
+        for token in ["<th", "ink>hm", "m</", "think", ">Hi <", "b>"] {
+            let split = filter.push(token);
+            reasoning.push_str(&split.reasoning);
+            visible.push_str(&split.visible);
+        }
+        let split = filter.finish();
+        visible.push_str(&split.visible);
+
+        assert_eq!(reasoning, "hmm");
+        assert_eq!(visible, "Hi <b>");
+
+        let mut filter = ReasoningStreamFilter::new(ReasoningFormat::None);
+        assert_eq!(filter.push("<think>").visible, "<think>");
+    }
+}
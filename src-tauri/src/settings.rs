@@ -26,9 +26,55 @@ pub enum ToolCallFormatName {
     PureJson,
     /// Python execution mode: tools called via python_execution sandbox
     CodeMode,
+    /// Anthropic messages format: `tool_use` / `tool_result` content blocks.
+    /// Only for models whose backend speaks it (`ToolFormat::Anthropic`).
+    Anthropic,
+}
+
+/// Description of a tool calling format, shown in the settings UI
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolCallFormatInfo {
+    pub id: ToolCallFormatName,
+    pub display_name: &'static str,
+    pub description: &'static str,
+    /// What a call looks like in this format
+    pub example: &'static str,
+    /// Calls run through the python_execution sandbox
+    pub requires_python_execution: bool,
+    /// The model must support native (API-level) tool calling
+    pub requires_native_support: bool,
+}
+
+/// A module python_execution code can import, shown in the settings UI
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PythonModuleInfo {
+    pub name: String,
+    pub description: String,
+    /// Part of the sandbox defaults (otherwise opted in via python_allowed_modules)
+    pub default_enabled: bool,
+    /// Importable with the current settings
+    pub enabled: bool,
+}
+
+/// The python sandbox's modules plus the ones that can never be imported
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PythonModuleCatalog {
+    pub modules: Vec<PythonModuleInfo>,
+    pub denied: Vec<String>,
 }
 
 impl ToolCallFormatName {
+    /// Every format, in the order the settings UI lists them
+    pub const ALL: [ToolCallFormatName; 7] = [
+        ToolCallFormatName::Native,
+        ToolCallFormatName::CodeMode,
+        ToolCallFormatName::Hermes,
+        ToolCallFormatName::Mistral,
+        ToolCallFormatName::Pythonic,
+        ToolCallFormatName::PureJson,
+        ToolCallFormatName::Anthropic,
+    ];
+
     pub fn as_str(&self) -> &'static str {
         match self {
             ToolCallFormatName::Native => "native",
@@ -37,6 +83,78 @@ impl ToolCallFormatName {
             ToolCallFormatName::Pythonic => "pythonic",
             ToolCallFormatName::PureJson => "pure_json",
             ToolCallFormatName::CodeMode => "code_mode",
+            ToolCallFormatName::Anthropic => "anthropic",
+        }
+    }
+
+    /// Inverse of `as_str`
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.as_str() == name)
+    }
+
+    /// Display metadata for this format
+    pub fn info(&self) -> ToolCallFormatInfo {
+        let (display_name, description, example) = match self {
+            ToolCallFormatName::Native => (
+                "Native (OpenAI API)",
+                "Use the model's native tool calling via the `tools` API parameter (recommended for supported models).",
+                r#"{"tool_calls": [{"function": {"name": "...", "arguments": "{...}"}}]}"#,
+            ),
+            ToolCallFormatName::CodeMode => (
+                "Code Mode (Python)",
+                "Model returns a single Python program executed in the sandbox.",
+                "result = tool_name(arg1=\"value\")\nprint(result)",
+            ),
+            ToolCallFormatName::Hermes => (
+                "Hermes (tag-delimited)",
+                "JSON call wrapped in <tool_call> tags.",
+                r#"<tool_call>{"name": "...", "arguments": {...}}</tool_call>"#,
+            ),
+            ToolCallFormatName::Mistral => (
+                "Mistral (bracket)",
+                "JSON array of calls after a [TOOL_CALLS] marker.",
+                r#"[TOOL_CALLS] [{"name": "...", "arguments": {...}}]"#,
+            ),
+            ToolCallFormatName::Pythonic => (
+                "Pythonic call",
+                "A Python-style function call.",
+                r#"tool_name(arg1="value", arg2=123)"#,
+            ),
+            ToolCallFormatName::PureJson => (
+                "Pure JSON",
+                "A raw JSON object (or array of objects) naming the tool and its arguments.",
+                r#"{"tool": "name", "args": {...}}"#,
+            ),
+            ToolCallFormatName::Anthropic => (
+                "Anthropic (content blocks)",
+                "tool_use / tool_result content blocks, for models served with the Anthropic messages format.",
+                r#"[{"type": "tool_use", "id": "toolu_01", "name": "...", "input": {...}}]"#,
+            ),
+        };
+        ToolCallFormatInfo {
+            id: *self,
+            display_name,
+            description,
+            example,
+            requires_python_execution: *self == ToolCallFormatName::CodeMode,
+            requires_native_support: matches!(
+                self,
+                ToolCallFormatName::Native | ToolCallFormatName::Anthropic
+            ),
+        }
+    }
+
+    /// Literal markers of this format's call syntax, used to spot prompts that ask
+    /// for a different format. Formats without distinctive markers have none.
+    pub fn markers(&self) -> &'static [&'static str] {
+        match self {
+            ToolCallFormatName::Hermes => &["<tool_call>", "</tool_call>", "<tool_response>"],
+            ToolCallFormatName::Mistral => &["[TOOL_CALLS]"],
+            ToolCallFormatName::Anthropic => &["tool_use", "tool_result"],
+            ToolCallFormatName::Native
+            | ToolCallFormatName::Pythonic
+            | ToolCallFormatName::PureJson
+            | ToolCallFormatName::CodeMode => &[],
         }
     }
 
@@ -50,6 +168,17 @@ impl ToolCallFormatName {
                 | ToolCallFormatName::PureJson
         )
     }
+
+    /// Marker that ends a call in this format, usable as a stop sequence.
+    /// Code mode's closing ``` fence also opens the python block, so stopping
+    /// on it would cut the response before the code; formats without a
+    /// closing marker have none either.
+    pub fn stop_sequence(&self) -> Option<&'static str> {
+        match self {
+            ToolCallFormatName::Hermes => Some("</tool_call>"),
+            _ => None,
+        }
+    }
 }
 
 // ============ Chat Formats ============
@@ -60,6 +189,8 @@ impl ToolCallFormatName {
 pub enum ChatFormatName {
     OpenaiCompletions,
     OpenaiResponses,
+    /// `POST /v1/messages` with content blocks; tool calls are `tool_use` blocks
+    AnthropicMessages,
 }
 
 impl ChatFormatName {
@@ -67,6 +198,82 @@ impl ChatFormatName {
         match self {
             ChatFormatName::OpenaiCompletions => "openai_completions",
             ChatFormatName::OpenaiResponses => "openai_responses",
+            ChatFormatName::AnthropicMessages => "anthropic_messages",
+        }
+    }
+}
+
+/// Chat format for a model: its override, else the default
+pub fn resolve_chat_format(
+    overrides: &HashMap<String, ChatFormatName>,
+    default: ChatFormatName,
+    model: &str,
+) -> ChatFormatName {
+    overrides.get(model).copied().unwrap_or(default)
+}
+
+// ============ Python Result Format ============
+
+/// How python_execution results are presented to the model.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultFormat {
+    /// Human-readable `STDOUT:` / `STDERR:` / `RETURN:` sections
+    #[default]
+    Text,
+    /// Compact JSON object: `{success, stdout, stderr, return_value, tool_calls_made}`
+    Json,
+}
+
+impl ResultFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResultFormat::Text => "text",
+            ResultFormat::Json => "json",
+        }
+    }
+}
+
+// ============ SQL Result Format ============
+
+/// How sql_select rows are presented to the model.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SqlResultFormat {
+    /// The query result as pretty-printed JSON
+    #[default]
+    Json,
+    /// A Markdown table with inferred column types, for small result sets
+    Table,
+}
+
+impl SqlResultFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SqlResultFormat::Json => "json",
+            SqlResultFormat::Table => "table",
+        }
+    }
+}
+
+// ============ Python Sandbox Backend ============
+
+/// Where python_execution code runs.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PythonSandboxBackend {
+    /// RustPython inside the app process, restricted at the Python level
+    #[default]
+    InProcess,
+    /// The sandbox compiled to WASM, run in Wasmtime with no filesystem or network
+    Wasm,
+}
+
+impl PythonSandboxBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PythonSandboxBackend::InProcess => "in_process",
+            PythonSandboxBackend::Wasm => "wasm",
         }
     }
 }
@@ -123,10 +330,42 @@ impl ToolCallFormatConfig {
         }
     }
 
+    /// Remove a format (e.g. one the current model can't use), keeping the config normalized
+    pub fn disable(&mut self, format: ToolCallFormatName) {
+        self.enabled.retain(|f| *f != format);
+        self.normalize();
+    }
+
+    /// For a model served with the Anthropic messages format: its native calls are
+    /// `tool_use` blocks, so Native is replaced by Anthropic (also as primary)
+    pub fn use_anthropic_blocks(&mut self) {
+        if !self.native_enabled() {
+            return;
+        }
+        for format in &mut self.enabled {
+            if *format == ToolCallFormatName::Native {
+                *format = ToolCallFormatName::Anthropic;
+            }
+        }
+        if self.primary == ToolCallFormatName::Native {
+            self.primary = ToolCallFormatName::Anthropic;
+        }
+        self.normalize();
+    }
+
     pub fn is_enabled(&self, format: ToolCallFormatName) -> bool {
         self.enabled.contains(&format)
     }
 
+    /// Stop sequences for the enabled formats' terminal markers
+    pub fn stop_sequences(&self) -> Vec<String> {
+        self.enabled
+            .iter()
+            .filter_map(|f| f.stop_sequence())
+            .map(str::to_string)
+            .collect()
+    }
+
     /// Returns true if any text-based format (Hermes, Mistral, Pythonic, PureJson) is enabled
     pub fn any_text_based(&self) -> bool {
         self.enabled.iter().any(|f| f.is_text_based())
@@ -403,8 +642,15 @@ pub struct DatabaseSourceConfig {
     /// Optional comma-separated allowlist of tables (BigQuery only). Empty => all tables.
     #[serde(default)]
     pub table_allowlist: Option<String>,
+    /// Regex for dataset and table names skipped during schema refresh.
+    /// Unset or blank => `DEFAULT_SKIP_TABLE_PATTERN` (date-sharded tables).
+    #[serde(default)]
+    pub skip_table_pattern: Option<String>,
 }
 
+/// Default `skip_table_pattern`: date shards such as `events_20240101`
+pub const DEFAULT_SKIP_TABLE_PATTERN: &str = r"_\d{8}$";
+
 impl DatabaseSourceConfig {
     pub fn new(id: String, name: String, kind: SupportedDatabaseKind) -> Self {
         Self {
@@ -422,9 +668,23 @@ impl DatabaseSourceConfig {
             sql_dialect: None,
             dataset_allowlist: None,
             table_allowlist: None,
+            skip_table_pattern: None,
         }
     }
 
+    /// Compile the pattern for dataset and table names to skip during schema refresh
+    pub fn skip_table_regex(&self) -> Result<regex::Regex, String> {
+        let pattern = self
+            .skip_table_pattern
+            .as_deref()
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .unwrap_or(DEFAULT_SKIP_TABLE_PATTERN);
+        regex::Regex::new(pattern).map_err(|e| {
+            format!("Invalid skip_table_pattern '{}' for source {}: {}", pattern, self.name, e)
+        })
+    }
+
     /// Get the SQL dialect for this source, respecting overrides
     pub fn get_sql_dialect(&self) -> &str {
         if let Some(dialect) = self.sql_dialect.as_ref() {
@@ -536,12 +796,37 @@ pub struct McpServerConfig {
     /// should NOT be exposed directly as MCP tools in the system prompt.
     #[serde(default)]
     pub is_database_source: bool,
+    /// Per-call timeout for this server's tools in seconds, overriding
+    /// `AppSettings::mcp_tool_timeout_secs` (0 = no limit)
+    #[serde(default)]
+    pub tool_timeout_secs: Option<u64>,
+    /// Per-tool auto-approve settings keyed by tool name, taking precedence
+    /// over `auto_approve_tools` for the tools listed
+    #[serde(default)]
+    pub auto_approve_tool_overrides: HashMap<String, bool>,
+    /// If true (default), tool call arguments are checked against the tool's
+    /// input schema before the call is sent. Turn off for servers whose
+    /// schemas are looser than what they actually accept.
+    #[serde(default = "default_validate_tool_arguments")]
+    pub validate_tool_arguments: bool,
+    /// If true, the background health check reconnects this server when it
+    /// stops responding
+    #[serde(default)]
+    pub auto_reconnect: bool,
+    /// Directories exposed to the server as MCP roots during the handshake.
+    /// Each path must exist when the server connects.
+    #[serde(default)]
+    pub roots: Vec<String>,
 }
 
 fn default_defer_tools() -> bool {
     true
 }
 
+fn default_validate_tool_arguments() -> bool {
+    true
+}
+
 impl McpServerConfig {
     pub fn new(id: String, name: String) -> Self {
         Self {
@@ -556,6 +841,29 @@ impl McpServerConfig {
             defer_tools: true,
             python_name: None,
             is_database_source: false,
+            tool_timeout_secs: None,
+            auto_approve_tool_overrides: HashMap::new(),
+            validate_tool_arguments: true,
+            auto_reconnect: false,
+            roots: Vec::new(),
+        }
+    }
+
+    /// Whether calls to `tool` on this server run without asking the user.
+    /// A per-tool override wins over the server-wide setting.
+    pub fn auto_approves(&self, tool: &str) -> bool {
+        self.auto_approve_tool_overrides
+            .get(tool)
+            .copied()
+            .unwrap_or(self.auto_approve_tools)
+    }
+
+    /// Timeout for one tool call on this server, given the global default
+    /// in seconds. None means wait indefinitely.
+    pub fn tool_timeout(&self, default_secs: u64) -> Option<std::time::Duration> {
+        match self.tool_timeout_secs.unwrap_or(default_secs) {
+            0 => None,
+            secs => Some(std::time::Duration::from_secs(secs)),
         }
     }
 
@@ -576,6 +884,27 @@ impl McpServerConfig {
     }
 }
 
+impl AppSettings {
+    /// Timeout for one call to a tool of this server (MCP or database source)
+    pub fn mcp_tool_timeout_for(&self, server_id: &str) -> Option<std::time::Duration> {
+        mcp_tool_timeout(&self.get_all_mcp_configs(), server_id, self.mcp_tool_timeout_secs)
+    }
+}
+
+/// Timeout for one call to a server's tool: the server's own limit, else the
+/// global default in seconds (also for unknown servers). None means no limit.
+pub fn mcp_tool_timeout(
+    server_configs: &[McpServerConfig],
+    server_id: &str,
+    default_secs: u64,
+) -> Option<std::time::Duration> {
+    match server_configs.iter().find(|c| c.id == server_id) {
+        Some(config) => config.tool_timeout(default_secs),
+        None if default_secs > 0 => Some(std::time::Duration::from_secs(default_secs)),
+        None => None,
+    }
+}
+
 /// Ensure python_name is populated and sanitized from the display name.
 pub fn enforce_python_name(config: &mut McpServerConfig) {
     let candidate = config
@@ -593,6 +922,88 @@ pub fn enforce_python_name(config: &mut McpServerConfig) {
     config.python_name = Some(sanitized);
 }
 
+/// Expand `${VAR}` and `$VAR` references in `value` using `lookup`.
+/// `$$` is a literal `$`, as is a `$` not followed by a variable name.
+/// Fails on the first variable `lookup` doesn't know.
+fn expand_env_refs(value: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+        let name = match chars.peek() {
+            Some('$') => {
+                chars.next();
+                out.push('$');
+                continue;
+            }
+            Some('{') => {
+                chars.next();
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(ch) => name.push(ch),
+                        None => return Err(format!("Unterminated ${{{} in '{}'", name, value)),
+                    }
+                }
+                name
+            }
+            Some(ch) if ch.is_ascii_alphabetic() || *ch == '_' => {
+                let mut name = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if !(ch.is_ascii_alphanumeric() || ch == '_') {
+                        break;
+                    }
+                    name.push(ch);
+                    chars.next();
+                }
+                name
+            }
+            _ => {
+                out.push('$');
+                continue;
+            }
+        };
+        let resolved = lookup(&name)
+            .ok_or_else(|| format!("Environment variable '{}' is not set", name))?;
+        out.push_str(&resolved);
+    }
+    Ok(out)
+}
+
+fn resolve_env_vars_with(
+    config: &mut McpServerConfig,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), String> {
+    let expand = |value: &str| {
+        expand_env_refs(value, lookup).map_err(|e| format!("MCP server '{}': {}", config.name, e))
+    };
+    let command = config.command.as_deref().map(expand).transpose()?;
+    let args = config
+        .args
+        .iter()
+        .map(|arg| expand(arg))
+        .collect::<Result<Vec<_>, _>>()?;
+    let env = config
+        .env
+        .iter()
+        .map(|(key, value)| Ok((key.clone(), expand(value)?)))
+        .collect::<Result<HashMap<_, _>, String>>()?;
+    config.command = command;
+    config.args = args;
+    config.env = env;
+    Ok(())
+}
+
+/// Expand `${VAR}`/`$VAR` references in `command`, `args` and `env` values
+/// from the process environment, just before a server is spawned.
+pub fn resolve_env_vars(config: &mut McpServerConfig) -> Result<(), String> {
+    resolve_env_vars_with(config, &|name| std::env::var(name).ok())
+}
+
 // ============ Always-On Configuration ============
 
 /// Configuration for an always-on database table
@@ -609,6 +1020,13 @@ pub struct AlwaysOnTableConfig {
 pub struct AppSettings {
     #[serde(default = "default_system_prompt")]
     pub system_prompt: String,
+    /// Instructions placed before the generated system prompt on every turn
+    #[serde(default)]
+    pub system_prompt_prefix: Option<String>,
+    /// Instructions placed after the generated system prompt on every turn
+    /// (e.g. "Always cite sources"); kept through state transitions
+    #[serde(default)]
+    pub system_prompt_suffix: Option<String>,
     /// Persisted model selection - applied on app startup
     #[serde(default)]
     pub selected_model: Option<String>,
@@ -620,6 +1038,10 @@ pub struct AppSettings {
     /// Optional per-model chat format overrides keyed by model id
     #[serde(default)]
     pub chat_format_overrides: HashMap<String, ChatFormatName>,
+    /// Optional per-model reasoning_effort values keyed by model id, sent in
+    /// place of the effort the chat asked for
+    #[serde(default)]
+    pub reasoning_effort_overrides: HashMap<String, String>,
     /// Tool calling format configuration (enabled formats + primary)
     #[serde(default)]
     pub tool_call_formats: ToolCallFormatConfig,
@@ -627,13 +1049,135 @@ pub struct AppSettings {
     /// Use "builtin" as server_id for built-in tools.
     #[serde(default)]
     pub tool_system_prompts: HashMap<String, String>,
+    /// Replacement tool descriptions keyed by "{server_id}::{tool_name}", used in
+    /// tool schemas, prompts and tool_search embeddings ("builtin" for built-ins)
+    #[serde(default)]
+    pub tool_description_overrides: HashMap<String, String>,
     /// Maximum number of tools returned by tool_search (defaults to 3 for token control)
     #[serde(default = "default_tool_search_max_results")]
     pub tool_search_max_results: usize,
+    /// Minimum relevance score (0.0-1.0) a tool_search hit needs to be shown to the model
+    #[serde(default = "default_tool_search_min_relevance")]
+    pub tool_search_min_relevance: f32,
+    /// Skip auto tool/schema discovery for messages shorter than this many characters
+    /// or that are plain acknowledgements like "thanks" (0 = always run it)
+    #[serde(default = "default_auto_discovery_min_prompt_chars")]
+    pub auto_discovery_min_prompt_chars: usize,
+    /// Maximum agentic loop iterations per turn before tool calling stops (1..=100)
+    #[serde(default = "default_max_tool_iterations")]
+    pub max_tool_iterations: usize,
+    /// Maximum tool executions per turn, counted across iterations (0 = no limit)
+    #[serde(default = "default_max_tool_calls_per_turn")]
+    pub max_tool_calls_per_turn: usize,
+    /// Run independent auto-approved tool calls from one model response concurrently
+    #[serde(default)]
+    pub parallel_tool_calls: bool,
+    /// Ask for one approval covering all tool calls of a model response before
+    /// any of them runs (approve all / reject all / edit)
+    #[serde(default)]
+    pub batch_approval_mode: bool,
+    /// Tag text-format tool calls and their results with a correlation id comment,
+    /// so saved transcripts show which result belongs to which call
+    #[serde(default)]
+    pub tool_call_ids_in_text: bool,
+    /// Clear materialized tools at the start of every chat message, so tools found by
+    /// tool_search are only visible for the turn that found them
+    #[serde(default = "default_strict_turn_tool_scope")]
+    pub strict_turn_tool_scope: bool,
+    /// When turns share materialized tools (strict scope off), drop materializations
+    /// older than this many seconds (0 = never expire)
+    #[serde(default = "default_materialized_tools_ttl_secs")]
+    pub materialized_tools_ttl_secs: u64,
+    /// Connect MCP servers, precompute tool embeddings and load the selected model
+    /// in the background right after launch
+    #[serde(default)]
+    pub warm_start_on_launch: bool,
+    /// Send a reasoning model's thinking as `reasoning-token` events instead of
+    /// inline in `chat-token`, so the UI can show it collapsed
+    #[serde(default)]
+    pub reasoning_events_enabled: bool,
+    /// Default per-call timeout for MCP tools in seconds (0 = no limit).
+    /// Servers can override it with `McpServerConfig::tool_timeout_secs`.
+    #[serde(default = "default_mcp_tool_timeout_secs")]
+    pub mcp_tool_timeout_secs: u64,
+    /// Milliseconds between `tool-heartbeat` events while a tool runs (0 = no heartbeats)
+    #[serde(default = "default_tool_heartbeat_interval_ms")]
+    pub tool_heartbeat_interval_ms: u64,
+    /// Seconds between health checks of connected MCP servers (0 = no checks)
+    #[serde(default = "default_mcp_health_check_interval_secs")]
+    pub mcp_health_check_interval_secs: u64,
+    /// Seconds between auto-saves of an in-progress assistant response (0 = save only at turn end)
+    #[serde(default = "default_chat_autosave_interval_secs")]
+    pub chat_autosave_interval_secs: u64,
+    /// Send the enabled tool call formats' closing markers (e.g. `</tool_call>`)
+    /// as stop sequences so the model stops right after a call
+    #[serde(default)]
+    pub use_format_stop_sequences: bool,
+    /// Tool results longer than this many characters are truncated in the middle
+    /// before being added to the model's history (0 = no limit)
+    #[serde(default = "default_max_tool_result_chars")]
+    pub max_tool_result_chars: usize,
+    /// How sql_select results are shown to the model (json by default). The UI
+    /// always receives the JSON result.
+    #[serde(default)]
+    pub sql_result_format: SqlResultFormat,
+    /// Replace oversized MCP tool results in the model's history with a model-written
+    /// summary (the UI still shows the raw result)
+    #[serde(default)]
+    pub summarize_large_tool_results: bool,
+    /// MCP tool results longer than this many characters are summarized when
+    /// `summarize_large_tool_results` is on
+    #[serde(default = "default_tool_result_summary_threshold_chars")]
+    pub tool_result_summary_threshold_chars: usize,
+    /// System prompts longer than this many characters have their lowest-priority
+    /// tool descriptions dropped until they fit (0 = no limit)
+    #[serde(default = "default_max_system_prompt_chars")]
+    pub max_system_prompt_chars: usize,
+    /// Summarize older turns into a single note once the history nears the
+    /// model's context size
+    #[serde(default = "default_history_compaction_enabled")]
+    pub history_compaction_enabled: bool,
+    /// Most recent turns kept verbatim when the history is compacted
+    #[serde(default = "default_history_compaction_keep_turns")]
+    pub history_compaction_keep_turns: usize,
+    /// How many times a chat request is retried (with exponential backoff) when
+    /// it can't be sent or the model produces no first token
+    #[serde(default = "default_foundry_chat_retries")]
+    pub foundry_chat_retries: u32,
+    /// Seconds to wait for the first streamed token before retrying (0 = wait forever)
+    #[serde(default = "default_first_token_timeout_secs")]
+    pub first_token_timeout_secs: u64,
     /// Whether python-driven tool calling is allowed. If false, we will not
     /// execute tool calls even if python_execution is enabled.
     #[serde(default = "default_python_tool_calling_enabled")]
     pub python_tool_calling_enabled: bool,
+    /// Wall-clock timeout for a single python_execution call in milliseconds (0 = no limit)
+    #[serde(default = "default_python_execution_timeout_ms")]
+    pub python_execution_timeout_ms: u64,
+    /// Ceiling on memory a single python_execution run may allocate, in MB (0 = no limit)
+    #[serde(default = "default_python_memory_limit_mb")]
+    pub python_memory_limit_mb: u64,
+    /// How python_execution results are rendered for the model (text by default)
+    #[serde(default)]
+    pub python_result_format: ResultFormat,
+    /// Where python_execution code runs (in-process RustPython by default)
+    #[serde(default)]
+    pub python_sandbox_backend: PythonSandboxBackend,
+    /// Run every python code block in a response, in order, as one program so
+    /// later blocks can use earlier blocks' variables (off = first block only)
+    #[serde(default)]
+    pub python_multi_block: bool,
+    /// Safe mode: no code execution, every MCP tool call needs approval and the
+    /// python sandbox drops the configured module list and SAFE_MODE_DENIED_MODULES
+    #[serde(default)]
+    pub safe_mode: bool,
+    /// Carry plain-data Python variables between python_execution calls within one turn
+    #[serde(default)]
+    pub python_session_persistence_enabled: bool,
+    /// Modules python_execution code may import, replacing the sandbox defaults
+    /// (None = defaults). os, sys, subprocess and socket are always rejected.
+    #[serde(default)]
+    pub python_allowed_modules: Option<Vec<String>>,
     /// Whether to allow legacy <tool_call> parsing. Disabled by default.
     #[serde(default)]
     pub legacy_tool_call_format_enabled: bool,
@@ -646,6 +1190,9 @@ pub struct AppSettings {
     /// Configuration for Google MCP Database Toolbox integration
     #[serde(default)]
     pub database_toolbox: DatabaseToolboxConfig,
+    /// Tables embedded at once during a schema refresh (default: number of CPUs)
+    #[serde(default = "default_schema_embed_concurrency")]
+    pub schema_embed_concurrency: usize,
     
     // ============ Relevancy Thresholds for State Machine ============
     
@@ -695,14 +1242,108 @@ fn default_tool_search_max_results() -> usize {
     3
 }
 
+fn default_tool_search_min_relevance() -> f32 {
+    0.3
+}
+
+fn default_auto_discovery_min_prompt_chars() -> usize {
+    12
+}
+
+/// Smallest allowed value for `max_tool_iterations`
+pub const MIN_TOOL_ITERATIONS: usize = 1;
+/// Largest allowed value for `max_tool_iterations`
+pub const MAX_TOOL_ITERATIONS: usize = 100;
+
+/// Built-in tools that run code and are removed in safe mode
+pub const SAFE_MODE_BLOCKED_BUILTINS: &[&str] = &["python_execution"];
+
+/// Sandbox default modules that are not importable in safe mode
+pub const SAFE_MODE_DENIED_MODULES: &[&str] =
+    &["random", "_random", "hashlib", "base64", "binascii", "html"];
+
+fn default_max_tool_iterations() -> usize {
+    20
+}
+
+fn default_max_tool_calls_per_turn() -> usize {
+    40
+}
+
+fn default_strict_turn_tool_scope() -> bool {
+    true
+}
+
+fn default_materialized_tools_ttl_secs() -> u64 {
+    600
+}
+
+fn default_mcp_tool_timeout_secs() -> u64 {
+    120
+}
+
+fn default_tool_heartbeat_interval_ms() -> u64 {
+    1000
+}
+
+fn default_mcp_health_check_interval_secs() -> u64 {
+    60
+}
+
+fn default_chat_autosave_interval_secs() -> u64 {
+    3
+}
+
+fn default_max_tool_result_chars() -> usize {
+    20_000
+}
+
+fn default_tool_result_summary_threshold_chars() -> usize {
+    8_000
+}
+
+fn default_max_system_prompt_chars() -> usize {
+    0
+}
+
+fn default_history_compaction_enabled() -> bool {
+    true
+}
+
+fn default_history_compaction_keep_turns() -> usize {
+    4
+}
+
+fn default_foundry_chat_retries() -> u32 {
+    2
+}
+
+fn default_first_token_timeout_secs() -> u64 {
+    120
+}
+
 fn default_python_tool_calling_enabled() -> bool {
     true
 }
 
+fn default_python_execution_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_python_memory_limit_mb() -> u64 {
+    (python_sandbox::protocol::DEFAULT_MEMORY_LIMIT_BYTES / (1024 * 1024)) as u64
+}
+
 fn default_tool_use_examples_max() -> usize {
     2
 }
 
+fn default_schema_embed_concurrency() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
 fn default_rag_chunk_min_relevancy() -> f32 {
     0.3
 }
@@ -715,7 +1356,111 @@ fn default_rag_dominant_threshold() -> f32 {
     0.6
 }
 
+/// reasoning_effort to send to `model`: its override if one is set, else
+/// `requested`. None when the model reports it doesn't support the parameter
+/// (`supported` is None when its capabilities are unknown).
+pub fn resolve_reasoning_effort(
+    overrides: &HashMap<String, String>,
+    model: &str,
+    requested: &str,
+    supported: Option<bool>,
+) -> Option<String> {
+    if supported == Some(false) {
+        return None;
+    }
+    Some(
+        overrides
+            .get(model)
+            .cloned()
+            .unwrap_or_else(|| requested.to_string()),
+    )
+}
+
 impl AppSettings {
+    /// Whether safe mode removes the built-in tool `name`.
+    pub fn safe_mode_blocks_builtin(&self, name: &str) -> bool {
+        self.safe_mode && SAFE_MODE_BLOCKED_BUILTINS.contains(&name)
+    }
+
+    /// Modules python code may import (None = sandbox defaults). Safe mode
+    /// ignores python_allowed_modules and also drops SAFE_MODE_DENIED_MODULES.
+    pub fn resolved_python_allowed_modules(&self) -> Option<Vec<String>> {
+        if self.safe_mode {
+            let mut modules = python_sandbox::sandbox::resolve_allowed_modules(None);
+            modules.retain(|m| !SAFE_MODE_DENIED_MODULES.contains(&m.as_str()));
+            return Some(modules);
+        }
+        self.python_allowed_modules
+            .as_deref()
+            .map(|modules| python_sandbox::sandbox::resolve_allowed_modules(Some(modules)))
+    }
+
+    /// Memory ceiling for python_execution runs (None = no limit)
+    pub fn python_memory_limit_bytes(&self) -> Option<usize> {
+        match self.python_memory_limit_mb {
+            0 => None,
+            mb => Some((mb as usize).saturating_mul(1024 * 1024)),
+        }
+    }
+
+    /// The documented sandbox modules plus any opted in via python_allowed_modules,
+    /// each marked with whether the current settings allow importing it
+    pub fn python_module_catalog(&self) -> PythonModuleCatalog {
+        use python_sandbox::sandbox::{
+            module_description, resolve_allowed_modules, ALLOWED_MODULES, MODULE_DESCRIPTIONS,
+            NEVER_ALLOWED_MODULES,
+        };
+
+        let enabled = self
+            .resolved_python_allowed_modules()
+            .unwrap_or_else(|| resolve_allowed_modules(None));
+        let mut modules: Vec<PythonModuleInfo> = MODULE_DESCRIPTIONS
+            .iter()
+            .map(|(name, description)| PythonModuleInfo {
+                name: name.to_string(),
+                description: description.to_string(),
+                default_enabled: true,
+                enabled: enabled.iter().any(|m| m == name),
+            })
+            .collect();
+
+        let configured = self
+            .python_allowed_modules
+            .as_deref()
+            .map(|requested| resolve_allowed_modules(Some(requested)))
+            .unwrap_or_default();
+        for name in configured {
+            if modules.iter().any(|m| m.name == name) {
+                continue;
+            }
+            modules.push(PythonModuleInfo {
+                description: module_description(&name)
+                    .unwrap_or("Added in python_allowed_modules")
+                    .to_string(),
+                default_enabled: ALLOWED_MODULES.contains(&name.as_str()),
+                enabled: enabled.contains(&name),
+                name,
+            });
+        }
+
+        PythonModuleCatalog {
+            modules,
+            denied: NEVER_ALLOWED_MODULES.iter().map(|m| m.to_string()).collect(),
+        }
+    }
+
+    /// Make every MCP tool call ask for approval when safe mode is on.
+    /// Database sources keep auto-approve since they only run read-only SELECTs.
+    pub fn apply_safe_mode_approvals(&self, configs: &mut [McpServerConfig]) {
+        if !self.safe_mode {
+            return;
+        }
+        for config in configs.iter_mut().filter(|c| !c.is_database_source) {
+            config.auto_approve_tools = false;
+            config.auto_approve_tool_overrides.clear();
+        }
+    }
+
     /// Check if a built-in tool is marked as Always On.
     pub fn is_builtin_always_on(&self, name: &str) -> bool {
         self.always_on_builtin_tools.contains(&name.to_string())
@@ -782,6 +1527,11 @@ impl AppSettings {
             defer_tools: source.defer_tools,
             python_name: None,
             is_database_source: true,
+            tool_timeout_secs: None,
+            auto_approve_tool_overrides: HashMap::new(),
+            validate_tool_arguments: true,
+            auto_reconnect: false,
+            roots: Vec::new(),
         }
     }
 
@@ -844,6 +1594,11 @@ pub fn default_mcp_test_server() -> McpServerConfig {
             defer_tools: false,       // Expose tools immediately for quick testing
             python_name: None,
             is_database_source: false,
+            tool_timeout_secs: None,
+            auto_approve_tool_overrides: HashMap::new(),
+            validate_tool_arguments: true,
+            auto_reconnect: false,
+            roots: Vec::new(),
         }
     } else {
         // Fall back to cargo run if binary not found
@@ -867,6 +1622,11 @@ pub fn default_mcp_test_server() -> McpServerConfig {
             defer_tools: false,       // Expose tools immediately for quick testing
             python_name: None,
             is_database_source: false,
+            tool_timeout_secs: None,
+            auto_approve_tool_overrides: HashMap::new(),
+            validate_tool_arguments: true,
+            auto_reconnect: false,
+            roots: Vec::new(),
         }
     };
     enforce_python_name(&mut base);
@@ -877,18 +1637,56 @@ impl Default for AppSettings {
     fn default() -> Self {
         Self {
             system_prompt: default_system_prompt(),
+            system_prompt_prefix: None,
+            system_prompt_suffix: None,
             selected_model: None,
             mcp_servers: vec![default_mcp_test_server()],
             chat_format_default: default_chat_format(),
             chat_format_overrides: HashMap::new(),
+            reasoning_effort_overrides: HashMap::new(),
             tool_call_formats: ToolCallFormatConfig::default(),
             tool_system_prompts: HashMap::new(),
+            tool_description_overrides: HashMap::new(),
             tool_search_max_results: default_tool_search_max_results(),
+            tool_search_min_relevance: default_tool_search_min_relevance(),
+            auto_discovery_min_prompt_chars: default_auto_discovery_min_prompt_chars(),
+            max_tool_iterations: default_max_tool_iterations(),
+            max_tool_calls_per_turn: default_max_tool_calls_per_turn(),
+            parallel_tool_calls: false,
+            batch_approval_mode: false,
+            tool_call_ids_in_text: false,
+            strict_turn_tool_scope: default_strict_turn_tool_scope(),
+            materialized_tools_ttl_secs: default_materialized_tools_ttl_secs(),
+            warm_start_on_launch: false,
+            reasoning_events_enabled: false,
+            mcp_tool_timeout_secs: default_mcp_tool_timeout_secs(),
+            tool_heartbeat_interval_ms: default_tool_heartbeat_interval_ms(),
+            mcp_health_check_interval_secs: default_mcp_health_check_interval_secs(),
+            chat_autosave_interval_secs: default_chat_autosave_interval_secs(),
+            use_format_stop_sequences: false,
+            max_tool_result_chars: default_max_tool_result_chars(),
+            sql_result_format: SqlResultFormat::Json,
+            summarize_large_tool_results: false,
+            tool_result_summary_threshold_chars: default_tool_result_summary_threshold_chars(),
+            max_system_prompt_chars: default_max_system_prompt_chars(),
+            history_compaction_enabled: default_history_compaction_enabled(),
+            history_compaction_keep_turns: default_history_compaction_keep_turns(),
+            foundry_chat_retries: default_foundry_chat_retries(),
+            first_token_timeout_secs: default_first_token_timeout_secs(),
             python_tool_calling_enabled: default_python_tool_calling_enabled(),
+            python_execution_timeout_ms: default_python_execution_timeout_ms(),
+            python_memory_limit_mb: default_python_memory_limit_mb(),
+            python_result_format: ResultFormat::Text,
+            python_sandbox_backend: PythonSandboxBackend::InProcess,
+            python_multi_block: false,
+            safe_mode: false,
+            python_session_persistence_enabled: false,
+            python_allowed_modules: None,
             legacy_tool_call_format_enabled: false,
             tool_use_examples_enabled: false,
             tool_use_examples_max: default_tool_use_examples_max(),
             database_toolbox: DatabaseToolboxConfig::default(),
+            schema_embed_concurrency: default_schema_embed_concurrency(),
             // Relevancy thresholds
             rag_chunk_min_relevancy: default_rag_chunk_min_relevancy(),
             schema_relevancy_threshold: default_schema_relevancy_threshold(),
@@ -1133,6 +1931,7 @@ pub fn default_demo_database_source() -> DatabaseSourceConfig {
         sql_dialect: Some("SQLite".to_string()),
         dataset_allowlist: None,
         table_allowlist: None,
+        skip_table_pattern: None,
     }
 }
 
@@ -1290,6 +2089,10 @@ pub async fn load_settings() -> AppSettings {
     // Normalize tool format config after load
     settings.tool_call_formats.normalize();
 
+    settings.max_tool_iterations = settings
+        .max_tool_iterations
+        .clamp(MIN_TOOL_ITERATIONS, MAX_TOOL_ITERATIONS);
+
     // Ensure default servers exist (migration)
     ensure_default_servers(&mut settings);
 
@@ -1322,10 +2125,51 @@ pub async fn save_settings(settings: &AppSettings) -> Result<(), String> {
 mod tests {
     use super::*;
 
+    fn test_env(name: &str) -> Option<String> {
+        match name {
+            "API_TOKEN" => Some("s3cret".to_string()),
+            "HOME" => Some("/home/ada".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_env_vars() {
+        let mut config = McpServerConfig::new("files".to_string(), "Files".to_string());
+        config.command = Some("$HOME/bin/server".to_string());
+        config.args = vec!["--root=${HOME}/data".to_string(), "--price=$$5".to_string()];
+        config.env.insert("TOKEN".to_string(), "Bearer ${API_TOKEN}".to_string());
+        config.env.insert("LITERAL".to_string(), "cost $ 5".to_string());
+
+        resolve_env_vars_with(&mut config, &test_env).unwrap();
+        assert_eq!(config.command.as_deref(), Some("/home/ada/bin/server"));
+        assert_eq!(config.args, vec!["--root=/home/ada/data", "--price=$5"]);
+        assert_eq!(config.env["TOKEN"], "Bearer s3cret");
+        assert_eq!(config.env["LITERAL"], "cost $ 5");
+    }
+
+    #[test]
+    fn test_resolve_env_vars_unresolved() {
+        let mut config = McpServerConfig::new("files".to_string(), "Files".to_string());
+        config.args = vec!["--key=${MISSING_KEY}".to_string()];
+        let err = resolve_env_vars_with(&mut config, &test_env).unwrap_err();
+        assert!(err.contains("MCP server 'Files'"));
+        assert!(err.contains("'MISSING_KEY' is not set"));
+        // Nothing is changed on failure
+        assert_eq!(config.args, vec!["--key=${MISSING_KEY}"]);
+
+        assert!(expand_env_refs("${API_TOKEN", &test_env)
+            .unwrap_err()
+            .starts_with("Unterminated"));
+        assert_eq!(expand_env_refs("$$API_TOKEN", &test_env).unwrap(), "$API_TOKEN");
+    }
+
     #[test]
     fn test_default_settings() {
         let settings = AppSettings::default();
         assert!(!settings.system_prompt.is_empty());
+        assert!(settings.system_prompt_prefix.is_none());
+        assert!(settings.system_prompt_suffix.is_none());
         // Default settings include the mcp-test-server (disabled by default)
         assert!(settings
             .mcp_servers
@@ -1340,6 +2184,7 @@ mod tests {
                 .enabled
         );
         assert!(settings.tool_system_prompts.is_empty());
+        assert!(settings.tool_description_overrides.is_empty());
         // python tool calling defaults
         assert!(settings.python_tool_calling_enabled);
         assert!(!settings.legacy_tool_call_format_enabled);
@@ -1347,6 +2192,40 @@ mod tests {
             settings.tool_search_max_results,
             default_tool_search_max_results()
         );
+        assert_eq!(settings.tool_search_min_relevance, 0.3);
+        assert_eq!(settings.auto_discovery_min_prompt_chars, 12);
+        assert_eq!(settings.max_tool_iterations, default_max_tool_iterations());
+        assert_eq!(settings.max_tool_calls_per_turn, 40);
+        assert!(!settings.parallel_tool_calls);
+        assert!(!settings.batch_approval_mode);
+        assert!(!settings.tool_call_ids_in_text);
+        assert!(settings.strict_turn_tool_scope);
+        assert_eq!(settings.materialized_tools_ttl_secs, 600);
+        assert!(!settings.warm_start_on_launch);
+        assert!(!settings.reasoning_events_enabled);
+        assert_eq!(settings.mcp_tool_timeout_secs, default_mcp_tool_timeout_secs());
+        assert_eq!(settings.tool_heartbeat_interval_ms, 1000);
+        assert_eq!(settings.mcp_health_check_interval_secs, 60);
+        assert_eq!(settings.chat_autosave_interval_secs, 3);
+        assert!(!settings.use_format_stop_sequences);
+        assert_eq!(settings.python_memory_limit_mb, 256);
+        assert_eq!(settings.python_memory_limit_bytes(), Some(256 * 1024 * 1024));
+        assert_eq!(settings.max_tool_result_chars, default_max_tool_result_chars());
+        assert_eq!(settings.sql_result_format, SqlResultFormat::Json);
+        assert!(!settings.summarize_large_tool_results);
+        assert_eq!(settings.tool_result_summary_threshold_chars, 8_000);
+        assert_eq!(settings.max_system_prompt_chars, 0);
+        assert!(settings.history_compaction_enabled);
+        assert_eq!(settings.history_compaction_keep_turns, 4);
+        assert_eq!(settings.foundry_chat_retries, 2);
+        assert_eq!(settings.first_token_timeout_secs, default_first_token_timeout_secs());
+        assert_eq!(settings.python_result_format, ResultFormat::Text);
+        assert_eq!(
+            settings.python_sandbox_backend,
+            PythonSandboxBackend::InProcess
+        );
+        assert!(!settings.python_multi_block);
+        assert!(!settings.safe_mode);
         assert!(!settings.tool_use_examples_enabled);
         assert_eq!(
             settings.tool_use_examples_max,
@@ -1355,6 +2234,7 @@ mod tests {
         assert_eq!(settings.tool_call_formats, ToolCallFormatConfig::default());
         assert_eq!(settings.chat_format_default, default_chat_format());
         assert!(settings.chat_format_overrides.is_empty());
+        assert!(settings.reasoning_effort_overrides.is_empty());
         assert!(settings.always_on_builtin_tools.is_empty());
     }
 
@@ -1373,6 +2253,11 @@ mod tests {
             defer_tools: true,
             python_name: Some("test_server".to_string()),
             is_database_source: false,
+            tool_timeout_secs: None,
+            auto_approve_tool_overrides: HashMap::new(),
+            validate_tool_arguments: true,
+            auto_reconnect: false,
+            roots: Vec::new(),
         });
 
         let json = serde_json::to_string(&settings).unwrap();
@@ -1392,6 +2277,168 @@ mod tests {
         let settings = AppSettings::default();
         assert!(!settings.database_toolbox.enabled);
         assert!(settings.always_on_builtin_tools.is_empty());
+        assert_eq!(
+            settings.schema_embed_concurrency,
+            default_schema_embed_concurrency()
+        );
+    }
+
+    #[test]
+    fn test_mcp_tool_timeout_override() {
+        let mut server = McpServerConfig::new("srv".to_string(), "Server".to_string());
+        assert_eq!(server.tool_timeout(30), Some(std::time::Duration::from_secs(30)));
+        assert_eq!(server.tool_timeout(0), None);
+
+        server.tool_timeout_secs = Some(5);
+        assert_eq!(server.tool_timeout(30), Some(std::time::Duration::from_secs(5)));
+
+        // An explicit 0 disables the timeout even when a global default is set
+        server.tool_timeout_secs = Some(0);
+        assert_eq!(server.tool_timeout(30), None);
+    }
+
+    #[test]
+    fn test_resolve_reasoning_effort() {
+        let mut overrides = HashMap::new();
+        overrides.insert("phi-4-mini-reasoning".to_string(), "medium".to_string());
+
+        // Pass-through when the model has no override
+        assert_eq!(
+            resolve_reasoning_effort(&overrides, "phi-4", "high", Some(true)),
+            Some("high".to_string())
+        );
+        assert_eq!(
+            resolve_reasoning_effort(&overrides, "phi-4", "high", None),
+            Some("high".to_string())
+        );
+        // The per-model value replaces the requested one
+        assert_eq!(
+            resolve_reasoning_effort(&overrides, "phi-4-mini-reasoning", "high", Some(true)),
+            Some("medium".to_string())
+        );
+        // Dropped when the model reports no support, override or not
+        assert_eq!(
+            resolve_reasoning_effort(&overrides, "qwen2.5-7b", "high", Some(false)),
+            None
+        );
+        assert_eq!(
+            resolve_reasoning_effort(&overrides, "phi-4-mini-reasoning", "high", Some(false)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_tool_call_format_names_round_trip() {
+        for format in ToolCallFormatName::ALL {
+            assert_eq!(ToolCallFormatName::from_name(format.as_str()), Some(format));
+            let serialized = serde_json::to_value(format.info()).unwrap();
+            assert_eq!(serialized["id"], format.as_str());
+        }
+        assert_eq!(ToolCallFormatName::from_name("xml"), None);
+        assert!(ToolCallFormatName::CodeMode.info().requires_python_execution);
+        assert!(ToolCallFormatName::Native.info().requires_native_support);
+        assert!(ToolCallFormatName::Anthropic.info().requires_native_support);
+    }
+
+    #[test]
+    fn test_python_module_catalog() {
+        let mut settings = AppSettings::default();
+        let catalog = settings.python_module_catalog();
+        let json = catalog.modules.iter().find(|m| m.name == "json").unwrap();
+        assert!(json.default_enabled && json.enabled);
+        assert!(!json.description.is_empty());
+        assert!(catalog.modules.iter().all(|m| !m.name.starts_with('_')));
+        assert_eq!(catalog.denied, vec!["os", "sys", "subprocess", "socket"]);
+
+        // A configured list narrows the defaults and adds opt-in modules
+        settings.python_allowed_modules = Some(vec!["json".to_string(), "csv".to_string()]);
+        let catalog = settings.python_module_catalog();
+        let find = |name: &str| catalog.modules.iter().find(|m| m.name == name).unwrap();
+        assert!(find("json").enabled);
+        assert!(!find("math").enabled && find("math").default_enabled);
+        assert!(find("csv").enabled && !find("csv").default_enabled);
+
+        // Safe mode ignores the configured list and drops its denied modules
+        settings.safe_mode = true;
+        let catalog = settings.python_module_catalog();
+        let find = |name: &str| catalog.modules.iter().find(|m| m.name == name).unwrap();
+        assert!(find("math").enabled);
+        assert!(!find("random").enabled);
+        assert!(!find("csv").enabled);
+    }
+
+    #[test]
+    fn test_stop_sequences_follow_enabled_formats() {
+        let mut config = ToolCallFormatConfig::default();
+        assert_eq!(config.stop_sequences(), vec!["</tool_call>".to_string()]);
+
+        config.disable(ToolCallFormatName::Hermes);
+        assert!(config.stop_sequences().is_empty());
+
+        let config = ToolCallFormatConfig {
+            enabled: ToolCallFormatName::ALL.to_vec(),
+            primary: ToolCallFormatName::Native,
+        };
+        let expected: Vec<String> = ToolCallFormatName::ALL
+            .iter()
+            .filter_map(|f| f.stop_sequence())
+            .map(str::to_string)
+            .collect();
+        assert_eq!(config.stop_sequences(), expected);
+    }
+
+    #[test]
+    fn test_anthropic_chat_format_swaps_native_for_tool_use_blocks() {
+        let mut overrides = HashMap::new();
+        overrides.insert("claude-local".to_string(), ChatFormatName::AnthropicMessages);
+        assert_eq!(
+            resolve_chat_format(&overrides, ChatFormatName::OpenaiCompletions, "claude-local"),
+            ChatFormatName::AnthropicMessages
+        );
+        assert_eq!(
+            resolve_chat_format(&overrides, ChatFormatName::OpenaiCompletions, "phi-4"),
+            ChatFormatName::OpenaiCompletions
+        );
+
+        let mut config = ToolCallFormatConfig {
+            enabled: vec![ToolCallFormatName::Native, ToolCallFormatName::Hermes],
+            primary: ToolCallFormatName::Native,
+        };
+        config.use_anthropic_blocks();
+        assert_eq!(
+            config.enabled,
+            vec![ToolCallFormatName::Anthropic, ToolCallFormatName::Hermes]
+        );
+        assert_eq!(config.primary, ToolCallFormatName::Anthropic);
+
+        // Without native calls there is nothing to swap
+        let mut config = ToolCallFormatConfig {
+            enabled: vec![ToolCallFormatName::Hermes],
+            primary: ToolCallFormatName::Hermes,
+        };
+        config.use_anthropic_blocks();
+        assert_eq!(config.enabled, vec![ToolCallFormatName::Hermes]);
+    }
+
+    #[test]
+    fn test_skip_table_regex() {
+        let mut source = DatabaseSourceConfig::new(
+            "bq".to_string(),
+            "Warehouse".to_string(),
+            SupportedDatabaseKind::Bigquery,
+        );
+        let skip = source.skip_table_regex().unwrap();
+        assert!(!skip.is_match("orders2"));
+        assert!(!skip.is_match("table2"));
+        assert!(skip.is_match("events_20240101"));
+
+        source.skip_table_pattern = Some("^tmp_".to_string());
+        let skip = source.skip_table_regex().unwrap();
+        assert!(skip.is_match("tmp_orders"));
+        assert!(!skip.is_match("events_20240101"));
+
+        source.skip_table_pattern = Some("(".to_string());
+        assert!(source.skip_table_regex().unwrap_err().contains("Warehouse"));
     }
 
     #[tokio::test]
@@ -230,6 +230,8 @@ pub struct SettingsStateMachine {
     tool_availability: ToolAvailability,
     /// Relevancy thresholds (from settings)
     relevancy_thresholds: RelevancyThresholds,
+    /// Safe mode (no code execution) is on
+    safe_mode: bool,
 }
 
 /// Relevancy thresholds from settings (duplicated from agentic_state to avoid circular deps)
@@ -267,6 +269,7 @@ impl SettingsStateMachine {
             enabled_capabilities,
             tool_availability,
             relevancy_thresholds,
+            safe_mode: settings.safe_mode,
         }
     }
 
@@ -290,6 +293,11 @@ impl SettingsStateMachine {
         &self.relevancy_thresholds
     }
 
+    /// Whether safe mode removed this built-in
+    pub fn safe_mode_blocks_builtin(&self, name: &str) -> bool {
+        self.safe_mode && crate::settings::SAFE_MODE_BLOCKED_BUILTINS.contains(&name)
+    }
+
     /// Check if a specific capability is enabled
     pub fn is_capability_enabled(&self, cap: Capability) -> bool {
         self.enabled_capabilities.contains(&cap)
@@ -328,7 +336,9 @@ impl SettingsStateMachine {
         if !turn_context.attached_tabular_files.is_empty() {
             enabled_modes.insert(SimplifiedMode::Code);
             // Implicitly enable python_execution for tabular data analysis
-            if filter.builtin_allowed("python_execution") {
+            if filter.builtin_allowed("python_execution")
+                && !settings.safe_mode_blocks_builtin("python_execution")
+            {
                 if !enabled_tools.contains(&"python_execution".to_string()) {
                     enabled_tools.push("python_execution".to_string());
                 }
@@ -340,7 +350,7 @@ impl SettingsStateMachine {
             // tool_key is "builtin::name" or "serverId::name"
             if tool_key.starts_with("builtin::") {
                 let name = &tool_key["builtin::".len()..];
-                if filter.builtin_allowed(name) {
+                if filter.builtin_allowed(name) && !settings.safe_mode_blocks_builtin(name) {
                     // Check if it's already added (like sql_select)
                     if !enabled_tools.contains(&name.to_string()) {
                         enabled_tools.push(name.to_string());
@@ -441,9 +451,10 @@ impl SettingsStateMachine {
             caps.insert(Capability::SqlQuery);
         }
 
-        // Python execution - requires presence in always_on_builtin_tools
+        // Python execution - requires presence in always_on_builtin_tools (never in safe mode)
         if always_on.contains(&"python_execution".to_string())
             && filter.builtin_allowed("python_execution") 
+            && !settings.safe_mode_blocks_builtin("python_execution")
         {
             caps.insert(Capability::PythonExecution);
         }
@@ -482,6 +493,7 @@ impl SettingsStateMachine {
         // Check each built-in - requires presence in always_on_builtin_tools
         if always_on.contains(&"python_execution".to_string())
             && filter.builtin_allowed("python_execution")
+            && !settings.safe_mode_blocks_builtin("python_execution")
             && settings
                 .tool_call_formats
                 .is_enabled(ToolCallFormatName::CodeMode)
@@ -65,6 +65,9 @@ pub struct AgenticStateMachine {
     state_history: Vec<AgenticState>,
     /// Base system prompt (user-configured)
     base_prompt: String,
+    /// User instructions kept before and after the generated prompt in every state
+    prompt_prefix: Option<String>,
+    prompt_suffix: Option<String>,
     
     // === Prompt Context (for unified prompt generation) ===
     
@@ -98,6 +101,8 @@ pub struct AgenticStateMachine {
     auto_tool_search: Option<crate::tools::tool_search::ToolSearchOutput>,
     /// Auto-discovered schema from schema_search (for this turn)
     auto_schema_search: Option<crate::tools::schema_search::SchemaSearchOutput>,
+    /// Tool descriptions dropped to fit the system prompt budget
+    omitted_tool_count: usize,
 }
 
 impl AgenticStateMachine {
@@ -142,6 +147,8 @@ impl AgenticStateMachine {
             thresholds,
             state_history: Vec::new(),
             base_prompt: prompt_context.base_prompt,
+            prompt_prefix: prompt_context.prepend.filter(|p| !p.trim().is_empty()),
+            prompt_suffix: prompt_context.append.filter(|s| !s.trim().is_empty()),
             mcp_context: prompt_context.mcp_context,
             tool_call_format: prompt_context.tool_call_format,
             model_tool_format: prompt_context.model_tool_format,
@@ -155,6 +162,7 @@ impl AgenticStateMachine {
             turn_config: None,
             auto_tool_search: None,
             auto_schema_search: None,
+            omitted_tool_count: 0,
         }
     }
 
@@ -336,6 +344,166 @@ impl AgenticStateMachine {
         self.auto_schema_search = schema_search;
     }
 
+    /// Auto-discovery context for this turn (tool_search, schema_search).
+    pub fn auto_discovery_context(
+        &self,
+    ) -> (
+        Option<&crate::tools::tool_search::ToolSearchOutput>,
+        Option<&crate::tools::schema_search::SchemaSearchOutput>,
+    ) {
+        (self.auto_tool_search.as_ref(), self.auto_schema_search.as_ref())
+    }
+
+    /// Replace the computed turn-start state with the one named `requested`.
+    ///
+    /// Names match the state previews ("SQL Retrieval") ignoring case, spaces
+    /// and underscores, so "SqlRetrieval" and "sql_retrieval" work too. Only
+    /// turn-start states possible with the enabled capabilities are accepted.
+    pub fn force_initial_state(&mut self, requested: &str) -> Result<(), String> {
+        let normalize = |name: &str| -> String {
+            name.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase()
+        };
+        let valid: Vec<String> = self
+            .get_possible_states()
+            .into_iter()
+            .map(|preview| preview.name)
+            .filter(|name| Self::turn_start_state_named(name).is_some())
+            .collect();
+        let name = valid
+            .iter()
+            .find(|name| normalize(name) == normalize(requested))
+            .ok_or_else(|| {
+                format!(
+                    "Cannot force state '{}'; valid states: {}",
+                    requested,
+                    valid.join(", ")
+                )
+            })?;
+
+        // Keep the computed state (and its discovered tables) if it already matches
+        if self.current_state.name() != name {
+            if let Some(state) = Self::turn_start_state_named(name) {
+                println!("[StateMachine] Forcing initial state: {}", name);
+                self.transition_to(state);
+            }
+        }
+        Ok(())
+    }
+
+    /// Empty turn-start state for a state preview name
+    fn turn_start_state_named(name: &str) -> Option<AgenticState> {
+        match name {
+            "Conversational" => Some(AgenticState::Conversational),
+            "RAG Retrieval" => Some(AgenticState::RagRetrieval {
+                max_chunk_relevancy: 0.0,
+                schema_relevancy: 0.0,
+            }),
+            "SQL Retrieval" => Some(AgenticState::SqlRetrieval {
+                discovered_tables: Vec::new(),
+                max_table_relevancy: 0.0,
+            }),
+            "Tool Orchestration" => Some(AgenticState::ToolOrchestration {
+                materialized_tools: Vec::new(),
+            }),
+            "Code Execution" => Some(AgenticState::CodeExecution {
+                available_tools: Vec::new(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Restore a checkpointed state when resuming an interrupted turn.
+    pub fn restore_state(&mut self, state: AgenticState) {
+        self.transition_to(state);
+    }
+
+    /// Trim tool descriptions until the system prompt fits in `max_chars`.
+    ///
+    /// Built-in tools and per-chat attached tools are never dropped. Other
+    /// active MCP tools go first, then auto-discovered tools from the lowest
+    /// relevance score up. Returns the total number of omitted tools.
+    pub fn fit_system_prompt_to_budget(&mut self, max_chars: usize) -> usize {
+        while self.build_system_prompt().chars().count() > max_chars {
+            if !self.drop_lowest_priority_tool() {
+                break;
+            }
+            self.omitted_tool_count += 1;
+        }
+        self.omitted_tool_count
+    }
+
+    /// Number of tool descriptions omitted from the system prompt.
+    pub fn omitted_tool_count(&self) -> usize {
+        self.omitted_tool_count
+    }
+
+    /// Remove the least relevant droppable tool. Returns false if none is left.
+    fn drop_lowest_priority_tool(&mut self) -> bool {
+        let discovered: Vec<(String, String, f32)> = self
+            .auto_tool_search
+            .as_ref()
+            .map(|output| {
+                output
+                    .tools
+                    .iter()
+                    .map(|t| (t.server_id.clone(), t.name.clone(), t.score))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let is_protected = |server_id: &str, name: &str| {
+            self.attached_tools.contains(&format!("{}::{}", server_id, name))
+        };
+
+        // Active tools that were neither discovered nor attached, last first
+        let undiscovered = self
+            .mcp_context
+            .active_tools
+            .iter()
+            .rev()
+            .find_map(|(server_id, tools)| {
+                tools
+                    .iter()
+                    .rev()
+                    .find(|tool| {
+                        !is_protected(server_id, &tool.name)
+                            && !discovered
+                                .iter()
+                                .any(|(s, n, _)| s == server_id && n == &tool.name)
+                    })
+                    .map(|tool| (server_id.clone(), tool.name.clone()))
+            });
+
+        let victim = undiscovered.or_else(|| {
+            discovered
+                .iter()
+                .filter(|(s, n, _)| !is_protected(s, n))
+                .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal))
+                .map(|(s, n, _)| (s.clone(), n.clone()))
+        });
+
+        let Some((server_id, name)) = victim else {
+            return false;
+        };
+        println!(
+            "[StateMachine] Omitting tool {}::{} to fit the system prompt budget",
+            server_id, name
+        );
+
+        if let Some(output) = self.auto_tool_search.as_mut() {
+            output.tools.retain(|t| !(t.server_id == server_id && t.name == name));
+        }
+        for (sid, tools) in self.mcp_context.active_tools.iter_mut() {
+            if *sid == server_id {
+                tools.retain(|t| t.name != name);
+            }
+        }
+        self.mcp_context.active_tools.retain(|(_, tools)| !tools.is_empty());
+        true
+    }
+
     /// Transition to a new state, recording history.
     fn transition_to(&mut self, new_state: AgenticState) {
         // Record current state in history
@@ -374,11 +542,20 @@ impl AgenticStateMachine {
                 }
             }
 
-            StateEvent::SqlExecuted { results: _, row_count } => {
+            StateEvent::SqlExecuted { results, row_count } => {
+                let query_context = if results.truncated {
+                    format!(
+                        "{} rows returned, with more rows remaining (call sql_select again \
+                        with a larger `offset` to page through them)",
+                        row_count
+                    )
+                } else {
+                    format!("{} rows returned", row_count)
+                };
                 AgenticState::SqlResultCommentary {
                     results_shown_to_user: true,
                     row_count,
-                    query_context: format!("{} rows returned", row_count),
+                    query_context,
                 }
             }
 
@@ -445,6 +622,38 @@ impl AgenticStateMachine {
         &self.thresholds
     }
 
+    /// Explain why built-in `tool_name` can't run because it is turned off for
+    /// this turn. None when the tool is enabled or isn't a built-in.
+    pub fn disabled_builtin_message(&self, tool_name: &str) -> Option<String> {
+        let (capability, how_to_enable) = match tool_name {
+            "python_execution" => (Capability::PythonExecution, "enable Code Execution"),
+            "tool_search" => (Capability::ToolSearch, "enable Tool Search"),
+            "schema_search" => (Capability::SchemaSearch, "enable database tools"),
+            "sql_select" => (Capability::SqlQuery, "enable database tools"),
+            _ => return None,
+        };
+        if self.enabled_capabilities.contains(&capability) {
+            return None;
+        }
+        if self.settings_sm.safe_mode_blocks_builtin(tool_name) {
+            return Some(format!(
+                "{} is currently disabled because safe mode is on; answer without running code",
+                tool_name
+            ));
+        }
+        let sql_tool = matches!(capability, Capability::SqlQuery | Capability::SchemaSearch);
+        if sql_tool && self.has_attachments {
+            return Some(format!(
+                "{} is currently disabled because documents are attached to this chat; answer from the attached documents instead",
+                tool_name
+            ));
+        }
+        Some(format!(
+            "{} is currently disabled; {} in Settings",
+            tool_name, how_to_enable
+        ))
+    }
+
     /// Check if a specific tool is allowed in the current state.
     pub fn is_tool_allowed(&self, tool_name: &str) -> bool {
         match &self.current_state {
@@ -688,7 +897,8 @@ impl AgenticStateMachine {
 
     /// Build the system prompt as a list of sections.
     pub fn build_system_prompt_sections(&self) -> Vec<String> {
-        let mut sections: Vec<String> = vec![self.base_prompt.clone()];
+        let mut sections: Vec<String> = self.prompt_prefix.iter().cloned().collect();
+        sections.push(self.base_prompt.clone());
         let active_capabilities = self.current_state.active_capabilities();
 
         // 1. Capabilities section (based on active capabilities)
@@ -761,6 +971,23 @@ impl AgenticStateMachine {
             }
         }
 
+        // 9. Note about tools trimmed to fit the prompt budget
+        if self.omitted_tool_count > 0 {
+            let mut note = format!(
+                "Note: {} tool(s) were omitted from this prompt to keep it within the size limit.",
+                self.omitted_tool_count
+            );
+            if self.enabled_capabilities.contains(&Capability::ToolSearch) {
+                note.push_str(" Use tool_search to discover them if needed.");
+            }
+            sections.push(note);
+        }
+
+        // 10. User suffix (system_prompt_suffix), always last
+        if let Some(ref suffix) = self.prompt_suffix {
+            sections.push(suffix.clone());
+        }
+
         sections
     }
 
@@ -819,11 +1046,15 @@ impl AgenticStateMachine {
                 } else {
                     let table_list = self.format_table_list(&filtered_tables);
                     let first_table = filtered_tables.first().map(|t| t.fully_qualified_name.as_str());
-                    let base_sql_instructions = system_prompt::build_sql_instructions(
+                    let mut base_sql_instructions = system_prompt::build_sql_instructions(
                         self.tool_call_format,
                         self.model_tool_format,
                         first_table,
                     );
+                    if let Some(notes) = system_prompt::build_sql_dialect_notes(&filtered_tables) {
+                        base_sql_instructions.push_str("\n\n");
+                        base_sql_instructions.push_str(&notes);
+                    }
                     Some(system_prompt::build_retrieved_sql_context(*max_table_relevancy, &table_list, &base_sql_instructions))
                 }
             }
@@ -891,6 +1122,10 @@ impl AgenticStateMachine {
                         self.model_tool_format,
                         first_table,
                     );
+                    if let Some(notes) = system_prompt::build_sql_dialect_notes(&filtered_tables) {
+                        instr.push_str("\n\n");
+                        instr.push_str(&notes);
+                    }
                     if let Some(custom) = self.custom_tool_prompts.get("builtin::sql_select") {
                         let trimmed = custom.trim();
                         if !trimmed.is_empty() {
@@ -1004,8 +1239,8 @@ impl AgenticStateMachine {
         }
     }
 
-    /// Build tool format instructions based on tool_call_format.
-    /// Build tool format instructions based on tool_call_format.
+    /// Build tool format instructions based on tool_call_format, with a call
+    /// example that uses one of this turn's available tools.
     fn build_format_instructions(&self) -> Option<String> {
         // Don't add format instructions if no tools are available
         if !self.enabled_capabilities.contains(&Capability::SqlQuery)
@@ -1016,7 +1251,64 @@ impl AgenticStateMachine {
             return None;
         }
 
-        system_prompt::build_format_instructions(self.tool_call_format, self.model_tool_format)
+        let mut instructions =
+            system_prompt::build_format_instructions(self.tool_call_format, self.model_tool_format)?;
+        if let Some((tool_name, arguments)) = self.format_example_tool() {
+            if let Some(example) = system_prompt::format_tool_call_example(
+                self.tool_call_format,
+                self.model_tool_format,
+                &tool_name,
+                &arguments,
+            ) {
+                instructions.push_str(&format!("\n\nExample with an available tool:\n{}", example));
+            }
+        }
+        Some(instructions)
+    }
+
+    /// Tool for the format example: the highest-scoring auto-discovered tool that is
+    /// still active, then the first active MCP tool, then a built-in enabled this turn.
+    fn format_example_tool(&self) -> Option<(String, serde_json::Value)> {
+        let active_tool = |server_id: &str, name: &str| {
+            self.mcp_context
+                .active_tools
+                .iter()
+                .filter(|(id, _)| id == server_id)
+                .flat_map(|(_, tools)| tools)
+                .find(|t| t.name == name)
+        };
+        let mut discovered: Vec<_> = self
+            .auto_tool_search
+            .as_ref()
+            .map(|search| search.tools.iter().collect())
+            .unwrap_or_default();
+        discovered.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+        let first_active = self.mcp_context.active_tools.iter().flat_map(|(_, tools)| tools).next();
+
+        if let Some(info) = discovered
+            .iter()
+            .find_map(|tool| active_tool(&tool.server_id, &tool.name))
+            .or(first_active)
+        {
+            let arguments = system_prompt::example_arguments(
+                info.parameters_schema.as_ref(),
+                info.input_examples.as_deref(),
+            );
+            return Some((info.name.clone(), arguments));
+        }
+
+        let builtin = if self.enabled_capabilities.contains(&Capability::SqlQuery) {
+            crate::tool_registry::sql_select_tool()
+        } else if self.enabled_capabilities.contains(&Capability::SchemaSearch) {
+            crate::tool_registry::schema_search_tool()
+        } else if self.enabled_capabilities.contains(&Capability::ToolSearch) {
+            crate::tool_registry::tool_search_tool()
+        } else {
+            return None;
+        };
+        let arguments =
+            system_prompt::example_arguments(Some(&builtin.parameters), Some(&builtin.input_examples));
+        Some((builtin.name, arguments))
     }
 
     /// Build MCP tool section from mcp_context.
@@ -1046,6 +1338,11 @@ impl AgenticStateMachine {
             ));
         }
 
+        // Chaining calls through extract (Python code can read results itself)
+        if !self.python_primary {
+            parts.push(system_prompt::EXTRACT_GUIDANCE.to_string());
+        }
+
         if parts.is_empty() {
             None
         } else {
@@ -1070,9 +1367,11 @@ impl AgenticStateMachine {
             - Use `sys.stderr.write(...)` for handoff text (triggers continuation)".to_string()
         );
 
+        parts.push(system_prompt::FINAL_ANSWER_GUIDANCE.to_string());
+
         parts.push(
-            "**Allowed imports**: math, json, random, re, datetime, collections, itertools, functools, \
-            operator, string, textwrap, copy, types, typing, abc, numbers, decimal, fractions, \
+            "**Allowed imports**: math, json, random, re, datetime, data, collections, itertools, \
+            functools, operator, string, textwrap, copy, types, typing, abc, numbers, decimal, fractions, \
             statistics, hashlib, base64, binascii, html.".to_string()
         );
 
@@ -1384,6 +1683,8 @@ mod tests {
                 model_tool_format: None,
                 custom_tool_prompts: HashMap::new(),
                 python_primary: false,
+                prepend: None,
+                append: None,
                 has_attachments: false,
             },
         )
@@ -1432,6 +1733,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_force_initial_state() {
+        let settings = test_settings();
+        let filter = ToolLaunchFilter::default();
+        let thresholds = RelevancyThresholds::default();
+
+        let mut machine =
+            create_test_machine(&settings, &filter, thresholds, "Test".to_string());
+        machine.compute_initial_state(0.0, 0.0, vec![], vec![]);
+
+        machine.force_initial_state("sql_retrieval").unwrap();
+        assert_eq!(machine.current_state().name(), "SQL Retrieval");
+        machine.force_initial_state("Conversational").unwrap();
+        assert_eq!(machine.current_state().name(), "Conversational");
+
+        // Mid-turn and unknown states are rejected with the valid list
+        let err = machine.force_initial_state("SqlResultCommentary").unwrap_err();
+        assert!(err.contains("valid states: Conversational"));
+        assert!(err.contains("Code Execution"));
+        assert!(machine.force_initial_state("Daydreaming").is_err());
+        assert_eq!(machine.current_state().name(), "Conversational");
+    }
+
     #[test]
     fn test_initial_state_sql_only() {
         let settings = test_settings();
@@ -1452,6 +1776,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_sql_prompt_includes_dialect_notes() {
+        let settings = test_settings();
+        let filter = ToolLaunchFilter::default();
+        let thresholds = RelevancyThresholds::default();
+        let mut machine =
+            create_test_machine(&settings, &filter, thresholds, "Test".to_string());
+
+        let table = |name: &str, source: &str, dialect: &str| TableInfo {
+            fully_qualified_name: name.to_string(),
+            source_id: source.to_string(),
+            sql_dialect: dialect.to_string(),
+            relevancy: 0.8,
+            columns: vec![],
+            description: None,
+        };
+        machine.compute_initial_state(
+            0.1,
+            0.8,
+            vec![
+                table("proj.sales.orders", "warehouse", "GoogleSQL"),
+                table("public.customers", "crm", "PostgreSQL"),
+            ],
+            vec![],
+        );
+        assert!(matches!(machine.current_state(), AgenticState::SqlRetrieval { .. }));
+
+        let prompt = machine.build_system_prompt();
+        assert!(prompt.contains("For source `warehouse` (GoogleSQL; tables: proj.sales.orders)"));
+        assert!(
+            prompt.contains("For source `crm` (PostgreSQL; tables: public.customers) use PostgreSQL")
+        );
+        assert!(prompt.contains("Cross-source joins are not supported"));
+    }
+
     #[test]
     fn test_tool_allowed_in_sql_state() {
         let settings = test_settings();
@@ -1502,6 +1861,23 @@ mod tests {
             _ => panic!("Expected SqlResultCommentary state"),
         }
 
+        // A truncated page tells the model that more rows remain
+        machine.handle_event(StateEvent::SqlExecuted {
+            results: SqlResults {
+                columns: vec![],
+                rows: vec![],
+                row_count: 25,
+                truncated: true,
+            },
+            row_count: 25,
+        });
+        match machine.current_state() {
+            AgenticState::SqlResultCommentary { query_context, .. } => {
+                assert!(query_context.starts_with("25 rows returned, with more rows remaining"));
+            }
+            _ => panic!("Expected SqlResultCommentary state"),
+        }
+
         // SQL tools should still be allowed in commentary state for multi-query scenarios
         assert!(machine.is_tool_allowed("sql_select"));
         assert!(machine.is_tool_allowed("schema_search"));
@@ -1509,6 +1885,112 @@ mod tests {
         assert!(machine.should_continue_loop());
     }
 
+    #[test]
+    fn test_disabled_builtins_get_tailored_message() {
+        let settings = AppSettings::default();
+        let machine = create_test_machine(
+            &settings,
+            &ToolLaunchFilter::default(),
+            RelevancyThresholds::default(),
+            "Test".to_string(),
+        );
+
+        let expected = [
+            ("python_execution", "enable Code Execution"),
+            ("tool_search", "enable Tool Search"),
+            ("schema_search", "enable database tools"),
+            ("sql_select", "enable database tools"),
+        ];
+        for (tool, how_to_enable) in expected {
+            assert_eq!(
+                machine.disabled_builtin_message(tool),
+                Some(format!("{} is currently disabled; {} in Settings", tool, how_to_enable))
+            );
+        }
+        // MCP tools are not built-ins
+        assert_eq!(machine.disabled_builtin_message("get_weather"), None);
+    }
+
+    #[test]
+    fn test_safe_mode_explains_disabled_python_execution() {
+        let mut settings = test_settings();
+        settings.safe_mode = true;
+        let machine = create_test_machine(
+            &settings,
+            &ToolLaunchFilter::default(),
+            RelevancyThresholds::default(),
+            "Test".to_string(),
+        );
+        assert_eq!(
+            machine.disabled_builtin_message("python_execution"),
+            Some(
+                "python_execution is currently disabled because safe mode is on; answer without running code"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_enabled_builtins_have_no_disabled_message() {
+        let settings = test_settings();
+        let machine = create_test_machine(
+            &settings,
+            &ToolLaunchFilter::default(),
+            RelevancyThresholds::default(),
+            "Test".to_string(),
+        );
+        for tool in ["python_execution", "tool_search", "schema_search", "sql_select"] {
+            assert_eq!(machine.disabled_builtin_message(tool), None);
+        }
+    }
+
+    #[test]
+    fn test_prompt_suffix_survives_state_transition() {
+        let settings = test_settings();
+        let settings_sm = SettingsStateMachine::from_settings(&settings, &ToolLaunchFilter::default());
+        let mut machine = AgenticStateMachine::new_from_settings_sm(
+            &settings_sm,
+            crate::agentic_state::PromptContext {
+                base_prompt: "Test".to_string(),
+                mcp_context: crate::agentic_state::McpToolContext::default(),
+                attached_tables: Vec::new(),
+                attached_tools: Vec::new(),
+                attached_tabular_files: Vec::new(),
+                tabular_column_info: Vec::new(),
+                tool_call_format: ToolCallFormatName::Hermes,
+                model_tool_format: None,
+                custom_tool_prompts: HashMap::new(),
+                python_primary: false,
+                prepend: Some("Company policy: be brief.".to_string()),
+                append: Some("Always answer in French.".to_string()),
+                has_attachments: false,
+            },
+        );
+
+        machine.compute_initial_state(0.1, 0.5, vec![], vec![]);
+        let initial = machine.build_system_prompt();
+        assert!(initial.starts_with("Company policy: be brief.\n\nTest"));
+        assert!(initial.ends_with("Always answer in French."));
+
+        // The prompt is rebuilt for the new state; the prefix and suffix stay at the edges
+        machine.handle_event(StateEvent::SqlExecuted {
+            results: SqlResults {
+                columns: vec!["col1".to_string()],
+                rows: vec![vec!["val1".to_string()]],
+                row_count: 1,
+                truncated: false,
+            },
+            row_count: 1,
+        });
+        assert!(matches!(
+            machine.current_state(),
+            AgenticState::SqlResultCommentary { .. }
+        ));
+        let rebuilt = machine.build_system_prompt();
+        assert!(rebuilt.starts_with("Company policy: be brief."));
+        assert!(rebuilt.ends_with("Always answer in French."));
+    }
+
     #[test]
     fn test_python_stderr_handoff() {
         let settings = test_settings();
@@ -1586,6 +2068,8 @@ mod tests {
                 model_tool_format: None,
                 custom_tool_prompts: HashMap::new(),
                 python_primary: false,
+                prepend: None,
+                append: None,
                 has_attachments: false,
             },
         );
@@ -1642,6 +2126,8 @@ mod tests {
                 model_tool_format: None,
                 custom_tool_prompts: HashMap::new(),
                 python_primary: false,
+                prepend: None,
+                append: None,
                 has_attachments: false,
             },
         );
@@ -1659,6 +2145,153 @@ mod tests {
         assert!(matches!(machine.current_state(), AgenticState::ToolOrchestration { .. }));
     }
 
+    #[test]
+    fn test_fit_system_prompt_to_budget_drops_lowest_priority_tools() {
+        let tool = |name: &str| crate::agentic_state::McpToolInfo {
+            name: name.to_string(),
+            description: Some(format!("{} {}", name, "does something useful. ".repeat(20))),
+            parameters_schema: None,
+            input_examples: None,
+        };
+        let discovered = |name: &str, score: f32| crate::tool_registry::ToolSearchResult {
+            name: name.to_string(),
+            description: None,
+            score,
+            server_id: "srv".to_string(),
+            parameters: serde_json::Value::Null,
+        };
+
+        let settings = test_settings();
+        let filter = ToolLaunchFilter::default();
+        let settings_sm = SettingsStateMachine::from_settings(&settings, &filter);
+        let mut machine = AgenticStateMachine::new_from_settings_sm(
+            &settings_sm,
+            crate::agentic_state::PromptContext {
+                base_prompt: "Test".to_string(),
+                mcp_context: crate::agentic_state::McpToolContext {
+                    active_tools: vec![(
+                        "srv".to_string(),
+                        vec![tool("plain_tool"), tool("low_tool"), tool("high_tool")],
+                    )],
+                    ..Default::default()
+                },
+                attached_tables: Vec::new(),
+                attached_tools: Vec::new(),
+                attached_tabular_files: Vec::new(),
+                tabular_column_info: Vec::new(),
+                tool_call_format: ToolCallFormatName::Hermes,
+                model_tool_format: None,
+                custom_tool_prompts: HashMap::new(),
+                python_primary: false,
+                prepend: None,
+                append: None,
+                has_attachments: false,
+            },
+        );
+        machine.set_auto_discovery_context(
+            Some(crate::tools::tool_search::ToolSearchOutput {
+                tools: vec![discovered("high_tool", 0.9), discovered("low_tool", 0.4)],
+                queries_used: Vec::new(),
+                python_docs: String::new(),
+            }),
+            None,
+        );
+
+        // Undiscovered tools go first
+        let full_len = machine.build_system_prompt().chars().count();
+        assert_eq!(machine.fit_system_prompt_to_budget(full_len - 1), 1);
+        let prompt = machine.build_system_prompt();
+        assert!(!prompt.contains("plain_tool"));
+        assert!(prompt.contains("low_tool") && prompt.contains("high_tool"));
+        assert!(prompt.contains("1 tool(s) were omitted"));
+
+        // Then discovered tools, lowest score first
+        let len = prompt.chars().count();
+        assert_eq!(machine.fit_system_prompt_to_budget(len - 1), 2);
+        let prompt = machine.build_system_prompt();
+        assert!(!prompt.contains("low_tool"));
+        assert!(prompt.contains("high_tool"));
+
+        // Nothing left to drop: stops rather than looping forever
+        assert_eq!(machine.fit_system_prompt_to_budget(0), 3);
+        assert_eq!(machine.omitted_tool_count(), 3);
+    }
+
+    #[test]
+    fn test_format_example_uses_available_tool() {
+        let tool = |name: &str, arg: &str| crate::agentic_state::McpToolInfo {
+            name: name.to_string(),
+            description: None,
+            parameters_schema: Some(serde_json::json!({
+                "type": "object",
+                "properties": {arg: {"type": "string"}, "limit": {"type": "integer"}},
+                "required": [arg]
+            })),
+            input_examples: None,
+        };
+
+        let settings = test_settings();
+        let filter = ToolLaunchFilter::default();
+        let settings_sm = SettingsStateMachine::from_settings(&settings, &filter);
+        let mut machine = AgenticStateMachine::new_from_settings_sm(
+            &settings_sm,
+            crate::agentic_state::PromptContext {
+                base_prompt: "Test".to_string(),
+                mcp_context: crate::agentic_state::McpToolContext {
+                    active_tools: vec![(
+                        "crm".to_string(),
+                        vec![tool("lookup_customer", "email"), tool("list_orders", "customer_id")],
+                    )],
+                    ..Default::default()
+                },
+                attached_tables: Vec::new(),
+                attached_tools: Vec::new(),
+                attached_tabular_files: Vec::new(),
+                tabular_column_info: Vec::new(),
+                tool_call_format: ToolCallFormatName::Hermes,
+                model_tool_format: None,
+                custom_tool_prompts: HashMap::new(),
+                python_primary: false,
+                prepend: None,
+                append: None,
+                has_attachments: false,
+            },
+        );
+
+        // Without discovery, the first active tool is the example
+        let instructions = machine.build_format_instructions().unwrap();
+        assert!(instructions.contains(
+            r#"<tool_call>{"name": "lookup_customer", "arguments": {"email":"..."}}</tool_call>"#
+        ));
+        assert!(!instructions.contains(r#""name": "tool_name""#));
+
+        // The highest-scoring discovered tool wins
+        let discovered = |name: &str, score: f32| crate::tool_registry::ToolSearchResult {
+            name: name.to_string(),
+            description: None,
+            score,
+            server_id: "crm".to_string(),
+            parameters: serde_json::Value::Null,
+        };
+        machine.set_auto_discovery_context(
+            Some(crate::tools::tool_search::ToolSearchOutput {
+                tools: vec![discovered("lookup_customer", 0.3), discovered("list_orders", 0.8)],
+                queries_used: Vec::new(),
+                python_docs: String::new(),
+            }),
+            None,
+        );
+        let instructions = machine.build_format_instructions().unwrap();
+        assert!(instructions.contains(r#""name": "list_orders""#));
+        assert!(instructions.contains(r#""customer_id":"...""#));
+
+        // No MCP tools: falls back to an enabled built-in
+        machine.mcp_context = crate::agentic_state::McpToolContext::default();
+        machine.auto_tool_search = None;
+        let instructions = machine.build_format_instructions().unwrap();
+        assert!(instructions.contains(r#""name": "sql_select""#));
+    }
+
     #[test]
     fn test_turn_attached_table_enables_sql_mode() {
         // Scenario: sql_select is enabled but no tables attached by default.
@@ -1691,6 +2324,8 @@ mod tests {
                 model_tool_format: None,
                 custom_tool_prompts: HashMap::new(),
                 python_primary: false,
+                prepend: None,
+                append: None,
                 has_attachments: false,
             },
         );
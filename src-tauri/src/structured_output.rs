@@ -0,0 +1,152 @@
+//! Structured output: constraining the final answer to a JSON schema.
+//!
+//! Models that take the constraint natively get it as `response_format`;
+//! for the rest the final answer is validated here and the model is asked
+//! once to repair it.
+
+use serde_json::{json, Value};
+
+use crate::tool_execution::{json_type_name, matches_schema_type};
+
+/// Name given to the schema in the request's `response_format`
+const RESPONSE_SCHEMA_NAME: &str = "response";
+
+/// `response_format` value for the chat completions API
+pub fn completions_response_format(schema: &Value) -> Value {
+    json!({
+        "type": "json_schema",
+        "json_schema": {
+            "name": RESPONSE_SCHEMA_NAME,
+            "schema": schema,
+            "strict": true,
+        }
+    })
+}
+
+/// `text.format` value for the Responses API
+pub fn responses_text_format(schema: &Value) -> Value {
+    json!({
+        "type": "json_schema",
+        "name": RESPONSE_SCHEMA_NAME,
+        "schema": schema,
+        "strict": true,
+    })
+}
+
+/// The JSON part of a response: the contents of a ```json fence if there is
+/// one, otherwise the trimmed text
+fn extract_json_text(response: &str) -> &str {
+    let trimmed = response.trim();
+    let Some(start) = trimmed.find("```") else {
+        return trimmed;
+    };
+    let after_fence = &trimmed[start + 3..];
+    let body_start = after_fence.find('\n').map_or(0, |i| i + 1);
+    let body = &after_fence[body_start..];
+    match body.find("```") {
+        Some(end) => body[..end].trim(),
+        None => trimmed,
+    }
+}
+
+/// Collect every way `value` violates `schema` (type, enum, required,
+/// properties and items; other keywords are ignored)
+fn collect_schema_problems(value: &Value, schema: &Value, path: &str, problems: &mut Vec<String>) {
+    let at = if path.is_empty() { "response" } else { path };
+
+    let expected: Vec<&str> = match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(|t| t.as_str()).collect(),
+        _ => Vec::new(),
+    };
+    if !expected.is_empty() && !expected.iter().any(|t| matches_schema_type(value, t)) {
+        problems.push(format!(
+            "{}: expected {}, got {}",
+            at,
+            expected.join(" or "),
+            json_type_name(value)
+        ));
+        return;
+    }
+    if let Some(allowed) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+            problems.push(format!("{}: must be one of {}", at, allowed.join(", ")));
+        }
+    }
+
+    if let Value::Object(object) = value {
+        let required = schema.get("required").and_then(|r| r.as_array());
+        for field in required.into_iter().flatten().filter_map(|f| f.as_str()) {
+            if !object.contains_key(field) {
+                problems.push(format!("{}: missing required field '{}'", at, field));
+            }
+        }
+        let properties = schema.get("properties").and_then(|p| p.as_object());
+        for (field, property) in properties.into_iter().flatten() {
+            if let Some(child) = object.get(field) {
+                collect_schema_problems(child, property, &format!("{}.{}", at, field), problems);
+            }
+        }
+    }
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            collect_schema_problems(item, item_schema, &format!("{}[{}]", at, i), problems);
+        }
+    }
+}
+
+/// Parse the final response and check it against the schema. Returns every
+/// problem found, joined with "; ".
+pub fn validate_structured_response(response: &str, schema: &Value) -> Result<Value, String> {
+    let value: Value = serde_json::from_str(extract_json_text(response))
+        .map_err(|e| format!("response is not valid JSON ({})", e))?;
+    let mut problems = Vec::new();
+    collect_schema_problems(&value, schema, "", &mut problems);
+    if problems.is_empty() {
+        Ok(value)
+    } else {
+        Err(problems.join("; "))
+    }
+}
+
+/// Follow-up message asking the model to fix an answer that failed validation
+pub fn repair_prompt(problems: &str, schema: &Value) -> String {
+    format!(
+        "Your answer does not match the required JSON schema: {}.\n\
+Reply again with only a JSON value that matches this schema, and nothing else:\n{}",
+        problems,
+        serde_json::to_string_pretty(schema).unwrap_or_else(|_| schema.to_string())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_structured_response() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name", "tags"],
+            "properties": {
+                "name": {"type": "string"},
+                "size": {"type": "string", "enum": ["small", "large"]},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            }
+        });
+
+        let fenced = "Here you go:\n```json\n{\"name\": \"Ada\", \"tags\": [\"x\"]}\n```";
+        let value = validate_structured_response(fenced, &schema).unwrap();
+        assert_eq!(value["name"], "Ada");
+
+        let problems =
+            validate_structured_response(r#"{"size": "huge", "tags": [1]}"#, &schema).unwrap_err();
+        assert!(problems.contains("missing required field 'name'"));
+        assert!(problems.contains("response.size: must be one of"));
+        assert!(problems.contains("response.tags[0]: expected string, got integer"));
+
+        let problems = validate_structured_response("Ada, no tags", &schema).unwrap_err();
+        assert!(problems.starts_with("response is not valid JSON"));
+    }
+}
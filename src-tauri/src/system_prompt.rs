@@ -3,6 +3,7 @@
 //! This module serves as the single source of truth for all LLM prompt content,
 //! consolidating guidance, format-specific syntax, and tool documentation.
 
+use serde::Serialize;
 use std::collections::HashSet;
 use crate::agentic_state::{Capability, ColumnInfo, McpToolInfo, TableInfo, RagChunk};
 use crate::protocol::{ToolSchema, ToolFormat};
@@ -19,6 +20,17 @@ pub const SQL_RULES: &str = "\
 - Use CAST(column AS STRING) instead of TO_CHAR
 - If a query fails, read the error and retry - never invent results";
 
+/// Code Mode guidance for ending a turn via the `final_answer` pseudo-tool
+pub const FINAL_ANSWER_GUIDANCE: &str = "**Finishing**: When you have everything needed to answer and no more code has to run, \
+return a program containing only `final_answer(\"<your answer>\")`. It is not executed; its text is shown to the user as your reply. \
+Code can also call `final_answer(...)` with a computed value (e.g. `final_answer(f\"The total is {total}\")`) to end the turn with that text once it finishes.";
+
+/// Guidance for the `extract` pseudo-tool that reads values out of earlier tool results
+pub const EXTRACT_GUIDANCE: &str = "**Reusing results**: To pass one field of an earlier tool result to another call, \
+call `extract` with `path` (e.g. `data.items[0].id`) and optionally `from_result_id` (default `last`) \
+instead of copying the value from the JSON yourself. A result's id is its tool call id, shown before the result as \
+`<!-- tool_call_id: ... -->` (e.g. `result_1`).";
+
 /// Success guidance for sql_select (post-execution)
 pub const SQL_SUCCESS_GUIDANCE: &str = "\n\n**NOTE**: The query results above have already been displayed to the user in a formatted table. \
 Your role now is to provide helpful commentary: summarize key insights, suggest follow-up analyses, \
@@ -39,7 +51,7 @@ pub const PYTHON_SANDBOX_RULES_TEXT_MODE: &str = "\
 - You must return exactly one runnable Python program in a single ```python ... ``` block. Do not return explanations or multiple blocks.
 - Your Python code will be executed directly. Do NOT emit <tool_call> tags, JSON tool calls, or any other format - ONLY valid Python code.
 - **CRITICAL: Only stdout (print output) is visible to the user.** This is NOT a REPL - expressions like `result` do NOT display anything. You MUST use print() to show results.
-- Allowed imports only: math, json, random, re, datetime, collections, itertools, functools, operator, string, textwrap, copy, types, abc, numbers, decimal, fractions, statistics, hashlib, base64, binascii, html.
+- Allowed imports only: math, json, random, re, datetime, data, collections, itertools, functools, operator, string, textwrap, copy, types, abc, numbers, decimal, fractions, statistics, hashlib, base64, binascii, html.
 
 **HELLO WORLD EXAMPLE** - This is the EXACT format required:
 
@@ -68,7 +80,7 @@ pub const PYTHON_SANDBOX_RULES_NATIVE_MODE: &str = "\
 - You MUST call the `python_execution` tool to execute Python code. Do NOT output raw code blocks.
 - The `code` parameter is a JSON array of strings, where each string is one line of Python code.
 - **CRITICAL: Only stdout (print output) is visible to the user.** This is NOT a REPL - expressions like `result` do NOT display anything. You MUST use print() to show results.
-- Allowed imports only: math, json, random, re, datetime, collections, itertools, functools, operator, string, textwrap, copy, types, abc, numbers, decimal, fractions, statistics, hashlib, base64, binascii, html.
+- Allowed imports only: math, json, random, re, datetime, data, collections, itertools, functools, operator, string, textwrap, copy, types, abc, numbers, decimal, fractions, statistics, hashlib, base64, binascii, html.
 
 **HELLO WORLD EXAMPLE** - Call python_execution with code as a JSON array:
 ```
@@ -91,7 +103,7 @@ python_execution(code=[\"print('Hello, World!')\"])
 
 /// Allowed Python imports list
 /// Note: typing is excluded because RustPython's typing module requires 'os' internally
-pub const PYTHON_ALLOWED_IMPORTS: &str = "math, json, random, re, datetime, collections, itertools, functools, operator, string, textwrap, copy, types, abc, numbers, decimal, fractions, statistics, hashlib, base64, binascii, html";
+pub const PYTHON_ALLOWED_IMPORTS: &str = "math, json, random, re, datetime, data, collections, itertools, functools, operator, string, textwrap, copy, types, abc, numbers, decimal, fractions, statistics, hashlib, base64, binascii, html";
 
 /// Legacy alias for backwards compatibility
 pub const PYTHON_SANDBOX_RULES: &str = PYTHON_SANDBOX_RULES_TEXT_MODE;
@@ -128,7 +140,7 @@ pub fn tool_call_syntax(
         None => "SELECT ...".to_string(),
     };
     match effective_format {
-        ToolCallFormatName::Native => format!(
+        ToolCallFormatName::Native | ToolCallFormatName::Anthropic => format!(
             "Trigger the `{}` tool with your query. Example: sql=\"{}\"",
             tool_name, sql
         ),
@@ -156,7 +168,7 @@ pub fn build_sql_instructions(
          **ACTION REQUIRED**: "
     );
 
-    if effective_format == ToolCallFormatName::Native {
+    if matches!(effective_format, ToolCallFormatName::Native | ToolCallFormatName::Anthropic) {
         prompt.push_str(&format!("{}.\n\n", syntax));
     } else {
         prompt.push_str(&format!(
@@ -613,6 +625,7 @@ pub fn build_format_instructions(
 
     match effective_format {
         ToolCallFormatName::Native => None, // Truly native models (like GPT-4) don't need instructions
+        ToolCallFormatName::Anthropic => None, // Tools are passed through the messages API
         ToolCallFormatName::Hermes => Some(
             "## Tool Calling Format\n\n\
             When you need to use a tool, output ONLY:\n\
@@ -646,6 +659,93 @@ pub fn build_format_instructions(
     }
 }
 
+/// Example arguments for a tool: its first object-valued input example, otherwise
+/// placeholder values for the required parameters (or the first parameter).
+pub fn example_arguments(
+    parameters_schema: Option<&serde_json::Value>,
+    input_examples: Option<&[serde_json::Value]>,
+) -> serde_json::Value {
+    if let Some(example) = input_examples
+        .and_then(|examples| examples.iter().find(|e| e.is_object()))
+    {
+        return example.clone();
+    }
+
+    let mut args = serde_json::Map::new();
+    let Some(props) = parameters_schema
+        .and_then(|s| s.get("properties"))
+        .and_then(|p| p.as_object())
+    else {
+        return serde_json::Value::Object(args);
+    };
+    let required: Vec<&str> = parameters_schema
+        .and_then(|s| s.get("required"))
+        .and_then(|r| r.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default();
+
+    for (name, prop) in props {
+        if !required.is_empty() && !required.contains(&name.as_str()) {
+            continue;
+        }
+        let value = match prop.get("type").and_then(|t| t.as_str()).unwrap_or("string") {
+            "integer" | "number" => serde_json::json!(1),
+            "boolean" => serde_json::json!(true),
+            "array" => serde_json::json!(["..."]),
+            "object" => serde_json::json!({}),
+            _ => serde_json::json!("..."),
+        };
+        args.insert(name.clone(), value);
+        if required.is_empty() {
+            break;
+        }
+    }
+    serde_json::Value::Object(args)
+}
+
+/// Render a concrete call of `tool_name` in the effective tool call format.
+/// Returns None for formats without text instructions (native, code mode).
+pub fn format_tool_call_example(
+    primary_format: ToolCallFormatName,
+    model_tool_format: Option<ToolFormat>,
+    tool_name: &str,
+    arguments: &serde_json::Value,
+) -> Option<String> {
+    let call = format!(
+        "{{\"name\": {}, \"arguments\": {}}}",
+        serde_json::Value::from(tool_name),
+        arguments
+    );
+
+    match resolve_effective_format(primary_format, model_tool_format) {
+        ToolCallFormatName::Native | ToolCallFormatName::Anthropic | ToolCallFormatName::CodeMode => {
+            None
+        }
+        ToolCallFormatName::Hermes => Some(format!("<tool_call>{}</tool_call>", call)),
+        ToolCallFormatName::Mistral => match model_tool_format {
+            Some(ToolFormat::Granite) => Some(format!("<function_call>{}</function_call>", call)),
+            _ => Some(format!("[TOOL_CALLS] [{}]", call)),
+        },
+        ToolCallFormatName::Pythonic => {
+            let args: Vec<String> = arguments
+                .as_object()
+                .map(|map| {
+                    map.iter()
+                        .map(|(k, v)| match v {
+                            serde_json::Value::Bool(b) => {
+                                format!("{}={}", k, if *b { "True" } else { "False" })
+                            }
+                            other => format!("{}={}", k, other),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            Some(format!("{}({})", tool_name, args.join(", ")))
+        }
+        ToolCallFormatName::PureJson => Some(call),
+    }
+}
+
 /// Build auto-discovery tool search section.
 pub fn build_auto_tool_search_section(tools: &[ToolSearchResult]) -> Option<String> {
     if tools.is_empty() {
@@ -1011,6 +1111,65 @@ pub fn format_table_list(tables: &[TableInfo]) -> String {
         .join("\n")
 }
 
+/// Syntax reminder for a SQL dialect, steering the model away from other dialects' functions.
+fn dialect_hint(dialect: &str) -> String {
+    let lower = dialect.to_lowercase();
+    if lower.contains("google") || lower.contains("bigquery") {
+        "use GoogleSQL (BigQuery standard SQL): backtick-quote `project.dataset.table`, \
+        SAFE_CAST, DATE_TRUNC(date, MONTH)"
+            .to_string()
+    } else if lower.contains("postgres") {
+        "use PostgreSQL: double-quote identifiers, `::type` casts, DATE_TRUNC('month', date); \
+        no backticks or BigQuery functions"
+            .to_string()
+    } else if lower.contains("mysql") {
+        "use MySQL: backtick-quote identifiers, DATE_FORMAT() for dates".to_string()
+    } else if lower.contains("sqlite") {
+        "use SQLite: no DATE_TRUNC, use strftime() for dates".to_string()
+    } else {
+        format!("use {} syntax", dialect)
+    }
+}
+
+/// Per-source SQL dialect notes, plus a warning when tables come from more than one source.
+/// Returns None when there are no tables.
+pub fn build_sql_dialect_notes(tables: &[TableInfo]) -> Option<String> {
+    // Group by (source, dialect), keeping the order tables were listed in
+    let mut groups: Vec<(&str, &str, Vec<&str>)> = Vec::new();
+    for table in tables {
+        let name = table.fully_qualified_name.as_str();
+        let existing = groups.iter_mut().find(|(source, dialect, _)| {
+            *source == table.source_id && *dialect == table.sql_dialect
+        });
+        match existing {
+            Some((_, _, names)) => names.push(name),
+            None => groups.push((&table.source_id, &table.sql_dialect, vec![name])),
+        }
+    }
+    if groups.is_empty() {
+        return None;
+    }
+
+    let mut notes = String::from("**SQL Dialects**:");
+    for (source, dialect, names) in &groups {
+        notes.push_str(&format!(
+            "\n- For source `{}` ({}; tables: {}) {}.",
+            source,
+            dialect,
+            names.join(", "),
+            dialect_hint(dialect)
+        ));
+    }
+    let sources: HashSet<&str> = groups.iter().map(|(source, _, _)| *source).collect();
+    if sources.len() > 1 {
+        notes.push_str(
+            "\n\nCross-source joins are not supported: each `sql_select` query runs against one source. \
+            Query each source separately and combine the results in your answer.",
+        );
+    }
+    Some(notes)
+}
+
 /// Format RAG chunks for the prompt.
 pub fn format_rag_chunks(chunks: &[RagChunk]) -> String {
     if chunks.is_empty() {
@@ -1064,3 +1223,152 @@ pub fn format_mcp_tool_documentation(
     }
     body
 }
+
+// ============ Prompt Lint ============
+
+/// A custom system prompt instruction that conflicts with the tool call format
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PromptLintWarning {
+    /// 1-based line of the prompt the instruction is on
+    pub line: usize,
+    /// The text that triggered the warning
+    pub excerpt: String,
+    pub message: String,
+    pub suggestion: String,
+}
+
+/// Instructions that break tool calling in some formats:
+/// (pattern, formats it conflicts with (None = all), problem)
+const CONFLICTING_DIRECTIVES: &[(&str, Option<&[ToolCallFormatName]>, &str)] = &[
+    (
+        r"(?i)\b(never|do not|don't|dont)\s+(use|call|invoke)\s+(any\s+)?(tools?|functions?)\b",
+        None,
+        "Tells the model not to use tools, so tool calls will be skipped",
+    ),
+    (
+        r"(?i)\b(only|always)\s+(respond|reply|answer|output)\s+(only\s+)?(in|with)\s+(valid\s+)?json\b",
+        Some(&[
+            ToolCallFormatName::Hermes,
+            ToolCallFormatName::Mistral,
+            ToolCallFormatName::Pythonic,
+            ToolCallFormatName::CodeMode,
+        ]),
+        "Asks for JSON-only replies, which leaves no room for text tool calls",
+    ),
+    (
+        r"(?i)\b(never|do not|don't|dont)\s+(use|output|include|write)\s+(any\s+)?(xml\s+|html\s+)?tags\b",
+        Some(&[ToolCallFormatName::Hermes]),
+        "Forbids tags, but Hermes tool calls are wrapped in <tool_call> tags",
+    ),
+];
+
+/// How calls should be described for a format
+fn format_call_hint(format: ToolCallFormatName) -> String {
+    match format {
+        ToolCallFormatName::Native => "With native tool calling, calls go through the API; the prompt doesn't need to describe a call syntax.".to_string(),
+        ToolCallFormatName::Anthropic => "Calls arrive as tool_use content blocks; the prompt doesn't need to describe a call syntax.".to_string(),
+        ToolCallFormatName::CodeMode => "In Code Mode the model writes one Python program that calls tools as functions.".to_string(),
+        _ => {
+            let info = format.info();
+            format!("Describe calls in the {} format, e.g. {}", info.display_name, info.example)
+        }
+    }
+}
+
+/// 1-based line number of a byte offset
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset].matches('\n').count() + 1
+}
+
+/// Scan a custom system prompt for instructions that conflict with `format`:
+/// markers of other formats' call syntax and directives known to break tool calling.
+pub fn lint_prompt_for_format(prompt: &str, format: ToolCallFormatName) -> Vec<PromptLintWarning> {
+    let mut warnings = Vec::new();
+    let active = format.info().display_name;
+
+    for other in ToolCallFormatName::ALL.into_iter().filter(|f| *f != format) {
+        if let Some((offset, marker)) = other
+            .markers()
+            .iter()
+            .filter_map(|marker| prompt.find(marker).map(|offset| (offset, *marker)))
+            .min()
+        {
+            warnings.push(PromptLintWarning {
+                line: line_of(prompt, offset),
+                excerpt: marker.to_string(),
+                message: format!(
+                    "Mentions `{}` from the {} format, but the active format is {}",
+                    marker,
+                    other.info().display_name,
+                    active
+                ),
+                suggestion: format_call_hint(format),
+            });
+        }
+    }
+
+    for (pattern, formats, problem) in CONFLICTING_DIRECTIVES {
+        if formats.is_some_and(|formats| !formats.contains(&format)) {
+            continue;
+        }
+        let Ok(re) = regex::Regex::new(pattern) else {
+            continue;
+        };
+        if let Some(found) = re.find(prompt) {
+            warnings.push(PromptLintWarning {
+                line: line_of(prompt, found.start()),
+                excerpt: found.as_str().to_string(),
+                message: format!("{} (active format: {})", problem, active),
+                suggestion: format!(
+                    "Remove or narrow this instruction. {}",
+                    format_call_hint(format)
+                ),
+            });
+        }
+    }
+
+    warnings.sort_by_key(|w| w.line);
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_flags_other_format_markers() {
+        let prompt =
+            "You are a helpful assistant.\nAlways wrap calls in <tool_call></tool_call> tags.";
+
+        let warnings = lint_prompt_for_format(prompt, ToolCallFormatName::Native);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].line, 2);
+        assert_eq!(warnings[0].excerpt, "<tool_call>");
+        assert!(warnings[0].message.contains("Hermes"));
+        assert!(warnings[0].message.contains("Native"));
+
+        // The same prompt is fine when Hermes is the active format
+        assert!(lint_prompt_for_format(prompt, ToolCallFormatName::Hermes).is_empty());
+
+        let warnings =
+            lint_prompt_for_format("Emit [TOOL_CALLS] first", ToolCallFormatName::Hermes);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].suggestion.contains("<tool_call>"));
+    }
+
+    #[test]
+    fn test_lint_flags_conflicting_directives() {
+        let warnings =
+            lint_prompt_for_format("Be brief. Never use tools.", ToolCallFormatName::Native);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].excerpt, "Never use tools");
+
+        let prompt = "Only respond in JSON.\nDo not use XML tags.";
+        assert_eq!(
+            lint_prompt_for_format(prompt, ToolCallFormatName::Hermes).len(),
+            2
+        );
+        assert!(lint_prompt_for_format(prompt, ToolCallFormatName::PureJson).is_empty());
+        assert!(lint_prompt_for_format("Plain prompt.", ToolCallFormatName::CodeMode).is_empty());
+    }
+}
@@ -61,6 +61,8 @@ impl AgenticIntegrationTestHarness {
             model_tool_format: None,
             custom_tool_prompts: HashMap::new(),
             python_primary: settings.tool_call_formats.primary == ToolCallFormatName::CodeMode,
+            prepend: None,
+            append: None,
             has_attachments: false,
         };
         AgenticStateMachine::new_from_settings_sm(&settings_sm, prompt_context)
@@ -210,16 +212,12 @@ async fn test_sql_select_triggering() {
         ChatMessage {
             role: "system".to_string(),
             content: system_prompt.clone(),
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         },
         ChatMessage {
             role: "user".to_string(),
             content: user_query.to_string(),
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         },
     ];
 
@@ -261,16 +259,12 @@ async fn test_python_execution_triggering() {
         ChatMessage {
             role: "system".to_string(),
             content: system_prompt.clone(),
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         },
         ChatMessage {
             role: "user".to_string(),
             content: user_query.to_string(),
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         },
     ];
 
@@ -378,16 +372,12 @@ async fn test_sql_error_recovery_with_schema_injection() {
         ChatMessage {
             role: "system".to_string(),
             content: system_prompt.clone(),
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         },
         ChatMessage {
             role: "user".to_string(),
             content: user_question.to_string(),
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         },
         ChatMessage {
             role: "assistant".to_string(),
@@ -395,9 +385,7 @@ async fn test_sql_error_recovery_with_schema_injection() {
                 "<tool_call>{{\"name\": \"sql_select\", \"arguments\": {{\"sql\": \"{}\"}}}}</tool_call>",
                 failed_sql
             ),
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         },
         ChatMessage {
             role: "user".to_string(),
@@ -405,9 +393,7 @@ async fn test_sql_error_recovery_with_schema_injection() {
                 "<tool_response error=\"true\">\n{{\n  \"success\": false,\n  \"error\": \"{}\",\n  \"sql_executed\": \"{}\"\n}}\n</tool_response>\n\n{}",
                 error_message, failed_sql, error_recovery_prompt
             ),
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         },
     ];
 
@@ -592,16 +578,12 @@ async fn test_sql_error_recovery_wrong_table() {
         ChatMessage {
             role: "system".to_string(),
             content: system_prompt.clone(),
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         },
         ChatMessage {
             role: "user".to_string(),
             content: user_question.to_string(),
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         },
         ChatMessage {
             role: "assistant".to_string(),
@@ -609,9 +591,7 @@ async fn test_sql_error_recovery_wrong_table() {
                 "<tool_call>{{\"name\": \"sql_select\", \"arguments\": {{\"sql\": \"{}\"}}}}</tool_call>",
                 failed_sql
             ),
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         },
         ChatMessage {
             role: "user".to_string(),
@@ -619,9 +599,7 @@ async fn test_sql_error_recovery_wrong_table() {
                 "<tool_response error=\"true\">\n{{\n  \"success\": false,\n  \"error\": \"{}\",\n  \"sql_executed\": \"{}\"\n}}\n</tool_response>\n\n{}",
                 error_message, failed_sql, error_recovery_prompt
             ),
-            system_prompt: None,
-            tool_calls: None,
-            tool_call_id: None,
+            ..Default::default()
         },
     ];
 
@@ -7,8 +7,10 @@
 //! - Foundry Local must be running
 //! - A model must be loaded (e.g., Phi-4)
 
+use crate::agentic_state::Capability;
 use crate::protocol::{ModelInfo, ToolFormat};
 use crate::settings::{AppSettings, ToolCallFormatName};
+use crate::settings_state_machine::SettingsStateMachine;
 use crate::tool_capability::{ToolCapabilityResolver, ToolLaunchFilter};
 use crate::tool_registry::ToolRegistry;
 
@@ -168,6 +170,7 @@ async fn test_tool_search_discovers_deferred() {
         input_schema: None,
         input_examples: None,
         allowed_callers: None,
+        streamable: false,
     };
     registry.register_mcp_tools("test_server", "test_server", &[deferred_tool], true);
 
@@ -246,5 +249,40 @@ async fn test_native_fallback_to_hermes() {
     assert!(!capabilities.use_native_tools);
 }
 
+#[test]
+fn test_safe_mode_removes_python_execution() {
+    // python_execution is always-on and CodeMode is primary, but safe mode wins
+    let mut settings = ToolCapabilityTestHarness::create_test_settings(true, false, ToolCallFormatName::CodeMode);
+    settings.safe_mode = true;
+    let model_info = ToolCapabilityTestHarness::create_test_model_info(false, ToolFormat::Hermes);
+    let filter = ToolLaunchFilter::default();
+    let registry = ToolCapabilityTestHarness::create_test_registry();
 
+    let capabilities = ToolCapabilityResolver::resolve(
+        &settings,
+        &model_info,
+        &filter,
+        &[],
+        &registry,
+    );
+    assert!(!capabilities
+        .available_builtins
+        .contains("python_execution"));
 
+    let settings_sm = SettingsStateMachine::from_settings(&settings, &filter);
+    assert!(!settings_sm.is_capability_enabled(Capability::PythonExecution));
+    assert!(!settings_sm.is_builtin_available("python_execution"));
+
+    // Turning safe mode off restores it
+    settings.safe_mode = false;
+    let capabilities = ToolCapabilityResolver::resolve(
+        &settings,
+        &model_info,
+        &filter,
+        &[],
+        &registry,
+    );
+    assert!(capabilities
+        .available_builtins
+        .contains("python_execution"));
+}
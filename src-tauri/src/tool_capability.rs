@@ -56,12 +56,16 @@ pub struct ResolvedToolCapabilities {
 }
 
 /// Launch-time tool filter (from CLI args)
-/// Used to restrict which tools are available at runtime
+/// Used to restrict which tools are available at runtime.
+/// Denied entries always win over the allowlist.
 #[derive(Debug, Clone, Default)]
 pub struct ToolLaunchFilter {
     pub allowed_builtins: Option<HashSet<String>>,
     pub allowed_servers: Option<HashSet<String>>,
     pub allowed_tools: Option<HashSet<(String, String)>>,
+    pub denied_builtins: HashSet<String>,
+    pub denied_servers: HashSet<String>,
+    pub denied_tools: HashSet<(String, String)>,
 }
 
 impl ToolLaunchFilter {
@@ -69,9 +73,15 @@ impl ToolLaunchFilter {
         self.allowed_builtins.is_none()
             && self.allowed_servers.is_none()
             && self.allowed_tools.is_none()
+            && self.denied_builtins.is_empty()
+            && self.denied_servers.is_empty()
+            && self.denied_tools.is_empty()
     }
 
     pub fn builtin_allowed(&self, name: &str) -> bool {
+        if self.denied_builtins.contains(name) {
+            return false;
+        }
         match &self.allowed_builtins {
             None => true,
             Some(set) => set.contains(name),
@@ -79,6 +89,9 @@ impl ToolLaunchFilter {
     }
 
     pub fn server_allowed(&self, server_id: &str) -> bool {
+        if self.denied_servers.contains(server_id) {
+            return false;
+        }
         match &self.allowed_servers {
             None => true,
             Some(set) => set.contains(server_id),
@@ -86,8 +99,11 @@ impl ToolLaunchFilter {
     }
 
     pub fn tool_allowed(&self, server_id: &str, tool_name: &str) -> bool {
+        if tool_entry_matches(&self.denied_tools, server_id, tool_name) {
+            return false;
+        }
         if let Some(tools) = &self.allowed_tools {
-            if !tools.contains(&(server_id.to_string(), tool_name.to_string())) {
+            if !tool_entry_matches(tools, server_id, tool_name) {
                 return false;
             }
         }
@@ -95,6 +111,48 @@ impl ToolLaunchFilter {
     }
 }
 
+/// Whether a `server::tool` entry covers the tool. Exact entries are checked
+/// first; entries containing `*` are then matched as globs.
+fn tool_entry_matches(
+    entries: &HashSet<(String, String)>,
+    server_id: &str,
+    tool_name: &str,
+) -> bool {
+    entries.contains(&(server_id.to_string(), tool_name.to_string()))
+        || entries.iter().any(|(server, tool)| {
+            (server.contains('*') || tool.contains('*'))
+                && glob_matches(server, server_id)
+                && glob_matches(tool, tool_name)
+        })
+}
+
+/// Match `text` against a glob pattern where `*` stands for any run of characters
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` seen, and the text index it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p + 1, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            // Let the last `*` swallow one more character
+            p = star_p;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 /// Central resolver for tool capabilities
 pub struct ToolCapabilityResolver;
 
@@ -222,6 +280,9 @@ impl ToolCapabilityResolver {
         {
             available.insert(BUILTIN_SQL_SELECT.to_string());
         }
+
+        // Safe mode removes built-ins that run code, whatever else is enabled
+        available.retain(|name| !settings.safe_mode_blocks_builtin(name));
         
         available
     }
@@ -522,3 +583,86 @@ impl ToolCapabilityResolver {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{parse_tool_filter, CliArgs};
+    use clap::Parser;
+
+    fn filter_from(args: &[&str]) -> ToolLaunchFilter {
+        let argv = std::iter::once("plugable-chat").chain(args.iter().copied());
+        parse_tool_filter(&CliArgs::parse_from(argv))
+    }
+
+    #[test]
+    fn test_deny_without_allowlist_hides_only_denied() {
+        let filter = filter_from(&["--tools-deny", "python_execution,files::delete_file,shell"]);
+
+        assert!(!filter.allow_all());
+        assert!(!filter.builtin_allowed("python_execution"));
+        assert!(filter.builtin_allowed("sql_select"));
+        assert!(!filter.tool_allowed("files", "delete_file"));
+        assert!(filter.tool_allowed("files", "read_file"));
+        assert!(!filter.server_allowed("shell"));
+        assert!(!filter.tool_allowed("shell", "run"));
+    }
+
+    #[test]
+    fn test_deny_wins_over_allowlist() {
+        let filter = filter_from(&[
+            "--tools",
+            "python_execution,files",
+            "--tools-deny",
+            "python_execution,files::delete_file",
+        ]);
+
+        assert!(!filter.builtin_allowed("python_execution"));
+        assert!(filter.server_allowed("files"));
+        assert!(filter.tool_allowed("files", "read_file"));
+        assert!(!filter.tool_allowed("files", "delete_file"));
+
+        // Denying a whole server overrides an allowlisted tool on it
+        let filter = filter_from(&["--tools", "files::read_file", "--tools-deny", "files"]);
+        assert!(!filter.tool_allowed("files", "read_file"));
+    }
+
+    #[test]
+    fn test_tool_allowlist_globs() {
+        let filter = filter_from(&["--tools", "files::read_*,*::list_*,git::*_status,*::exact"]);
+
+        assert!(filter.tool_allowed("files", "read_file"));
+        assert!(filter.tool_allowed("files", "read_"));
+        assert!(!filter.tool_allowed("files", "write_file"));
+        assert!(!filter.tool_allowed("other", "read_file"));
+        assert!(filter.tool_allowed("github", "list_issues"));
+        assert!(filter.tool_allowed("git", "repo_status"));
+        assert!(!filter.tool_allowed("git", "status_report"));
+        assert!(filter.tool_allowed("any", "exact"));
+
+        let filter = filter_from(&["--tools", "*::*", "--tools-deny", "files::delete_*"]);
+        assert!(filter.tool_allowed("files", "read_file"));
+        assert!(!filter.tool_allowed("files", "delete_file"));
+    }
+
+    #[test]
+    fn test_literal_star_server_id() {
+        // A plain server entry is matched exactly, so "*" names a server literally
+        let filter = filter_from(&["--tools", "*"]);
+        assert!(filter.server_allowed("*"));
+        assert!(!filter.server_allowed("files"));
+        assert!(filter.tool_allowed("*", "anything"));
+
+        let filter = filter_from(&["--tools", "*::run"]);
+        assert!(filter.tool_allowed("*", "run"));
+        assert!(!filter.tool_allowed("*", "stop"));
+    }
+
+    #[test]
+    fn test_no_filters_allow_all() {
+        let filter = filter_from(&[]);
+        assert!(filter.allow_all());
+        assert!(filter.builtin_allowed("python_execution"));
+        assert!(filter.tool_allowed("files", "delete_file"));
+    }
+}
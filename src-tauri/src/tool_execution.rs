@@ -8,31 +8,180 @@
 use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot, RwLock};
 
+use crate::actors::mcp_host_actor::McpToolResult;
 use crate::actors::python_actor::PythonMsg;
 use crate::protocol::{McpHostMsg, ParsedToolCall};
 use crate::python_helpers::strip_unsupported_python;
 use crate::tool_registry::{self, SharedToolRegistry, ToolSearchResult};
 use crate::tools::code_execution::{CodeExecutionExecutor, CodeExecutionInput, CodeExecutionOutput};
-use crate::tools::tool_search::{ToolSearchExecutor, ToolSearchInput};
+use crate::tools::tool_search::{
+    filter_by_min_relevance, ToolSearchExecutor, ToolSearchInput, NO_RELEVANT_TOOLS_NOTE,
+};
 use fastembed::TextEmbedding;
+use serde_json::Value;
 
 /// Tool type identifier for python_execution - used for allowed_callers filtering.
 pub const PYTHON_EXECUTION_TOOL_TYPE: &str = "python_execution_20251206";
 
+/// JSON type name of a value, as used in JSON schema
+pub fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Whether `value` satisfies a JSON schema `type` name
+pub fn matches_schema_type(value: &Value, expected: &str) -> bool {
+    match (expected, value) {
+        ("number", Value::Number(_)) => true,
+        ("integer", Value::Number(n)) => n.as_f64().is_some_and(|f| f.fract() == 0.0),
+        _ => json_type_name(value) == expected,
+    }
+}
+
+/// Check tool call arguments against the tool's input schema.
+///
+/// Only the top level is checked: the arguments must be an object, required
+/// fields must be present, and provided fields must match their declared
+/// `type` and `enum`. Returns every problem found, so the model can fix them
+/// all in one retry.
+pub fn validate_tool_arguments(arguments: &Value, schema: &Value) -> Result<(), String> {
+    let Some(arguments) = arguments.as_object() else {
+        return Err(format!(
+            "arguments must be a JSON object, got {}",
+            json_type_name(arguments)
+        ));
+    };
+
+    let mut problems = Vec::new();
+    let required = schema.get("required").and_then(|r| r.as_array());
+    for field in required.into_iter().flatten().filter_map(|f| f.as_str()) {
+        if arguments.get(field).is_none_or(Value::is_null) {
+            problems.push(format!("missing required field '{}'", field));
+        }
+    }
+
+    let properties = schema.get("properties").and_then(|p| p.as_object());
+    for (field, property) in properties.into_iter().flatten() {
+        let Some(value) = arguments.get(field).filter(|v| !v.is_null()) else {
+            continue;
+        };
+        let expected: Vec<&str> = match property.get("type") {
+            Some(Value::String(t)) => vec![t.as_str()],
+            Some(Value::Array(types)) => types.iter().filter_map(|t| t.as_str()).collect(),
+            _ => Vec::new(),
+        };
+        if !expected.is_empty() && !expected.iter().any(|t| matches_schema_type(value, t)) {
+            problems.push(format!(
+                "invalid field '{}': expected {}, got {}",
+                field,
+                expected.join(" or "),
+                json_type_name(value)
+            ));
+            continue;
+        }
+        if let Some(allowed) = property.get("enum").and_then(|e| e.as_array()) {
+            if !allowed.contains(value) {
+                let allowed: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+                problems.push(format!(
+                    "invalid field '{}': must be one of {}",
+                    field,
+                    allowed.join(", ")
+                ));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("; "))
+    }
+}
+
+/// Decoded size of a base64 payload
+fn base64_decoded_len(data: &str) -> usize {
+    data.trim().trim_end_matches('=').len() * 3 / 4
+}
+
+/// Flatten an MCP tool result into text for the model.
+///
+/// Text parts are joined as-is. Images, audio and binary resources become
+/// placeholders like `[image: image/png, 1024 bytes]` so the model knows they
+/// exist; images are also returned as data URLs for vision models.
+pub fn render_mcp_tool_result(result: &McpToolResult) -> (String, Vec<String>) {
+    let mut parts = Vec::new();
+    let mut images = Vec::new();
+
+    for content in &result.content {
+        if let Some(text) = &content.text {
+            parts.push(text.clone());
+            continue;
+        }
+        if let Some(resource) = &content.resource {
+            let mime = resource.mime_type.as_deref().unwrap_or("application/octet-stream");
+            match (&resource.text, &resource.blob) {
+                (Some(text), _) => parts.push(text.clone()),
+                (None, Some(blob)) => {
+                    parts.push(format!(
+                        "[resource: {}, {}, {} bytes]",
+                        resource.uri,
+                        mime,
+                        base64_decoded_len(blob)
+                    ));
+                    if mime.starts_with("image/") {
+                        images.push(format!("data:{};base64,{}", mime, blob.trim()));
+                    }
+                }
+                (None, None) => parts.push(format!("[resource: {}]", resource.uri)),
+            }
+            continue;
+        }
+        if let Some(data) = &content.data {
+            let mime = content.mime_type.as_deref().unwrap_or("application/octet-stream");
+            parts.push(format!(
+                "[{}: {}, {} bytes]",
+                content.content_type,
+                mime,
+                base64_decoded_len(data)
+            ));
+            if content.content_type == "image" {
+                images.push(format!("data:{};base64,{}", mime, data.trim()));
+            }
+            continue;
+        }
+        match &content.uri {
+            Some(uri) => parts.push(format!("[{}: {}]", content.content_type, uri)),
+            None => parts.push(format!("[{} content]", content.content_type)),
+        }
+    }
+
+    (parts.join("\n"), images)
+}
+
 /// Execute a tool call via McpHostActor.
 ///
 /// This is the main entry point for executing MCP server tools.
-/// The result is returned as a string, with errors wrapped in Result::Err.
+/// The result is returned as text plus any images it contained (as data
+/// URLs), with errors wrapped in Result::Err.
 pub async fn dispatch_tool_call_to_executor(
     mcp_host_tx: &mpsc::Sender<McpHostMsg>,
     call: &ParsedToolCall,
-) -> Result<String, String> {
+    timeout: Option<std::time::Duration>,
+) -> Result<(String, Vec<String>), String> {
     let (tx, rx) = oneshot::channel();
     mcp_host_tx
         .send(McpHostMsg::ExecuteTool {
             server_id: call.server.clone(),
             tool_name: call.tool.clone(),
             arguments: call.arguments.clone(),
+            timeout,
             respond_to: tx,
         })
         .await
@@ -40,19 +189,12 @@ pub async fn dispatch_tool_call_to_executor(
 
     let result = rx.await.map_err(|_| "MCP Host actor died".to_string())??;
 
-    // Convert the result to a string
-    let result_text = result
-        .content
-        .iter()
-        .filter_map(|c| c.text.as_ref())
-        .map(|s| s.as_str())
-        .collect::<Vec<_>>()
-        .join("\n");
+    let (result_text, images) = render_mcp_tool_result(&result);
 
     if result.is_error {
         Err(result_text)
     } else {
-        Ok(result_text)
+        Ok((result_text, images))
     }
 }
 
@@ -108,11 +250,13 @@ pub async fn resolve_mcp_server_for_tool(
 ///
 /// Searches the tool registry for tools matching the given queries,
 /// returning formatted results that guide the model to use python_execution.
+/// Hits scoring below `min_relevance` are neither materialized nor shown.
 pub async fn execute_tool_search(
     input: ToolSearchInput,
     tool_registry: SharedToolRegistry,
     embedding_model: Arc<RwLock<Option<Arc<TextEmbedding>>>>,
     max_results: usize,
+    min_relevance: f32,
 ) -> Result<(String, Vec<ToolSearchResult>), String> {
     let executor = ToolSearchExecutor::new(tool_registry.clone(), embedding_model);
     let mut capped_input = input.clone();
@@ -120,12 +264,12 @@ pub async fn execute_tool_search(
     capped_input.top_k = std::cmp::max(1, std::cmp::min(capped_input.top_k, top_cap));
     let output = executor.execute(capped_input).await?;
 
-    // Filter out tools that cannot be called from python_execution (respect allowed_callers)
+    // Drop weak matches and tools that cannot be called from python_execution
+    // (respect allowed_callers)
     let filtered_tools: Vec<ToolSearchResult> = {
         let registry_guard = tool_registry.read().await;
-        output
-            .tools
-            .iter()
+        filter_by_min_relevance(&output.tools, min_relevance)
+            .into_iter()
             .filter(|tool| {
                 let key = format!("{}___{}", tool.server_id, tool.name);
                 match registry_guard.get_tool(&key) {
@@ -133,10 +277,18 @@ pub async fn execute_tool_search(
                     None => true,
                 }
             })
-            .cloned()
             .collect()
     };
 
+    if filtered_tools.is_empty() {
+        let result = format!(
+            "{}\n\nNo tool matched the search closely enough. Answer directly or search again \
+with different queries.\n",
+            NO_RELEVANT_TOOLS_NOTE
+        );
+        return Ok((result, filtered_tools));
+    }
+
     // Materialize discovered tools
     executor.materialize_results(&filtered_tools).await;
 
@@ -181,14 +333,7 @@ pub async fn execute_tool_search(
 
                 // Build example call with placeholders
                 if is_required {
-                    let example_val = match type_str {
-                        "string" => format!("\"...\""),
-                        "integer" => "1".to_string(),
-                        "boolean" => "True".to_string(),
-                        "array" => "[]".to_string(),
-                        _ => "...".to_string(),
-                    };
-                    example_params.push(format!("{}={}", name, example_val));
+                    example_params.push(format!("{}={}", name, example_value_for_schema(schema)));
                 }
             }
         }
@@ -232,15 +377,66 @@ pub async fn execute_tool_search(
     Ok((result, filtered_tools))
 }
 
+/// Placeholder Python literal for a parameter in tool_search's example call.
+///
+/// Objects become dict literals of their required (or, if none are marked,
+/// all) properties, and arrays of objects become `[{...}]`. Only one level of
+/// nesting is expanded; deeper objects are shown as `{}`.
+pub fn example_value_for_schema(schema: &Value) -> String {
+    example_value_at_depth(schema, 0)
+}
+
+fn example_value_at_depth(schema: &Value, depth: usize) -> String {
+    match schema.get("type").and_then(|t| t.as_str()).unwrap_or("any") {
+        "string" => "\"...\"".to_string(),
+        "integer" => "1".to_string(),
+        "number" => "1.0".to_string(),
+        "boolean" => "True".to_string(),
+        "array" => match schema.get("items") {
+            Some(items) if items.get("type").and_then(|t| t.as_str()) == Some("object") => {
+                format!("[{}]", example_value_at_depth(items, depth))
+            }
+            _ => "[]".to_string(),
+        },
+        "object" => {
+            let props = match schema.get("properties").and_then(|p| p.as_object()) {
+                Some(props) if depth == 0 && !props.is_empty() => props,
+                _ => return "{}".to_string(),
+            };
+            let required: Vec<&str> = schema
+                .get("required")
+                .and_then(|r| r.as_array())
+                .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect())
+                .unwrap_or_default();
+            let entries: Vec<String> = props
+                .iter()
+                .filter(|(name, _)| required.is_empty() || required.contains(&name.as_str()))
+                .map(|(name, prop)| {
+                    format!("\"{}\": {}", name, example_value_at_depth(prop, depth + 1))
+                })
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+        _ => "...".to_string(),
+    }
+}
+
 /// Execute the python_execution built-in tool.
 ///
 /// Runs Python code in a sandboxed environment with access to tool functions.
+/// When `progress_tx` is set, stdout chunks are sent to it while the code runs.
+/// With `input.validate_only` the code is only checked, and problems are reported
+/// through the output's `stderr` instead of an `Err`.
 pub async fn execute_python_code(
     input: CodeExecutionInput,
     exec_id: String,
     tool_registry: SharedToolRegistry,
     python_tx: &mpsc::Sender<PythonMsg>,
     allow_tool_search: bool,
+    session_key: Option<String>,
+    allowed_modules: Option<Vec<String>>,
+    memory_limit_bytes: Option<usize>,
+    progress_tx: Option<mpsc::Sender<String>>,
 ) -> Result<CodeExecutionOutput, String> {
     // Strip unsupported keywords before execution
     let code = strip_unsupported_python(&input.code);
@@ -324,17 +520,22 @@ pub async fn execute_python_code(
     let _ = std::io::stdout().flush();
 
     // Create execution context
-    let context = CodeExecutionExecutor::create_context(
+    let mut context = CodeExecutionExecutor::create_context(
         exec_id.clone(),
         filtered_tools,
         input.context.clone(),
         tool_modules,
     );
+    context.session_key = session_key;
+    context.allowed_modules = allowed_modules;
+    context.memory_limit_bytes = memory_limit_bytes;
 
     // Create modified input with the cleaned code
     let cleaned_input = CodeExecutionInput {
         code,
         context: input.context,
+        timeout_ms: input.timeout_ms,
+        validate_only: input.validate_only,
     };
 
     // Pre-validate before sending to the Python actor so errors can be surfaced immediately
@@ -342,14 +543,33 @@ pub async fn execute_python_code(
     for module in &context.tool_modules {
         import_context.add_tool_module(module.python_name.clone(), module.server_id.clone());
     }
+    if let Some(modules) = &context.allowed_modules {
+        import_context.set_allowed_modules(modules.clone());
+    }
     let validation_context = crate::tools::code_execution::ValidationContext {
         import_context: Some(&import_context),
         allowed_functions: Some(&context.allowed_functions),
     };
-    crate::tools::code_execution::CodeExecutionExecutor::validate_input_with_rules(
+    let validation = crate::tools::code_execution::CodeExecutionExecutor::validate_input_with_rules(
         &cleaned_input,
         Some(validation_context),
-    )?;
+    );
+
+    // Validation-only mode: also compile the code, then report instead of running it
+    if cleaned_input.validate_only {
+        let validation =
+            validation.and_then(|_| python_sandbox::check_syntax(&cleaned_input.code.join("\n")));
+        println!(
+            "[python_execution] Validation only: {}",
+            if validation.is_ok() { "OK" } else { "FAILED" }
+        );
+        return Ok(CodeExecutionOutput {
+            success: validation.is_ok(),
+            stderr: validation.err().unwrap_or_default(),
+            ..Default::default()
+        });
+    }
+    validation?;
 
     println!("[python_execution] Sending to Python actor...");
     let _ = std::io::stdout().flush();
@@ -359,7 +579,8 @@ pub async fn execute_python_code(
     python_tx
         .send(PythonMsg::ExecuteSandboxedCode {
             input: cleaned_input,
-            context,
+            context: Box::new(context),
+            progress_tx,
             respond_to,
         })
         .await
@@ -382,6 +603,7 @@ pub async fn execute_python_code(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
 
     // Note: Most of these functions require actor infrastructure to test properly.
     // Unit tests are limited to pure functions.
@@ -391,4 +613,149 @@ mod tests {
         // Basic compilation check
         assert!(true);
     }
+
+    #[test]
+    fn test_render_mcp_tool_result_with_image() {
+        let result: McpToolResult = serde_json::from_value(json!({
+            "content": [
+                {"type": "text", "text": "Here is the chart"},
+                {"type": "image", "data": "iVBORw0KGgo=", "mimeType": "image/png"},
+                {"type": "resource", "resource": {"uri": "file:///report.txt", "text": "Totals"}},
+                {"type": "resource_link", "uri": "file:///raw.bin"}
+            ]
+        }))
+        .unwrap();
+
+        let (text, images) = render_mcp_tool_result(&result);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "Here is the chart",
+                "[image: image/png, 8 bytes]",
+                "Totals",
+                "[resource_link: file:///raw.bin]",
+            ]
+        );
+        assert_eq!(images, vec!["data:image/png;base64,iVBORw0KGgo=".to_string()]);
+    }
+
+    #[test]
+    fn test_validate_tool_arguments() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "city": {"type": "string"},
+                "days": {"type": "integer"},
+                "units": {"type": "string", "enum": ["metric", "imperial"]},
+                "note": {"type": ["string", "null"]}
+            },
+            "required": ["city"]
+        });
+
+        assert!(validate_tool_arguments(&json!({"city": "Oslo", "days": 3}), &schema).is_ok());
+        assert!(validate_tool_arguments(&json!({"city": "Oslo", "days": 3.0}), &schema).is_ok());
+
+        let err = validate_tool_arguments(&json!({"days": "3", "units": "kelvin"}), &schema)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "missing required field 'city'; invalid field 'days': expected integer, got string; \
+             invalid field 'units': must be one of \"metric\", \"imperial\""
+        );
+
+        let err = validate_tool_arguments(&json!("Oslo"), &schema).unwrap_err();
+        assert!(err.contains("must be a JSON object"));
+        // No schema constraints: anything object-shaped passes
+        assert!(validate_tool_arguments(&json!({"x": 1}), &json!({})).is_ok());
+    }
+
+    #[test]
+    fn test_example_value_for_scalar_schemas() {
+        assert_eq!(example_value_for_schema(&json!({"type": "string"})), "\"...\"");
+        assert_eq!(example_value_for_schema(&json!({"type": "integer"})), "1");
+        assert_eq!(example_value_for_schema(&json!({"type": "boolean"})), "True");
+        assert_eq!(example_value_for_schema(&json!({"type": "array"})), "[]");
+        assert_eq!(example_value_for_schema(&json!({})), "...");
+    }
+
+    #[test]
+    fn test_example_value_for_nested_object() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "field": {"type": "string"},
+                "limit": {"type": "integer"},
+                "inner": {"type": "object", "properties": {"x": {"type": "string"}}}
+            },
+            "required": ["field", "inner"]
+        });
+        assert_eq!(
+            example_value_for_schema(&schema),
+            "{\"field\": \"...\", \"inner\": {}}"
+        );
+
+        // Without a required list every property is shown
+        let schema = json!({"type": "object", "properties": {"field": {"type": "string"}}});
+        assert_eq!(example_value_for_schema(&schema), "{\"field\": \"...\"}");
+    }
+
+    #[test]
+    fn test_example_value_for_array_of_objects() {
+        let schema = json!({
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {"name": {"type": "string"}, "count": {"type": "integer"}},
+                "required": ["name"]
+            }
+        });
+        assert_eq!(example_value_for_schema(&schema), "[{\"name\": \"...\"}]");
+
+        let strings = json!({"type": "array", "items": {"type": "string"}});
+        assert_eq!(example_value_for_schema(&strings), "[]");
+    }
+
+    async fn validate(code: &[&str]) -> CodeExecutionOutput {
+        // Validation-only mode never reaches the Python actor
+        let (python_tx, _python_rx) = mpsc::channel(1);
+        let input = CodeExecutionInput {
+            code: code.iter().map(|s| s.to_string()).collect(),
+            context: None,
+            timeout_ms: None,
+            validate_only: true,
+        };
+        execute_python_code(
+            input,
+            "test".to_string(),
+            tool_registry::create_shared_registry(),
+            &python_tx,
+            false,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .expect("validation should report through the output")
+    }
+
+    #[tokio::test]
+    async fn test_validate_only_accepts_valid_code() {
+        let output = validate(&["import math", "print(math.sqrt(16))"]).await;
+        assert!(output.success);
+        assert!(output.stderr.is_empty());
+        assert!(output.stdout.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_validate_only_reports_problems() {
+        let output = validate(&["import subprocess"]).await;
+        assert!(!output.success);
+        assert!(output.stderr.contains("subprocess"), "got: {}", output.stderr);
+
+        let output = validate(&["if True", "    print(1)"]).await;
+        assert!(!output.success);
+        assert!(output.stderr.contains("line 1"), "got: {}", output.stderr);
+    }
 }
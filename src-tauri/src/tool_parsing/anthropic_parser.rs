@@ -0,0 +1,95 @@
+//! Anthropic content block tool call parser.
+//!
+//! Parses `tool_use` content blocks from responses of backends that speak the
+//! Anthropic messages format:
+//! `[{"type": "tool_use", "id": "toolu_01", "name": "get_weather", "input": {...}}]`
+
+use serde_json::Value;
+
+use super::common::parse_combined_tool_name;
+use crate::protocol::ParsedToolCall;
+
+/// Parse `tool_use` blocks from a response. Accepts a content block array, a whole
+/// message (`{"role": "assistant", "content": [...]}`), or single blocks, anywhere
+/// in the text. The block's `id` becomes the call id.
+pub fn parse_anthropic_tool_calls(content: &str) -> Vec<ParsedToolCall> {
+    let mut calls = Vec::new();
+    if !content.contains("tool_use") {
+        return calls;
+    }
+
+    let mut offset = 0;
+    while let Some(start) = content[offset..].find(['{', '[']).map(|i| offset + i) {
+        let mut stream = serde_json::Deserializer::from_str(&content[start..]).into_iter::<Value>();
+        match stream.next() {
+            Some(Ok(value)) => {
+                let end = start + stream.byte_offset();
+                collect_tool_use_blocks(&value, &content[start..end], &mut calls);
+                offset = end;
+            }
+            _ => offset = start + 1,
+        }
+    }
+    calls
+}
+
+/// Collect `tool_use` blocks from a block, a block array, or a message's `content`
+fn collect_tool_use_blocks(value: &Value, raw: &str, calls: &mut Vec<ParsedToolCall>) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                collect_tool_use_blocks(item, raw, calls);
+            }
+        }
+        Value::Object(map) => {
+            if map.get("type").and_then(|t| t.as_str()) == Some("tool_use") {
+                if let Some(name) = map.get("name").and_then(|n| n.as_str()) {
+                    let (server, tool) = parse_combined_tool_name(name);
+                    calls.push(ParsedToolCall {
+                        server,
+                        tool,
+                        arguments: map
+                            .get("input")
+                            .cloned()
+                            .unwrap_or_else(|| Value::Object(Default::default())),
+                        raw: raw.to_string(),
+                        id: map.get("id").and_then(|id| id.as_str()).map(str::to_string),
+                    });
+                }
+            } else if let Some(content) = map.get("content") {
+                collect_tool_use_blocks(content, raw, calls);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tool_use_blocks() {
+        let content = r#"[{"type": "text", "text": "Checking."}, {"type": "tool_use", "id": "toolu_01", "name": "weather___get_forecast", "input": {"city": "Oslo"}}]"#;
+        let calls = parse_anthropic_tool_calls(content);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].server, "weather");
+        assert_eq!(calls[0].tool, "get_forecast");
+        assert_eq!(calls[0].arguments["city"], "Oslo");
+        assert_eq!(calls[0].id.as_deref(), Some("toolu_01"));
+    }
+
+    #[test]
+    fn test_parse_tool_use_in_message_and_text() {
+        let content = r#"Let me look. {"role": "assistant", "content": [{"type": "tool_use", "id": "toolu_02", "name": "sql_select", "input": {"sql": "SELECT 1"}}]}"#;
+        let calls = parse_anthropic_tool_calls(content);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].server, "unknown");
+        assert_eq!(calls[0].tool, "sql_select");
+
+        assert!(
+            parse_anthropic_tool_calls(r#"{"type": "text", "text": "no tool_use here"}"#)
+                .is_empty()
+        );
+    }
+}
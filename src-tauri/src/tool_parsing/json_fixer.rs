@@ -65,19 +65,124 @@ pub fn repair_malformed_json(json_str: &str) -> String {
     result
 }
 
+/// Repair a tool call's arguments that aren't valid JSON: trailing commas,
+/// single-quoted strings, unquoted keys and Python literals. Unlike
+/// `repair_malformed_json`, string contents are never changed.
+/// Returns None unless the result is a JSON object or array.
+pub fn repair_json_arguments(raw: &str) -> Option<Value> {
+    let trimmed = raw.trim().trim_start_matches('\u{feff}');
+    serde_json::from_str::<Value>(trimmed)
+        .ok()
+        .or_else(|| serde_json::from_str::<Value>(&normalize_json_syntax(trimmed)).ok())
+        .or_else(|| json5::from_str::<Value>(trimmed).ok())
+        .filter(|value| value.is_object() || value.is_array())
+}
+
+/// Arguments that arrived as a JSON-encoded (possibly malformed) string,
+/// decoded into the object or array they describe; other values are unchanged
+pub fn repair_string_arguments(arguments: Value) -> Value {
+    match &arguments {
+        Value::String(raw) => repair_json_arguments(raw).unwrap_or(arguments),
+        _ => arguments,
+    }
+}
+
+/// Rewrite JSON-like text as JSON, scanning strings so their contents are kept:
+/// single-quoted strings become double-quoted, bare keys are quoted, Python
+/// literals outside strings are replaced and trailing commas are dropped.
+fn normalize_json_syntax(s: &str) -> String {
+    let chars: Vec<char> = s.chars().collect();
+    let next_significant = |from: usize| {
+        chars[from.min(chars.len())..]
+            .iter()
+            .find(|c| !c.is_whitespace())
+            .copied()
+    };
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' | '\'' => {
+                out.push('"');
+                i += 1;
+                while i < chars.len() && chars[i] != c {
+                    match chars[i] {
+                        '\\' if i + 1 < chars.len() => {
+                            // `\'` is not a JSON escape, and `'` needs none in a double-quoted string
+                            if chars[i + 1] != '\'' {
+                                out.push('\\');
+                            }
+                            out.push(chars[i + 1]);
+                            i += 1;
+                        }
+                        // Only reachable inside a single-quoted string
+                        '"' => out.push_str("\\\""),
+                        '\n' => out.push_str("\\n"),
+                        '\r' => out.push_str("\\r"),
+                        '\t' => out.push_str("\\t"),
+                        other => out.push(other),
+                    }
+                    i += 1;
+                }
+                out.push('"');
+                i += 1;
+            }
+            ',' => {
+                if !matches!(next_significant(i + 1), Some('}') | Some(']')) {
+                    out.push(',');
+                }
+                i += 1;
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                if next_significant(i) == Some(':') {
+                    out.push('"');
+                    out.push_str(&word);
+                    out.push('"');
+                } else {
+                    out.push_str(match word.as_str() {
+                        "True" => "true",
+                        "False" => "false",
+                        "None" => "null",
+                        other => other,
+                    });
+                }
+            }
+            other => {
+                out.push(other);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
 /// Parse JSON with lenient fallbacks.
 /// Fallback chain:
 /// 1. Direct serde_json parse (fast path)
-/// 2. repair_malformed_json preprocessing + serde_json
-/// 3. Single quote replacement + serde_json
-/// 4. json5 parser (handles unquoted keys, comments, trailing commas)
-/// 5. Balanced brace extraction + retry
+/// 2. repair_json_arguments (string-aware quote, key and comma fixes)
+/// 3. repair_malformed_json preprocessing + serde_json
+/// 4. Single quote replacement + serde_json
+/// 5. json5 parser (handles unquoted keys, comments, trailing commas)
+/// 6. Balanced brace extraction + retry
 pub fn parse_json_lenient(raw: &str) -> Option<Value> {
     // Fast path: try direct parse
     if let Ok(val) = serde_json::from_str::<Value>(raw) {
         return Some(unwrap_json_structure(val));
     }
 
+    if let Some(val) = repair_json_arguments(raw) {
+        return Some(unwrap_json_structure(val));
+    }
+
     // Fix trivial JSON issues first
     let fixed = repair_malformed_json(raw);
     if let Ok(val) = serde_json::from_str::<Value>(&fixed) {
@@ -206,6 +311,7 @@ pub fn find_json_objects_in_content(content: &str) -> Vec<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
 
     #[test]
     fn test_repair_malformed_json_python_booleans() {
@@ -243,6 +349,54 @@ mod tests {
         assert_eq!(val.get("name").and_then(|v| v.as_str()), Some("test_tool"));
     }
 
+    #[test]
+    fn test_repair_json_arguments_trailing_commas() {
+        let repaired = repair_json_arguments(r#"{"query": "orders", "tags": ["a", "b",],}"#);
+        assert_eq!(
+            repaired,
+            Some(json!({"query": "orders", "tags": ["a", "b"]}))
+        );
+    }
+
+    #[test]
+    fn test_repair_json_arguments_single_quoted_strings() {
+        let repaired = repair_json_arguments(
+            r#"{'city': 'Oslo', 'note': 'say "hi"', 'name': 'O\'Brien', "text": "it's, fine"}"#,
+        );
+        assert_eq!(
+            repaired,
+            Some(json!({
+                "city": "Oslo",
+                "note": "say \"hi\"",
+                "name": "O'Brien",
+                "text": "it's, fine"
+            }))
+        );
+    }
+
+    #[test]
+    fn test_repair_json_arguments_unquoted_keys() {
+        let repaired = repair_json_arguments(
+            "{limit: 10, include_archived: True, cursor: None, filter: {status: 'open'}}",
+        );
+        assert_eq!(
+            repaired,
+            Some(json!({
+                "limit": 10,
+                "include_archived": true,
+                "cursor": null,
+                "filter": {"status": "open"}
+            }))
+        );
+        // String contents that look like literals are left alone
+        assert_eq!(
+            repair_json_arguments("{msg: 'True, None,]'}"),
+            Some(json!({"msg": "True, None,]"}))
+        );
+        assert_eq!(repair_json_arguments("not json"), None);
+        assert_eq!(repair_json_arguments("'just a string'"), None);
+    }
+
     #[test]
     fn test_unwrap_json_structure_single_element_array() {
         let input = r#"[{"name": "test", "arguments": {}}]"#;
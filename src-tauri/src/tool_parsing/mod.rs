@@ -12,6 +12,7 @@
 //! - Harmony: <|channel|>commentary to={tool}... format (GPT-OSS)
 //! - Gemini: function_call in response, "function" role for results  
 //! - Granite: <function_call>XML</function_call> format
+//! - Anthropic: `tool_use` content blocks in the response, `tool_result` blocks for results
 //!
 //! ## Module Structure
 //! - `json_fixer`: JSON repair utilities for malformed LLM output
@@ -25,6 +26,7 @@
 //! - `markdown_json_parser`: ```json code block parsing
 //! - `json_parser`: Pure JSON parsing
 //! - `pythonic_parser`: Pythonic function call parsing
+//! - `anthropic_parser`: Anthropic `tool_use` content block parsing
 //! - `python_detector`: Python code detection for Code Mode
 //! - `result_formatter`: Tool result formatting for different models
 
@@ -42,6 +44,7 @@ pub mod braintrust_parser;
 pub mod markdown_json_parser;
 pub mod json_parser;
 pub mod pythonic_parser;
+pub mod anthropic_parser;
 
 // Python detection
 pub mod python_detector;
@@ -107,6 +110,24 @@ pub fn format_tools_for_model(
             // Harmony (gpt-oss) uses OpenAI-compatible tool definitions
             json!(tools)
         }
+        ToolFormat::Anthropic => {
+            // Anthropic tool definitions: name, description, input_schema
+            let definitions: Vec<Value> = tools
+                .iter()
+                .map(|t| {
+                    json!({
+                        "name": t.function.name,
+                        "description": t.function.description.clone().unwrap_or_default(),
+                        "input_schema": t
+                            .function
+                            .parameters
+                            .clone()
+                            .unwrap_or_else(|| json!({"type": "object", "properties": {}})),
+                    })
+                })
+                .collect();
+            json!(definitions)
+        }
         ToolFormat::TextBased => {
             // No native tool calling - return empty array
             // Text-based tools are handled via system prompt
@@ -138,16 +159,21 @@ pub fn parse_tool_calls_for_model_profile(
             ToolCallFormatName::Mistral => tagged_parser::parse_tagged_tool_calls(response),
             ToolCallFormatName::Pythonic => pythonic_parser::parse_pythonic_tool_calls(response),
             ToolCallFormatName::PureJson => json_parser::parse_pure_json_tool_calls(response),
+            // Only backends that speak the Anthropic messages format emit these blocks
+            ToolCallFormatName::Anthropic if tool_format == ToolFormat::Anthropic => {
+                anthropic_parser::parse_anthropic_tool_calls(response)
+            }
+            ToolCallFormatName::Anthropic => Vec::new(),
             // Native and CodeMode are handled via structured response or python_execution
             ToolCallFormatName::Native | ToolCallFormatName::CodeMode => Vec::new(),
         };
         if !calls.is_empty() {
-            return calls;
+            return with_repaired_arguments(calls);
         }
     }
 
     // Fallback to model-specific parsing only if the format is enabled.
-    match tool_format {
+    let calls = match tool_format {
         ToolFormat::OpenAI | ToolFormat::Hermes => {
             if formats.is_enabled(ToolCallFormatName::Hermes) {
                 hermes_parser::parse_hermes_tool_calls(response)
@@ -164,6 +190,13 @@ pub fn parse_tool_calls_for_model_profile(
                 Vec::new()
             }
         }
+        ToolFormat::Anthropic => {
+            if formats.is_enabled(ToolCallFormatName::Anthropic) {
+                anthropic_parser::parse_anthropic_tool_calls(response)
+            } else {
+                Vec::new()
+            }
+        }
         ToolFormat::Harmony => {
             // gpt-oss harmony format - always try to parse
             // Harmony uses native format so we don't check enabled formats
@@ -178,13 +211,196 @@ pub fn parse_tool_calls_for_model_profile(
                 Vec::new()
             }
         }
+    };
+    with_repaired_arguments(calls)
+}
+
+/// Decode arguments that a model sent as a JSON string, repairing common mistakes
+fn with_repaired_arguments(mut calls: Vec<ParsedToolCall>) -> Vec<ParsedToolCall> {
+    for call in &mut calls {
+        call.arguments = json_fixer::repair_string_arguments(std::mem::take(&mut call.arguments));
+    }
+    calls
+}
+
+/// Whether a streamed response already holds a complete tool call in `format`.
+///
+/// Used to stop generation early once the call is closed, before the model
+/// starts inventing tool results. Checks are kept cheap since they run per token.
+pub fn format_is_complete(response: &str, format: ToolCallFormatName) -> bool {
+    match format {
+        ToolCallFormatName::Hermes => {
+            response.contains("</tool_call>") || response.contains("</function_call>")
+        }
+        ToolCallFormatName::Mistral => {
+            let Some(idx) = response.find("[TOOL_CALLS]") else {
+                return false;
+            };
+            let payload = &response[idx + "[TOOL_CALLS]".len()..];
+            payload.contains("[/TOOL_CALLS]")
+                || (is_closed_json(payload)
+                    && !tagged_parser::parse_tagged_tool_calls(response).is_empty())
+        }
+        ToolCallFormatName::PureJson => {
+            let trimmed = response.trim();
+            (trimmed.starts_with('{') || trimmed.starts_with('['))
+                && is_closed_json(trimmed)
+                && !json_parser::parse_pure_json_tool_calls(trimmed).is_empty()
+        }
+        ToolCallFormatName::Pythonic => {
+            response.trim_end().ends_with(')')
+                && !pythonic_parser::parse_pythonic_tool_calls(response).is_empty()
+        }
+        ToolCallFormatName::CodeMode => response
+            .find("```python")
+            .map(|start| response[start + "```python".len()..].contains("```"))
+            .unwrap_or(false),
+        ToolCallFormatName::Anthropic => {
+            response.contains("\"tool_use\"")
+                && !anthropic_parser::parse_anthropic_tool_calls(response).is_empty()
+        }
+        // Native calls arrive as structured deltas, not in the text
+        ToolCallFormatName::Native => false,
+    }
+}
+
+/// Whether any of the enabled formats has a complete tool call in the response
+pub fn any_format_complete(response: &str, formats: &ToolCallFormatConfig) -> bool {
+    formats
+        .enabled
+        .iter()
+        .any(|fmt| format_is_complete(response, *fmt))
+}
+
+/// Describe a tool call the model attempted in an enabled format but left
+/// malformed (cut off, or with JSON that doesn't parse even leniently).
+///
+/// Returns a short hint for the model, or None when there is no such attempt.
+/// Lenient parsing can still pull a name out of a broken call, so this is
+/// checked before parsed calls are trusted.
+pub fn detect_malformed_tool_call(
+    response: &str,
+    formats: &ToolCallFormatConfig,
+) -> Option<String> {
+    if formats.is_enabled(ToolCallFormatName::Hermes) {
+        if let Some(start) = response.rfind("<tool_call>") {
+            let body = &response[start + "<tool_call>".len()..];
+            match body.find("</tool_call>") {
+                Some(end) => {
+                    // Stray `>` or `/` from a garbled closing tag is tolerated by the parser
+                    let json = body[..end].trim().trim_end_matches(['>', '/']);
+                    if json.starts_with('{') && json_fixer::parse_json_lenient(json).is_none() {
+                        return Some(format!(
+                            "the JSON inside <tool_call> is invalid ({})",
+                            json_error(json)
+                        ));
+                    }
+                }
+                None if json_fixer::extract_balanced_json_braces(body.trim()).is_none() => {
+                    return Some(
+                        "the <tool_call> block ends before its JSON is complete and has no \
+closing </tool_call> tag"
+                            .to_string(),
+                    );
+                }
+                None => {}
+            }
+        }
+    }
+
+    if formats.is_enabled(ToolCallFormatName::Mistral)
+        && response.contains("[TOOL_CALLS]")
+        && tagged_parser::parse_tagged_tool_calls(response).is_empty()
+    {
+        return Some("the [TOOL_CALLS] payload is not a valid JSON array of calls".to_string());
+    }
+
+    if formats.is_enabled(ToolCallFormatName::PureJson) {
+        let trimmed = response.trim();
+        let json = trimmed
+            .strip_prefix("```json")
+            .map(|rest| rest.trim_end().trim_end_matches("```").trim())
+            .unwrap_or(trimmed);
+        if (json.starts_with('{') || json.starts_with('['))
+            && json.contains("\"name\"")
+            && json_fixer::parse_json_lenient(json).is_none()
+        {
+            return Some(format!("the JSON tool call is invalid ({})", json_error(json)));
+        }
+    }
+
+    None
+}
+
+/// serde_json's description of why `text` is not valid JSON
+fn json_error(text: &str) -> String {
+    match serde_json::from_str::<Value>(text) {
+        Ok(_) => "unexpected structure".to_string(),
+        Err(e) => e.to_string(),
     }
 }
 
+/// Answer text of a streamed response: everything before the first tool call
+/// in an enabled format (a response that opens with JSON is all call).
+/// Used for partial saves, so the markup of a call still streaming is left out.
+pub fn text_before_tool_call<'a>(response: &'a str, formats: &ToolCallFormatConfig) -> &'a str {
+    let trimmed = response.trim_start();
+    let json_formats = [ToolCallFormatName::PureJson, ToolCallFormatName::Anthropic];
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && json_formats.iter().any(|fmt| formats.is_enabled(*fmt))
+    {
+        return "";
+    }
+    let markers: &[(ToolCallFormatName, &[&str])] = &[
+        (ToolCallFormatName::Hermes, &["<tool_call>", "<function_call>"]),
+        (ToolCallFormatName::Mistral, &["[TOOL_CALLS]"]),
+        (ToolCallFormatName::CodeMode, &["```python"]),
+    ];
+    let end = markers
+        .iter()
+        .filter(|(fmt, _)| formats.is_enabled(*fmt))
+        .flat_map(|(_, tags)| tags.iter())
+        .filter_map(|tag| response.find(tag))
+        .min()
+        .unwrap_or(response.len());
+    &response[..end]
+}
+
+/// Strict check that the text is a finished JSON value; lenient repair would
+/// otherwise close a call that is still streaming.
+fn is_closed_json(text: &str) -> bool {
+    let trimmed = text.trim();
+    (trimmed.ends_with('}') || trimmed.ends_with(']'))
+        && serde_json::from_str::<Value>(trimmed).is_ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn text_before_tool_call_drops_call_markup() {
+        let formats = ToolCallFormatConfig {
+            enabled: vec![ToolCallFormatName::Hermes, ToolCallFormatName::CodeMode],
+            primary: ToolCallFormatName::Hermes,
+        };
+
+        assert_eq!(
+            text_before_tool_call("Let me check. <tool_call>{\"name\": \"we", &formats),
+            "Let me check. "
+        );
+        assert_eq!(text_before_tool_call("Run:\n```python\nprint(1)", &formats), "Run:\n");
+        assert_eq!(text_before_tool_call("It's sunny.", &formats), "It's sunny.");
+        // Markup of formats that are not enabled is plain text
+        assert_eq!(text_before_tool_call("[TOOL_CALLS] [", &formats), "[TOOL_CALLS] [");
+
+        let json_formats = ToolCallFormatConfig {
+            enabled: vec![ToolCallFormatName::PureJson],
+            primary: ToolCallFormatName::PureJson,
+        };
+        assert_eq!(text_before_tool_call(" {\"tool\": \"echo\"", &json_formats), "");
+    }
+
     #[test]
     fn parse_tool_calls_prefers_primary_enabled_format() {
         let formats = ToolCallFormatConfig {
@@ -264,4 +480,62 @@ mod tests {
         assert_eq!(calls[0].server, "builtin");
         assert_eq!(calls[0].tool, "echo");
     }
+
+    #[test]
+    fn format_is_complete_detects_closed_mistral_call() {
+        let partial = r#"[TOOL_CALLS] [{"name": "builtin___echo", "arguments": {"text": "h"#;
+        let complete = r#"[TOOL_CALLS] [{"name": "builtin___echo", "arguments": {"text": "hi"}}]"#;
+
+        assert!(!format_is_complete(partial, ToolCallFormatName::Mistral));
+        assert!(format_is_complete(complete, ToolCallFormatName::Mistral));
+        assert!(format_is_complete(
+            "[TOOL_CALLS] [{\"name\": \"builtin___echo\"}] [/TOOL_CALLS]",
+            ToolCallFormatName::Mistral
+        ));
+    }
+
+    #[test]
+    fn format_is_complete_detects_closed_pure_json_call() {
+        let partial = r#"{"name": "builtin___echo", "arguments": {"text": "hi"}"#;
+        let complete = r#"{"name": "builtin___echo", "arguments": {"text": "hi"}}"#;
+
+        assert!(!format_is_complete(partial, ToolCallFormatName::PureJson));
+        assert!(format_is_complete(complete, ToolCallFormatName::PureJson));
+        // Plain JSON that is not a tool call does not stop the stream
+        assert!(!format_is_complete(
+            r#"{"answer": 42}"#,
+            ToolCallFormatName::PureJson
+        ));
+    }
+
+    #[test]
+    fn any_format_complete_only_checks_enabled_formats() {
+        let formats = ToolCallFormatConfig {
+            enabled: vec![ToolCallFormatName::PureJson],
+            primary: ToolCallFormatName::PureJson,
+        };
+        let hermes = r#"<tool_call>{"name": "builtin___echo", "arguments": {}}</tool_call>"#;
+        let json = r#"{"tool": "builtin___echo", "args": {"text": "hi"}}"#;
+
+        assert!(!any_format_complete(hermes, &formats));
+        assert!(any_format_complete(json, &formats));
+    }
+
+    #[test]
+    fn detect_malformed_tool_call_only_checks_enabled_formats() {
+        let mistral = ToolCallFormatConfig {
+            enabled: vec![ToolCallFormatName::Mistral],
+            primary: ToolCallFormatName::Mistral,
+        };
+        let truncated = r#"[TOOL_CALLS] [{"name": "search", "arguments": {"query": "#;
+
+        assert!(detect_malformed_tool_call(truncated, &mistral).is_some());
+        assert!(detect_malformed_tool_call(
+            r#"[TOOL_CALLS] [{"name": "search", "arguments": {"query": "AI"}}]"#,
+            &mistral
+        )
+        .is_none());
+        // A cut-off Hermes call is ignored when Hermes is not enabled
+        assert!(detect_malformed_tool_call(r#"<tool_call>{"name": "search""#, &mistral).is_none());
+    }
 }
@@ -9,15 +9,13 @@ use serde_json::Value;
 
 use crate::protocol::ParsedToolCall;
 use super::common::parse_combined_tool_name;
+use crate::tool_registry::is_builtin_tool_name;
 
-/// Known tool names to filter out false positives.
-/// This prevents Python builtins like print() from being treated as tool calls.
-const KNOWN_BUILTIN_TOOLS: [&str; 4] = [
-    "sql_select",
-    "schema_search",
-    "tool_search",
-    "python_execution",
-];
+/// Whether a call names a known built-in tool (or final_answer), to filter out
+/// false positives like Python's print()
+fn is_known_builtin_tool(name: &str) -> bool {
+    name == "final_answer" || is_builtin_tool_name(name)
+}
 
 /// Parse Pythonic function calls inside markdown code blocks.
 /// Handles formats like:
@@ -51,7 +49,7 @@ pub fn parse_pythonic_code_block_tool_calls(content: &str) -> Vec<ParsedToolCall
                     }
 
                     // Only parse if it looks like a known tool or has server prefix
-                    let is_known = KNOWN_BUILTIN_TOOLS.contains(&name) || name.contains("___");
+                    let is_known = is_known_builtin_tool(name) || name.contains("___");
 
                     if !is_known {
                         // Skip unknown function names to avoid false positives
@@ -103,7 +101,7 @@ pub fn parse_pythonic_tool_calls(content: &str) -> Vec<ParsedToolCall> {
         }
 
         // Only parse if it looks like a known tool or has server prefix (e.g., "server___tool")
-        let is_known = KNOWN_BUILTIN_TOOLS.contains(&name) || name.contains("___");
+        let is_known = is_known_builtin_tool(name) || name.contains("___");
         if !is_known {
             continue;
         }
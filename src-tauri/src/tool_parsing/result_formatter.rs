@@ -4,6 +4,7 @@
 //! expected format.
 
 use crate::protocol::{ParsedToolCall, ToolFormat};
+use crate::settings::ResultFormat;
 use crate::system_prompt;
 
 /// Success guidance for sql_select - tells model that results have been shown to user
@@ -17,6 +18,14 @@ use crate::system_prompt;
 /// `build_sql_error_recovery_prompt()` which injects the schema directly into
 /// the error response. This is the "Cursor for SQL" approach: small models
 /// don't look back in context, so we re-inject what they need.
+///
+/// With `ResultFormat::Json`, python_execution results are already a JSON object,
+/// so formats that wrap results in JSON embed them as-is instead of as a string.
+///
+/// When `include_call_id` is set and the call has an id, the block is preceded by
+/// a `tool_call_id_marker` so saved transcripts can match results to calls and
+/// the model can name the result in `extract`.
+#[allow(clippy::too_many_arguments)]
 pub fn format_tool_result(
     call: &ParsedToolCall,
     result: &str,
@@ -24,7 +33,10 @@ pub fn format_tool_result(
     tool_format: ToolFormat,
     original_user_prompt: Option<&str>,
     schema_context: Option<&str>,
+    result_format: ResultFormat,
+    include_call_id: bool,
 ) -> String {
+    let embed_json = result_format == ResultFormat::Json && call.tool == "python_execution";
     let guidance = if is_error {
         // For SQL errors with schema context, use enhanced recovery prompt
         if call.tool == "sql_select" && schema_context.is_some() {
@@ -38,9 +50,10 @@ pub fn format_tool_result(
         String::new()
     };
 
-    match tool_format {
-        ToolFormat::OpenAI => {
+    let formatted = match tool_format {
+        ToolFormat::OpenAI | ToolFormat::Anthropic => {
             // OpenAI format - this would typically be a separate message with role "tool"
+            // (a tool_result block for Anthropic)
             // For text-based injection, we use a simple format
             if is_error {
                 format!(
@@ -67,7 +80,12 @@ pub fn format_tool_result(
         }
         ToolFormat::Gemini => {
             // Gemini uses function_response format
-            if is_error {
+            if embed_json {
+                format!(
+                    "{{\"function_response\": {{\"name\": \"{}___{}\", \"response\": {}}}}}{}",
+                    call.server, call.tool, result, guidance
+                )
+            } else if is_error {
                 format!(
                     "{{\"function_response\": {{\"name\": \"{}___{}\", \"response\": {{\"error\": \"{}\"}}}}}}{}",
                     call.server,
@@ -98,7 +116,7 @@ pub fn format_tool_result(
         }
         ToolFormat::Harmony => {
             // Harmony format uses <|start|>tool to={tool_name}<|message|>{result}<|end|>
-            if is_error {
+            if is_error && !embed_json {
                 format!(
                     "<|start|>tool to={}<|message|>{{\"error\": \"{}\"}}<|end|>{}",
                     call.tool,
@@ -128,9 +146,20 @@ pub fn format_tool_result(
                 )
             }
         }
+    };
+
+    match call.id.as_deref().filter(|_| include_call_id) {
+        Some(id) => format!("{}\n{}", tool_call_id_marker(id), formatted),
+        None => formatted,
     }
 }
 
+/// Correlation marker linking a text-format tool call to its result. It is an
+/// HTML comment, which the tool call parsers ignore.
+pub fn tool_call_id_marker(id: &str) -> String {
+    format!("<!-- tool_call_id: {} -->", id)
+}
+
 /// Build SQL error recovery guidance by extracting SQL and error from the result JSON.
 /// 
 /// This parses the sql_select output format and uses the enhanced recovery prompt
@@ -183,7 +212,7 @@ mod tests {
             id: None,
         };
 
-        let result = format_tool_result(&call, "Hello, World!", false, ToolFormat::Hermes, None, None);
+        let result = format_tool_result(&call, "Hello, World!", false, ToolFormat::Hermes, None, None, ResultFormat::Text, false);
         assert!(result.contains("<tool_response>"));
         assert!(result.contains("Hello, World!"));
         // Success case should NOT include error guidance
@@ -202,7 +231,7 @@ mod tests {
 
         let sql_result = r#"{"success": true, "columns": ["id", "name"], "rows": [[1, "Alice"]], "row_count": 1}"#;
 
-        let result = format_tool_result(&call, sql_result, false, ToolFormat::Hermes, None, None);
+        let result = format_tool_result(&call, sql_result, false, ToolFormat::Hermes, None, None, ResultFormat::Text, false);
         assert!(
             result.contains("already been displayed to the user"),
             "Should tell model results were shown to user, got: {}",
@@ -210,6 +239,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_format_tool_result_with_call_id() {
+        let mut call = ParsedToolCall {
+            server: "builtin".to_string(),
+            tool: "echo".to_string(),
+            arguments: json!({}),
+            raw: "".to_string(),
+            id: Some("call_ab12cd34".to_string()),
+        };
+        let result = format_tool_result(&call, "hi", false, ToolFormat::Hermes, None, None, ResultFormat::Text, true);
+        assert!(result.starts_with("<!-- tool_call_id: call_ab12cd34 -->\n<tool_response>"));
+
+        let without = format_tool_result(&call, "hi", false, ToolFormat::Hermes, None, None, ResultFormat::Text, false);
+        assert!(!without.contains("tool_call_id"));
+
+        call.id = None;
+        let no_id = format_tool_result(&call, "hi", false, ToolFormat::Hermes, None, None, ResultFormat::Text, true);
+        assert!(no_id.starts_with("<tool_response>"));
+    }
+
     #[test]
     fn test_format_harmony_tool_result_success() {
         let call = ParsedToolCall {
@@ -226,6 +275,8 @@ mod tests {
             ToolFormat::Harmony,
             None,
             None,
+            ResultFormat::Text,
+            false,
         );
         assert!(result.contains("<|start|>tool to=sql_select"), "Should use harmony format");
         assert!(result.contains("<|message|>"), "Should contain message token");
@@ -249,8 +300,32 @@ mod tests {
             ToolFormat::Harmony,
             None,
             None,
+            ResultFormat::Text,
+            false,
         );
         assert!(result.contains("<|start|>tool to=sql_select"), "Should use harmony format");
         assert!(result.contains("error"), "Should contain error field");
     }
+
+    #[test]
+    fn test_format_python_json_result_embedded_as_object() {
+        let call = ParsedToolCall {
+            server: "builtin".to_string(),
+            tool: "python_execution".to_string(),
+            arguments: json!({"code": ["print(1)"]}),
+            raw: "".to_string(),
+            id: None,
+        };
+        let output = r#"{"success":false,"stdout":"","stderr":"boom","return_value":null,"tool_calls_made":0}"#;
+
+        let gemini = format_tool_result(&call, output, true, ToolFormat::Gemini, None, None, ResultFormat::Json, false);
+        assert!(gemini.contains(&format!("\"response\": {}", output)));
+
+        let harmony = format_tool_result(&call, output, true, ToolFormat::Harmony, None, None, ResultFormat::Json, false);
+        assert!(harmony.contains(&format!("<|message|>{}<|end|>", output)));
+
+        // Text format keeps wrapping the result as an escaped string
+        let text = format_tool_result(&call, output, true, ToolFormat::Gemini, None, None, ResultFormat::Text, false);
+        assert!(text.contains("\"response\": {\"error\": "));
+    }
 }
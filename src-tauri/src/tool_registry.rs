@@ -1,14 +1,16 @@
 //! Tool Registry - Manages built-in tools and domain tools with deferred loading
 //!
 //! This module provides a centralized registry for all tools available in Plugable Chat:
-//! - Built-in tools: `python_execution`, `tool_search`, `schema_search`, `sql_select`
+//! - Built-in tools: `python_execution`, `tool_search`, `schema_search`, `sql_select`,
+//!   plus any added with `ToolRegistry::register_builtin`
 //! - Domain tools from MCP servers (can be deferred for semantic discovery)
 //!
 //! The registry also stores precomputed embeddings for semantic tool search.
 
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
 use crate::actors::mcp_host_actor::McpTool;
@@ -46,6 +48,7 @@ pub fn python_execution_tool() -> ToolSchema {
         tool_type: Some("python_execution_20251206".to_string()),
         allowed_callers: None, // Anyone can call python_execution
         defer_loading: false,
+        streamable: false,
         embedding: None,
     }
 }
@@ -68,6 +71,11 @@ pub fn tool_search_tool() -> ToolSchema {
                     "items": { "type": "string" },
                     "description": "Semantic search queries describing what you're looking for. E.g., ['get user data', 'weather forecast']"
                 },
+                "query_weights": {
+                    "type": "array",
+                    "items": { "type": "number" },
+                    "description": "Optional relative weight for each query, in the same order (default: all equal)"
+                },
                 "top_k": {
                     "type": "integer",
                     "description": "Maximum number of tools to return (default: 3)"
@@ -79,6 +87,7 @@ pub fn tool_search_tool() -> ToolSchema {
         tool_type: Some("tool_search_20251201".to_string()),
         allowed_callers: None, // Anyone can call tool_search
         defer_loading: false,
+        streamable: false,
         embedding: None,
     }
 }
@@ -106,11 +115,16 @@ pub fn schema_search_tool() -> ToolSchema {
                 },
                 "max_columns_per_table": {
                     "type": "integer",
-                    "description": "Maximum columns per table to return (default: 5)"
+                    "description": "Maximum columns per table to return, most relevant first (default: 10)"
                 },
                 "min_relevance": {
                     "type": "number",
                     "description": "Minimum relevance score 0.0-1.0 (default: 0.3)"
+                },
+                "source_ids": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Only search tables from these database source IDs (default: all enabled sources)"
                 }
             },
             "required": ["query"]
@@ -119,6 +133,7 @@ pub fn schema_search_tool() -> ToolSchema {
         tool_type: Some("schema_search_20251210".to_string()),
         allowed_callers: None,
         defer_loading: false,
+        streamable: false,
         embedding: None,
     }
 }
@@ -155,6 +170,10 @@ pub fn sql_select_tool() -> ToolSchema {
                 "max_rows": {
                     "type": "integer",
                     "description": "Maximum rows to return (default: 100)"
+                },
+                "offset": {
+                    "type": "integer",
+                    "description": "Rows to skip, for paging through large results (returns has_more)"
                 }
             },
             "required": ["sql"]
@@ -163,10 +182,78 @@ pub fn sql_select_tool() -> ToolSchema {
         tool_type: Some("sql_select_20251210".to_string()),
         allowed_callers: None,
         defer_loading: false,
+        streamable: false,
         embedding: None,
     }
 }
 
+/// Core built-in tool schemas
+fn core_builtin_tools() -> Vec<ToolSchema> {
+    vec![
+        python_execution_tool(),
+        tool_search_tool(),
+        schema_search_tool(),
+        sql_select_tool(),
+    ]
+}
+
+/// Sorted names of the core built-in tools, for callers without a registry
+/// (CLI parsing, tool call parsers). A registry also knows the built-ins
+/// registered on it: see `ToolRegistry::builtin_tool_names`.
+pub fn builtin_tool_names() -> Vec<String> {
+    let names: BTreeSet<String> = core_builtin_tools().into_iter().map(|t| t.name).collect();
+    names.into_iter().collect()
+}
+
+/// Whether `name` is a core built-in tool
+pub fn is_builtin_tool_name(name: &str) -> bool {
+    core_builtin_tools().iter().any(|t| t.name == name)
+}
+
+// ========== Description Overrides ==========
+
+/// The user's replacement description for a tool, from overrides keyed by
+/// "{server_id}::{tool_name}" ("builtin" for built-ins). Blank overrides are ignored.
+pub fn description_override<'a>(
+    overrides: &'a HashMap<String, String>,
+    server_id: &str,
+    tool_name: &str,
+) -> Option<&'a str> {
+    overrides
+        .get(&format!("{}::{}", server_id, tool_name))
+        .map(|d| d.trim())
+        .filter(|d| !d.is_empty())
+}
+
+/// Replace a tool schema's description with its override, if any
+pub fn apply_description_override(
+    schema: &mut ToolSchema,
+    server_id: &str,
+    overrides: &HashMap<String, String>,
+) {
+    if let Some(description) = description_override(overrides, server_id, &schema.name) {
+        schema.description = Some(description.to_string());
+    }
+}
+
+/// Replace MCP tool descriptions with their overrides, so prompts, schemas and
+/// tool_search embeddings all see the same text
+pub fn apply_mcp_description_overrides(
+    tool_descriptions: &mut [(String, Vec<McpTool>)],
+    overrides: &HashMap<String, String>,
+) {
+    if overrides.is_empty() {
+        return;
+    }
+    for (server_id, tools) in tool_descriptions.iter_mut() {
+        for tool in tools.iter_mut() {
+            if let Some(description) = description_override(overrides, server_id, &tool.name) {
+                tool.description = Some(description.to_string());
+            }
+        }
+    }
+}
+
 // ========== Tool Search Result ==========
 
 /// Result from a tool search operation
@@ -191,12 +278,15 @@ pub struct ToolRegistry {
     domain_tools: HashMap<String, ToolSchema>,
     /// Precomputed embeddings for tool descriptions (for semantic search)
     tool_embeddings: HashMap<String, Vec<f32>>,
-    /// Set of tools that have been materialized (made visible after tool_search)
-    materialized_tools: std::collections::HashSet<String>,
+    /// Tools that have been materialized (made visible after tool_search), with when
+    materialized_tools: HashMap<String, Instant>,
     /// Mapping of server_id to python module name
     server_python_names: HashMap<String, String>,
     /// Reverse mapping of python module name to server_id
     python_name_to_server: HashMap<String, String>,
+    /// Names of every known built-in, enabled or not: the core ones plus those
+    /// added with `register_builtin`
+    builtin_names: BTreeSet<String>,
 }
 
 impl ToolRegistry {
@@ -209,9 +299,10 @@ impl ToolRegistry {
             internal_tools,
             domain_tools: HashMap::new(),
             tool_embeddings: HashMap::new(),
-            materialized_tools: std::collections::HashSet::new(),
+            materialized_tools: HashMap::new(),
             server_python_names: HashMap::new(),
             python_name_to_server: HashMap::new(),
+            builtin_names: builtin_tool_names().into_iter().collect(),
         }
     }
 
@@ -239,6 +330,25 @@ impl ToolRegistry {
         }
     }
 
+    /// Register a built-in tool and make it available; this registry recognizes
+    /// it as a built-in (`builtin_tool_names`, `is_builtin_tool`) from then on
+    pub fn register_builtin(&mut self, schema: ToolSchema) {
+        self.builtin_names.insert(schema.name.clone());
+        self.internal_tools.retain(|t| t.name != schema.name);
+        println!("[ToolRegistry] Registered built-in: {}", schema.name);
+        self.internal_tools.push(schema);
+    }
+
+    /// Names of all known built-in tools (enabled or not)
+    pub fn builtin_tool_names(&self) -> Vec<String> {
+        self.builtin_names.iter().cloned().collect()
+    }
+
+    /// Whether `name` is a built-in tool known to this registry
+    pub fn is_builtin_tool(&self, name: &str) -> bool {
+        self.builtin_names.contains(name)
+    }
+
     /// Register domain tools from an MCP server with its Python module name
     pub fn register_mcp_tools(
         &mut self,
@@ -279,6 +389,7 @@ impl ToolRegistry {
                 tool_type: None,
                 allowed_callers,
                 defer_loading: defer,
+                streamable: tool.streamable,
                 embedding: None,
             };
 
@@ -353,7 +464,7 @@ impl ToolRegistry {
 
         for (key, schema) in &self.domain_tools {
             // Include non-deferred tools immediately; deferred tools only after materialization
-            let is_materialized = self.materialized_tools.contains(key);
+            let is_materialized = self.materialized_tools.contains_key(key);
             if schema.defer_loading && !is_materialized {
                 continue;
             }
@@ -464,7 +575,7 @@ impl ToolRegistry {
 
         for (key, schema) in &self.domain_tools {
             // Include if not deferred OR if materialized
-            if !schema.defer_loading || self.materialized_tools.contains(key) {
+            if !schema.defer_loading || self.materialized_tools.contains_key(key) {
                 tools.push(schema.clone());
             }
         }
@@ -482,7 +593,7 @@ impl ToolRegistry {
             .collect();
 
         for (key, schema) in &self.domain_tools {
-            if !schema.defer_loading || self.materialized_tools.contains(key) {
+            if !schema.defer_loading || self.materialized_tools.contains_key(key) {
                 // key format: server_id___tool_name
                 let server_id = key.splitn(2, "___").next().unwrap_or("unknown").to_string();
                 tools.push((server_id, schema.clone()));
@@ -496,7 +607,7 @@ impl ToolRegistry {
     pub fn is_tool_visible(&self, server_id: &str, tool_name: &str) -> bool {
         let key = format!("{}___{}", server_id, tool_name);
         match self.domain_tools.get(&key) {
-            Some(schema) => !schema.defer_loading || self.materialized_tools.contains(&key),
+            Some(schema) => !schema.defer_loading || self.materialized_tools.contains_key(&key),
             None => false,
         }
     }
@@ -530,7 +641,7 @@ impl ToolRegistry {
     /// Materialize a deferred tool (make it visible after tool_search discovers it)
     pub fn materialize_tool(&mut self, tool_key: &str) -> bool {
         if self.domain_tools.contains_key(tool_key) {
-            self.materialized_tools.insert(tool_key.to_string());
+            self.materialized_tools.insert(tool_key.to_string(), Instant::now());
             println!("[ToolRegistry] Materialized tool: {}", tool_key);
             true
         } else {
@@ -555,6 +666,36 @@ impl ToolRegistry {
         println!("[ToolRegistry] Cleared all domain tools");
     }
 
+    /// Keys of the tools materialized so far this conversation
+    pub fn materialized_tool_keys(&self) -> Vec<String> {
+        self.materialized_tools.keys().cloned().collect()
+    }
+
+    /// Materialized tools with when each was materialized, for `restore_materialized`
+    pub fn materialized_entries(&self) -> Vec<(String, Instant)> {
+        self.materialized_tools
+            .iter()
+            .map(|(key, at)| (key.clone(), *at))
+            .collect()
+    }
+
+    /// Re-materialize saved entries that are still registered, keeping their original
+    /// materialization time so a TTL keeps counting from it
+    pub fn restore_materialized(&mut self, entries: Vec<(String, Instant)>) {
+        for (key, at) in entries {
+            if self.domain_tools.contains_key(&key) {
+                self.materialized_tools.insert(key, at);
+            }
+        }
+    }
+
+    /// Drop materializations older than `max_age`. Returns how many were dropped.
+    pub fn expire_materialized(&mut self, max_age: Duration) -> usize {
+        let before = self.materialized_tools.len();
+        self.materialized_tools.retain(|_, at| at.elapsed() < max_age);
+        before - self.materialized_tools.len()
+    }
+
     /// Clear all materialized tools (for a new conversation)
     pub fn clear_materialized(&mut self) {
         self.materialized_tools.clear();
@@ -562,22 +703,46 @@ impl ToolRegistry {
 
     /// Perform semantic search over all domain tools
     ///
-    /// Returns the top-k tools that match the query embeddings, sorted by score.
     /// Searches ALL domain tools (both deferred and non-deferred) so models can
-    /// discover relevant tools even if they're already visible.
+    /// discover relevant tools even if they're already visible. Each query scores
+    /// tools on its own, scaled by its weight (weights are normalized so the
+    /// largest is 1.0; missing weights count as 1.0), and a tool's score is its
+    /// best score over the queries, so a tool relevant to only one query isn't
+    /// averaged away. The top-k first takes each query's best tool, then fills
+    /// the rest by score, and is returned sorted by score.
     pub fn search_tools(
         &self,
         query_embeddings: &[Vec<f32>],
+        query_weights: &[f32],
         top_k: usize,
     ) -> Vec<ToolSearchResult> {
-        let mut results: Vec<ToolSearchResult> = Vec::new();
+        let weights: Vec<f32> = (0..query_embeddings.len())
+            .map(|i| query_weights.get(i).copied().unwrap_or(1.0))
+            .collect();
+        let max_weight = weights.iter().copied().fold(0.0, f32::max);
+        let weights: Vec<f32> = weights
+            .iter()
+            .map(|w| {
+                if max_weight > 0.0 {
+                    w / max_weight
+                } else {
+                    1.0
+                }
+            })
+            .collect();
 
+        // Each candidate with its weighted score per query
+        let mut candidates: Vec<(ToolSearchResult, Vec<f32>)> = Vec::new();
         for (key, schema) in self.get_all_domain_tools() {
             if let Some(tool_embedding) = self.tool_embeddings.get(key) {
-                // Calculate max cosine similarity across all query embeddings
-                let max_score = query_embeddings
+                let query_scores: Vec<f32> = query_embeddings
                     .iter()
-                    .map(|q| cosine_similarity(q, tool_embedding))
+                    .zip(&weights)
+                    .map(|(q, w)| w * cosine_similarity(q, tool_embedding))
+                    .collect();
+                let max_score = query_scores
+                    .iter()
+                    .copied()
                     .fold(f32::NEG_INFINITY, f32::max);
 
                 // Parse server_id from key
@@ -588,27 +753,51 @@ impl ToolRegistry {
                     "unknown"
                 };
 
-                results.push(ToolSearchResult {
-                    name: schema.name.clone(),
-                    description: schema.description.clone(),
-                    score: max_score,
-                    server_id: server_id.to_string(),
-                    parameters: schema.parameters.clone(),
-                });
+                candidates.push((
+                    ToolSearchResult {
+                        name: schema.name.clone(),
+                        description: schema.description.clone(),
+                        score: max_score,
+                        server_id: server_id.to_string(),
+                        parameters: schema.parameters.clone(),
+                    },
+                    query_scores,
+                ));
             }
         }
 
         // Sort by score descending
-        results.sort_by(|a, b| {
-            b.score
-                .partial_cmp(&a.score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+        let by_score = |a: f32, b: f32| b.partial_cmp(&a).unwrap_or(std::cmp::Ordering::Equal);
+        candidates.sort_by(|a, b| by_score(a.0.score, b.0.score));
 
-        // Take top-k
-        results.truncate(top_k);
+        // Each query's best tool first, then the rest by score
+        let mut picked: Vec<usize> = Vec::new();
+        for (query, weight) in weights.iter().enumerate() {
+            if picked.len() >= top_k || *weight <= 0.0 {
+                continue;
+            }
+            let best = (0..candidates.len())
+                .filter(|i| !picked.contains(i))
+                .min_by(|&a, &b| by_score(candidates[a].1[query], candidates[b].1[query]));
+            if let Some(best) = best {
+                picked.push(best);
+            }
+        }
+        for i in 0..candidates.len() {
+            if picked.len() >= top_k {
+                break;
+            }
+            if !picked.contains(&i) {
+                picked.push(i);
+            }
+        }
 
-        results
+        candidates
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| picked.contains(i))
+            .map(|(_, (result, _))| result)
+            .collect()
     }
 
     /// Check if code_mode should be enabled based on available tools
@@ -631,6 +820,68 @@ impl ToolRegistry {
             tools_with_embeddings: self.tool_embeddings.len(),
         }
     }
+
+    /// Snapshot of every registered tool and its status, for debugging
+    pub fn snapshot(&self) -> RegistrySnapshot {
+        let describe = |key: &str, schema: &ToolSchema, builtin: bool| {
+            let materialized = self.materialized_tools.contains_key(key);
+            let visible = builtin || !schema.defer_loading || materialized;
+            let server_id = key.split("___").next().unwrap_or_default();
+            RegisteredToolInfo {
+                key: key.to_string(),
+                name: schema.name.clone(),
+                description: schema.description.clone(),
+                deferred: schema.defer_loading,
+                materialized,
+                visible,
+                python_callable: !builtin
+                    && visible
+                    && schema.can_be_called_by(Some(PYTHON_CALLER_TYPE))
+                    && self.server_python_names.contains_key(server_id),
+                allowed_callers: schema.allowed_callers.clone(),
+                has_embedding: self.tool_embeddings.contains_key(key),
+            }
+        };
+
+        let internal_tools = self
+            .internal_tools
+            .iter()
+            .map(|schema| describe(&schema.name, schema, true))
+            .collect();
+
+        let mut servers: Vec<ServerToolsSnapshot> = Vec::new();
+        let mut keys: Vec<&String> = self.domain_tools.keys().collect();
+        keys.sort();
+        for key in keys {
+            let server_id = key.split("___").next().unwrap_or("unknown");
+            let info = describe(key, &self.domain_tools[key], false);
+            match servers.iter_mut().find(|s| s.server_id == server_id) {
+                Some(server) => server.tools.push(info),
+                None => servers.push(ServerToolsSnapshot {
+                    server_id: server_id.to_string(),
+                    python_name: self.server_python_names.get(server_id).cloned(),
+                    tools: vec![info],
+                }),
+            }
+        }
+
+        let mut materialized = self.materialized_tool_keys();
+        materialized.sort();
+        let mut deferred: Vec<String> = self
+            .get_deferred_tools()
+            .into_iter()
+            .map(|(key, _)| key.clone())
+            .collect();
+        deferred.sort();
+
+        RegistrySnapshot {
+            internal_tools,
+            servers,
+            materialized,
+            deferred,
+            stats: self.stats(),
+        }
+    }
 }
 
 impl Default for ToolRegistry {
@@ -640,7 +891,7 @@ impl Default for ToolRegistry {
 }
 
 /// Statistics about the tool registry
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct RegistryStats {
     pub internal_tools: usize,
     pub domain_tools: usize,
@@ -649,6 +900,45 @@ pub struct RegistryStats {
     pub tools_with_embeddings: usize,
 }
 
+/// One registered tool, as reported by `ToolRegistry::snapshot`
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RegisteredToolInfo {
+    /// Registry key (`server_id___tool_name`, or the name for built-ins)
+    pub key: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub deferred: bool,
+    pub materialized: bool,
+    /// Whether the model currently sees the tool
+    pub visible: bool,
+    /// Whether python_execution code can call the tool now: it is visible, its
+    /// allowed_callers include python and its server has a module name
+    pub python_callable: bool,
+    pub allowed_callers: Option<Vec<String>>,
+    pub has_embedding: bool,
+}
+
+/// Domain tools registered by one MCP server
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ServerToolsSnapshot {
+    pub server_id: String,
+    /// Python module the server's tools are imported from
+    pub python_name: Option<String>,
+    pub tools: Vec<RegisteredToolInfo>,
+}
+
+/// Full registry contents for debugging
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RegistrySnapshot {
+    pub internal_tools: Vec<RegisteredToolInfo>,
+    pub servers: Vec<ServerToolsSnapshot>,
+    /// Keys of materialized domain tools
+    pub materialized: Vec<String>,
+    /// Keys of deferred domain tools
+    pub deferred: Vec<String>,
+    pub stats: RegistryStats,
+}
+
 // ========== Helper Functions ==========
 
 /// Calculate cosine similarity between two vectors
@@ -696,6 +986,35 @@ mod tests {
             .any(|t| t.name == "tool_search"));
     }
 
+    #[test]
+    fn test_registered_builtin_is_recognized() {
+        let mut registry = ToolRegistry::new();
+        for name in ["python_execution", "tool_search", "schema_search", "sql_select"] {
+            assert!(crate::cli::is_builtin_tool(name));
+            assert!(registry.is_builtin_tool(name));
+        }
+        assert!(!registry.is_builtin_tool("word_count"));
+
+        registry.register_builtin(ToolSchema {
+            name: "word_count".to_string(),
+            description: Some("Count words".to_string()),
+            parameters: json!({"type": "object", "properties": {"text": {"type": "string"}}}),
+            input_examples: Vec::new(),
+            tool_type: None,
+            allowed_callers: None,
+            defer_loading: false,
+            streamable: false,
+            embedding: None,
+        });
+
+        assert!(registry.is_builtin_tool("word_count"));
+        assert!(registry.builtin_tool_names().contains(&"word_count".to_string()));
+        assert!(registry.get_internal_tools().iter().any(|t| t.name == "word_count"));
+        // Other registries (and the process) are unaffected
+        assert!(!ToolRegistry::new().is_builtin_tool("word_count"));
+        assert!(!crate::cli::is_builtin_tool("word_count"));
+    }
+
     #[test]
     fn test_tool_registration() {
         let mut registry = ToolRegistry::new();
@@ -708,6 +1027,7 @@ mod tests {
             ),
             input_examples: None,
             allowed_callers: None,
+            streamable: false,
         }];
 
         registry.register_mcp_tools("weather_server", "weather", &mcp_tools, false);
@@ -733,6 +1053,7 @@ mod tests {
             input_schema: None,
             input_examples: None,
             allowed_callers: None,
+            streamable: false,
         }];
 
         registry.register_mcp_tools("internal", "internal_tools", &mcp_tools, true);
@@ -749,6 +1070,156 @@ mod tests {
         assert!(visible.iter().any(|t| t.name == "internal_api"));
     }
 
+    #[test]
+    fn test_materialized_tools_survive_reregistration() {
+        let mut registry = ToolRegistry::new();
+        let tool = |name: &str| McpTool {
+            name: name.to_string(),
+            description: None,
+            input_schema: None,
+            input_examples: None,
+            allowed_callers: None,
+            streamable: false,
+        };
+
+        registry.register_mcp_tools("internal", "internal_tools", &[tool("a"), tool("b")], true);
+        registry.materialize_tool("internal___a");
+        registry.materialize_tool("internal___b");
+
+        // Refresh: tool "b" was removed from the server
+        let materialized = registry.materialized_tool_keys();
+        registry.clear_domain_tools();
+        registry.register_mcp_tools("internal", "internal_tools", &[tool("a")], true);
+        registry.materialize_tools(&materialized);
+
+        assert_eq!(registry.materialized_tool_keys(), vec!["internal___a".to_string()]);
+    }
+
+    #[test]
+    fn test_snapshot_reports_tool_status() {
+        let mut registry = ToolRegistry::new();
+        let tool = |name: &str, allowed_callers: Option<Vec<String>>| McpTool {
+            name: name.to_string(),
+            description: None,
+            input_schema: None,
+            input_examples: None,
+            allowed_callers,
+            streamable: false,
+        };
+
+        registry.register_mcp_tools(
+            "crm",
+            "crm_tools",
+            &[tool("find", None), tool("merge", None)],
+            true,
+        );
+        registry.register_mcp_tools(
+            "mail",
+            "mail",
+            &[tool("send", Some(vec!["direct".to_string()]))],
+            false,
+        );
+        registry.materialize_tool("crm___find");
+
+        let snapshot = registry.snapshot();
+        assert_eq!(snapshot.internal_tools.len(), 2);
+        assert!(snapshot
+            .internal_tools
+            .iter()
+            .all(|t| t.visible && !t.python_callable));
+        assert_eq!(snapshot.materialized, vec!["crm___find".to_string()]);
+        assert_eq!(
+            snapshot.deferred,
+            vec!["crm___find".to_string(), "crm___merge".to_string()]
+        );
+        assert_eq!(snapshot.stats.domain_tools, 3);
+
+        let crm = &snapshot.servers[0];
+        assert_eq!(crm.server_id, "crm");
+        assert_eq!(crm.python_name.as_deref(), Some("crm_tools"));
+        let (find, merge) = (&crm.tools[0], &crm.tools[1]);
+        assert!(find.visible && find.python_callable);
+        // Deferred and not yet found by tool_search
+        assert!(!merge.visible && !merge.python_callable);
+
+        // Visible, but its allowed_callers leave out python
+        let send = &snapshot.servers[1].tools[0];
+        assert!(send.visible && !send.python_callable);
+        assert_eq!(send.allowed_callers, Some(vec!["direct".to_string()]));
+    }
+
+    #[test]
+    fn test_restore_and_expire_materialized() {
+        let mut registry = ToolRegistry::new();
+        let tool = |name: &str| McpTool {
+            name: name.to_string(),
+            description: None,
+            input_schema: None,
+            input_examples: None,
+            allowed_callers: None,
+            streamable: false,
+        };
+
+        registry.register_mcp_tools("internal", "internal_tools", &[tool("a"), tool("b")], true);
+        registry.materialize_tool("internal___a");
+        let entries = registry.materialized_entries();
+
+        registry.clear_domain_tools();
+        registry.register_mcp_tools("internal", "internal_tools", &[tool("a")], true);
+        registry.restore_materialized(entries.clone());
+        assert_eq!(registry.materialized_entries(), entries);
+
+        assert_eq!(registry.expire_materialized(Duration::from_secs(3600)), 0);
+        assert_eq!(registry.expire_materialized(Duration::ZERO), 1);
+        assert!(registry.materialized_tool_keys().is_empty());
+    }
+
+    #[test]
+    fn test_search_tools_keeps_each_querys_best_tool() {
+        let mut registry = ToolRegistry::new();
+        let tool = |name: &str| McpTool {
+            name: name.to_string(),
+            description: None,
+            input_schema: None,
+            input_examples: None,
+            allowed_callers: None,
+            streamable: false,
+        };
+        let tools: Vec<McpTool> = ["forecast", "radar", "alerts", "send_email"]
+            .iter()
+            .map(|n| tool(n))
+            .collect();
+        registry.register_mcp_tools("srv", "srv", &tools, true);
+
+        // Three weather tools close to the first query; the email tool is a
+        // weaker but clear match for the second
+        registry.set_tool_embedding("srv___forecast", vec![1.0, 0.0, 0.0]);
+        registry.set_tool_embedding("srv___radar", vec![0.95, 0.05, 0.0]);
+        registry.set_tool_embedding("srv___alerts", vec![0.9, 0.1, 0.0]);
+        registry.set_tool_embedding("srv___send_email", vec![0.0, 0.6, 0.8]);
+        let queries = vec![vec![1.0, 0.0, 0.0], vec![0.0, 0.0, 1.0]];
+
+        let names = |results: Vec<ToolSearchResult>| -> Vec<String> {
+            results.into_iter().map(|r| r.name).collect()
+        };
+        assert_eq!(
+            names(registry.search_tools(&queries, &[], 3)),
+            vec!["forecast", "radar", "send_email"]
+        );
+
+        // Weights scale each query's scores
+        let weighted = registry.search_tools(&queries, &[1.0, 0.5], 4);
+        let email = weighted.iter().find(|r| r.name == "send_email").unwrap();
+        assert!((email.score - 0.4).abs() < 0.001);
+        assert_eq!(weighted.last().unwrap().name, "send_email");
+
+        // A zero weight drops the query's guaranteed slot
+        assert_eq!(
+            names(registry.search_tools(&queries, &[1.0, 0.0], 3)),
+            vec!["forecast", "radar", "alerts"]
+        );
+    }
+
     #[test]
     fn test_cosine_similarity() {
         let a = vec![1.0, 0.0, 0.0];
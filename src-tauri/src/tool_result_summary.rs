@@ -0,0 +1,151 @@
+//! Summaries of oversized MCP tool results.
+//!
+//! Some MCP tools return far more text than the model needs (large JSON dumps,
+//! whole documents). When enabled, results over the threshold are summarized by
+//! a separate, tool-free model call and the summary goes into the history in
+//! place of the raw text. The UI still receives the raw result through the
+//! `tool-result` event.
+
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+
+use crate::history_compaction::request_summary;
+use crate::message_builders::truncate_tool_result;
+use crate::protocol::{ChatMessage, FoundryMsg, ParsedToolCall};
+use crate::settings::ChatFormatName;
+
+/// Prefix of a summarized result; results that already carry it are never re-summarized
+pub const SUMMARY_PREFIX: &str = "[Summary of a large tool result]";
+
+/// Longest slice of the raw result sent to the summarization call
+const MAX_SUMMARIZER_INPUT_CHARS: usize = 32_000;
+
+const SUMMARY_INSTRUCTIONS: &str = "Summarize the tool result below for an assistant that \
+is answering the user's question. Keep every fact, identifier, number and error that could \
+matter for the question; drop repetition and formatting noise. Be concise; no preamble.";
+
+/// Whether a result should be summarized: an MCP tool's successful result longer
+/// than `threshold_chars` (0 = never) that isn't a summary already
+pub fn should_summarize(
+    call: &ParsedToolCall,
+    result: &str,
+    is_error: bool,
+    threshold_chars: usize,
+) -> bool {
+    threshold_chars > 0
+        && !is_error
+        && call.server != "builtin"
+        && !result.starts_with(SUMMARY_PREFIX)
+        && result.chars().count() > threshold_chars
+}
+
+/// Messages for the summarization request
+pub fn summary_request_messages(
+    call: &ParsedToolCall,
+    user_question: &str,
+    result: &str,
+) -> Vec<ChatMessage> {
+    let message = |role: &str, content: String| ChatMessage {
+        role: role.to_string(),
+        content,
+        ..Default::default()
+    };
+    let result = truncate_tool_result(result, MAX_SUMMARIZER_INPUT_CHARS);
+    vec![
+        message("system", SUMMARY_INSTRUCTIONS.to_string()),
+        message(
+            "user",
+            format!(
+                "User question: {}\n\nTool: {}.{}\nArguments: {}\n\nResult:\n{}",
+                user_question, call.server, call.tool, call.arguments, result
+            ),
+        ),
+    ]
+}
+
+/// The text that replaces the raw result in the history
+pub fn summarized_result(summary: &str, original_chars: usize) -> String {
+    format!(
+        "{} (original was {} chars)\n{}",
+        SUMMARY_PREFIX,
+        original_chars,
+        summary.trim()
+    )
+}
+
+/// Summarize one tool result. Fails if the model returns nothing or a summary
+/// that isn't shorter than the original.
+pub async fn summarize_tool_result(
+    foundry_tx: &mpsc::Sender<FoundryMsg>,
+    model: &str,
+    call: &ParsedToolCall,
+    user_question: &str,
+    result: &str,
+    chat_format_default: ChatFormatName,
+    chat_format_overrides: HashMap<String, ChatFormatName>,
+) -> Result<String, String> {
+    let summary = request_summary(
+        foundry_tx,
+        model,
+        summary_request_messages(call, user_question, result),
+        chat_format_default,
+        chat_format_overrides,
+    )
+    .await?;
+
+    let original_chars = result.chars().count();
+    let summarized = summarized_result(&summary, original_chars);
+    if summarized.chars().count() >= original_chars {
+        return Err("Summary is not shorter than the original result".to_string());
+    }
+    Ok(summarized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(server: &str, tool: &str) -> ParsedToolCall {
+        ParsedToolCall {
+            server: server.to_string(),
+            tool: tool.to_string(),
+            arguments: json!({"query": "orders"}),
+            raw: String::new(),
+            id: None,
+        }
+    }
+
+    #[test]
+    fn test_should_summarize() {
+        let mcp = call("crm", "search");
+        let big = "x".repeat(101);
+
+        assert!(should_summarize(&mcp, &big, false, 100));
+        // Small results, errors, built-in tools and a disabled threshold are left alone
+        assert!(!should_summarize(&mcp, "short", false, 100));
+        assert!(!should_summarize(&mcp, &big, true, 100));
+        assert!(!should_summarize(&call("builtin", "sql_select"), &big, false, 100));
+        assert!(!should_summarize(&mcp, &big, false, 0));
+    }
+
+    #[test]
+    fn test_summaries_are_not_summarized_again() {
+        let summary = summarized_result(&"y".repeat(200), 5000);
+        assert!(summary.starts_with(SUMMARY_PREFIX));
+        assert!(summary.contains("original was 5000 chars"));
+        assert!(!should_summarize(&call("crm", "search"), &summary, false, 100));
+    }
+
+    #[test]
+    fn test_summary_request_caps_input() {
+        let result = "z".repeat(MAX_SUMMARIZER_INPUT_CHARS * 2);
+        let messages = summary_request_messages(&call("crm", "search"), "How many orders?", &result);
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].role, "system");
+        assert!(messages[1].content.contains("User question: How many orders?"));
+        assert!(messages[1].content.contains("Tool: crm.search"));
+        assert!(messages[1].content.chars().count() < MAX_SUMMARIZER_INPUT_CHARS + 1000);
+    }
+}
@@ -8,7 +8,7 @@ use serde_json::Value;
 use std::collections::{HashMap, HashSet};
 
 use crate::protocol::{ExtendedToolCall, ToolCallCaller, ToolCallKind, ToolSchema};
-use python_sandbox::protocol::ToolModuleInfo;
+use python_sandbox::protocol::{ToolCallResult, ToolModuleInfo};
 
 /// Input for the python_execution built-in tool
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +18,12 @@ pub struct CodeExecutionInput {
     /// Optional context/variables to pass to the code
     #[serde(default)]
     pub context: Option<Value>,
+    /// Wall-clock limit for the whole execution in milliseconds (None = unbounded)
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Only compile and statically validate the code, without running it
+    #[serde(default)]
+    pub validate_only: bool,
 }
 
 /// Output from python_execution
@@ -29,10 +35,17 @@ pub struct CodeExecutionOutput {
     pub stderr: String,
     /// Return value from the code (if any)
     pub result: Option<Value>,
+    /// Value of the final expression statement, when the code ends with one
+    #[serde(default)]
+    pub return_value: Option<Value>,
     /// Whether execution succeeded
     pub success: bool,
-    /// Number of tool calls made during execution
-    pub tool_calls_made: usize,
+    /// Tool calls the code made, in call order
+    #[serde(default)]
+    pub tool_calls_made: Vec<PythonToolCallRecord>,
+    /// Answer the code passed to `final_answer(...)`; it ends the turn
+    #[serde(default)]
+    pub final_answer: Option<String>,
     /// Duration of execution in milliseconds
     pub duration_ms: u64,
 }
@@ -43,13 +56,66 @@ impl Default for CodeExecutionOutput {
             stdout: String::new(),
             stderr: String::new(),
             result: None,
+            return_value: None,
             success: false,
-            tool_calls_made: 0,
+            tool_calls_made: Vec::new(),
+            final_answer: None,
             duration_ms: 0,
         }
     }
 }
 
+impl CodeExecutionOutput {
+    /// Compact JSON rendering for `ResultFormat::Json`, so the model doesn't
+    /// have to pick apart the `STDOUT:`/`STDERR:` text block.
+    pub fn to_model_json(&self) -> String {
+        serde_json::json!({
+            "success": self.success,
+            "stdout": self.stdout,
+            "stderr": self.stderr,
+            "return_value": self.return_value,
+            "tool_calls_made": self.tool_calls_made.len(),
+        })
+        .to_string()
+    }
+}
+
+/// Longest `result_summary` kept for a tool call made from Python
+const TOOL_CALL_SUMMARY_CHARS: usize = 200;
+
+/// A tool call made by python_execution code, so the transcript can
+/// attribute it to the code that made it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PythonToolCallRecord {
+    pub name: String,
+    pub server: String,
+    pub arguments: Value,
+    /// Start of the result (or error) text the code received
+    pub result_summary: String,
+    pub is_error: bool,
+}
+
+impl PythonToolCallRecord {
+    pub fn new(name: &str, server: &str, arguments: &Value, result: &ToolCallResult) -> Self {
+        let text = match (&result.error, &result.result) {
+            (Some(error), _) if !result.success => error.clone(),
+            (_, Value::String(text)) => text.clone(),
+            (_, value) => value.to_string(),
+        };
+        let mut result_summary: String = text.chars().take(TOOL_CALL_SUMMARY_CHARS).collect();
+        if result_summary.len() < text.len() {
+            result_summary.push('…');
+        }
+        Self {
+            name: name.to_string(),
+            server: server.to_string(),
+            arguments: arguments.clone(),
+            result_summary,
+            is_error: !result.success,
+        }
+    }
+}
+
 /// A tool call made from within Python code
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InnerToolCall {
@@ -190,6 +256,12 @@ pub struct ExecutionContext {
     pub tool_server_map: HashMap<String, String>,
     /// Allowed global function names for validation
     pub allowed_functions: HashSet<String>,
+    /// Session key (chat_id:generation_id) whose variables persist between executions
+    pub session_key: Option<String>,
+    /// Modules user code may import (None = the sandbox's default list)
+    pub allowed_modules: Option<Vec<String>>,
+    /// Ceiling on memory the code may allocate (None = no limit)
+    pub memory_limit_bytes: Option<usize>,
 }
 
 /// Result of resolving an inner tool call
@@ -220,6 +292,7 @@ pub const ALLOWED_MODULES: &[&str] = &[
     "random",
     "re",
     "datetime",
+    "data",
     "collections",
     "itertools",
     "functools",
@@ -296,7 +369,9 @@ pub const DEFAULT_ALLOWED_FUNCTIONS: &[&str] = &[
     "sandbox_stderr",
     "eprint",
     "tool_call",
+    "tool_call_stream",
     "get_tool_result",
+    "final_answer",
     // Common safe builtins
     "len",
     "range",
@@ -335,6 +410,8 @@ pub const DEFAULT_ALLOWED_FUNCTIONS: &[&str] = &[
 pub struct DynamicImportContext {
     /// Tool modules that are available for import (python_name -> server_id)
     pub tool_modules: std::collections::HashMap<String, String>,
+    /// Configured replacement for ALLOWED_MODULES (None = use the default list)
+    pub allowed_modules: Option<Vec<String>>,
 }
 
 /// Combined validation context (imports + allowed functions)
@@ -348,9 +425,15 @@ impl DynamicImportContext {
     pub fn new() -> Self {
         Self {
             tool_modules: std::collections::HashMap::new(),
+            allowed_modules: None,
         }
     }
 
+    /// Replace the default allowed-module list
+    pub fn set_allowed_modules(&mut self, modules: Vec<String>) {
+        self.allowed_modules = Some(modules);
+    }
+
     /// Add a tool module
     pub fn add_tool_module(&mut self, python_name: String, server_id: String) {
         self.tool_modules.insert(python_name, server_id);
@@ -479,7 +562,10 @@ impl CodeExecutionExecutor {
             disallowed.sort();
             disallowed.dedup();
 
-            let mut allowed_list = ALLOWED_MODULES.join(", ");
+            let mut allowed_list = match context.and_then(|ctx| ctx.allowed_modules.as_ref()) {
+                Some(modules) => modules.join(", "),
+                None => ALLOWED_MODULES.join(", "),
+            };
 
             // Include tool modules in the allowed list if any
             if let Some(ctx) = context {
@@ -557,9 +643,18 @@ impl CodeExecutionExecutor {
 
     /// Check if a module is allowed (either built-in or a tool module)
     fn is_module_allowed(module: &str, context: Option<&DynamicImportContext>) -> bool {
-        // Check built-in modules
-        if ALLOWED_MODULES.contains(&module) {
-            return true;
+        // Check built-in modules (or the configured replacement list)
+        match context.and_then(|ctx| ctx.allowed_modules.as_ref()) {
+            Some(modules) => {
+                if modules.iter().any(|m| m == module) {
+                    return true;
+                }
+            }
+            None => {
+                if ALLOWED_MODULES.contains(&module) {
+                    return true;
+                }
+            }
         }
 
         // Check tool modules from context
@@ -612,6 +707,9 @@ impl CodeExecutionExecutor {
             tool_modules,
             tool_server_map,
             allowed_functions,
+            session_key: None,
+            allowed_modules: None,
+            memory_limit_bytes: Some(python_sandbox::protocol::DEFAULT_MEMORY_LIMIT_BYTES),
         }
     }
 }
@@ -643,12 +741,16 @@ mod tests {
         let good_input = CodeExecutionInput {
             code: vec!["x = 1".to_string(), "print(x)".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         assert!(CodeExecutionExecutor::validate_input(&good_input).is_ok());
 
         let bad_input = CodeExecutionInput {
             code: vec!["import os".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         let err = CodeExecutionExecutor::validate_input(&bad_input).unwrap_err();
         assert!(err.contains("Cannot import 'os'"));
@@ -661,6 +763,8 @@ mod tests {
         let pandas_input = CodeExecutionInput {
             code: vec!["import pandas".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         let err = CodeExecutionExecutor::validate_input(&pandas_input).unwrap_err();
         assert!(
@@ -683,6 +787,8 @@ mod tests {
         let numpy_input = CodeExecutionInput {
             code: vec!["import numpy as np".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         let err = CodeExecutionExecutor::validate_input(&numpy_input).unwrap_err();
         assert!(
@@ -695,6 +801,8 @@ mod tests {
         let from_input = CodeExecutionInput {
             code: vec!["from pandas import DataFrame".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         let err = CodeExecutionExecutor::validate_input(&from_input).unwrap_err();
         assert!(
@@ -720,6 +828,8 @@ mod tests {
             let input = CodeExecutionInput {
                 code: vec![import_stmt.to_string()],
                 context: None,
+                timeout_ms: None,
+                validate_only: false,
             };
             assert!(
                 CodeExecutionExecutor::validate_input(&input).is_ok(),
@@ -746,6 +856,7 @@ mod tests {
             tool_type: None,
             allowed_callers: Some(vec!["python_execution_20251206".to_string()]),
             defer_loading: false,
+            streamable: false,
             embedding: None,
         }];
 
@@ -784,6 +895,8 @@ mod tests {
         let input = CodeExecutionInput {
             code: vec![],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         let err = CodeExecutionExecutor::validate_input(&input).unwrap_err();
         assert!(
@@ -798,6 +911,8 @@ mod tests {
         let input = CodeExecutionInput {
             code: vec!["result = eval('1 + 1')".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         let err = CodeExecutionExecutor::validate_input(&input).unwrap_err();
         assert!(err.contains("eval("), "Error should mention eval: {}", err);
@@ -808,6 +923,8 @@ mod tests {
         let input = CodeExecutionInput {
             code: vec!["exec('x = 1')".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         let err = CodeExecutionExecutor::validate_input(&input).unwrap_err();
         assert!(err.contains("exec("), "Error should mention exec: {}", err);
@@ -818,6 +935,8 @@ mod tests {
         let input = CodeExecutionInput {
             code: vec!["code = compile('x = 1', '', 'exec')".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         let err = CodeExecutionExecutor::validate_input(&input).unwrap_err();
         assert!(
@@ -832,6 +951,8 @@ mod tests {
         let input = CodeExecutionInput {
             code: vec!["os = __import__('os')".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         let err = CodeExecutionExecutor::validate_input(&input).unwrap_err();
         assert!(
@@ -851,6 +972,8 @@ mod tests {
                 "import subprocess".to_string(),
             ],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         let err = CodeExecutionExecutor::validate_input(&input).unwrap_err();
         // Should mention at least one of them
@@ -881,6 +1004,8 @@ mod tests {
         let input = CodeExecutionInput {
             code: vec!["import math as m".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         assert!(
             CodeExecutionExecutor::validate_input(&input).is_ok(),
@@ -893,6 +1018,8 @@ mod tests {
         let input = CodeExecutionInput {
             code: vec!["import math, json, random".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         // Note: This tests comma-separated imports
         // Our regex may or may not support this - let's verify behavior
@@ -915,6 +1042,8 @@ mod tests {
                 "z = x + y".to_string(),
             ],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         let err = CodeExecutionExecutor::validate_input(&input).unwrap_err();
         assert!(
@@ -930,6 +1059,8 @@ mod tests {
         let input = CodeExecutionInput {
             code: vec!["from datetime import datetime, timedelta".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         assert!(
             CodeExecutionExecutor::validate_input(&input).is_ok(),
@@ -943,6 +1074,8 @@ mod tests {
         let input = CodeExecutionInput {
             code: vec!["from os.path import join".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         let err = CodeExecutionExecutor::validate_input(&input).unwrap_err();
         assert!(
@@ -985,6 +1118,8 @@ mod tests {
             let input = CodeExecutionInput {
                 code: vec![import_stmt.to_string()],
                 context: None,
+                timeout_ms: None,
+                validate_only: false,
             };
             assert!(
                 CodeExecutionExecutor::validate_input(&input).is_ok(),
@@ -1011,6 +1146,8 @@ mod tests {
             let input = CodeExecutionInput {
                 code: pattern.iter().map(|s| s.to_string()).collect(),
                 context: None,
+                timeout_ms: None,
+                validate_only: false,
             };
             assert!(
                 CodeExecutionExecutor::validate_input(&input).is_ok(),
@@ -1027,9 +1164,11 @@ mod tests {
             stdout: "Hello, world!".to_string(),
             stderr: String::new(),
             result: Some(json!(42)),
+            return_value: None,
             success: true,
-            tool_calls_made: 0,
+            tool_calls_made: Vec::new(),
             duration_ms: 100,
+            final_answer: None,
         };
 
         let json = serde_json::to_value(&output).unwrap();
@@ -1046,7 +1185,7 @@ mod tests {
         assert_eq!(default.stderr, "");
         assert!(default.result.is_none());
         assert!(!default.success);
-        assert_eq!(default.tool_calls_made, 0);
+        assert!(default.tool_calls_made.is_empty());
         assert_eq!(default.duration_ms, 0);
     }
 
@@ -1073,6 +1212,8 @@ mod tests {
         let input = CodeExecutionInput {
             code: vec!["from weather_api import get_forecast".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         assert!(
             CodeExecutionExecutor::validate_input_with_context(&input, Some(&ctx)).is_ok(),
@@ -1083,6 +1224,8 @@ mod tests {
         let input2 = CodeExecutionInput {
             code: vec!["import unknown_module".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         assert!(
             CodeExecutionExecutor::validate_input_with_context(&input2, Some(&ctx)).is_err(),
@@ -1096,6 +1239,8 @@ mod tests {
         let input = CodeExecutionInput {
             code: vec!["from weather_api import get_forecast".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         assert!(
             CodeExecutionExecutor::validate_input(&input).is_err(),
@@ -1116,6 +1261,8 @@ mod tests {
                 "from collections import Counter".to_string(),
             ],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         assert!(
             CodeExecutionExecutor::validate_input_with_context(&input, Some(&ctx)).is_ok(),
@@ -1123,12 +1270,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_configured_allowed_modules_replace_defaults() {
+        let mut ctx = DynamicImportContext::new();
+        ctx.set_allowed_modules(vec!["math".to_string()]);
+        ctx.add_tool_module("my_tools".to_string(), "mcp-tools".to_string());
+
+        let input = CodeExecutionInput {
+            code: vec![
+                "import math".to_string(),
+                "from my_tools import do_something".to_string(),
+            ],
+            context: None,
+            timeout_ms: None,
+            validate_only: false,
+        };
+        assert!(CodeExecutionExecutor::validate_input_with_context(&input, Some(&ctx)).is_ok());
+
+        // A default module outside the configured list is rejected
+        let input2 = CodeExecutionInput {
+            code: vec!["import json".to_string()],
+            context: None,
+            timeout_ms: None,
+            validate_only: false,
+        };
+        let err = CodeExecutionExecutor::validate_input_with_context(&input2, Some(&ctx))
+            .expect_err("json is not in the configured list");
+        assert!(err.contains("Allowed modules: math, tool modules: my_tools"), "{}", err);
+    }
+
     #[test]
     #[ignore = "Validator does not yet preempt open() usage outside the sandbox"]
     fn test_open_call_should_be_reported_before_execution() {
         let input = CodeExecutionInput {
             code: vec!["f = open('/etc/passwd')".to_string()],
             context: None,
+            timeout_ms: None,
+            validate_only: false,
         };
         let _ = input;
     }
@@ -1138,4 +1316,74 @@ mod tests {
     fn test_runtime_error_surfaces_as_structured_failure() {
         // This will be enabled when we wire a stub executor for unit tests.
     }
+
+    #[test]
+    fn test_tool_call_record_summarizes_result() {
+        let long = "x".repeat(TOOL_CALL_SUMMARY_CHARS + 50);
+        let record = PythonToolCallRecord::new(
+            "read_file",
+            "files",
+            &json!({ "path": "a.txt" }),
+            &ToolCallResult {
+                success: true,
+                result: json!(long),
+                error: None,
+            },
+        );
+        assert_eq!(
+            record.result_summary.chars().count(),
+            TOOL_CALL_SUMMARY_CHARS + 1
+        );
+        assert!(record.result_summary.ends_with('…'));
+        assert!(!record.is_error);
+
+        let failed = PythonToolCallRecord::new(
+            "read_file",
+            "files",
+            &json!({}),
+            &ToolCallResult {
+                success: false,
+                result: Value::Null,
+                error: Some("missing path".to_string()),
+            },
+        );
+        assert_eq!(failed.result_summary, "missing path");
+        assert!(failed.is_error);
+    }
+
+    #[test]
+    fn test_output_to_model_json() {
+        let output = CodeExecutionOutput {
+            stdout: "3\n".to_string(),
+            return_value: Some(json!([1, 2])),
+            success: true,
+            tool_calls_made: vec![
+                PythonToolCallRecord::new(
+                    "a",
+                    "srv",
+                    &json!({}),
+                    &ToolCallResult {
+                        success: true,
+                        result: json!("ok"),
+                        error: None,
+                    }
+                );
+                2
+            ],
+            duration_ms: 15,
+            ..Default::default()
+        };
+
+        let parsed: Value = serde_json::from_str(&output.to_model_json()).unwrap();
+        assert_eq!(
+            parsed,
+            json!({
+                "success": true,
+                "stdout": "3\n",
+                "stderr": "",
+                "return_value": [1, 2],
+                "tool_calls_made": 2,
+            })
+        );
+    }
 }
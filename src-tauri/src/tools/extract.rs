@@ -0,0 +1,297 @@
+//! Extract Implementation
+//!
+//! The `extract` pseudo-tool pulls one value out of an earlier tool result of the
+//! same turn with a dotted/JSONPath-style path, so the model can chain calls
+//! without echoing whole payloads back.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+
+use crate::protocol::ToolSchema;
+
+/// Name of the extract pseudo-tool
+pub const EXTRACT_TOOL: &str = "extract";
+
+/// Id that always refers to the most recent tool result
+pub const LAST_RESULT_ID: &str = "last";
+
+/// Tool results kept per turn for extract
+const MAX_STORED_RESULTS: usize = 20;
+
+/// Schema of the extract pseudo-tool, for native tool calling
+pub fn extract_tool_schema() -> ToolSchema {
+    ToolSchema {
+        name: EXTRACT_TOOL.to_string(),
+        description: Some(
+            "Read one value from an earlier tool result of this turn by path, instead of \
+            copying it by hand. Paths look like `data.items[0].id` (negative indexes count \
+            from the end)."
+                .to_string(),
+        ),
+        parameters: json!({
+            "type": "object",
+            "properties": {
+                "path": {
+                    "type": "string",
+                    "description": "Path into the JSON result, e.g. 'data.items[0].id'"
+                },
+                "from_result_id": {
+                    "type": "string",
+                    "description": "Tool call id whose result to read (default: 'last', the most recent result)"
+                }
+            },
+            "required": ["path"]
+        }),
+        input_examples: Vec::new(),
+        tool_type: None,
+        allowed_callers: None,
+        defer_loading: false,
+        streamable: false,
+        embedding: None,
+    }
+}
+
+/// Input for the extract pseudo-tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractInput {
+    /// Path into the result, e.g. `data.items[0].id` or `$.data.items[-1]["display name"]`
+    pub path: String,
+    /// Id of the tool call whose result to read (defaults to the most recent result)
+    #[serde(default, alias = "result_id", alias = "from")]
+    pub from_result_id: Option<String>,
+}
+
+/// One step of a parsed path
+#[derive(Debug, Clone, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(i64),
+}
+
+/// Recent tool results of the current turn, keyed by tool call id
+#[derive(Debug, Default)]
+pub struct TurnResultStore {
+    results: VecDeque<(String, String)>,
+}
+
+impl TurnResultStore {
+    /// Remember a result; the oldest is dropped past the per-turn limit
+    pub fn insert(&mut self, result_id: &str, result: &str) {
+        self.results.retain(|(id, _)| id != result_id);
+        self.results
+            .push_back((result_id.to_string(), result.to_string()));
+        while self.results.len() > MAX_STORED_RESULTS {
+            self.results.pop_front();
+        }
+    }
+
+    /// Result for an id, or the most recent one for `last`
+    pub fn get(&self, result_id: &str) -> Option<&str> {
+        if result_id == LAST_RESULT_ID {
+            return self.results.back().map(|(_, result)| result.as_str());
+        }
+        self.results
+            .iter()
+            .find(|(id, _)| id == result_id)
+            .map(|(_, result)| result.as_str())
+    }
+
+    /// Run an extract call. Returns `(result_text, is_error)`.
+    pub fn execute(&self, arguments: &Value) -> (String, bool) {
+        let input: ExtractInput = match serde_json::from_value(arguments.clone()) {
+            Ok(input) => input,
+            Err(e) => return (format!("Invalid extract arguments: {}", e), true),
+        };
+        let result_id = input.from_result_id.as_deref().unwrap_or(LAST_RESULT_ID);
+        let Some(result) = self.get(result_id) else {
+            let known: Vec<&str> = self.results.iter().map(|(id, _)| id.as_str()).collect();
+            return (
+                format!(
+                    "No tool result with id '{}' in this turn (available: {})",
+                    result_id,
+                    if known.is_empty() {
+                        "none".to_string()
+                    } else {
+                        known.join(", ")
+                    }
+                ),
+                true,
+            );
+        };
+        let Some(json) = parse_json_result(result) else {
+            return (format!("Tool result '{}' is not JSON", result_id), true);
+        };
+        match extract_path(&json, &input.path) {
+            Ok(Value::String(s)) => (s, false),
+            Ok(value) => (value.to_string(), false),
+            Err(e) => (e, true),
+        }
+    }
+}
+
+/// Parse a tool result as JSON, allowing text around a single object or array
+fn parse_json_result(result: &str) -> Option<Value> {
+    if let Ok(value) = serde_json::from_str(result.trim()) {
+        return Some(value);
+    }
+    let start = result.find(['{', '['])?;
+    let end = result.rfind(['}', ']'])?;
+    (start < end)
+        .then(|| serde_json::from_str(&result[start..=end]).ok())
+        .flatten()
+}
+
+/// Split a path like `$.a.b[0]["c d"]` into segments
+fn parse_path(path: &str) -> Result<Vec<PathSegment>, String> {
+    let path = path.trim();
+    let path = path.strip_prefix('$').unwrap_or(path);
+    let chars: Vec<char> = path.chars().collect();
+    let mut segments = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '.' => i += 1,
+            '[' => {
+                let close = chars[i..]
+                    .iter()
+                    .position(|&c| c == ']')
+                    .map(|offset| i + offset)
+                    .ok_or_else(|| format!("Unclosed '[' in path '{}'", path))?;
+                let inner: String = chars[i + 1..close].iter().collect();
+                let inner = inner.trim();
+                let quoted = inner.len() >= 2
+                    && ((inner.starts_with('"') && inner.ends_with('"'))
+                        || (inner.starts_with('\'') && inner.ends_with('\'')));
+                if quoted {
+                    segments.push(PathSegment::Key(inner[1..inner.len() - 1].to_string()));
+                } else {
+                    let index = inner
+                        .parse::<i64>()
+                        .map_err(|_| format!("Invalid index '[{}]' in path '{}'", inner, path))?;
+                    segments.push(PathSegment::Index(index));
+                }
+                i = close + 1;
+            }
+            _ => {
+                let end = chars[i..]
+                    .iter()
+                    .position(|&c| c == '.' || c == '[')
+                    .map_or(chars.len(), |offset| i + offset);
+                segments.push(PathSegment::Key(chars[i..end].iter().collect()));
+                i = end;
+            }
+        }
+    }
+    Ok(segments)
+}
+
+/// Apply a dotted/JSONPath-style path to a value. Negative indexes count from the end.
+pub fn extract_path(value: &Value, path: &str) -> Result<Value, String> {
+    let mut current = value;
+    let mut walked = String::from("$");
+    for segment in parse_path(path)? {
+        current = match (&segment, current) {
+            (PathSegment::Key(key), Value::Object(map)) => map
+                .get(key)
+                .ok_or_else(|| format!("Key '{}' not found at {}", key, walked))?,
+            (PathSegment::Index(index), Value::Array(items)) => {
+                let resolved = if *index < 0 {
+                    items.len() as i64 + index
+                } else {
+                    *index
+                };
+                usize::try_from(resolved)
+                    .ok()
+                    .and_then(|i| items.get(i))
+                    .ok_or_else(|| {
+                        format!(
+                            "Index {} out of range at {} ({} items)",
+                            index,
+                            walked,
+                            items.len()
+                        )
+                    })?
+            }
+            (PathSegment::Key(key), _) => {
+                return Err(format!(
+                    "Cannot read key '{}' at {}: not an object",
+                    key, walked
+                ))
+            }
+            (PathSegment::Index(index), _) => {
+                return Err(format!(
+                    "Cannot index [{}] at {}: not an array",
+                    index, walked
+                ))
+            }
+        };
+        match segment {
+            PathSegment::Key(key) => walked.push_str(&format!(".{}", key)),
+            PathSegment::Index(index) => walked.push_str(&format!("[{}]", index)),
+        }
+    }
+    Ok(current.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extract_nested_and_array_paths() {
+        let value = json!({
+            "data": {
+                "items": [
+                    {"id": 7, "name": "first"},
+                    {"id": 9, "tags": ["a", "b"], "display name": "last"}
+                ]
+            }
+        });
+
+        assert_eq!(extract_path(&value, "data.items[0].id").unwrap(), json!(7));
+        assert_eq!(
+            extract_path(&value, "$.data.items[1].tags[1]").unwrap(),
+            json!("b")
+        );
+        assert_eq!(
+            extract_path(&value, "data.items[-1][\"display name\"]").unwrap(),
+            json!("last")
+        );
+        assert_eq!(extract_path(&value, "$").unwrap(), value);
+
+        assert!(extract_path(&value, "data.items[5]")
+            .unwrap_err()
+            .contains("out of range"));
+        assert!(extract_path(&value, "data.missing")
+            .unwrap_err()
+            .contains("not found"));
+        assert!(extract_path(&value, "data.items.id")
+            .unwrap_err()
+            .contains("not an object"));
+        assert!(extract_path(&value, "data.items[x]").is_err());
+    }
+
+    #[test]
+    fn test_turn_result_store_extract() {
+        let mut store = TurnResultStore::default();
+        store.insert("call_1", r#"{"user": {"id": "u-42"}}"#);
+        store.insert("call_2", "Result:\n[{\"order\": 1001}, {\"order\": 1002}]");
+
+        let (value, is_error) =
+            store.execute(&json!({"path": "user.id", "from_result_id": "call_1"}));
+        assert!(!is_error);
+        assert_eq!(value, "u-42");
+
+        // Defaults to the most recent result, JSON embedded in text is found
+        let (value, is_error) = store.execute(&json!({"path": "[1].order"}));
+        assert!(!is_error);
+        assert_eq!(value, "1002");
+
+        let (message, is_error) = store.execute(&json!({"path": "x", "from_result_id": "call_9"}));
+        assert!(is_error);
+        assert!(message.contains("available: call_1, call_2"));
+    }
+}
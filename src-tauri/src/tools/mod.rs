@@ -5,8 +5,10 @@
 //! - `python_execution`: Python code execution in a WASM sandbox
 //! - `schema_search`: Semantic search over cached database schemas
 //! - `sql_select`: Execute SQL queries against configured databases
+//! - `extract`: Read one value from an earlier tool result of the turn by path
 
 pub mod code_execution;
+pub mod extract;
 pub mod schema_search;
 pub mod sql_select;
 pub mod tool_search;
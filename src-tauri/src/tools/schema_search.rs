@@ -6,6 +6,7 @@
 
 use fastembed::TextEmbedding;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot, RwLock};
 
@@ -32,9 +33,8 @@ pub fn is_numeric_data_type(data_type: &str) -> bool {
 
 /// Hybrid column selection: all non-numeric columns + top N numeric columns.
 /// 
-/// This is the shared column selection strategy used by both:
-/// - The `schema_search` tool
-/// - Attached table schema formatting
+/// Used for attached table schema formatting; the `schema_search` tool ranks
+/// all columns by relevance instead (see `rank_columns_by_relevance`).
 /// 
 /// # Arguments
 /// * `columns` - All columns from the table schema
@@ -94,6 +94,28 @@ pub fn select_columns_hybrid<'a>(
     (selected, numeric_count, non_numeric_count)
 }
 
+/// The `max_columns` columns most relevant to the query, best first.
+///
+/// `scores` maps column names to their embedding similarity; columns without
+/// a score rank last, in schema order.
+pub fn rank_columns_by_relevance(
+    columns: &[CachedColumnSchema],
+    scores: &HashMap<String, f32>,
+    max_columns: usize,
+) -> Vec<ColumnOutput> {
+    let mut ranked: Vec<ColumnOutput> = columns
+        .iter()
+        .map(|c| {
+            let relevance = scores.get(&c.name).copied().unwrap_or(0.0);
+            ColumnOutput::from_cached_column_schema(c, relevance)
+        })
+        .collect();
+    // Stable, so ties keep schema order
+    ranked.sort_by(|a, b| b.relevance.total_cmp(&a.relevance));
+    ranked.truncate(max_columns);
+    ranked
+}
+
 /// Input for the schema_search built-in tool
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SchemaSearchInput {
@@ -109,6 +131,9 @@ pub struct SchemaSearchInput {
     /// Minimum relevance score (0.0-1.0) to include a table (default: 0.3)
     #[serde(default = "default_min_score")]
     pub min_relevance: f32,
+    /// Only search tables from these database sources (empty = all sources)
+    #[serde(default)]
+    pub source_ids: Vec<String>,
 }
 
 fn default_max_tables() -> usize {
@@ -246,6 +271,7 @@ impl SchemaSearchExecutor {
                 query_embedding: query_embedding.clone(),
                 limit: input.max_tables,
                 min_score: min_relevance,
+                source_ids: input.source_ids.clone(),
                 respond_to: table_tx,
             })
             .await
@@ -260,9 +286,8 @@ impl SchemaSearchExecutor {
             table_results.len()
         );
 
-        // For each table, use hybrid column selection:
-        // - Include ALL non-numeric columns (TEXT, DATE, BOOLEAN, etc.)
-        // - Semantic search on numeric columns, limited by max_columns_per_table
+        // For each table, rank every column by its own similarity to the query
+        // and keep the top max_columns_per_table
         let mut output_tables = Vec::new();
 
         for table in table_results {
@@ -279,67 +304,37 @@ impl SchemaSearchExecutor {
             let full_schema = schema_rx.await.unwrap_or(None);
 
             let relevant_columns = if let Some(schema) = full_schema {
-                // 2. Separate columns into numeric vs non-numeric
-                let (numeric_cols, non_numeric_cols): (Vec<_>, Vec<_>) = schema
-                    .columns
-                    .iter()
-                    .partition(|c| is_numeric_data_type(&c.data_type));
+                // 2. Score all of the table's columns against the query
+                let (col_tx, col_rx) = oneshot::channel();
+                self.schema_tx
+                    .send(SchemaVectorMsg::SearchColumns {
+                        query_embedding: query_embedding.clone(),
+                        table_fq_name: Some(table.table_fq_name.clone()),
+                        limit: schema.columns.len().max(input.max_columns_per_table),
+                        respond_to: col_tx,
+                    })
+                    .await
+                    .map_err(|e| format!("Failed to search columns: {}", e))?;
 
-                // 3. Include ALL non-numeric columns with high relevance (1.0)
-                let mut columns: Vec<ColumnOutput> = non_numeric_cols
+                let scores: HashMap<String, f32> = col_rx
+                    .await
+                    .unwrap_or_default()
                     .into_iter()
-                    .map(|c| ColumnOutput::from_cached_column_schema(c, 1.0))
+                    .map(|c| (c.column_name, c.relevance_score))
                     .collect();
 
+                // 3. Keep the most relevant columns, best first
+                let columns = rank_columns_by_relevance(
+                    &schema.columns,
+                    &scores,
+                    input.max_columns_per_table,
+                );
                 println!(
-                    "[SchemaSearch] Table '{}': {} non-numeric columns included",
+                    "[SchemaSearch] Table '{}': {} of {} columns selected by relevance",
                     table.table_fq_name,
-                    columns.len()
+                    columns.len(),
+                    schema.columns.len()
                 );
-
-                // 4. Semantic search on numeric columns, limited by max_columns_per_table
-                if !numeric_cols.is_empty() {
-                    let (col_tx, col_rx) = oneshot::channel();
-                    self.schema_tx
-                        .send(SchemaVectorMsg::SearchColumns {
-                            query_embedding: query_embedding.clone(),
-                            table_fq_name: Some(table.table_fq_name.clone()),
-                            limit: input.max_columns_per_table,
-                            respond_to: col_tx,
-                        })
-                        .await
-                        .map_err(|e| format!("Failed to search columns: {}", e))?;
-
-                    let column_results = col_rx.await.unwrap_or_default();
-
-                    // Filter to only include numeric columns from the search results
-                    let numeric_col_names: std::collections::HashSet<_> =
-                        numeric_cols.iter().map(|c| c.name.as_str()).collect();
-
-                    let numeric_search_results: Vec<ColumnOutput> = column_results
-                        .into_iter()
-                        .filter(|c| numeric_col_names.contains(c.column_name.as_str()))
-                        .take(input.max_columns_per_table)
-                        .map(|c| ColumnOutput {
-                            name: c.column_name,
-                            data_type: c.data_type,
-                            relevance: c.relevance_score,
-                            description: c.description,
-                            special_attributes: c.special_attributes,
-                            top_values: c.top_values,
-                        })
-                        .collect();
-
-                    println!(
-                        "[SchemaSearch] Table '{}': {} numeric columns selected (of {} total numeric)",
-                        table.table_fq_name,
-                        numeric_search_results.len(),
-                        numeric_cols.len()
-                    );
-
-                    columns.extend(numeric_search_results);
-                }
-
                 columns
             } else {
                 // Fallback: if we can't get the full schema, use old behavior
@@ -493,4 +488,43 @@ mod tests {
         assert!(json.contains("orders"));
         assert!(json.contains("GoogleSQL"));
     }
+
+    #[test]
+    fn test_rank_columns_by_relevance() {
+        let column = |name: &str, data_type: &str| CachedColumnSchema {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable: true,
+            description: None,
+            special_attributes: Vec::new(),
+            top_values: Vec::new(),
+        };
+        let columns = vec![
+            column("id", "INT64"),
+            column("created_by", "STRING"),
+            column("notes", "STRING"),
+            column("legacy_flag", "BOOL"),
+            column("total_amount", "FLOAT64"),
+        ];
+        let scores: HashMap<String, f32> = [
+            ("id", 0.21),
+            ("created_by", 0.18),
+            ("notes", 0.35),
+            ("total_amount", 0.82),
+        ]
+        .into_iter()
+        .map(|(name, score)| (name.to_string(), score))
+        .collect();
+
+        let ranked = rank_columns_by_relevance(&columns, &scores, 2);
+        let names: Vec<&str> = ranked.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["total_amount", "notes"]);
+        assert!((ranked[0].relevance - 0.82).abs() < 1e-6);
+
+        // Unscored columns come last
+        let ranked = rank_columns_by_relevance(&columns, &scores, 10);
+        assert_eq!(ranked.len(), 5);
+        assert_eq!(ranked[4].name, "legacy_flag");
+        assert_eq!(ranked[4].relevance, 0.0);
+    }
 }
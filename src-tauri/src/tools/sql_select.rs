@@ -8,6 +8,7 @@ use serde_json::Value;
 use tokio::sync::{mpsc, oneshot};
 
 use crate::actors::database_toolbox_actor::DatabaseToolboxMsg;
+use crate::settings::SqlResultFormat;
 
 /// Input for the sql_select built-in tool
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,10 +24,53 @@ pub struct SqlSelectInput {
     /// Maximum number of rows to return (default: 25)
     #[serde(default = "default_max_rows")]
     pub max_rows: usize,
+    /// Rows to skip before the returned window, for paging through large results
+    #[serde(default, deserialize_with = "deserialize_offset")]
+    pub offset: Option<usize>,
 }
 
+/// Default page size for sql_select
+pub const DEFAULT_MAX_ROWS: usize = 25;
+
+/// Largest offset accepted for paging; beyond this the query should aggregate instead
+pub const MAX_SQL_OFFSET: usize = 1_000_000;
+
 fn default_max_rows() -> usize {
-    25
+    DEFAULT_MAX_ROWS
+}
+
+fn deserialize_offset<'de, D>(deserializer: D) -> Result<Option<usize>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<Value>::deserialize(deserializer)?;
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => parse_offset(&value).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Parse a model-supplied `offset`, rejecting negative or oversized values.
+///
+/// Numeric strings are accepted since models often quote integers.
+pub fn parse_offset(value: &Value) -> Result<usize, String> {
+    let offset = match value {
+        Value::Number(n) => n.as_i64().or_else(|| n.as_f64().map(|f| f as i64)),
+        Value::String(s) => s.trim().parse::<i64>().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| format!("offset must be an integer, got {}", value))?;
+
+    if offset < 0 {
+        return Err(format!("offset must be non-negative, got {}", offset));
+    }
+    if offset as u64 > MAX_SQL_OFFSET as u64 {
+        return Err(format!(
+            "offset {} exceeds the maximum of {}; use aggregation or a WHERE clause instead",
+            offset, MAX_SQL_OFFSET
+        ));
+    }
+    Ok(offset as usize)
 }
 
 /// Output from sql_select
@@ -46,6 +90,9 @@ pub struct SqlSelectOutput {
     pub error: Option<String>,
     /// The SQL that was executed
     pub sql_executed: String,
+    /// Whether more rows exist past this window (page with a larger `offset`)
+    #[serde(default)]
+    pub has_more: bool,
 }
 
 /// Executor for the sql_select built-in tool
@@ -99,9 +146,19 @@ impl SqlSelectExecutor {
         if input.sql.trim().is_empty() {
             return Err("SQL query cannot be empty".to_string());
         }
+        ensure_read_only_select(&input.sql)?;
 
-        // Apply row limit to SELECT queries
-        let limited_sql = apply_row_limit(&input.sql, input.max_rows);
+        let offset = input.offset.unwrap_or(0);
+        if offset > MAX_SQL_OFFSET {
+            return Err(format!(
+                "offset {} exceeds the maximum of {}",
+                offset, MAX_SQL_OFFSET
+            ));
+        }
+        let max_rows = input.max_rows.max(1);
+
+        // Fetch one row past the window so we can tell whether more remain
+        let limited_sql = apply_row_window(&input.sql, max_rows, offset)?;
 
         // Execute via the Database Toolbox Actor
         let (tx, rx) = oneshot::channel();
@@ -121,19 +178,24 @@ impl SqlSelectExecutor {
 
         match result {
             Ok(exec_result) => {
+                let mut rows = exec_result.rows;
+                let has_more = rows.len() > max_rows;
+                rows.truncate(max_rows);
                 println!(
-                    "[SqlSelect] Success: {} rows returned",
-                    exec_result.row_count
+                    "[SqlSelect] Success: {} rows returned (has_more: {})",
+                    rows.len(),
+                    has_more
                 );
 
                 Ok(SqlSelectOutput {
                     success: true,
                     columns: exec_result.columns,
-                    rows: exec_result.rows,
-                    row_count: exec_result.row_count,
+                    row_count: rows.len(),
+                    rows,
                     rows_affected: None, // Would need to parse from result for DML
                     error: None,
                     sql_executed: limited_sql,
+                    has_more,
                 })
             }
             Err(e) => {
@@ -147,12 +209,97 @@ impl SqlSelectExecutor {
                     rows_affected: None,
                     error: Some(e),
                     sql_executed: limited_sql,
+                    has_more: false,
                 })
             }
         }
     }
 }
 
+/// Keywords that write data, change schema or run procedures
+const FORBIDDEN_SQL_KEYWORDS: &[&str] = &[
+    "INSERT", "UPDATE", "DELETE", "MERGE", "UPSERT", "DROP", "CREATE", "ALTER", "TRUNCATE",
+    "RENAME", "GRANT", "REVOKE", "EXEC", "EXECUTE", "CALL", "ATTACH", "DETACH", "PRAGMA",
+    "VACUUM", "REINDEX", "COPY", "INTO",
+];
+
+/// Remove comments and the contents of string literals and quoted identifiers,
+/// so keyword and `;` checks only see SQL structure.
+fn strip_sql_literals(sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '-' if chars.peek() == Some(&'-') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                out.push(' ');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+                out.push(' ');
+            }
+            '\'' | '"' | '`' | '[' => {
+                let close = if c == '[' { ']' } else { c };
+                for c in chars.by_ref() {
+                    if c == close {
+                        break;
+                    }
+                }
+                out.push_str(" _ ");
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Reject anything other than a single read-only `SELECT` (or `WITH ... SELECT`).
+pub fn ensure_read_only_select(sql: &str) -> Result<(), String> {
+    let stripped = strip_sql_literals(sql);
+    let body = stripped.trim().trim_end_matches(';').trim_end();
+    if body.contains(';') {
+        return Err(
+            "Only a single SQL statement is allowed; remove the extra ';' statements".to_string(),
+        );
+    }
+
+    let upper = body.to_uppercase();
+    let mut words = upper
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|w| !w.is_empty());
+    match words.next() {
+        Some("SELECT") | Some("WITH") => {}
+        Some(first) => {
+            return Err(format!(
+                "sql_select only runs read-only SELECT queries; '{}' statements are not allowed",
+                first
+            ));
+        }
+        None => return Err("SQL query cannot be empty".to_string()),
+    }
+    if let Some(keyword) = words.find(|w| FORBIDDEN_SQL_KEYWORDS.contains(w)) {
+        return Err(format!(
+            "sql_select only runs read-only SELECT queries; '{}' is not allowed",
+            keyword
+        ));
+    }
+    if !upper.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).any(|w| w == "SELECT") {
+        return Err("sql_select only runs read-only SELECT queries".to_string());
+    }
+    Ok(())
+}
+
 /// Truncate SQL for logging
 fn truncate_sql(sql: &str, max_len: usize) -> String {
     let normalized: String = sql.split_whitespace().collect::<Vec<_>>().join(" ");
@@ -181,6 +328,141 @@ fn apply_row_limit(sql: &str, max_rows: usize) -> String {
     }
 }
 
+/// Restrict a SELECT to the window `[offset, offset + max_rows]`.
+///
+/// One extra row is requested so callers can report `has_more`. Without an
+/// offset this is `apply_row_limit`; with one, the query is wrapped in a
+/// subquery so any LIMIT/ORDER BY it already has still applies.
+pub fn apply_row_window(sql: &str, max_rows: usize, offset: usize) -> Result<String, String> {
+    if offset == 0 {
+        return Ok(apply_row_limit(sql, max_rows + 1));
+    }
+
+    let body = sql.trim().trim_end_matches(';').trim();
+    if !body.to_uppercase().starts_with("SELECT") {
+        return Err("offset is only supported for SELECT queries".to_string());
+    }
+    Ok(format!(
+        "SELECT * FROM ({}) AS paged_result LIMIT {} OFFSET {}",
+        body,
+        max_rows + 1,
+        offset
+    ))
+}
+
+/// Results with more rows than this stay JSON even in table format
+pub const TABLE_FORMAT_MAX_ROWS: usize = 50;
+
+/// Table cells longer than this are cut with an ellipsis
+const MAX_TABLE_CELL_CHARS: usize = 40;
+
+/// Column type inferred from the first non-null value in the column
+fn infer_column_type(rows: &[Vec<Value>], column: usize) -> &'static str {
+    match rows
+        .iter()
+        .filter_map(|row| row.get(column))
+        .find(|value| !value.is_null())
+    {
+        Some(Value::Bool(_)) => "bool",
+        Some(Value::Number(n)) if n.is_i64() || n.is_u64() => "int",
+        Some(Value::Number(_)) => "float",
+        Some(Value::String(_)) => "text",
+        Some(Value::Array(_)) | Some(Value::Object(_)) => "json",
+        Some(Value::Null) | None => "null",
+    }
+}
+
+fn format_table_cell(value: &Value) -> String {
+    let text = match value {
+        Value::Null => "NULL".to_string(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let text = text.replace(['\n', '\r'], " ");
+    let text = if text.chars().count() > MAX_TABLE_CELL_CHARS {
+        let cut: String = text.chars().take(MAX_TABLE_CELL_CHARS - 1).collect();
+        format!("{}…", cut.trim_end())
+    } else {
+        text
+    };
+    text.replace('|', "\\|")
+}
+
+/// Render rows as a Markdown table whose header names each column's inferred type
+pub fn format_sql_result_table(columns: &[String], rows: &[Vec<Value>]) -> String {
+    if rows.is_empty() {
+        return if columns.is_empty() {
+            "Query returned no rows.".to_string()
+        } else {
+            format!("Query returned no rows (columns: {}).", columns.join(", "))
+        };
+    }
+
+    let header: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            format!(
+                "{} ({})",
+                name.replace('|', "\\|"),
+                infer_column_type(rows, i)
+            )
+        })
+        .collect();
+    let mut table = format!(
+        "{} row{}\n| {} |\n|{}\n",
+        rows.len(),
+        if rows.len() == 1 { "" } else { "s" },
+        header.join(" | "),
+        " --- |".repeat(columns.len())
+    );
+    for row in rows {
+        let cells: Vec<String> = (0..columns.len())
+            .map(|i| format_table_cell(row.get(i).unwrap_or(&Value::Null)))
+            .collect();
+        table.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    table.trim_end().to_string()
+}
+
+/// The text of a successful sql_select result as the model should see it. Table
+/// format applies to results of up to `TABLE_FORMAT_MAX_ROWS` rows; anything
+/// else (or a result that isn't the usual JSON) is returned unchanged.
+pub fn render_sql_result_for_model(result: &str, format: SqlResultFormat) -> String {
+    if format != SqlResultFormat::Table {
+        return result.to_string();
+    }
+    let Ok(parsed) = serde_json::from_str::<Value>(result) else {
+        return result.to_string();
+    };
+    let (Some(columns), Some(rows)) = (
+        parsed.get("columns").and_then(|c| c.as_array()),
+        parsed.get("rows").and_then(|r| r.as_array()),
+    ) else {
+        return result.to_string();
+    };
+    if rows.len() > TABLE_FORMAT_MAX_ROWS {
+        return result.to_string();
+    }
+
+    let columns: Vec<String> = columns
+        .iter()
+        .map(|c| c.as_str().map_or_else(|| c.to_string(), str::to_string))
+        .collect();
+    let rows: Vec<Vec<Value>> = rows
+        .iter()
+        .map(|row| row.as_array().cloned().unwrap_or_default())
+        .collect();
+    let mut table = format_sql_result_table(&columns, &rows);
+    if let Some(offset) = parsed.get("offset").and_then(|o| o.as_u64()) {
+        table.push_str(&format!("\n(offset {})", offset));
+    }
+    if parsed.get("has_more").and_then(|m| m.as_bool()) == Some(true) {
+        table.push_str("\nMore rows are available; page with a larger offset.");
+    }
+    table
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -194,6 +476,40 @@ mod tests {
         assert_eq!(input.sql, "SELECT * FROM orders");
         assert!(input.parameters.is_empty());
         assert_eq!(input.max_rows, 25);
+        assert_eq!(input.offset, None);
+    }
+
+    #[test]
+    fn test_sql_select_input_offset() {
+        let parse = |json: &str| serde_json::from_str::<SqlSelectInput>(json);
+
+        let input = parse(r#"{"sql": "SELECT 1", "offset": 50}"#).unwrap();
+        assert_eq!(input.offset, Some(50));
+        let quoted = parse(r#"{"sql": "SELECT 1", "offset": "10"}"#).unwrap();
+        assert_eq!(quoted.offset, Some(10));
+        let null = parse(r#"{"sql": "SELECT 1", "offset": null}"#).unwrap();
+        assert_eq!(null.offset, None);
+
+        assert!(parse(r#"{"sql": "SELECT 1", "offset": -5}"#).is_err());
+        assert!(parse(r#"{"sql": "SELECT 1", "offset": 10000000000}"#).is_err());
+    }
+
+    #[test]
+    fn test_apply_row_window() {
+        // First page fetches one extra row to detect has_more
+        assert_eq!(
+            apply_row_window("SELECT * FROM orders;", 25, 0).unwrap(),
+            "SELECT * FROM orders LIMIT 26"
+        );
+
+        // Later pages wrap the query so its own ordering and limits survive
+        assert_eq!(
+            apply_row_window("SELECT * FROM orders ORDER BY id LIMIT 500;", 25, 50).unwrap(),
+            "SELECT * FROM (SELECT * FROM orders ORDER BY id LIMIT 500) AS paged_result \
+            LIMIT 26 OFFSET 50"
+        );
+
+        assert!(apply_row_window("DELETE FROM orders", 25, 10).is_err());
     }
 
     #[test]
@@ -234,6 +550,7 @@ mod tests {
             rows_affected: None,
             error: None,
             sql_executed: "SELECT id, name FROM users LIMIT 100".to_string(),
+            has_more: true,
         };
 
         let json = serde_json::to_string(&output).unwrap();
@@ -242,6 +559,89 @@ mod tests {
         assert!(parsed.success);
         assert_eq!(parsed.row_count, 2);
         assert_eq!(parsed.columns.len(), 2);
+        assert!(parsed.has_more);
+    }
+
+    #[test]
+    fn test_render_sql_result_json_format_unchanged() {
+        let result = r#"{"success": true, "columns": ["id"], "rows": [[1]], "row_count": 1}"#;
+        assert_eq!(
+            render_sql_result_for_model(result, SqlResultFormat::Json),
+            result
+        );
+    }
+
+    #[test]
+    fn test_render_sql_result_table_format() {
+        let long_note = "x".repeat(60);
+        let result = serde_json::json!({
+            "success": true,
+            "columns": ["id", "name", "score", "note"],
+            "rows": [
+                [1, "Alice", 9.5, null],
+                [2, "Bob|Smith", null, long_note],
+            ],
+            "row_count": 2,
+            "offset": 10,
+            "has_more": true,
+        })
+        .to_string();
+
+        let table = render_sql_result_for_model(&result, SqlResultFormat::Table);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "2 rows");
+        assert_eq!(
+            lines[1],
+            "| id (int) | name (text) | score (float) | note (text) |"
+        );
+        assert_eq!(lines[2], "| --- | --- | --- | --- |");
+        assert_eq!(lines[3], "| 1 | Alice | 9.5 | NULL |");
+        assert!(lines[4].starts_with("| 2 | Bob\\|Smith | NULL | xxx"));
+        assert!(lines[4].ends_with("x… |"));
+        assert!(table.contains("(offset 10)"));
+        assert!(table.contains("More rows are available"));
+
+        // Large results stay JSON
+        let rows: Vec<Value> = (0..=TABLE_FORMAT_MAX_ROWS)
+            .map(|i| serde_json::json!([i]))
+            .collect();
+        let large = serde_json::json!({"columns": ["id"], "rows": rows}).to_string();
+        assert_eq!(
+            render_sql_result_for_model(&large, SqlResultFormat::Table),
+            large
+        );
+    }
+
+    #[test]
+    fn test_render_sql_result_table_empty() {
+        let result = r#"{"success": true, "columns": ["id", "name"], "rows": [], "row_count": 0}"#;
+        assert_eq!(
+            render_sql_result_for_model(result, SqlResultFormat::Table),
+            "Query returned no rows (columns: id, name)."
+        );
+    }
+
+    #[test]
+    fn test_read_only_select_check() {
+        assert!(ensure_read_only_select("SELECT * FROM orders;").is_ok());
+        assert!(ensure_read_only_select(
+            "WITH recent AS (SELECT * FROM orders WHERE day > '2024-01-01') \
+             SELECT COUNT(*) FROM recent"
+        )
+        .is_ok());
+        // Keywords inside literals, quoted identifiers and comments are fine
+        assert!(ensure_read_only_select(
+            "SELECT 'DROP TABLE x; --', \"update\" FROM t -- delete later"
+        )
+        .is_ok());
+
+        let err = ensure_read_only_select("DROP TABLE orders").unwrap_err();
+        assert!(err.contains("DROP"));
+        let err = ensure_read_only_select("SELECT 1; DELETE FROM orders").unwrap_err();
+        assert!(err.contains("single SQL statement"));
+        assert!(ensure_read_only_select("WITH x AS (SELECT 1) DELETE FROM orders").is_err());
+        assert!(ensure_read_only_select("SELECT * INTO backup FROM orders").is_err());
+        assert!(ensure_read_only_select("WITH x AS (VALUES (1)) VALUES (2)").is_err());
     }
 
     #[test]
@@ -10,13 +10,18 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use crate::protocol::ToolSchema;
 use crate::tool_registry::{SharedToolRegistry, ToolSearchResult};
 
 /// Input for the tool_search built-in tool
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolSearchInput {
-    /// Semantic search queries describing what tools are needed
+    /// Semantic search queries describing what tools are needed. Each query is
+    /// scored separately and a tool keeps its best score (see `search_tools`).
     pub queries: Vec<String>,
+    /// Optional relative weight per query, in query order (empty = all equal)
+    #[serde(default)]
+    pub query_weights: Vec<f32>,
     /// Maximum number of tools to return (default: 3)
     #[serde(default = "default_top_k")]
     pub top_k: usize,
@@ -26,6 +31,20 @@ fn default_top_k() -> usize {
     3
 }
 
+/// Note shown in place of discovered tools when no hit clears the relevance floor
+pub const NO_RELEVANT_TOOLS_NOTE: &str = "# No relevant tools found";
+
+/// Keep only the hits scoring at least `min_relevance`
+pub fn filter_by_min_relevance(
+    hits: &[ToolSearchResult],
+    min_relevance: f32,
+) -> Vec<ToolSearchResult> {
+    hits.iter()
+        .filter(|hit| hit.score >= min_relevance)
+        .cloned()
+        .collect()
+}
+
 /// Output from tool_search
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolSearchOutput {
@@ -66,6 +85,16 @@ impl ToolSearchExecutor {
         if input.queries.is_empty() {
             return Err("At least one search query is required".to_string());
         }
+        if !input.query_weights.is_empty() && input.query_weights.len() != input.queries.len() {
+            return Err(format!(
+                "query_weights has {} entries but there are {} queries",
+                input.query_weights.len(),
+                input.queries.len()
+            ));
+        }
+        if input.query_weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return Err("query_weights must be non-negative numbers".to_string());
+        }
 
         // Get the embedding model
         let model_guard = self.embedding_model.read().await;
@@ -79,7 +108,7 @@ impl ToolSearchExecutor {
 
         // Search the registry
         let registry = self.registry.read().await;
-        let results = registry.search_tools(&query_embeddings, input.top_k);
+        let results = registry.search_tools(&query_embeddings, &input.query_weights, input.top_k);
 
         println!("[ToolSearch] Found {} matching tools", results.len());
         for result in &results {
@@ -218,6 +247,15 @@ impl ToolSearchExecutor {
     }
 }
 
+/// Text embedded for a tool: its name and (possibly overridden) description
+pub fn tool_embedding_text(schema: &ToolSchema) -> String {
+    format!(
+        "{}: {}",
+        schema.name,
+        schema.description.as_deref().unwrap_or("")
+    )
+}
+
 /// Pre-compute embeddings for all tools in the registry
 pub async fn precompute_tool_search_embeddings(
     registry: SharedToolRegistry,
@@ -239,15 +277,7 @@ pub async fn precompute_tool_search_embeddings(
         registry_guard
             .get_all_domain_tools()
             .iter()
-            .map(|(key, schema)| {
-                // Create embedding text from name and description
-                let text = format!(
-                    "{}: {}",
-                    schema.name,
-                    schema.description.as_deref().unwrap_or("")
-                );
-                ((*key).clone(), text)
-            })
+            .map(|(key, schema)| ((*key).clone(), tool_embedding_text(schema)))
             .collect()
     };
 
@@ -307,4 +337,52 @@ mod tests {
 
         assert_eq!(input.top_k, 3); // default_top_k() returns 3
     }
+
+    #[test]
+    fn test_filter_by_min_relevance() {
+        let hit = |name: &str, score: f32| ToolSearchResult {
+            name: name.to_string(),
+            description: None,
+            score,
+            server_id: "server".to_string(),
+            parameters: json!({}),
+        };
+        let hits = vec![hit("get_weather", 0.82), hit("send_email", 0.12)];
+
+        let kept = filter_by_min_relevance(&hits, 0.3);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].name, "get_weather");
+
+        assert!(filter_by_min_relevance(&hits, 0.9).is_empty());
+        assert_eq!(filter_by_min_relevance(&hits, 0.0).len(), 2);
+    }
+
+    #[test]
+    fn test_description_override_changes_embedded_text() {
+        use crate::actors::mcp_host_actor::McpTool;
+        use crate::tool_registry::{apply_mcp_description_overrides, ToolRegistry};
+
+        let tool = McpTool {
+            name: "lookup".to_string(),
+            description: Some("Look something up".to_string()),
+            input_schema: None,
+            input_examples: None,
+            allowed_callers: None,
+            streamable: false,
+        };
+        let mut tool_descriptions = vec![("crm".to_string(), vec![tool])];
+        let overrides = HashMap::from([(
+            "crm::lookup".to_string(),
+            "Find a customer account by email or phone number".to_string(),
+        )]);
+        apply_mcp_description_overrides(&mut tool_descriptions, &overrides);
+
+        let mut registry = ToolRegistry::new();
+        registry.register_mcp_tools("crm", "crm", &tool_descriptions[0].1, true);
+        let schema = registry.get_tool("crm___lookup").unwrap();
+        assert_eq!(
+            tool_embedding_text(schema),
+            "lookup: Find a customer account by email or phone number"
+        );
+    }
 }
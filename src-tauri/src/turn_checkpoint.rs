@@ -0,0 +1,243 @@
+//! Checkpoints for resumable turns.
+//!
+//! The agentic loop records where a turn is after each iteration: the state
+//! machine state, the auto-discovery context and the full message history.
+//! The checkpoint is kept on `TurnProgress` so `resume_turn` can pick the turn
+//! up within the same session, and mirrored to disk so a turn cut off by a
+//! crash can be resumed after a restart. A clean finish or a cancel clears it.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+use crate::agentic_state::AgenticState;
+use crate::paths::get_data_dir;
+use crate::protocol::ChatMessage;
+use crate::settings_state_machine::AttachedTableInfo;
+use crate::tools::schema_search::SchemaSearchOutput;
+use crate::tools::tool_search::ToolSearchOutput;
+
+const CHECKPOINT_FILE_NAME: &str = "turn_checkpoint.json";
+
+/// The frontend arguments of a `chat` call, kept so the turn can be rebuilt
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatTurnRequest {
+    pub chat_id: Option<String>,
+    pub title: Option<String>,
+    pub message: String,
+    /// Prior messages; left empty in checkpoints, where `full_history` supersedes it
+    #[serde(default)]
+    pub history: Vec<ChatMessage>,
+    pub reasoning_effort: String,
+    pub model: String,
+    #[serde(default)]
+    pub attached_files: Vec<String>,
+    #[serde(default)]
+    pub attached_tables: Vec<AttachedTableInfo>,
+    #[serde(default)]
+    pub attached_tools: Vec<String>,
+    #[serde(default)]
+    pub attached_tabular_files: Vec<String>,
+    /// Images for the user message (data URLs, base64 or file paths); left
+    /// empty in checkpoints, where `full_history` carries them
+    #[serde(default)]
+    pub images: Vec<String>,
+    /// JSON schema the final answer must follow (structured output)
+    #[serde(default)]
+    pub response_schema: Option<serde_json::Value>,
+    /// Sampling temperature override for this turn
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// Sampling seed for reproducible output
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Turn-start state to use instead of the computed one (e.g. "SqlRetrieval")
+    #[serde(default)]
+    pub force_state: Option<String>,
+    /// Skip (true) or force (false) auto-discovery; None leaves it to the prompt heuristic
+    #[serde(default)]
+    pub skip_auto_discovery: Option<bool>,
+}
+
+impl ChatTurnRequest {
+    /// Request that replays the last user message of a stored chat against
+    /// `model`, with the messages before it as history. The answer it got
+    /// (and anything after it) is left out.
+    pub fn rerun_of(
+        messages: &[ChatMessage],
+        model: &str,
+        reasoning_effort: &str,
+    ) -> Result<Self, String> {
+        let last_user = messages
+            .iter()
+            .rposition(|m| m.role == "user" && !m.intermediate)
+            .ok_or_else(|| "Chat has no user message to re-run".to_string())?;
+        Ok(Self {
+            message: messages[last_user].content.clone(),
+            images: messages[last_user].images.clone(),
+            history: messages[..last_user].to_vec(),
+            model: model.to_string(),
+            reasoning_effort: reasoning_effort.to_string(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Snapshot of an in-flight turn, taken at the start of each loop iteration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnCheckpoint {
+    pub request: ChatTurnRequest,
+    /// State machine state to restore
+    pub state: AgenticState,
+    /// Auto-discovered tools for the turn
+    pub tool_search: Option<ToolSearchOutput>,
+    /// Auto-discovered tables for the turn
+    pub schema_search: Option<SchemaSearchOutput>,
+    /// Messages sent to the model, including tool calls and results so far
+    pub full_history: Vec<ChatMessage>,
+    /// Chat before this turn as stored, so the resumed turn saves it too
+    /// (the checkpointed request carries no history)
+    #[serde(default)]
+    pub stored_history: Vec<ChatMessage>,
+    /// Loop iteration to continue from
+    pub iteration: usize,
+}
+
+/// Where the checkpoint of the last interrupted turn is persisted
+pub fn get_checkpoint_path() -> PathBuf {
+    get_data_dir().join(CHECKPOINT_FILE_NAME)
+}
+
+/// Write a checkpoint, replacing any previous one
+pub async fn save_checkpoint(path: &Path, checkpoint: &TurnCheckpoint) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create checkpoint directory: {}", e))?;
+    }
+
+    let contents = serde_json::to_string(checkpoint)
+        .map_err(|e| format!("Failed to serialize turn checkpoint: {}", e))?;
+
+    // Write then rename so a crash mid-write never leaves a truncated checkpoint
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, contents)
+        .await
+        .map_err(|e| format!("Failed to write turn checkpoint: {}", e))?;
+    fs::rename(&tmp_path, path)
+        .await
+        .map_err(|e| format!("Failed to write turn checkpoint: {}", e))
+}
+
+/// Read the persisted checkpoint, if there is a readable one
+pub async fn load_checkpoint(path: &Path) -> Option<TurnCheckpoint> {
+    let contents = fs::read_to_string(path).await.ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(checkpoint) => Some(checkpoint),
+        Err(e) => {
+            println!("[TurnCheckpoint] Ignoring unreadable checkpoint {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Remove the persisted checkpoint (missing is fine)
+pub async fn clear_checkpoint(path: &Path) {
+    let _ = fs::remove_file(path).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint() -> TurnCheckpoint {
+        TurnCheckpoint {
+            request: ChatTurnRequest {
+                chat_id: Some("chat-1".to_string()),
+                message: "How many orders?".to_string(),
+                model: "phi-4".to_string(),
+                attached_tools: vec!["builtin::sql_select".to_string()],
+                ..Default::default()
+            },
+            state: AgenticState::SqlResultCommentary {
+                results_shown_to_user: true,
+                row_count: 3,
+                query_context: "3 rows returned".to_string(),
+            },
+            tool_search: None,
+            schema_search: None,
+            full_history: vec![ChatMessage {
+                role: "user".to_string(),
+                content: "How many orders?".to_string(),
+                ..Default::default()
+            }],
+            stored_history: Vec::new(),
+            iteration: 2,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join(CHECKPOINT_FILE_NAME);
+
+        save_checkpoint(&path, &checkpoint()).await.unwrap();
+        let loaded = load_checkpoint(&path).await.unwrap();
+
+        assert_eq!(loaded.request.chat_id.as_deref(), Some("chat-1"));
+        assert_eq!(loaded.iteration, 2);
+        assert_eq!(loaded.full_history.len(), 1);
+        assert!(matches!(
+            loaded.state,
+            AgenticState::SqlResultCommentary { row_count: 3, .. }
+        ));
+
+        clear_checkpoint(&path).await;
+        assert!(load_checkpoint(&path).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_checkpoint_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CHECKPOINT_FILE_NAME);
+        fs::write(&path, "{not json").await.unwrap();
+
+        assert!(load_checkpoint(&path).await.is_none());
+    }
+
+    #[test]
+    fn test_rerun_of_replays_last_user_message() {
+        let message = |role: &str, content: &str| ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        };
+        let mut last_question = message("user", "And in Bergen?");
+        last_question.images = vec!["data:image/png;base64,AAAA".to_string()];
+        let messages = vec![
+            message("user", "Weather in Oslo?"),
+            message("assistant", "Sunny."),
+            last_question,
+            // The turn's stored tool traffic is not a user message to replay
+            ChatMessage {
+                intermediate: true,
+                ..message("user", "<tool_response>Rain</tool_response>")
+            },
+            message("assistant", "Rainy."),
+        ];
+
+        let request = ChatTurnRequest::rerun_of(&messages, "qwen2.5-7b", "low").unwrap();
+
+        assert_eq!(request.message, "And in Bergen?");
+        assert_eq!(request.images, vec!["data:image/png;base64,AAAA".to_string()]);
+        let history: Vec<&str> = request.history.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(history, vec!["Weather in Oslo?", "Sunny."]);
+        assert_eq!(request.model, "qwen2.5-7b");
+        assert_eq!(request.reasoning_effort, "low");
+        // The caller assigns the forked chat's id
+        assert!(request.chat_id.is_none());
+
+        let no_question = vec![message("assistant", "Hello!")];
+        assert!(ChatTurnRequest::rerun_of(&no_question, "phi-4", "low").is_err());
+    }
+}
@@ -0,0 +1,243 @@
+//! Warm start: connect MCP servers and preload the model at launch.
+//!
+//! Without it the first chat message pays for spawning MCP servers, embedding
+//! their tools and loading the model into memory. When `warm_start_on_launch`
+//! is on (or the `warm_start` command is called) those stages run in the
+//! background after the window opens, reporting each step with a
+//! `warm-start-progress` event.
+
+use fastembed::TextEmbedding;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{mpsc, oneshot, RwLock};
+
+use crate::commands::mcp::sync_and_register_tools;
+use crate::protocol::{FoundryMsg, McpHostMsg};
+use crate::settings::{AppSettings, McpServerConfig};
+use crate::tool_capability::ToolLaunchFilter;
+use crate::tool_registry::SharedToolRegistry;
+use crate::tools::tool_search::precompute_tool_search_embeddings;
+
+/// Event emitted as each warm start stage starts and finishes
+pub const WARM_START_EVENT: &str = "warm-start-progress";
+
+/// Delay before a launch warm start, so window setup and the startup handshake go first
+pub const LAUNCH_DELAY_MS: u64 = 1500;
+
+/// How long to wait for the embedding model to finish loading
+const EMBEDDING_WAIT_SECS: u64 = 120;
+
+const EMBEDDING_POLL_MS: u64 = 500;
+
+/// Warm start stages, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarmStartStage {
+    McpServers,
+    ToolEmbeddings,
+    Model,
+}
+
+impl WarmStartStage {
+    pub const ALL: [WarmStartStage; 3] = [
+        WarmStartStage::McpServers,
+        WarmStartStage::ToolEmbeddings,
+        WarmStartStage::Model,
+    ];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarmStartStatus {
+    Started,
+    Done,
+    Skipped,
+    Failed,
+}
+
+/// Payload of `warm-start-progress`
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmStartProgress {
+    pub stage: WarmStartStage,
+    pub status: WarmStartStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Why a stage has nothing to do, or None when it should run
+pub fn skip_reason(
+    stage: WarmStartStage,
+    configs: &[McpServerConfig],
+    selected_model: Option<&str>,
+) -> Option<&'static str> {
+    match stage {
+        WarmStartStage::McpServers | WarmStartStage::ToolEmbeddings => {
+            (!configs.iter().any(|c| c.enabled)).then_some("No MCP servers are enabled")
+        }
+        WarmStartStage::Model => selected_model
+            .is_none_or(|m| m.trim().is_empty())
+            .then_some("No model selected"),
+    }
+}
+
+fn emit_progress(
+    app_handle: &AppHandle,
+    stage: WarmStartStage,
+    status: WarmStartStatus,
+    message: Option<String>,
+) -> WarmStartProgress {
+    let progress = WarmStartProgress {
+        stage,
+        status,
+        message,
+    };
+    println!(
+        "[WarmStart] {:?} {:?}{}",
+        stage,
+        status,
+        progress
+            .message
+            .as_deref()
+            .map(|m| format!(": {}", m))
+            .unwrap_or_default()
+    );
+    let _ = app_handle.emit(WARM_START_EVENT, &progress);
+    progress
+}
+
+/// Wait for the embedding model, which loads in the background after launch
+async fn wait_for_embedding_model(
+    embedding_model: &Arc<RwLock<Option<Arc<TextEmbedding>>>>,
+) -> bool {
+    let attempts = EMBEDDING_WAIT_SECS * 1000 / EMBEDDING_POLL_MS;
+    for _ in 0..attempts {
+        if embedding_model.read().await.is_some() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(EMBEDDING_POLL_MS)).await;
+    }
+    embedding_model.read().await.is_some()
+}
+
+async fn embed_tools(
+    registry: &SharedToolRegistry,
+    embedding_model: &Arc<RwLock<Option<Arc<TextEmbedding>>>>,
+) -> Result<String, String> {
+    if !wait_for_embedding_model(embedding_model).await {
+        return Err("Embedding model did not finish loading".to_string());
+    }
+    precompute_tool_search_embeddings(registry.clone(), embedding_model.clone())
+        .await
+        .map(|count| format!("{} tools embedded", count))
+}
+
+async fn load_model(foundry_tx: &mpsc::Sender<FoundryMsg>, model: &str) -> Result<String, String> {
+    let (tx, rx) = oneshot::channel();
+    foundry_tx
+        .send(FoundryMsg::LoadModel {
+            model_name: model.to_string(),
+            respond_to: tx,
+        })
+        .await
+        .map_err(|e| format!("Failed to send load request: {}", e))?;
+    rx.await
+        .map_err(|_| "Foundry actor died".to_string())?
+        .map(|_| format!("Loaded {}", model))
+}
+
+/// Run every warm start stage, emitting progress as it goes. A failed stage is
+/// reported and the rest still run. Returns the final status of each stage.
+pub async fn run_warm_start(
+    app_handle: AppHandle,
+    mcp_host_tx: mpsc::Sender<McpHostMsg>,
+    foundry_tx: mpsc::Sender<FoundryMsg>,
+    settings: Arc<RwLock<AppSettings>>,
+    tool_filter: ToolLaunchFilter,
+    registry: SharedToolRegistry,
+    embedding_model: Arc<RwLock<Option<Arc<TextEmbedding>>>>,
+) -> Vec<WarmStartProgress> {
+    let (configs, selected_model, description_overrides) = {
+        let settings = settings.read().await;
+        (
+            settings.get_all_mcp_configs(),
+            settings.selected_model.clone(),
+            settings.tool_description_overrides.clone(),
+        )
+    };
+    let model = selected_model.as_deref().unwrap_or_default();
+
+    let mut results = Vec::new();
+    for stage in WarmStartStage::ALL {
+        if let Some(reason) = skip_reason(stage, &configs, selected_model.as_deref()) {
+            results.push(emit_progress(
+                &app_handle,
+                stage,
+                WarmStartStatus::Skipped,
+                Some(reason.to_string()),
+            ));
+            continue;
+        }
+
+        emit_progress(&app_handle, stage, WarmStartStatus::Started, None);
+        let outcome = match stage {
+            WarmStartStage::McpServers => sync_and_register_tools(
+                &mcp_host_tx,
+                &configs,
+                &tool_filter,
+                &description_overrides,
+                &registry,
+            )
+            .await
+            .map(|count| format!("{} servers registered tools", count)),
+            WarmStartStage::ToolEmbeddings => embed_tools(&registry, &embedding_model).await,
+            WarmStartStage::Model => load_model(&foundry_tx, model).await,
+        };
+        results.push(match outcome {
+            Ok(message) => emit_progress(&app_handle, stage, WarmStartStatus::Done, Some(message)),
+            Err(e) => emit_progress(&app_handle, stage, WarmStartStatus::Failed, Some(e)),
+        });
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::default_mcp_test_server;
+
+    #[test]
+    fn test_skip_reason() {
+        let mut server = default_mcp_test_server();
+        server.enabled = true;
+        let enabled = vec![server.clone()];
+        server.enabled = false;
+        let disabled = vec![server];
+
+        for stage in WarmStartStage::ALL {
+            assert_eq!(skip_reason(stage, &enabled, Some("phi-4")), None);
+        }
+        assert!(skip_reason(WarmStartStage::McpServers, &disabled, Some("phi-4")).is_some());
+        assert!(skip_reason(WarmStartStage::ToolEmbeddings, &[], Some("phi-4")).is_some());
+        assert_eq!(
+            skip_reason(WarmStartStage::Model, &disabled, Some("phi-4")),
+            None
+        );
+        assert!(skip_reason(WarmStartStage::Model, &enabled, None).is_some());
+        assert!(skip_reason(WarmStartStage::Model, &enabled, Some(" ")).is_some());
+    }
+
+    #[test]
+    fn test_progress_serialization() {
+        let progress = WarmStartProgress {
+            stage: WarmStartStage::ToolEmbeddings,
+            status: WarmStartStatus::Skipped,
+            message: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&progress).unwrap(),
+            serde_json::json!({"stage": "tool_embeddings", "status": "skipped"})
+        );
+    }
+}
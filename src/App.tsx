@@ -1,6 +1,7 @@
 import { invoke } from "@tauri-apps/api/core";
 import { useEffect, useRef } from "react";
 import type { ReasoningEffort } from "./store/chat-store";
+import type { TurnMetrics } from "./lib/tool-calls";
 import { Sidebar } from "./components/Sidebar";
 import { ChatArea } from "./components/ChatArea";
 import { SettingsModal } from "./components/settings";
@@ -315,6 +316,8 @@ function App() {
       finished: boolean;
       had_tool_calls: boolean;
       timestamp_ms: number;
+      metrics: TurnMetrics | null;
+      resumable: boolean;
     };
 
     const reconcileFromBackend = async () => {
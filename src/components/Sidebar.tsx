@@ -1,7 +1,7 @@
 import { useChatStore } from '../store/chat-store';
 import { useSettingsStore } from '../store/settings-store';
 import { useEffect, useState, useRef } from 'react';
-import { MoreHorizontal, Pin, Trash, Edit, MessageSquare, Plus, Search, Loader2, Settings } from 'lucide-react';
+import { MoreHorizontal, Pin, Trash, Edit, MessageSquare, Plus, Search, Loader2, Settings, RotateCcw } from 'lucide-react';
 import { invoke } from '@tauri-apps/api/core';
 
 type SidebarProps = {
@@ -22,6 +22,8 @@ type ChatItemProps = {
     onRenameSubmit: (id: string) => void;
     onStartEdit: (id: string, title: string) => void;
     onTogglePin: (id: string) => void;
+    onRerun: (id: string) => void;
+    rerunModel: string;
     onDelete: (id: string) => Promise<void>;
 };
 
@@ -39,6 +41,8 @@ function ChatItem({
     onRenameSubmit,
     onStartEdit,
     onTogglePin,
+    onRerun,
+    rerunModel,
     onDelete,
 }: ChatItemProps) {
     return (
@@ -106,6 +110,17 @@ function ChatItem({
                         >
                             <Pin size={12} /> {chat.pinned ? 'Unpin' : 'Pin'}
                         </button>
+                        <button
+                            onClick={() => {
+                                onRerun(chat.id);
+                                onMenuToggle(null);
+                            }}
+                            disabled={!rerunModel}
+                            title={rerunModel ? `Re-run the last message with ${rerunModel} in a new chat` : undefined}
+                            className="flex items-center gap-2 px-3 py-2 text-xs text-gray-700 hover:bg-gray-100 w-full text-left disabled:opacity-50"
+                        >
+                            <RotateCcw size={12} /> Re-run
+                        </button>
                         <div className="h-px bg-gray-100 my-1"></div>
                         <button
                             onClick={async (e) => {
@@ -127,8 +142,8 @@ function ChatItem({
 
 export function Sidebar({ className = "" }: SidebarProps) {
     const {
-        history, fetchHistory, loadChat, deleteChat, renameChat, togglePin, currentChatId,
-        relevanceResults, isSearchingRelevance, chatInputValue
+        history, fetchHistory, loadChat, deleteChat, renameChat, togglePin, rerunTurn, currentChatId,
+        currentModel, relevanceResults, isSearchingRelevance, chatInputValue
     } = useChatStore();
 
     const [editingId, setEditingId] = useState<string | null>(null);
@@ -178,6 +193,15 @@ export function Sidebar({ className = "" }: SidebarProps) {
         }
     };
 
+    // Re-run the chat's last message with the selected model; the store switches to the fork
+    const handleRerun = async (id: string) => {
+        try {
+            await rerunTurn(id, currentModel);
+        } catch (err) {
+            await invoke('log_to_terminal', { message: `[Sidebar] rerunTurn ERROR: ${err}` });
+        }
+    };
+
     const handleStartEdit = (id: string, title: string) => {
         setEditTitle(title);
         setEditingId(id);
@@ -236,6 +260,8 @@ export function Sidebar({ className = "" }: SidebarProps) {
                                     onRenameSubmit={handleRenameSubmit}
                                     onStartEdit={handleStartEdit}
                                     onTogglePin={togglePin}
+                                    onRerun={handleRerun}
+                                    rerunModel={currentModel}
                                     onDelete={handleDelete}
                                 />
                             ))}
@@ -273,6 +299,8 @@ export function Sidebar({ className = "" }: SidebarProps) {
                                     onRenameSubmit={handleRenameSubmit}
                                     onStartEdit={handleStartEdit}
                                     onTogglePin={togglePin}
+                                    onRerun={handleRerun}
+                                    rerunModel={currentModel}
                                     onDelete={handleDelete}
                                 />
                             ))
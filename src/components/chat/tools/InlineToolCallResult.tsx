@@ -99,6 +99,14 @@ export const InlineToolCallResult = ({ call }: InlineToolCallResultProps) => {
                         {call.durationMs && (
                             <span className="text-xs text-gray-400">{formatMillisecondsAsDuration(call.durationMs)}</span>
                         )}
+                        {call.truncatedFrom && (
+                            <span
+                                className="text-xs px-1.5 py-0.5 rounded bg-amber-100 text-amber-700"
+                                title={`The model received a shortened copy of this ${call.truncatedFrom.toLocaleString()}-character result`}
+                            >
+                                Result truncated
+                            </span>
+                        )}
                     </div>
                     {/* Show arguments - always visible for errors so users can debug */}
                     <details 
@@ -518,6 +518,22 @@ export function DatabasesTab({
                                 </div>
                             </div>
                         )}
+
+                        <div>
+                            <label className="block text-xs font-medium text-gray-700 mb-1">
+                                Skip table pattern (regex, optional)
+                            </label>
+                            <input
+                                type="text"
+                                value={source.skip_table_pattern || ''}
+                                onChange={(e) => updateSource(idx, { skip_table_pattern: e.target.value })}
+                                placeholder="_\d{8}$"
+                                className="w-full text-sm border-gray-300 rounded-md shadow-sm focus:border-blue-500 focus:ring-blue-500"
+                            />
+                            <p className="text-[11px] text-gray-500 mt-1">
+                                Datasets and tables matching this are not cached. Leave blank to skip date shards like events_20240101.
+                            </p>
+                        </div>
                     </div>
                 ))}
 
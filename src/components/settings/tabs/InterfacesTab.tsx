@@ -1,6 +1,17 @@
 import { useState, useEffect, useCallback } from 'react';
 import { useSettingsStore, DEFAULT_TOOL_CALL_FORMATS, type ToolCallFormatConfig, type ToolCallFormatName, type ChatFormatName } from '../../../store/settings-store';
 import { useChatStore } from '../../../store/chat-store';
+import { invoke } from '../../../lib/api';
+
+/** Tool calling format metadata from `get_available_tool_call_formats` */
+interface ToolCallFormatInfo {
+    id: ToolCallFormatName;
+    display_name: string;
+    description: string;
+    example: string;
+    requires_python_execution: boolean;
+    requires_native_support: boolean;
+}
 
 interface InterfacesTabProps {
     onDirtyChange?: (dirty: boolean) => void;
@@ -17,17 +28,26 @@ export function InterfacesTab({
     const currentModel = useChatStore((state) => state.currentModel);
     const availableModels = useChatStore((state) => state.availableModels);
     const formatConfig = settings?.tool_call_formats || DEFAULT_TOOL_CALL_FORMATS;
-    const formatOptions: { id: ToolCallFormatName; label: string; description: string }[] = [
-        { id: 'native', label: 'Native (OpenAI API)', description: 'Use the model\'s native tool calling via the `tools` API parameter (recommended for supported models).' },
-        { id: 'code_mode', label: 'Code Mode (Python)', description: 'Model returns a single Python program executed in the sandbox.' },
-        { id: 'hermes', label: 'Hermes (tag-delimited)', description: '<tool_call>{"name": "...", "arguments": {...}}</tool_call>' },
-        { id: 'mistral', label: 'Mistral (bracket)', description: '[TOOL_CALLS] [{"name": "...", "arguments": {...}}]' },
-        { id: 'pythonic', label: 'Pythonic call', description: 'tool_name(arg1="value", arg2=123)' },
-        { id: 'pure_json', label: 'Pure JSON', description: '{"tool": "name", "args": {...}} or array' },
-    ];
+    // The backend describes every format; until it answers, show the ids alone
+    const [formatInfos, setFormatInfos] = useState<ToolCallFormatInfo[]>([]);
+    useEffect(() => {
+        invoke<ToolCallFormatInfo[]>('get_available_tool_call_formats')
+            .then(setFormatInfos)
+            .catch((e) => console.error('[InterfacesTab] Failed to load tool call formats:', e));
+    }, []);
+    const formatOptions: { id: ToolCallFormatName; label: string; description: string; example: string }[] =
+        formatInfos.length > 0
+            ? formatInfos.map((info) => ({
+                id: info.id,
+                label: info.display_name,
+                description: info.description,
+                example: info.example,
+            }))
+            : formatConfig.enabled.map((id) => ({ id, label: id, description: '', example: '' }));
     const chatFormatOptions: { id: ChatFormatName; label: string; description: string }[] = [
         { id: 'openai_completions', label: 'OpenAI Chat Completions', description: 'POST /v1/chat/completions (messages array).' },
         { id: 'openai_responses', label: 'OpenAI Responses', description: 'POST /v1/responses (input blocks). Requires endpoint/model support.' },
+        { id: 'anthropic_messages', label: 'Anthropic Messages', description: 'POST /v1/messages (content blocks). Native tool calls become tool_use blocks.' },
     ];
 
     const chatFormatDefault = settings?.chat_format_default ?? 'openai_completions';
@@ -147,7 +167,10 @@ export function InterfacesTab({
                                     />
                                     <div>
                                         <div className="text-sm font-medium text-gray-900">{option.label}</div>
-                                        <p className="text-xs text-gray-500 font-mono">{option.description}</p>
+                                        <p className="text-xs text-gray-500">{option.description}</p>
+                                        {option.example && (
+                                            <p className="text-xs text-gray-500 font-mono whitespace-pre-wrap">{option.example}</p>
+                                        )}
                                     </div>
                                 </div>
                                 <label className={`flex items-center gap-2 text-xs ${enabled ? 'text-gray-700' : 'text-gray-400'}`}>
@@ -41,7 +41,7 @@ export interface MessagePart {
 /**
  * Model family identifiers for response format detection
  */
-export type ModelFamily = 'gpt_oss' | 'phi' | 'gemma' | 'granite' | 'generic';
+export type ModelFamily = 'gpt_oss' | 'phi' | 'qwen' | 'gemma' | 'granite' | 'generic';
 
 /**
  * Detect the response format from content patterns
@@ -494,6 +494,7 @@ export function parseMessageContent(content: string, modelFamily?: ModelFamily):
             parts = parseChannelFormat(cleanedContent);
             break;
         case 'phi':
+        case 'qwen':
             parts = parseThinkFormat(cleanedContent);
             break;
         case 'granite':
@@ -75,6 +75,12 @@ export interface ToolResultEvent {
     tool: string;
     result: string;
     is_error: boolean;
+    /** Full length in chars when the model only saw a truncated copy */
+    original_length?: number;
+    /** Built-in tool whose code made this call (e.g. python_execution) */
+    caller?: string;
+    /** Arguments of a call made by a caller */
+    arguments?: Record<string, unknown>;
 }
 
 export interface ToolLoopFinishedEvent {
@@ -82,6 +88,16 @@ export interface ToolLoopFinishedEvent {
     had_tool_calls: boolean;
 }
 
+/** Timing summary emitted as `turn-metrics` at the end of each turn */
+export interface TurnMetrics {
+    /** Model calls made during the turn */
+    iterations: number;
+    iteration_metrics: { tokens: number; ttft_ms: number | null; duration_ms: number }[];
+    /** Durations in ms of each tool call, keyed by `server::tool` */
+    tool_durations_ms: Record<string, number[]>;
+    total_ms: number;
+}
+
 // Detect tool calls in content
 export async function detectToolCalls(content: string): Promise<ParsedToolCall[]> {
     try {
@@ -166,6 +182,28 @@ export async function rejectToolCall(approvalKey: string): Promise<boolean> {
     }
 }
 
+// Cancel a pending tool approval (e.g. its turn was aborted)
+export async function cancelPendingApproval(approvalKey: string): Promise<boolean> {
+    try {
+        const result = await invoke<boolean>('cancel_pending_approval', { approvalKey });
+        return result;
+    } catch (e) {
+        console.error('[ToolCalls] Failed to cancel pending approval:', e);
+        return false;
+    }
+}
+
+// Approve a pending tool batch, optionally replacing its calls with edited ones
+export async function approveToolBatch(approvalKey: string, calls?: ParsedToolCall[]): Promise<boolean> {
+    try {
+        const result = await invoke<boolean>('approve_tool_batch', { approvalKey, calls: calls ?? null });
+        return result;
+    } catch (e) {
+        console.error('[ToolCalls] Failed to approve tool batch:', e);
+        return false;
+    }
+}
+
 // Get list of pending tool approvals
 export async function getPendingToolApprovals(): Promise<string[]> {
     try {
@@ -176,3 +214,37 @@ export async function getPendingToolApprovals(): Promise<string[]> {
     }
 }
 
+// Result of checking Python code without running it
+export interface PythonValidationResult {
+    success: boolean;
+    stderr: string;
+}
+
+// Check Python code the way python_execution would, without executing it
+export async function validatePythonCode(code: string[]): Promise<PythonValidationResult> {
+    try {
+        return await invoke<PythonValidationResult>('validate_python_code', { code });
+    } catch (e) {
+        console.error('[ToolCalls] Failed to validate Python code:', e);
+        return { success: false, stderr: String(e) };
+    }
+}
+
+
+// Output of a standalone tool_search run (scores included, nothing materialized)
+export interface ToolSearchRunOutput {
+    tools: Array<{
+        name: string;
+        description?: string | null;
+        score: number;
+        server_id: string;
+        parameters: JSONSchema;
+    }>;
+    queries_used: string[];
+    python_docs: string;
+}
+
+// Run tool_search outside a chat turn, for tuning discovery
+export async function runToolSearch(queries: string[], topK: number): Promise<ToolSearchRunOutput> {
+    return await invoke<ToolSearchRunOutput>('run_tool_search', { queries, topK });
+}
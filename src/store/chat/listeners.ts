@@ -5,6 +5,7 @@ import {
     ToolExecutingEvent, 
     ToolResultEvent, 
     ToolLoopFinishedEvent,
+    TurnMetrics,
 } from '../../lib/tool-calls';
 import type { 
     ChatSummary, 
@@ -27,15 +28,22 @@ let unlistenToken: (() => void) | undefined;
 let unlistenFinished: (() => void) | undefined;
 let unlistenChatError: (() => void) | undefined;
 let unlistenChatWarning: (() => void) | undefined;
+let unlistenChatRetry: (() => void) | undefined;
+let unlistenEmptyResponse: (() => void) | undefined;
 let unlistenModelSelected: (() => void) | undefined;
 let unlistenToolBlocked: (() => void) | undefined;
 let unlistenChatSaved: (() => void) | undefined;
 let unlistenSidebarUpdate: (() => void) | undefined;
 let unlistenToolCallsPending: (() => void) | undefined;
+let unlistenToolBatchPending: (() => void) | undefined;
 let unlistenToolExecuting: (() => void) | undefined;
 let unlistenToolHeartbeat: (() => void) | undefined;
+let unlistenPythonStdoutChunk: (() => void) | undefined;
+let unlistenToolTimeout: (() => void) | undefined;
+let unlistenToolCancelled: (() => void) | undefined;
 let unlistenToolResult: (() => void) | undefined;
 let unlistenToolLoopFinished: (() => void) | undefined;
+let unlistenTurnMetrics: (() => void) | undefined;
 let unlistenDownloadProgress: (() => void) | undefined;
 let unlistenLoadComplete: (() => void) | undefined;
 let unlistenServiceStopStarted: (() => void) | undefined;
@@ -430,6 +438,37 @@ export const createListenerSlice: StateCreator<
                 }, 5000);
             });
 
+            const chatRetryListener = await listen<{ attempt: number; max_attempts: number; delay_ms: number; reason: string }>('chat-retry', (event) => {
+                const { attempt, max_attempts, reason } = event.payload;
+                console.warn(`[ChatStore] chat-retry: attempt ${attempt}/${max_attempts} (${reason})`);
+                set({
+                    operationStatus: {
+                        type: 'streaming',
+                        message: `Reconnecting to model… (attempt ${attempt} of ${max_attempts})`,
+                        startTime: Date.now(),
+                    },
+                    statusBarDismissed: false,
+                    lastStreamActivityTs: Date.now(),
+                } as any);
+            });
+
+            const emptyResponseListener = await listen<{ iteration: number; will_retry: boolean; model_state: { state: string } | null }>('empty-response', (event) => {
+                const { will_retry, model_state } = event.payload;
+                const state = model_state?.state ?? 'unknown';
+                console.warn(`[ChatStore] empty-response: model produced no tokens (state=${state}, will_retry=${will_retry})`);
+                logToBackend(`[FRONTEND] empty-response: state=${state}, will_retry=${will_retry}`);
+                set({
+                    operationStatus: {
+                        type: 'streaming',
+                        message: will_retry
+                            ? 'Model produced no output, retrying…'
+                            : `Model produced no output (model state: ${state})`,
+                        startTime: Date.now(),
+                    },
+                    statusBarDismissed: false,
+                } as any);
+            });
+
             // Chat stream status listener
             const chatStreamStatusListener = await listen<{ phase: string; message: string; time_to_first_response_ms?: number }>('chat-stream-status', (event) => {
                 const { phase, message } = event.payload;
@@ -477,7 +516,7 @@ export const createListenerSlice: StateCreator<
                 });
             });
             
-            const systemPromptListener = await listen<{ chat_id?: string; generation_id?: number; prompt: string }>('system-prompt', (event) => {
+            const systemPromptListener = await listen<{ chat_id?: string; generation_id?: number; prompt: string; omitted_tools?: number }>('system-prompt', (event) => {
                 set((state) => {
                     const prompt = event.payload?.prompt;
                     if (!prompt) return state;
@@ -920,6 +959,18 @@ export const createListenerSlice: StateCreator<
                 } as any);
             });
 
+            // Batch approval mode: one decision for every call of the iteration
+            const toolBatchPendingListener = await listen<ToolCallsPendingEvent>('tool-batch-pending', (event) => {
+                console.log(`[ChatStore] Tool batch pending: ${event.payload.approval_key}`, event.payload.calls);
+                set({
+                    pendingToolApproval: {
+                        approvalKey: event.payload.approval_key,
+                        calls: event.payload.calls,
+                        iteration: event.payload.iteration,
+                    }
+                } as any);
+            });
+
             const toolExecutingListener = await listen<ToolExecutingEvent>('tool-executing', (event) => {
                 const { server, tool, arguments: payloadArgs } = event.payload;
                 const toolName = tool;
@@ -937,6 +988,9 @@ export const createListenerSlice: StateCreator<
                     ? 'Searching for tools...'
                     : `Executing ${toolName}...`;
                 const scheduleUpdate = () => set((state) => ({
+                    codeExecution: toolName === 'python_execution'
+                        ? { ...state.codeExecution, isRunning: true, stdout: '' }
+                        : state.codeExecution,
                     toolExecution: {
                         ...state.toolExecution,
                         currentTool: { 
@@ -945,6 +999,7 @@ export const createListenerSlice: StateCreator<
                             arguments: payloadArgs,
                             startTime: Date.now(),
                         },
+                        lastProgress: undefined,
                     },
                     operationStatus: {
                         type: 'streaming',
@@ -962,7 +1017,7 @@ export const createListenerSlice: StateCreator<
                 }
             });
 
-            const toolHeartbeatListener = await listen<{ server: string; tool: string; elapsed_ms: number; beat: number }>('tool-heartbeat', (event) => {
+            const toolHeartbeatListener = await listen<{ server: string; tool: string; elapsed_ms: number; beat: number; progress?: string }>('tool-heartbeat', (event) => {
                 set((state) => {
                     const current = state.toolExecution.currentTool;
                     if (!current) return state;
@@ -973,14 +1028,74 @@ export const createListenerSlice: StateCreator<
                         toolExecution: {
                             ...state.toolExecution,
                             lastHeartbeatTs: Date.now(),
+                            lastProgress: event.payload.progress ?? state.toolExecution.lastProgress,
                         },
                         lastStreamActivityTs: Date.now(),
                     } as any;
                 });
             });
 
+            const pythonStdoutChunkListener = await listen<{ exec_id: string; chunk: string }>('python-stdout-chunk', (event) => {
+                set((state) => ({
+                    codeExecution: {
+                        ...state.codeExecution,
+                        stdout: state.codeExecution.stdout + event.payload.chunk,
+                    },
+                    lastStreamActivityTs: Date.now(),
+                } as any));
+            });
+
+            const toolTimeoutListener = await listen<{ server: string; tool: string; timeout_secs: number }>('tool-timeout', (event) => {
+                const { server, tool, timeout_secs } = event.payload;
+                console.warn(`[ChatStore] Tool timed out after ${timeout_secs}s: ${server}::${tool}`);
+                set((state) => ({
+                    toolExecution: {
+                        ...state.toolExecution,
+                        lastTimeout: { server, tool, timeoutSecs: timeout_secs },
+                    },
+                } as any));
+            });
+
+            const toolCancelledListener = await listen<{ server: string; tool: string }>('tool-cancelled', (event) => {
+                const { server, tool } = event.payload;
+                console.log(`[ChatStore] Tool cancelled: ${server}::${tool}`);
+                set((state) => ({
+                    codeExecution: tool === 'python_execution'
+                        ? { ...state.codeExecution, isRunning: false, success: false }
+                        : state.codeExecution,
+                    toolExecution: {
+                        ...state.toolExecution,
+                        currentTool: null,
+                    },
+                } as any));
+            });
+
             const toolResultListener = await listen<ToolResultEvent>('tool-result', (event) => {
                 console.log(`[ChatStore] Tool result: ${event.payload.server}::${event.payload.tool}, error=${event.payload.is_error}`);
+                // Calls made from python_execution code: record them, but the caller is still running
+                if (event.payload.caller) {
+                    set((state) => {
+                        const newMessages = [...state.chatMessages];
+                        const lastIdx = newMessages.length - 1;
+                        if (lastIdx >= 0 && newMessages[lastIdx].role === 'assistant') {
+                            const innerCall: ToolCallRecord = {
+                                id: `tool-${Date.now()}-${Math.random().toString(36).substr(2, 9)}`,
+                                server: event.payload.server,
+                                tool: event.payload.tool,
+                                arguments: event.payload.arguments || {},
+                                result: event.payload.result,
+                                isError: event.payload.is_error,
+                                caller: event.payload.caller,
+                            };
+                            newMessages[lastIdx] = {
+                                ...newMessages[lastIdx],
+                                toolCalls: [...(newMessages[lastIdx].toolCalls || []), innerCall],
+                            };
+                        }
+                        return { chatMessages: newMessages } as any;
+                    });
+                    return;
+                }
                 set((state) => {
                     const startTime = state.toolExecution.currentTool?.startTime;
                     const durationMs = startTime ? Date.now() - startTime : undefined;
@@ -993,6 +1108,7 @@ export const createListenerSlice: StateCreator<
                         result: event.payload.result,
                         isError: event.payload.is_error,
                         durationMs,
+                        truncatedFrom: event.payload.original_length,
                     };
                     
                     const newMessages = [...state.chatMessages];
@@ -1020,6 +1136,9 @@ export const createListenerSlice: StateCreator<
                     return {
                         chatMessages: newMessages,
                         operationStatus: newOperationStatus,
+                        codeExecution: event.payload.tool === 'python_execution'
+                            ? { ...state.codeExecution, isRunning: false, success: !event.payload.is_error }
+                            : state.codeExecution,
                         toolExecution: {
                             ...state.toolExecution,
                             currentTool: null,
@@ -1049,6 +1168,16 @@ export const createListenerSlice: StateCreator<
                 } as any));
             });
 
+            const turnMetricsListener = await listen<TurnMetrics>('turn-metrics', (event) => {
+                console.log(`[ChatStore] Turn metrics: ${event.payload.iterations} model calls, ${event.payload.total_ms}ms total`);
+                set((state) => ({
+                    toolExecution: {
+                        ...state.toolExecution,
+                        lastTurnMetrics: event.payload,
+                    },
+                } as any));
+            });
+
             // Service restart listeners
             const serviceStopStartedListener = await listen<{ message: string }>('service-stop-started', (event) => {
                 console.log(`[ChatStore] Service stop started: ${event.payload.message}`);
@@ -1160,6 +1289,8 @@ export const createListenerSlice: StateCreator<
                 finishedListener();
                 chatErrorListener();
                 chatWarningListener();
+                chatRetryListener();
+                emptyResponseListener();
                 modelSelectedListener();
                 modelStuckListener();
                 modelFallbackListener();
@@ -1167,9 +1298,11 @@ export const createListenerSlice: StateCreator<
                 chatSavedListener();
                 sidebarUpdateListener();
                 toolCallsPendingListener();
+                toolBatchPendingListener();
                 toolExecutingListener();
                 toolResultListener();
                 toolLoopFinishedListener();
+                turnMetricsListener();
                 systemPromptListener();
                 downloadProgressListener();
                 loadCompleteListener();
@@ -1186,6 +1319,9 @@ export const createListenerSlice: StateCreator<
                 modelStateChangedListener();
                 startupProgressListener();
                 toolHeartbeatListener();
+                pythonStdoutChunkListener();
+                toolTimeoutListener();
+                toolCancelledListener();
                 isSettingUp = false;
                 return;
             }
@@ -1195,15 +1331,22 @@ export const createListenerSlice: StateCreator<
             unlistenFinished = finishedListener;
             unlistenChatError = chatErrorListener;
             unlistenChatWarning = chatWarningListener;
+            unlistenChatRetry = chatRetryListener;
+            unlistenEmptyResponse = emptyResponseListener;
             unlistenChatStreamStatus = chatStreamStatusListener;
             unlistenModelSelected = modelSelectedListener;
             unlistenModelStateChanged = modelStateChangedListener;
             unlistenToolBlocked = toolBlockedListener;
             unlistenToolCallsPending = toolCallsPendingListener;
+            unlistenToolBatchPending = toolBatchPendingListener;
             unlistenToolExecuting = toolExecutingListener;
             unlistenToolHeartbeat = toolHeartbeatListener;
+            unlistenPythonStdoutChunk = pythonStdoutChunkListener;
+            unlistenToolTimeout = toolTimeoutListener;
+            unlistenToolCancelled = toolCancelledListener;
             unlistenToolResult = toolResultListener;
             unlistenToolLoopFinished = toolLoopFinishedListener;
+            unlistenTurnMetrics = turnMetricsListener;
             unlistenSystemPrompt = systemPromptListener;
             unlistenChatSaved = chatSavedListener;
             unlistenSidebarUpdate = sidebarUpdateListener;
@@ -1246,6 +1389,8 @@ export const createListenerSlice: StateCreator<
         if (unlistenFinished) { unlistenFinished(); unlistenFinished = undefined; }
         if (unlistenChatError) { unlistenChatError(); unlistenChatError = undefined; }
         if (unlistenChatWarning) { unlistenChatWarning(); unlistenChatWarning = undefined; }
+        if (unlistenChatRetry) { unlistenChatRetry(); unlistenChatRetry = undefined; }
+        if (unlistenEmptyResponse) { unlistenEmptyResponse(); unlistenEmptyResponse = undefined; }
         if (unlistenModelSelected) { unlistenModelSelected(); unlistenModelSelected = undefined; }
         if (unlistenModelStateChanged) { unlistenModelStateChanged(); unlistenModelStateChanged = undefined; }
         if (unlistenToolBlocked) { unlistenToolBlocked(); unlistenToolBlocked = undefined; }
@@ -1254,10 +1399,15 @@ export const createListenerSlice: StateCreator<
         if (unlistenModelStuck) { unlistenModelStuck(); unlistenModelStuck = undefined; }
         if (unlistenModelFallback) { unlistenModelFallback(); unlistenModelFallback = undefined; }
         if (unlistenToolCallsPending) { unlistenToolCallsPending(); unlistenToolCallsPending = undefined; }
+        if (unlistenToolBatchPending) { unlistenToolBatchPending(); unlistenToolBatchPending = undefined; }
         if (unlistenToolExecuting) { unlistenToolExecuting(); unlistenToolExecuting = undefined; }
         if (unlistenToolHeartbeat) { unlistenToolHeartbeat(); unlistenToolHeartbeat = undefined; }
+        if (unlistenPythonStdoutChunk) { unlistenPythonStdoutChunk(); unlistenPythonStdoutChunk = undefined; }
+        if (unlistenToolTimeout) { unlistenToolTimeout(); unlistenToolTimeout = undefined; }
+        if (unlistenToolCancelled) { unlistenToolCancelled(); unlistenToolCancelled = undefined; }
         if (unlistenToolResult) { unlistenToolResult(); unlistenToolResult = undefined; }
         if (unlistenToolLoopFinished) { unlistenToolLoopFinished(); unlistenToolLoopFinished = undefined; }
+        if (unlistenTurnMetrics) { unlistenTurnMetrics(); unlistenTurnMetrics = undefined; }
         if (unlistenSystemPrompt) { unlistenSystemPrompt(); unlistenSystemPrompt = undefined; }
         if (unlistenDownloadProgress) { unlistenDownloadProgress(); unlistenDownloadProgress = undefined; }
         if (unlistenLoadComplete) { unlistenLoadComplete(); unlistenLoadComplete = undefined; }
@@ -1,16 +1,35 @@
 import type { StateCreator } from 'zustand';
 import { invoke } from '../../../lib/api';
-import type { ChatSummary, Message } from '../types';
+import type { ChatExportFormat, ChatSummary, Message, ReasoningEffort } from '../types';
 import { RELEVANCE_SEARCH_DEBOUNCE_MS, RELEVANCE_SEARCH_MIN_LENGTH } from '../constants';
+import { generateClientChatIdentifier, deriveChatPreviewFromMessage } from '../helpers';
 
 // Module-level state for relevance search
 let relevanceSearchTimeout: ReturnType<typeof setTimeout> | null = null;
 let relevanceSearchGeneration = 0; // Incremented on each new search to cancel stale results
 
+/** Messages of a stored chat as shown: tool calls and results stay in the
+ *  stored chat (the backend keeps them as history) but are not displayed */
+function toDisplayMessages(stored: any[]): Message[] {
+    return stored
+        .filter((m: any) => !m.intermediate)
+        .map((m: any, idx: number) => ({
+            ...m,
+            // Ensure messages have IDs if missing (legacy)
+            id: m.id || `${Date.now()}-${idx}`,
+            timestamp: m.timestamp || Date.now(),
+            systemPromptText: m.system_prompt || m.systemPromptText,
+            // An auto-saved partial answer whose turn never finished
+            content: m.provisional ? `${m.content}\n\n*(interrupted)*` : m.content,
+        }));
+}
+
 // Dependencies from other slices
 interface ChatHistorySliceDeps {
     chatMessages: Message[];
     currentModel: string;
+    reasoningEffort: ReasoningEffort;
+    assistantStreamingActive: boolean;
     streamingChatId: string | null;
     streamingMessages: Message[];
     setModel: (model: string) => Promise<void>;
@@ -37,6 +56,13 @@ export interface ChatHistorySlice {
     upsertHistoryEntry: (summary: ChatSummary) => void;
     renameChat: (id: string, newTitle: string) => Promise<void>;
     togglePin: (id: string) => Promise<void>;
+    /** Render a chat transcript (tool calls included) for saving */
+    exportChat: (id: string, format: ChatExportFormat) => Promise<string>;
+    /** Continue the last interrupted turn from its checkpoint; resolves to its chat id */
+    resumeTurn: () => Promise<string>;
+    /** Replay a chat's last message with another model in a new chat, switching
+     *  to it while it streams; resolves to its id */
+    rerunTurn: (chatId: string, model: string) => Promise<string>;
     
     // Relevance search (embedding-based autocomplete)
     relevanceResults: ChatSummary[] | null;
@@ -137,14 +163,7 @@ export const createChatHistorySlice: StateCreator<
             
             const messagesJson = await invoke<string | null>('load_chat', { id });
             if (messagesJson) {
-                const messages = JSON.parse(messagesJson);
-                // Ensure messages have IDs if missing (legacy)
-                const processedMessages = messages.map((m: any, idx: number) => ({
-                    ...m,
-                    id: m.id || `${Date.now()}-${idx}`,
-                    timestamp: m.timestamp || Date.now(),
-                    systemPromptText: m.system_prompt || m.systemPromptText,
-                }));
+                const processedMessages = toDisplayMessages(JSON.parse(messagesJson));
                 set({ chatMessages: processedMessages, currentChatId: id, backendError: null } as any);
             } else {
                 set({ chatMessages: [], currentChatId: id } as any);
@@ -222,6 +241,74 @@ export const createChatHistorySlice: StateCreator<
             console.error("Failed to toggle pin", e);
         }
     },
+    exportChat: (id, format) => invoke<string>('export_chat', { id, format }),
+    resumeTurn: () => invoke<string>('resume_turn'),
+    rerunTurn: async (chatId, model) => {
+        const state = get();
+        if (state.assistantStreamingActive) {
+            throw new Error('A response is already streaming');
+        }
+        const messagesJson = await invoke<string | null>('load_chat', { id: chatId });
+        const messages = toDisplayMessages(messagesJson ? JSON.parse(messagesJson) : []);
+        const lastUser = messages.map(m => m.role).lastIndexOf('user');
+        if (lastUser < 0) {
+            throw new Error('Chat has no user message to re-run');
+        }
+
+        // Switch to the fork: the conversation up to the replayed message and
+        // an empty answer that the new model's tokens stream into
+        const forkId = generateClientChatIdentifier();
+        const source = state.history.find(c => c.id === chatId);
+        const timestamp = Date.now();
+        state.upsertHistoryEntry({
+            id: forkId,
+            title: `${source?.title || 'Untitled Chat'} (${model})`,
+            preview: deriveChatPreviewFromMessage(messages[lastUser].content),
+            score: 0,
+            pinned: false,
+            model,
+        });
+        set({
+            chatMessages: [
+                ...messages.slice(0, lastUser + 1),
+                { id: `${timestamp}-rerun`, role: 'assistant', content: '', timestamp },
+            ],
+            currentChatId: forkId,
+            assistantStreamingActive: true,
+            streamingChatId: forkId,
+            operationStatus: {
+                type: 'streaming',
+                message: `Re-running with ${model}...`,
+                startTime: timestamp,
+            },
+            statusBarDismissed: false,
+            lastStreamActivityTs: timestamp,
+            backendError: null,
+        } as any);
+
+        try {
+            return await invoke<string>('rerun_turn', {
+                chatId,
+                model,
+                reasoningEffort: state.reasoningEffort,
+                newChatId: forkId,
+            });
+        } catch (error) {
+            console.error('[ChatStore] Failed to re-run turn:', error);
+            set((s) => {
+                const newMessages = [...s.chatMessages];
+                const lastIdx = newMessages.length - 1;
+                newMessages[lastIdx] = { ...newMessages[lastIdx], content: `Error: ${error}` };
+                return {
+                    chatMessages: newMessages,
+                    assistantStreamingActive: false,
+                    streamingChatId: null,
+                    operationStatus: null,
+                } as any;
+            });
+            throw error;
+        }
+    },
 
     // Relevance search (embedding-based autocomplete)
     relevanceResults: null,
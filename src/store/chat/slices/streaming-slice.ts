@@ -1,12 +1,14 @@
 import type { StateCreator } from 'zustand';
 import { invoke } from '../../../lib/api';
-import type { Message, OperationStatus } from '../types';
+import { cancelPendingApproval } from '../../../lib/tool-calls';
+import type { Message, OperationStatus, PendingToolApproval } from '../types';
 
 // Streaming slice needs to interact with operation status and message slices
 interface StreamingSliceDeps {
     operationStatus: OperationStatus | null;
     chatGenerationCounter: number;
     chatMessages: Message[];
+    pendingToolApproval: PendingToolApproval | null;
 }
 
 export interface StreamingSlice {
@@ -45,6 +47,14 @@ export const createStreamingSlice: StateCreator<
     stopActiveChatGeneration: async () => {
         console.log('[ChatStore] 🛑 STOP BUTTON PRESSED by user');
 
+        // Release a tool approval the stopped turn is waiting on
+        const pending = get().pendingToolApproval;
+        if (pending) {
+            console.log(`[ChatStore] Cancelling pending approval: ${pending.approvalKey}`);
+            set({ pendingToolApproval: null } as any);
+            await cancelPendingApproval(pending.approvalKey);
+        }
+
         // Increment generationId to ignore any incoming tokens from the stopped generation
         const currentGenId = get().chatGenerationCounter;
         console.log('[ChatStore] Current generation to cancel:', currentGenId);
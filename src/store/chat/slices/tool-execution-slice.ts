@@ -35,6 +35,8 @@ export const createToolExecutionSlice: StateCreator<
         totalIterations: 0,
         hadToolCalls: false,
         lastHeartbeatTs: undefined,
+        lastTimeout: null,
+        lastTurnMetrics: null,
     },
     
     approveCurrentToolCall: async () => {
@@ -1,4 +1,4 @@
-import type { ParsedToolCall } from '../../lib/tool-calls';
+import type { ParsedToolCall, TurnMetrics } from '../../lib/tool-calls';
 
 // ============ Basic Types ============
 
@@ -26,7 +26,7 @@ export interface CachedModel {
 }
 
 // Model family for format-specific handling
-export type ModelFamily = 'gpt_oss' | 'gemma' | 'phi' | 'granite' | 'generic';
+export type ModelFamily = 'gpt_oss' | 'gemma' | 'phi' | 'qwen' | 'granite' | 'generic';
 
 // Tool calling format supported by the model
 export type ToolFormat = 'openai' | 'hermes' | 'gemini' | 'granite' | 'text_based';
@@ -124,6 +124,9 @@ export interface ModelStateData {
 
 // ============ Chat & Message Types ============
 
+/** Output format for the export_chat command */
+export type ChatExportFormat = 'json' | 'markdown';
+
 export interface ChatSummary {
     id: string;
     title: string;
@@ -142,6 +145,10 @@ export interface ToolCallRecord {
     result: string;
     isError: boolean;
     durationMs?: number;
+    /** Full result length when the model received a truncated copy */
+    truncatedFrom?: number;
+    /** Built-in tool whose code made this call (e.g. python_execution) */
+    caller?: string;
 }
 
 // A code execution record for display
@@ -178,6 +185,10 @@ export interface Message {
     codeExecutions?: CodeExecutionRecord[];
     /** RAG chunks used as context for this assistant message */
     ragChunks?: RagChunk[];
+    /** Generation was stopped by the user; content is the partial response */
+    cancelled?: boolean;
+    /** Auto-saved while the turn was still running; the app stopped before it finished */
+    provisional?: boolean;
 }
 
 // ============ RAG Types ============
@@ -244,6 +255,12 @@ export interface ToolExecutionState {
     hadToolCalls: boolean;
     /** Last heartbeat timestamp (ms since epoch) while tool runs */
     lastHeartbeatTs?: number;
+    /** Last line the running tool printed, from its heartbeat */
+    lastProgress?: string;
+    /** Most recent tool call abandoned after its timeout */
+    lastTimeout?: { server: string; tool: string; timeoutSecs: number } | null;
+    /** Timing summary of the last finished turn */
+    lastTurnMetrics?: TurnMetrics | null;
 }
 
 // Code execution state for code_execution tool
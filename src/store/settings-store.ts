@@ -17,12 +17,22 @@ export interface McpServerConfig {
     args: string[];
     env: Record<string, string>;
     auto_approve_tools: boolean;
+    /** Per-tool auto-approve keyed by tool name; wins over auto_approve_tools */
+    auto_approve_tool_overrides?: Record<string, boolean>;
     defer_tools?: boolean;
     python_name?: string;  // Derived from server name for Python imports
+    /** Per-call tool timeout in seconds; overrides mcp_tool_timeout_secs (0 = no limit) */
+    tool_timeout_secs?: number | null;
+    /** Check tool call arguments against the tool's input schema before calling (default true) */
+    validate_tool_arguments?: boolean;
+    /** Reconnect this server when the background health check finds it unresponsive */
+    auto_reconnect?: boolean;
+    /** Directories exposed to the server as MCP roots; each must exist when connecting */
+    roots?: string[];
 }
 
 // Shared tool-calling format names (must match Rust)
-export type ToolCallFormatName = 'native' | 'hermes' | 'mistral' | 'pythonic' | 'pure_json' | 'code_mode';
+export type ToolCallFormatName = 'native' | 'hermes' | 'mistral' | 'pythonic' | 'pure_json' | 'code_mode' | 'anthropic';
 
 export interface ToolCallFormatConfig {
     enabled: ToolCallFormatName[];
@@ -30,7 +40,16 @@ export interface ToolCallFormatConfig {
 }
 
 // Chat formats (per-model)
-export type ChatFormatName = 'openai_completions' | 'openai_responses';
+export type ChatFormatName = 'openai_completions' | 'openai_responses' | 'anthropic_messages';
+
+// python_execution result rendering (must match Rust ResultFormat)
+export type PythonResultFormat = 'text' | 'json';
+
+// Where python_execution code runs (must match Rust PythonSandboxBackend)
+export type PythonSandboxBackend = 'in_process' | 'wasm';
+
+// sql_select result rendering for the model (must match Rust SqlResultFormat)
+export type SqlResultFormat = 'json' | 'table';
 
 // Database source kinds (must match Rust SupportedDatabaseKind)
 export type SupportedDatabaseKind = 'bigquery' | 'postgres' | 'mysql' | 'sqlite' | 'spanner';
@@ -50,6 +69,8 @@ export interface DatabaseSourceConfig {
     project_id?: string; // Optional for BigQuery or other sources
     dataset_allowlist?: string; // Comma-separated dataset list (BigQuery only)
     table_allowlist?: string; // Comma-separated table list (BigQuery only)
+    /** Regex for dataset/table names skipped during schema refresh (blank = date shards like `_20240101`) */
+    skip_table_pattern?: string;
 }
 
 // Database Toolbox configuration
@@ -61,20 +82,96 @@ export interface DatabaseToolboxConfig {
 // Application settings
 export interface AppSettings {
     system_prompt: string;
+    /** Text placed before the generated system prompt, kept across state transitions */
+    system_prompt_prefix?: string | null;
+    /** Text placed after the generated system prompt, kept across state transitions */
+    system_prompt_suffix?: string | null;
     /** Persisted model selection - applied on app startup */
     selected_model: string | null;
     mcp_servers: McpServerConfig[];
     chat_format_default: ChatFormatName;
     chat_format_overrides: Record<string, ChatFormatName>;
+    /** reasoning_effort sent to a model in place of the requested one, keyed by model id */
+    reasoning_effort_overrides?: Record<string, string>;
     tool_call_formats: ToolCallFormatConfig;
     tool_system_prompts: Record<string, string>;
+    /** Replacement tool descriptions keyed by `server_id::tool_name` ('builtin' for built-ins) */
+    tool_description_overrides?: Record<string, string>;
     tool_search_max_results: number;
+    /** Minimum relevance score (0-1) for a tool_search hit to be shown to the model */
+    tool_search_min_relevance?: number;
+    /** Skip auto tool/schema discovery for shorter messages and plain acknowledgements (0 = always run) */
+    auto_discovery_min_prompt_chars?: number;
+    /** Max agentic loop iterations per turn (1-100) */
+    max_tool_iterations?: number;
+    /** Max tool executions per turn across iterations (0 = no limit) */
+    max_tool_calls_per_turn?: number;
+    /** Run independent auto-approved tool calls from one response concurrently */
+    parallel_tool_calls?: boolean;
+    /** Default per-call MCP tool timeout in seconds (0 = no limit) */
+    mcp_tool_timeout_secs?: number;
+    /** Milliseconds between tool heartbeat events while a tool runs (0 = no heartbeats) */
+    tool_heartbeat_interval_ms?: number;
+    /** Seconds between MCP server health checks (0 = no checks) */
+    mcp_health_check_interval_secs?: number;
+    /** Seconds between auto-saves of an in-progress response (0 = save only at turn end) */
+    chat_autosave_interval_secs?: number;
+    /** Send enabled tool call formats' closing markers (e.g. </tool_call>) as stop sequences */
+    use_format_stop_sequences?: boolean;
+    /** Tool results longer than this are truncated in the middle for the model (0 = no limit) */
+    max_tool_result_chars?: number;
+    /** How sql_select results are shown to the model; the UI always gets JSON */
+    sql_result_format?: SqlResultFormat;
+    /** Replace oversized MCP tool results in the model's history with a summary (UI keeps the raw result) */
+    summarize_large_tool_results?: boolean;
+    /** MCP tool results longer than this many characters are summarized when enabled (0 = never) */
+    tool_result_summary_threshold_chars?: number;
+    /** Drop low-priority tool descriptions once the system prompt exceeds this many characters (0 = no limit) */
+    max_system_prompt_chars?: number;
+    /** Summarize older turns into one note when the history nears the model's context size */
+    history_compaction_enabled?: boolean;
+    /** Most recent turns kept verbatim when the history is compacted */
+    history_compaction_keep_turns?: number;
+    /** Retries (with backoff) when a chat request fails to start streaming */
+    foundry_chat_retries?: number;
+    /** Seconds to wait for the first model token before retrying (0 = wait forever) */
+    first_token_timeout_secs?: number;
+    /** Ask for one approve/reject/edit decision covering all tool calls of an iteration */
+    batch_approval_mode?: boolean;
+    /** Tag text-format tool calls and results with a correlation id comment in saved history */
+    tool_call_ids_in_text?: boolean;
+    /** Clear tools materialized by tool_search at the start of every chat message */
+    strict_turn_tool_scope?: boolean;
+    /** Seconds before a shared materialized tool expires when strict scope is off (0 = never) */
+    materialized_tools_ttl_secs?: number;
+    /** Connect MCP servers and load the selected model in the background at launch */
+    warm_start_on_launch?: boolean;
+    /** Send model reasoning as `reasoning-token` events instead of inline in the answer stream */
+    reasoning_events_enabled?: boolean;
     python_tool_calling_enabled: boolean;
+    /** Wall-clock timeout for python_execution in ms (0 = no limit) */
+    python_execution_timeout_ms?: number;
+    /** Memory ceiling for a python_execution run in MB (0 = no limit) */
+    python_memory_limit_mb?: number;
+    /** How python_execution results are shown to the model (defaults to 'text') */
+    python_result_format?: PythonResultFormat;
+    /** Where python_execution code runs; 'wasm' adds a Wasmtime sandbox around it (defaults to 'in_process') */
+    python_sandbox_backend?: PythonSandboxBackend;
+    /** Run all python code blocks in a response, in order, as one program */
+    python_multi_block?: boolean;
+    /** Safe mode: no code execution and every MCP tool call needs approval */
+    safe_mode?: boolean;
+    /** Keep plain-data Python variables between python_execution calls in a turn */
+    python_session_persistence_enabled?: boolean;
+    /** Modules python_execution may import; replaces the sandbox defaults when set */
+    python_allowed_modules?: string[] | null;
     legacy_tool_call_format_enabled: boolean;
     tool_use_examples_enabled: boolean;
     tool_use_examples_max: number;
     // Database built-ins
     database_toolbox: DatabaseToolboxConfig;
+    /** Tables embedded at once during a schema refresh (defaults to the number of CPUs) */
+    schema_embed_concurrency?: number;
     // Relevancy thresholds for state machine
     rag_chunk_min_relevancy: number;
     schema_relevancy_threshold: number;
@@ -290,6 +387,7 @@ export const useSettingsStore = create<SettingsState>((set, get) => ({
                 defer_tools: source.defer_tools ?? true,
                 dataset_allowlist: source.dataset_allowlist ?? '',
                 table_allowlist: source.table_allowlist ?? '',
+                skip_table_pattern: source.skip_table_pattern ?? '',
             }));
             const mergedSettings: AppSettings = {
                 ...settings,